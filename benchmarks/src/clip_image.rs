@@ -0,0 +1,137 @@
+use std::{path::PathBuf, time::Instant};
+
+use common_lib::{BatchRequest, ImageBatchRequest};
+use reqwest::Url;
+use serde_json::Value;
+use tracing_unwrap::ResultExt;
+
+use crate::get_reqwest_client;
+
+/// Extensions of image files that are read from directory
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// Read all images in directory (not recursively)
+async fn read_images(images_dir: PathBuf) -> Vec<Vec<u8>> {
+    let mut images = Vec::new();
+    let mut entries = tokio::fs::read_dir(images_dir)
+        .await
+        .expect_or_log("Error reading directory");
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .expect_or_log("Error reading directory")
+    {
+        let is_image = entry.path().extension().is_some_and(|x| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|ext| x.eq_ignore_ascii_case(ext))
+        });
+        if is_image {
+            images.push(
+                tokio::fs::read(entry.path())
+                    .await
+                    .expect_or_log("Error reading file"),
+            );
+        }
+    }
+    images
+}
+
+/// Send each image in its own request, `concurrency` requests at a time, as indexer did before
+async fn process_single(
+    reqwest_client: &reqwest_middleware::ClientWithMiddleware,
+    mut nn_server_url: Url,
+    images: &[Vec<u8>],
+    concurrency: usize,
+) {
+    nn_server_url.set_path("/clip/image");
+    for (i, chunk) in images.chunks(concurrency).enumerate() {
+        let tasks: Vec<_> = chunk
+            .iter()
+            .map(|image| {
+                let req_builder = reqwest_client
+                    .post(nn_server_url.clone())
+                    .query(&BatchRequest { batched: true })
+                    .body(image.clone());
+                tokio::spawn(async move {
+                    // Unreadable images are answered with client error
+                    req_builder
+                        .send()
+                        .await
+                        .expect_or_log("Error sending request")
+                })
+            })
+            .collect();
+        for x in tasks {
+            x.await.unwrap_or_log();
+        }
+        tracing::info!("Single requests: processed {} chunks", i + 1);
+    }
+}
+
+/// Send images in batch requests of `concurrency` images, as indexer does now
+async fn process_batches(
+    reqwest_client: &reqwest_middleware::ClientWithMiddleware,
+    mut nn_server_url: Url,
+    images: &[Vec<u8>],
+    concurrency: usize,
+) {
+    nn_server_url.set_path("/clip/image_batch");
+    for (i, chunk) in images.chunks(concurrency).enumerate() {
+        let embeddings: Vec<Value> = reqwest_client
+            .post(nn_server_url.clone())
+            .query(&BatchRequest { batched: false })
+            .json(&ImageBatchRequest {
+                images: chunk.to_vec(),
+            })
+            .send()
+            .await
+            .expect_or_log("Error sending request")
+            .error_for_status()
+            .expect_or_log("Server returned error")
+            .json()
+            .await
+            .expect_or_log("Error parsing response");
+        assert_eq!(
+            embeddings.len(),
+            chunk.len(),
+            "Server must return embedding for each image"
+        );
+        tracing::info!("Batch requests: processed {} chunks", i + 1);
+    }
+}
+
+pub async fn benchmark(images_dir: PathBuf, concurrency: usize, nn_server_address: Url) {
+    let images = read_images(images_dir).await;
+    let concurrency = concurrency.max(1);
+    tracing::info!("Read {} images", images.len());
+
+    // Create reqwest client for HTTP requests
+    let reqwest_client = get_reqwest_client();
+
+    let start_time = Instant::now();
+    process_single(
+        &reqwest_client,
+        nn_server_address.clone(),
+        &images,
+        concurrency,
+    )
+    .await;
+    let single_duration = Instant::now() - start_time;
+
+    let start_time = Instant::now();
+    process_batches(&reqwest_client, nn_server_address, &images, concurrency).await;
+    let batch_duration = Instant::now() - start_time;
+
+    let images_cnt = images.len() as f32;
+    tracing::info!(
+        "Single requests: {:.2} s, {:.2} images/s",
+        single_duration.as_secs_f32(),
+        images_cnt / single_duration.as_secs_f32()
+    );
+    tracing::info!(
+        "Batch requests: {:.2} s, {:.2} images/s",
+        batch_duration.as_secs_f32(),
+        images_cnt / batch_duration.as_secs_f32()
+    );
+}
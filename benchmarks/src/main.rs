@@ -8,6 +8,7 @@ use tracing_subscriber::{
 };
 use tracing_unwrap::ResultExt;
 
+mod clip_image;
 mod coco;
 mod mrobust;
 
@@ -16,6 +17,8 @@ mod mrobust;
 struct Args {
     #[arg(long, default_value_t = Url::parse("http://127.0.0.1:11000/").unwrap())]
     indexer_address: Url,
+    #[arg(long, default_value_t = Url::parse("http://127.0.0.1:10000/").unwrap())]
+    nn_server_address: Url,
     #[command(subcommand)]
     command: Commands,
 }
@@ -23,13 +26,22 @@ struct Args {
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// Evaluate text-to-image search on COCO dataset.
-    /// Before running, you must index all images and set number of results per page to 100
+    /// Before running, you must index all images
     Coco {
         /// Path to the captions file (captions_val2017.json)
         captions_path: PathBuf,
         /// Directory for storing results
         results_dir: PathBuf,
     },
+    /// Compare throughput of calculating image embeddings with a request per image
+    /// and with batch requests
+    ClipImage {
+        /// Number of concurrently sent images, like maximum number of concurrently indexed files
+        #[arg(short = 'n', long, default_value_t = 32)]
+        concurrency: usize,
+        /// Directory with images
+        images_dir: PathBuf,
+    },
     /// Evaluate text-to-text search on mRobust dataset
     #[command(name = "mrobust")]
     MRobust(MRobust),
@@ -51,7 +63,7 @@ enum MRobustCommands {
         output_dir: PathBuf,
     },
     /// Run benchmark.
-    /// Before running, you must index all documents and set number of results per page to 100
+    /// Before running, you must index all documents
     Run {
         /// Enable content search
         #[arg(short = 'c', long, action)]
@@ -105,6 +117,10 @@ async fn main() {
             captions_path,
             results_dir,
         } => coco::benchmark(captions_path, results_dir, args.indexer_address).await,
+        Commands::ClipImage {
+            concurrency,
+            images_dir,
+        } => clip_image::benchmark(images_dir, concurrency, args.nn_server_address).await,
         Commands::MRobust(MRobust { command }) => match command {
             MRobustCommands::CreateFiles {
                 collection_path,
@@ -1,6 +1,8 @@
 use std::{path::PathBuf, time::Instant};
 
-use common_lib::search::{QueryType, SearchRequest, SearchResponse, TextQuery};
+use common_lib::search::{
+    QueryType, SearchRequest, SearchResponse, SortOrder, SuggestionMode, TextQuery,
+};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use tracing_unwrap::{OptionExt, ResultExt};
@@ -74,6 +76,7 @@ async fn process_query(
 ) -> Vec<QueryResult> {
     let search_request = SearchRequest {
         page: 0,
+        results_per_page: Some(MAX_RANK as u32),
         query: QueryType::Text(TextQuery {
             query: query.text,
             content_enabled,
@@ -86,18 +89,36 @@ async fn process_query(
             text_search_coeff,
             image_search_coeff: 1.0,
             reranking_coeff,
+            auto_correct: false,
+            content_fragments: 1,
         }),
         path_prefix: None,
+        path_glob: None,
         content_type: None,
         path_enabled: false,
         hash_enabled: false,
         modified_from: None,
         modified_to: None,
+        modified_in_future: None,
         size_from: None,
         size_to: None,
+        tags: Vec::new(),
+        is_executable: None,
+        is_readonly: None,
+        is_hidden: None,
+        is_online_only: None,
+        parse_failed: None,
+        language: None,
+        provenance: Default::default(),
+        sort: SortOrder::Relevance,
+        recency_boost: None,
+        path_boost: None,
+        suggestions: SuggestionMode::Never,
+        debug: false,
         image_data: Default::default(),
         multimedia_data: Default::default(),
         document_data: Default::default(),
+        email_data: Default::default(),
     };
 
     let start_time = Instant::now();
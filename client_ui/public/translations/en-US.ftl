@@ -2,18 +2,35 @@ lang_code = en
 title = Desktop search
 search_tab = Search
 indexing_status_tab = Indexing
+duplicates_tab = Duplicates
 settings_tab = Settings
 
 info = Information
 error = Error
 loading = ⏳ Loading...
+starting_backend = ⏳ Starting search backend ({ $component })...
+api_token_prompt = Enter API token of indexing server:
 ok = OK
 select = Select...
 cancel = Cancel
 show = Show
 open = Open
 open_folder = Open folder
+reveal_file = Show in folder
+find_similar = Find similar
+move_to_trash = Move to trash
+select_result = Select
+compare = Compare
+clear_selection = Clear selection
+retry_failed_parses = Parse again
+retry_parses_started = Indexing of selected files started, their contents will be parsed again
+compare_selected = Selected: { $count }
+compare_field = Field
+compare_metadata_only = One of the files has no text content, only metadata is compared
+compare_content_truncated = The content is too long, only its beginning is compared
+compare_error = ❌ Comparison error: { $error }
 save = Save
+reset_search = Reset
 
 duration_h_m_s = { $hours } h { $minutes } min { $seconds } s
 duration_m_s = { $minutes } min { $seconds } s
@@ -24,9 +41,25 @@ file_size_mib = { $size } MiB
 file_size_gib = { $size } GiB
 
 search_placeholder = Search...
+sort = Sort results
+sort_relevance = By relevance
+sort_modified_asc = Oldest first
+sort_modified_desc = Newest first
+sort_size_asc = Smallest first
+sort_size_desc = Largest first
+sort_name_asc = By name (A-Z)
+sort_name_desc = By name (Z-A)
 search = Search
 select_file = Select file
+query_image_upload = Upload image:
+query_image_paste_hint = Click here and paste an image with Ctrl+V
+query_image_unsupported = ❌ Unsupported file, select an image
+query_image_too_large = ❌ Image is larger than { $size } MiB
 saved_requests = Saved requests
+search_history = History
+search_history_clear = Clear history
+search_history_entry = { $query } — { $time }, results on page: { $count }
+search_history_image_missing = { $name } (file no longer exists)
 query_type = Query type
 query_type_text = Text query
 query_type_image = Image query
@@ -35,6 +68,9 @@ content_search = Content search
 semantic_text_search = Semantic text search
 semantic_image_search = Semantic image search
 reranking = Reranking
+auto_correct = Automatic query correction
+content_fragments = Content fragments
+suggestions_when_empty = Suggest corrections only if nothing is found
 semantic_search_page_count = Semantic search page count
 text_search_pages = Text search
 image_search_pages = Image search
@@ -43,23 +79,63 @@ query_coeff = Content search
 text_search_coeff = Semantic text search
 image_search_coeff = Semantic image search
 reranking_coeff = Reranking
+recency_boost = Boost of recent files
+debug_mode = Debug mode
 search_in_folder = Search in folder
+filter_path_glob = Path pattern
+filter_path_glob_placeholder = */reports/*.pdf
+filter_path_glob_empty = Pattern is empty
+filter_path_glob_unsupported = Only wildcards * and ? are supported
+filter_path_glob_leading_wildcard = Patterns starting with wildcard are slow and may be rejected for large indexes
 opening_error = ❌ Opening error: { $error }
+moving_to_trash_error = ❌ Error moving to trash: { $error }
+move_to_trash_confirm = Move { $name } to trash? Its document will be removed from index.
+file_missing = ❌ File { $name } no longer exists
+file_missing_confirm = File { $name } no longer exists. Remove it from index?
 request_opening_error = ❌ Request opening error: { $error }
+export_results = Export all results:
+exporting_error = ❌ Export error: { $error }
 request_saving_error = ❌ Request saving error: { $error }
 search_error = ❌ Search error: { $error }
+similar_search_error = ❌ Error finding similar files: { $error }
 file_loading_error = ❌ File loading error: { $error }
+duplicates = Duplicate files
+duplicates_min_size = Minimum file size (KiB):
+duplicates_path_prefix = Only in folder, all indexed files if empty:
+find_duplicates = Find duplicates
+duplicates_none = No duplicate files found
+duplicates_group = Files: { $count }, size of each: { $size }, wasted: { $wasted }
+duplicates_not_listed = Files not listed: { $count }
+duplicates_load_more = Load more
+duplicates_loading_error = ❌ Duplicates loading error: { $error }
 possible_query = Possible query:
+result_languages = Languages of results:
+language_count = { $language } ({ $count })
+debug_request = Elasticsearch request
+debug_took = Search took { $took } ms, in Elasticsearch { $es_took } ms
+debug_knn = kNN search by { $field }: k = { $k }, num_candidates = { $num_candidates }
+debug_knn_filtered_count = Documents matching filters inside folder: { $count }
+showing_results_for = Showing results for
+search_instead_for = Search instead for
 start_text_1 = Before you start, select the indexed folders in the Settings tab and save them.
 start_text_2 = Then index them in the Indexing tab.
 start_text_3 = To search, select the query type on the left, enter the query text or select an image.
 start_text_4 = If necessary, select the search type, file type, search folder, and additional filters on the left.
 nothing_found = Nothing was found
+unknown_album = Unknown album
+album_info = Tracks: { $track_cnt }, total duration: { $duration }
+expand_album = Show tracks
+collapse_album = Hide tracks
 page_first = << First
 page_previous = < Previous
 page_next = Next >
 page_last = Last >>
 preview_not_supported = File preview is not supported
+preview_binary = File is binary and can't be shown as text
+preview_content_info = Encoding: { $encoding }, loaded { $loaded } of { $total }
+preview_load_more = Load more
+new_tag = New tag
+tags_updating_error = ❌ Tags updating error: { $error }
 
 main_file_properties = Main file properties
 filter_text_search = Text search
@@ -67,6 +143,16 @@ filter_file_path = File path
 filter_hash = Hash
 filter_modification_datetime = Modification date and time
 filter_file_size = File size (MiB)
+filter_tags = Tags
+filter_attributes = Attributes
+filter_executable = Executable:
+filter_readonly = Read-only:
+filter_hidden = Hidden:
+filter_online_only = Online-only:
+filter_parse_failed = Not parsed:
+filter_modified_in_future = Modified in future:
+filter_language = Text language
+add_tag = Add
 inch = inch
 cm = centimeter
 image_properties = Image properties
@@ -89,6 +175,17 @@ audio_5_1 = 5.1
 audio_7_1 = 7.1
 audio_16 = 16 channels
 audio_other = unknown
+language_en = english
+language_ru = russian
+language_de = german
+language_fr = french
+language_es = spanish
+language_it = italian
+language_pt = portuguese
+language_nl = dutch
+language_zh = chinese
+language_ja = japanese
+language_ko = korean
 multimedia_properties = Multimedia properties
 filter_artist = Artist
 filter_album = Album
@@ -99,23 +196,68 @@ filter_release_date = Release date
 filter_duration_min = Duration (min)
 filter_audio_sample_rate = Audio sample rate
 filter_audio_channel_type = Audio channel type
+filter_video_width = Video width (pixels)
+filter_video_height = Video height (pixels)
+group_albums = Group by album
 document_properties = Document properties
 filter_title = Title
 filter_creator = Creator
+filter_publisher = Publisher
 filter_doc_created = Creation date and time
 filter_doc_modified = Modification date and time
 filter_num_pages = Number of pages
 filter_num_words = Number of words
 filter_num_characters = Number of characters
+email_properties = Email properties
+filter_sender = Sender
+filter_recipients = Recipients
+filter_subject = Subject
+filter_email_sent = Sending date and time
+filter_has_attachments = Has attachments:
 filter_from = From:
 filter_to = To:
 filter_file_type = File type
 filter_file_type_any = Any
+filter_chip = { $filter }: { $value }
 
 results_path = Full path: { $path }
 results_hash = SHA-256 hash: { $hash }
 results_modified = Modified: { $modified }
 results_size = Size: { $size }
+results_executable = Executable: { $executable }
+results_readonly = Read-only: { $readonly }
+results_hidden = Hidden: { $hidden }
+results_online_only = Online-only: { $online_only }
+results_parse_failed = Not parsed: { $parse_failed }
+results_symlink = Symbolic link: { $symlink }
+online_only = ☁ Online-only
+online_only_hint = File is stored only in the cloud, its contents were not downloaded and are not searched
+parse_failed = ⚠ Not parsed
+parse_failed_hint = Parsing of file kept failing, only its metadata is searched
+symlink = ↪ Symbolic link
+symlink_hint = File was indexed by symbolic link
+matched_fields_hint = Fields that matched query
+matched_field = { $field ->
+    [path] Path
+    [hash] Hash
+    [content] Content
+    [title] Title
+    [image_make] Camera manufacturer
+    [image_model] Camera model
+    [image_software] Software
+    [artist] Artist
+    [album] Album
+    [genre] Genre
+    [track_number] Track number
+    [disc_number] Disc number
+    [release_date] Release date
+    [creator] Creator
+    [publisher] Publisher
+    [semantic] Semantic search
+   *[other] { $field }
+}
+results_modified_in_future = Modified in future: { $modified_in_future }
+results_xattrs = Extended attributes: { $xattrs }
 results_device_manufacturer = Device manufacturer: { $device_manufacturer }
 results_device_model = Device model: { $device_model }
 results_image_software = Software: { $image_software }
@@ -139,13 +281,23 @@ results_release_date = Release date: { $release_date }
 results_duration = Duration: { $duration }
 results_audio_sample_rate = Audio sample rate: { $audio_sample_rate }
 results_audio_channel_type = Audio channel type: { $audio_channel_type }
+results_video_resolution = Video resolution: { $width }×{ $height }
+results_video_width = Video width: { $width } pixels
+results_video_height = Video height: { $height } pixels
 results_title = Title: { $title }
 results_creator = Creator: { $creator }
+results_publisher = Publisher: { $publisher }
+results_doc_language = Language: { $doc_language }
 results_doc_created = Created: { $doc_created }
 results_doc_modified = Modified: { $doc_modified }
 results_num_pages = Pages: { $num_pages }
 results_num_words = Words: { $num_words }
 results_num_characters = Characters: { $num_characters }
+results_sender = Sender: { $sender }
+results_recipients = Recipients: { $recipients }
+results_subject = Subject: { $subject }
+results_email_sent = Sent: { $email_sent }
+results_has_attachments = Attachments: { $has_attachments }
 
 mime_other = Other
 mime_text = Text formats
@@ -153,6 +305,8 @@ mime_text_plain = Plain text
 mime_text_csv = CSV
 mime_text_html = HTML
 mime_text_css = CSS
+mime_text_markdown = Markdown
+mime_text_source = Source code
 mime_image = Images
 mime_image_jpeg = JPEG
 mime_image_png = PNG
@@ -179,6 +333,8 @@ mime_video_flv = FLV (Flash)
 mime_video_daala = Daala
 mime_video_ogg = OGG
 mime_video_theora = Theora
+mime_message = Email
+mime_message_rfc822 = Email message (EML)
 mime_application = Other
 mime_application_pdf = PDF
 mime_application_word_old = Microsoft Word (pre-2007)
@@ -193,6 +349,8 @@ mime_application_odp = OpenDocument Presentation
 mime_application_apple_pages = Apple Pages
 mime_application_apple_numbers = Apple Numbers
 mime_application_apple_keynote = Apple Keynote
+mime_application_ebook = E-books (EPUB, FB2)
+mime_application_outlook = Outlook message (MSG)
 mime_application_zip = ZIP
 mime_application_rar = RAR
 mime_application_7zip = 7-Zip
@@ -208,39 +366,161 @@ indexing_status_indexing = indexing is going on
 indexing_results = Results of the last indexation:
 indexing_add_remove_update = Adding { $to_add }, removing { $to_remove }, updating { $to_update } files in the index
 indexing_processed_sent = { $processed } files processed, { $sent } changes uploaded
+indexing_progress = Progress: { $percent }%
+indexing_rate = { $rate } files/s
+indexing_eta = Time remaining: { $duration }
+indexing_files_changed = Files modified during processing and processed again: { $count }
+indexing_failed_operations = Changes not applied to index: { $count }
+indexing_modified_in_future = Files with modification time in future: { $count }
+indexing_hydration = Online-only files downloaded: { $size } MiB, left for next indexings: { $deferred }
 indexing_elapsed = Elapsed: { $duration }
 indexing_error = ❌ Indexing error: { $error }
 indexing_more_errors = ({ $count } more errors)
+indexing_stage_durations = Time of processing stages: { $stages }
+indexing_slow_files = Slow files: { $count }
+indexing_slow_file = { $path }: { $seconds } s ({ $stages })
+stage_duration = { $stage } { $seconds } s
+stage_hash = hashing
+stage_tika = Apache Tika
+stage_thumbnail = thumbnail
+stage_text_embedding = text embedding
+stage_image_embedding = image embedding
+stage_bulk_enqueue = queue to Elasticsearch
+stage_other = other
 indexing_statistics = Statistics
 indexing_doc_cnt = Number of files in the index: { $count }
 indexing_index_size = Index size: { $size }
+directory_stats_path = Folder
+directory_stats_doc_count = Documents
+directory_stats_total_size = Total size
+directory_stats_excluded = { $path } (excluded)
+client_errors = Interface errors
+client_errors_none = No reported errors
+client_errors_load = Load interface errors
+client_error = { $time } (version { $version }, { $tab }): { $message } { $location }
+client_errors_loading_error = Error loading interface errors: { $error }
+file_errors = Errors of files
+file_errors_latest = Errors of files in the latest indexing
+file_errors_count = Errors of files in the latest indexing: { $count }
+file_error = { $path } ({ $stage }): { $error }
+file_error_stage_hash = reading
+file_error_stage_parse = parsing
+file_error_stage_embedding = embedding
+file_error_stage_bulk = sending to index
+file_errors_load_more = Load more
+file_errors_loading_error = Error loading errors of files: { $error }
+retry_failed_files = Retry failed files
+watcher_activity = Recent watcher activity
+watcher_activity_none = No changes of files since the page was opened
+mute_watcher = Mute watcher activity (during bulk file operations)
+watcher_event = { $time } — { $event } ({ $indexing })
+watcher_event_changed = changed: { $path }
+watcher_event_moved = renamed to: { $path }
+watcher_event_full_indexing = too many changes, all files are indexed
+watcher_event_queued = indexing started
+watcher_event_coalesced = waits for current indexing
+directory_stats_loading_error = Error loading statistics of folders: { $error }
 clear_index = Clear index
+rebuild_index = Rebuild index
 index = Index
 indexing_status_loading_error = ❌ Indexing status loading error: { $error }
 index_clearing_error = ❌ Index clearing error: { $error }
+clear_index_confirm = This will remove { $count } indexed documents. Continue?
+index_schema_outdated = ⚠️ Index is incompatible with this version (index schema version { $index_version }, required { $current_version }). Clear index and index files again. Reason: { $reason }
+index_rebuilding_error = ❌ Index rebuilding error: { $error }
+indexing_estimate = Indexing estimate
+estimate_sample_percent = Sample size (% of files):
+estimate = Estimate
+estimating = ⏳ Estimating...
+estimate_sample = Processed { $sample_cnt } of { $files_cnt } files ({ $sample_size } of { $files_size }), errors: { $errors_cnt }
+estimate_duration = Projected indexing time: { $duration }
+estimate_stages = Including hashing: { $hash }, metadata extraction: { $metadata }, contents processing and embeddings: { $processing }
+estimate_index_size = Projected index size: { $size }
+estimate_caveat = The projection is extrapolated from a random sample of files and may differ significantly from the actual values: processing time depends on file types, caches and the load of Apache Tika and the neural network server. Only files not exceeding the maximum file size are counted. Nothing is written to the index.
+estimation_error = ❌ Estimation error: { $error }
 
 warning = Warning
 settings_warning =
     A restart is required to apply the server settings and neural network server settings.
     Indexing must be performed after adding or removing indexable folders.
-    To apply neural network server settings that affect indexing, such as toggling semantic text or image search or changing their parameters, reindexing (cleaning the index and indexing again) is required.
+    To apply neural network server settings that affect indexing, such as toggling semantic text or image search or changing their parameters, rebuilding the index is required.
 indexable_folders = Indexable folders
 exclude_file_regex = Regular expression to exclude files:
+case_insensitive_paths = Paths differing only in case are the same file (Windows, macOS)
+skip_content_types = Content types of files that are indexed without parsing (MIME types or their prefixes):
+content_type_overrides = Content types of files that are read as text without Apache Tika (extension=MIME type):
+index_archives = Index text of files in zip archives:
 server_settings = Server settings
 indexer_address = Indexing server address:
-elasticsearch_url = Elasticsearch server URL:
+api_token = API token, required in requests to indexing server if set:
+elasticsearch_urls = Elasticsearch node URLs, comma-separated:
+elasticsearch_user = Elasticsearch user (empty without authentication):
+elasticsearch_password = Elasticsearch password:
+elasticsearch_ca_cert_path = Path to CA certificate of Elasticsearch (PEM, empty for system certificates):
 tika_url = Apache Tika server URL:
 nn_server_url = Neural network server URL:
+wake_url = Launcher URL for starting stopped indexer (optional):
 open_on_start = Open the interface on server startup:
+metrics_enabled = Serve metrics in Prometheus format at /metrics:
+file_actions_enabled = Allow moving files to trash from search results
+report_errors = Report interface errors to indexer (without queries and file paths):
+client_language = Interface language:
+client_language_auto = Browser language
+client_language_en = English
+client_language_ru = Русский
+trigger_test_error = Trigger test error
+interface_settings = Interface
+theme = Theme:
+theme_auto = As in system
+theme_light = Light
+theme_dark = Dark
+results_layout = Layout of search results:
+results_layout_list = List
+results_layout_grid = Grid
+thumbnails_enabled = Show thumbnails in search results
+interface_local = Save interface settings only in this browser
 indexing_settings = Indexing settings
 watcher_enabled = Watch for file changes:
 debouncer_timeout = File system events delay time (s):
+periodic_indexing_enabled = Index on schedule:
+periodic_indexing_interval_hours = Interval between scheduled indexings (h):
 max_file_size = Maximum file size (MiB):
+hash_change_detection_max_size = Maximum folder size for hash comparison (GiB):
+hash_algorithm = Hash of file contents:
+hash_algorithm_sha256 = SHA-256
+hash_algorithm_xxh3 = xxHash (XXH3, faster)
+hash_algorithm_none = Not calculated
+hash_chunk_size = Size of chunks for reading files when hashing (KiB):
+online_only_xattr = Extended attribute of online-only files (Linux):
+online_only_hydration_budget = Maximum size of online-only files downloaded per indexing (GiB):
 max_concurrent_files = Maximum number of concurrently processed files:
+slow_file_threshold = Report files processed longer than (s, 0 to disable):
+parse_failures_max = Index files without parsing after failures (0 to always retry):
+index_xattrs = Index names of extended attributes of files (slows down scanning)
+follow_symlinks = Follow symbolic links (files reachable by several paths are indexed once)
+cjk_analysis = Split Chinese, Japanese and Korean text into bigrams (requires rebuilding index)
+content_languages = Additional languages of text with stemming, such as de or fr (requires rebuilding index):
+ocr_enabled = Recognize text in images with Tesseract OCR:
+ocr_tesseract_path = Path to Tesseract executable:
+ocr_languages = OCR languages:
+ocr_min_dimension = Minimum image width and height for OCR:
+thumbnail_cache_enabled = Cache thumbnails on disk
+thumbnail_cache_dir = Thumbnail cache directory:
+thumbnail_cache_max_size = Maximum thumbnail cache size (MiB):
+preview_chunk_size = Size of text loaded at once in preview (KiB):
 elasticsearch_batch_size = Number of updates sent to Elasticsearch at a time:
+elasticsearch_pit_keep_alive = Time to keep Elasticsearch point in time between pages of files list (minutes):
+elasticsearch_bulk_retries = Number of retries of sending changes to Elasticsearch:
 search_settings = Search settings
 results_per_page = Number of results per page:
+results_per_page_max = Maximum number of results per page in search requests:
 knn_candidates_multiplier = Multiplier of the number of kNN candidates for semantic search:
+recency_boost_scale_days = Age of files in days at which boost of recent files is halved:
+prefetch_next_page = Load next page of results in background
+prefetch_with_reranking = Load next page in background even with reranking enabled (slow)
+auto_correct_default = Automatically correct misspelled queries by default
+auto_correct_max_hits = Correct queries with fewer results than:
+field_weight = Weight of field "{ $field }" in text search:
 nn_server_settings = Neural network server settings
 nn_server_address = Neural network server address:
 text_search_enabled = Semantic text search:
@@ -254,9 +534,16 @@ max_sentences = Maximum number of processed sentences:
 window_size = Window size (words):
 window_step = Window step (words):
 summary_len = Number of sentences per document for reranking:
+summary_len_max = Maximum number of sentences for long documents:
+text_cache_capacity = Cached results of text models (0 to disable):
 nn_setting_device = { $model }: device:
+nn_setting_effective_device = Model is running on { $device }, configured device is unavailable
 nn_setting_batch_size = { $model }: batch size:
 nn_setting_max_delay = { $model }: batch latency (ms):
+nn_setting_max_queue = { $model }: maximum queue size:
+nn_setting_model_path = { $model }: model directory:
+nn_setting_embedding_dim = { $model }: embedding dimension:
+nn_setting_idle_timeout = { $model }: unload after idle time (min, 0 to keep loaded):
 cpu = CPU
 cuda = CUDA
 include = Include
@@ -266,6 +553,20 @@ excluded = Excluded
 watch = Watch
 watching = Watching
 not_watching = Not watching
+change_detection_mtime_size = Compare modification time
+change_detection_hash = Compare hashes
+change_detection_hash_always = Always compare hashes
+hash_always = Ignore modification time
+exclude_regex = Exclude files regex
+max_concurrency = Concurrent files
+max_concurrency_hint = Maximum number of concurrently processed files of directory, e.g. for slow network mounts (empty for global limit)
+content_type_preset = Default file type filter when searching in this folder
+content_type_preset_none = Any file type
+content_type_preset_images = Images
+content_type_preset_audio = Audio
+content_type_preset_video = Video
+content_type_preset_documents = Documents
+content_type_preset_custom = Custom filter
 dialog_opening_error = ❌ Dialog opening error: { $error }
 settings_loading_error = ❌ Settings loading error: { $error }
 settings_saving_error = ❌ Settings saving error: { $error }
@@ -2,18 +2,35 @@ lang_code = ru
 title = Локальный поиск
 search_tab = Поиск
 indexing_status_tab = Индексация
+duplicates_tab = Дубликаты
 settings_tab = Настройки
 
 info = Информация
 error = Ошибка
 loading = ⏳ Загрузка...
+starting_backend = ⏳ Запуск поисковой системы ({ $component })...
+api_token_prompt = Введите токен API сервера индексации:
 ok = ОК
 select = Выбрать...
 cancel = Отмена
 show = Показать
 open = Открыть
 open_folder = Открыть папку
+reveal_file = Показать в папке
+find_similar = Найти похожие
+move_to_trash = В корзину
+select_result = Выбрать
+compare = Сравнить
+clear_selection = Снять выделение
+retry_failed_parses = Разобрать заново
+retry_parses_started = Индексация выбранных файлов запущена, их содержимое будет разобрано заново
+compare_selected = Выбрано: { $count }
+compare_field = Поле
+compare_metadata_only = У одного из файлов нет текстового содержимого, сравниваются только метаданные
+compare_content_truncated = Содержимое слишком длинное, сравнивается только его начало
+compare_error = ❌ Ошибка сравнения: { $error }
 save = Сохранить
+reset_search = Сбросить
 
 duration_h_m_s = { $hours } ч { $minutes } мин { $seconds } с
 duration_m_s = { $minutes } мин { $seconds } с
@@ -24,9 +41,25 @@ file_size_mib = { $size } МиБ
 file_size_gib = { $size } ГиБ
 
 search_placeholder = Поиск...
+sort = Сортировка результатов
+sort_relevance = По релевантности
+sort_modified_asc = Сначала старые
+sort_modified_desc = Сначала новые
+sort_size_asc = Сначала маленькие
+sort_size_desc = Сначала большие
+sort_name_asc = По имени (А-Я)
+sort_name_desc = По имени (Я-А)
 search = Искать
 select_file = Выбрать файл
+query_image_upload = Загрузить изображение:
+query_image_paste_hint = Нажмите здесь и вставьте изображение с помощью Ctrl+V
+query_image_unsupported = ❌ Неподдерживаемый файл, выберите изображение
+query_image_too_large = ❌ Изображение больше { $size } МиБ
 saved_requests = Сохранённые запросы
+search_history = История
+search_history_clear = Очистить историю
+search_history_entry = { $query } — { $time }, результатов на странице: { $count }
+search_history_image_missing = { $name } (файл больше не существует)
 query_type = Тип запроса
 query_type_text = По тексту
 query_type_image = По изображению
@@ -35,6 +68,9 @@ content_search = Поиск по содержимому
 semantic_text_search = Семантический поиск по тексту
 semantic_image_search = Семантический поиск по изображениям
 reranking = Переранжирование
+auto_correct = Автоматическое исправление запроса
+content_fragments = Фрагменты содержимого
+suggestions_when_empty = Предлагать исправления, только если ничего не найдено
 semantic_search_page_count = Количество страниц семантического поиска
 text_search_pages = По тексту
 image_search_pages = По изображениям
@@ -43,23 +79,63 @@ query_coeff = По содержимому
 text_search_coeff = Семантический по тексту
 image_search_coeff = Семантический по изображениям
 reranking_coeff = Переранжирование
+recency_boost = Усиление недавних файлов
+debug_mode = Режим отладки
 search_in_folder = Искать в папке
+filter_path_glob = Шаблон пути
+filter_path_glob_placeholder = */отчёты/*.pdf
+filter_path_glob_empty = Шаблон пуст
+filter_path_glob_unsupported = Поддерживаются только символы подстановки * и ?
+filter_path_glob_leading_wildcard = Шаблоны, начинающиеся с символа подстановки, выполняются медленно и могут быть отклонены для больших индексов
 opening_error = ❌ Ошибка открытия: { $error }
+moving_to_trash_error = ❌ Ошибка перемещения в корзину: { $error }
+move_to_trash_confirm = Переместить { $name } в корзину? Его документ будет удалён из индекса.
+file_missing = ❌ Файл { $name } больше не существует
+file_missing_confirm = Файл { $name } больше не существует. Удалить его из индекса?
 request_opening_error = ❌ Ошибка открытия запроса: { $error }
+export_results = Экспорт всех результатов:
+exporting_error = ❌ Ошибка экспорта: { $error }
 request_saving_error = ❌ Ошибка сохранения запроса: { $error }
 search_error = ❌ Ошибка поиска: { $error }
+similar_search_error = ❌ Ошибка поиска похожих файлов: { $error }
 file_loading_error = ❌ Ошибка загрузки файла: { $error }
+duplicates = Повторяющиеся файлы
+duplicates_min_size = Минимальный размер файла (КиБ):
+duplicates_path_prefix = Только в папке, все проиндексированные файлы, если пусто:
+find_duplicates = Найти дубликаты
+duplicates_none = Повторяющиеся файлы не найдены
+duplicates_group = Файлов: { $count }, размер каждого: { $size }, лишнее место: { $wasted }
+duplicates_not_listed = Файлов не показано: { $count }
+duplicates_load_more = Загрузить ещё
+duplicates_loading_error = ❌ Ошибка загрузки дубликатов: { $error }
 possible_query = Возможный запрос:
+result_languages = Языки результатов:
+language_count = { $language } ({ $count })
+debug_request = Запрос к Elasticsearch
+debug_took = Поиск занял { $took } мс, в Elasticsearch { $es_took } мс
+debug_knn = kNN-поиск по { $field }: k = { $k }, num_candidates = { $num_candidates }
+debug_knn_filtered_count = Документов в папке, подходящих под фильтры: { $count }
+showing_results_for = Показаны результаты для
+search_instead_for = Искать вместо этого
 start_text_1 = Перед началом работы выберите индексируемые папки на вкладке "Настройки" и сохраните их.
 start_text_2 = Затем проиндексируйте их на вкладке "Индексация".
 start_text_3 = Для поиска выберите тип запроса слева, введите текст запроса или выберите изображение выше.
 start_text_4 = При необходимости выберите тип поиска, тип файлов, папку поиска, дополнительные фильтры слева.
 nothing_found = Ничего не найдено
+unknown_album = Неизвестный альбом
+album_info = Треков: { $track_cnt }, общая длительность: { $duration }
+expand_album = Показать треки
+collapse_album = Скрыть треки
 page_first = << Первая
 page_previous = < Предыдущая
 page_next = Следующая >
 page_last = Последняя >>
 preview_not_supported = Предпросмотр файла не поддерживается
+preview_binary = Файл двоичный и не может быть показан как текст
+preview_content_info = Кодировка: { $encoding }, загружено { $loaded } из { $total }
+preview_load_more = Загрузить ещё
+new_tag = Новый тег
+tags_updating_error = ❌ Ошибка изменения тегов: { $error }
 
 main_file_properties = Основные свойства файла
 filter_text_search = Текстовый поиск
@@ -67,6 +143,16 @@ filter_file_path = Путь файла
 filter_hash = Хеш
 filter_modification_datetime = Дата и время изменения
 filter_file_size = Размер файла (МиБ)
+filter_tags = Теги
+filter_attributes = Атрибуты
+filter_executable = Исполняемый:
+filter_readonly = Только для чтения:
+filter_hidden = Скрытый:
+filter_online_only = Только в облаке:
+filter_parse_failed = Не разобран:
+filter_modified_in_future = Изменён в будущем:
+filter_language = Язык текста
+add_tag = Добавить
 inch = дюйм
 cm = сантиметр
 image_properties = Свойства изображения
@@ -89,6 +175,17 @@ audio_5_1 = 5.1
 audio_7_1 = 7.1
 audio_16 = 16 каналов
 audio_other = неизвестно
+language_en = английский
+language_ru = русский
+language_de = немецкий
+language_fr = французский
+language_es = испанский
+language_it = итальянский
+language_pt = португальский
+language_nl = нидерландский
+language_zh = китайский
+language_ja = японский
+language_ko = корейский
 multimedia_properties = Свойства мультимедиа
 filter_artist = Исполнитель
 filter_album = Альбом
@@ -99,23 +196,68 @@ filter_release_date = Дата выпуска
 filter_duration_min = Длительность (мин)
 filter_audio_sample_rate = Частота дискретизации аудио
 filter_audio_channel_type = Тип аудиоканала
+filter_video_width = Ширина видео (пиксели)
+filter_video_height = Высота видео (пиксели)
+group_albums = Группировать по альбомам
 document_properties = Свойства документа
 filter_title = Заголовок
 filter_creator = Создатель
+filter_publisher = Издатель
 filter_doc_created = Дата и время создания
 filter_doc_modified = Дата и время изменения
 filter_num_pages = Количество страниц
 filter_num_words = Количество слов
 filter_num_characters = Количество символов
+email_properties = Свойства письма
+filter_sender = Отправитель
+filter_recipients = Получатели
+filter_subject = Тема
+filter_email_sent = Дата и время отправки
+filter_has_attachments = Есть вложения:
 filter_from = От:
 filter_to = До:
 filter_file_type = Тип файла
 filter_file_type_any = Любой
+filter_chip = { $filter }: { $value }
 
 results_path = Полный путь: { $path }
 results_hash = Хеш SHA-256: { $hash }
 results_modified = Изменено: { $modified }
 results_size = Размер: { $size }
+results_executable = Исполняемый: { $executable }
+results_readonly = Только для чтения: { $readonly }
+results_hidden = Скрытый: { $hidden }
+results_online_only = Только в облаке: { $online_only }
+results_parse_failed = Не разобран: { $parse_failed }
+results_symlink = Символическая ссылка: { $symlink }
+online_only = ☁ Только в облаке
+online_only_hint = Файл хранится только в облаке, его содержимое не загружалось и не участвует в поиске
+parse_failed = ⚠ Не разобран
+parse_failed_hint = Разбор файла повторно завершался ошибкой, поиск ведётся только по метаданным
+symlink = ↪ Символическая ссылка
+symlink_hint = Файл проиндексирован по символической ссылке
+matched_fields_hint = Поля, соответствующие запросу
+matched_field = { $field ->
+    [path] Путь
+    [hash] Хеш
+    [content] Содержимое
+    [title] Название
+    [image_make] Производитель камеры
+    [image_model] Модель камеры
+    [image_software] Программа
+    [artist] Исполнитель
+    [album] Альбом
+    [genre] Жанр
+    [track_number] Номер трека
+    [disc_number] Номер диска
+    [release_date] Дата выпуска
+    [creator] Создатель
+    [publisher] Издатель
+    [semantic] Семантический поиск
+   *[other] { $field }
+}
+results_modified_in_future = Изменён в будущем: { $modified_in_future }
+results_xattrs = Расширенные атрибуты: { $xattrs }
 results_device_manufacturer = Производитель устройства: { $device_manufacturer }
 results_device_model = Модель устройства: { $device_model }
 results_image_software = Программное обеспечение: { $image_software }
@@ -139,13 +281,23 @@ results_release_date = Дата выпуска: { $release_date }
 results_duration = Длительность: { $duration }
 results_audio_sample_rate = Частота дискретизации аудио: { $audio_sample_rate }
 results_audio_channel_type = Тип аудиоканала: { $audio_channel_type }
+results_video_resolution = Разрешение видео: { $width }×{ $height }
+results_video_width = Ширина видео: { $width } пикселей
+results_video_height = Высота видео: { $height } пикселей
 results_title = Заголовок: { $title }
 results_creator = Создатель: { $creator }
+results_publisher = Издатель: { $publisher }
+results_doc_language = Язык: { $doc_language }
 results_doc_created = Создано: { $doc_created }
 results_doc_modified = Изменено: { $doc_modified }
 results_num_pages = Страниц: { $num_pages }
 results_num_words = Слов: { $num_words }
 results_num_characters = Символов: { $num_characters }
+results_sender = Отправитель: { $sender }
+results_recipients = Получатели: { $recipients }
+results_subject = Тема: { $subject }
+results_email_sent = Отправлено: { $email_sent }
+results_has_attachments = Вложения: { $has_attachments }
 
 mime_other = Другие
 mime_text = Текстовые форматы
@@ -153,6 +305,8 @@ mime_text_plain = Простой текст
 mime_text_csv = CSV
 mime_text_html = HTML
 mime_text_css = CSS
+mime_text_markdown = Markdown
+mime_text_source = Исходный код
 mime_image = Изображения
 mime_image_jpeg = JPEG
 mime_image_png = PNG
@@ -179,6 +333,8 @@ mime_video_flv = FLV (Flash)
 mime_video_daala = Daala
 mime_video_ogg = OGG
 mime_video_theora = Theora
+mime_message = Электронная почта
+mime_message_rfc822 = Письмо (EML)
 mime_application = Другие
 mime_application_pdf = PDF
 mime_application_word_old = Microsoft Word (до 2007)
@@ -193,6 +349,8 @@ mime_application_odp = OpenDocument Presentation
 mime_application_apple_pages = Apple Pages
 mime_application_apple_numbers = Apple Numbers
 mime_application_apple_keynote = Apple Keynote
+mime_application_ebook = Электронные книги (EPUB, FB2)
+mime_application_outlook = Письмо Outlook (MSG)
 mime_application_zip = ZIP
 mime_application_rar = RAR
 mime_application_7zip = 7-Zip
@@ -208,39 +366,161 @@ indexing_status_indexing = идёт индексация
 indexing_results = Результаты последней индексации:
 indexing_add_remove_update = Добавление { $to_add }, удаление { $to_remove }, обновление { $to_update } файлов в индексе
 indexing_processed_sent = Обработано { $processed } файлов, загружено { $sent } изменений
+indexing_progress = Прогресс: { $percent }%
+indexing_rate = { $rate } файлов/с
+indexing_eta = Осталось: { $duration }
+indexing_files_changed = Файлов изменено во время обработки и обработано повторно: { $count }
+indexing_failed_operations = Изменений, не применённых к индексу: { $count }
+indexing_modified_in_future = Файлов с временем изменения в будущем: { $count }
+indexing_hydration = Загружено файлов из облака: { $size } МиБ, отложено до следующих индексаций: { $deferred }
 indexing_elapsed = Прошло: { $duration }
 indexing_error = ❌ Ошибка индексации: { $error }
 indexing_more_errors = (ещё { $count } ошибок)
+indexing_stage_durations = Время этапов обработки: { $stages }
+indexing_slow_files = Медленные файлы: { $count }
+indexing_slow_file = { $path }: { $seconds } с ({ $stages })
+stage_duration = { $stage } { $seconds } с
+stage_hash = хеширование
+stage_tika = Apache Tika
+stage_thumbnail = миниатюра
+stage_text_embedding = эмбеддинг текста
+stage_image_embedding = эмбеддинг изображения
+stage_bulk_enqueue = очередь в Elasticsearch
+stage_other = прочее
 indexing_statistics = Статистика
 indexing_doc_cnt = Количество файлов в индексе: { $count }
 indexing_index_size = Размер индекса: { $size }
+directory_stats_path = Папка
+directory_stats_doc_count = Документов
+directory_stats_total_size = Общий размер
+directory_stats_excluded = { $path } (исключена)
+client_errors = Ошибки интерфейса
+client_errors_none = Нет сообщений об ошибках
+client_errors_load = Загрузить ошибки интерфейса
+client_error = { $time } (версия { $version }, { $tab }): { $message } { $location }
+client_errors_loading_error = Ошибка загрузки ошибок интерфейса: { $error }
+file_errors = Ошибки файлов
+file_errors_latest = Ошибки файлов при последней индексации
+file_errors_count = Ошибки файлов при последней индексации: { $count }
+file_error = { $path } ({ $stage }): { $error }
+file_error_stage_hash = чтение
+file_error_stage_parse = разбор
+file_error_stage_embedding = вычисление эмбеддинга
+file_error_stage_bulk = отправка в индекс
+file_errors_load_more = Загрузить ещё
+file_errors_loading_error = Ошибка загрузки ошибок файлов: { $error }
+retry_failed_files = Повторить для файлов с ошибками
+watcher_activity = Последние действия отслеживания изменений
+watcher_activity_none = Изменений файлов с открытия страницы не было
+mute_watcher = Не показывать действия отслеживания (при массовых операциях с файлами)
+watcher_event = { $time } — { $event } ({ $indexing })
+watcher_event_changed = изменён: { $path }
+watcher_event_moved = переименован в: { $path }
+watcher_event_full_indexing = слишком много изменений, индексируются все файлы
+watcher_event_queued = индексация запущена
+watcher_event_coalesced = ожидает текущую индексацию
+directory_stats_loading_error = Ошибка загрузки статистики папок: { $error }
 clear_index = Очистить индекс
+rebuild_index = Перестроить индекс
 index = Индексировать
 indexing_status_loading_error = ❌ Ошибка загрузки статуса индексации: { $error }
 index_clearing_error = ❌ Ошибка очищения индекса: { $error }
+clear_index_confirm = Будет удалено проиндексированных документов: { $count }. Продолжить?
+index_schema_outdated = ⚠️ Индекс несовместим с этой версией (версия схемы индекса { $index_version }, требуется { $current_version }). Очистите индекс и проиндексируйте файлы заново. Причина: { $reason }
+index_rebuilding_error = ❌ Ошибка перестроения индекса: { $error }
+indexing_estimate = Оценка индексации
+estimate_sample_percent = Размер выборки (% файлов):
+estimate = Оценить
+estimating = ⏳ Идёт оценка...
+estimate_sample = Обработано { $sample_cnt } из { $files_cnt } файлов ({ $sample_size } из { $files_size }), ошибок: { $errors_cnt }
+estimate_duration = Прогнозируемое время индексации: { $duration }
+estimate_stages = В том числе хеширование: { $hash }, извлечение метаданных: { $metadata }, обработка содержимого и вычисление эмбеддингов: { $processing }
+estimate_index_size = Прогнозируемый размер индекса: { $size }
+estimate_caveat = Прогноз экстраполирован по случайной выборке файлов и может значительно отличаться от реальных значений: время обработки зависит от типов файлов, кешей и загруженности Apache Tika и сервера нейронных сетей. Учитываются только файлы, не превышающие максимальный размер файла. В индекс ничего не записывается.
+estimation_error = ❌ Ошибка оценки: { $error }
 
 warning = Предупреждение
 settings_warning =
     Для применения серверных настроек и настроек сервера нейронных сетей требуется перезапуск.
     После добавления или удаления индексируемых папок нужно выполнить индексацию.
-    Для применения настроек сервера нейронных сетей, влияющих на индексацию, таких как использование семантического поиска по тексту или изображениям (и их параметров), нужно выполнить переиндексацию (очистить индекс и заново проиндексировать).
+    Для применения настроек сервера нейронных сетей, влияющих на индексацию, таких как использование семантического поиска по тексту или изображениям (и их параметров), нужно перестроить индекс.
 indexable_folders = Индексируемые папки
 exclude_file_regex = Регулярное выражение для исключения файлов:
+case_insensitive_paths = Пути, отличающиеся только регистром, указывают на один файл (Windows, macOS)
+skip_content_types = Типы файлов, индексируемых без обработки содержимого (MIME-типы или их начала):
+content_type_overrides = Типы файлов, читаемых как текст без Apache Tika (расширение=MIME-тип):
+index_archives = Индексировать текст файлов в zip-архивах:
 server_settings = Серверные настройки
 indexer_address = Адрес сервера индексации:
-elasticsearch_url = URL сервера Elasticsearch:
+api_token = Токен API, обязательный в запросах к серверу индексации, если задан:
+elasticsearch_urls = URL узлов Elasticsearch через запятую:
+elasticsearch_user = Пользователь Elasticsearch (пустой без аутентификации):
+elasticsearch_password = Пароль Elasticsearch:
+elasticsearch_ca_cert_path = Путь к сертификату CA для Elasticsearch (PEM, пустой для системных сертификатов):
 tika_url = URL сервера Apache Tika:
 nn_server_url = URL сервера нейронных сетей:
+wake_url = URL лаунчера для запуска остановленного индексатора (необязательно):
 open_on_start = Открывать интерфейс при запуске сервера:
+metrics_enabled = Отдавать метрики в формате Prometheus по адресу /metrics:
+file_actions_enabled = Разрешить перемещение файлов в корзину из результатов поиска
+report_errors = Сообщать индексатору об ошибках интерфейса (без запросов и путей к файлам):
+client_language = Язык интерфейса:
+client_language_auto = Язык браузера
+client_language_en = English
+client_language_ru = Русский
+trigger_test_error = Вызвать тестовую ошибку
+interface_settings = Интерфейс
+theme = Тема:
+theme_auto = Как в системе
+theme_light = Светлая
+theme_dark = Тёмная
+results_layout = Вид результатов поиска:
+results_layout_list = Список
+results_layout_grid = Сетка
+thumbnails_enabled = Показывать миниатюры в результатах поиска
+interface_local = Сохранять настройки интерфейса только в этом браузере
 indexing_settings = Настройки индексации
 watcher_enabled = Отслеживать изменения файлов:
 debouncer_timeout = Время задержки событий файловой системы (с):
+periodic_indexing_enabled = Индексировать по расписанию:
+periodic_indexing_interval_hours = Интервал между индексациями по расписанию (ч):
 max_file_size = Максимальный размер файла (МиБ):
+hash_change_detection_max_size = Максимальный размер папки для сравнения хешей (ГиБ):
+hash_algorithm = Хеш содержимого файлов:
+hash_algorithm_sha256 = SHA-256
+hash_algorithm_xxh3 = xxHash (XXH3, быстрее)
+hash_algorithm_none = Не вычисляется
+hash_chunk_size = Размер блоков чтения файлов при вычислении хеша (КиБ):
+online_only_xattr = Расширенный атрибут файлов только в облаке (Linux):
+online_only_hydration_budget = Максимальный размер файлов только в облаке, загружаемых за индексацию (ГиБ):
 max_concurrent_files = Максимальное количество одновременно обрабатываемых файлов:
+slow_file_threshold = Сообщать о файлах, обрабатываемых дольше (с, 0 — не сообщать):
+parse_failures_max = Индексировать файлы без разбора после неудач (0 — всегда повторять):
+index_xattrs = Индексировать имена расширенных атрибутов файлов (замедляет сканирование)
+follow_symlinks = Переходить по символическим ссылкам (файлы, доступные по нескольким путям, индексируются один раз)
+cjk_analysis = Разбивать текст на китайском, японском и корейском языках на биграммы (требуется перестроение индекса)
+content_languages = Дополнительные языки текста со стеммингом, например, de или fr (требуется перестроение индекса):
+ocr_enabled = Распознавать текст на изображениях с помощью Tesseract OCR:
+ocr_tesseract_path = Путь к исполняемому файлу Tesseract:
+ocr_languages = Языки OCR:
+ocr_min_dimension = Минимальная ширина и высота изображения для OCR:
+thumbnail_cache_enabled = Кэшировать миниатюры на диске
+thumbnail_cache_dir = Папка кэша миниатюр:
+thumbnail_cache_max_size = Максимальный размер кэша миниатюр (МиБ):
+preview_chunk_size = Размер текста, загружаемого за раз при предпросмотре (КиБ):
 elasticsearch_batch_size = Количество отправляемых в Elasticsearch изменений за раз:
+elasticsearch_pit_keep_alive = Время хранения точки во времени Elasticsearch между страницами списка файлов (минуты):
+elasticsearch_bulk_retries = Количество повторных попыток отправки изменений в Elasticsearch:
 search_settings = Настройки поиска
 results_per_page = Количество результатов на странице:
+results_per_page_max = Максимальное количество результатов на странице в поисковых запросах:
 knn_candidates_multiplier = Множитель количества кандидатов kNN при семантическом поиске:
+recency_boost_scale_days = Возраст файлов в днях, при котором усиление недавних файлов уменьшается вдвое:
+prefetch_next_page = Загружать следующую страницу результатов в фоне
+prefetch_with_reranking = Загружать следующую страницу в фоне и при включённом переранжировании (медленно)
+auto_correct_default = Автоматически исправлять запросы с опечатками по умолчанию
+auto_correct_max_hits = Исправлять запросы с числом результатов меньше:
+field_weight = Вес поля "{ $field }" в текстовом поиске:
 nn_server_settings = Настройки сервера нейронных сетей
 nn_server_address = Адрес сервера нейронных сетей:
 text_search_enabled = Семантический поиск по тексту:
@@ -254,9 +534,16 @@ max_sentences = Максимальное количество обрабатыв
 window_size = Размер окна слов:
 window_step = Шаг окна слов:
 summary_len = Количество предложений на документ для переранжирования:
+summary_len_max = Максимальное количество предложений для длинных документов:
+text_cache_capacity = Кэшируемых результатов текстовых моделей (0 — отключить):
 nn_setting_device = { $model }: устройство:
+nn_setting_effective_device = Модель работает на { $device }, так как выбранное устройство недоступно
 nn_setting_batch_size = { $model }: размер пакета:
 nn_setting_max_delay = { $model }: время ожидания пакета (мс):
+nn_setting_max_queue = { $model }: максимальный размер очереди:
+nn_setting_model_path = { $model }: папка модели:
+nn_setting_embedding_dim = { $model }: размерность векторов:
+nn_setting_idle_timeout = { $model }: выгружать после простоя (мин, 0 — не выгружать):
 cpu = Процессор
 cuda = CUDA
 include = Включить
@@ -266,6 +553,20 @@ excluded = Исключено
 watch = Отслеживать
 watching = Отслеживается
 not_watching = Не отслеживается
+change_detection_mtime_size = Сравнивать время изменения
+change_detection_hash = Сравнивать хеши
+change_detection_hash_always = Всегда сравнивать хеши
+hash_always = Игнорировать время изменения
+exclude_regex = Регулярное выражение исключаемых файлов
+max_concurrency = Одновременно файлов
+max_concurrency_hint = Максимальное число одновременно обрабатываемых файлов папки, например, для медленных сетевых дисков (пусто — общее ограничение)
+content_type_preset = Фильтр типа файлов по умолчанию при поиске в этой папке
+content_type_preset_none = Любой тип файлов
+content_type_preset_images = Изображения
+content_type_preset_audio = Аудио
+content_type_preset_video = Видео
+content_type_preset_documents = Документы
+content_type_preset_custom = Свой фильтр
 dialog_opening_error = ❌ Ошибка открытия диалога: { $error }
 settings_loading_error = ❌ Ошибка загрузки настроек: { $error }
 settings_saving_error = ❌ Ошибка сохранения настроек: { $error }
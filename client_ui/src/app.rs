@@ -1,26 +1,66 @@
-use std::{borrow::Cow, str::FromStr, sync::OnceLock};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    future::Future,
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
 
-use common_lib::{settings::Settings, ClientTranslation};
+use common_lib::{
+    indexer::IndexSchemaStatus, launcher::WakeStatus, settings::Settings, ClientTranslation,
+};
 use derive_more::Display;
 use fluent_bundle::{bundle::FluentBundle, FluentArgs, FluentResource};
 use intl_memoizer::concurrent::IntlLangMemoizer;
-use js_sys::JSON;
+use js_sys::{Promise, JSON};
 use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::{from_value, to_value};
 use sycamore::prelude::*;
 use sycamore::rt::Event;
 use unic_langid::LanguageIdentifier;
-use wasm_bindgen::{JsCast, JsValue};
+use url::Url;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{HtmlElement, Request, RequestInit, RequestMode, Response};
+use web_sys::{
+    AbortController, AbortSignal, Blob, Element, Headers, HtmlElement, KeyboardEvent, Request,
+    RequestInit, RequestMode, Response, Storage,
+};
 
-use crate::{search::Search, settings::Settings, status::Status};
+use crate::{
+    duplicates::Duplicates,
+    search::Search,
+    settings::Settings,
+    status::{IndexSchemaBanner, Status},
+};
 
-use self::widgets::{StatusDialog, StatusDialogState};
+use self::{
+    abort::{Abort, AbortSlot},
+    interface::Interface,
+    widgets::{StatusDialog, StatusDialogState},
+};
 
+mod abort;
+pub mod error_report;
+pub mod interface;
 pub mod widgets;
 
 static TRANSLATION: OnceLock<FluentBundle<FluentResource, IntlLangMemoizer>> = OnceLock::new();
+/// Launcher endpoint that starts indexer, from settings
+static WAKE_URL: Mutex<Option<Url>> = Mutex::new(None);
+/// Token of indexer API, from page URL or entered by user
+static API_TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+thread_local! {
+    /// Status of starting indexer through launcher, while waiting for it
+    static WAKE_STATUS: RcSignal<Option<WakeStatus>> = create_rc_signal(None);
+}
+
+const WAKE_POLL_DELAY_MIN_MS: i32 = 250;
+const WAKE_POLL_DELAY_MAX_MS: i32 = 4000;
+/// Key of API token in local storage
+const API_TOKEN_STORAGE_KEY: &str = "api_token";
+/// Status of response to request without valid API token
+const UNAUTHORIZED_STATUS: u16 = 401;
 
 #[derive(Display, PartialEq, Eq, Hash, Clone, Copy)]
 enum AppTabs {
@@ -28,6 +68,8 @@ enum AppTabs {
     Search,
     #[display(fmt = "indexing_status_tab")]
     IndexingStatus,
+    #[display(fmt = "duplicates_tab")]
+    Duplicates,
     #[display(fmt = "settings_tab")]
     Settings,
 }
@@ -39,14 +81,107 @@ impl FromStr for AppTabs {
         match s {
             "search_tab" => Ok(AppTabs::Search),
             "indexing_status_tab" => Ok(AppTabs::IndexingStatus),
+            "duplicates_tab" => Ok(AppTabs::Duplicates),
             "settings_tab" => Ok(AppTabs::Settings),
             _ => Err(std::fmt::Error),
         }
     }
 }
 
+/// Checks if key was pressed while typing text, including with on-screen keyboard
+fn is_typing(event: &KeyboardEvent) -> bool {
+    event.is_composing()
+        || event
+            .target()
+            .and_then(|x| x.dyn_into::<Element>().ok())
+            .is_some_and(|x| matches!(x.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"))
+}
+
+/// Action of keyboard shortcut of search tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    /// `/`
+    FocusQuery,
+    /// Down arrow
+    NextResult,
+    /// Up arrow
+    PreviousResult,
+    /// `Enter`, opens selected result
+    OpenResult,
+    /// `p`, shows preview of selected result
+    PreviewResult,
+    /// `Esc`, closes preview or filters drawer
+    Close,
+    /// `[`
+    PreviousPage,
+    /// `]`
+    NextPage,
+}
+
+impl Shortcut {
+    /// Shortcut of pressed key. Keys are ignored while typing or with modifiers,
+    /// and `Enter` still activates focused buttons and links
+    fn from_event(event: &KeyboardEvent) -> Option<Self> {
+        if event.ctrl_key() || event.alt_key() || event.meta_key() || is_typing(event) {
+            return None;
+        }
+        let on_control = event
+            .target()
+            .and_then(|x| x.dyn_into::<Element>().ok())
+            .is_some_and(|x| matches!(x.tag_name().as_str(), "BUTTON" | "A" | "SUMMARY"));
+        let shortcut = match event.key().as_str() {
+            "/" => Self::FocusQuery,
+            "ArrowDown" => Self::NextResult,
+            "ArrowUp" => Self::PreviousResult,
+            "Enter" if !on_control => Self::OpenResult,
+            "p" => Self::PreviewResult,
+            "Escape" => Self::Close,
+            "[" => Self::PreviousPage,
+            "]" => Self::NextPage,
+            _ => return None,
+        };
+        Some(shortcut)
+    }
+}
+
+/// Listens to keys pressed anywhere on page and sends their shortcuts to signal while enabled
+#[component(inline_props)]
+fn KeyboardShortcuts<'a, G: Html>(
+    cx: Scope<'a>,
+    enabled: &'a ReadSignal<bool>,
+    shortcut: RcSignal<Option<Shortcut>>,
+) -> View<G> {
+    // Listener outlives scope, so it reads copy of signal
+    let is_enabled = create_rc_signal(false);
+    let is_enabled_ = is_enabled.clone();
+    create_effect(cx, move || is_enabled_.set(*enabled.get()));
+
+    let on_keydown = Closure::<dyn Fn(KeyboardEvent)>::new(move |e: KeyboardEvent| {
+        if !*is_enabled.get_untracked() {
+            return;
+        }
+        let Some(x) = Shortcut::from_event(&e) else {
+            return;
+        };
+        // Slash isn't typed into focused query, arrows don't scroll page
+        if x != Shortcut::Close {
+            e.prevent_default();
+        }
+        shortcut.set(Some(x));
+    });
+    _ = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref());
+    on_keydown.forget();
+
+    view! { cx, }
+}
+
 #[component]
 pub async fn App<G: Html>(cx: Scope<'_>) -> View<G> {
+    load_api_token();
     assert!(TRANSLATION.set(load_translation().await).is_ok());
 
     let document = web_sys::window()
@@ -62,13 +197,40 @@ pub async fn App<G: Html>(cx: Scope<'_>) -> View<G> {
 
     // Use default settings until loaded from server
     let settings = create_signal(cx, Settings::default());
+    create_effect(cx, || {
+        *WAKE_URL.lock().unwrap() = settings.get().client.wake_url.clone();
+        error_report::set_report_errors(settings.get().client.report_errors);
+    });
+    // Overrides saved in this browser are read again when settings are saved
+    let interface = create_memo(cx, || Interface::load(&settings.get().client));
+    create_effect(cx, || interface.get().apply_theme());
+    let wake_status = WAKE_STATUS.with(Clone::clone);
+    let wake_status_str = create_memo(cx, move || match *wake_status.get() {
+        Some(WakeStatus::Starting(component)) => {
+            let args = FluentArgs::from_iter([("component", component.to_string())]);
+            Some(get_translation("starting_backend", Some(&args)).to_string())
+        }
+        _ => None,
+    });
 
     let status_dialog_state = create_signal(cx, StatusDialogState::None);
+    let index_schema = create_signal(cx, IndexSchemaStatus::Compatible);
     let tabs = create_signal(
         cx,
-        vec![AppTabs::Search, AppTabs::IndexingStatus, AppTabs::Settings],
+        vec![
+            AppTabs::Search,
+            AppTabs::IndexingStatus,
+            AppTabs::Duplicates,
+            AppTabs::Settings,
+        ],
     );
     let curr_tab = create_signal(cx, AppTabs::Search);
+    create_effect(cx, || {
+        error_report::set_current_tab(curr_tab.get().to_string())
+    });
+    let search_tab_shown = create_memo(cx, || *curr_tab.get() == AppTabs::Search);
+    let search_shortcut = create_rc_signal(None);
+    let search_shortcut_ = search_shortcut.clone();
     let switch_tab = |event: Event| {
         let event_target = event.target().unwrap();
         let element: &HtmlElement = event_target.dyn_ref::<HtmlElement>().unwrap();
@@ -95,24 +257,170 @@ pub async fn App<G: Html>(cx: Scope<'_>) -> View<G> {
             }
         }
 
+        (if let Some(x) = (*wake_status_str.get()).clone() {
+            view! { cx,
+                p(id="wake_status") { (x) }
+            }
+        } else {
+            view! { cx, }
+        })
+        IndexSchemaBanner(status_dialog_state=status_dialog_state, index_schema=index_schema)
+
         div(style={if *curr_tab.get().as_ref() == AppTabs::Search { "display: block;" } else { "display: none;" }}) {
-            Search(settings=settings, status_dialog_state=status_dialog_state)
+            Search(settings=settings, interface=interface, status_dialog_state=status_dialog_state,
+                shortcut=search_shortcut)
         }
         div(style={if *curr_tab.get().as_ref() == AppTabs::IndexingStatus { "display: block;" } else { "display: none;" }}) {
-            Status(status_dialog_state=status_dialog_state)
+            Status(status_dialog_state=status_dialog_state, index_schema=index_schema)
+        }
+        div(style={if *curr_tab.get().as_ref() == AppTabs::Duplicates { "display: block;" } else { "display: none;" }}) {
+            Duplicates(status_dialog_state=status_dialog_state)
         }
         div(style={if *curr_tab.get().as_ref() == AppTabs::Settings { "display: block;" } else { "display: none;" }}) {
             Settings(settings=settings, status_dialog_state=status_dialog_state)
         }
 
         StatusDialog(status=status_dialog_state)
+        KeyboardShortcuts(enabled=search_tab_shown, shortcut=search_shortcut_)
     }
 }
 
-async fn fetch_response(
+pub async fn sleep(ms: i32) {
+    let promise = Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+            .unwrap();
+    });
+    JsFuture::from(promise).await.unwrap();
+}
+
+/// Save blob to file with browser download
+pub async fn download_blob(blob: &Blob, file_name: &str) -> Result<(), JsValue> {
+    let url = web_sys::Url::create_object_url_with_blob(blob)?;
+    let link: HtmlElement = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("a")?
+        .dyn_into()?;
+    link.set_attribute("href", &url)?;
+    link.set_attribute("download", file_name)?;
+    link.click();
+    // Some browsers start download after click returns
+    sleep(1000).await;
+    web_sys::Url::revoke_object_url(&url)
+}
+
+fn local_storage() -> Option<Storage> {
+    web_sys::window().unwrap().local_storage().ok().flatten()
+}
+
+/// Load API token from `#token=...` in page URL, removing it from address bar,
+/// or from local storage
+fn load_api_token() {
+    let window = web_sys::window().unwrap();
+    let location = window.location();
+    let hash = location.hash().unwrap_or_default();
+    let url_token = url::form_urlencoded::parse(hash.trim_start_matches('#').as_bytes())
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned());
+    match url_token {
+        Some(token) => {
+            set_api_token(Some(token));
+            if let (Ok(history), Ok(path)) = (window.history(), location.pathname()) {
+                _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&path));
+            }
+        }
+        None => {
+            *API_TOKEN.lock().unwrap() =
+                local_storage().and_then(|x| x.get_item(API_TOKEN_STORAGE_KEY).ok().flatten());
+        }
+    }
+}
+
+/// Use API token in requests and keep it for next visits
+pub fn set_api_token(token: Option<String>) {
+    if let Some(storage) = local_storage() {
+        _ = match &token {
+            Some(x) => storage.set_item(API_TOKEN_STORAGE_KEY, x),
+            None => storage.remove_item(API_TOKEN_STORAGE_KEY),
+        };
+    }
+    *API_TOKEN.lock().unwrap() = token;
+}
+
+pub fn api_token() -> Option<String> {
+    API_TOKEN.lock().ok().and_then(|x| x.clone())
+}
+
+/// Add API token as query parameter, for requests without headers: images, WebSocket, beacons
+pub fn add_api_token(url: &mut Url) {
+    if let Some(token) = api_token() {
+        url.query_pairs_mut().append_pair("token", &token);
+    }
+}
+
+/// Ask user for API token after request was rejected, cancelling keeps current token
+fn prompt_api_token() {
+    let token = web_sys::window()
+        .unwrap()
+        .prompt_with_message(&get_translation("api_token_prompt", None))
+        .ok()
+        .flatten();
+    if let Some(token) = token.filter(|x| !x.is_empty()) {
+        set_api_token(Some(token));
+    }
+}
+
+async fn fetch_wake_status(wake_url: &Url, method: &str) -> Result<WakeStatus, JsValue> {
+    let mut opts = RequestInit::new();
+    opts.method(method).mode(RequestMode::Cors);
+    let request = Request::new_with_str_and_init(wake_url.as_str(), &opts)?;
+    if let Some(token) = api_token() {
+        request
+            .headers()
+            .set("Authorization", &format!("Bearer {token}"))?;
+    }
+
+    let window = web_sys::window().unwrap();
+    let response_value = JsFuture::from(window.fetch_with_request(&request)).await?;
+    let response: Response = response_value.dyn_into().unwrap();
+    let response_json = JsFuture::from(response.json()?).await?;
+    from_value(response_json).map_err(|e| e.into())
+}
+
+/// Start indexer through launcher and wait until it's running
+async fn wake_indexer(wake_url: &Url) -> Result<(), JsValue> {
+    let mut delay_ms = WAKE_POLL_DELAY_MIN_MS;
+    let mut status_res = fetch_wake_status(wake_url, "POST").await;
+    let res = loop {
+        let status = match status_res {
+            Ok(x) => x,
+            Err(e) => break Err(e),
+        };
+        WAKE_STATUS.with(|x| x.set(Some(status.clone())));
+        match status {
+            WakeStatus::Running => break Ok(()),
+            WakeStatus::Failed(e) => break Err(JsValue::from(e)),
+            WakeStatus::Idle => break Err(JsValue::from("Indexer has stopped")),
+            WakeStatus::Starting(_) => {}
+        }
+
+        sleep(delay_ms).await;
+        delay_ms = (delay_ms * 2).min(WAKE_POLL_DELAY_MAX_MS);
+        status_res = fetch_wake_status(wake_url, "GET").await;
+    };
+    WAKE_STATUS.with(|x| x.set(None));
+    res
+}
+
+/// Send request, starting indexer and asking for API token if needed. Response can have any status
+async fn send_request(
     uri: &str,
     method: &str,
     body: Option<&impl Serialize>,
+    signal: Option<&AbortSignal>,
 ) -> Result<Response, JsValue> {
     let request_body = body
         .map(|x| to_value(x).map_err(Into::<JsValue>::into))
@@ -121,19 +429,66 @@ async fn fetch_response(
         .transpose()?
         .map(JsValue::from);
 
-    let mut opts = RequestInit::new();
-    opts.method(method)
-        .mode(RequestMode::SameOrigin)
-        .body(request_body.as_ref());
+    let create_request = || {
+        let mut opts = RequestInit::new();
+        opts.method(method)
+            .mode(RequestMode::SameOrigin)
+            .body(request_body.as_ref())
+            .signal(signal);
 
-    let request = Request::new_with_str_and_init(uri, &opts)?;
-    if request_body.is_some() {
-        request.headers().set("Content-Type", "application/json")?;
-    }
+        let request = Request::new_with_str_and_init(uri, &opts)?;
+        if request_body.is_some() {
+            request.headers().set("Content-Type", "application/json")?;
+        }
+        if let Some(token) = api_token() {
+            request
+                .headers()
+                .set("Authorization", &format!("Bearer {token}"))?;
+        }
+        Ok::<_, JsValue>(request)
+    };
+
+    let sent_token = api_token();
 
     let window = web_sys::window().unwrap();
-    let response_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let response: Response = response_value.dyn_into().unwrap();
+    let response_value = match JsFuture::from(window.fetch_with_request(&create_request()?)).await {
+        Ok(x) => x,
+        Err(e) if signal.is_some_and(AbortSignal::aborted) => return Err(e),
+        // Network error, try to start indexer if it's stopped
+        Err(e) => {
+            let wake_url = WAKE_URL.lock().unwrap().clone();
+            match wake_url {
+                Some(wake_url) => {
+                    wake_indexer(&wake_url).await?;
+                    JsFuture::from(window.fetch_with_request(&create_request()?)).await?
+                }
+                None => return Err(e),
+            }
+        }
+    };
+    let mut response: Response = response_value.dyn_into().unwrap();
+    if response.status() == UNAUTHORIZED_STATUS {
+        // Other request could have received new token while this one was running
+        if api_token() == sent_token {
+            prompt_api_token();
+        }
+        if api_token() != sent_token {
+            response = JsFuture::from(window.fetch_with_request(&create_request()?))
+                .await?
+                .dyn_into()
+                .unwrap();
+        }
+    }
+    Ok(response)
+}
+
+async fn fetch_response(
+    uri: &str,
+    method: &str,
+    body: Option<&impl Serialize>,
+    signal: Option<&AbortSignal>,
+) -> Result<Response, JsValue> {
+    let response = send_request(uri, method, body, signal).await?;
     if response.ok() {
         Ok(response)
     } else {
@@ -141,28 +496,174 @@ async fn fetch_response(
     }
 }
 
-pub async fn fetch<T>(uri: &str, method: &str, body: Option<&impl Serialize>) -> Result<T, JsValue>
+async fn fetch_with_signal<T>(
+    uri: &str,
+    method: &str,
+    body: Option<&impl Serialize>,
+    signal: Option<&AbortSignal>,
+) -> Result<T, JsValue>
 where
     T: for<'de> Deserialize<'de>,
 {
-    let response = fetch_response(uri, method, body).await?;
+    let response = fetch_response(uri, method, body, signal).await?;
     let response_json = JsFuture::from(response.json()?).await?;
     from_value(response_json).map_err(|e| e.into())
 }
 
+pub async fn fetch<T>(uri: &str, method: &str, body: Option<&impl Serialize>) -> Result<T, JsValue>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    fetch_with_signal(uri, method, body, None).await
+}
+
 pub async fn fetch_empty(
     uri: &str,
     method: &str,
     body: Option<&impl Serialize>,
 ) -> Result<(), JsValue> {
-    fetch_response(uri, method, body).await?;
+    fetch_response(uri, method, body, None).await?;
     Ok(())
 }
 
-async fn load_translation() -> FluentBundle<FluentResource, IntlLangMemoizer> {
-    let translation_data: ClientTranslation = fetch("/client_translation", "GET", None::<&()>)
+/// Status code of response, for requests where error status is an expected result
+pub async fn fetch_status(uri: &str, method: &str) -> Result<u16, JsValue> {
+    Ok(send_request(uri, method, None::<&()>, None).await?.status())
+}
+
+impl Abort for AbortController {
+    fn abort(&self) {
+        AbortController::abort(self)
+    }
+}
+
+/// Requests of single widget, with its own loading indicator.
+/// New request cancels previous one, results of cancelled requests are `None`
+#[derive(Clone, Copy)]
+pub struct RequestScope<'a> {
+    loading: &'a Signal<bool>,
+    slot: &'a RefCell<AbortSlot<AbortController>>,
+}
+
+impl<'a> RequestScope<'a> {
+    pub fn new(cx: Scope<'a>) -> Self {
+        Self {
+            loading: create_signal(cx, false),
+            slot: create_ref(cx, RefCell::new(AbortSlot::default())),
+        }
+    }
+
+    /// Whether request is in flight
+    pub fn loading(self) -> &'a ReadSignal<bool> {
+        self.loading
+    }
+
+    /// Run request, aborting previous one
+    async fn run<T, F, Fut>(self, request: F) -> Option<Result<T, JsValue>>
+    where
+        F: FnOnce(AbortSignal) -> Fut,
+        Fut: Future<Output = Result<T, JsValue>>,
+    {
+        let controller = AbortController::new().unwrap();
+        let signal = controller.signal();
+        let id = self.slot.borrow_mut().start(controller);
+        self.loading.set(true);
+
+        let res = request(signal).await;
+        if !self.slot.borrow_mut().finish(id) {
+            return None;
+        }
+        self.loading.set(false);
+        Some(res)
+    }
+
+    pub async fn fetch<T>(
+        self,
+        uri: &str,
+        method: &str,
+        body: Option<&impl Serialize>,
+    ) -> Option<Result<T, JsValue>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.run(|signal| async move { fetch_with_signal(uri, method, body, Some(&signal)).await })
+            .await
+    }
+
+    pub async fn fetch_empty(
+        self,
+        uri: &str,
+        method: &str,
+        body: Option<&impl Serialize>,
+    ) -> Option<Result<(), JsValue>> {
+        self.run(|signal| async move {
+            fetch_response(uri, method, body, Some(&signal)).await?;
+            Ok(())
+        })
         .await
-        .unwrap();
+    }
+
+    pub async fn fetch_blob(
+        self,
+        uri: &str,
+        method: &str,
+        body: Option<&impl Serialize>,
+    ) -> Option<Result<Blob, JsValue>> {
+        self.run(|signal| async move {
+            let response = fetch_response(uri, method, body, Some(&signal)).await?;
+            JsFuture::from(response.blob()?).await?.dyn_into()
+        })
+        .await
+    }
+
+    /// Status code and text of response, for requests where error status is an expected result
+    pub async fn fetch_status_text(
+        self,
+        uri: &str,
+        method: &str,
+        body: Option<&impl Serialize>,
+    ) -> Option<Result<(u16, String), JsValue>> {
+        self.run(|signal| async move {
+            let response = send_request(uri, method, body, Some(&signal)).await?;
+            let text = JsFuture::from(response.text()?).await?;
+            Ok((response.status(), text.as_string().unwrap_or_default()))
+        })
+        .await
+    }
+
+    /// Text of response with its headers
+    pub async fn fetch_text_with_headers(
+        self,
+        uri: &str,
+    ) -> Option<Result<(String, Headers), JsValue>> {
+        self.run(|signal| async move {
+            let response = fetch_response(uri, "GET", None::<&()>, Some(&signal)).await?;
+            let text = JsFuture::from(response.text()?).await?;
+            Ok((text.as_string().unwrap_or_default(), response.headers()))
+        })
+        .await
+    }
+
+    /// Abort current request
+    pub fn cancel(self) {
+        let was_active = self.slot.borrow().is_active();
+        if was_active {
+            self.slot.borrow_mut().cancel();
+            self.loading.set(false);
+        }
+    }
+}
+
+/// Load translation to language from settings, or to language of browser
+async fn load_translation() -> FluentBundle<FluentResource, IntlLangMemoizer> {
+    let mut uri = "/client_translation".to_owned();
+    if let Some(lang) = web_sys::window().unwrap().navigator().language() {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("lang", &lang)
+            .finish();
+        uri = format!("{uri}?{query}");
+    }
+    let translation_data: ClientTranslation = fetch(&uri, "GET", None::<&()>).await.unwrap();
 
     let lang_id: LanguageIdentifier = translation_data.lang_id.parse().unwrap();
     let mut bundle = FluentBundle::new_concurrent(vec![lang_id]);
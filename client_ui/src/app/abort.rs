@@ -0,0 +1,122 @@
+/// Handle that cancels in-flight request
+pub trait Abort {
+    fn abort(&self);
+}
+
+/// Slot for single in-flight request of widget.
+/// Starting new request aborts previous one, so only result of the latest request is used
+#[derive(Debug)]
+pub struct AbortSlot<H: Abort> {
+    current: Option<(u64, H)>,
+    next_id: u64,
+}
+
+impl<H: Abort> Default for AbortSlot<H> {
+    fn default() -> Self {
+        Self {
+            current: None,
+            next_id: 0,
+        }
+    }
+}
+
+impl<H: Abort> AbortSlot<H> {
+    /// Abort previous request and remember handle of new one.
+    /// Returns ID of new request
+    pub fn start(&mut self, handle: H) -> u64 {
+        self.cancel();
+        let id = self.next_id;
+        self.next_id += 1;
+        self.current = Some((id, handle));
+        id
+    }
+
+    /// Mark request as finished.
+    /// Returns `false` if request was aborted or replaced, then its result should be ignored
+    pub fn finish(&mut self, id: u64) -> bool {
+        match self.current {
+            Some((current_id, _)) if current_id == id => {
+                self.current = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Abort current request, if any
+    pub fn cancel(&mut self) {
+        if let Some((_, handle)) = self.current.take() {
+            handle.abort();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    /// Handle that counts aborts of request
+    struct CountingHandle(Rc<Cell<u32>>);
+
+    impl Abort for CountingHandle {
+        fn abort(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    fn handle() -> (CountingHandle, Rc<Cell<u32>>) {
+        let aborts = Rc::new(Cell::new(0));
+        (CountingHandle(Rc::clone(&aborts)), aborts)
+    }
+
+    #[test]
+    fn finished_request_is_not_aborted() {
+        let mut slot = AbortSlot::default();
+        let (first, first_aborts) = handle();
+
+        let id = slot.start(first);
+        assert!(slot.is_active());
+        assert!(slot.finish(id));
+        assert!(!slot.is_active());
+        slot.cancel();
+
+        assert_eq!(first_aborts.get(), 0);
+    }
+
+    #[test]
+    fn new_request_aborts_previous_one() {
+        let mut slot = AbortSlot::default();
+        let (first, first_aborts) = handle();
+        let (second, second_aborts) = handle();
+
+        let first_id = slot.start(first);
+        let second_id = slot.start(second);
+
+        assert_ne!(first_id, second_id);
+        assert_eq!(first_aborts.get(), 1);
+        assert!(!slot.finish(first_id));
+        assert!(slot.is_active());
+        assert!(slot.finish(second_id));
+        assert_eq!(second_aborts.get(), 0);
+    }
+
+    #[test]
+    fn cancelled_request_is_aborted_once_and_its_result_ignored() {
+        let mut slot = AbortSlot::default();
+        let (first, first_aborts) = handle();
+
+        let id = slot.start(first);
+        slot.cancel();
+        slot.cancel();
+
+        assert_eq!(first_aborts.get(), 1);
+        assert!(!slot.is_active());
+        assert!(!slot.finish(id));
+    }
+}
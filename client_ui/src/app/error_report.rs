@@ -0,0 +1,209 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use common_lib::client_error::{ClientErrorReport, CLIENT_ERROR_MESSAGE_MAX_LEN};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::ErrorEvent;
+
+/// Are errors reported to indexer, from settings
+static REPORT_ERRORS: AtomicBool = AtomicBool::new(false);
+/// Was there a panic, JS errors after it are its consequences and aren't reported
+static PANICKED: AtomicBool = AtomicBool::new(false);
+/// Currently open tab
+static CURRENT_TAB: Mutex<String> = Mutex::new(String::new());
+
+pub fn set_report_errors(report_errors: bool) {
+    REPORT_ERRORS.store(report_errors, Ordering::Relaxed);
+}
+
+pub fn set_current_tab(tab: String) {
+    *CURRENT_TAB.lock().unwrap() = tab;
+}
+
+/// Placeholder of removed parts of error message
+const REMOVED: &str = "…";
+
+/// Characters that separate words of error message, kept in sanitized message
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '{' | '}' | ',' | ':' | ';')
+}
+
+fn is_ascii_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace quoted spans with placeholder. Apostrophes inside of words don't start quotes
+fn remove_quoted(message: &str) -> String {
+    let mut unquoted = String::with_capacity(message.len());
+    let mut quote = None;
+    let mut prev = ' ';
+    for c in message.chars() {
+        match quote {
+            Some(q) if c == q => {
+                quote = None;
+                unquoted.push_str(REMOVED);
+            }
+            Some(_) => {}
+            None if matches!(c, '"' | '`' | '\'') && !prev.is_alphanumeric() => quote = Some(c),
+            None => unquoted.push(c),
+        }
+        prev = c;
+    }
+    if quote.is_some() {
+        unquoted.push_str(REMOVED);
+    }
+    unquoted
+}
+
+/// Keep only parts of message that can't contain query text or file paths: separators and
+/// words that are ASCII identifiers, such as error kind and wording of the message.
+/// Quoted spans and other words are replaced with placeholder
+fn sanitize(message: &str) -> String {
+    let unquoted = remove_quoted(message);
+    let mut sanitized = String::with_capacity(unquoted.len());
+    for part in unquoted.split_inclusive(is_separator) {
+        let (word, separator) = match part.char_indices().last() {
+            Some((i, c)) if is_separator(c) => part.split_at(i),
+            _ => (part, ""),
+        };
+        // Punctuation at the end of sentence isn't part of word
+        let (word, punctuation) = word.split_at(word.trim_end_matches(['.', '!', '?']).len());
+        if word.is_empty() || is_ascii_identifier(word) {
+            sanitized.push_str(word);
+            sanitized.push_str(punctuation);
+        } else if sanitized.trim_end().ends_with(REMOVED) {
+            // Consecutive removed words are replaced with one placeholder
+            sanitized.truncate(sanitized.trim_end().len());
+        } else {
+            sanitized.push_str(REMOVED);
+        }
+        sanitized.push_str(separator);
+    }
+    sanitized
+        .chars()
+        .take(CLIENT_ERROR_MESSAGE_MAX_LEN)
+        .collect()
+}
+
+/// Send error report to indexer if enabled. Beacon is used
+/// because it's sent even if the app is broken after panic
+fn report(message: &str, location: Option<String>) {
+    if !REPORT_ERRORS.load(Ordering::Relaxed) {
+        return;
+    }
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let navigator = window.navigator();
+    let report = ClientErrorReport {
+        message: sanitize(message),
+        location,
+        user_agent: navigator.user_agent().unwrap_or_default(),
+        tab: CURRENT_TAB.lock().map(|x| x.clone()).unwrap_or_default(),
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+    };
+    // Beacon can't have headers, token is sent as query parameter
+    let uri = match super::api_token() {
+        Some(token) => format!(
+            "/client_error?{}",
+            url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("token", &token)
+                .finish()
+        ),
+        None => "/client_error".to_owned(),
+    };
+    if let Ok(body) = serde_json::to_string(&report) {
+        _ = navigator.send_beacon_with_opt_str(&uri, Some(&body));
+    }
+}
+
+/// Log panics to console and report them
+pub fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        console_error_panic_hook::hook(info);
+        PANICKED.store(true, Ordering::Relaxed);
+
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|x| x.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let location = info
+            .location()
+            .map(|x| format!("{}:{}:{}", x.file(), x.line(), x.column()));
+        report(&message, location);
+    }));
+}
+
+/// Report unexpected JS errors
+pub fn listen_js_errors() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let on_error = Closure::<dyn Fn(ErrorEvent)>::new(|event: ErrorEvent| {
+        if !PANICKED.load(Ordering::Relaxed) {
+            report(&event.message(), None);
+        }
+    });
+    _ = window.add_event_listener_with_callback("error", on_error.as_ref().unchecked_ref());
+    on_error.forget();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_kind_and_wording_are_kept() {
+        assert_eq!(
+            sanitize("Uncaught TypeError: Cannot read properties of undefined (reading 'x')"),
+            "Uncaught TypeError: Cannot read properties of undefined (reading …)"
+        );
+        assert_eq!(
+            sanitize("RuntimeError: unreachable executed."),
+            "RuntimeError: unreachable executed."
+        );
+    }
+
+    #[test]
+    fn quoted_spans_are_removed() {
+        assert_eq!(
+            sanitize("called `Option::unwrap()` on a `None` value"),
+            "called … on a … value"
+        );
+        assert_eq!(
+            sanitize(r#"invalid query "secret words" in request"#),
+            "invalid query … in request"
+        );
+        assert_eq!(sanitize("Unexpected token 'secret"), "Unexpected token …");
+    }
+
+    #[test]
+    fn paths_numbers_and_non_ascii_words_are_removed() {
+        assert_eq!(
+            sanitize("Can't open /home/user/report 2024.pdf."),
+            "… open …"
+        );
+        assert_eq!(sanitize("Ошибка поиска: документ"), "…: …");
+        assert_eq!(
+            sanitize("index out of bounds: the len is 3 but the index is 5"),
+            "index out of bounds: the len is … but the index is …"
+        );
+    }
+
+    #[test]
+    fn long_message_is_truncated() {
+        let message = "word ".repeat(CLIENT_ERROR_MESSAGE_MAX_LEN);
+        assert_eq!(
+            sanitize(&message).chars().count(),
+            CLIENT_ERROR_MESSAGE_MAX_LEN
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use common_lib::settings::{ClientSettings, ClientTheme, ResultsLayout};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const INTERFACE_STORAGE_KEY: &str = "interface";
+
+/// Interface settings in effect, from settings of server or overridden in this browser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interface {
+    pub theme: ClientTheme,
+    pub results_layout: ResultsLayout,
+    pub thumbnails_enabled: bool,
+}
+
+impl Interface {
+    pub fn from_settings(settings: &ClientSettings) -> Self {
+        Self {
+            theme: settings.theme,
+            results_layout: settings.results_layout,
+            thumbnails_enabled: settings.thumbnails_enabled,
+        }
+    }
+
+    /// Overrides saved in this browser, `None` if settings of server are used
+    pub fn local() -> Option<Self> {
+        LocalStorage::get(INTERFACE_STORAGE_KEY).ok()
+    }
+
+    /// Overrides saved in this browser, or settings of server
+    pub fn load(settings: &ClientSettings) -> Self {
+        Self::local().unwrap_or_else(|| Self::from_settings(settings))
+    }
+
+    /// Override settings of server in this browser
+    pub fn save_local(&self) {
+        _ = LocalStorage::set(INTERFACE_STORAGE_KEY, self);
+    }
+
+    /// Use settings of server in this browser again
+    pub fn clear_local() {
+        LocalStorage::delete(INTERFACE_STORAGE_KEY);
+    }
+
+    /// Sets theme as attribute of document, stylesheet follows theme of system for `auto`
+    pub fn apply_theme(&self) {
+        if let Some(element) = web_sys::window()
+            .and_then(|x| x.document())
+            .and_then(|x| x.document_element())
+        {
+            _ = element.set_attribute("data-theme", &self.theme.to_string());
+        }
+    }
+}
@@ -79,3 +79,17 @@ pub fn StatusDialog<'a, G: Html>(
         }
     }
 }
+
+/// Indicator of request of single widget, not blocking other widgets
+#[component(inline_props)]
+pub fn LoadingIndicator<'a, G: Html>(cx: Scope<'a>, loading: &'a ReadSignal<bool>) -> View<G> {
+    view! { cx,
+        (if *loading.get() {
+            view! { cx,
+                span(class="loading_indicator") { (get_translation("loading", None)) }
+            }
+        } else {
+            view! { cx, }
+        })
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+
+use common_lib::{
+    actions::OpenPathArgs,
+    duplicates::{DuplicateGroup, DuplicatesPage, DuplicatesQuery},
+};
+use fluent_bundle::FluentArgs;
+use sycamore::{futures::spawn_local_scoped, prelude::*};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    app::{
+        get_translation,
+        widgets::{LoadingIndicator, StatusDialogState},
+        RequestScope,
+    },
+    formatting::file_size_str,
+    settings::widgets::{NumberSetting, SimpleTextSetting},
+};
+
+/// Maximum of minimum size of files in KiB
+const MIN_SIZE_KIB_MAX: u64 = u64::MAX / 1024;
+
+async fn duplicates(
+    requests: RequestScope<'_>,
+    query: &DuplicatesQuery,
+) -> Option<Result<DuplicatesPage, JsValue>> {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    serializer.append_pair("min_size", &query.min_size.to_string());
+    if let Some(path_prefix) = &query.path_prefix {
+        serializer.append_pair("path_prefix", &path_prefix.to_string_lossy());
+    }
+    if let Some(after) = &query.after {
+        serializer.append_pair("after", after);
+    }
+    let uri = format!("/duplicates?{}", serializer.finish());
+    requests.fetch(&uri, "GET", None::<&()>).await
+}
+
+async fn open_path(requests: RequestScope<'_>, args: &OpenPathArgs) -> Option<Result<(), JsValue>> {
+    requests.fetch_empty("/open_path", "POST", Some(args)).await
+}
+
+#[component(inline_props)]
+pub fn Duplicates<'a, G: Html>(
+    cx: Scope<'a>,
+    status_dialog_state: &'a Signal<StatusDialogState>,
+) -> View<G> {
+    let min_size_kib = create_signal(cx, 1u64);
+    let min_size_valid = create_signal(cx, true);
+    let path_prefix = create_signal(cx, String::new());
+
+    // Query of loaded groups, next pages are requested with its filters
+    let query = create_signal(cx, None::<DuplicatesQuery>);
+    let groups = create_signal(cx, Vec::<DuplicateGroup>::new());
+
+    let duplicates_requests = RequestScope::new(cx);
+    let load_page = move || {
+        spawn_local_scoped(cx, async move {
+            let Some(curr_query) = (*query.get_untracked()).clone() else {
+                return;
+            };
+            match duplicates(duplicates_requests, &curr_query).await {
+                Some(Ok(page)) => {
+                    // Pages are sorted separately, so all loaded groups are sorted again
+                    let mut groups_ = groups.modify();
+                    groups_.extend(page.groups);
+                    groups_.sort_by(|a, b| b.wasted_size.cmp(&a.wasted_size));
+                    query.set(Some(DuplicatesQuery {
+                        after: page.after,
+                        ..curr_query
+                    }));
+                }
+                Some(Err(e)) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str =
+                        get_translation("duplicates_loading_error", Some(&error_args)).to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+                None => {}
+            }
+        });
+    };
+    let find_duplicates = move |_| {
+        duplicates_requests.cancel();
+        groups.set(Vec::new());
+        query.set(Some(DuplicatesQuery {
+            min_size: *min_size_kib.get() * 1024,
+            path_prefix: Some(path_prefix.get().trim())
+                .filter(|x| !x.is_empty())
+                .map(PathBuf::from),
+            after: None,
+        }));
+        load_page();
+    };
+    let has_more = create_memo(cx, || {
+        query.get().as_ref().is_some_and(|x| x.after.is_some())
+    });
+
+    let open_requests = RequestScope::new(cx);
+    let open = move |path, in_archive| {
+        spawn_local_scoped(cx, async move {
+            if let Some(Err(e)) = open_path(open_requests, &OpenPathArgs { path, in_archive }).await
+            {
+                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                let error_str = get_translation("opening_error", Some(&error_args)).to_string();
+                status_dialog_state.set(StatusDialogState::Error(error_str));
+            }
+        })
+    };
+
+    let groups_view = create_memo(cx, move || {
+        View::new_fragment(
+            groups
+                .get()
+                .iter()
+                .map(|group| {
+                    let group_args = FluentArgs::from_iter([
+                        ("count", group.file_cnt.to_string()),
+                        ("size", file_size_str(group.size)),
+                        ("wasted", file_size_str(group.wasted_size)),
+                    ]);
+                    let group_str = get_translation("duplicates_group", Some(&group_args)).to_string();
+                    let not_listed = group.file_cnt.saturating_sub(group.files.len() as u64);
+                    let files = View::new_fragment(
+                        group
+                            .files
+                            .iter()
+                            .map(|file| {
+                                let path = file.path.clone();
+                                let in_archive = file.in_archive;
+                                let path_str = path.display().to_string();
+                                let open_file = {
+                                    let path = path.clone();
+                                    move |_| open(path.clone(), in_archive)
+                                };
+                                let open_folder = move |_| {
+                                    open(path.parent().map(PathBuf::from).unwrap_or_default(), in_archive)
+                                };
+
+                                view! { cx,
+                                    li {
+                                        span(style="overflow-wrap: anywhere;") { (path_str) }
+                                        button(type="button", on:click=open_file) { (get_translation("open", None)) }
+                                        button(type="button", on:click=open_folder) { (get_translation("open_folder", None)) }
+                                    }
+                                }
+                            })
+                            .collect(),
+                    );
+
+                    view! { cx,
+                        fieldset(class="duplicate_group") {
+                            legend { (group_str) }
+                            ul { (files) }
+                            (if not_listed > 0 {
+                                let not_listed_args = FluentArgs::from_iter([("count", not_listed)]);
+                                let not_listed_str = get_translation("duplicates_not_listed", Some(&not_listed_args)).to_string();
+
+                                view! { cx, p { (not_listed_str) } }
+                            } else {
+                                view! { cx, }
+                            })
+                        }
+                    }
+                })
+                .collect(),
+        )
+    });
+
+    view! { cx,
+        div(class="main_container") {
+            main {
+                form(id="duplicates", on:submit=find_duplicates, action="javascript:void(0);") {
+                    fieldset {
+                        legend { (get_translation("duplicates", None)) }
+                        NumberSetting(id="duplicates_min_size".to_owned(),
+                            label=get_translation("duplicates_min_size", None),
+                            min=0, max=MIN_SIZE_KIB_MAX,
+                            value=min_size_kib, valid=min_size_valid)
+                        SimpleTextSetting(id="duplicates_path_prefix",
+                            label=get_translation("duplicates_path_prefix", None),
+                            value=path_prefix)
+                        div(class="settings_buttons") {
+                            LoadingIndicator(loading=duplicates_requests.loading())
+                            button(type="submit", disabled=!*min_size_valid.get()) { (get_translation("find_duplicates", None)) }
+                        }
+                    }
+
+                    (if query.get().is_some() && groups.get().is_empty() && !*duplicates_requests.loading().get() {
+                        view! { cx, p { (get_translation("duplicates_none", None)) } }
+                    } else {
+                        view! { cx, }
+                    })
+                    (*groups_view.get()).clone()
+                    (if *has_more.get() {
+                        view! { cx,
+                            button(type="button", on:click=move |_| load_page(), disabled=*duplicates_requests.loading().get()) {
+                                (get_translation("duplicates_load_more", None))
+                            }
+                        }
+                    } else {
+                        view! { cx, }
+                    })
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,15 @@
+use common_lib::elasticsearch::{AudioChannelType, ContentLanguage};
 use fluent_bundle::{FluentArgs, FluentValue};
 
 use crate::app::get_translation;
 
 pub fn duration_str_from_seconds(total_float_s: f32) -> String {
+    let (message_id, args) = duration_message(total_float_s);
+    get_translation(message_id, Some(&args)).to_string()
+}
+
+/// Message ID and arguments of duration, for translation
+pub fn duration_message(total_float_s: f32) -> (&'static str, FluentArgs<'static>) {
     let total_s = total_float_s.floor() as u64;
     let (h, m, s) = (
         total_s / 3600,
@@ -22,10 +29,16 @@ pub fn duration_str_from_seconds(total_float_s: f32) -> String {
     } else {
         "duration_s"
     };
-    get_translation(format_str, Some(&args)).to_string()
+    (format_str, args)
 }
 
 pub fn file_size_str(size: u64) -> String {
+    let (message_id, args) = file_size_message(size);
+    get_translation(message_id, Some(&args)).to_string()
+}
+
+/// Message ID and arguments of file size, for translation
+pub fn file_size_message(size: u64) -> (&'static str, FluentArgs<'static>) {
     let (format_size, format_str): (FluentValue, _) = if size < 1024 {
         ((size).into(), "file_size_b")
     } else if size < 1024 * 1024 {
@@ -46,5 +59,42 @@ pub fn file_size_str(size: u64) -> String {
     };
 
     let args = FluentArgs::from_iter([("size", format_size)]);
-    get_translation(format_str, Some(&args)).to_string()
+    (format_str, args)
+}
+
+pub fn audio_channel_type_str(audio_channel_type: AudioChannelType) -> String {
+    get_translation(audio_channel_type_message(audio_channel_type), None).to_string()
+}
+
+/// Message ID of audio channel type, for translation
+pub fn audio_channel_type_message(audio_channel_type: AudioChannelType) -> &'static str {
+    match audio_channel_type {
+        AudioChannelType::Mono => "audio_mono",
+        AudioChannelType::Stereo => "audio_stereo",
+        AudioChannelType::_5_1 => "audio_5_1",
+        AudioChannelType::_7_1 => "audio_7_1",
+        AudioChannelType::_16 => "audio_16",
+        AudioChannelType::Other => "audio_other",
+    }
+}
+
+pub fn language_str(language: ContentLanguage) -> String {
+    get_translation(language_message(language), None).to_string()
+}
+
+/// Message ID of language, for translation
+pub fn language_message(language: ContentLanguage) -> &'static str {
+    match language {
+        ContentLanguage::En => "language_en",
+        ContentLanguage::Ru => "language_ru",
+        ContentLanguage::De => "language_de",
+        ContentLanguage::Fr => "language_fr",
+        ContentLanguage::Es => "language_es",
+        ContentLanguage::It => "language_it",
+        ContentLanguage::Pt => "language_pt",
+        ContentLanguage::Nl => "language_nl",
+        ContentLanguage::Zh => "language_zh",
+        ContentLanguage::Ja => "language_ja",
+        ContentLanguage::Ko => "language_ko",
+    }
 }
@@ -1,4 +1,5 @@
 mod app;
+mod duplicates;
 mod formatting;
 mod search;
 mod settings;
@@ -7,6 +8,7 @@ mod status;
 use app::App;
 
 fn main() {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    app::error_report::set_panic_hook();
+    app::error_report::listen_js_errors();
     sycamore::render(App);
 }
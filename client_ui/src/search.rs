@@ -1,50 +1,141 @@
-use std::path::{Path, PathBuf};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
 
 use common_lib::{
-    actions::PickFileResult,
-    search::{ImageQuery, PageType, SearchRequest, SearchResponse, TextQuery},
-    settings::Settings,
+    actions::{OpenPathArgs, PickFileResult},
+    elasticsearch::{ContentLanguage, FileES},
+    search::{
+        ImageQuery, KnnDebugInfo, PageType, SearchRequest, SearchResponse, SearchResult,
+        SimilarRequest, SortOrder, SuggestionMode, TextQuery, CONTENT_FRAGMENTS_MAX,
+        QUERY_IMAGE_MAX_SIZE,
+    },
+    settings::{DirectoryDefaultFilters, ResultsLayout, Settings},
+    BINARY_CONTENT_ERROR, CONTENT_ENCODING_HEADER, CONTENT_NEXT_OFFSET_HEADER,
+    CONTENT_TOTAL_LENGTH_HEADER,
 };
 use fluent_bundle::FluentArgs;
-use gloo_net::http::Request;
+use gloo_storage::{LocalStorage, Storage};
+use js_sys::{Array, Uint8Array};
 use sycamore::{futures::spawn_local_scoped, prelude::*};
 use url::Url;
-use wasm_bindgen::JsValue;
-use web_sys::window;
+use uuid::Uuid;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    window, Blob, ClipboardEvent, Event, File, HtmlElement, HtmlInputElement, HtmlSelectElement,
+    ScrollIntoViewOptions, ScrollLogicalPosition,
+};
 
 use crate::{
-    app::{fetch, fetch_empty, get_translation, widgets::StatusDialogState},
+    app::{
+        add_api_token, fetch_empty, get_translation,
+        interface::Interface,
+        sleep,
+        widgets::{LoadingIndicator, StatusDialogState},
+        RequestScope, Shortcut,
+    },
+    formatting::{file_size_str, language_str},
     search::{
+        albums::AlbumResults,
         filters::{
             content_type::{
                 content_type_filter_items, get_content_type_request_items,
                 load_from_content_type_request_items, ContentTypeFilter,
             },
-            CheckboxFilter, DateTimeFilter, NumberFilter, RadioFilter, RangeWidget,
+            CheckboxFilter, CheckboxOptionFilter, DateTimeFilter, NumberFilter, RadioFilter,
+            RangeWidget, SelectOptionFilter, TagsFilter,
         },
-        results::SearchResults,
+        results::{open_path, ExportResults, SearchResults},
     },
     settings::{MAX_FILE_SIZE_MAX, MAX_FILE_SIZE_MIN},
 };
 
 use self::{
+    compare::Compare,
     filter_groups::{
-        DocumentFilters, DocumentFiltersData, ImageFilters, ImageFiltersData, MultimediaFilters,
-        MultimediaFiltersData,
+        DocumentFilters, DocumentFiltersData, EmailFilters, EmailFiltersData, ImageFilters,
+        ImageFiltersData, MultimediaFilters, MultimediaFiltersData,
     },
-    filters::PathFilter,
+    filter_summary::{filter_chips, ActiveFilter, FilterChips},
+    filters::{PathFilter, PathGlobFilter, SortSelect},
+    history::{
+        add_to_history, clear_history, history_entry_label, load_history, missing_query_images,
+    },
+    prefetch::{idle, same_results, PageCache},
 };
 
+mod albums;
+mod compare;
 mod filter_groups;
+mod filter_summary;
 mod filters;
+mod history;
+mod prefetch;
 mod results;
 
+/// Delay after typing before requesting query completion
+const COMPLETION_DELAY_MS: i32 = 200;
+/// Keys of last search request and scroll position of its results in local storage
+const LAST_SEARCH_STORAGE_KEY: &str = "last_search_request";
+const LAST_SEARCH_SCROLL_STORAGE_KEY: &str = "last_search_scroll";
+
+/// Maximum width of screen with filters drawer and full-screen preview, same as in `base.css`
+const NARROW_SCREEN_MAX_WIDTH: f64 = 800.0;
+
+/// Checks if filters and preview are shown over results
+fn is_narrow_screen() -> bool {
+    window()
+        .unwrap()
+        .inner_width()
+        .ok()
+        .and_then(|x| x.as_f64())
+        .is_some_and(|x| x <= NARROW_SCREEN_MAX_WIDTH)
+}
+
+/// Scroll to result selected with keyboard, if it isn't fully visible
+fn scroll_to_keyboard_selected() {
+    let element = window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .query_selector("article.search_result.keyboard_selected")
+        .ok()
+        .flatten();
+    if let Some(element) = element {
+        let mut options = ScrollIntoViewOptions::new();
+        options.block(ScrollLogicalPosition::Nearest);
+        element.scroll_into_view_with_scroll_into_view_options(&options);
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum QueryType {
     Text,
     Image,
 }
 
+/// Default filters of included indexing directory containing path, the deepest one if nested.
+/// Paths are compared as strings because client can't parse paths of server OS
+fn directory_default_filters(settings: &Settings, path: &Path) -> DirectoryDefaultFilters {
+    let path = path.to_string_lossy();
+    settings
+        .indexing_directories
+        .iter()
+        .filter(|x| !x.exclude)
+        .filter(|x| {
+            let dir = x.path.to_string_lossy();
+            path.strip_prefix(dir.as_ref()).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with(['/', '\\']) || dir.ends_with(['/', '\\'])
+            })
+        })
+        .max_by_key(|x| x.path.as_os_str().len())
+        .and_then(|x| x.default_filters.clone())
+        .unwrap_or_default()
+}
+
 fn get_local_file_url<P: AsRef<Path>>(path: P, content_type: Option<&str>, thumbnail: bool) -> Url {
     let base = Url::parse(&web_sys::window().unwrap().location().origin().unwrap()).unwrap();
     let mut file_url = base.join("/file").unwrap();
@@ -55,99 +146,311 @@ fn get_local_file_url<P: AsRef<Path>>(path: P, content_type: Option<&str>, thumb
     if let Some(x) = content_type {
         file_url.query_pairs_mut().append_pair("content_type", x);
     }
+    add_api_token(&mut file_url);
     file_url
 }
 
-async fn pick_file() -> Result<PickFileResult, JsValue> {
-    fetch("/pick_file", "POST", None::<&()>).await
+/// Read uploaded or pasted image of query, checking its type and size
+async fn read_query_image(file: File) -> Result<Vec<u8>, String> {
+    if !file.type_().starts_with("image/") {
+        return Err(get_translation("query_image_unsupported", None).to_string());
+    }
+    if file.size() > QUERY_IMAGE_MAX_SIZE as f64 {
+        let error_args = FluentArgs::from_iter([("size", QUERY_IMAGE_MAX_SIZE / 1024 / 1024)]);
+        return Err(get_translation("query_image_too_large", Some(&error_args)).to_string());
+    }
+    let buffer = JsFuture::from(file.array_buffer())
+        .await
+        .map_err(|e| format!("{e:#?}"))?;
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+/// Object URL of image of query for preview
+fn get_query_image_url(data: &[u8]) -> String {
+    let parts = Array::of1(&Uint8Array::from(data));
+    let blob = Blob::new_with_u8_array_sequence(&parts).unwrap();
+    web_sys::Url::create_object_url_with_blob(&blob).unwrap()
+}
+
+/// Lines describing sizes of kNN search in debug mode
+fn knn_debug_strs(knn: &KnnDebugInfo) -> Vec<String> {
+    let filtered_count = knn.filtered_count.map(|x| {
+        let args = FluentArgs::from_iter([("count", x)]);
+        get_translation("debug_knn_filtered_count", Some(&args)).to_string()
+    });
+    let clauses = knn.clauses.iter().map(|(field, k, num_candidates)| {
+        let args = FluentArgs::from_iter([
+            ("field", field.clone()),
+            ("k", k.to_string()),
+            ("num_candidates", num_candidates.to_string()),
+        ]);
+        get_translation("debug_knn", Some(&args)).to_string()
+    });
+    filtered_count.into_iter().chain(clauses).collect()
+}
+
+/// Get URL of thumbnail for image, video and audio files, unless it's known to be unavailable
+fn get_thumbnail_url(file: &FileES) -> Option<Url> {
+    let has_thumbnail_type = ["image", "video", "audio"]
+        .iter()
+        .any(|x| file.content_type.starts_with(x));
+    if !has_thumbnail_type || file.has_thumbnail == Some(false) {
+        return None;
+    }
+
+    let mut url = get_local_file_url(&file.path, Some(&file.content_type), true);
+    if let Some(id) = &file._id {
+        url.query_pairs_mut().append_pair("id", id);
+    }
+    if let Some(hash) = &file.hash {
+        url.query_pairs_mut().append_pair("hash", hash);
+    }
+    if let Some(x) = file.has_thumbnail {
+        url.query_pairs_mut()
+            .append_pair("has_thumbnail", &x.to_string());
+    }
+    Some(url)
+}
+
+async fn pick_file(requests: RequestScope<'_>) -> Option<Result<PickFileResult, JsValue>> {
+    requests.fetch("/pick_file", "POST", None::<&()>).await
 }
 
-async fn open_request() -> Result<Option<SearchRequest>, JsValue> {
-    fetch("/open_request", "POST", None::<&()>).await
+async fn open_request(
+    requests: RequestScope<'_>,
+) -> Option<Result<Option<SearchRequest>, JsValue>> {
+    requests.fetch("/open_request", "POST", None::<&()>).await
 }
 
 async fn save_request(search_request: &SearchRequest) -> Result<(), JsValue> {
     fetch_empty("/save_request", "POST", Some(search_request)).await
 }
 
-async fn search(search_request: &SearchRequest) -> Result<SearchResponse, JsValue> {
-    fetch("/search", "POST", Some(search_request)).await
+async fn search(
+    requests: RequestScope<'_>,
+    search_request: &SearchRequest,
+) -> Option<Result<SearchResponse, JsValue>> {
+    requests
+        .fetch("/search", "POST", Some(search_request))
+        .await
+}
+
+async fn find_similar(
+    requests: RequestScope<'_>,
+    similar_request: &SimilarRequest,
+) -> Option<Result<SearchResponse, JsValue>> {
+    requests
+        .fetch("/search/similar", "POST", Some(similar_request))
+        .await
+}
+
+/// Part of document content loaded for preview
+struct ContentPart {
+    text: String,
+    encoding: String,
+    total_length: u64,
+    next_offset: u64,
+}
+
+async fn get_document_content(
+    requests: RequestScope<'_>,
+    id: &str,
+    offset: u64,
+) -> Option<Result<ContentPart, JsValue>> {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("id", id)
+        .append_pair("offset", &offset.to_string())
+        .finish();
+    let res = requests
+        .fetch_text_with_headers(&format!("/document_content?{query}"))
+        .await?;
+    Some(res.map(|(text, headers)| {
+        let header = |name| headers.get(name).ok().flatten().unwrap_or_default();
+        ContentPart {
+            text,
+            encoding: header(CONTENT_ENCODING_HEADER),
+            total_length: header(CONTENT_TOTAL_LENGTH_HEADER)
+                .parse()
+                .unwrap_or_default(),
+            next_offset: header(CONTENT_NEXT_OFFSET_HEADER)
+                .parse()
+                .unwrap_or_default(),
+        }
+    }))
+}
+
+async fn complete(
+    requests: RequestScope<'_>,
+    prefix: &str,
+) -> Option<Result<Vec<String>, JsValue>> {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("q", prefix)
+        .finish();
+    requests
+        .fetch(&format!("/search/complete?{query}"), "GET", None::<&()>)
+        .await
 }
 
 #[component(inline_props)]
 pub fn Search<'a, G: Html>(
     cx: Scope<'a>,
     settings: &'a Signal<Settings>,
+    /// Layout of results and whether thumbnails are shown
+    interface: &'a ReadSignal<Interface>,
     status_dialog_state: &'a Signal<StatusDialogState>,
+    /// Keyboard shortcuts pressed while search tab is shown
+    shortcut: RcSignal<Option<Shortcut>>,
 ) -> View<G> {
     let query = create_signal(cx, String::new());
     let query_image_path = create_signal(cx, PathBuf::new());
+    let query_image_data = create_signal(cx, None::<Vec<u8>>);
+    let query_image_url = create_memo(cx, || {
+        query_image_data.get().as_deref().map(get_query_image_url)
+    });
 
     let query_type = create_signal(cx, QueryType::Text);
     let content_enabled = create_signal(cx, true);
     let text_search_enabled = create_signal(cx, settings.get().nn_server.text_search_enabled);
     let image_search_enabled = create_signal(cx, settings.get().nn_server.image_search_enabled);
     let reranking_enabled = create_signal(cx, settings.get().nn_server.reranking_enabled);
+    let auto_correct = create_signal(cx, settings.get().client.auto_correct);
+    let suggestions_when_empty = create_signal(cx, false);
     let text_search_pages = create_signal(cx, 1);
+    let content_fragments = create_signal(cx, 1);
     let image_search_pages = create_signal(cx, 1);
     let query_coeff = create_signal(cx, 1.0);
     let text_search_coeff = create_signal(cx, 7.5);
     let image_search_coeff = create_signal(cx, 7.5);
     let reranking_coeff = create_signal(cx, 1.1);
+    // Zero disables boost of recently modified files
+    let recency_boost = create_signal(cx, 0.0f32);
+    // Show generated Elasticsearch request and timings below results
+    let debug_mode = create_signal(cx, false);
 
-    let display_filters = create_signal(cx, true);
+    let sort = create_signal(cx, SortOrder::default().to_string());
+    let display_filters = create_signal(cx, !is_narrow_screen());
     let path_prefix = create_signal(cx, None);
+    let path_glob = create_signal(cx, None);
+    let path_glob_valid = create_signal(cx, true);
     let content_type_disabled = create_signal(cx, true);
     let content_type_items = content_type_filter_items(cx);
     let path_enabled = create_signal(cx, true);
     let hash_enabled = create_signal(cx, true);
     let modified_from = create_signal(cx, None);
     let modified_to = create_signal(cx, None);
+    let modified_in_future = create_signal(cx, None);
     let modified_valid = create_signal(cx, true);
     let size_from = create_signal(cx, None);
     let size_to = create_signal(cx, None);
     let size_valid = create_signal(cx, true);
+    let tags = create_signal(cx, Vec::new());
+    let is_executable = create_signal(cx, None);
+    let is_readonly = create_signal(cx, None);
+    let is_hidden = create_signal(cx, None);
+    let is_online_only = create_signal(cx, None);
+    let parse_failed = create_signal(cx, None);
+    let language = create_signal(cx, None);
+    let language_options = create_signal(
+        cx,
+        ContentLanguage::ALL
+            .into_iter()
+            .map(|x| (x, language_str(x)))
+            .collect::<Vec<_>>(),
+    );
 
     let image_filters_data = create_signal(cx, ImageFiltersData::new(cx));
     let multimedia_filters_data = create_signal(cx, MultimediaFiltersData::new(cx));
     let document_filters_data = create_signal(cx, DocumentFiltersData::new(cx));
+    let email_filters_data = create_signal(cx, EmailFiltersData::new(cx));
 
     let any_invalid = create_memo(cx, || {
         !*modified_valid.get()
             || !*size_valid.get()
+            || !*path_glob_valid.get()
             || *image_filters_data.get().any_invalid.get()
             || *multimedia_filters_data.get().any_invalid.get()
             || *document_filters_data.get().any_invalid.get()
+            || *email_filters_data.get().any_invalid.get()
     });
 
     let preview_data = create_signal(cx, PreviewData::default());
+    let selected = create_signal(cx, Vec::new());
 
     let no_searches = create_signal(cx, true);
     let search_results = create_signal(cx, Vec::new());
+    let album_results = create_signal(cx, Vec::new());
+    let last_search_request = create_signal(cx, None);
     let pages = create_signal(cx, Vec::new());
     let suggestion = create_signal(cx, None);
+    let correction = create_signal(cx, None);
+    // Numbers of results in each language of text, for narrowing search by language
+    let language_counts = create_signal(cx, Vec::new());
+    // Formatted Elasticsearch request and timings of search, in debug mode
+    let debug_info = create_signal(cx, None);
 
     // Update search configuration on settings change
     create_effect(cx, || {
         text_search_enabled.set(settings.get().nn_server.text_search_enabled);
         image_search_enabled.set(settings.get().nn_server.image_search_enabled);
         reranking_enabled.set(settings.get().nn_server.reranking_enabled);
+        auto_correct.set(settings.get().client.auto_correct);
+    });
+
+    // Apply default filters of selected directory, but keep filters changed by user
+    let applied_default_filters = create_ref(cx, RefCell::new(DirectoryDefaultFilters::default()));
+    create_effect(cx, move || {
+        let default_filters = path_prefix
+            .get()
+            .as_ref()
+            .as_ref()
+            .map(|x| directory_default_filters(&settings.get(), x))
+            .unwrap_or_default();
+        let applied = applied_default_filters.replace(default_filters.clone());
+        let size_mib = |x: Option<u64>| x.map(|x| x as f64 / 1024.0 / 1024.0);
+
+        untrack(|| {
+            let content_type = (!*content_type_disabled.get())
+                .then(|| get_content_type_request_items(content_type_items));
+            if content_type == applied.content_type {
+                match default_filters.content_type {
+                    Some(x) => {
+                        content_type_disabled.set(false);
+                        load_from_content_type_request_items(&x, content_type_items);
+                    }
+                    None => content_type_disabled.set(true),
+                }
+            }
+            if *modified_from.get() == applied.modified_from {
+                modified_from.set(default_filters.modified_from);
+            }
+            if *modified_to.get() == applied.modified_to {
+                modified_to.set(default_filters.modified_to);
+            }
+            if *size_from.get() == size_mib(applied.size_from) {
+                size_from.set(size_mib(default_filters.size_from));
+            }
+            if *size_to.get() == size_mib(applied.size_to) {
+                size_to.set(size_mib(default_filters.size_to));
+            }
+        });
     });
 
     let toggle_filters = move |_| {
         display_filters.set(!*display_filters.get());
     };
 
+    let pick_file_requests = RequestScope::new(cx);
     let select_file = move |_| {
-        spawn_local_scoped(cx, async {
-            status_dialog_state.set(StatusDialogState::Loading);
-
-            match pick_file().await {
+        spawn_local_scoped(cx, async move {
+            let Some(res) = pick_file(pick_file_requests).await else {
+                return;
+            };
+            match res {
                 Ok(res) => {
                     if let Some(path) = res.path {
                         query_image_path.set(path);
+                        query_image_data.set(None);
                     }
-                    status_dialog_state.set(StatusDialogState::None);
                 }
                 Err(e) => {
                     let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
@@ -159,6 +462,44 @@ pub fn Search<'a, G: Html>(
         });
     };
 
+    // Uploaded or pasted image replaces selected file
+    let set_query_image = move |file: File| {
+        spawn_local_scoped(cx, async move {
+            match read_query_image(file).await {
+                Ok(data) => {
+                    query_image_path.set(PathBuf::new());
+                    query_image_data.set(Some(data));
+                }
+                Err(e) => status_dialog_state.set(StatusDialogState::Error(e)),
+            }
+        });
+    };
+    let upload_image = move |e: Event| {
+        let Some(file) = e
+            .target()
+            .and_then(|x| x.dyn_into::<HtmlInputElement>().ok())
+            .and_then(|x| x.files())
+            .and_then(|x| x.get(0))
+        else {
+            return;
+        };
+        set_query_image(file);
+    };
+    let paste_image = move |e: Event| {
+        let Ok(e) = e.dyn_into::<ClipboardEvent>() else {
+            return;
+        };
+        let Some(file) = e
+            .clipboard_data()
+            .and_then(|x| x.files())
+            .and_then(|x| x.get(0))
+        else {
+            return;
+        };
+        e.prevent_default();
+        set_query_image(file);
+    };
+
     let get_search_request = |page: u32| {
         let search_query = match *query_type.get() {
             QueryType::Text => common_lib::search::QueryType::Text(TextQuery {
@@ -173,33 +514,59 @@ pub fn Search<'a, G: Html>(
                 text_search_coeff: *text_search_coeff.get(),
                 image_search_coeff: *image_search_coeff.get(),
                 reranking_coeff: *reranking_coeff.get(),
+                auto_correct: *auto_correct.get(),
+                content_fragments: *content_fragments.get(),
             }),
             QueryType::Image => common_lib::search::QueryType::Image(ImageQuery {
                 image_path: (*query_image_path.get()).clone(),
+                image_data: (*query_image_data.get()).clone(),
                 image_search_pages: *image_search_pages.get(),
             }),
         };
         SearchRequest {
             page,
+            results_per_page: None,
             query: search_query,
             path_prefix: path_prefix.get().as_ref().clone(),
+            path_glob: path_glob.get().as_ref().clone(),
             content_type: (!*content_type_disabled.get())
                 .then(|| get_content_type_request_items(content_type_items)),
             path_enabled: *path_enabled.get(),
             hash_enabled: *hash_enabled.get(),
             modified_from: *modified_from.get(),
             modified_to: *modified_to.get(),
+            modified_in_future: *modified_in_future.get(),
             size_from: size_from.get().map(|x| (x * 1024.0 * 1024.0) as u64),
             size_to: size_to.get().map(|x| (x * 1024.0 * 1024.0) as u64),
+            tags: (*tags.get()).clone(),
+            is_executable: *is_executable.get(),
+            is_readonly: *is_readonly.get(),
+            is_hidden: *is_hidden.get(),
+            is_online_only: *is_online_only.get(),
+            parse_failed: *parse_failed.get(),
+            language: *language.get(),
+            provenance: Default::default(),
+            sort: sort.get().parse().unwrap_or_default(),
+            recency_boost: (*recency_boost.get() > 0.0).then_some(*recency_boost.get()),
+            path_boost: None,
+            // Suggestion is requested separately only for searches without results
+            suggestions: if *suggestions_when_empty.get() {
+                SuggestionMode::WhenFewResults(1)
+            } else {
+                SuggestionMode::Always
+            },
+            debug: *debug_mode.get(),
             image_data: image_filters_data.get().to_request(),
             multimedia_data: multimedia_filters_data.get().to_request(),
             document_data: document_filters_data.get().to_request(),
+            email_data: email_filters_data.get().to_request(),
         }
     };
 
     let load_from_search_request = |search_request: SearchRequest| {
         match search_request.query {
             common_lib::search::QueryType::Text(text_query) => {
+                query_type.set(QueryType::Text);
                 query.set(text_query.query);
                 content_enabled.set(text_query.content_enabled);
                 text_search_enabled.set(text_query.text_search_enabled);
@@ -211,13 +578,18 @@ pub fn Search<'a, G: Html>(
                 text_search_coeff.set(text_query.text_search_coeff);
                 image_search_coeff.set(text_query.image_search_coeff);
                 reranking_coeff.set(text_query.reranking_coeff);
+                auto_correct.set(text_query.auto_correct);
+                content_fragments.set(text_query.content_fragments);
             }
             common_lib::search::QueryType::Image(image_query) => {
+                query_type.set(QueryType::Image);
                 query_image_path.set(image_query.image_path);
+                query_image_data.set(image_query.image_data);
                 image_search_pages.set(image_query.image_search_pages);
             }
         };
         path_prefix.set(search_request.path_prefix);
+        path_glob.set(search_request.path_glob);
         match search_request.content_type {
             Some(x) => {
                 content_type_disabled.set(false);
@@ -229,12 +601,27 @@ pub fn Search<'a, G: Html>(
         hash_enabled.set(search_request.hash_enabled);
         modified_from.set(search_request.modified_from);
         modified_to.set(search_request.modified_to);
+        modified_in_future.set(search_request.modified_in_future);
         size_from.set(
             search_request
                 .size_from
                 .map(|x| (x as f64) / 1024.0 / 1024.0),
         );
         size_to.set(search_request.size_to.map(|x| (x as f64) / 1024.0 / 1024.0));
+        tags.set(search_request.tags);
+        is_executable.set(search_request.is_executable);
+        is_readonly.set(search_request.is_readonly);
+        is_hidden.set(search_request.is_hidden);
+        is_online_only.set(search_request.is_online_only);
+        parse_failed.set(search_request.parse_failed);
+        language.set(search_request.language);
+        sort.set(search_request.sort.to_string());
+        recency_boost.set(search_request.recency_boost.unwrap_or_default());
+        debug_mode.set(search_request.debug);
+        suggestions_when_empty.set(matches!(
+            search_request.suggestions,
+            SuggestionMode::WhenFewResults(_)
+        ));
         image_filters_data
             .modify()
             .update_from_request(search_request.image_data);
@@ -244,18 +631,25 @@ pub fn Search<'a, G: Html>(
         document_filters_data
             .modify()
             .update_from_request(search_request.document_data);
+        email_filters_data
+            .modify()
+            .update_from_request(search_request.email_data);
     };
+    // Initial values of query and filters, for resetting them
+    let default_search_request = create_ref(cx, get_search_request(0));
+    let default_content_types = create_ref(cx, get_content_type_request_items(content_type_items));
 
+    let open_request_requests = RequestScope::new(cx);
     let open_search_request = move |_| {
         spawn_local_scoped(cx, async move {
-            status_dialog_state.set(StatusDialogState::Loading);
-
-            match open_request().await {
+            let Some(res) = open_request(open_request_requests).await else {
+                return;
+            };
+            match res {
                 Ok(res) => {
                     if let Some(search_request) = res {
                         load_from_search_request(search_request);
                     }
-                    status_dialog_state.set(StatusDialogState::None);
                 }
                 Err(e) => {
                     let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
@@ -285,31 +679,399 @@ pub fn Search<'a, G: Html>(
         });
     };
 
+    // Images of queries in history are checked when history changes
+    let history = create_signal(cx, load_history());
+    let missing_images = create_signal(cx, BTreeSet::new());
+    create_effect(cx, move || {
+        let entries = (*history.get()).clone();
+        spawn_local_scoped(cx, async move {
+            missing_images.set(missing_query_images(&entries).await);
+        });
+    });
+    let history_labels = create_memo(cx, || {
+        history
+            .get()
+            .iter()
+            .map(|x| history_entry_label(x, &missing_images.get()))
+            .enumerate()
+            .collect::<Vec<_>>()
+    });
+    let clear_search_history = move |_| {
+        clear_history();
+        history.set(Vec::new());
+    };
+
+    // Prefetched pages are valid only for current query and filters
+    let page_cache = create_ref(cx, RefCell::new(PageCache::default()));
+    let prefetch_requests = RequestScope::new(cx);
+    create_effect(cx, move || {
+        // Request is built only to track all query and filter signals
+        get_search_request(0);
+        prefetch_requests.cancel();
+        page_cache.borrow_mut().clear();
+    });
+    // Reranking makes searches too expensive to run them speculatively
+    let prefetch_enabled = move || {
+        let client_settings = &settings.get().client;
+        client_settings.prefetch_next_page
+            && (!*reranking_enabled.get() || client_settings.prefetch_with_reranking)
+    };
+    let prefetch = move |search_request: SearchRequest| {
+        spawn_local_scoped(cx, async move {
+            idle().await;
+            if let Some(Ok(x)) = search(prefetch_requests, &search_request).await {
+                page_cache.borrow_mut().insert(&search_request, x);
+            }
+        })
+    };
+
+    // Scroll position of restored search, applied once its results are shown
+    let restored_scroll = create_ref(cx, Cell::<Option<f64>>::new(None));
+    let set_debug_info = move |x: &SearchResponse| {
+        debug_info.set(x.es_request.as_ref().map(|request| {
+            (
+                serde_json::to_string_pretty(request).unwrap_or_default(),
+                x.took_ms.unwrap_or_default(),
+                x.es_took_ms.unwrap_or_default(),
+                x.knn.as_ref().map(knn_debug_strs).unwrap_or_default(),
+            )
+        }));
+    };
+    let show_response = move |search_request: SearchRequest, x: SearchResponse| {
+        _ = LocalStorage::set(LAST_SEARCH_STORAGE_KEY, &search_request);
+        set_debug_info(&x);
+        search_results.set(x.results);
+        album_results.set(x.albums);
+        last_search_request.set(Some(search_request));
+        pages.set(x.pages);
+        suggestion.set(x.suggestion);
+        correction.set(x.original_query.zip(x.corrected_query));
+        language_counts.set(x.languages);
+        let scroll_y = restored_scroll.take().unwrap_or_default();
+        window().unwrap().scroll_to_with_x_and_y(0.0, scroll_y);
+    };
+
+    // New search cancels previous one
+    let search_requests = RequestScope::new(cx);
+    // Similar files are shown instead of search results until next search
+    let last_similar_request = create_signal(cx, None);
     let search = move |page: u32| {
         spawn_local_scoped(cx, async move {
             no_searches.set(false);
-            status_dialog_state.set(StatusDialogState::Loading);
+            prefetch_requests.cancel();
+            last_similar_request.set(None);
 
             let search_request = get_search_request(page);
 
-            match search(&search_request).await {
+            // Show prefetched page instantly, then revalidate it
+            let cached = page_cache.borrow().get(&search_request);
+            if let Some(x) = cached.clone() {
+                show_response(search_request.clone(), x);
+            }
+
+            let Some(res) = search(search_requests, &search_request).await else {
+                return;
+            };
+            match res {
+                Ok(x) => {
+                    let next_page = x.pages.iter().find_map(|x| match x {
+                        PageType::Next(p) => Some(*p),
+                        _ => None,
+                    });
+                    add_to_history(
+                        &mut history.modify(),
+                        search_request.clone(),
+                        x.results.len() + x.albums.len(),
+                    );
+                    match cached {
+                        Some(cached) if same_results(&cached, &x) => {
+                            set_debug_info(&x);
+                            pages.set(x.pages);
+                            suggestion.set(x.suggestion);
+                            correction.set(x.original_query.zip(x.corrected_query));
+                            language_counts.set(x.languages);
+                        }
+                        _ => show_response(search_request.clone(), x),
+                    }
+
+                    if let Some(page) = next_page.filter(|_| prefetch_enabled()) {
+                        prefetch(SearchRequest {
+                            page,
+                            ..search_request
+                        });
+                    }
+                }
+                Err(e) => {
+                    search_results.set(Vec::new());
+                    album_results.set(Vec::new());
+                    language_counts.set(Vec::new());
+                    debug_info.set(None);
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str = get_translation("search_error", Some(&error_args)).to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+            }
+        })
+    };
+    // Result of file moved to trash is removed without searching again
+    let remove_result = move |id: Uuid| {
+        let mut results = search_results.modify();
+        if let Some(i) = results.iter().position(|x| x.id == id) {
+            let removed = results.remove(i);
+            selected
+                .modify()
+                .retain(|x| Some(x) != removed.file._id.as_ref());
+        }
+    };
+    // Selected results indexed without contents after failures of parsing
+    let failed_paths = create_memo(cx, || {
+        let selected = selected.get();
+        search_results
+            .get()
+            .iter()
+            .filter(|x: &&SearchResult| {
+                x.file.parse_failed == Some(true)
+                    && x.file._id.as_ref().is_some_and(|id| selected.contains(id))
+            })
+            .map(|x| x.file.path.clone())
+            .collect::<Vec<_>>()
+    });
+    let find_similar = move |similar_request: SimilarRequest| {
+        spawn_local_scoped(cx, async move {
+            prefetch_requests.cancel();
+            let Some(res) = find_similar(search_requests, &similar_request).await else {
+                return;
+            };
+            match res {
                 Ok(x) => {
                     search_results.set(x.results);
+                    album_results.set(Vec::new());
+                    // Export and filter chips apply only to search results
+                    last_search_request.set(None);
+                    last_similar_request.set(Some(similar_request));
                     pages.set(x.pages);
-                    suggestion.set(x.suggestion);
-                    status_dialog_state.set(StatusDialogState::None);
+                    suggestion.set(None);
+                    correction.set(None);
+                    language_counts.set(Vec::new());
+                    debug_info.set(None);
                     window().unwrap().scroll_to_with_x_and_y(0.0, 0.0);
                 }
                 Err(e) => {
-                    search_results.set(Vec::new());
                     let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
-                    let error_str = get_translation("search_error", Some(&error_args)).to_string();
+                    let error_str =
+                        get_translation("similar_search_error", Some(&error_args)).to_string();
                     status_dialog_state.set(StatusDialogState::Error(error_str));
                 }
             }
         })
     };
-    let search_without_page = move |_| search(0);
+    let show_page = move |page: u32| match (*last_similar_request.get()).clone() {
+        Some(similar_request) => find_similar(SimilarRequest {
+            page,
+            ..similar_request
+        }),
+        None => search(page),
+    };
+    let search_without_page = move |_| {
+        // Show results instead of filters drawer
+        if is_narrow_screen() {
+            display_filters.set(false);
+        }
+        selected.modify().clear();
+        search(0)
+    };
+
+    // Index of result selected with keyboard, selection is reset by new results
+    let keyboard_selected = create_signal(cx, None::<usize>);
+    create_effect(cx, move || {
+        search_results.track();
+        keyboard_selected.set(None);
+    });
+    let keyboard_selected_id = create_memo(cx, move || {
+        keyboard_selected
+            .get()
+            .and_then(|i| search_results.get().get(i).map(|x| x.id))
+    });
+    let open_requests = RequestScope::new(cx);
+    let on_shortcut = move |x: Shortcut| {
+        let results_cnt = search_results.get().len();
+        let selected_result = keyboard_selected
+            .get()
+            .and_then(|i| search_results.get().get(i).cloned());
+        // Pages of similar files are switched as well
+        let switch_page = |is_target: fn(&PageType) -> Option<u32>| {
+            if let Some(page) = pages.get().iter().find_map(is_target) {
+                show_page(page);
+            }
+        };
+        match x {
+            Shortcut::FocusQuery => {
+                let query_input = window()
+                    .unwrap()
+                    .document()
+                    .unwrap()
+                    .get_element_by_id("query")
+                    .and_then(|x| x.dyn_into::<HtmlElement>().ok());
+                if let Some(query_input) = query_input {
+                    _ = query_input.focus();
+                }
+            }
+            Shortcut::NextResult if results_cnt > 0 => {
+                let i = keyboard_selected
+                    .get()
+                    .map_or(0, |i| (i + 1).min(results_cnt - 1));
+                keyboard_selected.set(Some(i));
+                scroll_to_keyboard_selected();
+            }
+            Shortcut::PreviousResult if results_cnt > 0 => {
+                let i = keyboard_selected.get().map_or(0, |i| i.saturating_sub(1));
+                keyboard_selected.set(Some(i));
+                scroll_to_keyboard_selected();
+            }
+            Shortcut::OpenResult => {
+                let Some(result) = selected_result else {
+                    return;
+                };
+                spawn_local_scoped(cx, async move {
+                    let args = OpenPathArgs {
+                        path: result.file.path,
+                        in_archive: result.file.in_archive == Some(true),
+                    };
+                    if let Some(Err(e)) = open_path(open_requests, &args).await {
+                        let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                        let error_str =
+                            get_translation("opening_error", Some(&error_args)).to_string();
+                        status_dialog_state.set(StatusDialogState::Error(error_str));
+                    }
+                });
+            }
+            Shortcut::PreviewResult => {
+                if let Some(result) = selected_result.filter(|x| x.file.size > 0) {
+                    preview_data.set(PreviewData::from_result(&result));
+                }
+            }
+            // Close preview first, then filters drawer
+            Shortcut::Close => {
+                if preview_data.get().display {
+                    preview_data.modify().display = false;
+                } else if *display_filters.get() && is_narrow_screen() {
+                    display_filters.set(false);
+                }
+            }
+            Shortcut::PreviousPage => switch_page(|x| match x {
+                PageType::Previous(p) => Some(*p),
+                _ => None,
+            }),
+            Shortcut::NextPage => switch_page(|x| match x {
+                PageType::Next(p) => Some(*p),
+                _ => None,
+            }),
+            Shortcut::NextResult | Shortcut::PreviousResult => {}
+        }
+    };
+    create_effect(cx, move || {
+        if let Some(x) = *shortcut.get() {
+            untrack(|| on_shortcut(x));
+        }
+    });
+
+    let active_filter_chips = create_memo(cx, || {
+        last_search_request
+            .get()
+            .as_ref()
+            .as_ref()
+            .map(|x| filter_chips(x, &|id, args| get_translation(id, args).to_string()))
+            .unwrap_or_default()
+    });
+    let reset_filter = move |filter: ActiveFilter| {
+        let Some(mut search_request) = (*last_search_request.get()).clone() else {
+            return;
+        };
+        filter.reset(&mut search_request);
+        load_from_search_request(search_request);
+        search(0);
+    };
+
+    let open_history_entry = move |e: Event| {
+        let Some(select) = e
+            .target()
+            .and_then(|x| x.dyn_into::<HtmlSelectElement>().ok())
+        else {
+            return;
+        };
+        let entry = select
+            .value()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| history.get().get(i).cloned());
+        // List shows its title again, so the same entry can be selected later
+        select.set_value("");
+        if let Some(entry) = entry {
+            load_from_search_request(entry.search_request);
+            search(0);
+        }
+    };
+
+    // Last search is repeated after reload, on the same page and scroll position
+    if let Ok(search_request) = LocalStorage::get::<SearchRequest>(LAST_SEARCH_STORAGE_KEY) {
+        restored_scroll.set(LocalStorage::get(LAST_SEARCH_SCROLL_STORAGE_KEY).ok());
+        let page = search_request.page;
+        load_from_search_request(search_request);
+        search(page);
+    }
+    let save_scroll = Closure::<dyn Fn()>::new(|| {
+        if let Ok(scroll_y) = window().unwrap().scroll_y() {
+            _ = LocalStorage::set(LAST_SEARCH_SCROLL_STORAGE_KEY, scroll_y);
+        }
+    });
+    _ = window()
+        .unwrap()
+        .add_event_listener_with_callback("pagehide", save_scroll.as_ref().unchecked_ref());
+    save_scroll.forget();
+
+    let reset_search = move |_| {
+        LocalStorage::delete(LAST_SEARCH_STORAGE_KEY);
+        LocalStorage::delete(LAST_SEARCH_SCROLL_STORAGE_KEY);
+        search_requests.cancel();
+        prefetch_requests.cancel();
+        load_from_search_request(default_search_request.clone());
+        load_from_content_type_request_items(default_content_types, content_type_items);
+        query_image_path.set(PathBuf::new());
+        query_image_data.set(None);
+        last_similar_request.set(None);
+        last_search_request.set(None);
+        search_results.set(Vec::new());
+        album_results.set(Vec::new());
+        pages.set(Vec::new());
+        suggestion.set(None);
+        correction.set(None);
+        language_counts.set(Vec::new());
+        debug_info.set(None);
+        selected.modify().clear();
+        no_searches.set(true);
+    };
+
+    // Completion is requested when typing pauses, newer request cancels previous one
+    let completions = create_signal(cx, Vec::new());
+    let completion_requests = RequestScope::new(cx);
+    create_effect(cx, move || {
+        let prefix = (*query.get()).clone();
+        spawn_local_scoped(cx, async move {
+            sleep(COMPLETION_DELAY_MS).await;
+            if *query.get_untracked() != prefix {
+                return;
+            }
+            if prefix.trim().is_empty() {
+                completion_requests.cancel();
+                completions.set(Vec::new());
+                return;
+            }
+            // Completion is optional, errors aren't shown
+            if let Some(Ok(x)) = complete(completion_requests, &prefix).await {
+                completions.set(x);
+            }
+        });
+    });
 
     view! { cx,
         header {
@@ -318,20 +1080,41 @@ pub fn Search<'a, G: Html>(
                     view! { cx,
                         div {
                             button(form="search", type="button", on:click=toggle_filters) { "☰" }
-                            input(form="search", type="search", id="query", name="query",
+                            input(form="search", type="search", id="query", name="query", list="query_completions",
                                 placeholder=get_translation("search_placeholder", None), bind:value=query)
+                            datalist(id="query_completions") {
+                                Keyed(
+                                    iterable=completions,
+                                    view=|cx, x| view! { cx, option(value=x) },
+                                    key=|x| x.clone(),
+                                )
+                            }
+                            SortSelect(value=sort)
                             button(form="search", type="submit", disabled=*any_invalid.get()) { (get_translation("search", None)) }
+                            LoadingIndicator(loading=search_requests.loading())
                         }
                     }
                 }
                 QueryType::Image => {
                     view! { cx,
-                        div {
+                        // Focusable to receive pasted images
+                        div(tabindex="0", title=get_translation("query_image_paste_hint", None), on:paste=paste_image) {
                             button(form="search", type="button", on:click=toggle_filters) { "☰" }
                             button(form="search", type="button", on:click=select_file) { (get_translation("select_file", None)) }
+                            LoadingIndicator(loading=pick_file_requests.loading())
+                            label(for="query_image_upload") { (get_translation("query_image_upload", None)) }
+                            input(type="file", id="query_image_upload", accept="image/*", on:change=upload_image) {}
+                            SortSelect(value=sort)
                             button(form="search", type="submit", disabled=*any_invalid.get()) { (get_translation("search", None)) }
+                            LoadingIndicator(loading=search_requests.loading())
                         }
-                        (if !query_image_path.get().as_os_str().is_empty() {
+                        (if let Some(img_url) = (*query_image_url.get()).clone() {
+                            view! { cx,
+                                div {
+                                    img(src=img_url, id="query_image") {}
+                                }
+                            }
+                        } else if !query_image_path.get().as_os_str().is_empty() {
                             let img_url = get_local_file_url(&*query_image_path.get(), None, false);
                             view! { cx,
                                 div {
@@ -346,13 +1129,36 @@ pub fn Search<'a, G: Html>(
             })
         }
         div(class="main_container") {
-            aside(style={if *display_filters.get() { "display: block;" } else { "display: none;" }}) {
+            (if *display_filters.get() {
+                view! { cx,
+                    div(class="drawer_backdrop", on:click=toggle_filters)
+                }
+            } else {
+                view! { cx, }
+            })
+            aside(id="filters", style={if *display_filters.get() { "display: block;" } else { "display: none;" }}) {
+                button(class="drawer_close", type="button", on:click=toggle_filters) { "✖" }
                 form(id="search", on:submit=search_without_page, action="javascript:void(0);") {
                     fieldset {
                         legend { (get_translation("saved_requests", None)) }
                         div(id="saved_requests") {
                             button(form="search", type="button", on:click=open_search_request) { (get_translation("open", None)) }
                             button(form="search", type="button", on:click=save_search_request) { (get_translation("save", None)) }
+                            button(form="search", type="button", on:click=reset_search) { (get_translation("reset_search", None)) }
+                            LoadingIndicator(loading=open_request_requests.loading())
+                        }
+                        div(id="search_history") {
+                            select(id="search_history_entry", disabled=history.get().is_empty(),
+                                on:change=open_history_entry) {
+                                option(value="", selected=true) { (get_translation("search_history", None)) }
+                                Keyed(
+                                    iterable=history_labels,
+                                    view=|cx, (i, label)| view! { cx, option(value=i.to_string()) { (label) } },
+                                    key=|x| x.clone(),
+                                )
+                            }
+                            button(form="search", type="button", disabled=history.get().is_empty(),
+                                on:click=clear_search_history) { (get_translation("search_history_clear", None)) }
                         }
                     }
                     fieldset {
@@ -377,7 +1183,13 @@ pub fn Search<'a, G: Html>(
                                         id="image_search", value_enabled=image_search_enabled)
                                     CheckboxFilter(text=get_translation("reranking", None),
                                         id="reranking", value_enabled=reranking_enabled)
+                                    CheckboxFilter(text=get_translation("auto_correct", None),
+                                        id="auto_correct", value_enabled=auto_correct)
+                                    CheckboxFilter(text=get_translation("suggestions_when_empty", None),
+                                        id="suggestions_when_empty", value_enabled=suggestions_when_empty)
                                 }
+                                RangeWidget(legend=get_translation("content_fragments", None), id="content_fragments",
+                                    min=1, max=CONTENT_FRAGMENTS_MAX, step=1, value=content_fragments)
 
                                 details {
                                     summary { (get_translation("semantic_search_page_count", None)) }
@@ -399,6 +1211,10 @@ pub fn Search<'a, G: Html>(
                                         min=1.0, max=10.0, step=0.1, value=image_search_coeff)
                                     RangeWidget(legend=get_translation("reranking_coeff", None), id="reranking_coeff",
                                         min=0.1, max=5.0, step=0.1, value=reranking_coeff)
+                                    RangeWidget(legend=get_translation("recency_boost", None), id="recency_boost",
+                                        min=0.0, max=5.0, step=0.1, value=recency_boost)
+                                    CheckboxFilter(text=get_translation("debug_mode", None),
+                                        id="debug_mode", value_enabled=debug_mode)
                                 }
                             }
                         }
@@ -416,6 +1232,8 @@ pub fn Search<'a, G: Html>(
 
                     PathFilter(legend=get_translation("search_in_folder", None), id="path_prefix",
                         value=path_prefix, status_dialog_state=status_dialog_state)
+                    PathGlobFilter(legend=get_translation("filter_path_glob", None), id="path_glob",
+                        value=path_glob, valid=path_glob_valid)
 
                     ContentTypeFilter(items=content_type_items, disabled=content_type_disabled)
 
@@ -432,10 +1250,34 @@ pub fn Search<'a, G: Html>(
 
                         DateTimeFilter(legend=get_translation("filter_modification_datetime", None),
                             id="modified", value_from=modified_from, value_to=modified_to, valid=modified_valid)
+                        CheckboxOptionFilter(text=get_translation("filter_modified_in_future", None),
+                            id="modified_in_future", value_enabled=modified_in_future)
 
                         NumberFilter(legend=get_translation("filter_file_size", None), id="size",
                             min=MAX_FILE_SIZE_MIN, max=MAX_FILE_SIZE_MAX,
                             value_from=size_from, value_to=size_to, valid=size_valid)
+
+                        TagsFilter(legend=get_translation("filter_tags", None), id="tags", value=tags)
+
+                        fieldset {
+                            legend { (get_translation("filter_attributes", None)) }
+                            CheckboxOptionFilter(text=get_translation("filter_executable", None),
+                                id="is_executable", value_enabled=is_executable)
+                            CheckboxOptionFilter(text=get_translation("filter_readonly", None),
+                                id="is_readonly", value_enabled=is_readonly)
+                            CheckboxOptionFilter(text=get_translation("filter_hidden", None),
+                                id="is_hidden", value_enabled=is_hidden)
+                            CheckboxOptionFilter(text=get_translation("filter_online_only", None),
+                                id="is_online_only", value_enabled=is_online_only)
+                            CheckboxOptionFilter(text=get_translation("filter_parse_failed", None),
+                                id="parse_failed", value_enabled=parse_failed)
+                        }
+
+                        fieldset {
+                            legend { (get_translation("other", None)) }
+                            SelectOptionFilter(text=get_translation("filter_language", None), id="language",
+                                options=language_options, value=language)
+                        }
                     }
 
                     ImageFilters(data=image_filters_data)
@@ -443,10 +1285,38 @@ pub fn Search<'a, G: Html>(
                     MultimediaFilters(data=multimedia_filters_data)
 
                     DocumentFilters(data=document_filters_data)
+
+                    EmailFilters(data=email_filters_data)
                 }
             }
 
             main {
+                FilterChips(chips=active_filter_chips, reset=reset_filter)
+                Compare(selected=selected, failed_paths=failed_paths,
+                    status_dialog_state=status_dialog_state)
+
+                (if let Some((original_query, corrected_query)) = (*correction.get()).clone() {
+                    let original_query_ = original_query.clone();
+                    let search_original = move |e| {
+                        query.set(original_query_.clone());
+                        auto_correct.set(false);
+                        search_without_page(e);
+                    };
+
+                    view! { cx,
+                        h3 {
+                            (get_translation("showing_results_for", None)) " "
+                            b { (corrected_query) }
+                        }
+                        p {
+                            (get_translation("search_instead_for", None)) " "
+                            a(on:click=search_original, href="javascript:void(0);") { (original_query) }
+                        }
+                    }
+                } else {
+                    view! { cx, }
+                })
+
                 (if let Some((highlight, text)) = (*suggestion.get()).clone() {
                     let change_query = move |e| {
                         query.set(text.clone());
@@ -464,6 +1334,40 @@ pub fn Search<'a, G: Html>(
                     view! { cx, }
                 })
 
+                (if language_counts.get().is_empty() {
+                    view! { cx, }
+                } else {
+                    let counts = View::new_fragment(
+                        language_counts
+                            .get()
+                            .iter()
+                            .map(|&(x, count)| {
+                                let filter_language = move |e| {
+                                    language.set(Some(x));
+                                    search_without_page(e);
+                                };
+                                let count_args = FluentArgs::from_iter([
+                                    ("language", language_str(x)),
+                                    ("count", count.to_string()),
+                                ]);
+                                let count_str =
+                                    get_translation("language_count", Some(&count_args)).to_string();
+
+                                view! { cx,
+                                    a(on:click=filter_language, href="javascript:void(0);") { (count_str) } " "
+                                }
+                            })
+                            .collect(),
+                    );
+
+                    view! { cx,
+                        p {
+                            (get_translation("result_languages", None)) " "
+                            (counts)
+                        }
+                    }
+                })
+
                 (if *no_searches.get() {
                     view! { cx,
                         div(style="text-align: center;") {
@@ -475,22 +1379,70 @@ pub fn Search<'a, G: Html>(
                     }
                 } else {
                     view! { cx,
-                        (if search_results.get().is_empty() {
+                        (if !album_results.get().is_empty() {
+                            view! { cx,
+                                AlbumResults(albums=album_results, search_request=last_search_request,
+                                    preview_data=preview_data, selected=selected,
+                                    status_dialog_state=status_dialog_state,
+                                    thumbnails_enabled=interface.get().thumbnails_enabled)
+                                Pagination(pages=pages, search=show_page)
+                            }
+                        } else if search_results.get().is_empty() {
                             view! { cx,
                                 h3(style="text-align: center;") { (get_translation("nothing_found", None)) }
                             }
                         } else {
                             view! { cx,
-                                SearchResults(search_results=search_results, preview_data=preview_data,
+                                ExportResults(search_request=last_search_request,
                                     status_dialog_state=status_dialog_state)
-                                Pagination(pages=pages, search=search)
+                                div(class=match interface.get().results_layout {
+                                    ResultsLayout::List => "search_results",
+                                    ResultsLayout::Grid => "search_results grid",
+                                }) {
+                                    SearchResults(search_results=search_results, preview_data=preview_data,
+                                        selected=selected, keyboard_selected=keyboard_selected_id,
+                                        status_dialog_state=status_dialog_state, find_similar=find_similar,
+                                        file_actions_enabled=settings.get().file_actions_enabled,
+                                        thumbnails_enabled=interface.get().thumbnails_enabled,
+                                        remove_result=remove_result)
+                                }
+                                Pagination(pages=pages, search=show_page)
                             }
                         })
                     }
                 })
+
+                (match debug_info.get().as_ref() {
+                    Some((request, took, es_took, knn_strs)) => {
+                        let took_args = FluentArgs::from_iter([
+                            ("took", took.to_string()),
+                            ("es_took", es_took.to_string()),
+                        ]);
+                        let took_str = get_translation("debug_took", Some(&took_args)).to_string();
+                        let request = request.clone();
+                        let knn_views = View::new_fragment(
+                            knn_strs
+                                .iter()
+                                .map(|x| {
+                                    let x = x.clone();
+                                    view! { cx, p { (x) } }
+                                })
+                                .collect(),
+                        );
+                        view! { cx,
+                            details(class="debug_info") {
+                                summary { (get_translation("debug_request", None)) }
+                                p { (took_str) }
+                                (knn_views)
+                                pre { (request) }
+                            }
+                        }
+                    }
+                    None => view! { cx, },
+                })
             }
 
-            Preview(preview_data=preview_data, status_dialog_state=status_dialog_state)
+            Preview(preview_data=preview_data, settings=settings, status_dialog_state=status_dialog_state)
         }
     }
 }
@@ -547,106 +1499,214 @@ struct PreviewData {
     path: PathBuf,
     content_type: String,
     id: String,
+    /// Thumbnail that is shown while image is loading
+    thumbnail_url: Option<Url>,
+    /// Width and height of image, if known
+    dimensions: Option<(u32, u32)>,
+}
+
+impl PreviewData {
+    /// Data for showing preview of search result
+    fn from_result(result: &SearchResult) -> Self {
+        Self {
+            display: true,
+            path: result.file.path.clone(),
+            content_type: result.file.content_type.clone(),
+            id: result.file._id.clone().unwrap(),
+            thumbnail_url: get_thumbnail_url(&result.file),
+            dimensions: result
+                .file
+                .image_data
+                .width
+                .zip(result.file.image_data.height),
+        }
+    }
+}
+
+/// URL of image for preview, downscaled by server if it's larger than maximum dimension
+/// from settings or its size is unknown. Animated and vector images are served as is
+fn get_preview_image_url(preview_data: &PreviewData, max_dimension: u32) -> Url {
+    let mut url = get_local_file_url(&preview_data.path, Some(&preview_data.content_type), false);
+    let is_large = preview_data
+        .dimensions
+        .map_or(true, |(width, height)| width.max(height) > max_dimension);
+    if max_dimension > 0 && is_large {
+        url.query_pairs_mut()
+            .append_pair("max_dimension", &max_dimension.to_string());
+    }
+    url
 }
 
 #[component(inline_props)]
 fn Preview<'a, G: Html>(
     cx: Scope<'a>,
     preview_data: &'a Signal<PreviewData>,
+    settings: &'a Signal<Settings>,
     status_dialog_state: &'a Signal<StatusDialogState>,
 ) -> View<G> {
+    let content_requests = RequestScope::new(cx);
+    let image_loaded = create_signal(cx, false);
+    let content_encoding = create_signal(cx, String::new());
+    let content_total_length = create_signal(cx, 0);
+    let content_next_offset = create_signal(cx, 0);
+    let content_binary = create_signal(cx, false);
+
     let hide_preview = move |_| {
         preview_data.modify().display = false;
     };
 
-    view! { cx,
-        (if preview_data.get().display {
-            let content_type = preview_data.get().content_type.clone();
-            let object_url = get_local_file_url(&preview_data.get().path, Some(&content_type), false);
-            let id = preview_data.get().id.clone();
+    // Parts of content are appended to text, so large files are loaded incrementally
+    let load_content_part = move |id: String, offset: u64| {
+        spawn_local_scoped(cx, async move {
+            let Some(part) = get_document_content(content_requests, &id, offset).await else {
+                return;
+            };
+            match part {
+                Ok(part) => {
+                    let element = web_sys::window()
+                        .expect("`window` not found")
+                        .document()
+                        .expect("`document` not found")
+                        .get_element_by_id("preview_object")
+                        .expect("`preview_object` not found");
+                    element
+                        .insert_adjacent_text("beforeend", &part.text)
+                        .expect("Can't append content");
+                    content_encoding.set(part.encoding);
+                    content_total_length.set(part.total_length);
+                    content_next_offset.set(part.next_offset);
+                }
+                Err(e) if e.as_string().as_deref() == Some(BINARY_CONTENT_ERROR) => {
+                    content_binary.set(true);
+                }
+                Err(e) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str =
+                        get_translation("file_loading_error", Some(&error_args)).to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+            }
+        });
+    };
+    let load_more = move |_| {
+        load_content_part(preview_data.get().id.clone(), *content_next_offset.get());
+    };
 
-            view! { cx,
-                aside(id="preview") {
-                    button(form="search", type="button", on:click=hide_preview) { "✖" }
+    view! { cx,
+        ({
+            // Content of previous file isn't needed when preview is changed or hidden
+            content_requests.cancel();
 
-                    (if content_type.starts_with("image") {
-                        let object_url = object_url.clone();
+            if preview_data.get().display {
+                let content_type = preview_data.get().content_type.clone();
+                let object_url = get_local_file_url(&preview_data.get().path, Some(&content_type), false);
+                let id = preview_data.get().id.clone();
 
-                        view! { cx,
-                            img(id="preview_object", src=object_url)
-                        }
-                    } else if content_type.starts_with("video") {
-                        let object_url = object_url.clone();
+                view! { cx,
+                    aside(id="preview") {
+                        button(form="search", type="button", on:click=hide_preview) { "✖" }
+                        LoadingIndicator(loading=content_requests.loading())
 
-                        view! { cx,
-                            video(id="preview_object", controls=true, autoplay=true) {
-                                source(src=object_url)
+                        (if content_type.starts_with("image") {
+                            // Thumbnail is shown until full image is loaded
+                            let image_url = get_preview_image_url(&preview_data.get(), settings.get().preview_max_dimension);
+                            let thumbnail_url = preview_data.get().thumbnail_url.clone();
+                            image_loaded.set(thumbnail_url.is_none());
 
-                                p(style="text-align: center;") {
-                                    (get_translation("preview_not_supported", None))
-                                }
+                            view! { cx,
+                                (if let Some(thumbnail_url) = thumbnail_url.clone().filter(|_| !*image_loaded.get()) {
+                                    view! { cx,
+                                        img(id="preview_thumbnail", src=thumbnail_url)
+                                    }
+                                } else {
+                                    view! { cx, }
+                                })
+                                img(id="preview_object", src=image_url,
+                                    style=if *image_loaded.get() { "" } else { "display: none;" },
+                                    on:load=move |_| image_loaded.set(true))
                             }
-                        }
-                    } else if content_type.starts_with("audio") {
-                        let object_url = object_url.clone();
+                        } else if content_type.starts_with("video") {
+                            let object_url = object_url.clone();
 
-                        view! { cx,
-                            audio(id="preview_object", controls=true, autoplay=true) {
-                                source(src=object_url)
+                            view! { cx,
+                                video(id="preview_object", controls=true, autoplay=true) {
+                                    source(src=object_url)
 
-                                p(style="text-align: center;") {
-                                    (get_translation("preview_not_supported", None))
+                                    p(style="text-align: center;") {
+                                        (get_translation("preview_not_supported", None))
+                                    }
                                 }
                             }
-                        }
-                    } else if content_type != "text/html" && content_type != "application/pdf" {
-                        let id = id.clone();
-                        spawn_local_scoped(cx, async move {
-                            let content = match Request::get("/document_content")
-                                .query([("id", id)])
-                                .send()
-                                .await
-                            {
-                                Ok(response) => response.text().await,
-                                Err(e) => Err(e),
-                            };
-                            match content {
-                                Ok(content) => {
-                                    let element = web_sys::window()
-                                        .expect("`window` not found")
-                                        .document()
-                                        .expect("`document` not found")
-                                        .get_element_by_id("preview_object")
-                                        .expect("`preview_object` not found");
-                                    element.set_text_content(Some(&content));
-                                    status_dialog_state.set(StatusDialogState::None);
-                                }
-                                Err(e) => {
-                                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
-                                    let error_str = get_translation("file_loading_error", Some(&error_args)).to_string();
-                                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                        } else if content_type.starts_with("audio") {
+                            let object_url = object_url.clone();
+
+                            view! { cx,
+                                audio(id="preview_object", controls=true, autoplay=true) {
+                                    source(src=object_url)
+
+                                    p(style="text-align: center;") {
+                                        (get_translation("preview_not_supported", None))
+                                    }
                                 }
                             }
-                        });
+                        } else if content_type != "text/html" && content_type != "application/pdf" {
+                            content_encoding.set(String::new());
+                            content_total_length.set(0);
+                            content_next_offset.set(0);
+                            content_binary.set(false);
+                            load_content_part(id.clone(), 0);
 
-                        view! { cx,
-                            pre(id="preview_object", style="overflow: scroll; white-space: pre-wrap;")
-                        }
-                    } else {
-                        let object_url = object_url.clone();
+                            view! { cx,
+                                pre(id="preview_object", style="overflow: scroll; white-space: pre-wrap;")
+                                (if *content_binary.get() {
+                                    view! { cx,
+                                        p(style="text-align: center;") {
+                                            (get_translation("preview_binary", None))
+                                        }
+                                    }
+                                } else if !content_encoding.get().is_empty() {
+                                    let args = FluentArgs::from_iter([
+                                        ("encoding", (*content_encoding.get()).clone()),
+                                        ("loaded", file_size_str(*content_next_offset.get())),
+                                        ("total", file_size_str(*content_total_length.get())),
+                                    ]);
 
-                        view! { cx,
-                            object(id="preview_object", data=object_url) {
-                                p(style="text-align: center;") {
-                                    (get_translation("preview_not_supported", None))
+                                    view! { cx,
+                                        p {
+                                            (get_translation("preview_content_info", Some(&args)))
+                                            " "
+                                            (if *content_next_offset.get() < *content_total_length.get() {
+                                                view! { cx,
+                                                    button(type="button", disabled=*content_requests.loading().get(),
+                                                        on:click=load_more) {
+                                                        (get_translation("preview_load_more", None))
+                                                    }
+                                                }
+                                            } else {
+                                                view! { cx, }
+                                            })
+                                        }
+                                    }
+                                } else {
+                                    view! { cx, }
+                                })
+                            }
+                        } else {
+                            let object_url = object_url.clone();
+
+                            view! { cx,
+                                object(id="preview_object", data=object_url) {
+                                    p(style="text-align: center;") {
+                                        (get_translation("preview_not_supported", None))
+                                    }
                                 }
                             }
-                        }
-                    })
+                        })
+                    }
                 }
+            } else {
+                view! { cx, }
             }
-        } else {
-            view! { cx, }
         })
     }
 }
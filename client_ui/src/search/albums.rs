@@ -0,0 +1,125 @@
+use common_lib::search::{AlbumResult, SearchRequest};
+use fluent_bundle::FluentArgs;
+use sycamore::{futures::spawn_local_scoped, prelude::*};
+
+use crate::{
+    app::{
+        get_translation,
+        widgets::{LoadingIndicator, StatusDialogState},
+        RequestScope,
+    },
+    formatting::duration_str_from_seconds,
+};
+
+use super::{get_thumbnail_url, results::SearchResults, search, PreviewData};
+
+#[component(inline_props)]
+pub(super) fn AlbumResults<'a, G: Html>(
+    cx: Scope<'a>,
+    albums: &'a ReadSignal<Vec<AlbumResult>>,
+    search_request: &'a ReadSignal<Option<SearchRequest>>,
+    preview_data: &'a Signal<PreviewData>,
+    selected: &'a Signal<Vec<String>>,
+    status_dialog_state: &'a Signal<StatusDialogState>,
+    thumbnails_enabled: bool,
+) -> View<G> {
+    view! { cx,
+        Keyed(
+            iterable=albums,
+            key=|item| item.id,
+            view=move |cx, item| {
+                let expanded = create_signal(cx, false);
+                let tracks = create_signal(cx, Vec::new());
+                let track_requests = RequestScope::new(cx);
+
+                let album_name = item.album.clone()
+                    .unwrap_or_else(|| get_translation("unknown_album", None).to_string());
+                let artist = item.file.multimedia_data.artist.clone();
+                let album_info_args = FluentArgs::from_iter([
+                    ("track_cnt", item.track_cnt.to_string()),
+                    ("duration", duration_str_from_seconds(item.duration)),
+                ]);
+                let album_info = get_translation("album_info", Some(&album_info_args)).to_string();
+                let img_url = get_thumbnail_url(&item.file).filter(|_| thumbnails_enabled);
+
+                let toggle_tracks = move |_| {
+                    if *expanded.get() {
+                        expanded.set(false);
+                        return;
+                    }
+                    let Some(mut track_request) = (*search_request.get()).clone() else {
+                        return;
+                    };
+                    track_request.page = 0;
+                    track_request.multimedia_data.group_albums = false;
+                    track_request.multimedia_data.album_exact = Some(item.album.clone().unwrap_or_default());
+
+                    spawn_local_scoped(cx, async move {
+                        let Some(res) = search(track_requests, &track_request).await else {
+                            return;
+                        };
+                        match res {
+                            Ok(x) => {
+                                tracks.set(x.results);
+                                expanded.set(true);
+                            }
+                            Err(e) => {
+                                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                                let error_str = get_translation("search_error", Some(&error_args)).to_string();
+                                status_dialog_state.set(StatusDialogState::Error(error_str));
+                            }
+                        }
+                    });
+                };
+
+                view! { cx,
+                    article(class="search_result") {
+                        (if let Some(img_url) = img_url.clone() {
+                            view! { cx,
+                                img(src=(img_url), onerror="this.style.display='none'") {}
+                            }
+                        } else {
+                            view! { cx, }
+                        })
+
+                        h3(style="overflow-wrap: anywhere;") { (album_name) }
+                        (if let Some(artist) = artist.clone() {
+                            view! { cx,
+                                p(style="overflow-wrap: anywhere;") {
+                                    (get_translation("results_artist", Some(&FluentArgs::from_iter(
+                                        [("artist", artist)]))).to_string())
+                                }
+                            }
+                        } else {
+                            view! { cx, }
+                        })
+                        p { (album_info) }
+                        div {
+                            button(form="search", type="button", on:click=toggle_tracks) {
+                                (if *expanded.get() {
+                                    get_translation("collapse_album", None)
+                                } else {
+                                    get_translation("expand_album", None)
+                                })
+                            }
+                            LoadingIndicator(loading=track_requests.loading())
+                        }
+                        (if *expanded.get() {
+                            view! { cx,
+                                div(class="album_tracks") {
+                                    SearchResults(search_results=tracks, preview_data=preview_data,
+                                        selected=selected, keyboard_selected=create_signal(cx, None),
+                                        status_dialog_state=status_dialog_state,
+                                        file_actions_enabled=false, thumbnails_enabled=thumbnails_enabled,
+                                        remove_result=|_| {})
+                                }
+                            }
+                        } else {
+                            view! { cx, }
+                        })
+                    }
+                }
+            }
+        )
+    }
+}
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use common_lib::{
+    compare::{CompareResponse, DiffLine},
+    indexer::RetryParsesRequest,
+};
+use fluent_bundle::FluentArgs;
+use sycamore::{futures::spawn_local_scoped, prelude::*};
+use wasm_bindgen::JsValue;
+
+use crate::app::{
+    get_translation,
+    widgets::{LoadingIndicator, StatusDialogState},
+    RequestScope,
+};
+
+async fn compare_documents(
+    requests: RequestScope<'_>,
+    id_a: &str,
+    id_b: &str,
+) -> Option<Result<CompareResponse, JsValue>> {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("id_a", id_a)
+        .append_pair("id_b", id_b)
+        .finish();
+    requests
+        .fetch(&format!("/document/compare?{query}"), "GET", None::<&()>)
+        .await
+}
+
+async fn retry_parses(
+    requests: RequestScope<'_>,
+    paths: Vec<PathBuf>,
+) -> Option<Result<(), JsValue>> {
+    requests
+        .fetch_empty(
+            "/index/parse_failures/retry",
+            "POST",
+            Some(&RetryParsesRequest { paths }),
+        )
+        .await
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Selected results and comparison of two of them.
+/// Parsing of selected files that kept failing can be retried
+#[component(inline_props)]
+pub(super) fn Compare<'a, G: Html>(
+    cx: Scope<'a>,
+    selected: &'a Signal<Vec<String>>,
+    failed_paths: &'a ReadSignal<Vec<PathBuf>>,
+    status_dialog_state: &'a Signal<StatusDialogState>,
+) -> View<G> {
+    let comparison = create_signal(cx, None);
+    let compare_requests = RequestScope::new(cx);
+
+    let compare = move |_| {
+        let selected = selected.get();
+        let [id_a, id_b] = selected.as_slice() else {
+            return;
+        };
+        let (id_a, id_b) = (id_a.clone(), id_b.clone());
+        spawn_local_scoped(cx, async move {
+            let Some(res) = compare_documents(compare_requests, &id_a, &id_b).await else {
+                return;
+            };
+            match res {
+                Ok(res) => comparison.set(Some(res)),
+                Err(e) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str = get_translation("compare_error", Some(&error_args)).to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+            }
+        });
+    };
+    let retry_requests = RequestScope::new(cx);
+    let retry = move |_| {
+        let paths = (*failed_paths.get()).clone();
+        spawn_local_scoped(cx, async move {
+            match retry_parses(retry_requests, paths).await {
+                Some(Ok(())) => {
+                    let info_str = get_translation("retry_parses_started", None).to_string();
+                    status_dialog_state.set(StatusDialogState::Info(info_str));
+                }
+                Some(Err(e)) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str =
+                        get_translation("indexing_error", Some(&error_args)).to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+                None => {}
+            }
+        });
+    };
+    let clear_selection = move |_| selected.modify().clear();
+    let hide_comparison = move |_| {
+        compare_requests.cancel();
+        comparison.set(None);
+    };
+
+    view! { cx,
+        (if !selected.get().is_empty() {
+            let selected_args = FluentArgs::from_iter([("count", selected.get().len())]);
+            let selected_str = get_translation("compare_selected", Some(&selected_args)).to_string();
+
+            view! { cx,
+                div(id="compare_bar") {
+                    (selected_str)
+                    button(form="search", type="button", disabled=selected.get().len() != 2,
+                        on:click=compare) { (get_translation("compare", None)) }
+                    (if !failed_paths.get().is_empty() {
+                        view! { cx,
+                            button(form="search", type="button", disabled=*retry_requests.loading().get(),
+                                on:click=retry) { (get_translation("retry_failed_parses", None)) }
+                        }
+                    } else {
+                        view! { cx, }
+                    })
+                    button(form="search", type="button", on:click=clear_selection) { (get_translation("clear_selection", None)) }
+                    LoadingIndicator(loading=compare_requests.loading())
+                }
+            }
+        } else {
+            view! { cx, }
+        })
+
+        (if let Some(res) = (*comparison.get()).clone() {
+            let rows = View::new_fragment(
+                res.fields
+                    .iter()
+                    .map(|x| {
+                        let class = if x.is_equal() { "" } else { "different" };
+                        let field = x.field.clone();
+                        let value_a = x.value_a.clone().unwrap_or_default();
+                        let value_b = x.value_b.clone().unwrap_or_default();
+                        view! { cx,
+                            tr(class=class) {
+                                td { (field) }
+                                td { (value_a) }
+                                td { (value_b) }
+                            }
+                        }
+                    })
+                    .collect(),
+            );
+            let content_truncated = res.content_truncated;
+            let content_diff = match res.content_diff {
+                Some(diff) => {
+                    let lines = View::new_fragment(
+                        diff.into_iter()
+                            .map(|x| {
+                                let (class, line) = match x {
+                                    DiffLine::Equal(s) => ("", format!("  {s}")),
+                                    DiffLine::Removed(s) => ("diff_removed", format!("- {s}")),
+                                    DiffLine::Added(s) => ("diff_added", format!("+ {s}")),
+                                };
+                                view! { cx, div(class=class) { (line) } }
+                            })
+                            .collect(),
+                    );
+                    view! { cx,
+                        (if content_truncated {
+                            view! { cx, p { (get_translation("compare_content_truncated", None)) } }
+                        } else {
+                            view! { cx, }
+                        })
+                        pre(class="content_diff") { (lines) }
+                    }
+                }
+                None => view! { cx, p { (get_translation("compare_metadata_only", None)) } },
+            };
+
+            view! { cx,
+                section(id="comparison") {
+                    button(form="search", type="button", on:click=hide_comparison) { "✖" }
+                    table {
+                        thead {
+                            tr {
+                                th { (get_translation("compare_field", None)) }
+                                th(title=res.path_a.display().to_string()) { (file_name(&res.path_a)) }
+                                th(title=res.path_b.display().to_string()) { (file_name(&res.path_b)) }
+                            }
+                        }
+                        tbody { (rows) }
+                    }
+                    (content_diff)
+                }
+            }
+        } else {
+            view! { cx, }
+        })
+    }
+}
@@ -1,7 +1,9 @@
 use chrono::{DateTime, Utc};
 use common_lib::{
     elasticsearch::{AudioChannelType, ResolutionUnit},
-    search::{DocumentSearchRequest, ImageSearchRequest, MultimediaSearchRequest},
+    search::{
+        DocumentSearchRequest, EmailSearchRequest, ImageSearchRequest, MultimediaSearchRequest,
+    },
 };
 use sycamore::prelude::*;
 
@@ -254,6 +256,16 @@ pub struct MultimediaFiltersData<'a> {
 
     audio_channel_type: &'a Signal<Option<AudioChannelType>>,
 
+    video_width_from: &'a Signal<Option<u32>>,
+    video_width_to: &'a Signal<Option<u32>>,
+    video_width_valid: &'a Signal<bool>,
+
+    video_height_from: &'a Signal<Option<u32>>,
+    video_height_to: &'a Signal<Option<u32>>,
+    video_height_valid: &'a Signal<bool>,
+
+    group_albums: &'a Signal<bool>,
+
     pub any_invalid: &'a ReadSignal<bool>,
 }
 
@@ -261,8 +273,13 @@ impl<'a> MultimediaFiltersData<'a> {
     pub fn new(cx: Scope<'a>) -> Self {
         let duration_min_valid = create_signal(cx, true);
         let audio_sample_rate_valid = create_signal(cx, true);
+        let video_width_valid = create_signal(cx, true);
+        let video_height_valid = create_signal(cx, true);
         let any_invalid = create_memo(cx, || {
-            !*duration_min_valid.get() || !*audio_sample_rate_valid.get()
+            !*duration_min_valid.get()
+                || !*audio_sample_rate_valid.get()
+                || !*video_width_valid.get()
+                || !*video_height_valid.get()
         });
 
         Self {
@@ -283,6 +300,16 @@ impl<'a> MultimediaFiltersData<'a> {
 
             audio_channel_type: create_signal(cx, None),
 
+            video_width_from: create_signal(cx, None),
+            video_width_to: create_signal(cx, None),
+            video_width_valid,
+
+            video_height_from: create_signal(cx, None),
+            video_height_to: create_signal(cx, None),
+            video_height_valid,
+
+            group_albums: create_signal(cx, false),
+
             any_invalid,
         }
     }
@@ -300,6 +327,12 @@ impl<'a> MultimediaFiltersData<'a> {
             audio_sample_rate_from: *self.audio_sample_rate_from.get(),
             audio_sample_rate_to: *self.audio_sample_rate_to.get(),
             audio_channel_type: *self.audio_channel_type.get(),
+            video_width_from: *self.video_width_from.get(),
+            video_width_to: *self.video_width_to.get(),
+            video_height_from: *self.video_height_from.get(),
+            video_height_to: *self.video_height_to.get(),
+            group_albums: *self.group_albums.get(),
+            album_exact: None,
         }
     }
 
@@ -316,6 +349,11 @@ impl<'a> MultimediaFiltersData<'a> {
             .set(request.audio_sample_rate_from);
         self.audio_sample_rate_to.set(request.audio_sample_rate_to);
         self.audio_channel_type.set(request.audio_channel_type);
+        self.video_width_from.set(request.video_width_from);
+        self.video_width_to.set(request.video_width_to);
+        self.video_height_from.set(request.video_height_from);
+        self.video_height_to.set(request.video_height_to);
+        self.group_albums.set(request.group_albums);
     }
 }
 
@@ -328,6 +366,8 @@ pub fn MultimediaFilters<'a, G: Html>(
     const DURATION_MIN_MAX: f32 = 10000.0;
     const AUDIO_SAMPLE_RATE_MIN: u32 = 0;
     const AUDIO_SAMPLE_RATE_MAX: u32 = 1000000;
+    const VIDEO_SIZE_MIN: u32 = 1;
+    const VIDEO_SIZE_MAX: u32 = 99999;
 
     let audio_channel_type_options = create_signal(
         cx,
@@ -375,10 +415,19 @@ pub fn MultimediaFilters<'a, G: Html>(
                 min=AUDIO_SAMPLE_RATE_MIN, max=AUDIO_SAMPLE_RATE_MAX,
                 value_from=data.get().audio_sample_rate_from, value_to=data.get().audio_sample_rate_to, valid=data.get().audio_sample_rate_valid)
 
+            NumberFilter(legend=get_translation("filter_video_width", None), id="video_width",
+                min=VIDEO_SIZE_MIN, max=VIDEO_SIZE_MAX,
+                value_from=data.get().video_width_from, value_to=data.get().video_width_to, valid=data.get().video_width_valid)
+            NumberFilter(legend=get_translation("filter_video_height", None), id="video_height",
+                min=VIDEO_SIZE_MIN, max=VIDEO_SIZE_MAX,
+                value_from=data.get().video_height_from, value_to=data.get().video_height_to, valid=data.get().video_height_valid)
+
             fieldset {
                 legend { (get_translation("other", None)) }
                 SelectOptionFilter(text=get_translation("filter_audio_channel_type", None), id="audio_channel_type",
                     options=audio_channel_type_options, value=data.get().audio_channel_type)
+                CheckboxFilter(text=get_translation("group_albums", None),
+                    id="group_albums", value_enabled=data.get().group_albums)
             }
         }
     }
@@ -388,6 +437,7 @@ pub fn MultimediaFilters<'a, G: Html>(
 pub struct DocumentFiltersData<'a> {
     title_enabled: &'a Signal<bool>,
     creator_enabled: &'a Signal<bool>,
+    publisher_enabled: &'a Signal<bool>,
 
     doc_created_from: &'a Signal<Option<DateTime<Utc>>>,
     doc_created_to: &'a Signal<Option<DateTime<Utc>>>,
@@ -430,6 +480,7 @@ impl<'a> DocumentFiltersData<'a> {
         Self {
             title_enabled: create_signal(cx, true),
             creator_enabled: create_signal(cx, true),
+            publisher_enabled: create_signal(cx, true),
 
             doc_created_from: create_signal(cx, None),
             doc_created_to: create_signal(cx, None),
@@ -459,6 +510,7 @@ impl<'a> DocumentFiltersData<'a> {
         DocumentSearchRequest {
             title_enabled: *self.title_enabled.get(),
             creator_enabled: *self.creator_enabled.get(),
+            publisher_enabled: *self.publisher_enabled.get(),
             doc_created_from: *self.doc_created_from.get(),
             doc_created_to: *self.doc_created_to.get(),
             doc_modified_from: *self.doc_modified_from.get(),
@@ -475,6 +527,7 @@ impl<'a> DocumentFiltersData<'a> {
     pub fn update_from_request(&mut self, request: DocumentSearchRequest) {
         self.title_enabled.set(request.title_enabled);
         self.creator_enabled.set(request.creator_enabled);
+        self.publisher_enabled.set(request.publisher_enabled);
         self.doc_created_from.set(request.doc_created_from);
         self.doc_created_to.set(request.doc_created_to);
         self.doc_modified_from.set(request.doc_modified_from);
@@ -503,6 +556,8 @@ pub fn DocumentFilters<'a, G: Html>(
                     id="title", value_enabled=data.get().title_enabled)
                 CheckboxFilter(text=get_translation("filter_creator", None),
                     id="creator", value_enabled=data.get().creator_enabled)
+                CheckboxFilter(text=get_translation("filter_publisher", None),
+                    id="publisher", value_enabled=data.get().publisher_enabled)
             }
 
             DateTimeFilter(legend=get_translation("filter_doc_created", None), id="doc_created",
@@ -530,3 +585,88 @@ pub fn DocumentFilters<'a, G: Html>(
         }
     }
 }
+
+#[derive(Clone)]
+pub struct EmailFiltersData<'a> {
+    sender_enabled: &'a Signal<bool>,
+    recipients_enabled: &'a Signal<bool>,
+    subject_enabled: &'a Signal<bool>,
+
+    email_sent_from: &'a Signal<Option<DateTime<Utc>>>,
+    email_sent_to: &'a Signal<Option<DateTime<Utc>>>,
+    email_sent_valid: &'a Signal<bool>,
+
+    has_attachments: &'a Signal<Option<bool>>,
+
+    pub any_invalid: &'a ReadSignal<bool>,
+}
+
+impl<'a> EmailFiltersData<'a> {
+    pub fn new(cx: Scope<'a>) -> Self {
+        let email_sent_valid = create_signal(cx, true);
+        let any_invalid = create_memo(cx, || !*email_sent_valid.get());
+
+        Self {
+            sender_enabled: create_signal(cx, true),
+            recipients_enabled: create_signal(cx, true),
+            subject_enabled: create_signal(cx, true),
+
+            email_sent_from: create_signal(cx, None),
+            email_sent_to: create_signal(cx, None),
+            email_sent_valid,
+
+            has_attachments: create_signal(cx, None),
+
+            any_invalid,
+        }
+    }
+
+    pub fn to_request(&self) -> EmailSearchRequest {
+        EmailSearchRequest {
+            sender_enabled: *self.sender_enabled.get(),
+            recipients_enabled: *self.recipients_enabled.get(),
+            subject_enabled: *self.subject_enabled.get(),
+            email_sent_from: *self.email_sent_from.get(),
+            email_sent_to: *self.email_sent_to.get(),
+            has_attachments: *self.has_attachments.get(),
+        }
+    }
+
+    pub fn update_from_request(&mut self, request: EmailSearchRequest) {
+        self.sender_enabled.set(request.sender_enabled);
+        self.recipients_enabled.set(request.recipients_enabled);
+        self.subject_enabled.set(request.subject_enabled);
+        self.email_sent_from.set(request.email_sent_from);
+        self.email_sent_to.set(request.email_sent_to);
+        self.has_attachments.set(request.has_attachments);
+    }
+}
+
+#[component(inline_props)]
+pub fn EmailFilters<'a, G: Html>(cx: Scope<'a>, data: &'a Signal<EmailFiltersData<'a>>) -> View<G> {
+    view! { cx,
+        details {
+            summary { (get_translation("email_properties", None)) }
+
+            fieldset {
+                legend { (get_translation("filter_text_search", None)) }
+                CheckboxFilter(text=get_translation("filter_sender", None),
+                    id="sender", value_enabled=data.get().sender_enabled)
+                CheckboxFilter(text=get_translation("filter_recipients", None),
+                    id="recipients", value_enabled=data.get().recipients_enabled)
+                CheckboxFilter(text=get_translation("filter_subject", None),
+                    id="subject", value_enabled=data.get().subject_enabled)
+            }
+
+            DateTimeFilter(legend=get_translation("filter_email_sent", None), id="email_sent",
+                value_from=data.get().email_sent_from, value_to=data.get().email_sent_to,
+                valid=data.get().email_sent_valid)
+
+            fieldset {
+                legend { (get_translation("other", None)) }
+                CheckboxOptionFilter(text=get_translation("filter_has_attachments", None),
+                    id="has_attachments", value_enabled=data.get().has_attachments)
+            }
+        }
+    }
+}
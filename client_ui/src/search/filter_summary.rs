@@ -0,0 +1,664 @@
+use chrono::{DateTime, Local, Utc};
+use common_lib::search::{ContentTypeRequestItem, SearchRequest};
+use fluent_bundle::FluentArgs;
+use sycamore::prelude::*;
+
+use crate::formatting::{
+    audio_channel_type_message, duration_message, file_size_message, language_message,
+};
+
+/// Filter that can be reset separately from others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActiveFilter {
+    PathPrefix,
+    PathGlob,
+    ContentType,
+    Modified,
+    ModifiedInFuture,
+    Size,
+    Tags,
+    Executable,
+    Readonly,
+    Hidden,
+    OnlineOnly,
+    ParseFailed,
+    Language,
+    Width,
+    Height,
+    XResolution,
+    YResolution,
+    FNumber,
+    FocalLength,
+    ExposureTime,
+    Flash,
+    DurationMin,
+    AudioSampleRate,
+    AudioChannelType,
+    VideoWidth,
+    VideoHeight,
+    DocCreated,
+    DocModified,
+    NumPages,
+    NumWords,
+    NumCharacters,
+    EmailSent,
+    HasAttachments,
+}
+
+impl ActiveFilter {
+    /// Reset filter in request to its default value
+    pub fn reset(self, request: &mut SearchRequest) {
+        let image_data = &mut request.image_data;
+        let multimedia_data = &mut request.multimedia_data;
+        let document_data = &mut request.document_data;
+        let email_data = &mut request.email_data;
+        match self {
+            Self::PathPrefix => request.path_prefix = None,
+            Self::PathGlob => request.path_glob = None,
+            Self::ContentType => request.content_type = None,
+            Self::Modified => (request.modified_from, request.modified_to) = (None, None),
+            Self::ModifiedInFuture => request.modified_in_future = None,
+            Self::Size => (request.size_from, request.size_to) = (None, None),
+            Self::Tags => request.tags.clear(),
+            Self::Executable => request.is_executable = None,
+            Self::Readonly => request.is_readonly = None,
+            Self::Hidden => request.is_hidden = None,
+            Self::OnlineOnly => request.is_online_only = None,
+            Self::ParseFailed => request.parse_failed = None,
+            Self::Language => request.language = None,
+            Self::Width => (image_data.width_from, image_data.width_to) = (None, None),
+            Self::Height => (image_data.height_from, image_data.height_to) = (None, None),
+            Self::XResolution => {
+                (image_data.x_resolution_from, image_data.x_resolution_to) = (None, None)
+            }
+            Self::YResolution => {
+                (image_data.y_resolution_from, image_data.y_resolution_to) = (None, None)
+            }
+            Self::FNumber => (image_data.f_number_from, image_data.f_number_to) = (None, None),
+            Self::FocalLength => {
+                (image_data.focal_length_from, image_data.focal_length_to) = (None, None)
+            }
+            Self::ExposureTime => {
+                (image_data.exposure_time_from, image_data.exposure_time_to) = (None, None)
+            }
+            Self::Flash => image_data.flash_fired = None,
+            Self::DurationMin => {
+                (
+                    multimedia_data.duration_min_from,
+                    multimedia_data.duration_min_to,
+                ) = (None, None)
+            }
+            Self::AudioSampleRate => {
+                (
+                    multimedia_data.audio_sample_rate_from,
+                    multimedia_data.audio_sample_rate_to,
+                ) = (None, None)
+            }
+            Self::AudioChannelType => multimedia_data.audio_channel_type = None,
+            Self::VideoWidth => {
+                (
+                    multimedia_data.video_width_from,
+                    multimedia_data.video_width_to,
+                ) = (None, None)
+            }
+            Self::VideoHeight => {
+                (
+                    multimedia_data.video_height_from,
+                    multimedia_data.video_height_to,
+                ) = (None, None)
+            }
+            Self::DocCreated => {
+                (document_data.doc_created_from, document_data.doc_created_to) = (None, None)
+            }
+            Self::DocModified => {
+                (
+                    document_data.doc_modified_from,
+                    document_data.doc_modified_to,
+                ) = (None, None)
+            }
+            Self::NumPages => {
+                (document_data.num_pages_from, document_data.num_pages_to) = (None, None)
+            }
+            Self::NumWords => {
+                (document_data.num_words_from, document_data.num_words_to) = (None, None)
+            }
+            Self::NumCharacters => {
+                (
+                    document_data.num_characters_from,
+                    document_data.num_characters_to,
+                ) = (None, None)
+            }
+            Self::EmailSent => {
+                (email_data.email_sent_from, email_data.email_sent_to) = (None, None)
+            }
+            Self::HasAttachments => email_data.has_attachments = None,
+        }
+    }
+}
+
+/// Human-readable description of active filter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FilterChip {
+    pub filter: ActiveFilter,
+    pub label: String,
+}
+
+/// Format range with at least one bound
+fn range_str<T, F>(from: Option<T>, to: Option<T>, format: F) -> Option<String>
+where
+    F: Fn(T) -> String,
+{
+    match (from, to) {
+        (Some(from), Some(to)) => Some(format!("{} – {}", format(from), format(to))),
+        (Some(from), None) => Some(format!("≥ {}", format(from))),
+        (None, Some(to)) => Some(format!("≤ {}", format(to))),
+        (None, None) => None,
+    }
+}
+
+fn datetime_str(datetime: DateTime<Utc>) -> String {
+    datetime
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Function that translates message with arguments
+pub type Translate<'a> = &'a dyn Fn(&str, Option<&FluentArgs<'_>>) -> String;
+
+/// Translate message with single argument
+fn translate(tr: Translate, message_id: &str, arg: &str, value: String) -> String {
+    tr(message_id, Some(&FluentArgs::from_iter([(arg, value)])))
+}
+
+/// Translate name of filter and its value
+fn translate_legend(tr: Translate, legend_id: &str, value: String) -> String {
+    let args = FluentArgs::from_iter([("filter", tr(legend_id, None)), ("value", value)]);
+    tr("filter_chip", Some(&args))
+}
+
+fn yes_no_str(tr: Translate, x: bool) -> String {
+    tr(if x { "yes" } else { "no" }, None)
+}
+
+fn content_type_str(items: &[ContentTypeRequestItem]) -> String {
+    items
+        .iter()
+        .flat_map(|x| match x {
+            ContentTypeRequestItem::IncludeType { type_ }
+            | ContentTypeRequestItem::ExcludeSubtypes { type_, .. } => vec![type_.clone()],
+            ContentTypeRequestItem::IncludeSubtypes { subtypes } => subtypes.clone(),
+            ContentTypeRequestItem::ExcludeType { .. } => Vec::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Get descriptions of all active filters in request, translated with given function
+pub fn filter_chips(request: &SearchRequest, tr: Translate) -> Vec<FilterChip> {
+    let image_data = &request.image_data;
+    let multimedia_data = &request.multimedia_data;
+    let document_data = &request.document_data;
+    let email_data = &request.email_data;
+    let to_string = |x: u32| x.to_string();
+    let f32_to_string = |x: f32| x.to_string();
+    let file_size_str = |x: u64| {
+        let (message_id, args) = file_size_message(x);
+        tr(message_id, Some(&args))
+    };
+    let duration_min_str = |x: f32| {
+        let (message_id, args) = duration_message(x * 60.0);
+        tr(message_id, Some(&args))
+    };
+
+    [
+        (
+            ActiveFilter::PathPrefix,
+            request.path_prefix.as_ref().map(|x| {
+                translate_legend(tr, "search_in_folder", x.to_string_lossy().into_owned())
+            }),
+        ),
+        (
+            ActiveFilter::PathGlob,
+            request
+                .path_glob
+                .as_ref()
+                .map(|x| translate_legend(tr, "filter_path_glob", x.clone())),
+        ),
+        (
+            ActiveFilter::ContentType,
+            request
+                .content_type
+                .as_ref()
+                .map(|x| translate_legend(tr, "filter_file_type", content_type_str(x))),
+        ),
+        (
+            ActiveFilter::Modified,
+            range_str(request.modified_from, request.modified_to, datetime_str)
+                .map(|x| translate(tr, "results_modified", "modified", x)),
+        ),
+        (
+            ActiveFilter::ModifiedInFuture,
+            request.modified_in_future.map(|x| {
+                translate(
+                    tr,
+                    "results_modified_in_future",
+                    "modified_in_future",
+                    yes_no_str(tr, x),
+                )
+            }),
+        ),
+        (
+            ActiveFilter::Size,
+            range_str(request.size_from, request.size_to, file_size_str)
+                .map(|x| translate(tr, "results_size", "size", x)),
+        ),
+        (
+            ActiveFilter::Tags,
+            (!request.tags.is_empty())
+                .then(|| translate_legend(tr, "filter_tags", request.tags.join(", "))),
+        ),
+        (
+            ActiveFilter::Executable,
+            request
+                .is_executable
+                .map(|x| translate(tr, "results_executable", "executable", yes_no_str(tr, x))),
+        ),
+        (
+            ActiveFilter::Readonly,
+            request
+                .is_readonly
+                .map(|x| translate(tr, "results_readonly", "readonly", yes_no_str(tr, x))),
+        ),
+        (
+            ActiveFilter::Hidden,
+            request
+                .is_hidden
+                .map(|x| translate(tr, "results_hidden", "hidden", yes_no_str(tr, x))),
+        ),
+        (
+            ActiveFilter::OnlineOnly,
+            request
+                .is_online_only
+                .map(|x| translate(tr, "results_online_only", "online_only", yes_no_str(tr, x))),
+        ),
+        (
+            ActiveFilter::ParseFailed,
+            request.parse_failed.map(|x| {
+                translate(
+                    tr,
+                    "results_parse_failed",
+                    "parse_failed",
+                    yes_no_str(tr, x),
+                )
+            }),
+        ),
+        (
+            ActiveFilter::Language,
+            request
+                .language
+                .map(|x| translate_legend(tr, "filter_language", tr(language_message(x), None))),
+        ),
+        (
+            ActiveFilter::Width,
+            range_str(image_data.width_from, image_data.width_to, to_string)
+                .map(|x| translate(tr, "results_width", "width", x)),
+        ),
+        (
+            ActiveFilter::Height,
+            range_str(image_data.height_from, image_data.height_to, to_string)
+                .map(|x| translate(tr, "results_height", "height", x)),
+        ),
+        (
+            ActiveFilter::XResolution,
+            range_str(
+                image_data.x_resolution_from,
+                image_data.x_resolution_to,
+                f32_to_string,
+            )
+            .map(|x| translate_legend(tr, "filter_x_resolution", x)),
+        ),
+        (
+            ActiveFilter::YResolution,
+            range_str(
+                image_data.y_resolution_from,
+                image_data.y_resolution_to,
+                f32_to_string,
+            )
+            .map(|x| translate_legend(tr, "filter_y_resolution", x)),
+        ),
+        (
+            ActiveFilter::FNumber,
+            range_str(
+                image_data.f_number_from,
+                image_data.f_number_to,
+                f32_to_string,
+            )
+            .map(|x| translate(tr, "results_f_number", "f_number", x)),
+        ),
+        (
+            ActiveFilter::FocalLength,
+            range_str(
+                image_data.focal_length_from,
+                image_data.focal_length_to,
+                f32_to_string,
+            )
+            .map(|x| translate(tr, "results_focal_length", "focal_length", x)),
+        ),
+        (
+            ActiveFilter::ExposureTime,
+            range_str(
+                image_data.exposure_time_from,
+                image_data.exposure_time_to,
+                f32_to_string,
+            )
+            .map(|x| translate(tr, "results_exposure_time", "exposure_time", x)),
+        ),
+        (
+            ActiveFilter::Flash,
+            image_data
+                .flash_fired
+                .map(|x| translate(tr, "results_flash", "flash", yes_no_str(tr, x))),
+        ),
+        (
+            ActiveFilter::DurationMin,
+            range_str(
+                multimedia_data.duration_min_from,
+                multimedia_data.duration_min_to,
+                duration_min_str,
+            )
+            .map(|x| translate(tr, "results_duration", "duration", x)),
+        ),
+        (
+            ActiveFilter::AudioSampleRate,
+            range_str(
+                multimedia_data.audio_sample_rate_from,
+                multimedia_data.audio_sample_rate_to,
+                to_string,
+            )
+            .map(|x| translate(tr, "results_audio_sample_rate", "audio_sample_rate", x)),
+        ),
+        (
+            ActiveFilter::AudioChannelType,
+            multimedia_data.audio_channel_type.map(|x| {
+                translate(
+                    tr,
+                    "results_audio_channel_type",
+                    "audio_channel_type",
+                    tr(audio_channel_type_message(x), None),
+                )
+            }),
+        ),
+        (
+            ActiveFilter::VideoWidth,
+            range_str(
+                multimedia_data.video_width_from,
+                multimedia_data.video_width_to,
+                to_string,
+            )
+            .map(|x| translate(tr, "results_video_width", "width", x)),
+        ),
+        (
+            ActiveFilter::VideoHeight,
+            range_str(
+                multimedia_data.video_height_from,
+                multimedia_data.video_height_to,
+                to_string,
+            )
+            .map(|x| translate(tr, "results_video_height", "height", x)),
+        ),
+        (
+            ActiveFilter::DocCreated,
+            range_str(
+                document_data.doc_created_from,
+                document_data.doc_created_to,
+                datetime_str,
+            )
+            .map(|x| translate(tr, "results_doc_created", "doc_created", x)),
+        ),
+        (
+            ActiveFilter::DocModified,
+            range_str(
+                document_data.doc_modified_from,
+                document_data.doc_modified_to,
+                datetime_str,
+            )
+            .map(|x| translate(tr, "results_doc_modified", "doc_modified", x)),
+        ),
+        (
+            ActiveFilter::NumPages,
+            range_str(
+                document_data.num_pages_from,
+                document_data.num_pages_to,
+                to_string,
+            )
+            .map(|x| translate(tr, "results_num_pages", "num_pages", x)),
+        ),
+        (
+            ActiveFilter::NumWords,
+            range_str(
+                document_data.num_words_from,
+                document_data.num_words_to,
+                to_string,
+            )
+            .map(|x| translate(tr, "results_num_words", "num_words", x)),
+        ),
+        (
+            ActiveFilter::NumCharacters,
+            range_str(
+                document_data.num_characters_from,
+                document_data.num_characters_to,
+                to_string,
+            )
+            .map(|x| translate(tr, "results_num_characters", "num_characters", x)),
+        ),
+        (
+            ActiveFilter::EmailSent,
+            range_str(
+                email_data.email_sent_from,
+                email_data.email_sent_to,
+                datetime_str,
+            )
+            .map(|x| translate(tr, "results_email_sent", "email_sent", x)),
+        ),
+        (
+            ActiveFilter::HasAttachments,
+            email_data.has_attachments.map(|x| {
+                translate(
+                    tr,
+                    "results_has_attachments",
+                    "has_attachments",
+                    yes_no_str(tr, x),
+                )
+            }),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(filter, label)| label.map(|label| FilterChip { filter, label }))
+    .collect()
+}
+
+#[component(inline_props)]
+pub(super) fn FilterChips<'a, F, G>(
+    cx: Scope<'a>,
+    chips: &'a ReadSignal<Vec<FilterChip>>,
+    reset: F,
+) -> View<G>
+where
+    F: Fn(ActiveFilter) + Copy + 'a,
+    G: Html,
+{
+    view! { cx,
+        div(class="tag_chips") {
+            Keyed(
+                iterable=chips,
+                key=|x| x.clone(),
+                view=move |cx, chip| {
+                    let reset_filter = move |_| reset(chip.filter);
+
+                    view! { cx,
+                        span(class="tag_chip") {
+                            (chip.label)
+                            button(type="button", on:click=reset_filter) { "✖" }
+                        }
+                    }
+                }
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use common_lib::{
+        elasticsearch::{AudioChannelType, ContentLanguage},
+        search::{DocumentSearchRequest, ImageSearchRequest, MultimediaSearchRequest},
+    };
+    use fluent_bundle::FluentValue;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Translation that shows message ID with its arguments
+    fn translate_ids(message_id: &str, args: Option<&FluentArgs<'_>>) -> String {
+        let args: Vec<_> = args
+            .into_iter()
+            .flat_map(|x| x.iter())
+            .map(|(name, value)| match value {
+                FluentValue::String(x) => format!("{name}={x}"),
+                FluentValue::Number(x) => format!("{name}={}", x.as_string()),
+                x => format!("{name}={x:?}"),
+            })
+            .collect();
+        format!("{message_id}({})", args.join(", "))
+    }
+
+    /// Request without filters
+    fn request() -> SearchRequest {
+        serde_json::from_value(json!({
+            "page": 0,
+            "query": { "Image": { "image_path": "query.jpg", "image_search_pages": 1 } },
+            "path_enabled": true,
+            "hash_enabled": false,
+            "image_data": ImageSearchRequest::default(),
+            "multimedia_data": MultimediaSearchRequest::default(),
+            "document_data": DocumentSearchRequest::default(),
+        }))
+        .unwrap()
+    }
+
+    /// Request with all filters that can be reset
+    fn request_with_all_filters() -> SearchRequest {
+        let date = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut request = request();
+        request.path_prefix = Some("/docs".into());
+        request.path_glob = Some("*.txt".to_owned());
+        request.content_type = Some(vec![ContentTypeRequestItem::IncludeType {
+            type_: "text".to_owned(),
+        }]);
+        request.modified_from = Some(date);
+        request.modified_in_future = Some(true);
+        request.size_to = Some(10);
+        request.tags = vec!["work".to_owned()];
+        request.is_executable = Some(false);
+        request.is_readonly = Some(false);
+        request.is_hidden = Some(false);
+        request.is_online_only = Some(false);
+        request.parse_failed = Some(true);
+        request.language = Some(ContentLanguage::De);
+        let image_data = &mut request.image_data;
+        image_data.width_from = Some(1);
+        image_data.height_from = Some(1);
+        image_data.x_resolution_from = Some(1.0);
+        image_data.y_resolution_from = Some(1.0);
+        image_data.f_number_from = Some(1.0);
+        image_data.focal_length_from = Some(1.0);
+        image_data.exposure_time_from = Some(1.0);
+        image_data.flash_fired = Some(true);
+        let multimedia_data = &mut request.multimedia_data;
+        multimedia_data.duration_min_from = Some(1.0);
+        multimedia_data.audio_sample_rate_from = Some(44100);
+        multimedia_data.audio_channel_type = Some(AudioChannelType::Stereo);
+        multimedia_data.video_width_from = Some(1);
+        multimedia_data.video_height_from = Some(1);
+        let document_data = &mut request.document_data;
+        document_data.doc_created_from = Some(date);
+        document_data.doc_modified_from = Some(date);
+        document_data.num_pages_from = Some(1);
+        document_data.num_words_from = Some(1);
+        document_data.num_characters_from = Some(1);
+        let email_data = &mut request.email_data;
+        email_data.email_sent_from = Some(date);
+        email_data.has_attachments = Some(true);
+        request
+    }
+
+    #[test]
+    fn request_without_filters_has_no_chips() {
+        assert!(filter_chips(&request(), &translate_ids).is_empty());
+    }
+
+    #[test]
+    fn chips_describe_filters_with_formatted_values() {
+        let mut request = request();
+        request.path_prefix = Some("/docs".into());
+        request.size_from = Some(2048);
+        request.size_to = Some(4096);
+        request.tags = vec!["work".to_owned(), "draft".to_owned()];
+        request.is_hidden = Some(false);
+        request.language = Some(ContentLanguage::De);
+        request.image_data.width_to = Some(100);
+        request.multimedia_data.duration_min_from = Some(1.5);
+        request.multimedia_data.audio_channel_type = Some(AudioChannelType::Stereo);
+
+        let chips: Vec<_> = filter_chips(&request, &translate_ids)
+            .into_iter()
+            .map(|x| (x.filter, x.label))
+            .collect();
+        let expected = [
+            (
+                ActiveFilter::PathPrefix,
+                "filter_chip(filter=search_in_folder(), value=/docs)",
+            ),
+            (
+                ActiveFilter::Size,
+                "results_size(size=file_size_kib(size=2.000) – file_size_kib(size=4.000))",
+            ),
+            (
+                ActiveFilter::Tags,
+                "filter_chip(filter=filter_tags(), value=work, draft)",
+            ),
+            (ActiveFilter::Hidden, "results_hidden(hidden=no())"),
+            (
+                ActiveFilter::Language,
+                "filter_chip(filter=filter_language(), value=language_de())",
+            ),
+            (ActiveFilter::Width, "results_width(width=≤ 100)"),
+            (
+                ActiveFilter::DurationMin,
+                "results_duration(duration=≥ duration_m_s(hours=0, minutes=1, seconds=30.000))",
+            ),
+            (
+                ActiveFilter::AudioChannelType,
+                "results_audio_channel_type(audio_channel_type=audio_stereo())",
+            ),
+        ]
+        .map(|(filter, label)| (filter, label.to_owned()));
+        assert_eq!(chips, expected);
+    }
+
+    #[test]
+    fn reset_removes_only_its_filter() {
+        let request = request_with_all_filters();
+        let chips = filter_chips(&request, &translate_ids);
+        assert_eq!(chips.len(), 33);
+
+        for chip in &chips {
+            let mut reset_request = request.clone();
+            chip.filter.reset(&mut reset_request);
+            let expected: Vec<_> = chips.iter().filter(|x| *x != chip).cloned().collect();
+            assert_eq!(filter_chips(&reset_request, &translate_ids), expected);
+        }
+    }
+}
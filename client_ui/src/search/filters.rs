@@ -7,12 +7,20 @@ use std::{
 };
 
 use chrono::{DateTime, Local, TimeZone, Utc};
-use common_lib::actions::PickFolderResult;
+use common_lib::{
+    actions::PickFolderResult,
+    search::{is_leading_wildcard_glob, validate_path_glob, PathGlobError, SortOrder},
+    tags::normalize_tags,
+};
 use fluent_bundle::FluentArgs;
 use sycamore::{futures::spawn_local_scoped, prelude::*};
 use wasm_bindgen::JsValue;
 
-use crate::app::{fetch, get_translation, widgets::StatusDialogState};
+use crate::app::{
+    get_translation,
+    widgets::{LoadingIndicator, StatusDialogState},
+    RequestScope,
+};
 
 pub mod content_type;
 
@@ -431,8 +439,29 @@ where
     }
 }
 
-async fn pick_folder() -> Result<PickFolderResult, JsValue> {
-    fetch("/pick_folder", "POST", None::<&()>).await
+/// Order of results, shown next to search button
+#[component(inline_props)]
+pub fn SortSelect<'a, G: Html>(cx: Scope<'a>, value: &'a Signal<String>) -> View<G> {
+    let options = View::new_fragment(
+        SortOrder::ALL
+            .into_iter()
+            .map(|x| {
+                let selected = *value.get_untracked() == x.to_string();
+                let text = get_translation(format!("sort_{x}"), None);
+                view! { cx, option(value=x.to_string(), selected=selected) { (text) } }
+            })
+            .collect(),
+    );
+
+    view! { cx,
+        select(form="search", id="sort", name="sort", title=get_translation("sort", None), bind:value=value) {
+            (options)
+        }
+    }
+}
+
+async fn pick_folder(requests: RequestScope<'_>) -> Option<Result<PickFolderResult, JsValue>> {
+    requests.fetch("/pick_folder", "POST", None::<&()>).await
 }
 
 #[derive(Prop)]
@@ -452,18 +481,38 @@ pub fn PathFilter<'a, S: 'static + AsRef<str> + Display, G: Html>(
     let value = create_signal(cx, PathBuf::new());
     let value_str = create_memo(cx, || value.get().to_string_lossy().into_owned());
 
+    // Values are set only on change to stop updates between widget and props
     create_effect(cx, || {
-        props
-            .value
-            .set_silent(enabled.get().then(|| value.get().as_ref().clone()))
+        let new_value = enabled.get().then(|| value.get().as_ref().clone());
+        if *props.value.get_untracked() != new_value {
+            props.value.set(new_value);
+        }
     });
     create_effect(cx, || {
-        value.set((*props.value.get()).clone().unwrap_or_default())
+        let new_value = props.value.get();
+        let curr_value = enabled
+            .get_untracked()
+            .then(|| value.get_untracked().as_ref().clone());
+        if *new_value != curr_value {
+            match new_value.as_ref() {
+                Some(x) => {
+                    value.set(x.clone());
+                    enabled.set(true);
+                }
+                // Keep selected directory when filter is disabled
+                None => enabled.set(false),
+            }
+        }
     });
 
+    let status_dialog_state = props.status_dialog_state;
+    let pick_folder_requests = RequestScope::new(cx);
     let select_directory = move |_| {
-        spawn_local_scoped(cx, async {
-            match pick_folder().await {
+        spawn_local_scoped(cx, async move {
+            let Some(res) = pick_folder(pick_folder_requests).await else {
+                return;
+            };
+            match res {
                 Ok(res) => {
                     if let Some(path) = res.path {
                         *value.modify() = path;
@@ -473,9 +522,7 @@ pub fn PathFilter<'a, S: 'static + AsRef<str> + Display, G: Html>(
                     let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
                     let error_str =
                         get_translation("dialog_opening_error", Some(&error_args)).to_string();
-                    props
-                        .status_dialog_state
-                        .set(StatusDialogState::Error(error_str));
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
                 }
             }
         });
@@ -488,6 +535,161 @@ pub fn PathFilter<'a, S: 'static + AsRef<str> + Display, G: Html>(
                 input(type="checkbox", id=props.id, name=props.id, bind:checked=enabled)
                 input(type="text", size=7, disabled=!*enabled.get(), readonly=true, value=value_str)
                 button(type="button", on:click=select_directory) { (get_translation("select", None)) }
+                LoadingIndicator(loading=pick_folder_requests.loading())
+            }
+        }
+    }
+}
+
+#[derive(Prop)]
+pub struct PathGlobFilterProps<'a, S: AsRef<str>> {
+    pub legend: S,
+    pub id: &'static str,
+    pub value: &'a Signal<Option<String>>,
+    pub valid: &'a Signal<bool>,
+}
+
+/// Glob pattern of path, checked while typing. Empty pattern disables filter
+#[component]
+pub fn PathGlobFilter<'a, S: 'static + AsRef<str> + Display, G: Html>(
+    cx: Scope<'a>,
+    props: PathGlobFilterProps<'a, S>,
+) -> View<G> {
+    let value_str = create_signal(cx, String::new());
+    let error = create_memo(cx, || {
+        let value_str = value_str.get();
+        let glob = value_str.trim();
+        if glob.is_empty() {
+            None
+        } else {
+            validate_path_glob(glob).err()
+        }
+    });
+
+    create_effect(cx, || {
+        let new_value = Some(value_str.get().trim().to_owned()).filter(|x| !x.is_empty());
+        props.valid.set(error.get().is_none());
+        if *props.value.get_untracked() != new_value {
+            props.value.set(new_value);
+        }
+    });
+    create_effect(cx, || {
+        let new_value = props.value.get().as_ref().clone().unwrap_or_default();
+        if value_str.get_untracked().trim() != new_value {
+            value_str.set(new_value);
+        }
+    });
+
+    view! { cx,
+        fieldset {
+            legend { (props.legend) }
+            div(class="filter_field") {
+                input(type="text", id=props.id, name=props.id, size=7,
+                    placeholder=get_translation("filter_path_glob_placeholder", None), bind:value=value_str)
+                (if error.get().is_none() { "✅" } else { "❌" })
+            }
+            (match *error.get() {
+                Some(e) => {
+                    let error_str = match e {
+                        PathGlobError::Empty => get_translation("filter_path_glob_empty", None),
+                        PathGlobError::UnsupportedSyntax => {
+                            get_translation("filter_path_glob_unsupported", None)
+                        }
+                    };
+                    view! { cx, p { (error_str) } }
+                }
+                None if is_leading_wildcard_glob(value_str.get().trim()) => {
+                    view! { cx, p { (get_translation("filter_path_glob_leading_wildcard", None)) } }
+                }
+                None => view! { cx, },
+            })
+        }
+    }
+}
+
+#[component(inline_props)]
+pub fn TagChips<'a, F, G>(cx: Scope<'a>, tags: &'a ReadSignal<Vec<String>>, remove: F) -> View<G>
+where
+    F: Fn(String) + Copy + 'a,
+    G: Html,
+{
+    view! { cx,
+        div(class="tag_chips") {
+            Keyed(
+                iterable=tags,
+                key=|x| x.clone(),
+                view=move |cx, tag| {
+                    let tag_ = tag.clone();
+                    let remove_tag = move |_| remove(tag_.clone());
+
+                    view! { cx,
+                        span(class="tag_chip") {
+                            (tag)
+                            button(type="button", on:click=remove_tag) { "✖" }
+                        }
+                    }
+                }
+            )
+        }
+    }
+}
+
+/// ID of list of existing tags for inputs of new tags
+pub const TAG_SUGGESTIONS_ID: &str = "tag_suggestions";
+
+#[derive(Prop)]
+pub struct TagsFilterProps<'a, S: AsRef<str>> {
+    pub legend: S,
+    pub id: &'static str,
+    pub value: &'a Signal<Vec<String>>,
+}
+
+#[component]
+pub fn TagsFilter<'a, S: 'static + AsRef<str> + Display, G: Html>(
+    cx: Scope<'a>,
+    props: TagsFilterProps<'a, S>,
+) -> View<G> {
+    let new_tag = create_signal(cx, String::new());
+
+    // Existing tags are suggested in this filter and in tag editors of results
+    let suggested_tags = create_signal(cx, Vec::<String>::new());
+    let tags_requests = RequestScope::new(cx);
+    spawn_local_scoped(cx, async move {
+        // Tags can be entered without suggestions, so errors are ignored
+        if let Some(Ok(x)) = tags_requests.fetch("/tags", "GET", None::<&()>).await {
+            suggested_tags.set(x);
+        }
+    });
+
+    let add_tag = move |_| {
+        let tags = normalize_tags(
+            props
+                .value
+                .get()
+                .iter()
+                .cloned()
+                .chain([(*new_tag.get()).clone()]),
+        );
+        props.value.set(tags);
+        new_tag.set(String::new());
+    };
+    let remove_tag = move |tag: String| props.value.modify().retain(|x| *x != tag);
+
+    view! { cx,
+        fieldset {
+            legend { (props.legend) }
+            TagChips(tags=props.value, remove=remove_tag)
+            div(class="filter_field") {
+                input(type="text", id=props.id, name=props.id, size=7, list=TAG_SUGGESTIONS_ID,
+                    bind:value=new_tag)
+                button(type="button", on:click=add_tag) { (get_translation("add_tag", None)) }
+            }
+            datalist(id=TAG_SUGGESTIONS_ID) {
+                Keyed(
+                    iterable=suggested_tags,
+                    key=|x: &String| x.clone(),
+                    view=|cx, x| view! { cx, option(value=x) }
+                )
             }
         }
     }
@@ -1,6 +1,6 @@
 use std::{borrow::Cow, collections::HashMap, fmt::Display};
 
-use common_lib::search::ContentTypeRequestItem;
+use common_lib::{search::ContentTypeRequestItem, settings::SOURCE_CONTENT_TYPES};
 use sycamore::prelude::*;
 use uuid::Uuid;
 
@@ -75,6 +75,18 @@ pub struct ContentTypeFilterProps<'a, S: AsRef<str>> {
     pub disabled: &'a Signal<bool>,
 }
 
+/// Content types of source code files read by indexer without Apache Tika
+fn source_code_essences() -> Vec<&'static str> {
+    let mut essences: Vec<_> = SOURCE_CONTENT_TYPES
+        .iter()
+        .map(|(_, content_type)| *content_type)
+        .filter(|x| *x != "text/markdown")
+        .collect();
+    essences.sort_unstable();
+    essences.dedup();
+    essences
+}
+
 pub fn content_type_filter_items(cx: Scope) -> &Signal<Vec<ContentTypeItem<'_, Cow<'_, str>>>> {
     create_signal(
         cx,
@@ -88,6 +100,8 @@ pub fn content_type_filter_items(cx: Scope) -> &Signal<Vec<ContentTypeItem<'_, C
                     ContentTypeSubitem::new(cx, get_translation("mime_text_csv", None), vec!["text/csv"]),
                     ContentTypeSubitem::new(cx, get_translation("mime_text_html", None), vec!["text/html"]),
                     ContentTypeSubitem::new(cx, get_translation("mime_text_css", None), vec!["text/css"]),
+                    ContentTypeSubitem::new(cx, get_translation("mime_text_markdown", None), vec!["text/markdown"]),
+                    ContentTypeSubitem::new(cx, get_translation("mime_text_source", None), source_code_essences()),
                     ContentTypeSubitem::new(cx, get_translation("mime_other", None), Vec::new()),
                 ],
             ),
@@ -157,6 +171,15 @@ pub fn content_type_filter_items(cx: Scope) -> &Signal<Vec<ContentTypeItem<'_, C
                     ContentTypeSubitem::new(cx, get_translation("mime_other", None), Vec::new()),
                 ],
             ),
+            ContentTypeItem::new(
+                cx,
+                get_translation("mime_message", None),
+                "message",
+                vec![
+                    ContentTypeSubitem::new(cx, get_translation("mime_message_rfc822", None), vec!["message/rfc822"]),
+                    ContentTypeSubitem::new(cx, get_translation("mime_other", None), Vec::new()),
+                ],
+            ),
             ContentTypeItem::new(
                 cx,
                 get_translation("mime_application", None),
@@ -268,6 +291,16 @@ pub fn content_type_filter_items(cx: Scope) -> &Signal<Vec<ContentTypeItem<'_, C
                             "application/vnd.apple.keynote.18",
                         ],
                     ),
+                    ContentTypeSubitem::new(
+                        cx,
+                        get_translation("mime_application_ebook", None),
+                        vec!["application/epub+zip", "application/x-fictionbook+xml"],
+                    ),
+                    ContentTypeSubitem::new(
+                        cx,
+                        get_translation("mime_application_outlook", None),
+                        vec!["application/vnd.ms-outlook"],
+                    ),
                     ContentTypeSubitem::new(cx, get_translation("mime_application_zip", None), vec!["application/zip"]),
                     ContentTypeSubitem::new(cx, get_translation("mime_application_rar", None), vec!["application/x-rar-compressed"]),
                     ContentTypeSubitem::new(cx, get_translation("mime_application_7zip", None), vec!["application/x-7z-compressed"]),
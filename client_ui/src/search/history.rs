@@ -0,0 +1,116 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use chrono::{DateTime, Local, Utc};
+use common_lib::search::{QueryType, SearchRequest};
+use fluent_bundle::FluentArgs;
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::app::{fetch_status, get_translation};
+
+use super::get_local_file_url;
+
+/// Key of history of searches in local storage
+const HISTORY_STORAGE_KEY: &str = "search_history";
+/// Maximum number of searches in history, the oldest ones are removed
+const HISTORY_MAX_LEN: usize = 50;
+/// Status of file request for file that doesn't exist
+const NOT_FOUND_STATUS: u16 = 404;
+
+/// Successful search with time and number of results on its page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub search_request: SearchRequest,
+    pub time: DateTime<Utc>,
+    pub result_count: usize,
+}
+
+/// Searches from local storage, the newest first
+pub fn load_history() -> Vec<HistoryEntry> {
+    LocalStorage::get(HISTORY_STORAGE_KEY).unwrap_or_default()
+}
+
+/// Add search to history and store it, unless it's the same as the previous one.
+/// Uploaded images are too large for local storage, so searches by them aren't added
+pub fn add_to_history(
+    history: &mut Vec<HistoryEntry>,
+    mut search_request: SearchRequest,
+    result_count: usize,
+) {
+    if let QueryType::Image(x) = &search_request.query {
+        if x.image_path.as_os_str().is_empty() {
+            return;
+        }
+    }
+    // Searches are opened from history on the first page, so other pages are the same search
+    search_request.page = 0;
+    let key = serde_json::to_string(&search_request).unwrap();
+    let is_repeated = history
+        .first()
+        .is_some_and(|x| serde_json::to_string(&x.search_request).unwrap() == key);
+    if is_repeated {
+        return;
+    }
+
+    history.insert(
+        0,
+        HistoryEntry {
+            search_request,
+            time: Utc::now(),
+            result_count,
+        },
+    );
+    history.truncate(HISTORY_MAX_LEN);
+    _ = LocalStorage::set(HISTORY_STORAGE_KEY, &*history);
+}
+
+pub fn clear_history() {
+    LocalStorage::delete(HISTORY_STORAGE_KEY);
+}
+
+/// Paths of images of queries in history that no longer exist.
+/// Errors other than missing file are ignored
+pub async fn missing_query_images(history: &[HistoryEntry]) -> BTreeSet<PathBuf> {
+    let paths: BTreeSet<_> = history
+        .iter()
+        .filter_map(|x| match &x.search_request.query {
+            QueryType::Image(x) => Some(x.image_path.clone()),
+            QueryType::Text(_) => None,
+        })
+        .collect();
+    let mut missing = BTreeSet::new();
+    for path in paths {
+        let url = get_local_file_url(&path, None, false);
+        if let Ok(NOT_FOUND_STATUS) = fetch_status(url.as_str(), "HEAD").await {
+            missing.insert(path);
+        }
+    }
+    missing
+}
+
+/// Description of search in list of history: query or image file, time and number of results
+pub fn history_entry_label(entry: &HistoryEntry, missing_images: &BTreeSet<PathBuf>) -> String {
+    let query = match &entry.search_request.query {
+        QueryType::Text(x) => x.query.clone(),
+        QueryType::Image(x) => {
+            let name = x
+                .image_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            if missing_images.contains(&x.image_path) {
+                let args = FluentArgs::from_iter([("name", name)]);
+                get_translation("search_history_image_missing", Some(&args)).to_string()
+            } else {
+                name
+            }
+        }
+    };
+    let args = FluentArgs::from_iter([
+        ("query", query),
+        ("time", entry.time.with_timezone(&Local).to_string()),
+        ("count", entry.result_count.to_string()),
+    ]);
+    get_translation("search_history_entry", Some(&args)).to_string()
+}
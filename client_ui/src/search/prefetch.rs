@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+
+use common_lib::search::{SearchRequest, SearchResponse};
+use js_sys::Promise;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::window;
+
+/// Maximum number of cached pages
+const PAGE_CACHE_CAPACITY: usize = 2;
+
+/// Prefetched pages of results, keyed by request including page number
+#[derive(Debug, Default)]
+pub struct PageCache {
+    entries: VecDeque<(String, SearchResponse)>,
+}
+
+impl PageCache {
+    fn key(request: &SearchRequest) -> String {
+        serde_json::to_string(request).unwrap()
+    }
+
+    pub fn get(&self, request: &SearchRequest) -> Option<SearchResponse> {
+        let key = Self::key(request);
+        self.entries
+            .iter()
+            .find(|(x, _)| *x == key)
+            .map(|(_, response)| response.clone())
+    }
+
+    /// Add page, removing the oldest one if cache is full
+    pub fn insert(&mut self, request: &SearchRequest, response: SearchResponse) {
+        let key = Self::key(request);
+        self.entries.retain(|(x, _)| *x != key);
+        if self.entries.len() == PAGE_CACHE_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, response));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Checks if responses contain the same files in the same order
+pub fn same_results(a: &SearchResponse, b: &SearchResponse) -> bool {
+    a.results.len() == b.results.len()
+        && a.albums.len() == b.albums.len()
+        && a.results
+            .iter()
+            .zip(&b.results)
+            .all(|(x, y)| x.file._id == y.file._id)
+        && a.albums
+            .iter()
+            .zip(&b.albums)
+            .all(|(x, y)| x.file._id == y.file._id)
+}
+
+/// Wait until browser is idle, or don't wait if it can't report that
+pub async fn idle() {
+    let promise = Promise::new(&mut |resolve, _| {
+        if window().unwrap().request_idle_callback(&resolve).is_err() {
+            resolve.call0(&JsValue::NULL).unwrap();
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
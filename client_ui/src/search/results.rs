@@ -1,48 +1,200 @@
 use chrono::Local;
 use common_lib::{
-    actions::OpenPathArgs,
+    actions::{DeletePathArgs, OpenPathArgs},
     elasticsearch::{
-        AudioChannelType, DocumentData, FileMetadata, ImageData, MultimediaData, ResolutionUnit,
+        DocumentData, EmailData, FileAttributes, FileMetadata, ImageData, MultimediaData,
+        ResolutionUnit,
     },
     search::{
-        DocumentHighlightedFields, ImageHighlightedFields, MultimediaHighlightedFields,
-        SearchResult,
+        DocumentHighlightedFields, EmailHighlightedFields, ExportFormat, ExportRequest,
+        ImageHighlightedFields, MultimediaHighlightedFields, SearchRequest, SearchResult,
+        SimilarRequest, SimilarityMode,
     },
+    tags::{TagsOperation, TagsRequest},
 };
 use fluent_bundle::FluentArgs;
 use sycamore::{futures::spawn_local_scoped, prelude::*};
+use uuid::Uuid;
 use wasm_bindgen::JsValue;
+use web_sys::Blob;
 
 use crate::{
-    app::{fetch_empty, get_translation, widgets::StatusDialogState},
-    formatting::{duration_str_from_seconds, file_size_str},
+    app::{
+        download_blob, get_translation,
+        widgets::{LoadingIndicator, StatusDialogState},
+        RequestScope,
+    },
+    formatting::{audio_channel_type_str, duration_str_from_seconds, file_size_str},
+};
+
+use super::{
+    filters::{TagChips, TAG_SUGGESTIONS_ID},
+    get_thumbnail_url, PreviewData,
 };
 
-use super::{get_local_file_url, PreviewData};
+/// Status of `/reveal_path` response when file of result no longer exists
+const MISSING_FILE_STATUS: u16 = 410;
+
+pub(super) async fn open_path(
+    requests: RequestScope<'_>,
+    args: &OpenPathArgs,
+) -> Option<Result<(), JsValue>> {
+    requests.fetch_empty("/open_path", "POST", Some(args)).await
+}
+
+/// Show file selected in file manager, returns status code and text of response
+async fn reveal_path(
+    requests: RequestScope<'_>,
+    args: &OpenPathArgs,
+) -> Option<Result<(u16, String), JsValue>> {
+    requests
+        .fetch_status_text("/reveal_path", "POST", Some(args))
+        .await
+}
+
+async fn delete_path(
+    requests: RequestScope<'_>,
+    args: &DeletePathArgs,
+) -> Option<Result<(), JsValue>> {
+    requests
+        .fetch_empty("/delete_path", "POST", Some(args))
+        .await
+}
+
+/// Ask user to confirm moving file to trash
+fn confirm_move_to_trash(file_name: &str) -> bool {
+    let confirm_args = FluentArgs::from_iter([("name", file_name)]);
+    let confirm_str = get_translation("move_to_trash_confirm", Some(&confirm_args)).to_string();
+    web_sys::window()
+        .unwrap()
+        .confirm_with_message(&confirm_str)
+        .unwrap_or(false)
+}
+
+/// Ask user to confirm removing result of file that no longer exists
+fn confirm_remove_missing(file_name: &str) -> bool {
+    let confirm_args = FluentArgs::from_iter([("name", file_name)]);
+    let confirm_str = get_translation("file_missing_confirm", Some(&confirm_args)).to_string();
+    web_sys::window()
+        .unwrap()
+        .confirm_with_message(&confirm_str)
+        .unwrap_or(false)
+}
+
+async fn update_tags(
+    requests: RequestScope<'_>,
+    id: &str,
+    tags_request: &TagsRequest,
+) -> Option<Result<Vec<String>, JsValue>> {
+    requests
+        .fetch(&format!("/document/{id}/tags"), "POST", Some(tags_request))
+        .await
+}
+
+async fn export_results(
+    requests: RequestScope<'_>,
+    export_request: &ExportRequest,
+) -> Option<Result<Blob, JsValue>> {
+    requests
+        .fetch_blob("/search/export", "POST", Some(export_request))
+        .await
+}
+
+/// Buttons for downloading results of search from all pages
+#[component(inline_props)]
+pub(super) fn ExportResults<'a, G: Html>(
+    cx: Scope<'a>,
+    search_request: &'a ReadSignal<Option<SearchRequest>>,
+    status_dialog_state: &'a Signal<StatusDialogState>,
+) -> View<G> {
+    let export_requests = RequestScope::new(cx);
+    let export = move |format: ExportFormat| {
+        let Some(search_request) = (*search_request.get()).clone() else {
+            return;
+        };
+        spawn_local_scoped(cx, async move {
+            let export_request = ExportRequest {
+                search_request: SearchRequest {
+                    page: 0,
+                    ..search_request
+                },
+                format,
+                max_results: None,
+            };
+            let res = match export_results(export_requests, &export_request).await {
+                Some(Ok(blob)) => download_blob(&blob, &format!("search_results.{format}")).await,
+                Some(Err(e)) => Err(e),
+                None => return,
+            };
+            if let Err(e) = res {
+                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                let error_str = get_translation("exporting_error", Some(&error_args)).to_string();
+                status_dialog_state.set(StatusDialogState::Error(error_str));
+            }
+        })
+    };
 
-async fn open_path(args: &OpenPathArgs) -> Result<(), JsValue> {
-    fetch_empty("/open_path", "POST", Some(args)).await
+    view! { cx,
+        div(class="export_results") {
+            (get_translation("export_results", None)) " "
+            button(type="button", on:click=move |_| export(ExportFormat::Csv)) { "CSV" }
+            button(type="button", on:click=move |_| export(ExportFormat::Json)) { "JSON" }
+            LoadingIndicator(loading=export_requests.loading())
+        }
+    }
 }
 
 #[component(inline_props)]
-pub(super) fn SearchResults<'a, G: Html>(
+pub(super) fn SearchResults<'a, F, R, G>(
     cx: Scope<'a>,
     search_results: &'a ReadSignal<Vec<SearchResult>>,
     preview_data: &'a Signal<PreviewData>,
+    selected: &'a Signal<Vec<String>>,
+    /// Result selected with keyboard, it's outlined
+    keyboard_selected: &'a ReadSignal<Option<Uuid>>,
     status_dialog_state: &'a Signal<StatusDialogState>,
-) -> View<G> {
+    find_similar: F,
+    /// Show button for moving file to trash
+    file_actions_enabled: bool,
+    thumbnails_enabled: bool,
+    /// Remove result from list after its file was moved to trash
+    remove_result: R,
+) -> View<G>
+where
+    F: Fn(SimilarRequest) + Copy + 'a,
+    R: Fn(Uuid) + Copy + 'a,
+    G: Html,
+{
     view! { cx,
         Keyed(
             iterable=search_results,
             key=|item| item.id,
             view=move |cx, item| {
                 let file_name = item.file.path.file_name().unwrap().to_string_lossy().into_owned();
-                let path = item.file.path.clone();
+                let img_url = get_thumbnail_url(&item.file).filter(|_| thumbnails_enabled);
+                let preview = PreviewData::from_result(&item);
                 let path_ = item.file.path.clone();
                 let path__ = item.file.path.clone();
-                let content_type = item.file.content_type.clone();
+                let path___ = item.file.path.clone();
+                let in_archive = item.file.in_archive == Some(true);
+                let id = item.file._id.clone().unwrap();
+                let tags = item.file.tags.clone();
 
                 let empty_file = item.file.size == 0;
+                let online_only = item.file.attributes.is_online_only == Some(true);
+                let parse_failed = item.file.parse_failed == Some(true);
+                let symlink = item.file.attributes.is_symlink == Some(true);
+
+                let matched_fields = View::new_fragment(
+                    item.matched_fields
+                        .iter()
+                        .map(|field| {
+                            let field_args = FluentArgs::from_iter([("field", field.clone())]);
+                            let field_str = get_translation("matched_field", Some(&field_args)).to_string();
+                            view! { cx, span(class="matched_field_badge") { (field_str) } }
+                        })
+                        .collect(),
+                );
 
                 let highlighted_path_args = FluentArgs::from_iter([("path", item.highlights.path)]);
                 let highlighted_path = get_translation("results_path", Some(&highlighted_path_args)).to_string();
@@ -51,25 +203,15 @@ pub(super) fn SearchResults<'a, G: Html>(
                     get_translation("results_hash", Some(&highlighted_hash_args)).to_string()
                 });
 
-                let show_preview = move |_| {
-                    preview_data.set(PreviewData {
-                        display: true,
-                        path: item.file.path.clone(),
-                        content_type: content_type.clone(),
-                        id: item.file._id.clone().unwrap(),
-                    });
-                };
+                let show_preview = move |_| preview_data.set(preview.clone());
+                let open_requests = RequestScope::new(cx);
                 let open_path = move |path| {
                     spawn_local_scoped(cx, async move {
-                        status_dialog_state.set(StatusDialogState::Loading);
-
-                        if let Err(e) = open_path(&OpenPathArgs { path }).await {
+                        if let Some(Err(e)) = open_path(open_requests, &OpenPathArgs { path, in_archive }).await {
                             let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
                             let error_str = get_translation("opening_error", Some(&error_args)).to_string();
                             status_dialog_state.set(StatusDialogState::Error(error_str));
-                            return;
                         }
-                        status_dialog_state.set(StatusDialogState::None);
                     })
                 };
                 let open_file = move |_| {
@@ -80,13 +222,102 @@ pub(super) fn SearchResults<'a, G: Html>(
                     let path = path__.parent().unwrap().to_path_buf();
                     open_path(path)
                 };
+                let id_ = id.clone();
+                let is_selected = create_memo(cx, move || selected.get().contains(&id_));
+                let item_id = item.id;
+                let is_keyboard_selected = create_memo(cx, move || *keyboard_selected.get() == Some(item_id));
+                // Images are compared by CLIP embeddings, other files by text embeddings
+                let similarity_mode = if item.file.content_type.starts_with("image") {
+                    SimilarityMode::Image
+                } else {
+                    SimilarityMode::Text
+                };
+                let id_ = id.clone();
+                let show_similar = move |_| {
+                    find_similar(SimilarRequest {
+                        id: id_.clone(),
+                        mode: similarity_mode,
+                        page: 0,
+                    })
+                };
+                let id_ = id.clone();
+                let toggle_selected = move |_| {
+                    let mut selected = selected.modify();
+                    match selected.iter().position(|x| *x == id_) {
+                        Some(i) => {
+                            selected.remove(i);
+                        }
+                        None => selected.push(id_.clone()),
+                    }
+                };
+                let trash_requests = RequestScope::new(cx);
+                // File is moved to trash if it exists, otherwise only its document is removed
+                let remove_document = move |id: String| {
+                    let args = DeletePathArgs { id };
+                    spawn_local_scoped(cx, async move {
+                        match delete_path(trash_requests, &args).await {
+                            Some(Ok(())) => remove_result(item_id),
+                            Some(Err(e)) => {
+                                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                                let error_str = get_translation("moving_to_trash_error", Some(&error_args)).to_string();
+                                status_dialog_state.set(StatusDialogState::Error(error_str));
+                            }
+                            None => {}
+                        }
+                    })
+                };
+                let id_ = id.clone();
+                let file_name_ = file_name.clone();
+                let move_to_trash = move |_| {
+                    if confirm_move_to_trash(&file_name_) {
+                        remove_document(id_.clone());
+                    }
+                };
+                let id_ = id.clone();
+                let file_name_ = file_name.clone();
+                let reveal_file = move |_| {
+                    let args = OpenPathArgs { path: path___.clone(), in_archive };
+                    let id = id_.clone();
+                    let file_name = file_name_.clone();
+                    spawn_local_scoped(cx, async move {
+                        let error_str = match reveal_path(open_requests, &args).await {
+                            // Stale document can be removed only if file actions are allowed
+                            Some(Ok((status, _))) if status == MISSING_FILE_STATUS && file_actions_enabled => {
+                                if confirm_remove_missing(&file_name) {
+                                    remove_document(id);
+                                }
+                                return;
+                            }
+                            Some(Ok((status, _))) if status == MISSING_FILE_STATUS => {
+                                let error_args = FluentArgs::from_iter([("name", file_name)]);
+                                get_translation("file_missing", Some(&error_args)).to_string()
+                            }
+                            Some(Ok((status, text))) if !(200..300).contains(&status) => {
+                                let error_args = FluentArgs::from_iter([("error", text)]);
+                                get_translation("opening_error", Some(&error_args)).to_string()
+                            }
+                            Some(Err(e)) => {
+                                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                                get_translation("opening_error", Some(&error_args)).to_string()
+                            }
+                            _ => return,
+                        };
+                        status_dialog_state.set(StatusDialogState::Error(error_str));
+                    })
+                };
+                let trash_button = if file_actions_enabled {
+                    view! { cx,
+                        button(form="search", type="button", disabled=*trash_requests.loading().get(),
+                            on:click=move_to_trash) { (get_translation("move_to_trash", None)) }
+                        LoadingIndicator(loading=trash_requests.loading())
+                    }
+                } else {
+                    view! { cx, }
+                };
 
                 view! { cx,
-                    article(class="search_result") {
-                        (if item.file.content_type.starts_with("image")
-                                || item.file.content_type.starts_with("video")
-                                || item.file.content_type.starts_with("audio") {
-                            let img_url = get_local_file_url(&path, Some(&item.file.content_type), true);
+                    article(class=if *is_keyboard_selected.get() { "search_result keyboard_selected" } else { "search_result" }) {
+                        (if let Some(img_url) = img_url.clone() {
                             view! { cx,
                                 img(src=(img_url), onerror="this.style.display='none'") {}
                             }
@@ -95,17 +326,65 @@ pub(super) fn SearchResults<'a, G: Html>(
                         })
 
                         h3(style="overflow-wrap: anywhere;") { (file_name) }
+                        (if online_only {
+                            view! { cx,
+                                span(class="online_only_badge", title=get_translation("online_only_hint", None)) {
+                                    (get_translation("online_only", None))
+                                }
+                            }
+                        } else {
+                            view! { cx, }
+                        })
+                        (if parse_failed {
+                            view! { cx,
+                                span(class="parse_failed_badge", title=get_translation("parse_failed_hint", None)) {
+                                    (get_translation("parse_failed", None))
+                                }
+                            }
+                        } else {
+                            view! { cx, }
+                        })
+                        (if symlink {
+                            view! { cx,
+                                span(class="symlink_badge", title=get_translation("symlink_hint", None)) {
+                                    (get_translation("symlink", None))
+                                }
+                            }
+                        } else {
+                            view! { cx, }
+                        })
                         p(style="overflow-wrap: anywhere;", dangerously_set_inner_html=&highlighted_path)
+                        div(class="matched_fields", title=get_translation("matched_fields_hint", None)) {
+                            (matched_fields)
+                        }
                         div {
                             button(form="search", type="button", disabled=empty_file,
                                 on:click=show_preview) { (get_translation("show", None)) }
                             button(form="search", type="button", on:click=open_file) { (get_translation("open", None)) }
                             button(form="search", type="button", on:click=open_folder) { (get_translation("open_folder", None)) }
+                            button(form="search", type="button", on:click=reveal_file) { (get_translation("reveal_file", None)) }
+                            button(form="search", type="button", on:click=show_similar) { (get_translation("find_similar", None)) }
+                            LoadingIndicator(loading=open_requests.loading())
+                            (trash_button)
+                            input(type="checkbox", id=format!("select_{}", item.id), checked=*is_selected.get(),
+                                on:change=toggle_selected)
+                            label(for=format!("select_{}", item.id)) { (get_translation("select_result", None)) }
                         }
+                        TagsEditor(id=id, tags=tags, status_dialog_state=status_dialog_state)
                         (if let Some(content) = item.highlights.content.clone() {
-                            view! { cx,
-                                p(style="overflow-wrap: anywhere;", dangerously_set_inner_html=&content)
-                            }
+                            // Fragments are separated by ellipses
+                            View::new_fragment(
+                                content
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, fragment)| {
+                                        view! { cx,
+                                            (if i > 0 { view! { cx, p { "…" } } } else { view! { cx, } })
+                                            p(style="overflow-wrap: anywhere;", dangerously_set_inner_html=&fragment)
+                                        }
+                                    })
+                                    .collect(),
+                            )
                         } else {
                             view! { cx, }
                         })
@@ -135,6 +414,7 @@ pub(super) fn SearchResults<'a, G: Html>(
                             } else {
                                 view! { cx, }
                             })
+                            FileAttributesDetails(attributes=item.file.attributes.clone())
                         }
 
                         (if item.file.image_data.any_metadata() {
@@ -160,6 +440,14 @@ pub(super) fn SearchResults<'a, G: Html>(
                         } else {
                             view! { cx, }
                         })
+
+                        (if item.file.email_data.any_metadata() {
+                            let email_data = item.file.email_data.clone();
+                            let email_highlights = item.highlights.email_data.clone();
+                            view! { cx, EmailDataDetails(data=email_data, highlights=email_highlights) }
+                        } else {
+                            view! { cx, }
+                        })
                     }
                 }
             }
@@ -167,6 +455,106 @@ pub(super) fn SearchResults<'a, G: Html>(
     }
 }
 
+#[component(inline_props)]
+fn TagsEditor<'a, G: Html>(
+    cx: Scope<'a>,
+    id: String,
+    tags: Vec<String>,
+    status_dialog_state: &'a Signal<StatusDialogState>,
+) -> View<G> {
+    let id = create_ref(cx, id);
+    let tags = create_signal(cx, tags);
+    let new_tag = create_signal(cx, String::new());
+
+    // Changes must not be cancelled, so new changes are ignored until previous one is finished
+    let tags_requests = RequestScope::new(cx);
+    let change_tags = move |tags_request: TagsRequest| {
+        if *tags_requests.loading().get() {
+            return;
+        }
+        spawn_local_scoped(cx, async move {
+            let Some(res) = update_tags(tags_requests, id, &tags_request).await else {
+                return;
+            };
+            match res {
+                Ok(x) => {
+                    tags.set(x);
+                }
+                Err(e) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str =
+                        get_translation("tags_updating_error", Some(&error_args)).to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+            }
+        })
+    };
+    let add_tag = move |_| {
+        let tag = std::mem::take(&mut *new_tag.modify());
+        change_tags(TagsRequest {
+            operation: TagsOperation::Add,
+            tags: vec![tag],
+        });
+    };
+    let remove_tag = move |tag: String| {
+        change_tags(TagsRequest {
+            operation: TagsOperation::Remove,
+            tags: vec![tag],
+        });
+    };
+
+    view! { cx,
+        div(class="tags_editor") {
+            TagChips(tags=tags, remove=remove_tag)
+            input(type="text", size=10, placeholder=get_translation("new_tag", None), list=TAG_SUGGESTIONS_ID,
+                bind:value=new_tag)
+            button(form="search", type="button", disabled=*tags_requests.loading().get(),
+                on:click=add_tag) { (get_translation("add_tag", None)) }
+            LoadingIndicator(loading=tags_requests.loading())
+        }
+    }
+}
+
+#[component(inline_props)]
+fn FileAttributesDetails<'a, G: Html>(cx: Scope<'a>, attributes: FileAttributes) -> View<G> {
+    let bool_attributes = [
+        ("results_executable", "executable", attributes.is_executable),
+        ("results_readonly", "readonly", attributes.is_readonly),
+        ("results_hidden", "hidden", attributes.is_hidden),
+        (
+            "results_online_only",
+            "online_only",
+            attributes.is_online_only,
+        ),
+        ("results_symlink", "symlink", attributes.is_symlink),
+    ];
+    let bool_views = View::new_fragment(
+        bool_attributes
+            .into_iter()
+            .filter_map(|(message_id, arg, value)| {
+                let value_str =
+                    get_translation(if value? { "yes" } else { "no" }, None).to_string();
+                let value_args = FluentArgs::from_iter([(arg, value_str)]);
+                let attribute_str = get_translation(message_id, Some(&value_args)).to_string();
+                Some(view! { cx, p { (attribute_str) } })
+            })
+            .collect(),
+    );
+
+    view! { cx,
+        (bool_views)
+        (if let Some(xattrs) = attributes.xattrs.as_ref().filter(|x| !x.is_empty()) {
+            let xattrs_args = FluentArgs::from_iter([("xattrs", xattrs.join(", "))]);
+            let xattrs_str = get_translation("results_xattrs", Some(&xattrs_args)).to_string();
+            view! { cx,
+                p(style="overflow-wrap: anywhere;") { (xattrs_str) }
+            }
+        } else {
+            view! { cx, }
+        })
+    }
+}
+
 #[component(inline_props)]
 fn ImageDataDetails<'a, G: Html>(
     cx: Scope<'a>,
@@ -364,14 +752,7 @@ fn MultimediaDataDetails<'a, G: Html>(
                 view! { cx, }
             })
             (if let Some(audio_channel_type) = data.audio_channel_type {
-                let audio_channel_type_str = match audio_channel_type {
-                    AudioChannelType::Mono => get_translation("audio_mono", None),
-                    AudioChannelType::Stereo => get_translation("audio_stereo", None),
-                    AudioChannelType::_5_1 => get_translation("audio_5_1", None),
-                    AudioChannelType::_7_1 => get_translation("audio_7_1", None),
-                    AudioChannelType::_16 => get_translation("audio_16", None),
-                    AudioChannelType::Other => get_translation("audio_other", None),
-                };
+                let audio_channel_type_str = audio_channel_type_str(audio_channel_type);
                 view! { cx,
                     p { (get_translation("results_audio_channel_type", Some(&FluentArgs::from_iter(
                             [("audio_channel_type", audio_channel_type_str.as_ref())]))).to_string()) }
@@ -379,6 +760,14 @@ fn MultimediaDataDetails<'a, G: Html>(
             } else {
                 view! { cx, }
             })
+            (if let Some((width, height)) = data.video_width.zip(data.video_height) {
+                view! { cx,
+                    p { (get_translation("results_video_resolution", Some(&FluentArgs::from_iter(
+                            [("width", width), ("height", height)]))).to_string()) }
+                }
+            } else {
+                view! { cx, }
+            })
         }
     }
 }
@@ -397,6 +786,10 @@ fn DocumentDataDetails<'a, G: Html>(
         let highlighted_creator_args = FluentArgs::from_iter([("creator", x)]);
         get_translation("results_creator", Some(&highlighted_creator_args)).to_string()
     });
+    let highlighted_publisher = highlights.publisher.map(|x| {
+        let highlighted_publisher_args = FluentArgs::from_iter([("publisher", x)]);
+        get_translation("results_publisher", Some(&highlighted_publisher_args)).to_string()
+    });
 
     view! { cx,
         details {
@@ -412,6 +805,19 @@ fn DocumentDataDetails<'a, G: Html>(
             } else {
                 view! { cx, }
             })
+            (if let Some(publisher) = highlighted_publisher.clone() {
+                view! { cx, p(dangerously_set_inner_html=&publisher) }
+            } else {
+                view! { cx, }
+            })
+            (if let Some(doc_language) = data.doc_language.clone() {
+                view! { cx,
+                    p { (get_translation("results_doc_language", Some(&FluentArgs::from_iter(
+                            [("doc_language", doc_language)]))).to_string()) }
+                }
+            } else {
+                view! { cx, }
+            })
             (if let Some(doc_created) = data.doc_created {
                 view! { cx,
                     p { (get_translation("results_doc_created", Some(&FluentArgs::from_iter(
@@ -455,3 +861,62 @@ fn DocumentDataDetails<'a, G: Html>(
         }
     }
 }
+
+#[component(inline_props)]
+fn EmailDataDetails<'a, G: Html>(
+    cx: Scope<'a>,
+    data: EmailData,
+    highlights: EmailHighlightedFields,
+) -> View<G> {
+    let highlighted_sender = highlights.sender.map(|x| {
+        let highlighted_sender_args = FluentArgs::from_iter([("sender", x)]);
+        get_translation("results_sender", Some(&highlighted_sender_args)).to_string()
+    });
+    let highlighted_recipients = highlights.recipients.map(|x| {
+        let highlighted_recipients_args = FluentArgs::from_iter([("recipients", x)]);
+        get_translation("results_recipients", Some(&highlighted_recipients_args)).to_string()
+    });
+    let highlighted_subject = highlights.subject.map(|x| {
+        let highlighted_subject_args = FluentArgs::from_iter([("subject", x)]);
+        get_translation("results_subject", Some(&highlighted_subject_args)).to_string()
+    });
+
+    view! { cx,
+        details {
+            summary { (get_translation("email_properties", None)) }
+
+            (if let Some(sender) = highlighted_sender.clone() {
+                view! { cx, p(dangerously_set_inner_html=&sender) }
+            } else {
+                view! { cx, }
+            })
+            (if let Some(recipients) = highlighted_recipients.clone() {
+                view! { cx, p(dangerously_set_inner_html=&recipients) }
+            } else {
+                view! { cx, }
+            })
+            (if let Some(subject) = highlighted_subject.clone() {
+                view! { cx, p(dangerously_set_inner_html=&subject) }
+            } else {
+                view! { cx, }
+            })
+            (if let Some(email_sent) = data.email_sent {
+                view! { cx,
+                    p { (get_translation("results_email_sent", Some(&FluentArgs::from_iter(
+                            [("email_sent", email_sent.with_timezone(&Local).to_string())]))).to_string()) }
+                }
+            } else {
+                view! { cx, }
+            })
+            (if let Some(has_attachments) = data.has_attachments {
+                let has_attachments_str = get_translation(if has_attachments { "yes" } else { "no" }, None);
+                view! { cx,
+                    p { (get_translation("results_has_attachments", Some(&FluentArgs::from_iter(
+                            [("has_attachments", has_attachments_str.as_ref())]))).to_string()) }
+                }
+            } else {
+                view! { cx, }
+            })
+        }
+    }
+}
@@ -1,36 +1,82 @@
-use std::{net::SocketAddr, str::FromStr};
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, str::FromStr};
 
-use common_lib::settings::{NNServerSettings, Settings};
+use common_lib::{
+    settings::{
+        ClientLanguage, ClientSettings, ClientTheme, HashAlgorithm, NNServerSettings,
+        ResultsLayout, Settings,
+    },
+    NNServerHealth,
+};
 use fluent_bundle::FluentArgs;
 use sycamore::{futures::spawn_local_scoped, prelude::*};
 use url::Url;
 use wasm_bindgen::JsValue;
 
-use crate::app::{fetch, fetch_empty, get_translation, widgets::StatusDialogState};
+use crate::app::{
+    fetch_empty, get_translation,
+    interface::Interface,
+    set_api_token,
+    widgets::{LoadingIndicator, StatusDialogState},
+    RequestScope,
+};
 
 use self::widgets::{
-    CheckboxSetting, DirectoryItem, DirectoryList, NNSetting, NNSettingsData, NumberSetting,
-    SimpleTextSetting, TextSetting,
+    CheckboxSetting, DirectoryItem, DirectoryList, FieldWeightsData, FieldWeightsSetting,
+    NNSetting, NNSettingsData, NumberSetting, SelectSetting, SimpleTextSetting, StringList,
+    TextSetting,
 };
 
-mod widgets;
+pub mod widgets;
 
 const DEBOUNCER_TIMEOUT_MIN: f32 = 0.1;
 const DEBOUNCER_TIMEOUT_MAX: f32 = 3600.0;
+const PERIODIC_INDEXING_INTERVAL_HOURS_MIN: u32 = 1;
+const PERIODIC_INDEXING_INTERVAL_HOURS_MAX: u32 = 24 * 365;
 pub const MAX_FILE_SIZE_MIN: f64 = 0.01;
 pub const MAX_FILE_SIZE_MAX: f64 = 1000.0;
+const HASH_CHANGE_DETECTION_MAX_SIZE_MIN: f64 = 0.01;
+const HASH_CHANGE_DETECTION_MAX_SIZE_MAX: f64 = 100000.0;
+const HASH_CHUNK_SIZE_MIN: f64 = 4.0;
+const HASH_CHUNK_SIZE_MAX: f64 = 65536.0;
+const ONLINE_ONLY_HYDRATION_BUDGET_MIN: f64 = 0.0;
+const ONLINE_ONLY_HYDRATION_BUDGET_MAX: f64 = 100000.0;
 const MAX_CONCURRENT_FILES_MIN: usize = 1;
 const MAX_CONCURRENT_FILES_MAX: usize = 256;
+const SLOW_FILE_THRESHOLD_MIN: f32 = 0.0;
+const SLOW_FILE_THRESHOLD_MAX: f32 = 86400.0;
+const PARSE_FAILURES_MAX_MIN: u32 = 0;
+const PARSE_FAILURES_MAX_MAX: u32 = 1000;
 const ELASTICSEARCH_BATCH_SIZE_MIN: usize = 1;
 const ELASTICSEARCH_BATCH_SIZE_MAX: usize = 1000;
+const ELASTICSEARCH_PIT_KEEP_ALIVE_MIN: u32 = 1;
+const ELASTICSEARCH_PIT_KEEP_ALIVE_MAX: u32 = 1440;
+const ELASTICSEARCH_BULK_RETRIES_MIN: u32 = 0;
+const ELASTICSEARCH_BULK_RETRIES_MAX: u32 = 20;
 const RESULTS_PER_PAGE_MIN: u32 = 1;
 const RESULTS_PER_PAGE_MAX: u32 = 1000;
 const KNN_CANDIDATES_MULTIPLIER_MIN: u32 = 1;
 const KNN_CANDIDATES_MULTIPLIER_MAX: u32 = 100;
+const RECENCY_BOOST_SCALE_DAYS_MIN: u32 = 1;
+const RECENCY_BOOST_SCALE_DAYS_MAX: u32 = 36500;
+const AUTO_CORRECT_MAX_HITS_MIN: u64 = 1;
+const AUTO_CORRECT_MAX_HITS_MAX: u64 = 1000;
+const OCR_MIN_DIMENSION_MIN: u32 = 1;
+const OCR_MIN_DIMENSION_MAX: u32 = 100000;
+const THUMBNAIL_CACHE_MAX_SIZE_MIN: f64 = 1.0;
+const THUMBNAIL_CACHE_MAX_SIZE_MAX: f64 = 1000000.0;
+const PREVIEW_CHUNK_SIZE_MIN: f64 = 4.0;
+const PREVIEW_CHUNK_SIZE_MAX: f64 = 65536.0;
 const BATCH_SIZE_MIN: usize = 1;
 const BATCH_SIZE_MAX: usize = 256;
 const MAX_DELAY_MS_MIN: u64 = 10;
 const MAX_DELAY_MS_MAX: u64 = 1000;
+const MAX_QUEUE_MIN: usize = 1;
+const MAX_QUEUE_MAX: usize = 4096;
+const EMBEDDING_DIM_MIN: usize = 1;
+/// Maximum dimension of indexed dense vector in Elasticsearch 8.7
+const EMBEDDING_DIM_MAX: usize = 1024;
+const IDLE_TIMEOUT_MINS_MIN: u64 = 0;
+const IDLE_TIMEOUT_MINS_MAX: u64 = 10080;
 const MAX_SENTENCES_MIN: u32 = 1;
 const MAX_SENTENCES_MAX: u32 = 1000;
 const WINDOW_SIZE_MIN: u32 = 10;
@@ -39,10 +85,57 @@ const WINDOW_STEP_MIN: u32 = 1;
 const WINDOW_STEP_MAX: u32 = 200;
 const SUMMARY_LEN_MIN: u32 = 1;
 const SUMMARY_LEN_MAX: u32 = 10;
+const TEXT_CACHE_CAPACITY_MIN: usize = 0;
+const TEXT_CACHE_CAPACITY_MAX: usize = 100000;
 
 trait SettingsUi {
     fn get_indexing_directories_dir_items(&self) -> Vec<DirectoryItem>;
     fn get_max_file_size_mib(&self) -> f64;
+    fn get_hash_change_detection_max_size_gib(&self) -> f64;
+    fn get_hash_chunk_size_kib(&self) -> f64;
+    fn get_online_only_hydration_budget_gib(&self) -> f64;
+    fn get_thumbnail_cache_max_size_mib(&self) -> f64;
+    fn get_preview_chunk_size_kib(&self) -> f64;
+    fn get_elasticsearch_urls_str(&self) -> String;
+    fn get_content_type_overrides_list(&self) -> Vec<String>;
+}
+
+trait ClientSettingsUi {
+    fn get_wake_url_str(&self) -> String;
+}
+
+impl ClientSettingsUi for ClientSettings {
+    fn get_wake_url_str(&self) -> String {
+        self.wake_url
+            .as_ref()
+            .map(Url::to_string)
+            .unwrap_or_default()
+    }
+}
+
+/// Empty wake URL disables starting indexer through launcher
+fn parse_wake_url(s: &str) -> Result<String, url::ParseError> {
+    if !s.is_empty() {
+        Url::parse(s)?;
+    }
+    Ok(s.to_owned())
+}
+
+/// Overrides of content types in the form "extension=content type", invalid lines are ignored
+fn parse_content_type_overrides(list: &[String]) -> BTreeMap<String, String> {
+    list.iter()
+        .filter_map(|x| x.split_once('='))
+        .map(|(extension, content_type)| {
+            let extension = extension.trim().trim_start_matches('.').to_lowercase();
+            (extension, content_type.trim().to_owned())
+        })
+        .filter(|(extension, content_type)| !extension.is_empty() && !content_type.is_empty())
+        .collect()
+}
+
+/// Comma-separated URLs of Elasticsearch nodes, at least one is required
+fn parse_elasticsearch_urls(s: &str) -> Result<Vec<Url>, url::ParseError> {
+    s.split(',').map(|x| Url::parse(x.trim())).collect()
 }
 
 impl SettingsUi for Settings {
@@ -55,10 +148,47 @@ impl SettingsUi for Settings {
     fn get_max_file_size_mib(&self) -> f64 {
         (self.max_file_size as f64) / 1024.0 / 1024.0
     }
+    fn get_hash_change_detection_max_size_gib(&self) -> f64 {
+        (self.hash_change_detection_max_size as f64) / 1024.0 / 1024.0 / 1024.0
+    }
+    fn get_hash_chunk_size_kib(&self) -> f64 {
+        (self.hash_chunk_size as f64) / 1024.0
+    }
+    fn get_online_only_hydration_budget_gib(&self) -> f64 {
+        (self.online_only_hydration_budget as f64) / 1024.0 / 1024.0 / 1024.0
+    }
+    fn get_thumbnail_cache_max_size_mib(&self) -> f64 {
+        (self.thumbnail_cache_max_size as f64) / 1024.0 / 1024.0
+    }
+    fn get_preview_chunk_size_kib(&self) -> f64 {
+        (self.preview_chunk_size as f64) / 1024.0
+    }
+
+    fn get_elasticsearch_urls_str(&self) -> String {
+        self.elasticsearch_urls
+            .iter()
+            .map(Url::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    fn get_content_type_overrides_list(&self) -> Vec<String> {
+        self.content_type_overrides
+            .iter()
+            .map(|(extension, content_type)| format!("{}={}", extension, content_type))
+            .collect()
+    }
+}
+
+async fn get_settings(requests: RequestScope<'_>) -> Option<Result<Settings, JsValue>> {
+    requests.fetch("/settings", "GET", None::<&()>).await
 }
 
-async fn get_settings() -> Result<Settings, JsValue> {
-    fetch("/settings", "GET", None::<&()>).await
+async fn get_nn_server_devices(
+    requests: RequestScope<'_>,
+) -> Option<Result<NNServerHealth, JsValue>> {
+    requests
+        .fetch("/settings/nn_server", "GET", None::<&()>)
+        .await
 }
 
 async fn put_settings(settings: &Settings) -> Result<(), JsValue> {
@@ -73,20 +203,153 @@ pub fn Settings<'a, G: Html>(
 ) -> View<G> {
     // Input values for settings
     let indexer_address = create_signal(cx, settings.get().indexer_address);
-    let elasticsearch_url = create_signal(cx, settings.get().elasticsearch_url.clone());
+    let api_token = create_signal(cx, settings.get().api_token.clone().unwrap_or_default());
+    let elasticsearch_urls = create_signal(cx, settings.get().get_elasticsearch_urls_str());
+    let elasticsearch_user = create_signal(
+        cx,
+        settings
+            .get()
+            .elasticsearch_user
+            .clone()
+            .unwrap_or_default(),
+    );
+    let elasticsearch_password = create_signal(
+        cx,
+        settings
+            .get()
+            .elasticsearch_password
+            .clone()
+            .unwrap_or_default(),
+    );
+    let elasticsearch_ca_cert_path = create_signal(
+        cx,
+        settings
+            .get()
+            .elasticsearch_ca_cert_path
+            .as_ref()
+            .map(|x| x.display().to_string())
+            .unwrap_or_default(),
+    );
     let tika_url = create_signal(cx, settings.get().tika_url.clone());
     let nn_server_url = create_signal(cx, settings.get().nn_server_url.clone());
+    let wake_url = create_signal(cx, settings.get().client.get_wake_url_str());
+    let prefetch_next_page = create_signal(cx, settings.get().client.prefetch_next_page);
+    let prefetch_with_reranking = create_signal(cx, settings.get().client.prefetch_with_reranking);
+    let report_errors = create_signal(cx, settings.get().client.report_errors);
+    let client_language = create_signal(cx, settings.get().client.client_language);
+    let client_language_options = create_signal(
+        cx,
+        vec![
+            (
+                ClientLanguage::Auto,
+                get_translation("client_language_auto", None),
+            ),
+            (
+                ClientLanguage::En,
+                get_translation("client_language_en", None),
+            ),
+            (
+                ClientLanguage::Ru,
+                get_translation("client_language_ru", None),
+            ),
+        ],
+    );
+    let interface = Interface::load(&settings.get().client);
+    let theme = create_signal(cx, interface.theme);
+    let theme_options = create_signal(
+        cx,
+        vec![
+            (ClientTheme::Auto, get_translation("theme_auto", None)),
+            (ClientTheme::Light, get_translation("theme_light", None)),
+            (ClientTheme::Dark, get_translation("theme_dark", None)),
+        ],
+    );
+    let results_layout = create_signal(cx, interface.results_layout);
+    let results_layout_options = create_signal(
+        cx,
+        vec![
+            (
+                ResultsLayout::List,
+                get_translation("results_layout_list", None),
+            ),
+            (
+                ResultsLayout::Grid,
+                get_translation("results_layout_grid", None),
+            ),
+        ],
+    );
+    let thumbnails_enabled = create_signal(cx, interface.thumbnails_enabled);
+    let interface_local = create_signal(cx, Interface::local().is_some());
     let open_on_start = create_signal(cx, settings.get().open_on_start);
+    let metrics_enabled = create_signal(cx, settings.get().metrics_enabled);
+    let file_actions_enabled = create_signal(cx, settings.get().file_actions_enabled);
     let indexing_directories =
         create_signal(cx, settings.get().get_indexing_directories_dir_items());
     let exclude_file_regex = create_signal(cx, settings.get().exclude_file_regex.clone());
+    let case_insensitive_paths = create_signal(cx, settings.get().case_insensitive_paths);
+    let skip_content_types = create_signal(cx, settings.get().skip_content_types.clone());
+    let content_type_overrides =
+        create_signal(cx, settings.get().get_content_type_overrides_list());
+    let index_archives = create_signal(cx, settings.get().index_archives);
+    let thumbnail_cache_enabled = create_signal(cx, settings.get().thumbnail_cache_enabled);
+    let thumbnail_cache_dir =
+        create_signal(cx, settings.get().thumbnail_cache_dir.display().to_string());
+    let thumbnail_cache_max_size =
+        create_signal(cx, settings.get().get_thumbnail_cache_max_size_mib());
+    let preview_chunk_size = create_signal(cx, settings.get().get_preview_chunk_size_kib());
     let watcher_enabled = create_signal(cx, settings.get().watcher_enabled);
     let debouncer_timeout = create_signal(cx, settings.get().debouncer_timeout);
+    let periodic_indexing_enabled = create_signal(cx, settings.get().periodic_indexing_enabled);
+    let periodic_indexing_interval_hours =
+        create_signal(cx, settings.get().periodic_indexing_interval_hours);
     let max_file_size = create_signal(cx, settings.get().get_max_file_size_mib());
+    let hash_change_detection_max_size =
+        create_signal(cx, settings.get().get_hash_change_detection_max_size_gib());
+    let hash_algorithm = create_signal(cx, settings.get().hash_algorithm);
+    let hash_algorithm_options = create_signal(
+        cx,
+        vec![
+            (
+                HashAlgorithm::Sha256,
+                get_translation("hash_algorithm_sha256", None),
+            ),
+            (
+                HashAlgorithm::Xxh3,
+                get_translation("hash_algorithm_xxh3", None),
+            ),
+            (
+                HashAlgorithm::None,
+                get_translation("hash_algorithm_none", None),
+            ),
+        ],
+    );
+    let hash_chunk_size = create_signal(cx, settings.get().get_hash_chunk_size_kib());
+    let online_only_xattr = create_signal(cx, settings.get().online_only_xattr.clone());
+    let online_only_hydration_budget =
+        create_signal(cx, settings.get().get_online_only_hydration_budget_gib());
     let max_concurrent_files = create_signal(cx, settings.get().max_concurrent_files);
+    let slow_file_threshold = create_signal(cx, settings.get().slow_file_threshold);
+    let parse_failures_max = create_signal(cx, settings.get().parse_failures_max);
+    let index_xattrs = create_signal(cx, settings.get().index_xattrs);
+    let follow_symlinks = create_signal(cx, settings.get().follow_symlinks);
+    let cjk_analysis = create_signal(cx, settings.get().cjk_analysis);
+    let content_languages = create_signal(cx, settings.get().content_languages.clone());
+    let ocr_enabled = create_signal(cx, settings.get().ocr_enabled);
+    let ocr_tesseract_path = create_signal(cx, settings.get().ocr_tesseract_path.clone());
+    let ocr_languages = create_signal(cx, settings.get().ocr_languages.clone());
+    let ocr_min_dimension = create_signal(cx, settings.get().ocr_min_dimension);
     let elasticsearch_batch_size = create_signal(cx, settings.get().elasticsearch_batch_size);
+    let elasticsearch_pit_keep_alive =
+        create_signal(cx, settings.get().elasticsearch_pit_keep_alive);
+    let elasticsearch_bulk_retries = create_signal(cx, settings.get().elasticsearch_bulk_retries);
     let results_per_page = create_signal(cx, settings.get().results_per_page);
+    let results_per_page_max = create_signal(cx, settings.get().results_per_page_max);
     let knn_candidates_multiplier = create_signal(cx, settings.get().knn_candidates_multiplier);
+    let recency_boost_scale_days = create_signal(cx, settings.get().recency_boost_scale_days);
+    let auto_correct_max_hits = create_signal(cx, settings.get().auto_correct_max_hits);
+    let auto_correct = create_signal(cx, settings.get().client.auto_correct);
+    let field_weights_data =
+        create_signal(cx, FieldWeightsData::new(cx, &settings.get().field_weights));
     let nn_server_address = create_signal(cx, settings.get().nn_server.nn_server_address);
     let text_search_enabled = create_signal(cx, settings.get().nn_server.text_search_enabled);
     let image_search_enabled = create_signal(cx, settings.get().nn_server.image_search_enabled);
@@ -107,38 +370,74 @@ pub fn Settings<'a, G: Html>(
         cx,
         NNSettingsData::new(cx, &settings.get().nn_server.minilm_rerank),
     );
+    let nn_devices = create_signal(cx, BTreeMap::new());
     let max_sentences = create_signal(cx, settings.get().nn_server.max_sentences);
     let window_size = create_signal(cx, settings.get().nn_server.window_size);
     let window_step = create_signal(cx, settings.get().nn_server.window_step);
     let summary_len = create_signal(cx, settings.get().nn_server.summary_len);
+    let summary_len_max = create_signal(cx, settings.get().nn_server.summary_len_max);
+    let text_cache_capacity = create_signal(cx, settings.get().nn_server.text_cache_capacity);
 
     // Validation values for settings
     let indexer_address_valid = create_signal(cx, true);
-    let elasticsearch_url_valid = create_signal(cx, true);
+    let elasticsearch_urls_valid = create_signal(cx, true);
     let tika_url_valid = create_signal(cx, true);
     let nn_server_url_valid = create_signal(cx, true);
+    let wake_url_valid = create_signal(cx, true);
     let debouncer_timeout_valid = create_signal(cx, true);
+    let periodic_indexing_interval_hours_valid = create_signal(cx, true);
     let max_file_size_valid = create_signal(cx, true);
+    let hash_change_detection_max_size_valid = create_signal(cx, true);
+    let hash_chunk_size_valid = create_signal(cx, true);
+    let online_only_hydration_budget_valid = create_signal(cx, true);
+    let ocr_min_dimension_valid = create_signal(cx, true);
+    let thumbnail_cache_max_size_valid = create_signal(cx, true);
+    let preview_chunk_size_valid = create_signal(cx, true);
     let max_concurrent_files_valid = create_signal(cx, true);
+    let slow_file_threshold_valid = create_signal(cx, true);
+    let parse_failures_max_valid = create_signal(cx, true);
     let elasticsearch_batch_size_valid = create_signal(cx, true);
+    let elasticsearch_pit_keep_alive_valid = create_signal(cx, true);
+    let elasticsearch_bulk_retries_valid = create_signal(cx, true);
     let results_per_page_valid = create_signal(cx, true);
+    let results_per_page_max_valid = create_signal(cx, true);
     let knn_candidates_multiplier_valid = create_signal(cx, true);
+    let recency_boost_scale_days_valid = create_signal(cx, true);
+    let auto_correct_max_hits_valid = create_signal(cx, true);
     let nn_server_address_valid = create_signal(cx, true);
     let max_sentences_valid = create_signal(cx, true);
     let window_size_valid = create_signal(cx, true);
     let window_step_valid = create_signal(cx, true);
     let summary_len_valid = create_signal(cx, true);
+    let summary_len_max_valid = create_signal(cx, true);
+    let text_cache_capacity_valid = create_signal(cx, true);
     let any_invalid = create_memo(cx, || {
         !*indexer_address_valid.get()
-            || !*elasticsearch_url_valid.get()
+            || !*elasticsearch_urls_valid.get()
             || !*tika_url_valid.get()
             || !*nn_server_url_valid.get()
+            || !*wake_url_valid.get()
             || !*debouncer_timeout_valid.get()
+            || !*periodic_indexing_interval_hours_valid.get()
             || !*max_file_size_valid.get()
+            || !*hash_change_detection_max_size_valid.get()
+            || !*hash_chunk_size_valid.get()
+            || !*online_only_hydration_budget_valid.get()
+            || !*ocr_min_dimension_valid.get()
+            || !*thumbnail_cache_max_size_valid.get()
+            || !*preview_chunk_size_valid.get()
             || !*max_concurrent_files_valid.get()
+            || !*slow_file_threshold_valid.get()
+            || !*parse_failures_max_valid.get()
             || !*elasticsearch_batch_size_valid.get()
+            || !*elasticsearch_pit_keep_alive_valid.get()
+            || !*elasticsearch_bulk_retries_valid.get()
             || !*results_per_page_valid.get()
+            || !*results_per_page_max_valid.get()
             || !*knn_candidates_multiplier_valid.get()
+            || !*recency_boost_scale_days_valid.get()
+            || !*auto_correct_max_hits_valid.get()
+            || *field_weights_data.get().any_invalid.get()
             || !*nn_server_address_valid.get()
             || *clip_image_data.get().any_invalid.get()
             || *clip_text_data.get().any_invalid.get()
@@ -148,24 +447,95 @@ pub fn Settings<'a, G: Html>(
             || !*window_size_valid.get()
             || !*window_step_valid.get()
             || !*summary_len_valid.get()
+            || !*summary_len_max_valid.get()
+            || !*text_cache_capacity_valid.get()
     });
 
     // Set input values from settings when they are updated (on load from server or reset)
     let update_settings = || {
         indexer_address.set(settings.get().indexer_address);
-        elasticsearch_url.set(settings.get().elasticsearch_url.clone());
+        api_token.set(settings.get().api_token.clone().unwrap_or_default());
+        elasticsearch_urls.set(settings.get().get_elasticsearch_urls_str());
+        elasticsearch_user.set(
+            settings
+                .get()
+                .elasticsearch_user
+                .clone()
+                .unwrap_or_default(),
+        );
+        elasticsearch_password.set(
+            settings
+                .get()
+                .elasticsearch_password
+                .clone()
+                .unwrap_or_default(),
+        );
+        elasticsearch_ca_cert_path.set(
+            settings
+                .get()
+                .elasticsearch_ca_cert_path
+                .as_ref()
+                .map(|x| x.display().to_string())
+                .unwrap_or_default(),
+        );
         tika_url.set(settings.get().tika_url.clone());
         nn_server_url.set(settings.get().nn_server_url.clone());
+        wake_url.set(settings.get().client.get_wake_url_str());
+        prefetch_next_page.set(settings.get().client.prefetch_next_page);
+        prefetch_with_reranking.set(settings.get().client.prefetch_with_reranking);
+        report_errors.set(settings.get().client.report_errors);
+        client_language.set(settings.get().client.client_language);
+        let interface = Interface::load(&settings.get().client);
+        theme.set(interface.theme);
+        results_layout.set(interface.results_layout);
+        thumbnails_enabled.set(interface.thumbnails_enabled);
+        interface_local.set(Interface::local().is_some());
         open_on_start.set(settings.get().open_on_start);
+        metrics_enabled.set(settings.get().metrics_enabled);
+        file_actions_enabled.set(settings.get().file_actions_enabled);
         indexing_directories.set(settings.get().get_indexing_directories_dir_items());
         exclude_file_regex.set(settings.get().exclude_file_regex.clone());
+        case_insensitive_paths.set(settings.get().case_insensitive_paths);
+        skip_content_types.set(settings.get().skip_content_types.clone());
+        content_type_overrides.set(settings.get().get_content_type_overrides_list());
+        index_archives.set(settings.get().index_archives);
+        thumbnail_cache_enabled.set(settings.get().thumbnail_cache_enabled);
+        thumbnail_cache_dir.set(settings.get().thumbnail_cache_dir.display().to_string());
+        thumbnail_cache_max_size.set(settings.get().get_thumbnail_cache_max_size_mib());
+        preview_chunk_size.set(settings.get().get_preview_chunk_size_kib());
         watcher_enabled.set(settings.get().watcher_enabled);
         debouncer_timeout.set(settings.get().debouncer_timeout);
+        periodic_indexing_enabled.set(settings.get().periodic_indexing_enabled);
+        periodic_indexing_interval_hours.set(settings.get().periodic_indexing_interval_hours);
         max_file_size.set(settings.get().get_max_file_size_mib());
+        hash_change_detection_max_size.set(settings.get().get_hash_change_detection_max_size_gib());
+        hash_algorithm.set(settings.get().hash_algorithm);
+        hash_chunk_size.set(settings.get().get_hash_chunk_size_kib());
+        online_only_xattr.set(settings.get().online_only_xattr.clone());
+        online_only_hydration_budget.set(settings.get().get_online_only_hydration_budget_gib());
         max_concurrent_files.set(settings.get().max_concurrent_files);
+        slow_file_threshold.set(settings.get().slow_file_threshold);
+        parse_failures_max.set(settings.get().parse_failures_max);
+        index_xattrs.set(settings.get().index_xattrs);
+        follow_symlinks.set(settings.get().follow_symlinks);
+        cjk_analysis.set(settings.get().cjk_analysis);
+        content_languages.set(settings.get().content_languages.clone());
+        ocr_enabled.set(settings.get().ocr_enabled);
+        ocr_tesseract_path.set(settings.get().ocr_tesseract_path.clone());
+        ocr_languages.set(settings.get().ocr_languages.clone());
+        ocr_min_dimension.set(settings.get().ocr_min_dimension);
         elasticsearch_batch_size.set(settings.get().elasticsearch_batch_size);
+        elasticsearch_pit_keep_alive.set(settings.get().elasticsearch_pit_keep_alive);
+        elasticsearch_bulk_retries.set(settings.get().elasticsearch_bulk_retries);
         results_per_page.set(settings.get().results_per_page);
+        results_per_page_max.set(settings.get().results_per_page_max);
         knn_candidates_multiplier.set(settings.get().knn_candidates_multiplier);
+        recency_boost_scale_days.set(settings.get().recency_boost_scale_days);
+        auto_correct_max_hits.set(settings.get().auto_correct_max_hits);
+        auto_correct.set(settings.get().client.auto_correct);
+        field_weights_data
+            .modify()
+            .update_from_settings(settings.get().field_weights.clone());
         nn_server_address.set(settings.get().nn_server.nn_server_address);
         text_search_enabled.set(settings.get().nn_server.text_search_enabled);
         image_search_enabled.set(settings.get().nn_server.image_search_enabled);
@@ -186,18 +556,21 @@ pub fn Settings<'a, G: Html>(
         window_size.set(settings.get().nn_server.window_size);
         window_step.set(settings.get().nn_server.window_step);
         summary_len.set(settings.get().nn_server.summary_len);
+        summary_len_max.set(settings.get().nn_server.summary_len_max);
+        text_cache_capacity.set(settings.get().nn_server.text_cache_capacity);
     };
     let reset_settings = move |_| update_settings();
 
     // Load settings
+    let load_requests = RequestScope::new(cx);
     spawn_local_scoped(cx, async move {
-        status_dialog_state.set(StatusDialogState::Loading);
-
-        match get_settings().await {
+        let Some(res) = get_settings(load_requests).await else {
+            return;
+        };
+        match res {
             Ok(res) => {
                 settings.set(res);
                 update_settings();
-                status_dialog_state.set(StatusDialogState::None);
             }
             Err(e) => {
                 let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
@@ -208,30 +581,97 @@ pub fn Settings<'a, G: Html>(
         }
     });
 
+    // Load devices of models, neural network server may be not running
+    let devices_requests = RequestScope::new(cx);
+    spawn_local_scoped(cx, async move {
+        if let Some(Ok(res)) = get_nn_server_devices(devices_requests).await {
+            nn_devices.set(res.devices);
+        }
+    });
+
     // Save settings
     let set_settings = move |_| {
         spawn_local_scoped(cx, async move {
             status_dialog_state.set(StatusDialogState::Loading);
 
+            let interface = Interface {
+                theme: *theme.get(),
+                results_layout: *results_layout.get(),
+                thumbnails_enabled: *thumbnails_enabled.get(),
+            };
+            // Interface overridden in this browser doesn't change settings of server
+            let server_interface = if *interface_local.get() {
+                Interface::from_settings(&settings.get().client)
+            } else {
+                interface
+            };
             let new_settings = Settings {
                 indexer_address: *indexer_address.get(),
-                elasticsearch_url: (*elasticsearch_url.get()).clone(),
+                api_token: Some((*api_token.get()).clone()).filter(|x| !x.is_empty()),
+                elasticsearch_urls: parse_elasticsearch_urls(&elasticsearch_urls.get()).unwrap(),
+                elasticsearch_user: Some((*elasticsearch_user.get()).clone())
+                    .filter(|x| !x.is_empty()),
+                elasticsearch_password: Some((*elasticsearch_password.get()).clone())
+                    .filter(|x| !x.is_empty()),
+                elasticsearch_ca_cert_path: Some(&*elasticsearch_ca_cert_path.get())
+                    .filter(|x| !x.is_empty())
+                    .map(PathBuf::from),
                 tika_url: (*tika_url.get()).clone(),
                 nn_server_url: (*nn_server_url.get()).clone(),
                 open_on_start: *open_on_start.get(),
+                metrics_enabled: *metrics_enabled.get(),
+                file_actions_enabled: *file_actions_enabled.get(),
                 indexing_directories: indexing_directories
                     .get()
                     .iter()
                     .map(|f| f.dir.clone())
                     .collect(),
                 exclude_file_regex: (*exclude_file_regex.get()).clone(),
+                case_insensitive_paths: *case_insensitive_paths.get(),
+                skip_content_types: (*skip_content_types.get()).clone(),
+                content_type_overrides: parse_content_type_overrides(&content_type_overrides.get()),
+                index_archives: *index_archives.get(),
+                thumbnail_cache_enabled: *thumbnail_cache_enabled.get(),
+                thumbnail_cache_dir: PathBuf::from(&*thumbnail_cache_dir.get()),
+                thumbnail_cache_max_size: (*thumbnail_cache_max_size.get() * 1024.0 * 1024.0)
+                    as u64,
+                preview_chunk_size: (*preview_chunk_size.get() * 1024.0) as u64,
                 watcher_enabled: *watcher_enabled.get(),
                 debouncer_timeout: *debouncer_timeout.get(),
+                periodic_indexing_enabled: *periodic_indexing_enabled.get(),
+                periodic_indexing_interval_hours: *periodic_indexing_interval_hours.get(),
                 max_file_size: (*max_file_size.get() * 1024.0 * 1024.0) as u64,
+                hash_change_detection_max_size: (*hash_change_detection_max_size.get()
+                    * 1024.0
+                    * 1024.0
+                    * 1024.0) as u64,
+                hash_algorithm: *hash_algorithm.get(),
+                hash_chunk_size: (*hash_chunk_size.get() * 1024.0) as usize,
+                online_only_xattr: (*online_only_xattr.get()).clone(),
+                online_only_hydration_budget: (*online_only_hydration_budget.get()
+                    * 1024.0
+                    * 1024.0
+                    * 1024.0) as u64,
                 max_concurrent_files: *max_concurrent_files.get(),
+                slow_file_threshold: *slow_file_threshold.get(),
+                parse_failures_max: *parse_failures_max.get(),
+                index_xattrs: *index_xattrs.get(),
+                follow_symlinks: *follow_symlinks.get(),
+                cjk_analysis: *cjk_analysis.get(),
+                content_languages: (*content_languages.get()).clone(),
+                ocr_enabled: *ocr_enabled.get(),
+                ocr_tesseract_path: (*ocr_tesseract_path.get()).clone(),
+                ocr_languages: (*ocr_languages.get()).clone(),
+                ocr_min_dimension: *ocr_min_dimension.get(),
                 elasticsearch_batch_size: *elasticsearch_batch_size.get(),
+                elasticsearch_pit_keep_alive: *elasticsearch_pit_keep_alive.get(),
+                elasticsearch_bulk_retries: *elasticsearch_bulk_retries.get(),
                 results_per_page: *results_per_page.get(),
+                results_per_page_max: *results_per_page_max.get(),
                 knn_candidates_multiplier: *knn_candidates_multiplier.get(),
+                recency_boost_scale_days: *recency_boost_scale_days.get(),
+                auto_correct_max_hits: *auto_correct_max_hits.get(),
+                field_weights: field_weights_data.get().to_settings(),
                 nn_server: NNServerSettings {
                     nn_server_address: *nn_server_address.get(),
                     text_search_enabled: *text_search_enabled.get(),
@@ -245,8 +685,25 @@ pub fn Settings<'a, G: Html>(
                     window_size: *window_size.get(),
                     window_step: *window_step.get(),
                     summary_len: *summary_len.get(),
+                    summary_len_max: *summary_len_max.get(),
+                    text_cache_capacity: *text_cache_capacity.get(),
                 },
+                client: ClientSettings {
+                    wake_url: Url::parse(&wake_url.get()).ok(),
+                    prefetch_next_page: *prefetch_next_page.get(),
+                    prefetch_with_reranking: *prefetch_with_reranking.get(),
+                    auto_correct: *auto_correct.get(),
+                    report_errors: *report_errors.get(),
+                    client_language: *client_language.get(),
+                    theme: server_interface.theme,
+                    results_layout: server_interface.results_layout,
+                    thumbnails_enabled: server_interface.thumbnails_enabled,
+                },
+                // Launcher settings are edited only in file
+                launcher: settings.get().launcher.clone(),
             };
+            let language_changed =
+                new_settings.client.client_language != settings.get().client.client_language;
 
             if let Err(e) = put_settings(&new_settings).await {
                 let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
@@ -256,13 +713,36 @@ pub fn Settings<'a, G: Html>(
                 return;
             }
 
+            if *interface_local.get() {
+                interface.save_local();
+            } else {
+                Interface::clear_local();
+            }
+            // Following requests are checked with new token
+            set_api_token(new_settings.api_token.clone());
             settings.set(new_settings);
             update_settings();
+            // Translation is loaded once on start
+            if language_changed {
+                _ = web_sys::window().unwrap().location().reload();
+                return;
+            }
             let saved_str = get_translation("settings_saved", None).to_string();
             status_dialog_state.set(StatusDialogState::Info(saved_str));
         })
     };
 
+    // Panic deliberately to check error reporting, only in debug builds
+    let test_error_button = if cfg!(debug_assertions) {
+        view! { cx,
+            button(type="button", on:click=|_| panic!("Test error")) {
+                (get_translation("trigger_test_error", None))
+            }
+        }
+    } else {
+        view! { cx, }
+    };
+
     view! { cx,
         div(class="main_container") {
             main {
@@ -278,6 +758,15 @@ pub fn Settings<'a, G: Html>(
                             status_dialog_state=status_dialog_state)
                         SimpleTextSetting(id="exclude_file_regex",
                             label=get_translation("exclude_file_regex", None), value=exclude_file_regex)
+                        CheckboxSetting(id="case_insensitive_paths",
+                            label=get_translation("case_insensitive_paths", None), value=case_insensitive_paths)
+                        StringList(id="skip_content_types",
+                            label=get_translation("skip_content_types", None).to_string(), list=skip_content_types)
+                        StringList(id="content_type_overrides",
+                            label=get_translation("content_type_overrides", None).to_string(),
+                            list=content_type_overrides)
+                        CheckboxSetting(id="index_archives", label=get_translation("index_archives", None),
+                            value=index_archives)
                     }
 
                     fieldset {
@@ -285,17 +774,51 @@ pub fn Settings<'a, G: Html>(
                         TextSetting(id="indexer_address", label=get_translation("indexer_address", None),
                             parse=SocketAddr::from_str,
                             value=indexer_address, valid=indexer_address_valid)
-                        TextSetting(id="elasticsearch_url", label=get_translation("elasticsearch_url", None),
-                            parse=Url::parse,
-                            value=elasticsearch_url, valid=elasticsearch_url_valid)
+                        SimpleTextSetting(id="api_token",
+                            label=get_translation("api_token", None), value=api_token)
+                        TextSetting(id="elasticsearch_urls", label=get_translation("elasticsearch_urls", None),
+                            parse=|s: &str| parse_elasticsearch_urls(s).map(|_| s.to_owned()),
+                            value=elasticsearch_urls, valid=elasticsearch_urls_valid)
+                        SimpleTextSetting(id="elasticsearch_user",
+                            label=get_translation("elasticsearch_user", None), value=elasticsearch_user)
+                        SimpleTextSetting(id="elasticsearch_password",
+                            label=get_translation("elasticsearch_password", None), value=elasticsearch_password)
+                        SimpleTextSetting(id="elasticsearch_ca_cert_path",
+                            label=get_translation("elasticsearch_ca_cert_path", None), value=elasticsearch_ca_cert_path)
                         TextSetting(id="tika_url", label=get_translation("tika_url", None),
                             parse=Url::parse,
                             value=tika_url, valid=tika_url_valid)
                         TextSetting(id="nn_server_url", label=get_translation("nn_server_url", None),
                             parse=Url::parse,
                             value=nn_server_url, valid=nn_server_url_valid)
+                        TextSetting(id="wake_url", label=get_translation("wake_url", None),
+                            parse=parse_wake_url,
+                            value=wake_url, valid=wake_url_valid)
                         CheckboxSetting(id="open_on_start", label=get_translation("open_on_start", None),
                             value=open_on_start)
+                        CheckboxSetting(id="metrics_enabled", label=get_translation("metrics_enabled", None),
+                            value=metrics_enabled)
+                        CheckboxSetting(id="file_actions_enabled",
+                            label=get_translation("file_actions_enabled", None), value=file_actions_enabled)
+                        CheckboxSetting(id="report_errors", label=get_translation("report_errors", None),
+                            value=report_errors)
+                        SelectSetting(id="client_language".to_owned(),
+                            label=get_translation("client_language", None),
+                            options=client_language_options, value=client_language)
+                        (test_error_button)
+                    }
+
+                    fieldset {
+                        legend { (get_translation("interface_settings", None)) }
+                        SelectSetting(id="theme".to_owned(), label=get_translation("theme", None),
+                            options=theme_options, value=theme)
+                        SelectSetting(id="results_layout".to_owned(),
+                            label=get_translation("results_layout", None),
+                            options=results_layout_options, value=results_layout)
+                        CheckboxSetting(id="thumbnails_enabled",
+                            label=get_translation("thumbnails_enabled", None), value=thumbnails_enabled)
+                        CheckboxSetting(id="interface_local",
+                            label=get_translation("interface_local", None), value=interface_local)
                     }
 
                     fieldset {
@@ -306,18 +829,88 @@ pub fn Settings<'a, G: Html>(
                             label=get_translation("debouncer_timeout", None),
                             min=DEBOUNCER_TIMEOUT_MIN, max=DEBOUNCER_TIMEOUT_MAX,
                             value=debouncer_timeout, valid=debouncer_timeout_valid)
+                        CheckboxSetting(id="periodic_indexing_enabled",
+                            label=get_translation("periodic_indexing_enabled", None),
+                            value=periodic_indexing_enabled)
+                        NumberSetting(id="periodic_indexing_interval_hours".to_owned(),
+                            label=get_translation("periodic_indexing_interval_hours", None),
+                            min=PERIODIC_INDEXING_INTERVAL_HOURS_MIN, max=PERIODIC_INDEXING_INTERVAL_HOURS_MAX,
+                            value=periodic_indexing_interval_hours, valid=periodic_indexing_interval_hours_valid)
                         NumberSetting(id="max_file_size".to_owned(),
                             label=get_translation("max_file_size", None),
                             min=MAX_FILE_SIZE_MIN, max=MAX_FILE_SIZE_MAX,
                             value=max_file_size, valid=max_file_size_valid)
+                        NumberSetting(id="hash_change_detection_max_size".to_owned(),
+                            label=get_translation("hash_change_detection_max_size", None),
+                            min=HASH_CHANGE_DETECTION_MAX_SIZE_MIN, max=HASH_CHANGE_DETECTION_MAX_SIZE_MAX,
+                            value=hash_change_detection_max_size, valid=hash_change_detection_max_size_valid)
+                        SelectSetting(id="hash_algorithm".to_owned(),
+                            label=get_translation("hash_algorithm", None),
+                            options=hash_algorithm_options, value=hash_algorithm)
+                        NumberSetting(id="hash_chunk_size".to_owned(),
+                            label=get_translation("hash_chunk_size", None),
+                            min=HASH_CHUNK_SIZE_MIN, max=HASH_CHUNK_SIZE_MAX,
+                            value=hash_chunk_size, valid=hash_chunk_size_valid)
+                        SimpleTextSetting(id="online_only_xattr",
+                            label=get_translation("online_only_xattr", None), value=online_only_xattr)
+                        NumberSetting(id="online_only_hydration_budget".to_owned(),
+                            label=get_translation("online_only_hydration_budget", None),
+                            min=ONLINE_ONLY_HYDRATION_BUDGET_MIN, max=ONLINE_ONLY_HYDRATION_BUDGET_MAX,
+                            value=online_only_hydration_budget, valid=online_only_hydration_budget_valid)
                         NumberSetting(id="max_concurrent_files".to_owned(),
                             label=get_translation("max_concurrent_files", None),
                             min=MAX_CONCURRENT_FILES_MIN, max=MAX_CONCURRENT_FILES_MAX,
                             value=max_concurrent_files, valid=max_concurrent_files_valid)
+                        NumberSetting(id="slow_file_threshold".to_owned(),
+                            label=get_translation("slow_file_threshold", None),
+                            min=SLOW_FILE_THRESHOLD_MIN, max=SLOW_FILE_THRESHOLD_MAX,
+                            value=slow_file_threshold, valid=slow_file_threshold_valid)
+                        NumberSetting(id="parse_failures_max".to_owned(),
+                            label=get_translation("parse_failures_max", None),
+                            min=PARSE_FAILURES_MAX_MIN, max=PARSE_FAILURES_MAX_MAX,
+                            value=parse_failures_max, valid=parse_failures_max_valid)
+                        CheckboxSetting(id="index_xattrs", label=get_translation("index_xattrs", None),
+                            value=index_xattrs)
+                        CheckboxSetting(id="follow_symlinks", label=get_translation("follow_symlinks", None),
+                            value=follow_symlinks)
+                        CheckboxSetting(id="cjk_analysis", label=get_translation("cjk_analysis", None),
+                            value=cjk_analysis)
+                        StringList(id="content_languages",
+                            label=get_translation("content_languages", None).to_string(), list=content_languages)
+                        CheckboxSetting(id="ocr_enabled", label=get_translation("ocr_enabled", None),
+                            value=ocr_enabled)
+                        SimpleTextSetting(id="ocr_tesseract_path",
+                            label=get_translation("ocr_tesseract_path", None), value=ocr_tesseract_path)
+                        SimpleTextSetting(id="ocr_languages",
+                            label=get_translation("ocr_languages", None), value=ocr_languages)
+                        NumberSetting(id="ocr_min_dimension".to_owned(),
+                            label=get_translation("ocr_min_dimension", None),
+                            min=OCR_MIN_DIMENSION_MIN, max=OCR_MIN_DIMENSION_MAX,
+                            value=ocr_min_dimension, valid=ocr_min_dimension_valid)
+                        CheckboxSetting(id="thumbnail_cache_enabled",
+                            label=get_translation("thumbnail_cache_enabled", None), value=thumbnail_cache_enabled)
+                        SimpleTextSetting(id="thumbnail_cache_dir",
+                            label=get_translation("thumbnail_cache_dir", None), value=thumbnail_cache_dir)
+                        NumberSetting(id="thumbnail_cache_max_size".to_owned(),
+                            label=get_translation("thumbnail_cache_max_size", None),
+                            min=THUMBNAIL_CACHE_MAX_SIZE_MIN, max=THUMBNAIL_CACHE_MAX_SIZE_MAX,
+                            value=thumbnail_cache_max_size, valid=thumbnail_cache_max_size_valid)
+                        NumberSetting(id="preview_chunk_size".to_owned(),
+                            label=get_translation("preview_chunk_size", None),
+                            min=PREVIEW_CHUNK_SIZE_MIN, max=PREVIEW_CHUNK_SIZE_MAX,
+                            value=preview_chunk_size, valid=preview_chunk_size_valid)
                         NumberSetting(id="elasticsearch_batch_size".to_owned(),
                             label=get_translation("elasticsearch_batch_size", None),
                             min=ELASTICSEARCH_BATCH_SIZE_MIN, max=ELASTICSEARCH_BATCH_SIZE_MAX,
                             value=elasticsearch_batch_size, valid=elasticsearch_batch_size_valid)
+                        NumberSetting(id="elasticsearch_pit_keep_alive".to_owned(),
+                            label=get_translation("elasticsearch_pit_keep_alive", None),
+                            min=ELASTICSEARCH_PIT_KEEP_ALIVE_MIN, max=ELASTICSEARCH_PIT_KEEP_ALIVE_MAX,
+                            value=elasticsearch_pit_keep_alive, valid=elasticsearch_pit_keep_alive_valid)
+                        NumberSetting(id="elasticsearch_bulk_retries".to_owned(),
+                            label=get_translation("elasticsearch_bulk_retries", None),
+                            min=ELASTICSEARCH_BULK_RETRIES_MIN, max=ELASTICSEARCH_BULK_RETRIES_MAX,
+                            value=elasticsearch_bulk_retries, valid=elasticsearch_bulk_retries_valid)
                     }
 
                     fieldset {
@@ -326,10 +919,30 @@ pub fn Settings<'a, G: Html>(
                             label=get_translation("results_per_page", None),
                             min=RESULTS_PER_PAGE_MIN, max=RESULTS_PER_PAGE_MAX,
                             value=results_per_page, valid=results_per_page_valid)
+                        NumberSetting(id="results_per_page_max".to_owned(),
+                            label=get_translation("results_per_page_max", None),
+                            min=RESULTS_PER_PAGE_MIN, max=RESULTS_PER_PAGE_MAX,
+                            value=results_per_page_max, valid=results_per_page_max_valid)
                         NumberSetting(id="knn_candidates_multiplier".to_owned(),
                             label=get_translation("knn_candidates_multiplier", None),
                             min=KNN_CANDIDATES_MULTIPLIER_MIN, max=KNN_CANDIDATES_MULTIPLIER_MAX,
                             value=knn_candidates_multiplier, valid=knn_candidates_multiplier_valid)
+                        NumberSetting(id="recency_boost_scale_days".to_owned(),
+                            label=get_translation("recency_boost_scale_days", None),
+                            min=RECENCY_BOOST_SCALE_DAYS_MIN, max=RECENCY_BOOST_SCALE_DAYS_MAX,
+                            value=recency_boost_scale_days, valid=recency_boost_scale_days_valid)
+                        CheckboxSetting(id="prefetch_next_page", label=get_translation("prefetch_next_page", None),
+                            value=prefetch_next_page)
+                        CheckboxSetting(id="prefetch_with_reranking",
+                            label=get_translation("prefetch_with_reranking", None),
+                            value=prefetch_with_reranking)
+                        CheckboxSetting(id="auto_correct", label=get_translation("auto_correct_default", None),
+                            value=auto_correct)
+                        NumberSetting(id="auto_correct_max_hits".to_owned(),
+                            label=get_translation("auto_correct_max_hits", None),
+                            min=AUTO_CORRECT_MAX_HITS_MIN, max=AUTO_CORRECT_MAX_HITS_MAX,
+                            value=auto_correct_max_hits, valid=auto_correct_max_hits_valid)
+                        FieldWeightsSetting(data=field_weights_data)
                     }
 
                     fieldset {
@@ -343,10 +956,14 @@ pub fn Settings<'a, G: Html>(
                             value=image_search_enabled)
                         CheckboxSetting(id="reranking_enabled", label=get_translation("reranking_enabled", None),
                             value=reranking_enabled)
-                        NNSetting(id="clip_image", label=get_translation("clip_image", None), data=clip_image_data)
-                        NNSetting(id="clip_text", label=get_translation("clip_text", None), data=clip_text_data)
-                        NNSetting(id="minilm_text", label=get_translation("minilm_text", None), data=minilm_text_data)
-                        NNSetting(id="minilm_rerank", label=get_translation("minilm_rerank", None), data=minilm_rerank_data)
+                        NNSetting(id="clip_image", label=get_translation("clip_image", None), data=clip_image_data,
+                            has_embeddings=true, devices=nn_devices)
+                        NNSetting(id="clip_text", label=get_translation("clip_text", None), data=clip_text_data,
+                            has_embeddings=true, devices=nn_devices)
+                        NNSetting(id="minilm_text", label=get_translation("minilm_text", None), data=minilm_text_data,
+                            has_embeddings=true, devices=nn_devices)
+                        NNSetting(id="minilm_rerank", label=get_translation("minilm_rerank", None), data=minilm_rerank_data,
+                            has_embeddings=false, devices=nn_devices)
                         NumberSetting(id="max_sentences".to_owned(),
                             label=get_translation("max_sentences", None),
                             min=MAX_SENTENCES_MIN, max=MAX_SENTENCES_MAX,
@@ -363,11 +980,22 @@ pub fn Settings<'a, G: Html>(
                             label=get_translation("summary_len", None),
                             min=SUMMARY_LEN_MIN, max=SUMMARY_LEN_MAX,
                             value=summary_len, valid=summary_len_valid)
+                        NumberSetting(id="summary_len_max".to_owned(),
+                            label=get_translation("summary_len_max", None),
+                            min=SUMMARY_LEN_MIN, max=SUMMARY_LEN_MAX,
+                            value=summary_len_max, valid=summary_len_max_valid)
+                        NumberSetting(id="text_cache_capacity".to_owned(),
+                            label=get_translation("text_cache_capacity", None),
+                            min=TEXT_CACHE_CAPACITY_MIN, max=TEXT_CACHE_CAPACITY_MAX,
+                            value=text_cache_capacity, valid=text_cache_capacity_valid)
                     }
 
                     div(class="settings_buttons") {
+                        LoadingIndicator(loading=load_requests.loading())
                         button(type="button", on:click=reset_settings) { (get_translation("cancel", None)) }
-                        button(type="submit", disabled=*any_invalid.get()) { (get_translation("save", None)) }
+                        button(type="submit", disabled=*any_invalid.get() || *load_requests.loading().get()) {
+                            (get_translation("save", None))
+                        }
                     }
                 }
             }
@@ -1,13 +1,18 @@
 use std::{
+    collections::BTreeMap,
     fmt::{Debug, Display},
     hash::Hash,
     ops::DerefMut,
+    path::PathBuf,
     str::FromStr,
 };
 
 use common_lib::{
     actions::PickFolderResult,
-    settings::{IndexingDirectory, NNDevice, NNSettings},
+    settings::{
+        ChangeDetection, ContentTypePreset, FieldWeights, IndexingDirectory, NNDevice, NNSettings,
+        FIELD_WEIGHT_MAX, FIELD_WEIGHT_MIN, SEARCH_TEXT_FIELDS,
+    },
 };
 use fluent_bundle::{FluentArgs, FluentValue};
 use sycamore::{futures::spawn_local_scoped, prelude::*};
@@ -15,8 +20,16 @@ use uuid::Uuid;
 use wasm_bindgen::JsValue;
 
 use crate::{
-    app::{fetch, get_translation, widgets::StatusDialogState},
-    settings::{BATCH_SIZE_MAX, BATCH_SIZE_MIN, MAX_DELAY_MS_MAX, MAX_DELAY_MS_MIN},
+    app::{
+        get_translation,
+        widgets::{LoadingIndicator, StatusDialogState},
+        RequestScope,
+    },
+    settings::{
+        BATCH_SIZE_MAX, BATCH_SIZE_MIN, EMBEDDING_DIM_MAX, EMBEDDING_DIM_MIN,
+        IDLE_TIMEOUT_MINS_MAX, IDLE_TIMEOUT_MINS_MIN, MAX_DELAY_MS_MAX, MAX_DELAY_MS_MIN,
+        MAX_QUEUE_MAX, MAX_QUEUE_MIN,
+    },
 };
 
 #[derive(Prop)]
@@ -220,8 +233,13 @@ impl DirectoryItem {
     }
 }
 
-async fn pick_folder() -> Result<PickFolderResult, JsValue> {
-    fetch("/pick_folder", "POST", None::<&()>).await
+/// Concurrency limit of directory, empty or invalid value means global limit
+fn parse_max_concurrency(s: &str) -> Option<usize> {
+    s.trim().parse().ok().filter(|x| *x > 0)
+}
+
+async fn pick_folder(requests: RequestScope<'_>) -> Option<Result<PickFolderResult, JsValue>> {
+    requests.fetch("/pick_folder", "POST", None::<&()>).await
 }
 
 #[component(inline_props)]
@@ -233,6 +251,11 @@ pub fn DirectoryList<'a, G: Html>(
     let curr_directory = create_signal(cx, IndexingDirectory::default());
     let curr_directory_exclude_str = create_signal(cx, "false".to_owned());
     let curr_directory_watch = create_signal(cx, false);
+    let curr_directory_change_detection_str =
+        create_signal(cx, ChangeDetection::default().to_string());
+    let curr_directory_hash_always = create_signal(cx, false);
+    let curr_directory_exclude_regex = create_signal(cx, String::new());
+    let curr_directory_max_concurrency = create_signal(cx, String::new());
     let curr_directory_empty = create_memo(cx, || curr_directory.get().path.as_os_str().is_empty());
 
     create_effect(cx, || {
@@ -241,10 +264,30 @@ pub fn DirectoryList<'a, G: Html>(
     create_effect(cx, || {
         curr_directory.modify().watch = *curr_directory_watch.get();
     });
+    create_effect(cx, || {
+        curr_directory.modify().change_detection =
+            curr_directory_change_detection_str.get().parse().unwrap();
+    });
+    create_effect(cx, || {
+        curr_directory.modify().hash_always = *curr_directory_hash_always.get();
+    });
+    create_effect(cx, || {
+        let exclude_regex = curr_directory_exclude_regex.get();
+        curr_directory.modify().exclude_regex =
+            (!exclude_regex.is_empty()).then(|| (*exclude_regex).clone());
+    });
+    create_effect(cx, || {
+        curr_directory.modify().max_concurrency =
+            parse_max_concurrency(&curr_directory_max_concurrency.get());
+    });
 
+    let pick_folder_requests = RequestScope::new(cx);
     let select_item = move |_| {
-        spawn_local_scoped(cx, async {
-            match pick_folder().await {
+        spawn_local_scoped(cx, async move {
+            let Some(res) = pick_folder(pick_folder_requests).await else {
+                return;
+            };
+            match res {
                 Ok(res) => {
                     if let Some(path) = res.path {
                         curr_directory.modify().path = path;
@@ -263,9 +306,18 @@ pub fn DirectoryList<'a, G: Html>(
     let add_item = |_| {
         let mut curr_dir = std::mem::take(curr_directory.modify().deref_mut());
         curr_dir.watch &= !curr_dir.exclude;
+        curr_dir.hash_always &= curr_dir.change_detection == ChangeDetection::Hash;
+        if curr_dir.exclude {
+            curr_dir.exclude_regex = None;
+            curr_dir.max_concurrency = None;
+        }
         directory_list.modify().push(DirectoryItem::new(curr_dir));
         curr_directory_exclude_str.set(curr_directory.get().exclude.to_string());
         curr_directory_watch.set(curr_directory.get().watch);
+        curr_directory_change_detection_str.set(curr_directory.get().change_detection.to_string());
+        curr_directory_hash_always.set(curr_directory.get().hash_always);
+        curr_directory_exclude_regex.set(String::new());
+        curr_directory_max_concurrency.set(String::new());
     };
 
     view! { cx,
@@ -277,11 +329,89 @@ pub fn DirectoryList<'a, G: Html>(
                     directory_list.modify().retain(|x| x.id != item.id);
                 };
 
+                // Preset of default content type filter, "custom" if it was set in settings file
+                let content_type = item.dir.default_filters.as_ref().and_then(|x| x.content_type.as_deref());
+                let is_custom = content_type.is_some_and(|x| ContentTypePreset::from_content_type(x).is_none());
+                let content_type_preset_str = create_signal(
+                    cx,
+                    match content_type {
+                        Some(x) => ContentTypePreset::from_content_type(x)
+                            .map_or_else(|| "custom".to_owned(), |x| x.to_string()),
+                        None => String::new(),
+                    },
+                );
+                let content_type_presets = View::new_fragment(
+                    ContentTypePreset::ALL
+                        .into_iter()
+                        .map(|x| {
+                            let text = get_translation(format!("content_type_preset_{x}"), None);
+                            let selected = *content_type_preset_str.get() == x.to_string();
+                            view! { cx, option(value=x.to_string(), selected=selected) { (text) } }
+                        })
+                        .collect(),
+                );
+                let change_content_type_preset = move |_| {
+                    let preset = content_type_preset_str.get();
+                    if *preset == "custom" {
+                        return;
+                    }
+                    let content_type = preset.parse::<ContentTypePreset>().ok().map(ContentTypePreset::content_type);
+                    let mut directory_list = directory_list.modify();
+                    let Some(x) = directory_list.iter_mut().find(|x| x.id == item.id) else {
+                        return;
+                    };
+                    let default_filters = x.dir.default_filters.get_or_insert_with(Default::default);
+                    default_filters.content_type = content_type;
+                    if *default_filters == Default::default() {
+                        x.dir.default_filters = None;
+                    }
+                };
+
+                // Regex is checked by indexer when settings are saved
+                let exclude_regex = create_signal(cx, item.dir.exclude_regex.clone().unwrap_or_default());
+                let change_exclude_regex = move |_| {
+                    let mut directory_list = directory_list.modify();
+                    if let Some(x) = directory_list.iter_mut().find(|x| x.id == item.id) {
+                        let exclude_regex = exclude_regex.get();
+                        x.dir.exclude_regex = (!exclude_regex.is_empty()).then(|| (*exclude_regex).clone());
+                    }
+                };
+
+                let max_concurrency = create_signal(cx, item.dir.max_concurrency.map(|x| x.to_string()).unwrap_or_default());
+                let change_max_concurrency = move |_| {
+                    let mut directory_list = directory_list.modify();
+                    if let Some(x) = directory_list.iter_mut().find(|x| x.id == item.id) {
+                        x.dir.max_concurrency = parse_max_concurrency(&max_concurrency.get());
+                    }
+                };
+
                 view! { cx,
                     div(class="setting") {
                         input(type="text", readonly=true, value=item.dir.path.display()) {}
                         p { (if item.dir.exclude { get_translation("excluded", None) } else { get_translation("included", None) }) }
                         p { (if item.dir.watch { get_translation("watching", None) } else { get_translation("not_watching", None) }) }
+                        p {
+                            (match (item.dir.change_detection, item.dir.hash_always) {
+                                (ChangeDetection::MtimeSize, _) => get_translation("change_detection_mtime_size", None),
+                                (ChangeDetection::Hash, false) => get_translation("change_detection_hash", None),
+                                (ChangeDetection::Hash, true) => get_translation("change_detection_hash_always", None),
+                            })
+                        }
+                        select(title=get_translation("content_type_preset", None), disabled=item.dir.exclude,
+                            bind:value=content_type_preset_str, on:change=change_content_type_preset) {
+                            option(value="") { (get_translation("content_type_preset_none", None)) }
+                            (content_type_presets)
+                            (if is_custom {
+                                view! { cx, option(value="custom", selected=true) { (get_translation("content_type_preset_custom", None)) } }
+                            } else {
+                                view! { cx, }
+                            })
+                        }
+                        input(type="text", placeholder=get_translation("exclude_regex", None), disabled=item.dir.exclude,
+                            bind:value=exclude_regex, on:change=change_exclude_regex) {}
+                        input(type="number", min=1, placeholder=get_translation("max_concurrency", None),
+                            title=get_translation("max_concurrency_hint", None), disabled=item.dir.exclude,
+                            bind:value=max_concurrency, on:change=change_max_concurrency) {}
                         button(type="button", on:click=delete_item) { "➖" }
                     }
                 }
@@ -291,6 +421,7 @@ pub fn DirectoryList<'a, G: Html>(
         div(class="setting") {
             input(type="text", readonly=true, value=curr_directory.get().path.display()) {}
             button(type="button", on:click=select_item) { (get_translation("select", None)) }
+            LoadingIndicator(loading=pick_folder_requests.loading())
             select(bind:value=curr_directory_exclude_str) {
                 option(selected=true, value="false") { (get_translation("include", None)) }
                 option(value="true") { (get_translation("exclude", None)) }
@@ -298,19 +429,83 @@ pub fn DirectoryList<'a, G: Html>(
             input(type="checkbox", id="curr_directory_watch", name="curr_directory_watch",
                 disabled=*curr_directory_exclude_str.get() == "true", bind:checked=curr_directory_watch)
             label(for="curr_directory_watch") { (get_translation("watch", None)) }
+            select(bind:value=curr_directory_change_detection_str) {
+                option(selected=true, value="mtime_size") { (get_translation("change_detection_mtime_size", None)) }
+                option(value="hash") { (get_translation("change_detection_hash", None)) }
+            }
+            input(type="checkbox", id="curr_directory_hash_always", name="curr_directory_hash_always",
+                disabled=*curr_directory_change_detection_str.get() != "hash", bind:checked=curr_directory_hash_always)
+            label(for="curr_directory_hash_always") { (get_translation("hash_always", None)) }
+            input(type="text", placeholder=get_translation("exclude_regex", None),
+                disabled=*curr_directory_exclude_str.get() == "true", bind:value=curr_directory_exclude_regex) {}
+            input(type="number", min=1, placeholder=get_translation("max_concurrency", None),
+                title=get_translation("max_concurrency_hint", None),
+                disabled=*curr_directory_exclude_str.get() == "true", bind:value=curr_directory_max_concurrency) {}
             button(type="button", on:click=add_item, disabled=*curr_directory_empty.get()) { "➕" }
         }
     }
 }
 
+/// Editable list of unique strings
+#[component(inline_props)]
+pub fn StringList<'a, G: Html>(
+    cx: Scope<'a>,
+    id: &'static str,
+    label: String,
+    list: &'a Signal<Vec<String>>,
+) -> View<G> {
+    let curr_item = create_signal(cx, String::new());
+    let curr_item_empty = create_memo(cx, || curr_item.get().trim().is_empty());
+
+    let add_item = |_| {
+        let item = curr_item.get().trim().to_owned();
+        if !list.get().contains(&item) {
+            list.modify().push(item);
+        }
+        curr_item.set(String::new());
+    };
+
+    view! { cx,
+        p { (label) }
+        Keyed(
+            iterable=list,
+            key=|item| item.clone(),
+            view=move |cx, item| {
+                let value = item.clone();
+                let delete_item = move |_| {
+                    list.modify().retain(|x| *x != item);
+                };
+
+                view! { cx,
+                    div(class="setting") {
+                        input(type="text", readonly=true, value=value) {}
+                        button(type="button", on:click=delete_item) { "➖" }
+                    }
+                }
+            }
+        )
+        div(class="setting") {
+            input(type="text", id=id, name=id, bind:value=curr_item) {}
+            button(type="button", on:click=add_item, disabled=*curr_item_empty.get()) { "➕" }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct NNSettingsData<'a> {
     device: &'a Signal<NNDevice>,
     batch_size: &'a Signal<usize>,
     max_delay_ms: &'a Signal<u64>,
+    max_queue: &'a Signal<usize>,
+    model_path: &'a Signal<String>,
+    embedding_dim: &'a Signal<usize>,
+    idle_timeout_mins: &'a Signal<u64>,
 
     batch_size_valid: &'a Signal<bool>,
     max_delay_ms_valid: &'a Signal<bool>,
+    max_queue_valid: &'a Signal<bool>,
+    embedding_dim_valid: &'a Signal<bool>,
+    idle_timeout_mins_valid: &'a Signal<bool>,
     pub any_invalid: &'a ReadSignal<bool>,
 }
 
@@ -318,16 +513,30 @@ impl<'a> NNSettingsData<'a> {
     pub fn new(cx: Scope<'a>, settings: &NNSettings) -> Self {
         let batch_size_valid = create_signal(cx, true);
         let max_delay_ms_valid = create_signal(cx, true);
+        let max_queue_valid = create_signal(cx, true);
+        let embedding_dim_valid = create_signal(cx, true);
+        let idle_timeout_mins_valid = create_signal(cx, true);
         let any_invalid = create_memo(cx, || {
-            !*batch_size_valid.get() || !*max_delay_ms_valid.get()
+            !*batch_size_valid.get()
+                || !*max_delay_ms_valid.get()
+                || !*max_queue_valid.get()
+                || !*embedding_dim_valid.get()
+                || !*idle_timeout_mins_valid.get()
         });
 
         Self {
             device: create_signal(cx, settings.device),
             batch_size: create_signal(cx, settings.batch_size),
             max_delay_ms: create_signal(cx, settings.max_delay_ms),
+            max_queue: create_signal(cx, settings.max_queue),
+            model_path: create_signal(cx, settings.model_path.to_string_lossy().into_owned()),
+            embedding_dim: create_signal(cx, settings.embedding_dim),
+            idle_timeout_mins: create_signal(cx, settings.idle_timeout_mins),
             batch_size_valid,
             max_delay_ms_valid,
+            max_queue_valid,
+            embedding_dim_valid,
+            idle_timeout_mins_valid,
             any_invalid,
         }
     }
@@ -337,6 +546,10 @@ impl<'a> NNSettingsData<'a> {
             device: *self.device.get(),
             batch_size: *self.batch_size.get(),
             max_delay_ms: *self.max_delay_ms.get(),
+            max_queue: *self.max_queue.get(),
+            model_path: PathBuf::from(&*self.model_path.get()),
+            embedding_dim: *self.embedding_dim.get(),
+            idle_timeout_mins: *self.idle_timeout_mins.get(),
         }
     }
 
@@ -344,6 +557,11 @@ impl<'a> NNSettingsData<'a> {
         self.device.set(settings.device);
         self.batch_size.set(settings.batch_size);
         self.max_delay_ms.set(settings.max_delay_ms);
+        self.max_queue.set(settings.max_queue);
+        self.model_path
+            .set(settings.model_path.to_string_lossy().into_owned());
+        self.embedding_dim.set(settings.embedding_dim);
+        self.idle_timeout_mins.set(settings.idle_timeout_mins);
     }
 }
 
@@ -353,6 +571,10 @@ pub fn NNSetting<'a, S, G>(
     id: &'static str,
     label: S,
     data: &'a Signal<NNSettingsData<'a>>,
+    /// Show dimension of embeddings, reranking model doesn't calculate them
+    has_embeddings: bool,
+    /// Devices that models of neural network server are running on
+    devices: &'a ReadSignal<BTreeMap<String, NNDevice>>,
 ) -> View<G>
 where
     S: 'static + AsRef<str>,
@@ -371,15 +593,145 @@ where
     let label_device = get_translation("nn_setting_device", Some(&label_args)).to_string();
     let label_batch_size = get_translation("nn_setting_batch_size", Some(&label_args)).to_string();
     let label_max_delay = get_translation("nn_setting_max_delay", Some(&label_args)).to_string();
+    let label_max_queue = get_translation("nn_setting_max_queue", Some(&label_args)).to_string();
+    let label_model_path = get_translation("nn_setting_model_path", Some(&label_args)).to_string();
+    let label_embedding_dim =
+        get_translation("nn_setting_embedding_dim", Some(&label_args)).to_string();
+    let label_idle_timeout =
+        get_translation("nn_setting_idle_timeout", Some(&label_args)).to_string();
 
     view! { cx,
         SelectSetting(id=id.to_owned() + "_device", label=label_device,
             options=device_options, value=data.get().device)
+        (match devices.get().get(id) {
+            // Server fell back to CPU, configured device couldn't be used
+            Some(&device) if device != *data.get().device.get() => {
+                let device_id = match device {
+                    NNDevice::CPU => "cpu",
+                    NNDevice::CUDA => "cuda",
+                };
+                let device_name = get_translation(device_id, None).to_string();
+                let device_args = FluentArgs::from_iter([("device", device_name)]);
+                let effective_device =
+                    get_translation("nn_setting_effective_device", Some(&device_args)).to_string();
+                view! { cx,
+                    div(class="setting") {
+                        p { (effective_device) }
+                    }
+                }
+            }
+            _ => view! { cx, },
+        })
         NumberSetting(id=id.to_owned() + "_batch_size", label=label_batch_size,
             min=BATCH_SIZE_MIN, max=BATCH_SIZE_MAX,
             value=data.get().batch_size, valid=data.get().batch_size_valid)
         NumberSetting(id=id.to_owned() + "_max_delay", label=label_max_delay,
             min=MAX_DELAY_MS_MIN, max=MAX_DELAY_MS_MAX,
             value=data.get().max_delay_ms, valid=data.get().max_delay_ms_valid)
+        NumberSetting(id=id.to_owned() + "_max_queue", label=label_max_queue,
+            min=MAX_QUEUE_MIN, max=MAX_QUEUE_MAX,
+            value=data.get().max_queue, valid=data.get().max_queue_valid)
+        div(class="setting") {
+            label(for=format!("{id}_model_path")) { (label_model_path) }
+            input(type="text", id=format!("{id}_model_path"), name=format!("{id}_model_path"),
+                bind:value=data.get().model_path) {}
+        }
+        (if has_embeddings {
+            let label_embedding_dim = label_embedding_dim.clone();
+            view! { cx,
+                NumberSetting(id=id.to_owned() + "_embedding_dim", label=label_embedding_dim,
+                    min=EMBEDDING_DIM_MIN, max=EMBEDDING_DIM_MAX,
+                    value=data.get().embedding_dim, valid=data.get().embedding_dim_valid)
+            }
+        } else {
+            view! { cx, }
+        })
+        NumberSetting(id=id.to_owned() + "_idle_timeout", label=label_idle_timeout,
+            min=IDLE_TIMEOUT_MINS_MIN, max=IDLE_TIMEOUT_MINS_MAX,
+            value=data.get().idle_timeout_mins, valid=data.get().idle_timeout_mins_valid)
+    }
+}
+
+struct FieldWeightData<'a> {
+    field: &'static str,
+    weight: &'a Signal<f32>,
+    valid: &'a Signal<bool>,
+}
+
+pub struct FieldWeightsData<'a> {
+    weights: Vec<FieldWeightData<'a>>,
+    pub any_invalid: &'a ReadSignal<bool>,
+}
+
+impl<'a> FieldWeightsData<'a> {
+    pub fn new(cx: Scope<'a>, settings: &FieldWeights) -> Self {
+        let weights: Vec<_> = SEARCH_TEXT_FIELDS
+            .into_iter()
+            .map(|field| FieldWeightData {
+                field,
+                weight: create_signal(cx, settings.get(field)),
+                valid: create_signal(cx, true),
+            })
+            .collect();
+        let valid: Vec<_> = weights.iter().map(|x| x.valid).collect();
+        let any_invalid = create_memo(cx, move || valid.iter().any(|x| !*x.get()));
+
+        Self {
+            weights,
+            any_invalid,
+        }
+    }
+
+    pub fn to_settings(&self) -> FieldWeights {
+        FieldWeights(
+            self.weights
+                .iter()
+                .map(|x| (x.field.to_owned(), *x.weight.get()))
+                .collect(),
+        )
+    }
+
+    pub fn update_from_settings(&mut self, settings: FieldWeights) {
+        for x in &self.weights {
+            x.weight.set(settings.get(x.field));
+        }
     }
 }
+
+/// Translation of name of field searched by text query
+fn field_name_id(field: &str) -> String {
+    match field {
+        "path" => "filter_file_path".to_owned(),
+        "content" => "content_search".to_owned(),
+        "image_make" => "filter_device_manufacturer".to_owned(),
+        "image_model" => "filter_device_model".to_owned(),
+        _ => format!("filter_{field}"),
+    }
+}
+
+#[component(inline_props)]
+pub fn FieldWeightsSetting<'a, G: Html>(
+    cx: Scope<'a>,
+    data: &'a Signal<FieldWeightsData<'a>>,
+) -> View<G> {
+    View::new_fragment(
+        data.get()
+            .weights
+            .iter()
+            .map(|x| {
+                let (weight, valid) = (x.weight, x.valid);
+                let field_args = FluentArgs::from_iter([(
+                    "field",
+                    get_translation(field_name_id(x.field), None).to_string(),
+                )]);
+                let label = get_translation("field_weight", Some(&field_args)).to_string();
+
+                view! { cx,
+                    NumberSetting(id=format!("field_weight_{}", x.field), label=label,
+                        min=FIELD_WEIGHT_MIN, max=FIELD_WEIGHT_MAX,
+                        value=weight, valid=valid)
+                }
+            })
+            .collect(),
+    )
+}
@@ -1,16 +1,53 @@
-use common_lib::indexer::{IndexStats, IndexingStatus, IndexingWSMessage, MAX_ERROR_CNT};
+use std::{collections::VecDeque, time::Duration};
+
+use chrono::{DateTime, Local};
+use common_lib::{
+    client_error::ClientError,
+    indexer::{
+        DeleteIndexRequest, DeleteToken, DirectoryStats, FileError, FileErrorsPage,
+        IndexSchemaStatus, IndexStats, IndexingEstimate, IndexingEstimateRequest, IndexingStatus,
+        IndexingWSConfig, IndexingWSMessage, WatcherEvent, WatcherEventKind, FILE_ERRORS_PAGE_SIZE,
+        MAX_ERROR_CNT, MAX_WATCHER_EVENT_CNT,
+    },
+};
 use fluent_bundle::FluentArgs;
-use futures::StreamExt;
+use futures::{channel::mpsc, StreamExt};
 use gloo_net::websocket::{futures::WebSocket, Message};
 use sycamore::{futures::spawn_local_scoped, prelude::*};
 use url::Url;
 use wasm_bindgen::JsValue;
 
 use crate::{
-    app::{fetch_empty, get_translation, widgets::StatusDialogState},
+    app::{
+        add_api_token, fetch, fetch_empty, get_translation,
+        widgets::{LoadingIndicator, StatusDialogState},
+        RequestScope,
+    },
     formatting::{duration_str_from_seconds, file_size_str},
+    settings::widgets::{CheckboxSetting, NumberSetting},
 };
 
+const ESTIMATE_SAMPLE_PERCENT_MIN: f64 = 0.01;
+const ESTIMATE_SAMPLE_PERCENT_MAX: f64 = 100.0;
+
+/// Names and durations of processing stages, the slowest first
+fn stage_durations_str(stages: &[(String, Duration)]) -> String {
+    let mut stages: Vec<_> = stages.iter().filter(|(_, x)| !x.is_zero()).collect();
+    stages.sort_by(|a, b| b.1.cmp(&a.1));
+    stages
+        .into_iter()
+        .map(|(name, duration)| {
+            let stage_str = get_translation(format!("stage_{name}"), None).to_string();
+            let stage_args = FluentArgs::from_iter([
+                ("stage", stage_str),
+                ("seconds", format!("{:.2}", duration.as_secs_f32())),
+            ]);
+            get_translation("stage_duration", Some(&stage_args)).to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 fn indexing_status_str(status: &IndexingStatus) -> String {
     match status {
         IndexingStatus::NotStarted | IndexingStatus::Finished(_) => {
@@ -29,24 +66,218 @@ fn indexing_status_str(status: &IndexingStatus) -> String {
     }
 }
 
-async fn index() -> Result<(), JsValue> {
-    fetch_empty("/index", "PATCH", None::<&()>).await
+async fn index(requests: RequestScope<'_>) -> Option<Result<(), JsValue>> {
+    requests.fetch_empty("/index", "PATCH", None::<&()>).await
+}
+
+async fn delete_token() -> Result<DeleteToken, JsValue> {
+    fetch("/index/delete_token", "GET", None::<&()>).await
+}
+
+async fn delete_index(delete_index_request: &DeleteIndexRequest) -> Result<(), JsValue> {
+    fetch_empty("/index", "DELETE", Some(delete_index_request)).await
+}
+
+/// Ask user to confirm deleting given number of documents
+fn confirm_delete(doc_cnt: u64) -> bool {
+    let confirm_args = FluentArgs::from_iter([("count", doc_cnt)]);
+    let confirm_str = get_translation("clear_index_confirm", Some(&confirm_args)).to_string();
+    web_sys::window()
+        .unwrap()
+        .confirm_with_message(&confirm_str)
+        .unwrap_or(false)
+}
+
+/// Ask user to confirm and clear index, showing progress and errors in status dialog.
+/// Returns whether index was cleared
+async fn clear_index(status_dialog_state: &Signal<StatusDialogState>) -> bool {
+    status_dialog_state.set(StatusDialogState::Loading);
+
+    let res = async {
+        let delete_token = delete_token().await?;
+        status_dialog_state.set(StatusDialogState::None);
+        if !confirm_delete(delete_token.doc_cnt) {
+            return Ok(false);
+        }
+
+        status_dialog_state.set(StatusDialogState::Loading);
+        delete_index(&DeleteIndexRequest {
+            token: delete_token.token,
+        })
+        .await
+        .map(|_| true)
+    }
+    .await;
+    match res {
+        Ok(cleared) => {
+            status_dialog_state.set(StatusDialogState::None);
+            cleared
+        }
+        Err(e) => {
+            let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+            let error_str = get_translation("index_clearing_error", Some(&error_args)).to_string();
+            status_dialog_state.set(StatusDialogState::Error(error_str));
+            false
+        }
+    }
+}
+
+async fn index_schema(requests: RequestScope<'_>) -> Option<Result<IndexSchemaStatus, JsValue>> {
+    requests.fetch("/index/schema", "GET", None::<&()>).await
+}
+
+async fn rebuild_index() -> Result<(), JsValue> {
+    fetch_empty("/index/rebuild", "POST", None::<&()>).await
+}
+
+/// Estimate of indexing, `None` if it was cancelled
+async fn estimate(
+    estimate_request: &IndexingEstimateRequest,
+) -> Result<Option<IndexingEstimate>, JsValue> {
+    fetch("/index/estimate", "POST", Some(estimate_request)).await
+}
+
+async fn cancel_estimate() -> Result<(), JsValue> {
+    fetch_empty("/index/estimate", "DELETE", None::<&()>).await
+}
+
+async fn directory_stats(
+    requests: RequestScope<'_>,
+) -> Option<Result<Vec<DirectoryStats>, JsValue>> {
+    requests
+        .fetch("/index/directories", "GET", None::<&()>)
+        .await
+}
+
+async fn client_errors(requests: RequestScope<'_>) -> Option<Result<Vec<ClientError>, JsValue>> {
+    requests.fetch("/client_errors", "GET", None::<&()>).await
+}
+
+/// Page of errors of files in the latest indexing
+async fn file_errors(
+    requests: RequestScope<'_>,
+    offset: usize,
+) -> Option<Result<FileErrorsPage, JsValue>> {
+    let uri = format!("/index/errors?run=latest&offset={offset}&limit={FILE_ERRORS_PAGE_SIZE}");
+    requests.fetch(&uri, "GET", None::<&()>).await
+}
+
+async fn retry_failed_files() -> Result<(), JsValue> {
+    fetch_empty("/index/errors/retry", "POST", None::<&()>).await
+}
+
+/// Warning shown on all tabs if index has to be cleared after upgrade or change of models
+#[component(inline_props)]
+pub fn IndexSchemaBanner<'a, G: Html>(
+    cx: Scope<'a>,
+    status_dialog_state: &'a Signal<StatusDialogState>,
+    index_schema: &'a Signal<IndexSchemaStatus>,
+) -> View<G> {
+    // Indexer may be not running yet, then banner isn't shown
+    let index_schema_requests = RequestScope::new(cx);
+    spawn_local_scoped(cx, async move {
+        if let Some(Ok(res)) = index_schema(index_schema_requests).await {
+            index_schema.set(res);
+        }
+    });
+
+    let clear = move |_| {
+        spawn_local_scoped(cx, async move {
+            if clear_index(status_dialog_state).await {
+                index_schema.set(IndexSchemaStatus::Compatible);
+            }
+        })
+    };
+
+    view! { cx,
+        (match &*index_schema.get() {
+            IndexSchemaStatus::Compatible => view! { cx, },
+            IndexSchemaStatus::Outdated { index_version, current_version, reason } => {
+                let outdated_args = FluentArgs::from_iter([
+                    ("index_version", index_version.to_string()),
+                    ("current_version", current_version.to_string()),
+                    ("reason", reason.to_owned()),
+                ]);
+                let outdated_str =
+                    get_translation("index_schema_outdated", Some(&outdated_args)).to_string();
+
+                view! { cx,
+                    div(id="index_schema_banner") {
+                        p { (outdated_str) }
+                        button(type="button", on:click=clear) { (get_translation("clear_index", None)) }
+                    }
+                }
+            }
+        })
+    }
 }
 
-async fn delete_index() -> Result<(), JsValue> {
-    fetch_empty("/index", "DELETE", None::<&()>).await
+/// Description of event of file system watcher and whether its indexing started
+fn watcher_event_str(time: &DateTime<Local>, event: &WatcherEvent) -> String {
+    let path = event
+        .path
+        .as_ref()
+        .map(|x| x.display().to_string())
+        .unwrap_or_default();
+    let event_args = FluentArgs::from_iter([("path", path)]);
+    let event_str = match event.kind {
+        WatcherEventKind::Changed => get_translation("watcher_event_changed", Some(&event_args)),
+        WatcherEventKind::Moved => get_translation("watcher_event_moved", Some(&event_args)),
+        WatcherEventKind::FullIndexing => get_translation("watcher_event_full_indexing", None),
+    };
+    let indexing_str = get_translation(
+        if event.queued {
+            "watcher_event_queued"
+        } else {
+            "watcher_event_coalesced"
+        },
+        None,
+    );
+    let item_args = FluentArgs::from_iter([
+        ("time", time.format("%H:%M:%S").to_string()),
+        ("event", event_str.to_string()),
+        ("indexing", indexing_str.to_string()),
+    ]);
+    get_translation("watcher_event", Some(&item_args)).to_string()
 }
 
 #[component(inline_props)]
 pub fn Status<'a, G: Html>(
     cx: Scope<'a>,
     status_dialog_state: &'a Signal<StatusDialogState>,
+    index_schema: &'a Signal<IndexSchemaStatus>,
 ) -> View<G> {
     let indexing_status = create_signal(cx, IndexingStatus::NotStarted);
     let index_stats = create_signal(cx, IndexStats::default());
 
     let is_indexing = create_memo(cx, || !indexing_status.get().can_start());
 
+    let estimate_sample_percent = create_signal(cx, 1.0);
+    let estimate_sample_percent_valid = create_signal(cx, true);
+    let is_estimating = create_signal(cx, false);
+    let estimate_cancelled = create_signal(cx, false);
+    let indexing_estimate = create_signal(cx, None);
+    let client_errors_list = create_signal(cx, None::<Vec<ClientError>>);
+    let directory_stats_list = create_signal(cx, Vec::<DirectoryStats>::new());
+    let file_errors_list = create_signal(cx, Vec::<FileError>::new());
+    // Number of all errors, `None` until the first page is loaded
+    let file_errors_total = create_signal(cx, None::<usize>);
+    let file_errors_open = create_signal(cx, false);
+    // The latest events of watcher, the oldest first
+    let watcher_events = create_signal(cx, VecDeque::<(DateTime<Local>, WatcherEvent)>::new());
+    let mute_watcher = create_signal(cx, false);
+
+    // Configuration messages are queued until WebSocket is opened
+    let (ws_config_tx, ws_config_rx) = mpsc::unbounded();
+    let ws_config_tx = create_ref(cx, ws_config_tx);
+    create_effect(cx, move || {
+        let config = IndexingWSConfig {
+            mute_watcher: *mute_watcher.get(),
+        };
+        // Channel is closed with WebSocket, then configuration doesn't matter
+        let _ = ws_config_tx.unbounded_send(config);
+    });
+
     spawn_local_scoped(cx, async move {
         status_dialog_state.set(StatusDialogState::Loading);
 
@@ -54,8 +285,15 @@ pub fn Status<'a, G: Html>(
             Url::parse(&web_sys::window().unwrap().location().origin().unwrap()).unwrap();
         ws_url.set_scheme("ws").unwrap();
         ws_url.set_path("/index");
+        add_api_token(&mut ws_url);
         let ws = WebSocket::open(ws_url.as_str()).unwrap();
-        let (_, mut ws_read) = ws.split();
+        let (ws_write, mut ws_read) = ws.split();
+        spawn_local_scoped(cx, async move {
+            let messages =
+                ws_config_rx.map(|x| Ok(Message::Text(serde_json::to_string(&x).unwrap())));
+            // Errors of WebSocket are reported by reading task
+            let _ = messages.forward(ws_write).await;
+        });
         spawn_local_scoped(cx, async move {
             if let Err(e) = async {
                 while let Some(msg) = ws_read.next().await {
@@ -68,6 +306,13 @@ pub fn Status<'a, G: Html>(
                                     indexing_status.modify().process_event(x)
                                 }
                                 IndexingWSMessage::IndexStats(x) => index_stats.set(x),
+                                IndexingWSMessage::WatcherEvent(x) => {
+                                    let mut events = watcher_events.modify();
+                                    events.push_back((Local::now(), x));
+                                    if events.len() > MAX_WATCHER_EVENT_CNT {
+                                        events.pop_front();
+                                    }
+                                }
                                 IndexingWSMessage::Error(e) => return Err(e),
                             }
                         }
@@ -86,42 +331,164 @@ pub fn Status<'a, G: Html>(
         });
     });
 
-    let index = move |_| {
+    // Counts by directory are reloaded with statistics of index, after each indexing
+    let directory_stats_requests = RequestScope::new(cx);
+    create_effect(cx, move || {
+        index_stats.track();
         spawn_local_scoped(cx, async move {
-            status_dialog_state.set(StatusDialogState::Loading);
+            match directory_stats(directory_stats_requests).await {
+                Some(Ok(x)) => directory_stats_list.set(x),
+                Some(Err(e)) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str =
+                        get_translation("directory_stats_loading_error", Some(&error_args))
+                            .to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
+                }
+                None => {}
+            }
+        });
+    });
 
-            match index().await {
-                Ok(_) => {
-                    status_dialog_state.set(StatusDialogState::None);
+    let client_errors_requests = RequestScope::new(cx);
+    let load_client_errors = move |_| {
+        spawn_local_scoped(cx, async move {
+            match client_errors(client_errors_requests).await {
+                Some(Ok(x)) => client_errors_list.set(Some(x)),
+                Some(Err(e)) => {
+                    let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                    let error_str =
+                        get_translation("client_errors_loading_error", Some(&error_args))
+                            .to_string();
+                    status_dialog_state.set(StatusDialogState::Error(error_str));
                 }
-                Err(e) => {
+                None => {}
+            }
+        });
+    };
+
+    // Pages of errors of files are loaded only when their list is opened
+    let file_errors_requests = RequestScope::new(cx);
+    let load_file_errors = move || {
+        spawn_local_scoped(cx, async move {
+            let offset = file_errors_list.get_untracked().len();
+            match file_errors(file_errors_requests, offset).await {
+                Some(Ok(page)) => {
+                    file_errors_list.modify().extend(page.errors);
+                    file_errors_total.set(Some(page.total));
+                }
+                Some(Err(e)) => {
                     let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
                     let error_str =
-                        get_translation("indexing_error", Some(&error_args)).to_string();
+                        get_translation("file_errors_loading_error", Some(&error_args)).to_string();
                     status_dialog_state.set(StatusDialogState::Error(error_str));
                 }
+                None => {}
+            }
+        });
+    };
+    let toggle_file_errors = move |_| {
+        file_errors_open.set(!*file_errors_open.get_untracked());
+        if *file_errors_open.get_untracked() && file_errors_total.get_untracked().is_none() {
+            load_file_errors();
+        }
+    };
+    // Loaded errors are of previous indexing after the next one starts or finishes
+    create_effect(cx, move || {
+        is_indexing.track();
+        file_errors_requests.cancel();
+        file_errors_list.set(Vec::new());
+        file_errors_total.set(None);
+        if *file_errors_open.get_untracked() {
+            load_file_errors();
+        }
+    });
+
+    let retry_failed_files = move |_| {
+        spawn_local_scoped(cx, async move {
+            if let Err(e) = retry_failed_files().await {
+                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                let error_str = get_translation("indexing_error", Some(&error_args)).to_string();
+                status_dialog_state.set(StatusDialogState::Error(error_str));
+            }
+        })
+    };
+
+    let index_requests = RequestScope::new(cx);
+    let index = move |_| {
+        spawn_local_scoped(cx, async move {
+            if let Some(Err(e)) = index(index_requests).await {
+                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                let error_str = get_translation("indexing_error", Some(&error_args)).to_string();
+                status_dialog_state.set(StatusDialogState::Error(error_str));
             }
         })
     };
 
     let delete_index = move |_| {
+        spawn_local_scoped(cx, async move {
+            if clear_index(status_dialog_state).await {
+                index_schema.set(IndexSchemaStatus::Compatible);
+            }
+        })
+    };
+
+    let rebuild_index = move |_| {
         spawn_local_scoped(cx, async move {
             status_dialog_state.set(StatusDialogState::Loading);
 
-            match delete_index().await {
+            match rebuild_index().await {
                 Ok(_) => {
                     status_dialog_state.set(StatusDialogState::None);
                 }
                 Err(e) => {
                     let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
                     let error_str =
-                        get_translation("index_clearing_error", Some(&error_args)).to_string();
+                        get_translation("index_rebuilding_error", Some(&error_args)).to_string();
                     status_dialog_state.set(StatusDialogState::Error(error_str));
                 }
             }
         })
     };
 
+    let start_estimate = move |_| {
+        spawn_local_scoped(cx, async move {
+            is_estimating.set(true);
+            estimate_cancelled.set(false);
+
+            let estimate_request = IndexingEstimateRequest {
+                sample_rate: *estimate_sample_percent.get() / 100.0,
+            };
+            match estimate(&estimate_request).await {
+                Ok(Some(x)) => {
+                    indexing_estimate.set(Some(x));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    if !*estimate_cancelled.get() {
+                        let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                        let error_str =
+                            get_translation("estimation_error", Some(&error_args)).to_string();
+                        status_dialog_state.set(StatusDialogState::Error(error_str));
+                    }
+                }
+            }
+            is_estimating.set(false);
+        })
+    };
+
+    let cancel_estimate = move |_| {
+        spawn_local_scoped(cx, async move {
+            estimate_cancelled.set(true);
+
+            if let Err(e) = cancel_estimate().await {
+                let error_args = FluentArgs::from_iter([("error", format!("{e:#?}"))]);
+                let error_str = get_translation("estimation_error", Some(&error_args)).to_string();
+                status_dialog_state.set(StatusDialogState::Error(error_str));
+            }
+        })
+    };
+
     view! { cx,
         div(class="main_container") {
             main {
@@ -141,6 +508,8 @@ pub fn Status<'a, G: Html>(
                         (match (*indexing_status.get()).clone() {
                             IndexingStatus::Indexing(data) | IndexingStatus::Finished(data) => {
                                 let errors = create_signal(cx, data.errors);
+                                let slow_files = create_signal(cx, data.slow_files);
+                                let stage_durations = create_signal(cx, data.stage_durations);
 
                                 let add_remove_update_args = FluentArgs::from_iter([("to_add", data.to_add), ("to_remove", data.to_remove), ("to_update", data.to_update)]);
                                 let add_remove_update_str = get_translation("indexing_add_remove_update", Some(&add_remove_update_args)).to_string();
@@ -148,9 +517,73 @@ pub fn Status<'a, G: Html>(
                                 let processed_sent_args = FluentArgs::from_iter([("processed", data.processed), ("sent", data.sent)]);
                                 let processed_sent_str = get_translation("indexing_processed_sent", Some(&processed_sent_args)).to_string();
 
+                                // Progress isn't shown if there are no files to process
+                                let is_processing = matches!(*indexing_status.get(), IndexingStatus::Indexing(_));
+                                let progress = data.progress().filter(|_| is_processing);
+
                                 view! { cx,
                                     p { (add_remove_update_str) }
                                     p { (processed_sent_str) }
+                                    (if let Some(progress) = progress {
+                                        let progress_args = FluentArgs::from_iter([("percent", format!("{:.1}", progress * 100.0))]);
+                                        let mut progress_str = get_translation("indexing_progress", Some(&progress_args)).to_string();
+                                        if let Some(rate) = data.rate {
+                                            let rate_args = FluentArgs::from_iter([("rate", format!("{rate:.1}"))]);
+                                            let rate_str = get_translation("indexing_rate", Some(&rate_args));
+                                            progress_str = format!("{progress_str}, {rate_str}");
+                                        }
+                                        let eta_str = data.eta.map(|eta| {
+                                            let eta_args = FluentArgs::from_iter([("duration", duration_str_from_seconds(eta.as_secs() as f32))]);
+                                            get_translation("indexing_eta", Some(&eta_args)).to_string()
+                                        });
+
+                                        view! { cx,
+                                            progress(max="1", value=progress.to_string()) {}
+                                            p { (progress_str) }
+                                            (if let Some(eta_str) = eta_str.clone() {
+                                                view! { cx, p { (eta_str) } }
+                                            } else {
+                                                view! { cx, }
+                                            })
+                                        }
+                                    } else {
+                                        view! { cx, }
+                                    })
+                                    (if data.failed_operations > 0 {
+                                        let failed_operations_args = FluentArgs::from_iter([("count", data.failed_operations)]);
+                                        let failed_operations_str = get_translation("indexing_failed_operations", Some(&failed_operations_args)).to_string();
+
+                                        view! { cx, p { (failed_operations_str) } }
+                                    } else {
+                                        view! { cx, }
+                                    })
+                                    (if data.changed > 0 {
+                                        let changed_args = FluentArgs::from_iter([("count", data.changed)]);
+                                        let changed_str = get_translation("indexing_files_changed", Some(&changed_args)).to_string();
+
+                                        view! { cx, p { (changed_str) } }
+                                    } else {
+                                        view! { cx, }
+                                    })
+                                    (if data.modified_in_future > 0 {
+                                        let modified_in_future_args = FluentArgs::from_iter([("count", data.modified_in_future)]);
+                                        let modified_in_future_str = get_translation("indexing_modified_in_future", Some(&modified_in_future_args)).to_string();
+
+                                        view! { cx, p { (modified_in_future_str) } }
+                                    } else {
+                                        view! { cx, }
+                                    })
+                                    (if data.hydration_size > 0 || data.online_only_deferred > 0 {
+                                        let hydration_args = FluentArgs::from_iter([
+                                            ("size", format!("{:.2}", data.hydration_size as f64 / 1024.0 / 1024.0)),
+                                            ("deferred", data.online_only_deferred.to_string()),
+                                        ]);
+                                        let hydration_str = get_translation("indexing_hydration", Some(&hydration_args)).to_string();
+
+                                        view! { cx, p { (hydration_str) } }
+                                    } else {
+                                        view! { cx, }
+                                    })
                                     (if let Some(duration) = data.duration {
                                         let duration_str = duration_str_from_seconds(duration.as_secs_f32());
                                         let elapsed_args = FluentArgs::from_iter([("duration", duration_str)]);
@@ -178,6 +611,43 @@ pub fn Status<'a, G: Html>(
                                     } else {
                                         view! { cx, }
                                     })
+                                    (if stage_durations.get().is_empty() {
+                                        view! { cx, }
+                                    } else {
+                                        let stages_args = FluentArgs::from_iter([("stages", stage_durations_str(&stage_durations.get()))]);
+                                        let stages_str = get_translation("indexing_stage_durations", Some(&stages_args)).to_string();
+
+                                        view! { cx, p { (stages_str) } }
+                                    })
+                                    (if slow_files.get().is_empty() {
+                                        view! { cx, }
+                                    } else {
+                                        let slow_files_args = FluentArgs::from_iter([("count", data.slow_files_cnt)]);
+                                        let slow_files_str = get_translation("indexing_slow_files", Some(&slow_files_args)).to_string();
+                                        let items = View::new_fragment(
+                                            slow_files
+                                                .get()
+                                                .iter()
+                                                .map(|x| {
+                                                    let slow_file_args = FluentArgs::from_iter([
+                                                        ("path", x.path.display().to_string()),
+                                                        ("seconds", format!("{:.1}", x.duration().as_secs_f32())),
+                                                        ("stages", stage_durations_str(&x.stages)),
+                                                    ]);
+                                                    let slow_file_str = get_translation("indexing_slow_file", Some(&slow_file_args)).to_string();
+
+                                                    view! { cx, li(style="overflow-wrap: anywhere;") { (slow_file_str) } }
+                                                })
+                                                .collect(),
+                                        );
+
+                                        view! { cx,
+                                            details {
+                                                summary { (slow_files_str) }
+                                                ul { (items) }
+                                            }
+                                        }
+                                    })
                                 }
                             }
                             _ => {
@@ -193,10 +663,204 @@ pub fn Status<'a, G: Html>(
                         p {
                             (get_translation("indexing_index_size", Some(&FluentArgs::from_iter([("size", file_size_str(index_stats.get().index_size))]))).to_string())
                         }
+                        (if directory_stats_list.get().is_empty() {
+                            view! { cx, }
+                        } else {
+                            let rows = View::new_fragment(
+                                directory_stats_list
+                                    .get()
+                                    .iter()
+                                    .map(|x| {
+                                        let path_str = x.path.display().to_string();
+                                        let path_str = if x.exclude {
+                                            let excluded_args = FluentArgs::from_iter([("path", path_str)]);
+                                            get_translation("directory_stats_excluded", Some(&excluded_args)).to_string()
+                                        } else {
+                                            path_str
+                                        };
+                                        let doc_count_str = x.doc_count.to_string();
+                                        let total_size_str = file_size_str(x.total_size);
+
+                                        view! { cx,
+                                            tr {
+                                                td { (path_str) }
+                                                td { (doc_count_str) }
+                                                td { (total_size_str) }
+                                            }
+                                        }
+                                    })
+                                    .collect(),
+                            );
+
+                            view! { cx,
+                                table {
+                                    thead {
+                                        tr {
+                                            th { (get_translation("directory_stats_path", None)) }
+                                            th { (get_translation("directory_stats_doc_count", None)) }
+                                            th { (get_translation("directory_stats_total_size", None)) }
+                                        }
+                                    }
+                                    tbody { (rows) }
+                                }
+                            }
+                        })
+                    }
+
+                    fieldset {
+                        legend { (get_translation("watcher_activity", None)) }
+                        CheckboxSetting(id="mute_watcher",
+                            label=get_translation("mute_watcher", None), value=mute_watcher)
+                        (if watcher_events.get().is_empty() {
+                            view! { cx, p { (get_translation("watcher_activity_none", None)) } }
+                        } else {
+                            let items = View::new_fragment(
+                                watcher_events
+                                    .get()
+                                    .iter()
+                                    .rev()
+                                    .map(|(time, event)| {
+                                        let event_str = watcher_event_str(time, event);
+                                        view! { cx, li(style="overflow-wrap: anywhere;") { (event_str) } }
+                                    })
+                                    .collect(),
+                            );
+                            view! { cx, ul { (items) } }
+                        })
+                    }
+
+                    fieldset {
+                        legend { (get_translation("file_errors", None)) }
+                        details(on:toggle=toggle_file_errors) {
+                            summary {
+                                (match *file_errors_total.get() {
+                                    Some(total) => get_translation("file_errors_count", Some(&FluentArgs::from_iter([("count", total)]))).to_string(),
+                                    None => get_translation("file_errors_latest", None).to_string(),
+                                })
+                            }
+                            (View::new_fragment(
+                                file_errors_list
+                                    .get()
+                                    .iter()
+                                    .map(|x| {
+                                        let error_args = FluentArgs::from_iter([
+                                            ("path", x.path.display().to_string()),
+                                            ("stage", get_translation(format!("file_error_stage_{}", x.stage.name()), None).to_string()),
+                                            ("error", x.error.clone()),
+                                        ]);
+                                        let error_str = get_translation("file_error", Some(&error_args)).to_string();
+
+                                        view! { cx, p { (error_str) } }
+                                    })
+                                    .collect(),
+                            ))
+                            (if file_errors_total.get().is_some_and(|x| x > file_errors_list.get().len()) {
+                                view! { cx,
+                                    button(type="button", on:click=move |_| load_file_errors()) { (get_translation("file_errors_load_more", None)) }
+                                }
+                            } else {
+                                view! { cx, }
+                            })
+                        }
+                        div(class="settings_buttons") {
+                            LoadingIndicator(loading=file_errors_requests.loading())
+                            button(type="button", on:click=retry_failed_files,
+                                disabled=*is_indexing.get() || *file_errors_total.get() == Some(0)) { (get_translation("retry_failed_files", None)) }
+                        }
+                    }
+
+                    fieldset {
+                        legend { (get_translation("client_errors", None)) }
+                        (match (*client_errors_list.get()).clone() {
+                            Some(errors) if errors.is_empty() => {
+                                view! { cx, p { (get_translation("client_errors_none", None)) } }
+                            }
+                            Some(errors) => {
+                                let errors = View::new_fragment(
+                                    errors
+                                        .into_iter()
+                                        .map(|x| {
+                                            let error_args = FluentArgs::from_iter([
+                                                ("time", x.time.with_timezone(&Local).to_string()),
+                                                ("version", x.report.version),
+                                                ("tab", get_translation(x.report.tab, None).to_string()),
+                                                ("message", x.report.message),
+                                                ("location", x.report.location.unwrap_or_default()),
+                                            ]);
+                                            let error_str = get_translation("client_error", Some(&error_args)).to_string();
+
+                                            view! { cx, p(title=x.report.user_agent) { (error_str) } }
+                                        })
+                                        .collect(),
+                                );
+                                errors
+                            }
+                            None => view! { cx, },
+                        })
+                        div(class="settings_buttons") {
+                            LoadingIndicator(loading=client_errors_requests.loading())
+                            button(type="button", on:click=load_client_errors) { (get_translation("client_errors_load", None)) }
+                        }
+                    }
+
+                    fieldset {
+                        legend { (get_translation("indexing_estimate", None)) }
+                        NumberSetting(id="estimate_sample_percent".to_owned(),
+                            label=get_translation("estimate_sample_percent", None),
+                            min=ESTIMATE_SAMPLE_PERCENT_MIN, max=ESTIMATE_SAMPLE_PERCENT_MAX,
+                            value=estimate_sample_percent, valid=estimate_sample_percent_valid)
+                        (if let Some(x) = (*indexing_estimate.get()).clone() {
+                            let sample_args = FluentArgs::from_iter([
+                                ("sample_cnt", x.sample_cnt.to_string()),
+                                ("files_cnt", x.files_cnt.to_string()),
+                                ("sample_size", file_size_str(x.sample_size)),
+                                ("files_size", file_size_str(x.files_size)),
+                                ("errors_cnt", x.errors_cnt.to_string()),
+                            ]);
+                            let sample_str = get_translation("estimate_sample", Some(&sample_args)).to_string();
+
+                            let duration_args = FluentArgs::from_iter([("duration", duration_str_from_seconds(x.total_duration.as_secs_f32()))]);
+                            let duration_str = get_translation("estimate_duration", Some(&duration_args)).to_string();
+
+                            let stages_args = FluentArgs::from_iter([
+                                ("hash", duration_str_from_seconds(x.hash_duration.as_secs_f32())),
+                                ("metadata", duration_str_from_seconds(x.metadata_duration.as_secs_f32())),
+                                ("processing", duration_str_from_seconds(x.processing_duration.as_secs_f32())),
+                            ]);
+                            let stages_str = get_translation("estimate_stages", Some(&stages_args)).to_string();
+
+                            let index_size_args = FluentArgs::from_iter([("size", file_size_str(x.index_size))]);
+                            let index_size_str = get_translation("estimate_index_size", Some(&index_size_args)).to_string();
+
+                            view! { cx,
+                                p { (sample_str) }
+                                p { (duration_str) }
+                                p { (stages_str) }
+                                p { (index_size_str) }
+                                p { small { (get_translation("estimate_caveat", None)) } }
+                            }
+                        } else {
+                            view! { cx, }
+                        })
+                        div(class="settings_buttons") {
+                            (if *is_estimating.get() {
+                                view! { cx,
+                                    p { (get_translation("estimating", None)) }
+                                    button(type="button", on:click=cancel_estimate) { (get_translation("cancel", None)) }
+                                }
+                            } else {
+                                view! { cx,
+                                    button(type="button", on:click=start_estimate,
+                                        disabled=!*estimate_sample_percent_valid.get()) { (get_translation("estimate", None)) }
+                                }
+                            })
+                        }
                     }
 
                     div(class="settings_buttons") {
+                        LoadingIndicator(loading=index_requests.loading())
                         button(type="button", on:click=delete_index, disabled=*is_indexing.get()) { (get_translation("clear_index", None)) }
+                        button(type="button", on:click=rebuild_index, disabled=*is_indexing.get()) { (get_translation("rebuild_index", None)) }
                         button(type="submit", disabled=*is_indexing.get()) { (get_translation("index", None)) }
                     }
                 }
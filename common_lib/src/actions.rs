@@ -5,6 +5,15 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenPathArgs {
     pub path: PathBuf,
+    /// Path is of entry of archive, archive is opened instead
+    #[serde(default)]
+    pub in_archive: bool,
+}
+
+/// Document of file that is moved to trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletePathArgs {
+    pub id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
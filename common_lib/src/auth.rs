@@ -0,0 +1,28 @@
+use sha2::{Digest, Sha256};
+
+/// Compares tokens in time independent of their contents, so token can't be guessed by timing.
+/// Digests are compared, so time doesn't depend on length of token either
+pub fn tokens_match(token: &str, api_token: &str) -> bool {
+    let token = Sha256::digest(token.as_bytes());
+    let api_token = Sha256::digest(api_token.as_bytes());
+    token
+        .iter()
+        .zip(api_token.iter())
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "secret token";
+
+    #[test]
+    fn tokens_are_matched_only_when_equal() {
+        assert!(tokens_match(TOKEN, TOKEN));
+        assert!(!tokens_match("secret", TOKEN));
+        assert!(!tokens_match("secret token!", TOKEN));
+        assert!(!tokens_match("", TOKEN));
+    }
+}
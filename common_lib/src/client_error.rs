@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of reported error message
+pub const CLIENT_ERROR_MESSAGE_MAX_LEN: usize = 1000;
+
+/// Error in client, reported to indexer if enabled in settings.
+/// Must not contain query text or file paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientErrorReport {
+    pub message: String,
+    /// Location of panic in client source code
+    pub location: Option<String>,
+    pub user_agent: String,
+    /// Tab that was open when error happened
+    pub tab: String,
+    pub version: String,
+}
+
+/// Reported client error with time of receiving it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientError {
+    pub time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub report: ClientErrorReport,
+}
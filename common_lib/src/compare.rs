@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Maximum length of content of each document that is compared, in bytes
+pub const COMPARE_CONTENT_MAX_LEN: usize = 100_000;
+
+/// Request to compare two indexed documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareQuery {
+    pub id_a: String,
+    pub id_b: String,
+}
+
+/// Values of metadata field in both documents, `None` if field is absent
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldComparison {
+    pub field: String,
+    pub value_a: Option<String>,
+    pub value_b: Option<String>,
+}
+
+impl FieldComparison {
+    pub fn is_equal(&self) -> bool {
+        self.value_a == self.value_b
+    }
+}
+
+/// Line of content diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffLine {
+    /// Line present in both documents
+    Equal(String),
+    /// Line present only in first document
+    Removed(String),
+    /// Line present only in second document
+    Added(String),
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareResponse {
+    pub path_a: PathBuf,
+    pub path_b: PathBuf,
+    /// Metadata fields present in any of documents, sorted by name
+    pub fields: Vec<FieldComparison>,
+    /// Line-based diff of content, `None` if any document has no text content
+    pub content_diff: Option<Vec<DiffLine>>,
+    /// Content of any document was longer than `COMPARE_CONTENT_MAX_LEN` and only its beginning is compared
+    #[serde(default)]
+    pub content_truncated: bool,
+}
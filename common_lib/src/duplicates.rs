@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Number of groups of duplicates in page, the last page can have fewer
+pub const DUPLICATES_PAGE_SIZE: usize = 50;
+/// Maximum number of listed files of group, larger groups are only counted
+pub const DUPLICATE_GROUP_MAX_FILES: usize = 100;
+
+/// Request of page of groups of duplicates
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicatesQuery {
+    /// Smaller files are ignored, in bytes
+    #[serde(default)]
+    pub min_size: u64,
+    /// Only files in this directory are compared
+    pub path_prefix: Option<PathBuf>,
+    /// Hash after which scanning continues, from previous page
+    pub after: Option<String>,
+}
+
+/// Indexed file with the same contents as other files of group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub id: String,
+    pub path: PathBuf,
+    /// File is entry of archive
+    #[serde(default)]
+    pub in_archive: bool,
+}
+
+/// Files with the same hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    /// Size of each file in bytes
+    pub size: u64,
+    /// Number of files, can be larger than number of listed files
+    pub file_cnt: u64,
+    /// Size of all files except one
+    pub wasted_size: u64,
+    pub files: Vec<DuplicateFile>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicatesPage {
+    /// Sorted by wasted size, the largest first
+    pub groups: Vec<DuplicateGroup>,
+    /// Hash to continue scanning after, `None` if all hashes were scanned
+    pub after: Option<String>,
+}
@@ -1,4 +1,7 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use chrono::{
     serde::{ts_seconds, ts_seconds_option},
@@ -8,9 +11,23 @@ use derive_more::Display;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
+use crate::provenance::Provenance;
+
 pub const ELASTICSEARCH_INDEX: &str = "files";
 pub const ELASTICSEARCH_MAX_SIZE: i64 = 10000;
-pub const ELASTICSEARCH_PIT_KEEP_ALIVE: &str = "1m";
+/// Maximum number of point in time reopenings after expiration
+pub const ELASTICSEARCH_PIT_MAX_RENEWALS: u32 = 5;
+/// Files modified later than this number of seconds after current time are modified in future
+pub const FUTURE_MODIFIED_TOLERANCE_SECS: i64 = 5 * 60;
+/// Separator of archive path and path of entry inside it in paths of archive entries
+pub const ARCHIVE_ENTRY_SEPARATOR: &str = "!/";
+
+/// Path of archive that contains entry with given path, `None` if path isn't of archive entry
+pub fn enclosing_archive(path: &Path) -> Option<PathBuf> {
+    path.to_str()?
+        .split_once(ARCHIVE_ENTRY_SEPARATOR)
+        .map(|(archive, _)| PathBuf::from(archive))
+}
 
 pub trait FileMetadata {
     fn any_metadata(&self) -> bool;
@@ -29,7 +46,9 @@ pub struct FileES {
     pub modified: DateTime<Utc>,
     /// Size of file in bytes
     pub size: u64,
-    /// Base16 representation of SHA-256 hash of file
+    /// Base16 representation of hash of file, calculated by `hash_algorithm` from settings:
+    /// SHA-256 (64 digits) by default, XXH3 (16 digits), or absent if hashing is disabled.
+    /// Files indexed before the setting was changed keep hashes of the old algorithm
     pub hash: Option<String>,
     /// MIME content type
     pub content_type: String,
@@ -37,8 +56,25 @@ pub struct FileES {
     pub content_type_mime_type: String,
     /// Essence part of content type
     pub content_type_mime_essence: String,
+    /// How content type was detected, for debugging
+    pub content_type_detection: Option<ContentTypeDetection>,
     /// Text content
     pub content: Option<String>,
+    /// Tags added by user
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Thumbnail can be generated for file, `None` if it wasn't tried yet
+    pub has_thumbnail: Option<bool>,
+    /// Versions of tools that processed contents, `None` if they weren't processed
+    pub provenance: Option<Provenance>,
+    /// Permissions and attributes of file
+    #[serde(flatten)]
+    pub attributes: FileAttributes,
+    /// Document is entry of archive, its path is archive path and entry path joined with
+    /// `ARCHIVE_ENTRY_SEPARATOR`
+    pub in_archive: Option<bool>,
+    /// Contents weren't parsed because parsing of unchanged file kept failing
+    pub parse_failed: Option<bool>,
     /// Fields for text files
     #[serde(flatten)]
     pub text_data: TextData,
@@ -51,6 +87,41 @@ pub struct FileES {
     /// Fields for document files
     #[serde(flatten)]
     pub document_data: DocumentData,
+    /// Fields for email files
+    #[serde(flatten)]
+    pub email_data: EmailData,
+}
+
+/// Method of detection of content type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentTypeDetection {
+    /// By Apache Tika from contents and file name
+    Tika,
+    /// Text type refined by file extension
+    Extension,
+    /// By extension from content type overrides in settings
+    Override,
+    /// By signature of contents, when Apache Tika returned generic binary type
+    Magic,
+}
+
+/// Permissions and attributes of file, absent in documents indexed before they were added
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileAttributes {
+    /// Executable bit is set (Unix only)
+    pub is_executable: Option<bool>,
+    /// File can't be written to
+    pub is_readonly: Option<bool>,
+    /// Dot file on Unix, hidden or system attribute on Windows
+    pub is_hidden: Option<bool>,
+    /// Online-only placeholder of cloud-synced file, contents weren't downloaded and processed
+    pub is_online_only: Option<bool>,
+    /// Names of extended attributes, if their indexing is enabled (Unix only)
+    pub xattrs: Option<Vec<String>>,
+    /// Path is symbolic link, it's indexed only if following symbolic links is enabled
+    pub is_symlink: Option<bool>,
 }
 
 /// Fields for text files
@@ -60,6 +131,156 @@ pub struct TextData {
     /// MiniLM embedding of text
     pub text_embedding: Option<Vec<f32>>,
     pub summary: Vec<String>,
+    /// Language of text, detected from its contents
+    pub language: Option<ContentLanguage>,
+}
+
+/// Maximum number of characters checked when detecting language
+#[cfg(feature = "language_detection")]
+const LANGUAGE_DETECTION_MAX_CHARS: usize = 10000;
+
+/// Language of text content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentLanguage {
+    #[display(fmt = "en")]
+    En,
+    #[display(fmt = "ru")]
+    Ru,
+    #[display(fmt = "de")]
+    De,
+    #[display(fmt = "fr")]
+    Fr,
+    #[display(fmt = "es")]
+    Es,
+    #[display(fmt = "it")]
+    It,
+    #[display(fmt = "pt")]
+    Pt,
+    #[display(fmt = "nl")]
+    Nl,
+    #[display(fmt = "zh")]
+    Zh,
+    #[display(fmt = "ja")]
+    Ja,
+    #[display(fmt = "ko")]
+    Ko,
+}
+
+impl ContentLanguage {
+    pub const ALL: [Self; 11] = [
+        Self::En,
+        Self::Ru,
+        Self::De,
+        Self::Fr,
+        Self::Es,
+        Self::It,
+        Self::Pt,
+        Self::Nl,
+        Self::Zh,
+        Self::Ja,
+        Self::Ko,
+    ];
+
+    /// Checks if text in language is written without spaces between words
+    pub fn is_cjk(self) -> bool {
+        matches!(self, Self::Zh | Self::Ja | Self::Ko)
+    }
+
+    /// Checks if language has stemmer and stopwords and can be listed in `content_languages`
+    /// setting, unspaced text is analyzed with bigrams of CJK characters instead
+    pub fn has_analyzer(self) -> bool {
+        !self.is_cjk()
+    }
+
+    /// Language of IETF language tag or English name of language from document metadata,
+    /// such as "fr-FR" or "French"
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_lowercase();
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary {
+            "english" => Some(Self::En),
+            "russian" => Some(Self::Ru),
+            "german" => Some(Self::De),
+            "french" => Some(Self::Fr),
+            "spanish" => Some(Self::Es),
+            "italian" => Some(Self::It),
+            "portuguese" => Some(Self::Pt),
+            "dutch" => Some(Self::Nl),
+            "chinese" => Some(Self::Zh),
+            "japanese" => Some(Self::Ja),
+            "korean" => Some(Self::Ko),
+            _ => primary.parse().ok(),
+        }
+    }
+}
+
+#[cfg(feature = "language_detection")]
+impl ContentLanguage {
+    /// Languages that can be detected, other languages aren't considered
+    const DETECTED: [whatlang::Lang; 11] = [
+        whatlang::Lang::Eng,
+        whatlang::Lang::Rus,
+        whatlang::Lang::Deu,
+        whatlang::Lang::Fra,
+        whatlang::Lang::Spa,
+        whatlang::Lang::Ita,
+        whatlang::Lang::Por,
+        whatlang::Lang::Nld,
+        whatlang::Lang::Cmn,
+        whatlang::Lang::Jpn,
+        whatlang::Lang::Kor,
+    ];
+
+    fn from_whatlang(lang: whatlang::Lang) -> Option<Self> {
+        match lang {
+            whatlang::Lang::Eng => Some(Self::En),
+            whatlang::Lang::Rus => Some(Self::Ru),
+            whatlang::Lang::Deu => Some(Self::De),
+            whatlang::Lang::Fra => Some(Self::Fr),
+            whatlang::Lang::Spa => Some(Self::Es),
+            whatlang::Lang::Ita => Some(Self::It),
+            whatlang::Lang::Por => Some(Self::Pt),
+            whatlang::Lang::Nld => Some(Self::Nl),
+            whatlang::Lang::Cmn => Some(Self::Zh),
+            whatlang::Lang::Jpn => Some(Self::Ja),
+            whatlang::Lang::Kor => Some(Self::Ko),
+            _ => None,
+        }
+    }
+
+    /// Detect language of text with whatlang among supported languages.
+    /// Returns `None` if text has no letters
+    pub fn detect(text: &str) -> Option<Self> {
+        let end = text
+            .char_indices()
+            .nth(LANGUAGE_DETECTION_MAX_CHARS)
+            .map_or(text.len(), |(i, _)| i);
+        whatlang::Detector::with_allowlist(Self::DETECTED.to_vec())
+            .detect_lang(&text[..end])
+            .and_then(Self::from_whatlang)
+    }
+}
+
+impl FromStr for ContentLanguage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "ru" => Ok(Self::Ru),
+            "de" => Ok(Self::De),
+            "fr" => Ok(Self::Fr),
+            "es" => Ok(Self::Es),
+            "it" => Ok(Self::It),
+            "pt" => Ok(Self::Pt),
+            "nl" => Ok(Self::Nl),
+            "zh" => Ok(Self::Zh),
+            "ja" => Ok(Self::Ja),
+            "ko" => Ok(Self::Ko),
+            _ => Err(anyhow::anyhow!("Unknown language")),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
@@ -183,6 +404,10 @@ pub struct MultimediaData {
     pub duration: Option<f32>,
     pub audio_sample_rate: Option<u32>,
     pub audio_channel_type: Option<AudioChannelType>,
+    /// Frame width of video in pixels
+    pub video_width: Option<u32>,
+    /// Frame height of video in pixels
+    pub video_height: Option<u32>,
 }
 
 impl FileMetadata for MultimediaData {
@@ -196,6 +421,8 @@ impl FileMetadata for MultimediaData {
             || self.duration.is_some()
             || self.audio_sample_rate.is_some()
             || self.audio_channel_type.is_some()
+            || self.video_width.is_some()
+            || self.video_height.is_some()
     }
 }
 
@@ -205,6 +432,10 @@ impl FileMetadata for MultimediaData {
 pub struct DocumentData {
     pub title: Option<String>,
     pub creator: Option<String>,
+    /// Language from document metadata, such as "en" or "ru-RU",
+    /// unlike detected language of text content
+    pub doc_language: Option<String>,
+    pub publisher: Option<String>,
     #[serde(default, with = "ts_seconds_option")]
     pub doc_created: Option<DateTime<Utc>>,
     #[serde(default, with = "ts_seconds_option")]
@@ -218,6 +449,8 @@ impl FileMetadata for DocumentData {
     fn any_metadata(&self) -> bool {
         self.title.is_some()
             || self.creator.is_some()
+            || self.doc_language.is_some()
+            || self.publisher.is_some()
             || self.doc_created.is_some()
             || self.doc_modified.is_some()
             || self.num_pages.is_some()
@@ -225,3 +458,26 @@ impl FileMetadata for DocumentData {
             || self.num_characters.is_some()
     }
 }
+
+/// Fields for email files
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmailData {
+    pub sender: Option<String>,
+    /// Addresses from "To", "Cc" and "Bcc" headers, joined with commas
+    pub recipients: Option<String>,
+    pub subject: Option<String>,
+    #[serde(default, with = "ts_seconds_option")]
+    pub email_sent: Option<DateTime<Utc>>,
+    pub has_attachments: Option<bool>,
+}
+
+impl FileMetadata for EmailData {
+    fn any_metadata(&self) -> bool {
+        self.sender.is_some()
+            || self.recipients.is_some()
+            || self.subject.is_some()
+            || self.email_sent.is_some()
+            || self.has_attachments.is_some()
+    }
+}
@@ -1,8 +1,19 @@
-use std::{mem::take, time::Duration};
+use std::{mem::take, path::PathBuf, time::Duration};
 
 use serde::{Deserialize, Serialize};
 
 pub const MAX_ERROR_CNT: usize = 20;
+/// Number of the slowest files kept in indexing status
+pub const MAX_SLOW_FILE_CNT: usize = 20;
+pub const ESTIMATE_MAX_SAMPLE_CNT: usize = 200;
+/// Time in seconds during which token for deleting index is valid
+pub const DELETE_TOKEN_TTL_SECS: u64 = 60;
+/// Default number of errors of files in page of report
+pub const FILE_ERRORS_PAGE_SIZE: usize = 50;
+/// Maximum number of errors of files in page of report
+pub const FILE_ERRORS_MAX_PAGE_SIZE: usize = 500;
+/// Number of the latest events of file system watcher shown in status
+pub const MAX_WATCHER_EVENT_CNT: usize = 20;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IndexingEvent {
@@ -12,11 +23,54 @@ pub enum IndexingEvent {
         to_add: usize,
         to_remove: usize,
         to_update: usize,
+        /// Number of scanned files with modification time in future
+        modified_in_future: usize,
+        /// Total size of online-only files that are downloaded for processing
+        hydration_size: u64,
+        /// Number of online-only files left for later indexings after hydration budget ran out
+        online_only_deferred: usize,
     },
     FileProcessed,
+    /// File was modified while being processed and is processed again
+    FileChanged,
     FilesSent(usize),
+    /// Periodic processing rate and estimated time remaining
+    Progress {
+        /// Files per second over recent window
+        rate: f64,
+        eta: Option<Duration>,
+    },
     Error(String),
-    Finished(Duration),
+    /// File was moved to trash from search results and its documents were removed
+    FileDeleted(PathBuf),
+    /// File took longer than threshold from settings to process
+    SlowFile {
+        path: PathBuf,
+        /// Durations of processing stages by their names
+        stages: Vec<(String, Duration)>,
+    },
+    Finished {
+        duration: Duration,
+        /// Number of operations that weren't applied to index
+        failed_operations: usize,
+        /// Total durations of processing stages of all files by their names
+        stage_durations: Vec<(String, Duration)>,
+    },
+}
+
+/// File that took longer than threshold from settings to process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowFile {
+    pub path: PathBuf,
+    /// Durations of processing stages by their names
+    pub stages: Vec<(String, Duration)>,
+}
+
+impl SlowFile {
+    /// Total duration of processing
+    pub fn duration(&self) -> Duration {
+        self.stages.iter().map(|(_, x)| *x).sum()
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -24,11 +78,54 @@ pub struct IndexingStatusData {
     pub to_add: usize,
     pub to_remove: usize,
     pub to_update: usize,
+    /// Number of scanned files with modification time in future
+    #[serde(default)]
+    pub modified_in_future: usize,
+    /// Total size of online-only files that are downloaded for processing
+    #[serde(default)]
+    pub hydration_size: u64,
+    /// Number of online-only files left for later indexings after hydration budget ran out
+    #[serde(default)]
+    pub online_only_deferred: usize,
     pub processed: usize,
     pub sent: usize,
+    /// Number of times files were modified while being processed
+    #[serde(default)]
+    pub changed: usize,
     pub duration: Option<Duration>,
+    /// Processing rate in files per second over recent window
+    #[serde(default)]
+    pub rate: Option<f64>,
+    /// Estimated time remaining, not known in first seconds of indexing
+    #[serde(default)]
+    pub eta: Option<Duration>,
     pub errors_cnt: usize,
     pub errors: Vec<String>,
+    /// Number of operations that weren't applied to index
+    #[serde(default)]
+    pub failed_operations: usize,
+    /// Number of files that took longer than threshold from settings to process
+    #[serde(default)]
+    pub slow_files_cnt: usize,
+    /// The slowest of these files, the slowest first
+    #[serde(default)]
+    pub slow_files: Vec<SlowFile>,
+    /// Total durations of processing stages of all files by their names, known after indexing
+    #[serde(default)]
+    pub stage_durations: Vec<(String, Duration)>,
+}
+
+impl IndexingStatusData {
+    /// Number of files to add, update and remove
+    pub fn total(&self) -> usize {
+        self.to_add + self.to_update + self.to_remove
+    }
+
+    /// Fraction of processed files, `None` if there are no files to process
+    pub fn progress(&self) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| (self.processed as f64 / total as f64).min(1.0))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +142,17 @@ impl IndexingStatus {
         !matches!(self, Self::CalculatingDiff | Self::Indexing(_))
     }
 
+    /// Name of status without its data
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NotStarted => "not_started",
+            Self::DiffFailed(_) => "diff_failed",
+            Self::CalculatingDiff => "calculating_diff",
+            Self::Indexing(_) => "indexing",
+            Self::Finished(_) => "finished",
+        }
+    }
+
     pub fn process_event(&mut self, event: IndexingEvent) {
         match event {
             IndexingEvent::Started => *self = Self::CalculatingDiff,
@@ -53,11 +161,17 @@ impl IndexingStatus {
                 to_add,
                 to_remove,
                 to_update,
+                modified_in_future,
+                hydration_size,
+                online_only_deferred,
             } => {
                 *self = Self::Indexing(IndexingStatusData {
                     to_add,
                     to_remove,
                     to_update,
+                    modified_in_future,
+                    hydration_size,
+                    online_only_deferred,
                     ..Default::default()
                 })
             }
@@ -67,12 +181,25 @@ impl IndexingStatus {
                 }
                 _ => unreachable!(),
             },
+            IndexingEvent::FileChanged => match self {
+                Self::Indexing(data) => {
+                    data.changed += 1;
+                }
+                _ => unreachable!(),
+            },
             IndexingEvent::FilesSent(cnt) => match self {
                 Self::Indexing(data) => {
                     data.sent += cnt;
                 }
                 _ => unreachable!(),
             },
+            // Progress can be reported right after indexing finished
+            IndexingEvent::Progress { rate, eta } => {
+                if let Self::Indexing(data) = self {
+                    data.rate = Some(rate);
+                    data.eta = eta;
+                }
+            }
             IndexingEvent::Error(e) => match self {
                 Self::Indexing(data) => {
                     data.errors_cnt += 1;
@@ -82,11 +209,34 @@ impl IndexingStatus {
                 }
                 _ => unreachable!(),
             },
-            IndexingEvent::Finished(duration) => {
+            // Files are moved to trash independently of indexing
+            IndexingEvent::FileDeleted(_) => {}
+            IndexingEvent::SlowFile { path, stages } => match self {
+                Self::Indexing(data) => {
+                    data.slow_files_cnt += 1;
+                    let file = SlowFile { path, stages };
+                    let i = data
+                        .slow_files
+                        .partition_point(|x| x.duration() >= file.duration());
+                    if i < MAX_SLOW_FILE_CNT {
+                        data.slow_files.insert(i, file);
+                        data.slow_files.truncate(MAX_SLOW_FILE_CNT);
+                    }
+                }
+                _ => unreachable!(),
+            },
+            IndexingEvent::Finished {
+                duration,
+                failed_operations,
+                stage_durations,
+            } => {
                 *self = match self {
                     Self::Indexing(data) => {
                         let mut tmp = take(data);
                         tmp.duration = Some(duration);
+                        tmp.failed_operations = failed_operations;
+                        tmp.stage_durations = stage_durations;
+                        tmp.eta = None;
                         Self::Finished(tmp)
                     }
                     _ => unreachable!(),
@@ -96,17 +246,179 @@ impl IndexingStatus {
     }
 }
 
+/// Stage of processing of file that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileErrorStage {
+    /// Reading file and calculating its hash
+    Hash,
+    /// Extracting metadata and contents
+    Parse,
+    /// Calculating embeddings by neural network server
+    Embedding,
+    /// Sending document to Elasticsearch
+    Bulk,
+}
+
+impl FileErrorStage {
+    /// Name of stage, same as in JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hash => "hash",
+            Self::Parse => "parse",
+            Self::Embedding => "embedding",
+            Self::Bulk => "bulk",
+        }
+    }
+}
+
+/// Error of processing file in indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub stage: FileErrorStage,
+    pub error: String,
+}
+
+/// Page of errors of files in the latest indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileErrorsPage {
+    /// Number of all errors in indexing
+    pub total: usize,
+    pub errors: Vec<FileError>,
+}
+
+/// Request to parse again files that were indexed without contents after repeated failures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryParsesRequest {
+    pub paths: Vec<PathBuf>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IndexStats {
     pub doc_cnt: u64,
     pub index_size: u64,
 }
 
+/// Availability of indexer and services it depends on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexerHealth {
+    pub elasticsearch: bool,
+    pub tika: bool,
+    pub nn_server: bool,
+    pub watcher_active: bool,
+    /// Name of current indexing status
+    pub indexing_status: String,
+    /// Index has to be cleared before it can be used, see [`IndexSchemaStatus`]
+    #[serde(default)]
+    pub index_schema_outdated: bool,
+}
+
+/// Compatibility of mapping of existing index with current version and settings of models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum IndexSchemaStatus {
+    Compatible,
+    /// Index has to be cleared and files have to be indexed again
+    Outdated {
+        /// 0 if index was created before schema versions were stored
+        index_version: u64,
+        current_version: u64,
+        reason: String,
+    },
+}
+
+/// Number and total size of indexed documents in indexing directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryStats {
+    pub path: PathBuf,
+    pub doc_count: u64,
+    pub total_size: u64,
+    /// Directory is excluded from indexing, it isn't counted
+    pub exclude: bool,
+}
+
+/// Short-lived token that confirms deleting index with known number of documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteToken {
+    pub token: String,
+    /// Number of documents in index when token was issued
+    pub doc_cnt: u64,
+}
+
+/// Request to delete index, with token received shortly before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteIndexRequest {
+    pub token: String,
+}
+
+/// Request to estimate time and index size of full indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexingEstimateRequest {
+    /// Fraction of indexable files to process, no more than `ESTIMATE_MAX_SAMPLE_CNT` files
+    pub sample_rate: f64,
+}
+
+/// Projected time and index size of full indexing, extrapolated from processed sample
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexingEstimate {
+    /// Number of files with processed contents
+    pub files_cnt: usize,
+    /// Total size of files with processed contents
+    pub files_size: u64,
+    /// Number of files in sample
+    pub sample_cnt: usize,
+    /// Total size of files in sample
+    pub sample_size: u64,
+    /// Number of sample files that couldn't be processed
+    pub errors_cnt: usize,
+    /// Time of hash calculation
+    pub hash_duration: Duration,
+    /// Time of metadata extraction by Apache Tika
+    pub metadata_duration: Duration,
+    /// Time of contents processing, including embeddings calculation
+    pub processing_duration: Duration,
+    /// Time of full indexing
+    pub total_duration: Duration,
+    /// Size of documents in index
+    pub index_size: u64,
+}
+
+/// Kind of activity of file system watcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherEventKind {
+    /// Path was created, modified or removed
+    Changed,
+    /// File was renamed without changes, path is its new path
+    Moved,
+    /// Too many paths changed, all files are indexed instead
+    FullIndexing,
+}
+
+/// Activity of file system watcher between indexings, sent when debouncer fires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherEvent {
+    /// Changed path, `None` for full indexing
+    pub path: Option<PathBuf>,
+    pub kind: WatcherEventKind,
+    /// Indexing of change started, otherwise it's coalesced with changes that wait for
+    /// current indexing to finish
+    pub queued: bool,
+}
+
+/// Configuration of indexing status WebSocket, sent by client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexingWSConfig {
+    /// Don't send activity of file system watcher, e.g. during bulk file operations
+    pub mute_watcher: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IndexingWSMessage {
     IndexingStatus(IndexingStatus),
     IndexingEvent(IndexingEvent),
     IndexStats(IndexStats),
+    WatcherEvent(WatcherEvent),
     Error(String),
 }
 
@@ -125,6 +437,11 @@ impl From<IndexStats> for IndexingWSMessage {
         Self::IndexStats(value)
     }
 }
+impl From<WatcherEvent> for IndexingWSMessage {
+    fn from(value: WatcherEvent) -> Self {
+        Self::WatcherEvent(value)
+    }
+}
 impl From<String> for IndexingWSMessage {
     fn from(value: String) -> Self {
         Self::Error(value)
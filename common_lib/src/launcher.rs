@@ -0,0 +1,56 @@
+use std::str::FromStr;
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+/// Component started by launcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Component {
+    #[display(fmt = "Elasticsearch")]
+    Elasticsearch,
+    #[display(fmt = "Apache Tika")]
+    Tika,
+    #[display(fmt = "nn_server")]
+    NNServer,
+    #[display(fmt = "indexer")]
+    Indexer,
+}
+
+impl Component {
+    /// All components in order of starting
+    pub const ALL: [Self; 4] = [
+        Self::Elasticsearch,
+        Self::Tika,
+        Self::NNServer,
+        Self::Indexer,
+    ];
+}
+
+impl FromStr for Component {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "elasticsearch" => Ok(Self::Elasticsearch),
+            "tika" => Ok(Self::Tika),
+            "nn_server" => Ok(Self::NNServer),
+            "indexer" => Ok(Self::Indexer),
+            _ => Err(anyhow::anyhow!(
+                "Unknown component, expected elasticsearch, tika, nn_server or indexer"
+            )),
+        }
+    }
+}
+
+/// State of components started by launcher in daemon mode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WakeStatus {
+    /// Indexer isn't running
+    Idle,
+    /// Waiting for component to start
+    Starting(Component),
+    /// All components are running
+    Running,
+    /// Component failed to start
+    Failed(String),
+}
@@ -1,10 +1,32 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use settings::NNDevice;
 
 pub mod actions;
+#[cfg(feature = "auth")]
+pub mod auth;
+pub mod client_error;
+pub mod compare;
+pub mod duplicates;
 pub mod elasticsearch;
 pub mod indexer;
+pub mod launcher;
+pub mod provenance;
+pub mod rerank;
 pub mod search;
 pub mod settings;
+pub mod tags;
+
+/// Total length of document content in bytes, in response with part of it
+pub const CONTENT_TOTAL_LENGTH_HEADER: &str = "X-Total-Length";
+/// Offset of next part of document content, equal to total length after the last part
+pub const CONTENT_NEXT_OFFSET_HEADER: &str = "X-Next-Offset";
+/// Detected encoding of text file, content is always returned in UTF-8
+pub const CONTENT_ENCODING_HEADER: &str = "X-Source-Encoding";
+/// Error of document content request for files that don't look like text
+pub const BINARY_CONTENT_ERROR: &str = "Binary file can't be previewed";
 
 /// Should the request be batched?
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +41,32 @@ impl BatchRequest {
     }
 }
 
+/// Several images for calculating their embeddings in one request, Base64-encoded in JSON.
+/// Embeddings are returned in the same order
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageBatchRequest {
+    #[serde_as(as = "Vec<Base64>")]
+    pub images: Vec<Vec<u8>>,
+}
+
+/// State of models of neural network server, by name of model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NNServerHealth {
+    /// Devices that models are running on, or will run on when unloaded models are loaded again
+    pub devices: BTreeMap<String, NNDevice>,
+    /// Whether models are loaded, idle models are unloaded to free memory
+    #[serde(default)]
+    pub loaded: BTreeMap<String, bool>,
+}
+
+/// Dimensions of embeddings calculated by enabled models of neural network server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NNServerInfo {
+    pub text_embedding_dim: Option<usize>,
+    pub image_embedding_dim: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientTranslation {
     pub lang_id: String,
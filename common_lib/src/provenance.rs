@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Versions of tools that processed contents of file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Version of Apache Tika that extracted text and metadata
+    pub tika_version: Option<String>,
+    /// Identifiers of models that calculated embeddings
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Version of parsing code of indexer
+    pub parser_version: Option<u32>,
+}
+
+impl Provenance {
+    /// Checks if file has to be processed again, given provenance of newly processed files
+    pub fn is_outdated(&self, current: &Provenance, criterion: ProvenanceCriterion) -> bool {
+        let tika_changed = self.tika_version != current.tika_version;
+        let models_changed = self.models.iter().any(|x| !current.models.contains(x));
+        let parser_changed = self.parser_version != current.parser_version;
+        match criterion {
+            ProvenanceCriterion::TikaVersionChanged => tika_changed,
+            ProvenanceCriterion::ModelsChanged => models_changed,
+            ProvenanceCriterion::ParserVersionChanged => parser_changed,
+            ProvenanceCriterion::AnyChanged => tika_changed || models_changed || parser_changed,
+        }
+    }
+}
+
+/// Identifiers of NN server models that calculate embeddings of files, `None` if model is disabled
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelVersions {
+    pub clip_image: Option<String>,
+    pub minilm_text: Option<String>,
+}
+
+impl ModelVersions {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        [&self.clip_image, &self.minilm_text].into_iter().flatten()
+    }
+}
+
+/// Files processed with any of listed versions of tools, ignored if empty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProvenanceFilter {
+    pub tika_versions: Vec<String>,
+    pub models: Vec<String>,
+    pub parser_versions: Vec<u32>,
+}
+
+/// Which files should be processed again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProvenanceCriterion {
+    /// Processed by another version of Apache Tika
+    TikaVersionChanged,
+    /// Processed by models that aren't used anymore
+    ModelsChanged,
+    /// Processed by another version of indexer parsing code
+    ParserVersionChanged,
+    /// Any of the above
+    AnyChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReprocessRequest {
+    pub criterion: ProvenanceCriterion,
+}
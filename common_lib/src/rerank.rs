@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Request to score passages by relevance to query with reranking model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankRequest {
+    pub query: String,
+    pub passages: Vec<String>,
+}
+
+/// Passage with its position in request and relevance score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedPassage {
+    pub index: usize,
+    pub passage: String,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankResponse {
+    /// Scores in order of passages of request
+    pub scores: Vec<f32>,
+    /// Passages sorted by descending score
+    pub passages: Vec<RankedPassage>,
+}
@@ -1,25 +1,72 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
-use chrono::{DateTime, Utc};
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use derive_more::Display;
 use serde::{Deserialize, Serialize};
-use serde_with::skip_serializing_none;
+use serde_with::{base64::Base64, serde_as, skip_serializing_none};
 use uuid::Uuid;
 
-use crate::elasticsearch::{AudioChannelType, FileES, ResolutionUnit};
+use crate::{
+    elasticsearch::{AudioChannelType, ContentLanguage, FileES, ResolutionUnit},
+    provenance::ProvenanceFilter,
+};
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchRequest {
     pub page: u32,
+    /// Overrides number of results per page from settings, clamped to its maximum
+    #[serde(default)]
+    pub results_per_page: Option<u32>,
     pub query: QueryType,
     pub path_prefix: Option<PathBuf>,
+    /// Glob pattern of full path, see `validate_path_glob`
+    #[serde(default)]
+    pub path_glob: Option<String>,
     pub content_type: Option<Vec<ContentTypeRequestItem>>,
     pub path_enabled: bool,
     pub hash_enabled: bool,
     pub modified_from: Option<DateTime<Utc>>,
     pub modified_to: Option<DateTime<Utc>>,
+    /// Filter by modification time later than current time, for files with wrong clock
+    pub modified_in_future: Option<bool>,
     pub size_from: Option<u64>,
     pub size_to: Option<u64>,
+    /// Documents with any of these tags, if not empty
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Filter by executable bit
+    pub is_executable: Option<bool>,
+    /// Filter by read-only permission
+    pub is_readonly: Option<bool>,
+    /// Filter by hidden attribute
+    pub is_hidden: Option<bool>,
+    /// Filter by online-only placeholders indexed without contents
+    pub is_online_only: Option<bool>,
+    /// Filter by files indexed without contents after repeated parsing failures
+    #[serde(default)]
+    pub parse_failed: Option<bool>,
+    /// Filter by detected language of text content
+    pub language: Option<ContentLanguage>,
+    /// Filter by versions of tools that processed file, not set by client
+    #[serde(default)]
+    pub provenance: ProvenanceFilter,
+    /// Order of results
+    #[serde(default)]
+    pub sort: SortOrder,
+    /// Score of recently modified files is multiplied by up to `1 + recency_boost`.
+    /// Applies only to text query, scores of semantic search aren't boosted
+    #[serde(default)]
+    pub recency_boost: Option<f32>,
+    /// Weight of path field for this request, multiplied by its weight from settings
+    #[serde(default)]
+    pub path_boost: Option<f32>,
+    /// When phrase suggestion is requested for text query
+    #[serde(default)]
+    pub suggestions: SuggestionMode,
+    /// Return generated Elasticsearch request and timings of search, for tuning coefficients
+    #[serde(default)]
+    pub debug: bool,
 
     /// Fields for image files
     pub image_data: ImageSearchRequest,
@@ -27,6 +74,208 @@ pub struct SearchRequest {
     pub multimedia_data: MultimediaSearchRequest,
     /// Fields for document files
     pub document_data: DocumentSearchRequest,
+    /// Fields for email files, absent in requests saved before they were indexed
+    #[serde(default)]
+    pub email_data: EmailSearchRequest,
+}
+
+impl SearchRequest {
+    /// Checks if content type filter includes only audio files
+    pub fn is_audio_only(&self) -> bool {
+        let Some(v) = &self.content_type else {
+            return false;
+        };
+        let is_audio_subtype = |x: &String| x.starts_with("audio/");
+        v.iter()
+            .any(|x| !matches!(x, ContentTypeRequestItem::ExcludeType { .. }))
+            && v.iter().all(|x| match x {
+                ContentTypeRequestItem::IncludeType { type_ }
+                | ContentTypeRequestItem::ExcludeSubtypes { type_, .. } => type_ == "audio",
+                ContentTypeRequestItem::IncludeSubtypes { subtypes } => {
+                    subtypes.iter().all(is_audio_subtype)
+                }
+                ContentTypeRequestItem::ExcludeType { .. } => true,
+            })
+    }
+
+    /// Checks if results should be grouped by album
+    pub fn group_albums(&self) -> bool {
+        self.multimedia_data.group_albums && self.is_audio_only()
+    }
+}
+
+/// When phrase suggestion ("did you mean") is requested for text query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SuggestionMode {
+    /// With every search
+    #[default]
+    Always,
+    /// With separate request if search has fewer results than this
+    WhenFewResults(u32),
+    /// Never, auto-correction is also disabled
+    Never,
+}
+
+impl SuggestionMode {
+    /// Checks if suggestion is shown for search with this number of results
+    pub fn is_shown(self, hits: u64) -> bool {
+        match self {
+            Self::Always => true,
+            Self::WhenFewResults(max_hits) => hits < max_hits.into(),
+            Self::Never => false,
+        }
+    }
+}
+
+/// Order of search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    #[display(fmt = "relevance")]
+    Relevance,
+    #[display(fmt = "modified_asc")]
+    ModifiedAsc,
+    #[display(fmt = "modified_desc")]
+    ModifiedDesc,
+    #[display(fmt = "size_asc")]
+    SizeAsc,
+    #[display(fmt = "size_desc")]
+    SizeDesc,
+    #[display(fmt = "name_asc")]
+    NameAsc,
+    #[display(fmt = "name_desc")]
+    NameDesc,
+}
+
+impl SortOrder {
+    pub const ALL: [Self; 7] = [
+        Self::Relevance,
+        Self::ModifiedAsc,
+        Self::ModifiedDesc,
+        Self::SizeAsc,
+        Self::SizeDesc,
+        Self::NameAsc,
+        Self::NameDesc,
+    ];
+}
+
+impl FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relevance" => Ok(Self::Relevance),
+            "modified_asc" => Ok(Self::ModifiedAsc),
+            "modified_desc" => Ok(Self::ModifiedDesc),
+            "size_asc" => Ok(Self::SizeAsc),
+            "size_desc" => Ok(Self::SizeDesc),
+            "name_asc" => Ok(Self::NameAsc),
+            "name_desc" => Ok(Self::NameDesc),
+            _ => Err(anyhow::anyhow!("Unknown sort order")),
+        }
+    }
+}
+
+/// Error of glob pattern of path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum PathGlobError {
+    #[display(fmt = "Pattern of path is empty")]
+    Empty,
+    #[display(fmt = "Character classes and alternatives aren't supported in pattern of path")]
+    UnsupportedSyntax,
+}
+
+/// Checks glob pattern of path. Only wildcards `*` (any characters, including separators)
+/// and `?` (one character) are supported, other characters match themselves
+pub fn validate_path_glob(glob: &str) -> Result<(), PathGlobError> {
+    if glob.is_empty() {
+        return Err(PathGlobError::Empty);
+    }
+    if glob.contains(['[', ']', '{', '}']) {
+        return Err(PathGlobError::UnsupportedSyntax);
+    }
+    Ok(())
+}
+
+/// Checks if glob pattern starts with wildcard, such patterns are slow on large indexes
+pub fn is_leading_wildcard_glob(glob: &str) -> bool {
+    glob.starts_with(['*', '?'])
+}
+
+/// Query parameters of simple search API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSearchParams {
+    pub q: String,
+    pub page: u32,
+    /// Number of results per page, default is from settings
+    pub per_page: Option<u32>,
+    pub path_prefix: Option<PathBuf>,
+    /// Glob pattern of full path, such as "*/reports/*.pdf"
+    pub path_glob: Option<String>,
+    /// Comma-separated types (such as "image") or subtypes (such as "image/png")
+    pub content_type: Option<String>,
+    pub modified_from: Option<DateTime<Utc>>,
+    pub modified_to: Option<DateTime<Utc>>,
+    pub size_from: Option<u64>,
+    pub size_to: Option<u64>,
+    /// Weight of path field, multiplied by its weight from settings
+    pub path_boost: Option<f32>,
+    /// Also search by embeddings and rerank results, if enabled in settings
+    pub semantic: bool,
+}
+
+/// Result of simple search API
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiSearchResult {
+    pub path: PathBuf,
+    pub score: f32,
+    pub content_type: String,
+    #[serde(with = "ts_seconds")]
+    pub modified: DateTime<Utc>,
+    pub size: u64,
+    /// Highlighted fragments of content
+    pub highlight: Option<String>,
+}
+
+/// File format of exported search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    #[display(fmt = "csv")]
+    Csv,
+    #[display(fmt = "json")]
+    Json,
+}
+
+/// Request for exporting results of search from all pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    pub search_request: SearchRequest,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Maximum number of exported results, limited by indexer
+    pub max_results: Option<u32>,
+}
+
+/// Stored embedding that is compared to find similar files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimilarityMode {
+    Image,
+    Text,
+}
+
+/// Request for files similar to indexed one, by nearest embeddings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarRequest {
+    /// ID of source document, excluded from results
+    pub id: String,
+    pub mode: SimilarityMode,
+    #[serde(default)]
+    pub page: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,11 +297,34 @@ pub struct TextQuery {
     pub text_search_coeff: f64,
     pub image_search_coeff: f64,
     pub reranking_coeff: f32,
+    /// Search with suggested correction of query if it has too few results
+    #[serde(default)]
+    pub auto_correct: bool,
+    /// Number of highlighted fragments of content
+    #[serde(default = "TextQuery::default_content_fragments")]
+    pub content_fragments: u32,
+}
+
+/// Maximum number of highlighted fragments of content
+pub const CONTENT_FRAGMENTS_MAX: u32 = 10;
+
+impl TextQuery {
+    fn default_content_fragments() -> u32 {
+        1
+    }
 }
 
+/// Maximum size of uploaded or pasted image of query in bytes
+pub const QUERY_IMAGE_MAX_SIZE: usize = 8 * 1024 * 1024;
+
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageQuery {
     pub image_path: PathBuf,
+    /// Uploaded or pasted image, used instead of local file if set. Base64-encoded in JSON
+    #[serde(default)]
+    #[serde_as(as = "Option<Base64>")]
+    pub image_data: Option<Vec<u8>>,
     pub image_search_pages: u32,
 }
 
@@ -94,6 +366,15 @@ pub struct MultimediaSearchRequest {
     pub audio_sample_rate_from: Option<u32>,
     pub audio_sample_rate_to: Option<u32>,
     pub audio_channel_type: Option<AudioChannelType>,
+    pub video_width_from: Option<u32>,
+    pub video_width_to: Option<u32>,
+    pub video_height_from: Option<u32>,
+    pub video_height_to: Option<u32>,
+    /// Group results by album, if content type filter is restricted to audio
+    #[serde(default)]
+    pub group_albums: bool,
+    /// Only files from this album, empty for files without album
+    pub album_exact: Option<String>,
 }
 
 #[skip_serializing_none]
@@ -101,6 +382,9 @@ pub struct MultimediaSearchRequest {
 pub struct DocumentSearchRequest {
     pub title_enabled: bool,
     pub creator_enabled: bool,
+    /// Absent in requests saved before publisher was indexed
+    #[serde(default)]
+    pub publisher_enabled: bool,
     pub doc_created_from: Option<DateTime<Utc>>,
     pub doc_created_to: Option<DateTime<Utc>>,
     pub doc_modified_from: Option<DateTime<Utc>>,
@@ -113,7 +397,18 @@ pub struct DocumentSearchRequest {
     pub num_characters_to: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmailSearchRequest {
+    pub sender_enabled: bool,
+    pub recipients_enabled: bool,
+    pub subject_enabled: bool,
+    pub email_sent_from: Option<DateTime<Utc>>,
+    pub email_sent_to: Option<DateTime<Utc>>,
+    pub has_attachments: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContentTypeRequestItem {
     IncludeType {
         type_: String,
@@ -135,7 +430,8 @@ pub enum ContentTypeRequestItem {
 pub struct HighlightedFields {
     pub path: String,
     pub hash: Option<String>,
-    pub content: Option<String>,
+    /// Fragments of content in order of appearance
+    pub content: Option<Vec<String>>,
     pub summary: Option<String>,
     /// Fields for image files
     pub image_data: ImageHighlightedFields,
@@ -143,6 +439,8 @@ pub struct HighlightedFields {
     pub multimedia_data: MultimediaHighlightedFields,
     /// Fields for document files
     pub document_data: DocumentHighlightedFields,
+    /// Fields for email files
+    pub email_data: EmailHighlightedFields,
 }
 
 /// Fields for image files
@@ -172,12 +470,25 @@ pub struct MultimediaHighlightedFields {
 pub struct DocumentHighlightedFields {
     pub title: Option<String>,
     pub creator: Option<String>,
+    pub publisher: Option<String>,
+}
+
+/// Fields for email files
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailHighlightedFields {
+    pub sender: Option<String>,
+    pub recipients: Option<String>,
+    pub subject: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub file: FileES,
     pub highlights: HighlightedFields,
+    /// Fields that matched query, and `semantic` if file was found only by semantic search
+    #[serde(default)]
+    pub matched_fields: Vec<String>,
     pub score: f32,
     pub id: Uuid,
 }
@@ -189,6 +500,26 @@ impl PartialEq for SearchResult {
 }
 impl Eq for SearchResult {}
 
+/// Audio files from the same album
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumResult {
+    /// Name of album, `None` for files without album
+    pub album: Option<String>,
+    /// Most relevant file from album
+    pub file: FileES,
+    pub track_cnt: u64,
+    /// Total duration in seconds
+    pub duration: f32,
+    pub id: Uuid,
+}
+
+impl PartialEq for AlbumResult {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl Eq for AlbumResult {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PageType {
     First,
@@ -202,6 +533,42 @@ pub enum PageType {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
+    /// Results grouped by album, if requested
+    #[serde(default)]
+    pub albums: Vec<AlbumResult>,
     pub pages: Vec<PageType>,
     pub suggestion: Option<(String, String)>,
+    /// Query of request, if results are for automatically corrected query
+    #[serde(default)]
+    pub original_query: Option<String>,
+    /// Automatically corrected query that results are for
+    #[serde(default)]
+    pub corrected_query: Option<String>,
+    /// Total number of results of query of request, if it was corrected
+    #[serde(default)]
+    pub original_hits: Option<u64>,
+    /// Numbers of results with each detected language of text, the most frequent first
+    #[serde(default)]
+    pub languages: Vec<(ContentLanguage, u64)>,
+    /// Body of Elasticsearch request, if debug info was requested. Embedding vectors are truncated
+    #[serde(default)]
+    pub es_request: Option<serde_json::Value>,
+    /// Total duration of search in milliseconds, if debug info was requested
+    #[serde(default)]
+    pub took_ms: Option<u64>,
+    /// Duration of search in Elasticsearch in milliseconds, if debug info was requested
+    #[serde(default)]
+    pub es_took_ms: Option<u64>,
+    /// Sizes of kNN search chosen by indexer, if debug info was requested
+    #[serde(default)]
+    pub knn: Option<KnnDebugInfo>,
+}
+
+/// Sizes of kNN search, for tuning coefficients
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KnnDebugInfo {
+    /// Number of documents matching filter, counted if search is limited by path prefix
+    pub filtered_count: Option<u64>,
+    /// Field, `k` and `num_candidates` of each kNN clause
+    pub clauses: Vec<(String, u32, u32)>,
 }
@@ -1,26 +1,127 @@
-use std::{net::SocketAddr, path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, str::FromStr};
 
+use chrono::{DateTime, Utc};
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
+use serde_with::{formats::PreferMany, serde_as, skip_serializing_none, OneOrMany};
 use url::Url;
 
+use crate::{elasticsearch::ContentLanguage, search::ContentTypeRequestItem};
+
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Settings {
     pub indexer_address: SocketAddr,
-    pub elasticsearch_url: Url,
+    /// Token required in requests to indexer, except for files of web interface.
+    /// Requests aren't authenticated if it's not set or empty
+    pub api_token: Option<String>,
+    /// Nodes of Elasticsearch cluster, requests are distributed between them.
+    /// Single URL of older settings files is also accepted
+    #[serde(alias = "elasticsearch_url")]
+    #[serde_as(as = "OneOrMany<_, PreferMany>")]
+    pub elasticsearch_urls: Vec<Url>,
+    /// Basic authentication in Elasticsearch, not used if user isn't set
+    pub elasticsearch_user: Option<String>,
+    pub elasticsearch_password: Option<String>,
+    /// PEM file of CA certificate that signed certificates of Elasticsearch nodes
+    pub elasticsearch_ca_cert_path: Option<PathBuf>,
     pub tika_url: Url,
     pub nn_server_url: Url,
     pub open_on_start: bool,
+    /// Serve metrics of indexer in Prometheus format at /metrics
+    pub metrics_enabled: bool,
+    /// Allow moving files to trash from search results
+    pub file_actions_enabled: bool,
     pub exclude_file_regex: String,
+    /// Paths differing only in case are the same file, for case-insensitive file systems
+    pub case_insensitive_paths: bool,
     pub watcher_enabled: bool,
     pub debouncer_timeout: f32,
+    /// Index all directories on schedule, independently of watcher
+    pub periodic_indexing_enabled: bool,
+    pub periodic_indexing_interval_hours: u32,
     pub max_file_size: u64,
+    /// Maximum total size of files in directory with hash change detection,
+    /// larger directories are checked only by modification time and size
+    pub hash_change_detection_max_size: u64,
+    /// Hash of contents stored in index, changing it doesn't rehash indexed files
+    pub hash_algorithm: HashAlgorithm,
+    /// Size of chunks in which files are read for hashing
+    pub hash_chunk_size: usize,
+    /// Name of extended attribute that marks online-only placeholders of cloud-synced files
+    /// on Unix, empty to not check it. On Windows file attributes are checked
+    pub online_only_xattr: String,
+    /// Maximum total size of online-only files downloaded for processing contents per indexing,
+    /// other online-only files are indexed without contents and processed by later indexings
+    pub online_only_hydration_budget: u64,
     pub max_concurrent_files: usize,
+    /// Files processed longer than this number of seconds are reported with durations
+    /// of their processing stages, 0 disables reporting
+    pub slow_file_threshold: f32,
+    /// Files that failed parsing this number of times without changes are indexed
+    /// with basic metadata only, 0 retries them forever
+    pub parse_failures_max: u32,
+    /// Index names of extended attributes of files, requires additional syscall per file
+    pub index_xattrs: bool,
+    /// Follow symbolic links when scanning, files reached by several paths are indexed once
+    pub follow_symlinks: bool,
+    /// Index content and titles with bigrams of CJK characters, requires rebuilding index
+    pub cjk_analysis: bool,
+    /// Languages of text such as "fr" whose stemmers and stopwords are used for additional
+    /// analysis of content and titles, requires rebuilding index. English and Russian are
+    /// always analyzed
+    pub content_languages: Vec<String>,
+    /// Recognize text in images with Tesseract, it's indexed as content
+    pub ocr_enabled: bool,
+    /// Path to Tesseract executable
+    pub ocr_tesseract_path: String,
+    /// Tesseract languages joined with '+', such as "eng+rus"
+    pub ocr_languages: String,
+    /// Images with smaller width or height aren't recognized
+    pub ocr_min_dimension: u32,
+    /// Content types (MIME essences or their prefixes) of files that are indexed
+    /// only with basic metadata, without parsing by Apache Tika
+    pub skip_content_types: Vec<String>,
+    /// Content types of files by extension (lowercase, without dot), overriding detected ones.
+    /// Files with these extensions are read as text directly, without parsing by Apache Tika
+    pub content_type_overrides: BTreeMap<String, String>,
+    /// Index text of entries of zip archives as separate documents
+    pub index_archives: bool,
+    /// Store created thumbnails on disk
+    pub thumbnail_cache_enabled: bool,
+    pub thumbnail_cache_dir: PathBuf,
+    /// Maximum total size of cached thumbnails, least recently used are removed
+    pub thumbnail_cache_max_size: u64,
+    /// Maximum size of part of document content loaded at once for preview
+    pub preview_chunk_size: u64,
+    /// Larger images are downscaled to this width and height for preview, 0 disables downscaling
+    pub preview_max_dimension: u32,
     pub elasticsearch_batch_size: usize,
+    /// Keep-alive duration of Elasticsearch point in time in minutes
+    pub elasticsearch_pit_keep_alive: u32,
+    /// Number of times bulk request is sent again with exponential backoff
+    /// when Elasticsearch is unavailable or overloaded
+    pub elasticsearch_bulk_retries: u32,
     pub results_per_page: u32,
+    /// Maximum number of results per page requested in search request
+    pub results_per_page_max: u32,
     pub knn_candidates_multiplier: u32,
+    /// Age of files in days at which recency boost of search request is halved
+    pub recency_boost_scale_days: u32,
+    /// Queries with auto-correction and fewer results than this are replaced with suggestion
+    pub auto_correct_max_hits: u64,
+    /// Allow slow queries, such as patterns of path starting with wildcard on large indexes
+    pub allow_expensive_queries: bool,
+    /// Maximum number of passages in request to reranking API
+    pub rerank_max_passages: usize,
+    /// Maximum length of passage in request to reranking API in characters,
+    /// reranking model truncates long passages anyway
+    pub rerank_max_passage_len: usize,
+    pub field_weights: FieldWeights,
     pub nn_server: NNServerSettings,
+    pub client: ClientSettings,
+    pub launcher: LauncherSettings,
     pub indexing_directories: Vec<IndexingDirectory>,
 }
 
@@ -28,21 +129,166 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             indexer_address: "127.0.0.1:11000".parse().unwrap(),
-            elasticsearch_url: Url::parse("http://127.0.0.1:9200").unwrap(),
+            api_token: None,
+            elasticsearch_urls: vec![Url::parse("http://127.0.0.1:9200").unwrap()],
+            elasticsearch_user: None,
+            elasticsearch_password: None,
+            elasticsearch_ca_cert_path: None,
             tika_url: Url::parse("http://127.0.0.1:9998").unwrap(),
             nn_server_url: Url::parse("http://127.0.0.1:10000").unwrap(),
             open_on_start: true,
+            metrics_enabled: false,
+            file_actions_enabled: false,
             indexing_directories: Vec::new(),
             exclude_file_regex: r"[/\\]\.git[/\\]|\.pygtex$|\.pygstyle$|\.aux$|\.bbl$|\.bcf$|\.blg$|\.synctex\.gz$|\.toc$".to_owned(),
+            case_insensitive_paths: cfg!(any(windows, target_os = "macos")),
             watcher_enabled: true,
             debouncer_timeout: 5.0,
+            periodic_indexing_enabled: false,
+            periodic_indexing_interval_hours: 24,
             max_file_size: 50 * 1024 * 1024, // 50 MiB
+            hash_change_detection_max_size: 10 * 1024 * 1024 * 1024, // 10 GiB
+            hash_algorithm: HashAlgorithm::default(),
+            hash_chunk_size: 1024 * 1024, // 1 MiB
+            online_only_xattr: String::new(),
+            online_only_hydration_budget: 0,
             max_concurrent_files: 32,
+            slow_file_threshold: 30.0,
+            parse_failures_max: 3,
+            index_xattrs: false,
+            follow_symlinks: false,
+            cjk_analysis: false,
+            content_languages: Vec::new(),
+            ocr_enabled: false,
+            ocr_tesseract_path: "tesseract".to_owned(),
+            ocr_languages: "eng+rus".to_owned(),
+            ocr_min_dimension: 300,
+            skip_content_types: Vec::new(),
+            content_type_overrides: SOURCE_CONTENT_TYPES
+                .iter()
+                .map(|(extension, content_type)| {
+                    (extension.to_string(), content_type.to_string())
+                })
+                .collect(),
+            index_archives: false,
+            thumbnail_cache_enabled: true,
+            thumbnail_cache_dir: PathBuf::from("thumbnail_cache"),
+            thumbnail_cache_max_size: 1024 * 1024 * 1024, // 1 GiB
+            preview_chunk_size: 256 * 1024, // 256 KiB
+            preview_max_dimension: 2048,
             elasticsearch_batch_size: 100,
+            elasticsearch_pit_keep_alive: 1,
+            elasticsearch_bulk_retries: 5,
             results_per_page: 20,
+            results_per_page_max: 1000,
             knn_candidates_multiplier: 10,
+            recency_boost_scale_days: 180,
+            auto_correct_max_hits: 3,
+            allow_expensive_queries: false,
+            rerank_max_passages: 100,
+            rerank_max_passage_len: 2000,
+            field_weights: Default::default(),
             nn_server: Default::default(),
+            client: Default::default(),
+            launcher: Default::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Languages of `content_languages` setting that have analyzers, others are skipped
+    pub fn content_analyzer_languages(&self) -> Vec<ContentLanguage> {
+        let mut languages = Vec::new();
+        for language in self
+            .content_languages
+            .iter()
+            .filter_map(|x| x.parse::<ContentLanguage>().ok())
+            .filter(|x| x.has_analyzer())
+        {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
         }
+        languages
+    }
+}
+
+/// Default content types of Markdown and source code files by extension
+pub const SOURCE_CONTENT_TYPES: [(&str, &str); 18] = [
+    ("md", "text/markdown"),
+    ("markdown", "text/markdown"),
+    ("rs", "text/x-rust"),
+    ("py", "text/x-python"),
+    ("go", "text/x-go"),
+    ("c", "text/x-c"),
+    ("h", "text/x-c"),
+    ("cpp", "text/x-c++"),
+    ("hpp", "text/x-c++"),
+    ("cs", "text/x-csharp"),
+    ("java", "text/x-java"),
+    ("kt", "text/x-kotlin"),
+    ("js", "text/javascript"),
+    ("ts", "text/x-typescript"),
+    ("rb", "text/x-ruby"),
+    ("php", "text/x-php"),
+    ("sh", "text/x-shellscript"),
+    ("toml", "text/x-toml"),
+];
+
+/// Fields searched by text query
+pub const SEARCH_TEXT_FIELDS: [&str; 18] = [
+    "path",
+    "hash",
+    "content",
+    "image_make",
+    "image_model",
+    "image_software",
+    "artist",
+    "album",
+    "genre",
+    "track_number",
+    "disc_number",
+    "release_date",
+    "title",
+    "creator",
+    "publisher",
+    "sender",
+    "recipients",
+    "subject",
+];
+pub const FIELD_WEIGHT_MIN: f32 = 0.1;
+pub const FIELD_WEIGHT_MAX: f32 = 10.0;
+
+/// Boosts of fields searched by text query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FieldWeights(pub BTreeMap<String, f32>);
+
+impl Default for FieldWeights {
+    fn default() -> Self {
+        Self(
+            SEARCH_TEXT_FIELDS
+                .iter()
+                .map(|&x| (x.to_owned(), 1.0))
+                .collect(),
+        )
+    }
+}
+
+impl FieldWeights {
+    /// Weight of field, 1.0 if it isn't set
+    pub fn get(&self, field: &str) -> f32 {
+        self.0
+            .get(field)
+            .map_or(1.0, |x| x.clamp(FIELD_WEIGHT_MIN, FIELD_WEIGHT_MAX))
+    }
+
+    /// Fields with weights that aren't searched by text query
+    pub fn unknown_fields(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .keys()
+            .map(String::as_str)
+            .filter(|x| !SEARCH_TEXT_FIELDS.contains(x))
     }
 }
 
@@ -77,6 +323,20 @@ pub struct NNSettings {
     pub device: NNDevice,
     pub batch_size: usize,
     pub max_delay_ms: u64,
+    /// Maximum number of requests waiting for batch, further requests wait for free space.
+    /// Missing in older settings files, filled with default
+    #[serde(default)]
+    pub max_queue: usize,
+    /// Directory with files of model. Missing in older settings files, filled with default
+    #[serde(default)]
+    pub model_path: PathBuf,
+    /// Dimension of embeddings calculated by model, unused by reranking model
+    #[serde(default)]
+    pub embedding_dim: usize,
+    /// Minutes without requests after which model is unloaded to free memory,
+    /// 0 keeps it loaded. Unloaded model is loaded again on the next request
+    #[serde(default)]
+    pub idle_timeout_mins: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,6 +354,11 @@ pub struct NNServerSettings {
     pub window_size: u32,
     pub window_step: u32,
     pub summary_len: u32,
+    /// Maximum summary length for long texts, summary length is increased
+    /// by how many times text exceeds `max_sentences`
+    pub summary_len_max: u32,
+    /// Number of cached results of text models for each model, 0 disables cache
+    pub text_cache_capacity: usize,
 }
 
 impl Default for NNServerSettings {
@@ -107,26 +372,225 @@ impl Default for NNServerSettings {
                 device: NNDevice::CUDA,
                 batch_size: 16,
                 max_delay_ms: 100,
+                max_queue: 32,
+                model_path: PathBuf::from("nn_server/models/clip-ViT-B-32"),
+                embedding_dim: 512,
+                idle_timeout_mins: 0,
             },
             clip_text: NNSettings {
                 device: NNDevice::CUDA,
                 batch_size: 32,
                 max_delay_ms: 100,
+                max_queue: 64,
+                model_path: PathBuf::from("nn_server/models/clip-ViT-B-32-multilingual-v1"),
+                embedding_dim: 512,
+                idle_timeout_mins: 0,
             },
             minilm_text: NNSettings {
                 device: NNDevice::CUDA,
                 batch_size: 32,
                 max_delay_ms: 100,
+                max_queue: 64,
+                model_path: PathBuf::from("nn_server/models/paraphrase-multilingual-MiniLM-L12-v2"),
+                embedding_dim: 384,
+                idle_timeout_mins: 0,
             },
             minilm_rerank: NNSettings {
                 device: NNDevice::CUDA,
                 batch_size: 8,
                 max_delay_ms: 100,
+                max_queue: 16,
+                model_path: PathBuf::from("nn_server/models/mMiniLM-L6-v2-mmarco-v2"),
+                embedding_dim: 0,
+                idle_timeout_mins: 0,
             },
             max_sentences: 100,
             window_size: 100,
             window_step: 75,
             summary_len: 3,
+            summary_len_max: 10,
+            text_cache_capacity: 1000,
+        }
+    }
+}
+
+impl NNServerSettings {
+    /// Fill settings of models missing in older settings files with defaults
+    pub fn fill_model_defaults(&mut self) {
+        let default = Self::default();
+        for (model, default) in [
+            (&mut self.clip_image, default.clip_image),
+            (&mut self.clip_text, default.clip_text),
+            (&mut self.minilm_text, default.minilm_text),
+            (&mut self.minilm_rerank, default.minilm_rerank),
+        ] {
+            if model.model_path.as_os_str().is_empty() {
+                model.model_path = default.model_path;
+            }
+            if model.max_queue == 0 {
+                model.max_queue = default.max_queue;
+            }
+            if model.embedding_dim == 0 {
+                model.embedding_dim = default.embedding_dim;
+            }
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientSettings {
+    /// Launcher endpoint that starts indexer if it isn't responding
+    pub wake_url: Option<Url>,
+    /// Load next page of results in background
+    pub prefetch_next_page: bool,
+    /// Prefetch pages even if reranking is enabled, which makes searches expensive
+    pub prefetch_with_reranking: bool,
+    /// Default of automatic correction of misspelled queries
+    pub auto_correct: bool,
+    /// Report client errors to indexer, without query text and file paths
+    pub report_errors: bool,
+    pub client_language: ClientLanguage,
+    /// Default theme of interface, can be overridden in browser
+    pub theme: ClientTheme,
+    /// Default layout of search results, can be overridden in browser
+    pub results_layout: ResultsLayout,
+    /// Show thumbnails in search results by default, can be overridden in browser
+    pub thumbnails_enabled: bool,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            wake_url: None,
+            prefetch_next_page: true,
+            prefetch_with_reranking: false,
+            auto_correct: false,
+            report_errors: false,
+            client_language: ClientLanguage::Auto,
+            theme: ClientTheme::Auto,
+            results_layout: ResultsLayout::List,
+            thumbnails_enabled: true,
+        }
+    }
+}
+
+/// Paths of components started by launcher, relative to its working directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LauncherSettings {
+    /// Folder of Elasticsearch, its bundled JDK also runs Apache Tika
+    pub elasticsearch_path: PathBuf,
+    pub tika_jar_path: PathBuf,
+    pub tika_config_path: PathBuf,
+    /// Executable of nn_server, `.exe` is added on Windows if it has no extension
+    pub nn_server_path: PathBuf,
+    /// Folder of ONNX Runtime libraries used by nn_server on Linux
+    pub onnx_runtime_lib_path: PathBuf,
+    /// Executable of indexer, `.exe` is added on Windows if it has no extension
+    pub indexer_path: PathBuf,
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        Self {
+            elasticsearch_path: PathBuf::from("elasticsearch-8.7.0"),
+            tika_jar_path: PathBuf::from("tika-server-standard-2.7.0.jar"),
+            tika_config_path: PathBuf::from("tika-config.xml"),
+            nn_server_path: PathBuf::from("nn_server/nn_server"),
+            onnx_runtime_lib_path: PathBuf::from("onnxruntime-linux-x64-gpu-1.14.1/lib"),
+            indexer_path: PathBuf::from("./indexer"),
+        }
+    }
+}
+
+/// Language of client interface
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientLanguage {
+    /// Language of browser
+    #[default]
+    #[display(fmt = "auto")]
+    Auto,
+    #[display(fmt = "en")]
+    En,
+    #[display(fmt = "ru")]
+    Ru,
+}
+
+impl ClientLanguage {
+    /// Identifier of translation, `None` if it's negotiated from language of browser
+    pub fn lang_id(self) -> Option<&'static str> {
+        match self {
+            Self::Auto => None,
+            Self::En => Some("en-US"),
+            Self::Ru => Some("ru-RU"),
+        }
+    }
+}
+
+impl FromStr for ClientLanguage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "en" => Ok(Self::En),
+            "ru" => Ok(Self::Ru),
+            _ => Err(anyhow::anyhow!("Unknown client language")),
+        }
+    }
+}
+
+/// Color theme of client interface
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientTheme {
+    /// Theme of operating system
+    #[default]
+    #[display(fmt = "auto")]
+    Auto,
+    #[display(fmt = "light")]
+    Light,
+    #[display(fmt = "dark")]
+    Dark,
+}
+
+impl FromStr for ClientTheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            _ => Err(anyhow::anyhow!("Unknown client theme")),
+        }
+    }
+}
+
+/// Layout of search results in client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultsLayout {
+    /// One result per row with all its fields
+    #[default]
+    #[display(fmt = "list")]
+    List,
+    /// Cards in several columns
+    #[display(fmt = "grid")]
+    Grid,
+}
+
+impl FromStr for ResultsLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "list" => Ok(Self::List),
+            "grid" => Ok(Self::Grid),
+            _ => Err(anyhow::anyhow!("Unknown results layout")),
         }
     }
 }
@@ -136,4 +600,234 @@ pub struct IndexingDirectory {
     pub path: PathBuf,
     pub exclude: bool,
     pub watch: bool,
+    #[serde(default)]
+    pub change_detection: ChangeDetection,
+    /// With hash change detection, compare hashes even if modification time changed,
+    /// for file systems and sync tools that don't keep it.
+    /// Requires reading all files with unchanged size on each indexing
+    #[serde(default)]
+    pub hash_always: bool,
+    /// Filters applied by default when searching in this directory
+    #[serde(default)]
+    pub default_filters: Option<DirectoryDefaultFilters>,
+    /// Regex of files excluded in this directory, in addition to global one
+    #[serde(default)]
+    pub exclude_regex: Option<String>,
+    /// Maximum number of concurrently processed files of this directory, e.g. for network
+    /// mounts with slow metadata. Limited by global `max_concurrent_files`
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+}
+
+/// Part of search request that is applied when directory is selected in path filter
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectoryDefaultFilters {
+    #[serde(with = "tagged_content_type")]
+    pub content_type: Option<Vec<ContentTypeRequestItem>>,
+    pub modified_from: Option<DateTime<Utc>>,
+    pub modified_to: Option<DateTime<Utc>>,
+    pub size_from: Option<u64>,
+    pub size_to: Option<u64>,
+}
+
+/// TOML doesn't support enum struct variants, so content type items are stored internally tagged
+mod tagged_content_type {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::search::ContentTypeRequestItem;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "kind")]
+    enum TaggedItem {
+        IncludeType {
+            type_: String,
+        },
+        IncludeSubtypes {
+            subtypes: Vec<String>,
+        },
+        ExcludeType {
+            type_: String,
+        },
+        ExcludeSubtypes {
+            type_: String,
+            subtypes: Vec<String>,
+        },
+    }
+
+    impl From<ContentTypeRequestItem> for TaggedItem {
+        fn from(item: ContentTypeRequestItem) -> Self {
+            match item {
+                ContentTypeRequestItem::IncludeType { type_ } => Self::IncludeType { type_ },
+                ContentTypeRequestItem::IncludeSubtypes { subtypes } => {
+                    Self::IncludeSubtypes { subtypes }
+                }
+                ContentTypeRequestItem::ExcludeType { type_ } => Self::ExcludeType { type_ },
+                ContentTypeRequestItem::ExcludeSubtypes { type_, subtypes } => {
+                    Self::ExcludeSubtypes { type_, subtypes }
+                }
+            }
+        }
+    }
+
+    impl From<TaggedItem> for ContentTypeRequestItem {
+        fn from(item: TaggedItem) -> Self {
+            match item {
+                TaggedItem::IncludeType { type_ } => Self::IncludeType { type_ },
+                TaggedItem::IncludeSubtypes { subtypes } => Self::IncludeSubtypes { subtypes },
+                TaggedItem::ExcludeType { type_ } => Self::ExcludeType { type_ },
+                TaggedItem::ExcludeSubtypes { type_, subtypes } => {
+                    Self::ExcludeSubtypes { type_, subtypes }
+                }
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        items: &Option<Vec<ContentTypeRequestItem>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        items
+            .as_ref()
+            .map(|x| x.iter().cloned().map(TaggedItem::from).collect::<Vec<_>>())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<ContentTypeRequestItem>>, D::Error> {
+        Ok(Option::<Vec<TaggedItem>>::deserialize(deserializer)?
+            .map(|x| x.into_iter().map(Into::into).collect()))
+    }
+}
+
+/// Content type filters that can be attached to directory in settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ContentTypePreset {
+    #[display(fmt = "images")]
+    Images,
+    #[display(fmt = "audio")]
+    Audio,
+    #[display(fmt = "video")]
+    Video,
+    #[display(fmt = "documents")]
+    Documents,
+}
+
+impl ContentTypePreset {
+    pub const ALL: [Self; 4] = [Self::Images, Self::Audio, Self::Video, Self::Documents];
+
+    /// Content type filter items for all types of client filter
+    pub fn content_type(self) -> Vec<ContentTypeRequestItem> {
+        const TYPES: [&str; 5] = ["text", "image", "audio", "video", "application"];
+        const ARCHIVE_SUBTYPES: [&str; 5] = [
+            "application/zip",
+            "application/x-rar-compressed",
+            "application/x-7z-compressed",
+            "application/gzip",
+            "application/zlib",
+        ];
+
+        TYPES
+            .into_iter()
+            .map(|type_| match (self, type_) {
+                (Self::Images, "image")
+                | (Self::Audio, "audio")
+                | (Self::Video, "video")
+                | (Self::Documents, "text") => ContentTypeRequestItem::IncludeType {
+                    type_: type_.to_owned(),
+                },
+                (Self::Documents, "application") => ContentTypeRequestItem::ExcludeSubtypes {
+                    type_: type_.to_owned(),
+                    subtypes: ARCHIVE_SUBTYPES.into_iter().map(str::to_owned).collect(),
+                },
+                _ => ContentTypeRequestItem::ExcludeType {
+                    type_: type_.to_owned(),
+                },
+            })
+            .collect()
+    }
+
+    /// Preset with the same content type filter items, if any
+    pub fn from_content_type(items: &[ContentTypeRequestItem]) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.content_type() == items)
+    }
+}
+
+impl FromStr for ContentTypePreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "images" => Ok(Self::Images),
+            "audio" => Ok(Self::Audio),
+            "video" => Ok(Self::Video),
+            "documents" => Ok(Self::Documents),
+            _ => Err(anyhow::anyhow!("Unknown content type preset")),
+        }
+    }
+}
+
+/// How modification of indexed files is detected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+pub enum ChangeDetection {
+    /// Compare last modification time and size
+    #[default]
+    #[display(fmt = "mtime_size")]
+    MtimeSize,
+    /// Also compare hashes of files that look unchanged, requires reading them
+    #[display(fmt = "hash")]
+    Hash,
+}
+
+impl FromStr for ChangeDetection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtime_size" => Ok(Self::MtimeSize),
+            "hash" => Ok(Self::Hash),
+            _ => Err(anyhow::anyhow!("Unknown change detection")),
+        }
+    }
+}
+
+/// Algorithm of hash of file contents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    #[default]
+    #[display(fmt = "sha256")]
+    Sha256,
+    /// Non-cryptographic 64-bit XXH3, much faster to calculate
+    #[display(fmt = "xxh3")]
+    Xxh3,
+    /// Don't calculate hashes, hash change detection falls back to modification time and size
+    #[display(fmt = "none")]
+    None,
+}
+
+impl HashAlgorithm {
+    /// Length of hex digest, to tell hashes calculated by other algorithm
+    pub fn digest_len(self) -> Option<usize> {
+        match self {
+            Self::Sha256 => Some(64),
+            Self::Xxh3 => Some(16),
+            Self::None => None,
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(Self::Sha256),
+            "xxh3" => Ok(Self::Xxh3),
+            "none" => Ok(Self::None),
+            _ => Err(anyhow::anyhow!("Unknown hash algorithm")),
+        }
+    }
 }
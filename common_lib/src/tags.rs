@@ -0,0 +1,55 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Operation on tags of document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TagsOperation {
+    /// Replace all tags
+    Set,
+    /// Add tags to existing ones
+    Add,
+    /// Remove tags from existing ones
+    Remove,
+}
+
+/// Request to change tags of document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagsRequest {
+    pub operation: TagsOperation,
+    pub tags: Vec<String>,
+}
+
+/// Request to replace all tags of document with given `_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetTagsRequest {
+    pub id: String,
+    pub tags: Vec<String>,
+}
+
+/// Trims tags and removes empty ones and duplicates
+pub fn normalize_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    tags.into_iter()
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+impl TagsRequest {
+    /// Applies operation to current tags of document
+    pub fn apply(self, current: Vec<String>) -> Vec<String> {
+        match self.operation {
+            TagsOperation::Set => normalize_tags(self.tags),
+            TagsOperation::Add => normalize_tags(current.into_iter().chain(self.tags)),
+            TagsOperation::Remove => {
+                let removed = normalize_tags(self.tags);
+                normalize_tags(current)
+                    .into_iter()
+                    .filter(|x| !removed.contains(x))
+                    .collect()
+            }
+        }
+    }
+}
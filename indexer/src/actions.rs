@@ -1,22 +1,233 @@
-use axum::{http::StatusCode, Json};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{extract::State, http::StatusCode, Json};
 use common_lib::{
-    actions::{OpenPathArgs, PickFileResult, PickFolderResult},
+    actions::{DeletePathArgs, OpenPathArgs, PickFileResult, PickFolderResult},
+    elasticsearch::{enclosing_archive, ARCHIVE_ENTRY_SEPARATOR, ELASTICSEARCH_INDEX},
+    indexer::IndexingEvent,
     search::SearchRequest,
 };
+use elasticsearch::{DeleteByQueryParts, GetParts};
 use rfd::AsyncFileDialog;
+use serde::Deserialize;
+use serde_json::{json, Value};
 use tracing_unwrap::ResultExt;
 
+use crate::{
+    file_server::add_picked_file,
+    indexer::{check_not_rebuilding, on_event, parse_failures::remove_parse_failure},
+    ServerState,
+};
+
+/// Path of archive that contains entry, or path itself for other files
+fn file_system_path(path: PathBuf, in_archive: bool) -> PathBuf {
+    match enclosing_archive(&path) {
+        Some(archive) if in_archive => archive,
+        _ => path,
+    }
+}
+
+/// Open file, or archive that contains entry
 pub async fn open_path(Json(args): Json<OpenPathArgs>) -> Result<(), (StatusCode, String)> {
-    open::that(args.path).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    let path = file_system_path(args.path, args.in_archive);
+    open::that(path).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
-pub async fn pick_file() -> Json<PickFileResult> {
-    Json(PickFileResult {
-        path: AsyncFileDialog::new()
-            .pick_file()
-            .await
-            .map(|x| x.path().to_owned()),
-    })
+/// Fail if command that shows file in file manager exited with error
+#[cfg(not(target_os = "windows"))]
+fn check_exit_status(status: std::process::ExitStatus) -> std::io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "File manager command failed: {status}"
+        )))
+    }
+}
+
+/// Open folder of file in file manager of operating system with file selected
+#[cfg(target_os = "windows")]
+async fn show_in_file_manager(path: &Path) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // Explorer doesn't accept argument quoted as a whole, so only path is quoted.
+    // It exits with error code even when it succeeds, so only its start is checked
+    std::process::Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .map(|_| ())
+}
+
+/// Open folder of file in file manager of operating system with file selected
+#[cfg(target_os = "macos")]
+async fn show_in_file_manager(path: &Path) -> std::io::Result<()> {
+    let status = tokio::process::Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .status()
+        .await?;
+    check_exit_status(status)
+}
+
+/// Open folder of file in file manager of operating system with file selected,
+/// with `org.freedesktop.FileManager1` D-Bus interface
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+async fn show_in_file_manager(path: &Path) -> std::io::Result<()> {
+    let uri = url::Url::from_file_path(path).map_err(|_| {
+        std::io::Error::new(ErrorKind::InvalidInput, "Path can't be converted to URI")
+    })?;
+    // Commas separate items of array in arguments of `dbus-send`
+    let uri = uri.as_str().replace(',', "%2C");
+    let status = tokio::process::Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{uri}"))
+        // Startup ID isn't known
+        .arg("string:")
+        .stdout(std::process::Stdio::null())
+        .status()
+        .await?;
+    check_exit_status(status)
+}
+
+/// Show file, or archive that contains entry, selected in file manager. If file manager can't
+/// select it, parent folder is opened instead. Missing file is reported with `410 Gone`,
+/// so its document can be removed
+pub async fn reveal_path(Json(args): Json<OpenPathArgs>) -> Result<(), (StatusCode, String)> {
+    let path = file_system_path(args.path, args.in_archive);
+    match tokio::fs::symlink_metadata(&path).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err((
+                StatusCode::GONE,
+                format!("File no longer exists: {}", path.display()),
+            ));
+        }
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+
+    if let Err(e) = show_in_file_manager(&path).await {
+        tracing::warn!(
+            "Can't show {} in file manager: {}, opening its folder",
+            path.display(),
+            e
+        );
+        let parent = path.parent().unwrap_or(&path).to_owned();
+        open::that(parent).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct DocumentPath {
+    path: PathBuf,
+    /// Stored only for entries of archives
+    #[serde(default)]
+    in_archive: bool,
+}
+
+/// Move file of document to trash of operating system, it's never deleted permanently.
+/// Then its document and documents of its entries, if it's archive, are removed from index.
+/// If file no longer exists, only documents are removed
+pub async fn delete_path(
+    State(state): State<Arc<ServerState>>,
+    Json(args): Json<DeletePathArgs>,
+) -> Result<(), (StatusCode, String)> {
+    if !state.settings.read().await.file_actions_enabled {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "File actions are disabled in settings".to_owned(),
+        ));
+    }
+    check_not_rebuilding(&state).await?;
+
+    // Path is taken from index, so only indexed files can be moved
+    let es_response = state
+        .es_client()
+        .await
+        .get(GetParts::IndexId(ELASTICSEARCH_INDEX, &args.id))
+        ._source(&["path", "in_archive"])
+        .send()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if es_response.status_code() == StatusCode::NOT_FOUND {
+        return Err((StatusCode::NOT_FOUND, "Document not found".to_owned()));
+    }
+    let es_response_body = es_response
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let DocumentPath { path, in_archive } =
+        serde_json::from_value(es_response_body["_source"].clone())
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if in_archive {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Entries of archives can't be moved to trash".to_owned(),
+        ));
+    }
+
+    match tokio::fs::symlink_metadata(&path).await {
+        Ok(_) => {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || trash::delete(path))
+                .await
+                .unwrap_or_log()
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            tracing::info!("File was already removed: {}", path.display());
+        }
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+
+    let entries_prefix = format!("{}{}", path.display(), ARCHIVE_ENTRY_SEPARATOR);
+    state
+        .es_client()
+        .await
+        .delete_by_query(DeleteByQueryParts::Index(&[ELASTICSEARCH_INDEX]))
+        .refresh(true)
+        .body(json!({
+            "query": {
+                "bool": {
+                    "should": [
+                        { "ids": { "values": [args.id] } },
+                        { "prefix": { "path.keyword": entries_prefix } }
+                    ]
+                }
+            }
+        }))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    remove_parse_failure(&state, &path).await;
+    on_event(state, IndexingEvent::FileDeleted(path)).await;
+    Ok(())
+}
+
+/// Select file in dialog, it can be read with `/file` afterwards
+pub async fn pick_file(State(state): State<Arc<ServerState>>) -> Json<PickFileResult> {
+    let path = AsyncFileDialog::new()
+        .pick_file()
+        .await
+        .map(|x| x.path().to_owned());
+    if let Some(x) = &path {
+        if let Ok(canonical_path) = tokio::fs::canonicalize(x).await {
+            add_picked_file(&state, canonical_path).await;
+        }
+    }
+    Json(PickFileResult { path })
 }
 
 pub async fn pick_folder() -> Json<PickFolderResult> {
@@ -58,3 +269,52 @@ pub async fn save_request(Json(request): Json<SearchRequest>) -> Result<(), (Sta
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{test_settings, test_state, MockElasticsearch};
+
+    use super::*;
+
+    #[test]
+    fn archive_is_used_only_for_entries() {
+        let path = PathBuf::from("/files/a.zip!/b.txt");
+        assert_eq!(
+            file_system_path(path.clone(), true),
+            PathBuf::from("/files/a.zip")
+        );
+        // Name of directory can end with separator of entries
+        assert_eq!(file_system_path(path.clone(), false), path);
+    }
+
+    #[tokio::test]
+    async fn only_files_that_are_not_entries_are_moved_to_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("a.zip!").join("b.txt");
+        std::fs::create_dir(file_path.parent().unwrap()).unwrap();
+        std::fs::write(&file_path, "b").unwrap();
+        let es = MockElasticsearch::with_documents([
+            ("file".to_owned(), json!({ "path": file_path })),
+            (
+                "entry".to_owned(),
+                json!({ "path": dir.path().join("c.zip!/d.txt"), "in_archive": true }),
+            ),
+        ]);
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        settings.file_actions_enabled = true;
+        let state = test_state(settings).await;
+
+        let delete = |id: &str| {
+            delete_path(
+                State(Arc::clone(&state)),
+                Json(DeletePathArgs { id: id.to_owned() }),
+            )
+        };
+        let (status, _) = delete("entry").await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        delete("file").await.unwrap();
+        let documents = es.documents.lock().unwrap();
+        assert_eq!(documents.keys().collect::<Vec<_>>(), ["entry"]);
+    }
+}
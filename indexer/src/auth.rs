@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use common_lib::auth::tokens_match;
+
+use crate::ServerState;
+
+/// Token from `Authorization: Bearer` header or `token` query parameter.
+/// Query parameter is used where headers can't be set: WebSocket, images and beacons
+fn request_token<B>(request: &Request<B>) -> Option<String> {
+    let header_token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .map(str::to_owned);
+    header_token.or_else(|| {
+        url::form_urlencoded::parse(request.uri().query()?.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    })
+}
+
+/// Reject requests without API token from settings, if it's set
+pub async fn require_token<B>(
+    State(state): State<Arc<ServerState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, (StatusCode, String)> {
+    let api_token = state.settings.read().await.api_token.clone();
+    if let Some(api_token) = api_token.filter(|x| !x.is_empty()) {
+        if !request_token(&request).is_some_and(|x| tokens_match(&x, &api_token)) {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid API token".to_owned(),
+            ));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{middleware, routing::get, Router};
+    use reqwest::Url;
+
+    use crate::{
+        file_server,
+        test_util::{serve, test_settings, test_state},
+    };
+
+    use super::*;
+
+    const TOKEN: &str = "secret token";
+
+    /// Protected routes, including ones of file server, and one route without token
+    async fn serve_app(api_token: Option<&str>) -> Url {
+        let mut settings = test_settings();
+        settings.api_token = api_token.map(str::to_owned);
+        let state = test_state(settings).await;
+        serve(
+            Router::new()
+                .route("/protected", get(|| async { "ok" }))
+                .route("/file", get(file_server::get_file))
+                .route("/document_content", get(file_server::get_document_content))
+                .route_layer(middleware::from_fn_with_state(
+                    Arc::clone(&state),
+                    require_token,
+                ))
+                .route("/health", get(|| async { "ok" }))
+                .with_state(state),
+        )
+    }
+
+    async fn get_status(url: Url, bearer: Option<&str>) -> StatusCode {
+        let mut request = reqwest::Client::new().get(url);
+        if let Some(bearer) = bearer {
+            request = request.bearer_auth(bearer);
+        }
+        let status = request.send().await.unwrap().status();
+        StatusCode::from_u16(status.as_u16()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_without_valid_token_are_unauthorized() {
+        let url = serve_app(Some(TOKEN)).await;
+        for path in [
+            "protected",
+            "file?path=/tmp/a.txt&thumbnail=false",
+            "document_content?id=1",
+        ] {
+            let path_url = url.join(path).unwrap();
+            assert_eq!(
+                get_status(path_url.clone(), None).await,
+                StatusCode::UNAUTHORIZED,
+                "{path}"
+            );
+            assert_eq!(
+                get_status(path_url.clone(), Some("wrong")).await,
+                StatusCode::UNAUTHORIZED,
+                "{path}"
+            );
+            let mut query_url = path_url;
+            query_url.query_pairs_mut().append_pair("token", "wrong");
+            assert_eq!(
+                get_status(query_url, None).await,
+                StatusCode::UNAUTHORIZED,
+                "{path}"
+            );
+        }
+        assert_eq!(
+            get_status(url.join("health").unwrap(), None).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_with_token_in_header_or_query_are_accepted() {
+        let url = serve_app(Some(TOKEN)).await;
+        assert_eq!(
+            get_status(url.join("protected").unwrap(), Some(TOKEN)).await,
+            StatusCode::OK
+        );
+        let mut query_url = url.join("protected").unwrap();
+        query_url.query_pairs_mut().append_pair("token", TOKEN);
+        assert_eq!(get_status(query_url, None).await, StatusCode::OK);
+
+        // File server checks access to file only after token is accepted
+        let mut file_url = url.join("file").unwrap();
+        file_url
+            .query_pairs_mut()
+            .append_pair("path", "/nonexistent/a.txt")
+            .append_pair("thumbnail", "false")
+            .append_pair("token", TOKEN);
+        assert_ne!(get_status(file_url, None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn requests_are_accepted_when_token_is_not_set() {
+        for api_token in [None, Some("")] {
+            let url = serve_app(api_token).await;
+            assert_eq!(
+                get_status(url.join("protected").unwrap(), None).await,
+                StatusCode::OK
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::Utc;
+use common_lib::client_error::{ClientError, ClientErrorReport, CLIENT_ERROR_MESSAGE_MAX_LEN};
+use tracing_unwrap::ResultExt;
+
+use crate::ServerState;
+
+const CLIENT_ERRORS_FILE_PATH: &str = "client_errors.jsonl";
+/// Maximum number of client errors kept in log file, older ones are removed
+const CLIENT_ERRORS_MAX_CNT: usize = 200;
+
+async fn read_client_errors_file() -> Vec<ClientError> {
+    match tokio::fs::read_to_string(CLIENT_ERRORS_FILE_PATH).await {
+        Ok(s) => s
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Add client error to log file, if reporting is enabled.
+/// Body is plain text because client sends it as beacon
+pub async fn report_client_error(
+    State(state): State<Arc<ServerState>>,
+    body: String,
+) -> Result<(), (StatusCode, String)> {
+    if !state.settings.read().await.client.report_errors {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Client error reporting is disabled".to_owned(),
+        ));
+    }
+    let mut report: ClientErrorReport =
+        serde_json::from_str(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if let Some((idx, _)) = report
+        .message
+        .char_indices()
+        .nth(CLIENT_ERROR_MESSAGE_MAX_LEN)
+    {
+        report.message.truncate(idx);
+    }
+    tracing::warn!("Client error: {}", report.message);
+
+    let _lock = state.client_errors_lock.lock().await;
+    let mut errors = read_client_errors_file().await;
+    errors.push(ClientError {
+        time: Utc::now(),
+        report,
+    });
+    let skip_cnt = errors.len().saturating_sub(CLIENT_ERRORS_MAX_CNT);
+    let s: String = errors[skip_cnt..]
+        .iter()
+        .map(|x| serde_json::to_string(x).unwrap_or_log() + "\n")
+        .collect();
+    tokio::fs::write(CLIENT_ERRORS_FILE_PATH, s)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Get reported client errors, newest first
+pub async fn get_client_errors(State(state): State<Arc<ServerState>>) -> Json<Vec<ClientError>> {
+    let _lock = state.client_errors_lock.lock().await;
+    let mut errors = read_client_errors_file().await;
+    errors.reverse();
+    Json(errors)
+}
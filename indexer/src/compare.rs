@@ -0,0 +1,132 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use common_lib::{
+    compare::{CompareQuery, CompareResponse, DiffLine, FieldComparison, COMPARE_CONTENT_MAX_LEN},
+    elasticsearch::{FileES, ELASTICSEARCH_INDEX},
+};
+use elasticsearch::GetParts;
+use serde_json::Value;
+use similar::{ChangeTag, TextDiff};
+
+use crate::ServerState;
+
+/// Fields that aren't compared as metadata
+const NOT_COMPARED_FIELDS: [&str; 5] = [
+    "_id",
+    "content",
+    "summary",
+    "text_embedding",
+    "image_embedding",
+];
+
+async fn get_document(state: &ServerState, id: &str) -> Result<FileES, (StatusCode, String)> {
+    let es_response = state
+        .es_client()
+        .await
+        .get(GetParts::IndexId(ELASTICSEARCH_INDEX, id))
+        .send()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if es_response.status_code() == StatusCode::NOT_FOUND {
+        return Err((StatusCode::NOT_FOUND, format!("Document not found: {id}")));
+    }
+    let es_response_body = es_response
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    serde_json::from_value(es_response_body["_source"].clone())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Metadata fields of document as strings
+fn metadata_fields(file: &FileES) -> BTreeMap<String, String> {
+    let Value::Object(fields) = serde_json::to_value(file).unwrap() else {
+        return BTreeMap::new();
+    };
+    fields
+        .into_iter()
+        .filter(|(field, value)| !NOT_COMPARED_FIELDS.contains(&field.as_str()) && !value.is_null())
+        .map(|(field, value)| match value {
+            Value::String(s) => (field, s),
+            _ => (field, value.to_string()),
+        })
+        .collect()
+}
+
+/// Beginning of text no longer than maximum length, and whether it was truncated
+fn truncate(text: &str) -> (&str, bool) {
+    if text.len() <= COMPARE_CONTENT_MAX_LEN {
+        return (text, false);
+    }
+    let mut end = COMPARE_CONTENT_MAX_LEN;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&text[..end], true)
+}
+
+fn diff_lines(content_a: &str, content_b: &str) -> (Vec<DiffLine>, bool) {
+    let (content_a, truncated_a) = truncate(content_a);
+    let (content_b, truncated_b) = truncate(content_b);
+    let diff = TextDiff::from_lines(content_a, content_b)
+        .iter_all_changes()
+        .map(|change| {
+            let line = change.value().trim_end_matches(['\r', '\n']).to_owned();
+            match change.tag() {
+                ChangeTag::Equal => DiffLine::Equal(line),
+                ChangeTag::Delete => DiffLine::Removed(line),
+                ChangeTag::Insert => DiffLine::Added(line),
+            }
+        })
+        .collect();
+    (diff, truncated_a || truncated_b)
+}
+
+/// Compare metadata and content of two documents
+pub async fn compare_documents(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<CompareQuery>,
+) -> Result<Json<CompareResponse>, (StatusCode, String)> {
+    let mut file_a = get_document(&state, &params.id_a).await?;
+    let mut file_b = get_document(&state, &params.id_b).await?;
+
+    let mut fields_a = metadata_fields(&file_a);
+    let mut fields_b = metadata_fields(&file_b);
+    let mut field_names: Vec<_> = fields_a.keys().chain(fields_b.keys()).cloned().collect();
+    field_names.sort_unstable();
+    field_names.dedup();
+    let fields = field_names
+        .into_iter()
+        .map(|field| FieldComparison {
+            value_a: fields_a.remove(&field),
+            value_b: fields_b.remove(&field),
+            field,
+        })
+        .collect();
+
+    // Binary files and files without text have only metadata compared
+    let (content_diff, content_truncated) = match (file_a.content.take(), file_b.content.take()) {
+        (Some(content_a), Some(content_b)) => {
+            // Diff is calculated in quadratic time in the worst case
+            let (diff, truncated) =
+                tokio::task::spawn_blocking(move || diff_lines(&content_a, &content_b))
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            (Some(diff), truncated)
+        }
+        _ => (None, false),
+    };
+
+    Ok(Json(CompareResponse {
+        path_a: file_a.path,
+        path_b: file_b.path,
+        fields,
+        content_diff,
+        content_truncated,
+    }))
+}
@@ -0,0 +1,204 @@
+use std::{cmp::Reverse, collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use common_lib::{
+    duplicates::{
+        DuplicateFile, DuplicateGroup, DuplicatesPage, DuplicatesQuery, DUPLICATES_PAGE_SIZE,
+        DUPLICATE_GROUP_MAX_FILES,
+    },
+    elasticsearch::ELASTICSEARCH_INDEX,
+};
+use elasticsearch::SearchParts;
+use serde_json::{json, Value};
+
+use crate::ServerState;
+
+/// Number of distinct hashes in each request of composite aggregation
+const HASHES_SCAN_PAGE_SIZE: u32 = 10_000;
+
+async fn search(state: &ServerState, body: Value) -> Result<Value, (StatusCode, String)> {
+    state
+        .es_client()
+        .await
+        .search(SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+        .body(body)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Filters of files that are compared
+fn duplicates_filter(query: &DuplicatesQuery) -> Vec<Value> {
+    let mut filter = vec![json!({
+        "range": {
+            "size": {
+                "gte": query.min_size
+            }
+        }
+    })];
+    if let Some(path_prefix) = &query.path_prefix {
+        filter.push(json!({
+            "term": {
+                "path.hierarchy": path_prefix.to_string_lossy().replace('\\', "/")
+            }
+        }));
+    }
+    filter
+}
+
+/// Hashes of files with duplicates and their numbers of files, in order of hashes.
+/// Distinct hashes are scanned with composite aggregation until page is filled,
+/// returned key continues scanning after the last returned hash
+async fn scan_duplicate_hashes(
+    state: &ServerState,
+    filter: &[Value],
+    mut after: Option<String>,
+) -> Result<(Vec<(String, u64)>, Option<String>), (StatusCode, String)> {
+    let mut hashes = Vec::new();
+    loop {
+        let mut composite = json!({
+            "size": HASHES_SCAN_PAGE_SIZE,
+            "sources": [
+                { "hash": { "terms": { "field": "hash" } } }
+            ]
+        });
+        if let Some(after) = &after {
+            composite["after"] = json!({ "hash": after });
+        }
+        let es_response_body = search(
+            state,
+            json!({
+                "size": 0,
+                "query": {
+                    "bool": {
+                        "filter": filter
+                    }
+                },
+                "aggs": {
+                    "hashes": {
+                        "composite": composite,
+                        "aggs": {
+                            // Composite aggregation doesn't support `min_doc_count`
+                            "duplicates": {
+                                "bucket_selector": {
+                                    "buckets_path": { "count": "_count" },
+                                    "script": "params.count > 1"
+                                }
+                            }
+                        }
+                    }
+                }
+            }),
+        )
+        .await?;
+
+        let aggregation = &es_response_body["aggregations"]["hashes"];
+        hashes.extend(
+            aggregation["buckets"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|x| {
+                    Some((
+                        x["key"]["hash"].as_str()?.to_owned(),
+                        x["doc_count"].as_u64()?,
+                    ))
+                }),
+        );
+        after = aggregation["after_key"]["hash"].as_str().map(str::to_owned);
+
+        if hashes.len() >= DUPLICATES_PAGE_SIZE {
+            hashes.truncate(DUPLICATES_PAGE_SIZE);
+            let last_hash = hashes.last().map(|(hash, _)| hash.clone());
+            return Ok((hashes, last_hash));
+        }
+        if after.is_none() {
+            return Ok((hashes, None));
+        }
+    }
+}
+
+/// Page of groups of files with the same hash, with the most wasted space first
+pub async fn get_duplicates(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<DuplicatesQuery>,
+) -> Result<Json<DuplicatesPage>, (StatusCode, String)> {
+    let filter = duplicates_filter(&query);
+    let (hashes, after) = scan_duplicate_hashes(&state, &filter, query.after).await?;
+    if hashes.is_empty() {
+        return Ok(Json(DuplicatesPage {
+            groups: Vec::new(),
+            after,
+        }));
+    }
+
+    // Each group is collapsed into one hit, with its files in inner hits
+    let file_cnts: HashMap<_, _> = hashes.into_iter().collect();
+    let es_response_body = search(
+        &state,
+        json!({
+            "size": file_cnts.len(),
+            "_source": ["hash", "size"],
+            "query": {
+                "bool": {
+                    "filter": filter,
+                    "must": {
+                        "terms": {
+                            "hash": file_cnts.keys().collect::<Vec<_>>()
+                        }
+                    }
+                }
+            },
+            "collapse": {
+                "field": "hash",
+                "inner_hits": {
+                    "name": "files",
+                    "size": DUPLICATE_GROUP_MAX_FILES,
+                    "_source": ["path", "in_archive"],
+                    "sort": [{ "path.keyword": "asc" }]
+                }
+            }
+        }),
+    )
+    .await?;
+
+    let mut groups: Vec<_> = es_response_body["hits"]["hits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|hit| {
+            let hash = hit["_source"]["hash"].as_str()?.to_owned();
+            let size = hit["_source"]["size"].as_u64()?;
+            let file_cnt = *file_cnts.get(&hash)?;
+            let files = hit["inner_hits"]["files"]["hits"]["hits"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|x| {
+                    Some(DuplicateFile {
+                        id: x["_id"].as_str()?.to_owned(),
+                        path: x["_source"]["path"].as_str()?.into(),
+                        in_archive: x["_source"]["in_archive"].as_bool().unwrap_or_default(),
+                    })
+                })
+                .collect();
+            Some(DuplicateGroup {
+                hash,
+                size,
+                file_cnt,
+                wasted_size: size * (file_cnt - 1),
+                files,
+            })
+        })
+        .collect();
+    groups.sort_by_key(|x| Reverse(x.wasted_size));
+    Ok(Json(DuplicatesPage { groups, after }))
+}
@@ -1,15 +1,45 @@
-use std::path::Path;
+use std::{fmt, path::Path, sync::Arc, time::Duration};
 
-use common_lib::BatchRequest;
+use anyhow::anyhow;
+use common_lib::{BatchRequest, ImageBatchRequest, NNServerHealth};
 use serde::Deserialize;
 use serde_json::json;
+use tokio::{sync::oneshot, time::sleep};
 use url::Url;
 
+use crate::{fault_injection::embedding_fault, telemetry::RequestTimer, ServerState};
+
+/// Maximum time that image waits for images of other files to be sent with them
+const IMAGE_BATCH_DELAY: Duration = Duration::from_millis(20);
+/// Maximum total size of images in batch, it's sent earlier when reached
+const IMAGE_BATCH_MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/// Context of errors of embedding calculation, separates them from other errors of parsing
+#[derive(Debug)]
+pub struct EmbeddingError;
+
+impl fmt::Display for EmbeddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error calculating embedding")
+    }
+}
+
 #[derive(Deserialize)]
 pub struct ImageEmbedding {
     pub embedding: Option<Vec<f32>>,
 }
 
+/// Image waiting to be sent in batch, with sender of its embedding
+type QueuedImage = (Vec<u8>, oneshot::Sender<anyhow::Result<ImageEmbedding>>);
+
+/// Images of concurrently processed files that are sent to neural network server in one request
+#[derive(Default)]
+pub struct ImageBatch {
+    images: Vec<QueuedImage>,
+    /// Total size of images
+    bytes: usize,
+}
+
 #[derive(Deserialize)]
 pub struct TextEmbedding {
     pub embedding: Vec<f32>,
@@ -32,7 +62,9 @@ pub async fn get_image_search_image_embedding_generic<T: Into<reqwest::Body>>(
     batch_request: BatchRequest,
     image: T,
 ) -> anyhow::Result<ImageEmbedding> {
+    embedding_fault().await?;
     nn_server_url.set_path("clip/image");
+    let _timer = RequestTimer::start("nn_server", "clip/image");
     let req_builder = reqwest_client.post(nn_server_url).query(&batch_request);
     let response = req_builder.body(image).send().await?;
     if response.status().is_client_error() {
@@ -53,13 +85,109 @@ pub async fn get_image_search_image_embedding(
         .await
 }
 
+/// Embeddings of several images in one request, in the same order as images
+pub async fn get_image_search_image_embeddings(
+    reqwest_client: &reqwest_middleware::ClientWithMiddleware,
+    mut nn_server_url: Url,
+    batch_request: BatchRequest,
+    images: Vec<Vec<u8>>,
+) -> anyhow::Result<Vec<ImageEmbedding>> {
+    embedding_fault().await?;
+    nn_server_url.set_path("clip/image_batch");
+    let _timer = RequestTimer::start("nn_server", "clip/image_batch");
+    let images_cnt = images.len();
+    let req_builder = reqwest_client.post(nn_server_url).query(&batch_request);
+    let embeddings: Vec<ImageEmbedding> = req_builder
+        .json(&ImageBatchRequest { images })
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if embeddings.len() != images_cnt {
+        return Err(anyhow!(
+            "Expected {} embeddings of images, received {}",
+            images_cnt,
+            embeddings.len()
+        ));
+    }
+    Ok(embeddings)
+}
+
+/// Embedding of image of indexed file, calculated together with images of other concurrently
+/// processed files. Batch is sent when it has image of each of them, when its images are too
+/// large, or after short delay since its first image
+pub async fn get_image_search_image_embedding_batched(
+    state: &Arc<ServerState>,
+    image: Vec<u8>,
+) -> anyhow::Result<ImageEmbedding> {
+    let max_images = state.settings.read().await.max_concurrent_files;
+    let (tx, rx) = oneshot::channel();
+    let (full_batch, is_first) = {
+        let mut batch = state.image_batch.lock().await;
+        batch.bytes += image.len();
+        batch.images.push((image, tx));
+        let is_first = batch.images.len() == 1;
+        let is_full = batch.images.len() >= max_images || batch.bytes >= IMAGE_BATCH_MAX_BYTES;
+        (is_full.then(|| std::mem::take(&mut *batch)), is_first)
+    };
+    match full_batch {
+        Some(batch) => {
+            tokio::spawn(send_image_batch(Arc::clone(state), batch));
+        }
+        None if is_first => {
+            let state = Arc::clone(state);
+            tokio::spawn(async move {
+                sleep(IMAGE_BATCH_DELAY).await;
+                // Batch could have been sent and started again, then the new one is sent earlier
+                let batch = std::mem::take(&mut *state.image_batch.lock().await);
+                send_image_batch(state, batch).await;
+            });
+        }
+        None => {}
+    }
+    rx.await?
+}
+
+/// Send batch of images and pass embedding or error to file of each image
+async fn send_image_batch(state: Arc<ServerState>, batch: ImageBatch) {
+    if batch.images.is_empty() {
+        return;
+    }
+    let (images, senders): (Vec<_>, Vec<_>) = batch.images.into_iter().unzip();
+    let nn_server_url = state.settings.read().await.nn_server_url.clone();
+    // Batch is complete, so it isn't delayed by neural network server
+    let res = get_image_search_image_embeddings(
+        &state.reqwest_client,
+        nn_server_url,
+        BatchRequest { batched: false },
+        images,
+    )
+    .await;
+    match res {
+        Ok(embeddings) => {
+            for (sender, embedding) in senders.into_iter().zip(embeddings) {
+                _ = sender.send(Ok(embedding));
+            }
+        }
+        Err(e) => {
+            let message = format!("{e:#}");
+            for sender in senders {
+                _ = sender.send(Err(anyhow!(message.clone())));
+            }
+        }
+    }
+}
+
 pub async fn get_image_search_text_embedding(
     reqwest_client: &reqwest_middleware::ClientWithMiddleware,
     mut nn_server_url: Url,
     batch_request: BatchRequest,
     text: &str,
 ) -> anyhow::Result<TextEmbedding> {
+    embedding_fault().await?;
     nn_server_url.set_path("clip/text");
+    let _timer = RequestTimer::start("nn_server", "clip/text");
     let req_builder = reqwest_client.post(nn_server_url).query(&batch_request);
     let embedding = req_builder
         .json(&json!({ "text": text }))
@@ -77,7 +205,9 @@ pub async fn get_text_search_embedding(
     text: &str,
     summary_enabled: bool,
 ) -> anyhow::Result<SummaryTextEmbedding> {
+    embedding_fault().await?;
     nn_server_url.set_path("minilm/text");
+    let _timer = RequestTimer::start("nn_server", "minilm/text");
     let req_builder = reqwest_client.post(nn_server_url).query(&batch_request);
     let embedding = req_builder
         .json(&json!({
@@ -99,6 +229,7 @@ pub async fn get_rerank_scores(
     paragraphs: Vec<String>,
 ) -> anyhow::Result<Scores> {
     nn_server_url.set_path("minilm/rerank");
+    let _timer = RequestTimer::start("nn_server", "minilm/rerank");
     let req_builder = reqwest_client.post(nn_server_url).query(&batch_request);
     let embedding = req_builder
         .json(&json!({
@@ -111,3 +242,105 @@ pub async fn get_rerank_scores(
         .await?;
     Ok(embedding)
 }
+
+/// Devices that models of neural network server are running on
+pub async fn get_nn_server_health(
+    reqwest_client: &reqwest_middleware::ClientWithMiddleware,
+    mut nn_server_url: Url,
+) -> anyhow::Result<NNServerHealth> {
+    nn_server_url.set_path("health");
+    let health = reqwest_client
+        .get(nn_server_url)
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(health)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use axum::{extract::DefaultBodyLimit, routing::post, Json, Router};
+
+    use crate::test_util::{serve, test_settings, test_state};
+
+    use super::*;
+
+    /// State with neural network server that records sizes of image batches
+    /// and returns length of each image as its embedding
+    async fn batch_test_state(
+        max_concurrent_files: usize,
+    ) -> (Arc<ServerState>, Arc<StdMutex<Vec<usize>>>) {
+        let batches = Arc::new(StdMutex::new(Vec::new()));
+        let router = Router::new()
+            .route(
+                "/clip/image_batch",
+                post({
+                    let batches = Arc::clone(&batches);
+                    move |Json(request): Json<ImageBatchRequest>| async move {
+                        batches.lock().unwrap().push(request.images.len());
+                        let embeddings: Vec<_> = request
+                            .images
+                            .iter()
+                            .map(|x| json!({ "embedding": [x.len()] }))
+                            .collect();
+                        Json(embeddings)
+                    }
+                }),
+            )
+            .layer(DefaultBodyLimit::disable());
+        let mut settings = test_settings();
+        settings.nn_server_url = serve(router);
+        settings.max_concurrent_files = max_concurrent_files;
+        (test_state(settings).await, batches)
+    }
+
+    async fn embedding(state: &Arc<ServerState>, image_len: usize) -> Vec<f32> {
+        get_image_search_image_embedding_batched(state, vec![0; image_len])
+            .await
+            .unwrap()
+            .embedding
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn full_batch_is_sent_without_waiting_for_delay() {
+        let (state, batches) = batch_test_state(2).await;
+
+        let embeddings = tokio::join!(
+            embedding(&state, 1),
+            embedding(&state, 2),
+            embedding(&state, 3)
+        );
+
+        assert_eq!(embeddings, (vec![1.0], vec![2.0], vec![3.0]));
+        let mut batches = batches.lock().unwrap().clone();
+        batches.sort();
+        assert_eq!(batches, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn incomplete_batch_is_sent_after_delay() {
+        let (state, batches) = batch_test_state(4).await;
+
+        let embeddings = tokio::join!(embedding(&state, 1), embedding(&state, 2));
+
+        assert_eq!(embeddings, (vec![1.0], vec![2.0]));
+        assert_eq!(*batches.lock().unwrap(), [2]);
+    }
+
+    #[tokio::test]
+    async fn batch_of_large_images_is_sent_early() {
+        let (state, batches) = batch_test_state(4).await;
+
+        let embeddings = tokio::join!(
+            embedding(&state, IMAGE_BATCH_MAX_BYTES),
+            embedding(&state, 1)
+        );
+
+        assert_eq!(embeddings, (vec![IMAGE_BATCH_MAX_BYTES as f32], vec![1.0]));
+        assert_eq!(*batches.lock().unwrap(), [1, 1]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Fault injection for exercising error paths of indexing pipeline.
+//! Enabled only with `fault_injection` feature, configured with TOML in
+//! `DESKTOP_SEARCH_FAULTS` environment variable. Tests always inject faults
+//! into files with paths containing names of hooks, like `fault_parse_file`
+
+use std::{io, path::Path, time::Duration};
+
+use serde_json::Value;
+
+/// Points of indexing pipeline where faults can be injected
+pub trait FaultInjector: Send + Sync {
+    /// Replace contents of file before parsing, as if it was modified after scan
+    fn change_file(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Fail parsing of file
+    fn parse_file(&self, _path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Delay before embedding request
+    fn embedding_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Fail embedding request
+    fn embedding(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Fail bulk operation instead of sending it to Elasticsearch
+    fn bulk_item(&self, _path: &Path, _action: &Value) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Fail reading of file metadata
+    fn metadata(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Injector that never fails
+#[cfg(not(test))]
+struct NoFaults;
+
+#[cfg(not(test))]
+impl FaultInjector for NoFaults {}
+
+#[cfg(all(not(feature = "fault_injection"), not(test)))]
+pub fn injector() -> &'static dyn FaultInjector {
+    &NoFaults
+}
+
+#[cfg(all(feature = "fault_injection", not(test)))]
+pub fn injector() -> &'static dyn FaultInjector {
+    use once_cell::sync::OnceCell;
+    use tracing_unwrap::ResultExt;
+
+    static INJECTOR: OnceCell<Box<dyn FaultInjector>> = OnceCell::new();
+
+    INJECTOR
+        .get_or_init(|| match std::env::var(config::FAULTS_ENV_VAR) {
+            Ok(config) => {
+                tracing::warn!("Fault injection is enabled");
+                Box::new(
+                    toml::from_str::<config::FaultsConfig>(&config)
+                        .expect_or_log("Can't parse fault injection config"),
+                )
+            }
+            Err(_) => Box::new(NoFaults),
+        })
+        .as_ref()
+}
+
+#[cfg(test)]
+pub fn injector() -> &'static dyn FaultInjector {
+    use std::sync::OnceLock;
+
+    static INJECTOR: OnceLock<config::FaultsConfig> = OnceLock::new();
+
+    INJECTOR.get_or_init(|| {
+        toml::from_str(
+            r#"
+            change_file = { probability = 1.0, path_regex = "fault_change_file" }
+            parse_file = { probability = 1.0, path_regex = "fault_parse_file" }
+            bulk_item = { probability = 1.0, path_regex = "fault_bulk_item" }
+            metadata = { probability = 1.0, path_regex = "fault_metadata" }
+            "#,
+        )
+        .unwrap()
+    })
+}
+
+/// Wait and possibly fail before embedding request
+pub async fn embedding_fault() -> anyhow::Result<()> {
+    if let Some(delay) = injector().embedding_delay() {
+        tokio::time::sleep(delay).await;
+    }
+    injector().embedding()
+}
+
+#[cfg(any(feature = "fault_injection", test))]
+mod config {
+    use std::{
+        fs::File,
+        io::{self, Write},
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+
+    use anyhow::anyhow;
+    use rand::Rng;
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer};
+    use serde_json::Value;
+
+    use super::FaultInjector;
+
+    #[cfg(not(test))]
+    pub const FAULTS_ENV_VAR: &str = "DESKTOP_SEARCH_FAULTS";
+
+    /// Fault probability, restricted to files with paths matching regex
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    pub struct PathFault {
+        pub probability: f64,
+        #[serde(deserialize_with = "deserialize_regex")]
+        pub path_regex: Option<Regex>,
+    }
+
+    impl PathFault {
+        fn happens(&self, path: &Path) -> bool {
+            if let Some(re) = &self.path_regex {
+                if !re.is_match(&path.to_string_lossy()) {
+                    return false;
+                }
+            }
+            happens(self.probability)
+        }
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    pub struct FaultsConfig {
+        pub change_file: PathFault,
+        pub parse_file: PathFault,
+        pub embedding_delay_ms: u64,
+        pub embedding_error_probability: f64,
+        pub bulk_item: PathFault,
+        pub metadata: PathFault,
+    }
+
+    fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|x| Regex::new(&x).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+
+    fn happens(probability: f64) -> bool {
+        probability > 0.0 && rand::thread_rng().gen_bool(probability.min(1.0))
+    }
+
+    /// Contents and modification time of changed file, they are the same after repeated changes
+    const CHANGED_CONTENTS: &[u8] = b"Injected change";
+    const CHANGED_MODIFIED_SECS: u64 = 86400;
+
+    impl FaultInjector for FaultsConfig {
+        fn change_file(&self, path: &Path) -> io::Result<()> {
+            if self.change_file.happens(path) {
+                let mut file = File::create(path)?;
+                file.write_all(CHANGED_CONTENTS)?;
+                file.set_modified(
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(CHANGED_MODIFIED_SECS),
+                )?;
+            }
+            Ok(())
+        }
+
+        fn parse_file(&self, path: &Path) -> anyhow::Result<()> {
+            if self.parse_file.happens(path) {
+                return Err(anyhow!("Injected parsing error: {}", path.display()));
+            }
+            Ok(())
+        }
+
+        fn embedding_delay(&self) -> Option<Duration> {
+            (self.embedding_delay_ms > 0).then(|| Duration::from_millis(self.embedding_delay_ms))
+        }
+
+        fn embedding(&self) -> anyhow::Result<()> {
+            if happens(self.embedding_error_probability) {
+                return Err(anyhow!("Injected embedding error"));
+            }
+            Ok(())
+        }
+
+        fn bulk_item(&self, path: &Path, action: &Value) -> anyhow::Result<()> {
+            if self.bulk_item.happens(path) {
+                return Err(anyhow!("Injected bulk item error: {}", action));
+            }
+            Ok(())
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<()> {
+            if self.metadata.happens(path) {
+                return Err(io::Error::other(format!(
+                    "Injected metadata error: {}",
+                    path.display()
+                )));
+            }
+            Ok(())
+        }
+    }
+}
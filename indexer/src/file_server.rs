@@ -1,22 +1,37 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use axum::{
     body::{boxed, Body, BoxBody},
     extract::{Query, State},
-    http::{HeaderMap, Request, StatusCode, Uri},
+    http::{header, HeaderMap, Request, StatusCode, Uri},
     response::Response,
     Json,
 };
-use common_lib::{elasticsearch::ELASTICSEARCH_INDEX, ClientTranslation};
+use chrono::{DateTime, Utc};
+use common_lib::{
+    elasticsearch::ELASTICSEARCH_INDEX, ClientTranslation, BINARY_CONTENT_ERROR,
+    CONTENT_ENCODING_HEADER, CONTENT_NEXT_OFFSET_HEADER, CONTENT_TOTAL_LENGTH_HEADER,
+};
 use rust_embed::RustEmbed;
 use serde::Deserialize;
-use serde_json::Value;
+use serde_json::{json, Value};
 use tower::ServiceExt;
 use tower_http::services::ServeFile;
 use tracing_unwrap::{OptionExt, ResultExt};
 use unic_langid::LanguageIdentifier;
 
-use crate::{thumbnails::get_thumbnail, ServerState};
+use crate::{
+    thumbnails::{cache, get_scaled_image, get_thumbnail, thumbnail_format},
+    ServerState,
+};
+
+use self::content::{indexed_part, read_file_part};
+
+mod content;
 
 #[derive(RustEmbed)]
 #[folder = "$CARGO_MANIFEST_DIR/../client_ui/dist"]
@@ -27,15 +42,38 @@ pub struct FileQuery {
     path: String,
     content_type: Option<String>,
     thumbnail: bool,
+    /// ID of document, to cache availability of thumbnail
+    id: Option<String>,
+    /// Availability of thumbnail already stored in document
+    has_thumbnail: Option<bool>,
+    /// Hash of contents known to client. Thumbnail is cached by browser without revalidation
+    /// only if it matches hash stored in index, as URL then changes with contents
+    hash: Option<String>,
+    /// Maximum width and height of image, larger images are downscaled for preview
+    max_dimension: Option<u32>,
 }
 
 #[derive(Deserialize)]
 pub struct DocumentQuery {
     id: String,
+    /// Start of requested part of content in bytes
+    #[serde(default)]
+    offset: u64,
+    /// Maximum size of requested part in bytes, limited by settings
+    limit: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct TranslationQuery {
+    /// Language of browser, preferred to `Accept-Language` header
+    lang: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct DocumentContent {
+    path: String,
+    content_type: String,
+    #[serde(default)]
     content: String,
 }
 
@@ -67,15 +105,25 @@ pub async fn get_client_file(uri: Uri) -> Result<Response<BoxBody>, (StatusCode,
     }
 }
 
-pub async fn get_client_translation(headers: HeaderMap) -> Json<ClientTranslation> {
+/// Get translation of client to language from settings, or to language of browser
+pub async fn get_client_translation(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<TranslationQuery>,
+    headers: HeaderMap,
+) -> Json<ClientTranslation> {
     const LANGUAGES: [&str; 2] = ["ru-RU", "en-US"];
 
-    let requested = fluent_langneg::parse_accepted_languages(
-        headers
-            .get("Accept-Language")
-            .map(|x| x.to_str().unwrap_or_default())
-            .unwrap_or_default(),
-    );
+    let client_language = state.settings.read().await.client.client_language;
+    let requested = match (client_language.lang_id(), params.lang) {
+        (Some(x), _) => vec![x.parse().unwrap_or_log()],
+        (None, Some(x)) => fluent_langneg::parse_accepted_languages(&x),
+        (None, None) => fluent_langneg::parse_accepted_languages(
+            headers
+                .get("Accept-Language")
+                .map(|x| x.to_str().unwrap_or_default())
+                .unwrap_or_default(),
+        ),
+    };
     let available = fluent_langneg::convert_vec_str_to_langids_lossy(LANGUAGES);
     let default: LanguageIdentifier = "en-US".parse().unwrap();
     let supported = fluent_langneg::negotiate_languages(
@@ -98,16 +146,287 @@ pub async fn get_client_translation(headers: HeaderMap) -> Json<ClientTranslatio
     })
 }
 
+/// Store availability of thumbnail in document, if it has changed
+async fn cache_has_thumbnail(state: &ServerState, params: &FileQuery, has_thumbnail: bool) {
+    let Some(id) = &params.id else {
+        return;
+    };
+    if params.has_thumbnail == Some(has_thumbnail) {
+        return;
+    }
+
+    let res = state
+        .es_client()
+        .await
+        .update(elasticsearch::UpdateParts::IndexId(ELASTICSEARCH_INDEX, id))
+        .body(json!({
+            "doc": {
+                "has_thumbnail": has_thumbnail
+            }
+        }))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code());
+    if let Err(e) = res {
+        tracing::error!("Error updating thumbnail availability: {}", e);
+    }
+}
+
+/// Hash of contents stored in document of file, if file wasn't modified since it was indexed
+async fn stored_hash(
+    state: &ServerState,
+    params: &FileQuery,
+    modified: DateTime<Utc>,
+) -> Option<String> {
+    let res = state
+        .es_client()
+        .await
+        .search(elasticsearch::SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+        ._source(&["hash", "modified"])
+        .size(1)
+        .body(json!({ "query": { "term": { "path.keyword": params.path } } }))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code())
+        .ok()?
+        .json::<Value>()
+        .await
+        .ok()?;
+    let source = &res["hits"]["hits"][0]["_source"];
+    if source["modified"].as_i64() != Some(modified.timestamp()) {
+        return None;
+    }
+    source["hash"].as_str().map(str::to_owned)
+}
+
+/// Key of thumbnail in cache, by hash of contents from index or by path and modification time,
+/// with the hash if it was used. Hash from request isn't trusted, as it can belong to other file.
+/// `None` if file can't be read
+async fn thumbnail_key(
+    state: &ServerState,
+    params: &FileQuery,
+    path: &Path,
+) -> Option<(String, Option<String>)> {
+    let modified: DateTime<Utc> = tokio::fs::metadata(path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?
+        .into();
+    match stored_hash(state, params, modified).await {
+        Some(hash) => Some((cache::hash_key(&hash), Some(hash))),
+        None => Some((cache::path_key(path, modified), None)),
+    }
+}
+
+/// Time for which file selected in file dialog can be read
+const PICKED_FILE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Number of remembered files selected in file dialog, the oldest ones are forgotten
+const MAX_PICKED_FILES: usize = 100;
+
+/// Allow reading of file selected in file dialog, forgetting expired and the oldest files
+pub async fn add_picked_file(state: &ServerState, path: PathBuf) {
+    let mut picked_files = state.picked_files.write().await;
+    picked_files.retain(|_, x| x.elapsed() <= PICKED_FILE_TTL);
+    if picked_files.len() >= MAX_PICKED_FILES {
+        if let Some(oldest) = picked_files
+            .iter()
+            .min_by_key(|(_, x)| **x)
+            .map(|(path, _)| path.clone())
+        {
+            picked_files.remove(&oldest);
+        }
+    }
+    picked_files.insert(path, Instant::now());
+}
+
+/// Checks that file is in indexed directory that isn't excluded, or was selected in file dialog.
+/// Paths are compared after resolving `..` and symlinks, so links can't lead outside of
+/// indexed directories. Returns the resolved path, the only one that can be read
+async fn check_file_access(
+    state: &ServerState,
+    path: &str,
+) -> Result<PathBuf, (StatusCode, String)> {
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Not Found".to_owned()))?;
+    let is_picked = state
+        .picked_files
+        .read()
+        .await
+        .get(&path)
+        .is_some_and(|x| x.elapsed() <= PICKED_FILE_TTL);
+    if is_picked {
+        return Ok(path);
+    }
+
+    let indexing_directories = state.settings.read().await.indexing_directories.clone();
+    // Innermost directory decides, excluded directories can be nested in indexed ones
+    let mut innermost: Option<(PathBuf, bool)> = None;
+    for dir in indexing_directories {
+        let Ok(dir_path) = tokio::fs::canonicalize(&dir.path).await else {
+            continue;
+        };
+        let is_inner = match &innermost {
+            Some((x, _)) => dir_path.starts_with(x),
+            None => true,
+        };
+        if path.starts_with(&dir_path) && is_inner {
+            innermost = Some((dir_path, dir.exclude));
+        }
+    }
+    match innermost {
+        Some((_, false)) => Ok(path),
+        _ => Err((
+            StatusCode::FORBIDDEN,
+            "File isn't in indexed directories".to_owned(),
+        )),
+    }
+}
+
+/// Directory of thumbnail cache, if it's enabled
+async fn thumbnail_cache_dir(state: &ServerState) -> Option<PathBuf> {
+    let settings = state.settings.read().await;
+    settings
+        .thumbnail_cache_enabled
+        .then(|| settings.thumbnail_cache_dir.clone())
+}
+
+/// Animated and vector images lose their features when downscaled, so they aren't
+fn is_scalable_image(content_type: &mime::Mime) -> bool {
+    content_type.type_() == mime::IMAGE
+        && content_type.essence_str() != mime::IMAGE_GIF
+        && content_type.essence_str() != mime::IMAGE_SVG
+}
+
+/// Image downscaled for preview, cached like thumbnails.
+/// `None` if it can't be created, then original file is served
+async fn get_scaled_image_response(
+    state: &Arc<ServerState>,
+    params: &FileQuery,
+    path: &Path,
+    content_type: &mime::Mime,
+    max_dimension: u32,
+) -> Option<Response<BoxBody>> {
+    let content_type = Some(content_type.essence_str().to_owned());
+    let (_, out_content_type) = thumbnail_format(&content_type);
+    let key = thumbnail_key(state, params, path)
+        .await
+        .map(|(x, _)| cache::scaled_key(&x, max_dimension));
+    let cache_dir = thumbnail_cache_dir(state).await;
+    let cached = match (&cache_dir, &key) {
+        (Some(dir), Some(key)) => cache::read(dir, key, out_content_type).await,
+        _ => None,
+    };
+    let bytes = match cached {
+        Some(x) => x,
+        None => match get_scaled_image(path, &content_type, max_dimension).await {
+            Ok((bytes, _)) => {
+                if let (Some(_), Some(key)) = (&cache_dir, &key) {
+                    cache::write(Arc::clone(state), key, out_content_type, &bytes).await;
+                }
+                bytes
+            }
+            Err(e) => {
+                tracing::debug!("Can't downscale image {}: {}", path.display(), e);
+                return None;
+            }
+        },
+    };
+    Some(
+        Response::builder()
+            .header(header::CONTENT_TYPE, out_content_type)
+            .header(header::CACHE_CONTROL, "private, no-cache")
+            .body(boxed(Body::from(bytes)))
+            .unwrap_or_log(),
+    )
+}
+
+/// Content type stored in document by ID, or in document of file by path
+async fn stored_content_type(state: &ServerState, params: &FileQuery) -> Option<String> {
+    let query = match &params.id {
+        Some(id) => json!({ "ids": { "values": [id] } }),
+        None => json!({ "term": { "path.keyword": params.path } }),
+    };
+    let res = state
+        .es_client()
+        .await
+        .search(elasticsearch::SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+        ._source(&["content_type"])
+        .size(1)
+        .body(json!({ "query": query }))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code())
+        .ok()?
+        .json::<Value>()
+        .await
+        .ok()?;
+    res["hits"]["hits"][0]["_source"]["content_type"]
+        .as_str()
+        .map(str::to_owned)
+}
+
 pub async fn get_file(
+    State(state): State<Arc<ServerState>>,
     headers: HeaderMap,
     Query(params): Query<FileQuery>,
 ) -> Result<Response<BoxBody>, (StatusCode, String)> {
+    let path = check_file_access(&state, &params.path).await?;
+
     if params.thumbnail {
-        match get_thumbnail(&params.path, &params.content_type).await {
-            Ok((res, out_content_type)) => Ok(Response::builder()
-                .header("Content-Type", out_content_type)
-                .body(boxed(Body::from(res)))
-                .unwrap_or_log()),
+        let (_, out_content_type) = thumbnail_format(&params.content_type);
+        let (key, hash) = thumbnail_key(&state, &params, &path).await.unzip();
+        // Thumbnail for hash of contents in URL never changes, others are revalidated with ETag
+        let cache_control = if hash.flatten().is_some_and(|x| params.hash == Some(x)) {
+            "private, max-age=31536000, immutable"
+        } else {
+            "private, no-cache"
+        };
+        let etag = key.as_ref().map(|x| format!("\"{x}\""));
+        if let Some(etag) = &etag {
+            let is_not_modified = headers
+                .get(header::IF_NONE_MATCH)
+                .and_then(|x| x.to_str().ok())
+                .is_some_and(|x| x.split(',').any(|x| x.trim() == etag));
+            if is_not_modified {
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .header(header::ETAG, etag)
+                    .header(header::CACHE_CONTROL, cache_control)
+                    .body(boxed(Body::empty()))
+                    .unwrap_or_log());
+            }
+        }
+
+        let cache_dir = thumbnail_cache_dir(&state).await;
+        let cached = match (&cache_dir, &key) {
+            (Some(dir), Some(key)) => cache::read(dir, key, out_content_type).await,
+            _ => None,
+        };
+        let thumbnail = match cached {
+            Some(x) => Ok(x),
+            None => {
+                let thumbnail = get_thumbnail(&path, &params.content_type)
+                    .await
+                    .map(|(x, _)| x);
+                cache_has_thumbnail(&state, &params, thumbnail.is_ok()).await;
+                if let (Ok(bytes), Some(_), Some(key)) = (&thumbnail, &cache_dir, &key) {
+                    cache::write(Arc::clone(&state), key, out_content_type, bytes).await;
+                }
+                thumbnail
+            }
+        };
+        match thumbnail {
+            Ok(res) => {
+                let mut response = Response::builder()
+                    .header(header::CONTENT_TYPE, out_content_type)
+                    .header(header::CACHE_CONTROL, cache_control);
+                if let Some(etag) = etag {
+                    response = response.header(header::ETAG, etag);
+                }
+                Ok(response.body(boxed(Body::from(res))).unwrap_or_log())
+            }
             Err(err) => Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Can't create thumbnail: {err}"),
@@ -123,10 +442,15 @@ pub async fn get_file(
             )
         })?;
 
-        let file_mime = match params.content_type {
+        // Content type detected during indexing is preferred to guessing by extension
+        let file_mime = match &params.content_type {
             Some(x) => x.parse().unwrap_or_log(),
             None => {
-                let mut tmp = mime_guess::from_path(&params.path).first_or_octet_stream();
+                let stored = stored_content_type(&state, &params)
+                    .await
+                    .and_then(|x| x.parse::<mime::Mime>().ok());
+                let mut tmp =
+                    stored.unwrap_or_else(|| mime_guess::from_path(&path).first_or_octet_stream());
                 if tmp.type_() == mime::TEXT && tmp.essence_str() != mime::TEXT_HTML {
                     tmp = mime::TEXT_PLAIN;
                 };
@@ -134,7 +458,18 @@ pub async fn get_file(
             }
         };
 
-        let res = match ServeFile::new_with_mime(params.path, &file_mime)
+        if let Some(max_dimension) = params
+            .max_dimension
+            .filter(|_| is_scalable_image(&file_mime))
+        {
+            let res =
+                get_scaled_image_response(&state, &params, &path, &file_mime, max_dimension).await;
+            if let Some(res) = res {
+                return Ok(res);
+            }
+        }
+
+        let res = match ServeFile::new_with_mime(path, &file_mime)
             .oneshot(request)
             .await
         {
@@ -153,26 +488,272 @@ pub async fn get_file(
     }
 }
 
+/// Part of document content in UTF-8, with total length, offset of next part and encoding in headers.
+/// Text files are read from disk with detected encoding, other documents return indexed content
 pub async fn get_document_content(
     State(state): State<Arc<ServerState>>,
     Query(params): Query<DocumentQuery>,
-) -> Result<String, (StatusCode, String)> {
+) -> Result<Response<BoxBody>, (StatusCode, String)> {
+    let chunk_size = state.settings.read().await.preview_chunk_size;
+    // Even size keeps parts of UTF-16 files aligned to code units
+    let limit = params
+        .limit
+        .unwrap_or(chunk_size)
+        .clamp(2, chunk_size.max(2))
+        & !1;
+
     let es_response_body = state
-        .es_client
+        .es_client()
+        .await
         .get(elasticsearch::GetParts::IndexId(
             ELASTICSEARCH_INDEX,
             &params.id,
         ))
-        ._source(&["content"])
+        ._source(&["path", "content_type", "content"])
         .send()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .json::<Value>()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    Ok(
-        serde_json::from_value::<DocumentContent>(es_response_body["_source"].clone())
-            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
-            .content,
-    )
+    let document = serde_json::from_value::<DocumentContent>(es_response_body["_source"].clone())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    // Entries of archives and inaccessible files are previewed by indexed content
+    let text_file_path = if document.content_type.starts_with("text/") {
+        check_file_access(&state, &document.path).await.ok()
+    } else {
+        None
+    };
+    let part = if let Some(path) = text_file_path {
+        tokio::task::spawn_blocking(move || read_file_part(&path, params.offset, limit))
+            .await
+            .unwrap_or_log()
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Can't read file: {e}"),
+                )
+            })?
+            .ok_or_else(|| {
+                (
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    BINARY_CONTENT_ERROR.to_owned(),
+                )
+            })?
+    } else {
+        indexed_part(&document.content, params.offset, limit)
+    };
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(CONTENT_TOTAL_LENGTH_HEADER, part.total_length)
+        .header(CONTENT_NEXT_OFFSET_HEADER, part.next_offset)
+        .header(CONTENT_ENCODING_HEADER, part.encoding)
+        .body(boxed(Body::from(part.text)))
+        .unwrap_or_log())
+}
+
+#[cfg(test)]
+mod tests {
+    use common_lib::settings::IndexingDirectory;
+
+    use crate::test_util::{test_settings, test_state, MockElasticsearch};
+
+    use super::*;
+
+    fn directory(path: &Path, exclude: bool) -> IndexingDirectory {
+        IndexingDirectory {
+            path: path.to_owned(),
+            exclude,
+            ..Default::default()
+        }
+    }
+
+    /// Status of access check for path, `OK` if access is allowed
+    async fn access_status(directories: Vec<IndexingDirectory>, path: &Path) -> StatusCode {
+        let mut settings = test_settings();
+        settings.indexing_directories = directories;
+        let state = test_state(settings).await;
+        match check_file_access(&state, path.to_str().unwrap()).await {
+            Ok(_) => StatusCode::OK,
+            Err((status, _)) => status,
+        }
+    }
+
+    #[tokio::test]
+    async fn parent_directory_traversal_is_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let indexed = dir.path().join("indexed");
+        std::fs::create_dir(&indexed).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        std::fs::write(indexed.join("a.txt"), "a").unwrap();
+
+        let directories = vec![directory(&indexed, false)];
+        assert_eq!(
+            access_status(directories.clone(), &indexed.join("a.txt")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            access_status(directories, &indexed.join("../secret.txt")).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlink_outside_of_indexed_directory_is_forbidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let indexed = dir.path().join("indexed");
+        std::fs::create_dir(&indexed).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret.txt"), indexed.join("link.txt"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.path(), indexed.join("link_dir")).unwrap();
+
+        let directories = vec![directory(&indexed, false)];
+        assert_eq!(
+            access_status(directories.clone(), &indexed.join("link.txt")).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            access_status(directories, &indexed.join("link_dir/secret.txt")).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resolved_path_is_returned_for_reading() {
+        let dir = tempfile::tempdir().unwrap();
+        let indexed = tokio::fs::canonicalize(dir.path()).await.unwrap();
+        std::fs::create_dir(indexed.join("sub")).unwrap();
+        std::fs::write(indexed.join("a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(indexed.join("a.txt"), indexed.join("link.txt")).unwrap();
+        let mut settings = test_settings();
+        settings.indexing_directories = vec![directory(&indexed, false)];
+        let state = test_state(settings).await;
+
+        for path in [indexed.join("link.txt"), indexed.join("sub/../a.txt")] {
+            assert_eq!(
+                check_file_access(&state, path.to_str().unwrap())
+                    .await
+                    .unwrap(),
+                indexed.join("a.txt")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn excluded_subdirectory_is_forbidden_in_any_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let excluded = dir.path().join("excluded");
+        std::fs::create_dir(&excluded).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(excluded.join("b.txt"), "b").unwrap();
+
+        let included_first = vec![directory(dir.path(), false), directory(&excluded, true)];
+        let excluded_first = vec![directory(&excluded, true), directory(dir.path(), false)];
+        for directories in [included_first, excluded_first] {
+            assert_eq!(
+                access_status(directories.clone(), &dir.path().join("a.txt")).await,
+                StatusCode::OK
+            );
+            assert_eq!(
+                access_status(directories, &excluded.join("b.txt")).await,
+                StatusCode::FORBIDDEN
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_path_is_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            access_status(
+                vec![directory(dir.path(), false)],
+                &dir.path().join("missing.txt")
+            )
+            .await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn picked_files_are_limited_and_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = tokio::fs::canonicalize(dir.path()).await.unwrap();
+        std::fs::write(path.join("picked.txt"), "picked").unwrap();
+        let state = test_state(test_settings()).await;
+
+        add_picked_file(&state, path.join("picked.txt")).await;
+        assert!(
+            check_file_access(&state, path.join("picked.txt").to_str().unwrap())
+                .await
+                .is_ok()
+        );
+
+        for i in 0..MAX_PICKED_FILES {
+            add_picked_file(&state, path.join(format!("{i}.txt"))).await;
+        }
+        assert_eq!(state.picked_files.read().await.len(), MAX_PICKED_FILES);
+        assert!(!state
+            .picked_files
+            .read()
+            .await
+            .contains_key(&path.join("picked.txt")));
+
+        // Expired file can't be read, and is forgotten when another file is picked
+        state.picked_files.write().await.insert(
+            path.join("picked.txt"),
+            Instant::now() - PICKED_FILE_TTL - Duration::from_secs(1),
+        );
+        assert!(
+            check_file_access(&state, path.join("picked.txt").to_str().unwrap())
+                .await
+                .is_err()
+        );
+        add_picked_file(&state, path.join("new.txt")).await;
+        assert!(!state
+            .picked_files
+            .read()
+            .await
+            .contains_key(&path.join("picked.txt")));
+    }
+
+    #[tokio::test]
+    async fn thumbnail_key_uses_hash_from_index_instead_of_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.jpg");
+        std::fs::write(&path, "a").unwrap();
+        let modified: DateTime<Utc> = std::fs::metadata(&path).unwrap().modified().unwrap().into();
+        let es = MockElasticsearch::with_documents([(
+            "a".to_owned(),
+            json!({ "path": path, "modified": modified.timestamp(), "hash": "stored" }),
+        )]);
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        let state = test_state(settings).await;
+        let params = FileQuery {
+            path: path.to_str().unwrap().to_owned(),
+            content_type: None,
+            thumbnail: true,
+            id: None,
+            has_thumbnail: None,
+            hash: Some("other".to_owned()),
+            max_dimension: None,
+        };
+
+        assert_eq!(
+            thumbnail_key(&state, &params, &path).await,
+            Some((cache::hash_key("stored"), Some("stored".to_owned())))
+        );
+
+        // Hash of file modified after indexing isn't used
+        es.documents.lock().unwrap().get_mut("a").unwrap()["modified"] = json!(0);
+        assert_eq!(
+            thumbnail_key(&state, &params, &path).await,
+            Some((cache::path_key(&path, modified), None))
+        );
+    }
 }
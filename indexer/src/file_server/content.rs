@@ -0,0 +1,106 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Size of start of file used to detect encoding and binary files
+const DETECTION_SIZE: u64 = 64 * 1024;
+
+/// Part of document content decoded to UTF-8
+pub struct ContentPart {
+    pub text: String,
+    /// Encoding of file, UTF-8 for indexed content
+    pub encoding: &'static str,
+    /// Total length of file or indexed content in bytes
+    pub total_length: u64,
+    /// Offset of next part, equal to total length after the last part
+    pub next_offset: u64,
+}
+
+/// Part of indexed content, boundaries are moved back to starts of characters
+pub fn indexed_part(content: &str, offset: u64, limit: u64) -> ContentPart {
+    let floor_char_boundary = |mut i: usize| {
+        while !content.is_char_boundary(i) {
+            i -= 1;
+        }
+        i
+    };
+
+    let total_length = content.len();
+    let start = floor_char_boundary(offset.min(total_length as u64) as usize);
+    let mut end = floor_char_boundary(start.saturating_add(limit as usize).min(total_length));
+    // Limit is smaller than character
+    if end == start && end < total_length {
+        end = (end + 1..=total_length)
+            .find(|&i| content.is_char_boundary(i))
+            .unwrap_or(total_length);
+    }
+    ContentPart {
+        text: content[start..end].to_owned(),
+        encoding: UTF_8.name(),
+        total_length: total_length as u64,
+        next_offset: end as u64,
+    }
+}
+
+/// Read part of text file and decode it to UTF-8.
+/// Encoding is detected by BOM or from the start of file, so all parts use the same one.
+/// Returns `None` if file looks binary
+pub fn read_file_part(path: &Path, offset: u64, limit: u64) -> io::Result<Option<ContentPart>> {
+    let mut file = File::open(path)?;
+    let total_length = file.metadata()?.len();
+
+    let mut start = Vec::new();
+    (&mut file).take(DETECTION_SIZE).read_to_end(&mut start)?;
+    let (encoding, bom_length) = match Encoding::for_bom(&start) {
+        Some(x) => x,
+        // Text without BOM doesn't contain NUL characters
+        None if start.contains(&0) => return Ok(None),
+        None => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(&start, start.len() as u64 == total_length);
+            (detector.guess(None, true), 0)
+        }
+    };
+
+    let offset = offset.clamp(bom_length as u64, total_length);
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(limit).read_to_end(&mut bytes)?;
+    let mut next_offset = offset + bytes.len() as u64;
+    if next_offset < total_length {
+        if let Some(end) = last_line_end(&bytes, encoding) {
+            bytes.truncate(end);
+            next_offset = offset + end as u64;
+        }
+    }
+
+    let (text, _) = encoding.decode_without_bom_handling(&bytes);
+    Ok(Some(ContentPart {
+        text: text.into_owned(),
+        encoding: encoding.name(),
+        total_length,
+        next_offset,
+    }))
+}
+
+/// End of the last line break, so that parts don't split multibyte characters.
+/// Without line breaks part is cut at limit
+fn last_line_end(bytes: &[u8], encoding: &'static Encoding) -> Option<usize> {
+    let utf_16_line_break = match encoding {
+        x if x == UTF_16LE => Some([b'\n', 0]),
+        x if x == UTF_16BE => Some([0, b'\n']),
+        _ => None,
+    };
+    match utf_16_line_break {
+        Some(line_break) => bytes
+            .chunks_exact(2)
+            .rposition(|x| x == line_break)
+            .map(|i| (i + 1) * 2),
+        None => bytes.iter().rposition(|&x| x == b'\n').map(|i| i + 1),
+    }
+}
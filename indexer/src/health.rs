@@ -0,0 +1,62 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, http::StatusCode, Json};
+use common_lib::indexer::{IndexSchemaStatus, IndexerHealth};
+use elasticsearch::cluster::ClusterHealthParts;
+use url::Url;
+
+use crate::ServerState;
+
+/// Timeout of each availability check including retries, so that health is reported quickly
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn elasticsearch_reachable(state: &ServerState) -> bool {
+    let es_client = state.es_client().await;
+    let request = es_client.cluster().health(ClusterHealthParts::None).send();
+    tokio::time::timeout(HEALTH_CHECK_TIMEOUT, request)
+        .await
+        .is_ok_and(|res| res.and_then(|x| x.error_for_status_code()).is_ok())
+}
+
+async fn service_reachable(state: &ServerState, url: Url) -> bool {
+    let request = state.reqwest_client.get(url).send();
+    tokio::time::timeout(HEALTH_CHECK_TIMEOUT, request)
+        .await
+        .is_ok_and(|res| res.is_ok_and(|x| x.status().is_success()))
+}
+
+/// Availability of Elasticsearch, Apache Tika and neural network server, watcher and indexing
+/// status. Responds with 503 if Elasticsearch is unreachable, indexer can't work without it
+pub async fn get_health(
+    State(state): State<Arc<ServerState>>,
+) -> (StatusCode, Json<IndexerHealth>) {
+    let (mut tika_url, mut nn_server_url) = {
+        let settings = state.settings.read().await;
+        (settings.tika_url.clone(), settings.nn_server_url.clone())
+    };
+    tika_url.set_path("tika");
+    nn_server_url.set_path("health");
+    let (elasticsearch, tika, nn_server) = tokio::join!(
+        elasticsearch_reachable(&state),
+        service_reachable(&state, tika_url),
+        service_reachable(&state, nn_server_url),
+    );
+
+    let health = IndexerHealth {
+        elasticsearch,
+        tika,
+        nn_server,
+        watcher_active: state.watcher.read().await.is_some(),
+        indexing_status: state.indexing_status.read().await.name().to_owned(),
+        index_schema_outdated: matches!(
+            *state.index_schema.read().await,
+            IndexSchemaStatus::Outdated { .. }
+        ),
+    };
+    let status_code = if elasticsearch {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status_code, Json(health))
+}
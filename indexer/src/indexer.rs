@@ -1,43 +1,180 @@
-use std::{future::Future, path::PathBuf, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    ops::AddAssign,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use axum::{extract::State, http::StatusCode};
+use anyhow::anyhow;
+use axum::{extract::State, http::StatusCode, Json};
 use common_lib::{
     elasticsearch::{FileES, ELASTICSEARCH_INDEX},
-    indexer::IndexingEvent,
+    indexer::{
+        DeleteIndexRequest, DeleteToken, FileError, FileErrorStage, IndexSchemaStatus,
+        IndexingEvent, IndexingStatus, DELETE_TOKEN_TTL_SECS,
+    },
+    provenance::{ProvenanceCriterion, ReprocessRequest},
 };
 use elasticsearch::{
-    http::request::JsonBody,
-    indices::{IndicesDeleteParts, IndicesRefreshParts},
-    BulkParts, Elasticsearch,
+    http::request::JsonBody, indices::IndicesRefreshParts, BulkParts, Elasticsearch, UpdateParts,
 };
+use metrics::{histogram, increment_counter};
 use serde_json::{json, Value};
 use tokio::sync::{
     mpsc::{self, Receiver, Sender},
-    Semaphore,
+    Mutex, Semaphore,
 };
 use tracing_unwrap::{OptionExt, ResultExt};
+use uuid::Uuid;
 
 use crate::{
-    parser::parse_file,
+    embeddings::EmbeddingError,
+    fault_injection::injector,
+    indexer::{
+        create_index::IndexOptions,
+        errors::{on_error, on_file_error, record_file_error, FileErrorContext},
+        parse_failures::{is_skipped_file, record_parse_failure, remove_parse_failure},
+    },
+    parser::{
+        archive::{is_indexed_archive, parse_archive_entries},
+        parse_file,
+    },
+    provenance::update_toolchain,
     scanner::{
-        get_elasticsearch_files_list, get_file_system_files_list,
-        get_file_system_partial_files_list, FileInfo, FilesDiff,
+        calculate_change_detection_hashes, directory_concurrency, get_elasticsearch_files_list,
+        get_file_system_files_list, get_file_system_partial_files_list, is_excluded_file,
+        is_zip_path, limit_hydration, remove_archive_entries, remove_outside_directories,
+        ExcludeRegexes, FileInfo, FilesDiff,
     },
+    telemetry::{BULK_BATCH_SIZE, FILES_PROCESSED},
+    thumbnails::cache,
     ServerState,
 };
 
 pub mod create_index;
+pub mod errors;
+pub mod estimate;
+pub mod parse_failures;
+pub mod periodic;
+pub mod progress;
 pub mod status;
 
 const CHANNEL_CAPACITY_MULTIPLIER: usize = 2;
+/// Delay before the first retry of bulk request, doubled after each retry
+const BULK_RETRY_DELAY_MIN: Duration = Duration::from_secs(1);
+const BULK_RETRY_DELAY_MAX: Duration = Duration::from_secs(60);
+/// Maximum number of times file modified during processing is processed again
+const CHANGED_FILE_MAX_RETRIES: u32 = 3;
+
+/// Index that receives changes during indexing
+pub enum IndexingTarget {
+    /// Current index, accessed through alias
+    Current,
+    /// New generation of index, alias is switched to it after successful indexing
+    NewGeneration(String),
+}
+
+impl IndexingTarget {
+    fn index(&self) -> &str {
+        match self {
+            IndexingTarget::Current => ELASTICSEARCH_INDEX,
+            IndexingTarget::NewGeneration(index) => index,
+        }
+    }
+}
+
+/// Durations of processing stages of file, or their totals for all files
+#[derive(Debug, Clone, Copy, Default)]
+struct StageDurations {
+    hash: Duration,
+    /// Reading file and extracting metadata with Apache Tika
+    tika: Duration,
+    thumbnail: Duration,
+    text_embedding: Duration,
+    image_embedding: Duration,
+    /// Waiting for space in channel of bulk requests
+    bulk_enqueue: Duration,
+    /// Rest of processing, such as text recognition and archive entries
+    other: Duration,
+}
+
+impl StageDurations {
+    fn total(&self) -> Duration {
+        self.hash
+            + self.tika
+            + self.thumbnail
+            + self.text_embedding
+            + self.image_embedding
+            + self.bulk_enqueue
+            + self.other
+    }
+
+    /// Durations by names of stages, as in indexing events
+    fn named(&self) -> Vec<(String, Duration)> {
+        [
+            ("hash", self.hash),
+            ("tika", self.tika),
+            ("thumbnail", self.thumbnail),
+            ("text_embedding", self.text_embedding),
+            ("image_embedding", self.image_embedding),
+            ("bulk_enqueue", self.bulk_enqueue),
+            ("other", self.other),
+        ]
+        .into_iter()
+        .map(|(name, duration)| (name.to_owned(), duration))
+        .collect()
+    }
+}
+
+impl AddAssign for StageDurations {
+    fn add_assign(&mut self, rhs: Self) {
+        self.hash += rhs.hash;
+        self.tika += rhs.tika;
+        self.thumbnail += rhs.thumbnail;
+        self.text_embedding += rhs.text_embedding;
+        self.image_embedding += rhs.image_embedding;
+        self.bulk_enqueue += rhs.bulk_enqueue;
+        self.other += rhs.other;
+    }
+}
+
+/// Operations for file, and its path with durations of its processing stages if it was processed
+type FileOperations = (Vec<BulkOperation>, Option<(PathBuf, StageDurations)>);
+
+/// File or pair of old and new file that is processed during indexing
+trait ProcessedFile {
+    /// Path of file, its indexing directory can limit concurrency
+    fn path(&self) -> &Path;
+}
+
+impl ProcessedFile for FileInfo {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ProcessedFile for (FileInfo, FileInfo) {
+    fn path(&self) -> &Path {
+        &self.1.path
+    }
+}
 
 /// Update indexing status and send event to channel
-async fn on_event(state: Arc<ServerState>, event: IndexingEvent) {
+pub async fn on_event(state: Arc<ServerState>, event: IndexingEvent) {
     match &event {
         IndexingEvent::Started => tracing::info!("Indexing started"),
         IndexingEvent::DiffCalculated { .. } => tracing::info!("Difference calculated"),
+        IndexingEvent::FileProcessed => increment_counter!(FILES_PROCESSED),
         IndexingEvent::Error(e) => tracing::error!("Error while indexing: {}", e),
-        IndexingEvent::Finished(duration) => tracing::info!("Indexing finished in {:#?}", duration),
+        IndexingEvent::FileDeleted(path) => tracing::info!("Moved to trash: {}", path.display()),
+        IndexingEvent::SlowFile { path, stages } => {
+            tracing::info!("Slow file: {}, stages: {:?}", path.display(), stages)
+        }
+        IndexingEvent::Finished { duration, .. } => {
+            tracing::info!("Indexing finished in {:#?}", duration)
+        }
         _ => {}
     }
     state
@@ -48,136 +185,605 @@ async fn on_event(state: Arc<ServerState>, event: IndexingEvent) {
 
     #[allow(unused_must_use)]
     {
-        state.indexing_events.send(event);
+        state.indexing_events.read().await.send(event);
     }
 }
 
-/// Process all files with given function and send results to channel, call function on each event.
-/// Processing is parallel with no more than given number of tasks at once
+/// Process all files with given function and send resulting operations to channel,
+/// call function on each event. Durations of processing stages are added to totals,
+/// files processed longer than threshold from settings are reported.
+/// Processing is parallel with no more than given number of tasks at once. Files of indexing
+/// directories with their own limit are processed by that many workers of the innermost such
+/// directory, so slow directory doesn't delay files of other directories
 async fn streaming_process<T, F, Fut>(
     state: Arc<ServerState>,
-    tx: Sender<(Value, Value)>,
+    tx: Sender<BulkOperation>,
     files: Vec<T>,
     process: F,
+    stage_durations: Arc<Mutex<StageDurations>>,
 ) where
-    T: Send + 'static,
+    T: ProcessedFile + Send + 'static,
     F: Fn(Arc<ServerState>, T) -> Fut + Send + Sync + Copy + 'static,
-    Fut: Future<Output = anyhow::Result<(Value, Value)>> + Send,
+    Fut: Future<Output = anyhow::Result<FileOperations>> + Send,
 {
-    let semaphore = Arc::new(Semaphore::new(
-        state.settings.read().await.max_concurrent_files,
-    ));
-    let mut futures = Vec::new();
+    let (max_concurrent_files, slow_file_threshold, directory_limits) = {
+        let settings = state.settings.read().await;
+        let max_concurrent_files = settings.max_concurrent_files;
+        let directory_limits: Vec<_> = settings
+            .indexing_directories
+            .iter()
+            .filter_map(|dir| {
+                let limit = directory_concurrency(dir.max_concurrency?, max_concurrent_files);
+                Some((dir.path.clone(), limit))
+            })
+            .collect();
+        (
+            max_concurrent_files,
+            Duration::from_secs_f32(settings.slow_file_threshold.max(0.0)),
+            directory_limits,
+        )
+    };
+
+    // Files of directories without their own limit are in the last group
+    let mut groups: Vec<_> = directory_limits
+        .iter()
+        .map(|(_, limit)| (*limit, Vec::new()))
+        .chain([(max_concurrent_files, Vec::new())])
+        .collect();
     for file in files {
-        let permit = Arc::clone(&semaphore).acquire_owned().await.unwrap_or_log();
-        let state = Arc::clone(&state);
-        let tx = tx.clone();
-        futures.push(tokio::spawn(async move {
-            let res = process(Arc::clone(&state), file).await;
-            tx.send(res?).await.unwrap_or_log();
-            on_event(state, IndexingEvent::FileProcessed).await;
-            drop(permit);
-            Ok::<(), anyhow::Error>(())
-        }));
-    }
-    for f in futures {
-        if let Err(e) = f.await.unwrap_or_log() {
-            on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
+        let group = directory_limits
+            .iter()
+            .enumerate()
+            .filter(|(_, (path, _))| file.path().starts_with(path))
+            .max_by_key(|(_, (path, _))| path.components().count())
+            .map_or(directory_limits.len(), |(i, _)| i);
+        groups[group].1.push(file);
+    }
+
+    // Workers of all groups together are limited by global number of permits
+    let semaphore = Arc::new(Semaphore::new(max_concurrent_files));
+    let mut workers = Vec::new();
+    for (limit, files) in groups {
+        let workers_cnt = limit.min(files.len());
+        let queue = Arc::new(Mutex::new(files.into_iter()));
+        for _ in 0..workers_cnt {
+            let queue = Arc::clone(&queue);
+            let semaphore = Arc::clone(&semaphore);
+            let state = Arc::clone(&state);
+            let tx = tx.clone();
+            let stage_durations = Arc::clone(&stage_durations);
+            workers.push(tokio::spawn(async move {
+                loop {
+                    let Some(file) = queue.lock().await.next() else {
+                        break;
+                    };
+                    let permit = Arc::clone(&semaphore).acquire_owned().await.unwrap_or_log();
+                    let result = async {
+                        let start_time = Instant::now();
+                        let (operations, durations) = process(Arc::clone(&state), file).await?;
+                        let enqueue_start_time = Instant::now();
+                        for operation in operations {
+                            tx.send(operation).await.unwrap_or_log();
+                        }
+                        if let Some((path, mut durations)) = durations {
+                            durations.bulk_enqueue = enqueue_start_time.elapsed();
+                            durations.other =
+                                start_time.elapsed().saturating_sub(durations.total());
+                            *stage_durations.lock().await += durations;
+                            if !slow_file_threshold.is_zero()
+                                && durations.total() > slow_file_threshold
+                            {
+                                let stages = durations.named();
+                                let event = IndexingEvent::SlowFile { path, stages };
+                                on_event(Arc::clone(&state), event).await;
+                            }
+                        }
+                        on_event(Arc::clone(&state), IndexingEvent::FileProcessed).await;
+                        Ok::<(), anyhow::Error>(())
+                    }
+                    .await;
+                    drop(permit);
+                    if let Err(e) = result {
+                        on_error(Arc::clone(&state), e).await;
+                    }
+                }
+            }));
         }
     }
+    for worker in workers {
+        worker.await.unwrap_or_log();
+    }
 }
 
-/// Create operation to add new file to index
-async fn add_new(state: Arc<ServerState>, file: FileInfo) -> anyhow::Result<(Value, Value)> {
-    tracing::debug!("Add file: {}", file.path.display());
-
-    let action = json!({"index": {}});
-    let process_contents = file.process_contents;
-    let mut file_es: FileES = file.try_into().unwrap_or_log();
-    if process_contents {
-        parse_file(state, &mut file_es)
+/// Convert file and parse its contents, returns durations of processing stages.
+/// If file was modified meanwhile, it's processed again with new metadata.
+/// If it keeps changing, metadata from scan is kept, so the file is updated by next indexing
+async fn process_file(
+    state: Arc<ServerState>,
+    mut file: FileInfo,
+) -> anyhow::Result<(FileES, StageDurations)> {
+    let mut retries = 0;
+    // Durations of all attempts are added up
+    let mut durations = StageDurations::default();
+    loop {
+        let process_contents = file.process_contents;
+        let (hash_algorithm, hash_chunk_size) = {
+            let settings = state.settings.read().await;
+            (settings.hash_algorithm, settings.hash_chunk_size)
+        };
+        let start_time = Instant::now();
+        let mut file_es = file
+            .clone()
+            .into_file_es(hash_algorithm, hash_chunk_size)
             .await
-            .map_err(|e| e.context(format!("Error parsing file: {}", file_es.path.display())))?;
+            .map_err(|e| {
+                anyhow::Error::from(e).context(FileErrorContext {
+                    path: file.path.clone(),
+                    stage: FileErrorStage::Hash,
+                })
+            })?;
+        durations.hash += start_time.elapsed();
+        // Unchanged files that keep failing parsing are indexed with basic metadata only
+        if process_contents && is_skipped_file(&state, &file_es).await {
+            tracing::debug!(
+                "Skipping parsing of file that kept failing: {}",
+                file_es.path.display()
+            );
+            file_es.parse_failed = Some(true);
+        } else if process_contents {
+            let parse_durations = match parse_file(Arc::clone(&state), &mut file_es).await {
+                Ok(x) => x,
+                Err(e) => {
+                    let stage = match e.downcast_ref::<EmbeddingError>() {
+                        Some(_) => FileErrorStage::Embedding,
+                        None => FileErrorStage::Parse,
+                    };
+                    if stage == FileErrorStage::Parse {
+                        record_parse_failure(&state, &file_es, &e).await;
+                    }
+                    return Err(e.context(FileErrorContext {
+                        path: file_es.path.clone(),
+                        stage,
+                    }));
+                }
+            };
+            remove_parse_failure(&state, &file_es.path).await;
+            durations.tika += parse_durations.metadata;
+            durations.thumbnail += parse_durations.parsers.thumbnail;
+            durations.text_embedding += parse_durations.parsers.text_embedding;
+            durations.image_embedding += parse_durations.parsers.image_embedding;
+        }
+
+        let (tmp_state, tmp_file) = (Arc::clone(&state), file.clone());
+        let current = tokio::task::spawn_blocking(move || {
+            tmp_file.refreshed(&tmp_state.settings.blocking_read())
+        })
+        .await
+        .unwrap_or_log();
+        match current {
+            Some(current) if file.is_modified(&current) => {
+                if retries == CHANGED_FILE_MAX_RETRIES {
+                    tracing::warn!(
+                        "File keeps changing, it will be updated by next indexing: {}",
+                        file.path.display()
+                    );
+                    return Ok((file_es, durations));
+                }
+                retries += 1;
+                tracing::debug!("File changed while processing: {}", file.path.display());
+                on_event(Arc::clone(&state), IndexingEvent::FileChanged).await;
+                file = current;
+            }
+            _ => return Ok((file_es, durations)),
+        }
     }
-    let data = serde_json::to_value(file_es).unwrap_or_log();
-    Ok((action, data))
 }
 
-/// Create operation to update file in index given old and new file info
+/// Action to add new document. ID is chosen before sending, so when response to bulk request
+/// is lost, retried operation replaces document that was already added instead of duplicating it
+fn index_new_action() -> Value {
+    json!({"index": { "_id": Uuid::new_v4().to_string() }})
+}
+
+/// Create operations to add entries of archive, if they are indexed.
+/// Archive is indexed without entries on errors
+async fn add_archive_entries(state: Arc<ServerState>, archive: &FileES) -> Vec<BulkOperation> {
+    if !is_indexed_archive(&state, archive).await {
+        return Vec::new();
+    }
+
+    match parse_archive_entries(Arc::clone(&state), archive).await {
+        Ok(entries) => entries
+            .into_iter()
+            .map(|x| BulkOperation {
+                path: x.path.clone(),
+                action: index_new_action(),
+                data: serde_json::to_value(x).unwrap_or_log(),
+            })
+            .collect(),
+        Err(e) => {
+            let error = FileError {
+                path: archive.path.clone(),
+                stage: FileErrorStage::Parse,
+                error: format!("{e:#}"),
+            };
+            let e = e.context(format!("Error reading archive: {}", archive.path.display()));
+            on_file_error(state, error, format!("{e:?}")).await;
+            Vec::new()
+        }
+    }
+}
+
+/// Create operations to add new file to index
+async fn add_new(state: Arc<ServerState>, file: FileInfo) -> anyhow::Result<FileOperations> {
+    tracing::debug!("Add file: {}", file.path.display());
+
+    let action = index_new_action();
+    let (file_es, durations) = process_file(Arc::clone(&state), file).await?;
+    let mut operations = add_archive_entries(state, &file_es).await;
+    let path = file_es.path.clone();
+    operations.push(BulkOperation {
+        path: path.clone(),
+        action,
+        data: serde_json::to_value(file_es).unwrap_or_log(),
+    });
+    Ok((operations, Some((path, durations))))
+}
+
+/// Create operations to update file in index given old and new file info.
+/// Old entries of archive are removed separately
 async fn update_modified(
     state: Arc<ServerState>,
-    (old_file, new_file): (FileInfo, FileInfo),
-) -> anyhow::Result<(Value, Value)> {
+    (old_file, mut new_file): (FileInfo, FileInfo),
+) -> anyhow::Result<FileOperations> {
     tracing::debug!("Update file: {}", new_file.path.display());
 
+    cache::invalidate(&state, &old_file).await;
+    new_file.tags = old_file.tags;
     let action = json!({"index": { "_id": old_file._id.unwrap_or_log() }});
-    let process_contents = new_file.process_contents;
-    let mut new_file_es: FileES = new_file.try_into().unwrap_or_log();
-    if process_contents {
-        parse_file(state, &mut new_file_es).await.map_err(|e| {
-            e.context(format!(
-                "Error parsing file: {}",
-                new_file_es.path.display()
-            ))
-        })?;
-    }
-    let data = serde_json::to_value(new_file_es).unwrap_or_log();
-    Ok((action, data))
+    let (new_file_es, durations) = process_file(Arc::clone(&state), new_file).await?;
+    let mut operations = add_archive_entries(state, &new_file_es).await;
+    let path = new_file_es.path.clone();
+    operations.push(BulkOperation {
+        path: path.clone(),
+        action,
+        data: serde_json::to_value(new_file_es).unwrap_or_log(),
+    });
+    Ok((operations, Some((path, durations))))
 }
 
 /// Create operation to remove file from index
-async fn remove_old(_state: Arc<ServerState>, file: FileInfo) -> anyhow::Result<(Value, Value)> {
+async fn remove_old(state: Arc<ServerState>, file: FileInfo) -> anyhow::Result<FileOperations> {
     tracing::debug!("Remove file: {}", file.path.display());
 
+    cache::invalidate(&state, &file).await;
+    remove_parse_failure(&state, &file.path).await;
     let action = json!({"delete": { "_id": file._id.unwrap_or_log() }});
-    Ok((action, Value::Null))
+    let operations = vec![BulkOperation {
+        path: file.path,
+        action,
+        data: Value::Null,
+    }];
+    Ok((operations, None))
 }
 
-/// Accept operations from channel and bulk send them to Elasticsearch
-async fn bulk_send(
-    state: Arc<ServerState>,
-    mut rx: Receiver<(Value, Value)>,
-) -> Result<(), elasticsearch::Error> {
-    async fn send_queue(
-        es_client: &Elasticsearch,
-        queue: &mut Vec<JsonBody<Value>>,
-    ) -> Result<(), elasticsearch::Error> {
-        tracing::debug!("Bulk send {} lines", queue.len());
-        let body = std::mem::take(queue);
-        es_client
-            .bulk(BulkParts::Index(ELASTICSEARCH_INDEX))
-            .body(body)
+/// Update path of file that was moved without modification, keeping its processed contents.
+/// Returns `false` if file isn't indexed, was modified or changed extension
+async fn move_file(state: Arc<ServerState>, from: &Path, to: &Path) -> anyhow::Result<bool> {
+    // Paths of archive entries include path of archive, content type is detected with extension
+    if is_zip_path(from) || from.extension() != to.extension() {
+        return Ok(false);
+    }
+
+    let (pit_keep_alive, case_insensitive_paths) = {
+        let settings = state.settings.read().await;
+        (
+            settings.elasticsearch_pit_keep_alive,
+            settings.case_insensitive_paths,
+        )
+    };
+    let old_file = get_elasticsearch_files_list(
+        &state.es_client().await,
+        ELASTICSEARCH_INDEX,
+        Some(&[from.to_path_buf()]),
+        pit_keep_alive,
+        case_insensitive_paths,
+    )
+    .await?
+    .into_iter()
+    .find(|x| x.path == from);
+    let tmp = Arc::clone(&state);
+    let to_tmp = to.to_path_buf();
+    let new_file = tokio::task::spawn_blocking(move || {
+        get_file_system_partial_files_list(&tmp.settings.blocking_read(), vec![to_tmp])
+    })
+    .await
+    .unwrap_or_log()?
+    .into_iter()
+    .find(|x| x.path == to);
+
+    let (Some(old_file), Some(new_file)) = (old_file, new_file) else {
+        return Ok(false);
+    };
+    if old_file.size != new_file.size
+        || old_file.modified.timestamp() != new_file.modified.timestamp()
+        || old_file.attributes.is_online_only != new_file.attributes.is_online_only
+    {
+        return Ok(false);
+    }
+
+    tracing::debug!("Move file: {} -> {}", from.display(), to.display());
+    // Attributes such as hidden can change with name
+    let mut doc = serde_json::to_value(&new_file.attributes).unwrap_or_log();
+    doc["path"] = json!(to);
+    state
+        .es_client()
+        .await
+        .update(UpdateParts::IndexId(
+            ELASTICSEARCH_INDEX,
+            &old_file._id.unwrap_or_log(),
+        ))
+        .body(json!({ "doc": doc }))
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(true)
+}
+
+/// Update paths of moved files in index instead of processing them again.
+/// Returns paths that must be indexed normally
+pub async fn move_files(state: Arc<ServerState>, moves: Vec<(PathBuf, PathBuf)>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut moved = 0;
+    for (from, to) in moves {
+        match move_file(Arc::clone(&state), &from, &to).await {
+            Ok(true) => moved += 1,
+            Ok(false) => paths.extend([from, to]),
+            Err(e) => {
+                tracing::error!("Error moving file in index: {}", e);
+                paths.extend([from, to]);
+            }
+        }
+    }
+
+    if moved > 0 {
+        tracing::info!("Moved {} files in index", moved);
+        if let Err(e) = state
+            .es_client()
+            .await
+            .indices()
+            .refresh(IndicesRefreshParts::Index(&[ELASTICSEARCH_INDEX]))
             .send()
-            .await?;
-        Ok(())
+            .await
+        {
+            tracing::error!("Error refreshing index: {}", e);
+        }
     }
+    paths
+}
+
+/// Operation of bulk request: action and document, document is null for deletion
+struct BulkOperation {
+    /// Path of file, for error messages
+    path: PathBuf,
+    action: Value,
+    data: Value,
+}
+
+/// Send operations in one bulk request, returns results of operations in the same order,
+/// or nothing if all of them succeeded
+async fn send_operations(
+    es_client: &Elasticsearch,
+    index: &str,
+    operations: &[BulkOperation],
+) -> anyhow::Result<Vec<Value>> {
+    tracing::debug!("Bulk send {} operations", operations.len());
+    let body: Vec<_> = operations
+        .iter()
+        .flat_map(|x| [Some(&x.action), (!x.data.is_null()).then_some(&x.data)])
+        .flatten()
+        .map(JsonBody::new)
+        .collect();
+    let mut response_body: Value = es_client
+        .bulk(BulkParts::Index(index))
+        .body(body)
+        .send()
+        .await?
+        .error_for_status_code()?
+        .json()
+        .await?;
+    if response_body["errors"] == false {
+        return Ok(Vec::new());
+    }
+    match response_body["items"].take() {
+        Value::Array(items) if items.len() == operations.len() => Ok(items),
+        _ => Err(anyhow!("Unexpected bulk response: {}", response_body)),
+    }
+}
+
+/// Send batch of operations, retrying with exponential backoff when Elasticsearch is unavailable.
+/// Operations rejected because of load are sent again, other failed operations are reported.
+/// Returns number of failed operations
+async fn send_batch(state: &Arc<ServerState>, index: &str, batch: Vec<BulkOperation>) -> usize {
+    let retries = state.settings.read().await.elasticsearch_bulk_retries;
+    let mut operations = batch;
+    let mut failed = 0;
+    let mut delay = BULK_RETRY_DELAY_MIN;
+    for attempt in 0..=retries {
+        let is_last_attempt = attempt == retries;
+        histogram!(BULK_BATCH_SIZE, operations.len() as f64);
+        match send_operations(&state.es_client().await, index, &operations).await {
+            Ok(items) => {
+                let mut rejected = Vec::new();
+                for (operation, item) in operations.into_iter().zip(items) {
+                    // Item has single key with type of action
+                    let result = item.as_object().and_then(|x| x.values().next());
+                    let Some(error) = result.and_then(|x| x.get("error")) else {
+                        continue;
+                    };
+                    if result.is_some_and(|x| x["status"] == 429) && !is_last_attempt {
+                        rejected.push(operation);
+                        continue;
+                    }
+                    failed += 1;
+                    let e = format!("Error indexing {}: {}", operation.path.display(), error);
+                    let error = FileError {
+                        path: operation.path,
+                        stage: FileErrorStage::Bulk,
+                        error: error.to_string(),
+                    };
+                    on_file_error(Arc::clone(state), error, e).await;
+                }
+                operations = rejected;
+            }
+            Err(e) if !is_last_attempt => tracing::warn!(
+                "Error sending {} operations to Elasticsearch, retrying in {:?}: {:?}",
+                operations.len(),
+                delay,
+                e
+            ),
+            Err(e) => {
+                failed += operations.len();
+                for operation in &operations {
+                    let error = FileError {
+                        path: operation.path.clone(),
+                        stage: FileErrorStage::Bulk,
+                        error: format!("{e:#}"),
+                    };
+                    record_file_error(state, error).await;
+                }
+                let e = e.context(format!(
+                    "Error sending {} operations to Elasticsearch",
+                    operations.len()
+                ));
+                on_event(Arc::clone(state), IndexingEvent::Error(format!("{e:?}"))).await;
+                break;
+            }
+        }
+        if operations.is_empty() {
+            break;
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(BULK_RETRY_DELAY_MAX);
+    }
+    failed
+}
 
+/// Accept operations from channel and bulk send them to Elasticsearch.
+/// Failed operations don't stop sending, returns their number
+async fn bulk_send(
+    state: Arc<ServerState>,
+    index: String,
+    mut rx: Receiver<BulkOperation>,
+) -> usize {
     let mut queue = Vec::new();
-    let mut cnt: usize = 0;
+    let mut failed = 0;
     let batch_size = state.settings.read().await.elasticsearch_batch_size;
-    while let Some((action, data)) = rx.recv().await {
-        queue.push(JsonBody::new(action));
-        if !data.is_null() {
-            queue.push(JsonBody::new(data));
+    while let Some(operation) = rx.recv().await {
+        if let Err(e) = injector().bulk_item(&operation.path, &operation.action) {
+            let error = FileError {
+                path: operation.path,
+                stage: FileErrorStage::Bulk,
+                error: format!("{e:#}"),
+            };
+            on_file_error(Arc::clone(&state), error, format!("{e:?}")).await;
+            failed += 1;
+            continue;
         }
-        cnt += 1;
+        queue.push(operation);
 
-        if cnt >= batch_size {
-            send_queue(&state.es_client, &mut queue).await?;
-            on_event(Arc::clone(&state), IndexingEvent::FilesSent(cnt)).await;
-            cnt = 0;
+        if queue.len() >= batch_size {
+            let cnt = queue.len();
+            let batch_failed = send_batch(&state, &index, std::mem::take(&mut queue)).await;
+            on_event(
+                Arc::clone(&state),
+                IndexingEvent::FilesSent(cnt - batch_failed),
+            )
+            .await;
+            failed += batch_failed;
+        }
+    }
+    if !queue.is_empty() {
+        let cnt = queue.len();
+        let batch_failed = send_batch(&state, &index, queue).await;
+        on_event(
+            Arc::clone(&state),
+            IndexingEvent::FilesSent(cnt - batch_failed),
+        )
+        .await;
+        failed += batch_failed;
+    }
+    failed
+}
+
+/// Delete partially filled new generation of index
+async fn discard_target(state: Arc<ServerState>, target: &IndexingTarget) {
+    if let IndexingTarget::NewGeneration(index) = target {
+        tracing::warn!("Deleting index {} after failed rebuild", index);
+        if let Err(e) =
+            create_index::delete_generation_index(&state.es_client().await, &[index.as_str()]).await
+        {
+            tracing::error!("Error deleting index {}: {}", index, e);
         }
     }
-    send_queue(&state.es_client, &mut queue).await?;
-    on_event(state, IndexingEvent::FilesSent(cnt)).await;
-    Ok(())
 }
 
 /// Indexing files
-pub async fn indexing_process(state: Arc<ServerState>, paths: Option<Vec<PathBuf>>) {
+pub async fn indexing_process(
+    state: Arc<ServerState>,
+    paths: Option<Vec<PathBuf>>,
+    target: IndexingTarget,
+    reprocess: Option<ProvenanceCriterion>,
+) {
     let start_time = Instant::now();
 
     on_event(Arc::clone(&state), IndexingEvent::Started).await;
+    errors::clear_file_errors(&state).await;
+
+    let current_provenance = update_toolchain(&state).await.provenance();
+
+    let (pit_keep_alive, case_insensitive_paths) = {
+        let settings = state.settings.read().await;
+        (
+            settings.elasticsearch_pit_keep_alive,
+            settings.case_insensitive_paths,
+        )
+    };
+
+    // Documents of removed indexing directories are removed before comparing files,
+    // as only full indexing would find them. New generation of index doesn't have them
+    if let IndexingTarget::Current = target {
+        let directories: Vec<_> = state
+            .settings
+            .read()
+            .await
+            .indexing_directories
+            .iter()
+            .filter(|x| !x.exclude)
+            .map(|x| x.path.clone())
+            .collect();
+        // With no directories all documents would be removed, that's left to full indexing
+        if !directories.is_empty() {
+            match remove_outside_directories(
+                &state.es_client().await,
+                ELASTICSEARCH_INDEX,
+                &directories,
+                case_insensitive_paths,
+            )
+            .await
+            {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(
+                    "Removed {} documents outside of indexing directories",
+                    removed
+                ),
+                Err(e) => tracing::error!(
+                    "Error removing documents outside of indexing directories: {}",
+                    e
+                ),
+            }
+        }
+    }
 
     // Get files lists from file system and Elasticsearch
     let tmp = Arc::clone(&state);
@@ -193,16 +799,24 @@ pub async fn indexing_process(state: Arc<ServerState>, paths: Option<Vec<PathBuf
         }),
     };
 
-    let elasticsearch_files_f = get_elasticsearch_files_list(&state.es_client, paths.as_deref());
+    let es_client = state.es_client().await;
+    let elasticsearch_files_f = get_elasticsearch_files_list(
+        &es_client,
+        target.index(),
+        paths.as_deref(),
+        pit_keep_alive,
+        case_insensitive_paths,
+    );
 
     let (file_system_files, elasticsearch_files) =
         tokio::join!(file_system_files_f, elasticsearch_files_f);
 
-    let file_system_files = match file_system_files.unwrap_or_log() {
+    let mut file_system_files = match file_system_files.unwrap_or_log() {
         Ok(x) => x,
         Err(e) => {
             on_event(Arc::clone(&state), IndexingEvent::DiffFailed(e.to_string())).await;
             tracing::error!("Error getting indexable files: {}", e);
+            discard_target(state, &target).await;
             return;
         }
     };
@@ -211,18 +825,124 @@ pub async fn indexing_process(state: Arc<ServerState>, paths: Option<Vec<PathBuf
         Err(e) => {
             on_event(Arc::clone(&state), IndexingEvent::DiffFailed(e.to_string())).await;
             tracing::error!("Error reading file info from Elasticsearch: {}", e);
+            discard_target(state, &target).await;
             return;
         }
     };
+    parse_failures::prune_parse_failures(&state, &file_system_files, paths.as_deref()).await;
+
+    // Entries of archives aren't in file system, they are compared by their archives
+    let (archive_entries, elasticsearch_files): (Vec<_>, Vec<_>) =
+        elasticsearch_files.into_iter().partition(|x| x.in_archive);
+
+    // Keep tags of files from current index when rebuilding
+    if let IndexingTarget::NewGeneration(_) = target {
+        let current_files = match get_elasticsearch_files_list(
+            &state.es_client().await,
+            ELASTICSEARCH_INDEX,
+            None,
+            pit_keep_alive,
+            case_insensitive_paths,
+        )
+        .await
+        {
+            Ok(x) => x,
+            Err(e) => {
+                on_event(Arc::clone(&state), IndexingEvent::DiffFailed(e.to_string())).await;
+                tracing::error!("Error reading file info from Elasticsearch: {}", e);
+                discard_target(state, &target).await;
+                return;
+            }
+        };
+        let mut current_tags: HashMap<_, _> = current_files
+            .into_iter()
+            .filter(|x| !x.tags.is_empty())
+            .map(|x| (x.path_key().into_owned(), x.tags))
+            .collect();
+        for file in &mut file_system_files {
+            if let Some(tags) = current_tags.remove(&*file.path_key()) {
+                file.tags = tags;
+            }
+        }
+    }
+
+    // Files with wrong clock are indexed normally, but reported
+    let modified_in_future = file_system_files
+        .iter()
+        .filter(|x| x.is_modified_in_future())
+        .inspect(|x| tracing::debug!("File modified in future: {}", x.path.display()))
+        .count();
+    if modified_in_future > 0 {
+        tracing::warn!(
+            "Modification time of {} files is in future",
+            modified_in_future
+        );
+    }
+
+    // Hash files with hash change detection before comparing, reading them is slow
+    let (hash_algorithm, hash_chunk_size) = {
+        let settings = state.settings.read().await;
+        (settings.hash_algorithm, settings.hash_chunk_size)
+    };
+    let (elasticsearch_files, file_system_files) = tokio::task::spawn_blocking(move || {
+        calculate_change_detection_hashes(
+            &elasticsearch_files,
+            &mut file_system_files,
+            hash_algorithm,
+            hash_chunk_size,
+        );
+        (elasticsearch_files, file_system_files)
+    })
+    .await
+    .unwrap_or_log();
 
-    // Calculate lists difference
-    let diff = FilesDiff::from_vec(elasticsearch_files, file_system_files);
+    // Calculate lists difference.
+    // Online-only files indexed without contents are processed when they were downloaded
+    // or can be downloaded. Files indexed after failures of parsing are processed when
+    // their failures were forgotten or the limit was raised
+    let (hydration_enabled, parse_failures_max) = {
+        let settings = state.settings.read().await;
+        (
+            settings.online_only_hydration_budget > 0,
+            settings.parse_failures_max,
+        )
+    };
+    let failures = state.parse_failures.read().await.clone();
+    let mut diff = FilesDiff::from_vec_with(elasticsearch_files, file_system_files, |old, new| {
+        let online_only_pending = old.attributes.is_online_only == Some(true)
+            && (hydration_enabled || new.attributes.is_online_only != Some(true));
+        let parse_pending = old.parse_failed
+            && !parse_failures::is_skipped(
+                &failures,
+                parse_failures_max,
+                &old.path,
+                &old.hash,
+                old.modified,
+            );
+        let outdated = reprocess.is_some_and(|criterion| match &old.provenance {
+            Some(provenance) => provenance.is_outdated(&current_provenance, criterion),
+            None => true,
+        });
+        online_only_pending || parse_pending || outdated
+    });
+    let hydration = limit_hydration(&*state.settings.read().await, &mut diff);
+    if hydration.deferred > 0 {
+        tracing::info!(
+            "Hydration budget is exhausted, {} online-only files are left for later indexings",
+            hydration.deferred
+        );
+    }
+    let index_archives = state.settings.read().await.index_archives;
+    remove_archive_entries(&mut diff, archive_entries, index_archives);
     on_event(
         Arc::clone(&state),
         IndexingEvent::DiffCalculated {
             to_add: diff.added.len(),
             to_remove: diff.removed.len(),
             to_update: diff.modified.len(),
+            modified_in_future,
+            hydration_size: hydration.size,
+            online_only_deferred: hydration.deferred,
         },
     )
     .await;
@@ -232,37 +952,83 @@ pub async fn indexing_process(state: Arc<ServerState>, paths: Option<Vec<PathBuf
         CHANNEL_CAPACITY_MULTIPLIER * state.settings.read().await.elasticsearch_batch_size;
     let (tx, rx) = mpsc::channel(channel_capacity);
     let tmp = Arc::clone(&state);
-    let bulk_send_f = tokio::spawn(async move { bulk_send(tmp, rx).await });
+    let index = target.index().to_owned();
+    let bulk_send_f = tokio::spawn(async move { bulk_send(tmp, index, rx).await });
+    let progress_f = tokio::spawn(progress::report_progress(Arc::clone(&state)));
 
     // Process differences and send operations to channel
-    streaming_process(Arc::clone(&state), tx.clone(), diff.added, add_new).await;
+    let stage_durations = Arc::new(Mutex::new(StageDurations::default()));
+    streaming_process(
+        Arc::clone(&state),
+        tx.clone(),
+        diff.added,
+        add_new,
+        Arc::clone(&stage_durations),
+    )
+    .await;
     streaming_process(
         Arc::clone(&state),
         tx.clone(),
         diff.modified,
         update_modified,
+        Arc::clone(&stage_durations),
     )
     .await;
-    streaming_process(Arc::clone(&state), tx, diff.removed, remove_old).await;
-    if let Err(e) = bulk_send_f.await.unwrap_or_log() {
-        on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
-    }
+    streaming_process(
+        Arc::clone(&state),
+        tx,
+        diff.removed,
+        remove_old,
+        Arc::clone(&stage_durations),
+    )
+    .await;
+    let failed_operations = bulk_send_f.await.unwrap_or_log();
+    // Rebuilt index without some files doesn't replace current one
+    let mut send_failed = failed_operations > 0;
+    progress_f.abort();
 
     // Finish indexing
     if let Err(e) = state
-        .es_client
+        .es_client()
+        .await
         .indices()
-        .refresh(IndicesRefreshParts::Index(&[ELASTICSEARCH_INDEX]))
+        .refresh(IndicesRefreshParts::Index(&[target.index()]))
         .send()
         .await
     {
         on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
+        send_failed = true;
+    }
+
+    // Replace current index with rebuilt one
+    if let IndexingTarget::NewGeneration(index) = &target {
+        if send_failed {
+            discard_target(Arc::clone(&state), &target).await;
+        } else {
+            let es_client = state.es_client().await;
+            match create_index::switch_alias(&es_client, index).await {
+                Ok(old_indices) => {
+                    *state.index_schema.write().await = IndexSchemaStatus::Compatible;
+                    create_index::delete_old_indices(&es_client, &old_indices).await;
+                }
+                Err(e) => {
+                    on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
+                    discard_target(Arc::clone(&state), &target).await;
+                }
+            }
+        }
     }
 
+    errors::save_file_errors(&state).await;
+    parse_failures::save_parse_failures(&state).await;
     let indexing_duration = Instant::now() - start_time;
     on_event(
         Arc::clone(&state),
-        IndexingEvent::Finished(indexing_duration),
+        IndexingEvent::Finished {
+            duration: indexing_duration,
+            failed_operations,
+            stage_durations: stage_durations.lock().await.named(),
+        },
     )
     .await;
 }
@@ -273,17 +1039,206 @@ pub async fn index(State(state): State<Arc<ServerState>>) -> (StatusCode, String
         return (StatusCode::BAD_REQUEST, "Already indexing".to_owned());
     }
 
-    tokio::spawn(async move { indexing_process(state, None).await });
+    tokio::spawn(async move { indexing_process(state, None, IndexingTarget::Current, None).await });
     (StatusCode::ACCEPTED, String::new())
 }
 
-/// Delete and create new index
+/// Remove documents of files that are excluded by current settings, after settings of exclusion
+/// were changed. Only these files are indexed again, which removes them from index,
+/// as partial indexing wouldn't reach files that are no longer changed
+pub async fn remove_excluded_files(state: Arc<ServerState>) -> anyhow::Result<()> {
+    let (pit_keep_alive, case_insensitive_paths) = {
+        let settings = state.settings.read().await;
+        (
+            settings.elasticsearch_pit_keep_alive,
+            settings.case_insensitive_paths,
+        )
+    };
+    let files = get_elasticsearch_files_list(
+        &state.es_client().await,
+        ELASTICSEARCH_INDEX,
+        None,
+        pit_keep_alive,
+        case_insensitive_paths,
+    )
+    .await?;
+    let paths: HashSet<_> = {
+        let settings = state.settings.read().await;
+        let exclude_regexes = ExcludeRegexes::new(&settings)?;
+        files
+            .into_iter()
+            .filter(|file| is_excluded_file(&settings, &exclude_regexes, file))
+            // Entries are removed with their archive
+            .map(|file| file.archive_path().into_owned())
+            .collect()
+    };
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    if !state.indexing_status.read().await.can_start() {
+        return Err(anyhow!(
+            "Already indexing, documents of {} excluded files are removed by the next full indexing",
+            paths.len()
+        ));
+    }
+    tracing::info!("Removing documents of {} excluded files", paths.len());
+    indexing_process(
+        state,
+        Some(paths.into_iter().collect()),
+        IndexingTarget::Current,
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+/// Start indexing files, also processing again files with outdated provenance
+pub async fn reprocess(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<ReprocessRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !state.indexing_status.read().await.can_start() {
+        return Err((StatusCode::CONFLICT, "Already indexing".to_owned()));
+    }
+
+    // Without known Tika version all files would be processed again
+    let toolchain = update_toolchain(&state).await;
+    if toolchain.tika_version.is_none()
+        && matches!(
+            request.criterion,
+            ProvenanceCriterion::TikaVersionChanged | ProvenanceCriterion::AnyChanged
+        )
+    {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Can't get Apache Tika version".to_owned(),
+        ));
+    }
+
+    tokio::spawn(async move {
+        indexing_process(
+            state,
+            None,
+            IndexingTarget::Current,
+            Some(request.criterion),
+        )
+        .await
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Start indexing all files into new index, replacing current one after indexing
+pub async fn rebuild_index(
+    State(state): State<Arc<ServerState>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let previous_status = mark_indexing_started(&state).await?;
+    // Set before tags are read from current index
+    *state.rebuilding.write().await = true;
+
+    let options = IndexOptions::new(&*state.settings.read().await);
+    let index = match create_index::create_next_generation_index(&state.es_client().await, &options)
+        .await
+    {
+        Ok(x) => x,
+        Err(e) => {
+            *state.rebuilding.write().await = false;
+            *state.indexing_status.write().await = previous_status;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+    tokio::spawn(async move {
+        indexing_process(
+            Arc::clone(&state),
+            None,
+            IndexingTarget::NewGeneration(index),
+            None,
+        )
+        .await;
+        *state.rebuilding.write().await = false;
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Checks that indexing can start and marks it as started under one lock, so concurrent
+/// requests can't start several indexings. Returns previous status to restore if starting fails
+pub async fn mark_indexing_started(
+    state: &ServerState,
+) -> Result<IndexingStatus, (StatusCode, String)> {
+    let mut status = state.indexing_status.write().await;
+    if !status.can_start() {
+        return Err((StatusCode::CONFLICT, "Already indexing".to_owned()));
+    }
+    Ok(std::mem::replace(
+        &mut *status,
+        IndexingStatus::CalculatingDiff,
+    ))
+}
+
+/// Reject changes of documents while index is rebuilt
+pub async fn check_not_rebuilding(state: &ServerState) -> Result<(), (StatusCode, String)> {
+    if *state.rebuilding.read().await {
+        return Err((
+            StatusCode::CONFLICT,
+            "Index is being rebuilt, try again after it finishes".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Issue token for deleting index, replacing previous one
+pub async fn delete_token(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<DeleteToken>, (StatusCode, String)> {
+    let doc_cnt = status::index_stats(&state.es_client().await)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .doc_cnt;
+    let token = Uuid::new_v4().to_string();
+    *state.delete_token.write().await = Some((token.clone(), Instant::now()));
+    Ok(Json(DeleteToken { token, doc_cnt }))
+}
+
+/// Get compatibility of index with current schema version and settings of models
+pub async fn index_schema(State(state): State<Arc<ServerState>>) -> Json<IndexSchemaStatus> {
+    Json(state.index_schema.read().await.clone())
+}
+
+/// Check that token is the last issued one and isn't expired. Token can be used only once
+async fn check_delete_token(state: &ServerState, token: &str) -> Result<(), (StatusCode, String)> {
+    let mut delete_token = state.delete_token.write().await;
+    match &*delete_token {
+        Some((issued_token, _)) if issued_token != token => Err((
+            StatusCode::CONFLICT,
+            "Delete token doesn't match last issued one".to_owned(),
+        )),
+        Some((_, issue_time))
+            if issue_time.elapsed() > Duration::from_secs(DELETE_TOKEN_TTL_SECS) =>
+        {
+            *delete_token = None;
+            Err((StatusCode::GONE, "Delete token has expired".to_owned()))
+        }
+        Some(_) => {
+            *delete_token = None;
+            Ok(())
+        }
+        None => Err((
+            StatusCode::BAD_REQUEST,
+            "Delete token wasn't issued".to_owned(),
+        )),
+    }
+}
+
+/// Replace current index with new empty one.
+/// New index is created before switching alias to it, so searches don't fail meanwhile
 pub async fn delete_index(
     State(state): State<Arc<ServerState>>,
+    Json(request): Json<DeleteIndexRequest>,
 ) -> Result<(), (StatusCode, String)> {
     if !state.indexing_status.read().await.can_start() {
         return Err((StatusCode::BAD_REQUEST, "Already indexing".to_owned()));
     }
+    check_delete_token(&state, &request.token).await?;
 
     let start_time = Instant::now();
     on_event(
@@ -292,27 +1247,610 @@ pub async fn delete_index(
             to_add: 0,
             to_remove: 0,
             to_update: 0,
+            modified_in_future: 0,
+            hydration_size: 0,
+            online_only_deferred: 0,
         },
     )
     .await;
 
-    state
-        .es_client
-        .indices()
-        .delete(IndicesDeleteParts::Index(&[ELASTICSEARCH_INDEX]))
-        .send()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    create_index::create_index(&state.es_client)
-        .await
-        .expect_or_log("Can't create Elasticsearch index");
+    let res = async {
+        let options = IndexOptions::new(&*state.settings.read().await);
+        let es_client = state.es_client().await;
+        let index = create_index::create_next_generation_index(&es_client, &options).await?;
+        let old_indices = match create_index::switch_alias(&es_client, &index).await {
+            Ok(x) => x,
+            Err(e) => {
+                discard_target(Arc::clone(&state), &IndexingTarget::NewGeneration(index)).await;
+                return Err(e);
+            }
+        };
+        *state.index_schema.write().await = IndexSchemaStatus::Compatible;
+        create_index::delete_old_indices(&es_client, &old_indices).await;
+        Ok::<(), elasticsearch::Error>(())
+    }
+    .await;
+    if let Err(e) = &res {
+        on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
+    }
 
     let deleting_duration = Instant::now() - start_time;
     on_event(
         Arc::clone(&state),
-        IndexingEvent::Finished(deleting_duration),
+        IndexingEvent::Finished {
+            duration: deleting_duration,
+            failed_operations: 0,
+            stage_durations: Vec::new(),
+        },
     )
     .await;
-    Ok(())
+    res.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex as StdMutex,
+        },
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use axum::extract::Path as PathParam;
+    use common_lib::{
+        provenance::Provenance,
+        settings::IndexingDirectory,
+        tags::{SetTagsRequest, TagsOperation, TagsRequest},
+    };
+    use serde_json::json;
+
+    use crate::{
+        provenance::PARSER_VERSION,
+        tags::{set_tags, update_tags},
+        test_util::{indexing_test_state, test_settings, test_state, MockElasticsearch},
+    };
+
+    use super::*;
+
+    /// Index with document of file with tags, file is modified after it was indexed
+    fn modified_tagged_file(dir: &Path) -> (PathBuf, MockElasticsearch) {
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "new text").unwrap();
+        let es = MockElasticsearch::with_documents([(
+            "notes".to_owned(),
+            json!({ "path": path, "modified": 0, "size": 8, "tags": ["work", "draft"] }),
+        )]);
+        (path, es)
+    }
+
+    #[tokio::test]
+    async fn tags_are_kept_when_modified_file_is_indexed_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, es) = modified_tagged_file(dir.path());
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(state, None, IndexingTarget::Current, None).await;
+
+        let documents = es.documents.lock().unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents["notes"]["path"], json!(path));
+        assert_eq!(documents["notes"]["content"], "new text");
+        assert_eq!(documents["notes"]["tags"], json!(["work", "draft"]));
+    }
+
+    #[tokio::test]
+    async fn tags_are_kept_when_modified_file_is_indexed_by_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, es) = modified_tagged_file(dir.path());
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(state, Some(vec![path]), IndexingTarget::Current, None).await;
+
+        let documents = es.documents.lock().unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents["notes"]["content"], "new text");
+        assert_eq!(documents["notes"]["tags"], json!(["work", "draft"]));
+    }
+
+    fn provenance(tika_version: &str) -> Value {
+        serde_json::to_value(Provenance {
+            tika_version: Some(tika_version.to_owned()),
+            models: Vec::new(),
+            parser_version: Some(PARSER_VERSION),
+        })
+        .unwrap()
+    }
+
+    /// Index with document of unmodified tagged file processed by old version of Apache Tika
+    fn file_with_old_provenance(dir: &Path) -> (PathBuf, MockElasticsearch) {
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "text").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1000))
+            .unwrap();
+        let es = MockElasticsearch::with_documents([(
+            "notes".to_owned(),
+            json!({
+                "path": path,
+                "modified": 1000,
+                "size": 4,
+                "content": "text",
+                "tags": ["work"],
+                "provenance": provenance("old")
+            }),
+        )]);
+        (path, es)
+    }
+
+    #[tokio::test]
+    async fn outdated_file_is_processed_again_with_current_provenance() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, es) = file_with_old_provenance(dir.path());
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+        assert_eq!(
+            es.documents.lock().unwrap()["notes"]["provenance"],
+            provenance("old")
+        );
+
+        let criterion = Some(ProvenanceCriterion::TikaVersionChanged);
+        indexing_process(state, None, IndexingTarget::Current, criterion).await;
+        let documents = es.documents.lock().unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents["notes"]["provenance"], provenance("test"));
+        assert_eq!(documents["notes"]["tags"], json!(["work"]));
+    }
+
+    #[tokio::test]
+    async fn provenance_is_kept_when_file_is_moved_or_tagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, es) = file_with_old_provenance(dir.path());
+        let state = indexing_test_state(dir.path(), &es).await;
+        let new_path = dir.path().join("moved.txt");
+        std::fs::rename(&path, &new_path).unwrap();
+
+        assert!(move_file(Arc::clone(&state), &path, &new_path)
+            .await
+            .unwrap());
+        let tags_request = TagsRequest {
+            operation: TagsOperation::Add,
+            tags: vec!["draft".to_owned()],
+        };
+        let Json(tags) = update_tags(
+            State(state),
+            PathParam("notes".to_owned()),
+            Json(tags_request),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tags, ["draft", "work"]);
+
+        let documents = es.documents.lock().unwrap();
+        assert_eq!(documents["notes"]["path"], json!(new_path));
+        assert_eq!(documents["notes"]["tags"], json!(["draft", "work"]));
+        assert_eq!(documents["notes"]["content"], "text");
+        assert_eq!(documents["notes"]["provenance"], provenance("old"));
+    }
+
+    #[tokio::test]
+    async fn tags_are_replaced_by_document_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, es) = file_with_old_provenance(dir.path());
+        let state = indexing_test_state(dir.path(), &es).await;
+        let request = SetTagsRequest {
+            id: "notes".to_owned(),
+            tags: vec![" toread ".to_owned(), "taxes2023".to_owned()],
+        };
+
+        let Json(tags) = set_tags(State(state), Json(request)).await.unwrap();
+        assert_eq!(tags, ["taxes2023", "toread"]);
+        assert_eq!(
+            es.documents.lock().unwrap()["notes"]["tags"],
+            json!(["taxes2023", "toread"])
+        );
+    }
+
+    #[tokio::test]
+    async fn tags_are_not_changed_during_rebuild() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, es) = file_with_old_provenance(dir.path());
+        let state = indexing_test_state(dir.path(), &es).await;
+        let tags_request = TagsRequest {
+            operation: TagsOperation::Add,
+            tags: vec!["draft".to_owned()],
+        };
+
+        // Tags of current index are already copied into new generation
+        *state.rebuilding.write().await = true;
+        let res = update_tags(
+            State(Arc::clone(&state)),
+            PathParam("notes".to_owned()),
+            Json(tags_request.clone()),
+        )
+        .await;
+        assert_eq!(res.err().map(|x| x.0), Some(StatusCode::CONFLICT));
+        assert_eq!(
+            es.documents.lock().unwrap()["notes"]["tags"],
+            json!(["work"])
+        );
+
+        *state.rebuilding.write().await = false;
+        let Json(tags) = update_tags(
+            State(state),
+            PathParam("notes".to_owned()),
+            Json(tags_request),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tags, ["draft", "work"]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_rebuilds_are_rejected() {
+        let state = test_state(test_settings()).await;
+
+        let (first, second) =
+            tokio::join!(mark_indexing_started(&state), mark_indexing_started(&state));
+        assert!(matches!(first, Ok(IndexingStatus::NotStarted)));
+        assert_eq!(second.err().map(|x| x.0), Some(StatusCode::CONFLICT));
+
+        let res = rebuild_index(State(Arc::clone(&state))).await;
+        assert_eq!(res.err().map(|x| x.0), Some(StatusCode::CONFLICT));
+        assert!(!*state.rebuilding.read().await);
+    }
+
+    #[tokio::test]
+    async fn delete_token_is_accepted_once() {
+        let state = test_state(test_settings()).await;
+        let status = |res: Result<(), (StatusCode, String)>| res.err().map(|x| x.0);
+        assert_eq!(
+            status(check_delete_token(&state, "token").await),
+            Some(StatusCode::BAD_REQUEST)
+        );
+
+        *state.delete_token.write().await = Some(("token".to_owned(), Instant::now()));
+        // Mismatched token doesn't revoke issued one
+        assert_eq!(
+            status(check_delete_token(&state, "other").await),
+            Some(StatusCode::CONFLICT)
+        );
+        assert_eq!(status(check_delete_token(&state, "token").await), None);
+        assert_eq!(
+            status(check_delete_token(&state, "token").await),
+            Some(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_delete_token_is_rejected_and_revoked() {
+        let state = test_state(test_settings()).await;
+        let issue_time = Instant::now() - Duration::from_secs(DELETE_TOKEN_TTL_SECS + 1);
+        *state.delete_token.write().await = Some(("token".to_owned(), issue_time));
+
+        let res = check_delete_token(&state, "token").await;
+        assert_eq!(res.err().map(|x| x.0), Some(StatusCode::GONE));
+        assert!(state.delete_token.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn file_changed_after_scan_is_indexed_with_new_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes_fault_change_file.txt");
+        std::fs::write(&path, "text from scan").unwrap();
+        let es = MockElasticsearch::default();
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+
+        let documents: Vec<_> = es.documents.lock().unwrap().values().cloned().collect();
+        let [document] = &documents[..] else {
+            panic!("File isn't indexed once");
+        };
+        assert_eq!(document["content"], "Injected change");
+        assert_eq!(document["size"], 15);
+        assert_eq!(document["modified"], 86400);
+        let IndexingStatus::Finished(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing didn't finish");
+        };
+        assert_eq!(data.changed, 1);
+        assert_eq!(data.errors_cnt, 0);
+    }
+
+    #[tokio::test]
+    async fn injected_faults_are_counted_without_losing_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "a.txt",
+            "b_fault_parse_file.txt",
+            "c_fault_bulk_item.txt",
+            "d_fault_metadata.txt",
+            "e.txt",
+        ] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+        let es = MockElasticsearch::with_documents([(
+            "removed".to_owned(),
+            json!({ "path": dir.path().join("removed.txt"), "modified": 0, "size": 1 }),
+        )]);
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+
+        // File with failed metadata isn't found by scan, others are indexed once
+        let mut paths: Vec<_> = es
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| PathBuf::from(x["path"].as_str().unwrap()))
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [dir.path().join("a.txt"), dir.path().join("e.txt")]);
+
+        let mut errors: Vec<_> = state
+            .file_errors
+            .read()
+            .await
+            .iter()
+            .map(|x| (x.path.clone(), x.stage))
+            .collect();
+        errors.sort_by(|x, y| x.0.cmp(&y.0));
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    dir.path().join("b_fault_parse_file.txt"),
+                    FileErrorStage::Parse
+                ),
+                (
+                    dir.path().join("c_fault_bulk_item.txt"),
+                    FileErrorStage::Bulk
+                ),
+            ]
+        );
+
+        let IndexingStatus::Finished(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing didn't finish");
+        };
+        assert_eq!((data.to_add, data.to_update, data.to_remove), (4, 0, 1));
+        // Files failed parsing aren't processed, failed bulk operations aren't sent
+        assert_eq!(data.processed, 3);
+        assert_eq!(data.sent, 3);
+        assert_eq!(data.errors_cnt, 2);
+        assert_eq!(data.failed_operations, 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_lost_bulk_response_does_not_duplicate_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+        let mut es = MockElasticsearch::default();
+        es.lost_bulk_responses = 1;
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+
+        let IndexingStatus::Finished(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing didn't finish");
+        };
+        assert_eq!(data.errors_cnt, 0);
+        assert_eq!(es.bulk_requests.load(Ordering::SeqCst), 2);
+        let mut paths: Vec<_> = es
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| PathBuf::from(x["path"].as_str().unwrap()))
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [dir.path().join("a.txt"), dir.path().join("b.txt")]);
+    }
+
+    #[tokio::test]
+    async fn documents_rejected_by_bulk_request_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b_rejected.txt", "c.txt"] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+        let mut es = MockElasticsearch::default();
+        es.rejected_path = Some("rejected");
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+
+        let mut paths: Vec<_> = es
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| PathBuf::from(x["path"].as_str().unwrap()))
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [dir.path().join("a.txt"), dir.path().join("c.txt")]);
+
+        let errors = state.file_errors.read().await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, dir.path().join("b_rejected.txt"));
+        assert_eq!(errors[0].stage, FileErrorStage::Bulk);
+        assert!(errors[0].error.contains("mapper_parsing_exception"));
+
+        let IndexingStatus::Finished(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing didn't finish");
+        };
+        assert_eq!(data.sent, 2);
+        assert_eq!(data.errors_cnt, 1);
+        assert_eq!(data.failed_operations, 1);
+        // Rejected document isn't sent again
+        assert_eq!(es.bulk_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn excluded_directory_is_removed_and_not_added_back_by_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let excluded = dir.path().join("secret");
+        std::fs::create_dir(&excluded).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(excluded.join("b.txt"), "b").unwrap();
+        let es = MockElasticsearch::default();
+        let state = indexing_test_state(dir.path(), &es).await;
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+        assert_eq!(es.documents.lock().unwrap().len(), 2);
+
+        state
+            .settings
+            .write()
+            .await
+            .indexing_directories
+            .push(IndexingDirectory {
+                path: excluded.clone(),
+                exclude: true,
+                ..Default::default()
+            });
+        remove_excluded_files(Arc::clone(&state)).await.unwrap();
+        // Watcher reports change of file inside of excluded directory
+        std::fs::write(excluded.join("b.txt"), "changed").unwrap();
+        indexing_process(
+            Arc::clone(&state),
+            Some(vec![excluded.join("b.txt")]),
+            IndexingTarget::Current,
+            None,
+        )
+        .await;
+
+        let paths: Vec<_> = es
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| PathBuf::from(x["path"].as_str().unwrap()))
+            .collect();
+        assert_eq!(paths, [dir.path().join("a.txt")]);
+    }
+
+    impl ProcessedFile for PathBuf {
+        fn path(&self) -> &Path {
+            self
+        }
+    }
+
+    /// Paths of files in order their processing finished
+    static PROCESSED_PATHS: StdMutex<Vec<PathBuf>> = StdMutex::new(Vec::new());
+
+    /// Files of slow directory take longer to process
+    async fn process_slow_directory(
+        _state: Arc<ServerState>,
+        path: PathBuf,
+    ) -> anyhow::Result<FileOperations> {
+        if path.starts_with("/slow") {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        PROCESSED_PATHS.lock().unwrap().push(path);
+        Ok((Vec::new(), None))
+    }
+
+    #[tokio::test]
+    async fn files_of_other_directories_dont_wait_for_limited_directory() {
+        let mut settings = test_settings();
+        settings.max_concurrent_files = 4;
+        settings.indexing_directories = vec![
+            IndexingDirectory {
+                path: PathBuf::from("/slow"),
+                max_concurrency: Some(1),
+                ..Default::default()
+            },
+            IndexingDirectory {
+                path: PathBuf::from("/fast"),
+                ..Default::default()
+            },
+        ];
+        let state = test_state(settings).await;
+        *state.indexing_status.write().await = IndexingStatus::Indexing(Default::default());
+        let (tx, _rx) = mpsc::channel(1);
+        let files = ["/slow/a.txt", "/slow/b.txt", "/fast/c.txt"].map(PathBuf::from);
+
+        streaming_process(
+            state,
+            tx,
+            files.to_vec(),
+            process_slow_directory,
+            Default::default(),
+        )
+        .await;
+
+        let [slow_a, slow_b, fast] = files;
+        assert_eq!(*PROCESSED_PATHS.lock().unwrap(), [fast, slow_a, slow_b]);
+    }
+
+    /// Numbers of files processed now and at most, of all directories and of slow one
+    static RUNNING: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+    static MAX_RUNNING: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+    async fn process_counting_concurrency(
+        _state: Arc<ServerState>,
+        path: PathBuf,
+    ) -> anyhow::Result<FileOperations> {
+        let counters = if path.starts_with("/slow") {
+            0..2
+        } else {
+            0..1
+        };
+        for i in counters.clone() {
+            let cnt = RUNNING[i].fetch_add(1, Ordering::SeqCst) + 1;
+            MAX_RUNNING[i].fetch_max(cnt, Ordering::SeqCst);
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        for i in counters {
+            RUNNING[i].fetch_sub(1, Ordering::SeqCst);
+        }
+        Ok((Vec::new(), None))
+    }
+
+    #[tokio::test]
+    async fn processing_is_limited_by_global_and_directory_limits() {
+        let mut settings = test_settings();
+        settings.max_concurrent_files = 3;
+        settings.indexing_directories = vec![IndexingDirectory {
+            path: PathBuf::from("/slow"),
+            max_concurrency: Some(2),
+            ..Default::default()
+        }];
+        let state = test_state(settings).await;
+        *state.indexing_status.write().await = IndexingStatus::Indexing(Default::default());
+        let (tx, _rx) = mpsc::channel(1);
+        let files: Vec<_> = (0..10)
+            .map(|i| PathBuf::from(format!("/slow/{i}.txt")))
+            .chain((0..10).map(|i| PathBuf::from(format!("/fast/{i}.txt"))))
+            .collect();
+
+        streaming_process(
+            Arc::clone(&state),
+            tx,
+            files,
+            process_counting_concurrency,
+            Default::default(),
+        )
+        .await;
+
+        assert_eq!(MAX_RUNNING[0].load(Ordering::SeqCst), 3);
+        assert_eq!(MAX_RUNNING[1].load(Ordering::SeqCst), 2);
+        let IndexingStatus::Indexing(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing status changed");
+        };
+        assert_eq!(data.processed, 20);
+    }
+
+    #[test]
+    fn directory_concurrency_is_limited_by_global_and_at_least_one() {
+        assert_eq!(directory_concurrency(4, 32), 4);
+        assert_eq!(directory_concurrency(64, 32), 32);
+        assert_eq!(directory_concurrency(0, 32), 1);
+        assert_eq!(directory_concurrency(4, 0), 1);
+    }
 }
@@ -1,287 +1,830 @@
+use anyhow::anyhow;
 use axum::http::StatusCode;
-use common_lib::elasticsearch::ELASTICSEARCH_INDEX;
+use common_lib::{
+    elasticsearch::{ContentLanguage, ELASTICSEARCH_INDEX},
+    indexer::IndexSchemaStatus,
+    settings::Settings,
+};
 use elasticsearch::{
-    indices::{IndicesCreateParts, IndicesExistsParts},
-    Elasticsearch,
+    indices::{
+        IndicesCreateParts, IndicesDeleteParts, IndicesExistsParts, IndicesGetAliasParts,
+        IndicesGetMappingParts, IndicesGetParts, IndicesPutMappingParts, IndicesUpdateAliasesParts,
+    },
+    params::Conflicts,
+    Elasticsearch, UpdateByQueryParts,
 };
-use serde_json::json;
+use serde_json::{json, Value};
 
-/// Creates index for storing indexed files, if it doesn't exist
-pub async fn create_index(es_client: &Elasticsearch) -> Result<(), elasticsearch::Error> {
-    // Check if index exists
-    if es_client
-        .indices()
-        .exists(IndicesExistsParts::Index(&[ELASTICSEARCH_INDEX]))
-        .send()
-        .await?
-        .status_code()
-        == StatusCode::OK
-    {
-        return Ok(());
+/// Separator between alias name and generation number in index names
+const GENERATION_SEPARATOR: &str = "-v";
+/// Version of mapping stored in index, increased when mapping changes.
+/// Index without stored version has version 0
+pub const INDEX_SCHEMA_VERSION: u64 = 3;
+
+/// Languages of main analyzer of text fields, their scripts are different
+const MAIN_ANALYZER_LANGUAGES: [ContentLanguage; 2] = [ContentLanguage::En, ContentLanguage::Ru];
+/// Fields that have subfields analyzed with each of additional content languages
+pub const CONTENT_LANGUAGE_FIELDS: [&str; 2] = ["content", "title"];
+
+/// Settings that affect mapping of index
+#[derive(Debug, Clone)]
+pub struct IndexOptions {
+    pub cjk_analysis: bool,
+    /// Languages with subfields of their own
+    pub content_languages: Vec<ContentLanguage>,
+    pub text_embedding_dim: usize,
+    pub image_embedding_dim: usize,
+}
+
+impl IndexOptions {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            cjk_analysis: settings.cjk_analysis,
+            content_languages: settings.content_analyzer_languages(),
+            text_embedding_dim: settings.nn_server.minilm_text.embedding_dim,
+            image_embedding_dim: settings.nn_server.clip_image.embedding_dim,
+        }
+    }
+}
+
+/// Elasticsearch names of language and of its stemmer, `None` for unspaced languages
+/// analyzed with bigrams of CJK characters
+fn language_analysis_names(language: ContentLanguage) -> Option<(&'static str, &'static str)> {
+    match language {
+        ContentLanguage::En => Some(("english", "english")),
+        ContentLanguage::Ru => Some(("russian", "russian")),
+        ContentLanguage::De => Some(("german", "light_german")),
+        ContentLanguage::Fr => Some(("french", "light_french")),
+        ContentLanguage::Es => Some(("spanish", "light_spanish")),
+        ContentLanguage::It => Some(("italian", "light_italian")),
+        ContentLanguage::Pt => Some(("portuguese", "light_portuguese")),
+        ContentLanguage::Nl => Some(("dutch", "dutch")),
+        ContentLanguage::Zh | ContentLanguage::Ja | ContentLanguage::Ko => None,
     }
+}
 
-    // Create index and set mapping
-    es_client
-        .indices()
-        .create(IndicesCreateParts::Index(ELASTICSEARCH_INDEX))
-        .body(json!({
-            "settings": {
-                "index": {
-                    "analysis": {
-                        "char_filter": {
-                            "path_char_filter": {
-                                "type": "mapping",
-                                "mappings": ["_ => -", ". => -"]
-                            },
-                            "path_hierarchy_char_filter": {
-                                "type": "mapping",
-                                "mappings": ["\\\\ => /"]
-                            }
+/// Articles merged with the next word by apostrophe, such as "l'" in French
+fn language_elision_articles(language: ContentLanguage) -> Option<&'static [&'static str]> {
+    match language {
+        ContentLanguage::Fr => Some(&[
+            "l", "m", "t", "qu", "n", "s", "j", "d", "c", "jusqu", "quoiqu", "lorsqu", "puisqu",
+        ]),
+        ContentLanguage::It => Some(&[
+            "c", "l", "all", "dall", "dell", "nell", "sull", "coll", "pell", "gl", "agl", "dagl",
+            "degl", "negl", "sugl", "un", "m", "t", "s", "v", "d",
+        ]),
+        _ => None,
+    }
+}
+
+/// Adds stemmer, stopwords and elision filters of languages of main analyzer and additional
+/// content languages, and analyzers of additional content languages
+fn add_language_analysis(analysis: &mut Value, content_languages: &[ContentLanguage]) {
+    let languages = MAIN_ANALYZER_LANGUAGES
+        .iter()
+        .chain(content_languages)
+        .filter_map(|&x| Some((x, language_analysis_names(x)?)));
+    for (language, (name, stemmer)) in languages {
+        let filter = &mut analysis["filter"];
+        filter[format!("{name}_stemmer")] = json!({
+            "type": "stemmer",
+            "name": stemmer
+        });
+        filter[format!("{name}_stop")] = json!({
+            "type": "stop",
+            "stopwords": format!("_{name}_")
+        });
+        let elision = language_elision_articles(language).map(|articles| {
+            filter[format!("{name}_elision")] = json!({
+                "type": "elision",
+                "articles": articles,
+                "articles_case": true
+            });
+            format!("{name}_elision")
+        });
+
+        if content_languages.contains(&language) {
+            // Elision goes before lowercasing, like in built-in analyzers
+            let filters: Vec<_> = elision
+                .into_iter()
+                .chain([
+                    "lowercase".to_owned(),
+                    format!("{name}_stop"),
+                    format!("{name}_stemmer"),
+                ])
+                .collect();
+            analysis["analyzer"][format!("{language}_analyzer")] = json!({
+                "tokenizer": "standard",
+                "filter": filters
+            });
+        }
+    }
+}
+
+/// Returns subfield of text field analyzed with stemmer and stopwords of language
+fn language_subfield(language: ContentLanguage) -> Value {
+    json!({
+        "type": "text",
+        "analyzer": format!("{language}_analyzer")
+    })
+}
+
+/// Returns name of index for given generation
+fn generation_index_name(generation: u32) -> String {
+    format!("{ELASTICSEARCH_INDEX}{GENERATION_SEPARATOR}{generation}")
+}
+
+/// Returns subfield of text field analyzed with bigrams of CJK characters
+fn cjk_subfield() -> Value {
+    json!({
+        "type": "text",
+        "analyzer": "cjk_analyzer",
+        // Required by fast vector highlighter
+        "term_vector": "with_positions_offsets"
+    })
+}
+
+/// Returns subfield of path lowercased for matching paths on case-insensitive file systems
+fn path_lowercase_subfield() -> Value {
+    json!({
+        "type": "keyword",
+        "normalizer": "lowercase"
+    })
+}
+
+/// Returns settings and mapping of index
+fn index_body(options: &IndexOptions) -> Value {
+    let mut body = json!({
+        "settings": {
+            "index": {
+                "analysis": {
+                    "char_filter": {
+                        "path_char_filter": {
+                            "type": "mapping",
+                            "mappings": ["_ => -", ". => -"]
                         },
-                        "tokenizer": {
-                            "path_hierarchy_tokenizer": {
-                                "type": "path_hierarchy",
-                                "delimiter": "/"
-                            }
+                        "path_hierarchy_char_filter": {
+                            "type": "mapping",
+                            "mappings": ["\\\\ => /"]
+                        }
+                    },
+                    "tokenizer": {
+                        "path_hierarchy_tokenizer": {
+                            "type": "path_hierarchy",
+                            "delimiter": "/"
+                        }
+                    },
+                    // Filters of languages are added from settings
+                    "filter": {
+                        "shingles": {
+                            "type": "shingle",
+                            "min_shingle_size": 2,
+                            "max_shingle_size": 2
+                        }
+                    },
+                    "analyzer": {
+                        "en_ru_analyzer": {
+                            "tokenizer": "standard",
+                            "filter": [
+                                "lowercase",
+                                "english_stemmer",
+                                "russian_stemmer",
+                                "english_stop",
+                                "russian_stop"
+                            ]
                         },
-                        "filter": {
-                            "english_stemmer": {
-                                "type": "stemmer",
-                                "name": "english"
-                            },
-                            "russian_stemmer": {
-                                "type": "stemmer",
-                                "name": "russian"
-                            },
-                            "english_stop": {
-                                "type": "stop",
-                                "stopwords": "_english_"
-                            },
-                            "russian_stop": {
-                                "type": "stop",
-                                "stopwords": "_russian_"
-                            },
-                            "shingles": {
-                                "type": "shingle",
-                                "min_shingle_size": 2,
-                                "max_shingle_size": 2
-                            }
+                        "path_en_ru_analyzer": {
+                            "char_filter": "path_char_filter",
+                            "tokenizer": "standard",
+                            "filter": [
+                                "lowercase",
+                                "english_stemmer",
+                                "russian_stemmer",
+                                "english_stop",
+                                "russian_stop"
+                            ]
+                        },
+                        "path_hierarchy_analyzer": {
+                            "char_filter": "path_hierarchy_char_filter",
+                            "tokenizer": "path_hierarchy_tokenizer"
+                        },
+                        "en_ru_analyzer_shingles": {
+                            "tokenizer": "standard",
+                            "filter": [
+                                "lowercase",
+                                "shingles"
+                            ]
+                        },
+                        "path_en_ru_analyzer_shingles": {
+                            "char_filter": "path_char_filter",
+                            "tokenizer": "standard",
+                            "filter": [
+                                "lowercase",
+                                "shingles"
+                            ]
+                        },
+                        "path_complete_analyzer": {
+                            "char_filter": "path_char_filter",
+                            "tokenizer": "standard",
+                            "filter": [
+                                "lowercase"
+                            ]
+                        },
+                        "cjk_analyzer": {
+                            "tokenizer": "standard",
+                            "filter": [
+                                "cjk_width",
+                                "lowercase",
+                                "cjk_bigram"
+                            ]
                         },
-                        "analyzer": {
-                            "en_ru_analyzer": {
-                                "tokenizer": "standard",
-                                "filter": [
-                                    "lowercase",
-                                    "english_stemmer",
-                                    "russian_stemmer",
-                                    "english_stop",
-                                    "russian_stop"
-                                ]
-                            },
-                            "path_en_ru_analyzer": {
-                                "char_filter": "path_char_filter",
-                                "tokenizer": "standard",
-                                "filter": [
-                                    "lowercase",
-                                    "english_stemmer",
-                                    "russian_stemmer",
-                                    "english_stop",
-                                    "russian_stop"
-                                ]
-                            },
-                            "path_hierarchy_analyzer": {
-                                "char_filter": "path_hierarchy_char_filter",
-                                "tokenizer": "path_hierarchy_tokenizer"
-                            },
-                            "en_ru_analyzer_shingles": {
-                                "tokenizer": "standard",
-                                "filter": [
-                                    "lowercase",
-                                    "shingles"
-                                ]
-                            },
-                            "path_en_ru_analyzer_shingles": {
-                                "char_filter": "path_char_filter",
-                                "tokenizer": "standard",
-                                "filter": [
-                                    "lowercase",
-                                    "shingles"
-                                ]
-                            },
-                        }
                     }
                 }
+            }
+        },
+        "mappings": {
+            "_meta": {
+                "schema_version": INDEX_SCHEMA_VERSION
             },
-            "mappings": {
-                "properties": {
-                    "path": {
-                        "type": "text",
-                        "analyzer": "path_en_ru_analyzer",
-                        "fields": {
-                            "keyword": {
-                                "type": "keyword"
-                            },
-                            "hierarchy": {
-                                "type": "text",
-                                "analyzer": "path_hierarchy_analyzer"
-                            },
-                            "shingles": {
-                                "type": "text",
-                                "analyzer": "path_en_ru_analyzer_shingles"
-                            }
+            "properties": {
+                "path": {
+                    "type": "text",
+                    "analyzer": "path_en_ru_analyzer",
+                    "fields": {
+                        "keyword": {
+                            "type": "keyword"
+                        },
+                        "lowercase": path_lowercase_subfield(),
+                        "hierarchy": {
+                            "type": "text",
+                            "analyzer": "path_hierarchy_analyzer"
+                        },
+                        "shingles": {
+                            "type": "text",
+                            "analyzer": "path_en_ru_analyzer_shingles"
+                        },
+                        // Words of path without stemming, for completion of typed prefix
+                        "complete": {
+                            "type": "search_as_you_type",
+                            "analyzer": "path_complete_analyzer"
                         }
-                    },
-                    "modified": {
-                        "type": "long"
-                    },
-                    "size": {
-                        "type": "long"
-                    },
-                    "hash": {
-                        "type": "keyword"
-                    },
-                    "content_type": {
-                        "type": "keyword"
-                    },
-                    "content_type_mime_type": {
-                        "type": "keyword"
-                    },
-                    "content_type_mime_essence": {
-                        "type": "keyword"
-                    },
-                    "content": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer",
-                        "fields": {
-                            "shingles": {
-                                "type": "text",
-                                "analyzer": "en_ru_analyzer_shingles"
-                            }
+                    }
+                },
+                "modified": {
+                    "type": "long"
+                },
+                "size": {
+                    "type": "long"
+                },
+                "hash": {
+                    "type": "keyword"
+                },
+                "content_type": {
+                    "type": "keyword"
+                },
+                "content_type_mime_type": {
+                    "type": "keyword"
+                },
+                "content_type_mime_essence": {
+                    "type": "keyword"
+                },
+                "content_type_detection": {
+                    "type": "keyword"
+                },
+                "content": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer",
+                    "fields": {
+                        "shingles": {
+                            "type": "text",
+                            "analyzer": "en_ru_analyzer_shingles"
                         }
-                    },
+                    }
+                },
+                "tags": {
+                    "type": "keyword"
+                },
+                "has_thumbnail": {
+                    "type": "boolean"
+                },
+                "provenance": {
+                    "properties": {
+                        "tika_version": {
+                            "type": "keyword"
+                        },
+                        "models": {
+                            "type": "keyword"
+                        },
+                        "parser_version": {
+                            "type": "integer"
+                        }
+                    }
+                },
+                "is_executable": {
+                    "type": "boolean"
+                },
+                "is_readonly": {
+                    "type": "boolean"
+                },
+                "is_hidden": {
+                    "type": "boolean"
+                },
+                "is_online_only": {
+                    "type": "boolean"
+                },
+                "xattrs": {
+                    "type": "keyword"
+                },
+                "is_symlink": {
+                    "type": "boolean"
+                },
+                "in_archive": {
+                    "type": "boolean"
+                },
+                "parse_failed": {
+                    "type": "boolean"
+                },
 
-                    "text_embedding": {
-                        "type": "dense_vector",
-                        "dims": 384,
-                        "index": true,
-                        "similarity": "dot_product"
-                    },
-                    "summary": {
-                        "type": "object",
-                        "enabled": false
-                    },
+                "text_embedding": {
+                    "type": "dense_vector",
+                    "dims": options.text_embedding_dim,
+                    "index": true,
+                    "similarity": "dot_product"
+                },
+                "summary": {
+                    "type": "object",
+                    "enabled": false
+                },
+                "language": {
+                    "type": "keyword"
+                },
 
-                    // Fields for image files
-                    "image_embedding": {
-                        "type": "dense_vector",
-                        "dims": 512,
-                        "index": true,
-                        "similarity": "dot_product"
-                    },
-                    "width": {
-                        "type": "integer"
-                    },
-                    "height": {
-                        "type": "integer"
-                    },
-                    "resolution_unit": {
-                        "type": "keyword"
-                    },
-                    "x_resolution": {
-                        "type": "float"
-                    },
-                    "y_resolution": {
-                        "type": "float"
-                    },
-                    "f_number": {
-                        "type": "float"
-                    },
-                    "focal_length": {
-                        "type": "float"
-                    },
-                    "exposure_time": {
-                        "type": "float"
-                    },
-                    "flash_fired": {
-                        "type": "boolean"
-                    },
-                    "image_make": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "image_model": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "image_software": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
+                // Fields for image files
+                "image_embedding": {
+                    "type": "dense_vector",
+                    "dims": options.image_embedding_dim,
+                    "index": true,
+                    "similarity": "dot_product"
+                },
+                "width": {
+                    "type": "integer"
+                },
+                "height": {
+                    "type": "integer"
+                },
+                "resolution_unit": {
+                    "type": "keyword"
+                },
+                "x_resolution": {
+                    "type": "float"
+                },
+                "y_resolution": {
+                    "type": "float"
+                },
+                "f_number": {
+                    "type": "float"
+                },
+                "focal_length": {
+                    "type": "float"
+                },
+                "exposure_time": {
+                    "type": "float"
+                },
+                "flash_fired": {
+                    "type": "boolean"
+                },
+                "image_make": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "image_model": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "image_software": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
 
-                    // Fields for multimedia files
-                    "artist": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "album": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "genre": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "track_number": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "disc_number": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "release_date": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "duration": {
-                        "type": "float"
-                    },
-                    "audio_sample_rate": {
-                        "type": "integer"
-                    },
-                    "audio_channel_type": {
-                        "type": "keyword"
-                    },
+                // Fields for multimedia files
+                "artist": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "album": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer",
+                    "fields": {
+                        "keyword": {
+                            "type": "keyword",
+                            "ignore_above": 256
+                        }
+                    }
+                },
+                "genre": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "track_number": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "disc_number": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "release_date": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "duration": {
+                    "type": "float"
+                },
+                "audio_sample_rate": {
+                    "type": "integer"
+                },
+                "audio_channel_type": {
+                    "type": "keyword"
+                },
+                "video_width": {
+                    "type": "integer"
+                },
+                "video_height": {
+                    "type": "integer"
+                },
 
-                    // Fields for document files
-                    "title": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "creator": {
-                        "type": "text",
-                        "analyzer": "en_ru_analyzer"
-                    },
-                    "doc_created": {
-                        "type": "long"
-                    },
-                    "doc_modified": {
-                        "type": "long"
-                    },
-                    "num_pages": {
-                        "type": "integer"
-                    },
-                    "num_words": {
-                        "type": "integer"
-                    },
-                    "num_characters": {
-                        "type": "integer"
+                // Fields for document files
+                "title": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "creator": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "doc_language": {
+                    "type": "keyword"
+                },
+                "publisher": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "doc_created": {
+                    "type": "long"
+                },
+                "doc_modified": {
+                    "type": "long"
+                },
+                "num_pages": {
+                    "type": "integer"
+                },
+                "num_words": {
+                    "type": "integer"
+                },
+                "num_characters": {
+                    "type": "integer"
+                },
+
+                // Fields for email files
+                "sender": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer",
+                    "fields": {
+                        "keyword": {
+                            "type": "keyword",
+                            "ignore_above": 256
+                        }
+                    }
+                },
+                "recipients": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer",
+                    "fields": {
+                        "keyword": {
+                            "type": "keyword",
+                            "ignore_above": 256
+                        }
                     }
+                },
+                "subject": {
+                    "type": "text",
+                    "analyzer": "en_ru_analyzer"
+                },
+                "email_sent": {
+                    "type": "long"
+                },
+                "has_attachments": {
+                    "type": "boolean"
                 }
             }
-        }))
+        }
+    });
+
+    add_language_analysis(
+        &mut body["settings"]["index"]["analysis"],
+        &options.content_languages,
+    );
+    let properties = &mut body["mappings"]["properties"];
+    for field in CONTENT_LANGUAGE_FIELDS {
+        for &language in &options.content_languages {
+            properties[field]["fields"][language.to_string()] = language_subfield(language);
+        }
+    }
+    if options.cjk_analysis {
+        properties["content"]["fields"]["cjk"] = cjk_subfield();
+        properties["title"]["fields"]["cjk"] = cjk_subfield();
+    }
+    body
+}
+
+/// Creates index with given name, optionally pointing alias to it
+async fn create_generation_index(
+    es_client: &Elasticsearch,
+    index: &str,
+    add_alias: bool,
+    options: &IndexOptions,
+) -> Result<(), elasticsearch::Error> {
+    let mut body = index_body(options);
+    if add_alias {
+        body["aliases"] = json!({ ELASTICSEARCH_INDEX: {} });
+    }
+
+    es_client
+        .indices()
+        .create(IndicesCreateParts::Index(index))
+        .body(body)
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(())
+}
+
+/// Returns why dimensions of embeddings in mapping of index don't match settings of models
+fn embedding_dims_mismatch(properties: &Value, options: &IndexOptions) -> Option<String> {
+    [
+        ("text_embedding", options.text_embedding_dim),
+        ("image_embedding", options.image_embedding_dim),
+    ]
+    .into_iter()
+    .find_map(|(field, dim)| {
+        let index_dim = properties[field]["dims"].as_u64()?;
+        (index_dim != dim as u64).then(|| {
+            format!(
+                "Dimension of {field} in index ({index_dim}) doesn't match embedding_dim \
+                 setting of model ({dim}). Restore previous model settings or clear index"
+            )
+        })
+    })
+}
+
+/// Checks that mapping of existing index matches current schema version and settings of
+/// models. New fields are added to index of older version if existing fields didn't change,
+/// documents are updated in place in background to fill them
+async fn check_index_schema(
+    es_client: &Elasticsearch,
+    options: &IndexOptions,
+) -> anyhow::Result<IndexSchemaStatus> {
+    let mappings: Value = es_client
+        .indices()
+        .get_mapping(IndicesGetMappingParts::Index(&[ELASTICSEARCH_INDEX]))
+        .send()
+        .await?
+        .error_for_status_code()?
+        .json()
+        .await?;
+    let current_mapping = index_body(options)["mappings"].clone();
+    // Alias can point to several indices
+    let mut is_updated = false;
+    for (index, mapping) in mappings.as_object().into_iter().flatten() {
+        let mapping = &mapping["mappings"];
+        let index_version = mapping["_meta"]["schema_version"].as_u64().unwrap_or(0);
+        let outdated = |reason| IndexSchemaStatus::Outdated {
+            index_version,
+            current_version: INDEX_SCHEMA_VERSION,
+            reason,
+        };
+
+        if let Some(reason) = embedding_dims_mismatch(&mapping["properties"], options) {
+            return Ok(outdated(reason));
+        }
+        if index_version == INDEX_SCHEMA_VERSION {
+            continue;
+        }
+        if index_version > INDEX_SCHEMA_VERSION {
+            return Ok(outdated(
+                "Index was created by newer version of application".to_owned(),
+            ));
+        }
+
+        let response = es_client
+            .indices()
+            .put_mapping(IndicesPutMappingParts::Index(&[index.as_str()]))
+            .body(&current_mapping)
+            .send()
+            .await?;
+        if !response.status_code().is_success() {
+            let body: Value = response.json().await?;
+            let reason = body["error"]["reason"]
+                .as_str()
+                .unwrap_or("Mapping of index can't be updated")
+                .to_owned();
+            return Ok(outdated(reason));
+        }
+        tracing::info!(
+            "Updated mapping of index {} from schema version {} to {}",
+            index,
+            index_version,
+            INDEX_SCHEMA_VERSION
+        );
+        is_updated = true;
+    }
+    if !is_updated {
+        return Ok(IndexSchemaStatus::Compatible);
+    }
+
+    tracing::info!("Documents are updated in background to fill new fields");
+    es_client
+        .update_by_query(UpdateByQueryParts::Index(&[ELASTICSEARCH_INDEX]))
+        .conflicts(Conflicts::Proceed)
+        .wait_for_completion(false)
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(IndexSchemaStatus::Compatible)
+}
+
+/// Creates index for storing indexed files and alias to it, if it doesn't exist.
+/// Existing index is checked to match schema version and settings of models
+pub async fn create_index(
+    es_client: &Elasticsearch,
+    options: &IndexOptions,
+) -> anyhow::Result<IndexSchemaStatus> {
+    // Check if index or alias exists
+    let status_code = es_client
+        .indices()
+        .exists(IndicesExistsParts::Index(&[ELASTICSEARCH_INDEX]))
+        .send()
+        .await?
+        .status_code();
+    match status_code {
+        StatusCode::OK => return check_index_schema(es_client, options).await,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            return Err(anyhow!(
+                "Elasticsearch denied access ({}), check elasticsearch_user \
+                 and elasticsearch_password settings",
+                status_code
+            ))
+        }
+        _ => {}
+    }
+
+    create_generation_index(es_client, &generation_index_name(1), true, options).await?;
+    Ok(IndexSchemaStatus::Compatible)
+}
+
+/// Returns names of indices the alias currently points to
+async fn get_alias_indices(es_client: &Elasticsearch) -> Result<Vec<String>, elasticsearch::Error> {
+    let response = es_client
+        .indices()
+        .get_alias(IndicesGetAliasParts::Name(&[ELASTICSEARCH_INDEX]))
+        .send()
+        .await?;
+    if response.status_code() == StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+
+    let body: Value = response.error_for_status_code()?.json().await?;
+    Ok(body
+        .as_object()
+        .map(|x| x.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Creates index of next generation without pointing alias to it, returns its name
+pub async fn create_next_generation_index(
+    es_client: &Elasticsearch,
+    options: &IndexOptions,
+) -> Result<String, elasticsearch::Error> {
+    let generation_prefix = format!("{ELASTICSEARCH_INDEX}{GENERATION_SEPARATOR}");
+    let indices: Value = es_client
+        .indices()
+        .get(IndicesGetParts::Index(&[&format!("{generation_prefix}*")]))
+        .send()
+        .await?
+        .error_for_status_code()?
+        .json()
+        .await?;
+    let last_generation = indices
+        .as_object()
+        .into_iter()
+        .flat_map(|x| x.keys())
+        .filter_map(|x| x.strip_prefix(&generation_prefix)?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+
+    let index = generation_index_name(last_generation + 1);
+    create_generation_index(es_client, &index, false, options).await?;
+    Ok(index)
+}
+
+/// Atomically points alias to given index, returns indices it pointed to before
+pub async fn switch_alias(
+    es_client: &Elasticsearch,
+    index: &str,
+) -> Result<Vec<String>, elasticsearch::Error> {
+    let old_indices = get_alias_indices(es_client).await?;
+
+    let mut actions: Vec<_> = old_indices
+        .iter()
+        .map(|old_index| json!({ "remove": { "index": old_index, "alias": ELASTICSEARCH_INDEX } }))
+        .collect();
+    // Index created before aliases were used has the same name as alias
+    // and has to be removed in the same request
+    if old_indices.is_empty()
+        && es_client
+            .indices()
+            .exists(IndicesExistsParts::Index(&[ELASTICSEARCH_INDEX]))
+            .send()
+            .await?
+            .status_code()
+            == StatusCode::OK
+    {
+        actions.push(json!({ "remove_index": { "index": ELASTICSEARCH_INDEX } }));
+    }
+    actions.push(json!({ "add": { "index": index, "alias": ELASTICSEARCH_INDEX } }));
+
+    es_client
+        .indices()
+        .update_aliases(IndicesUpdateAliasesParts::None)
+        .body(json!({ "actions": actions }))
+        .send()
+        .await?
+        .error_for_status_code()?;
+    Ok(old_indices)
+}
+
+/// Deletes indices alias pointed to before switching.
+/// Alias already points to new index, so failure only leaves unused indices behind
+pub async fn delete_old_indices(es_client: &Elasticsearch, old_indices: &[String]) {
+    if old_indices.is_empty() {
+        return;
+    }
+    let indices: Vec<_> = old_indices.iter().map(String::as_str).collect();
+    if let Err(e) = delete_generation_index(es_client, &indices).await {
+        tracing::error!("Error deleting old indices {:?}: {}", old_indices, e);
+    }
+}
+
+/// Deletes indices with given names
+pub async fn delete_generation_index(
+    es_client: &Elasticsearch,
+    indices: &[&str],
+) -> Result<(), elasticsearch::Error> {
+    es_client
+        .indices()
+        .delete(IndicesDeleteParts::Index(indices))
         .send()
         .await?
         .error_for_status_code()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(cjk_analysis: bool) -> IndexOptions {
+        IndexOptions {
+            cjk_analysis,
+            content_languages: Vec::new(),
+            text_embedding_dim: 384,
+            image_embedding_dim: 512,
+        }
+    }
+
+    #[test]
+    fn text_fields_have_cjk_subfields_only_with_cjk_analysis() {
+        let body = index_body(&options(true));
+        let cjk_subfield = json!({
+            "type": "text",
+            "analyzer": "cjk_analyzer",
+            "term_vector": "with_positions_offsets"
+        });
+        assert_eq!(
+            body["settings"]["index"]["analysis"]["analyzer"]["cjk_analyzer"],
+            json!({
+                "tokenizer": "standard",
+                "filter": ["cjk_width", "lowercase", "cjk_bigram"]
+            })
+        );
+        let properties = &body["mappings"]["properties"];
+        assert_eq!(properties["content"]["fields"]["cjk"], cjk_subfield);
+        assert_eq!(properties["title"]["fields"]["cjk"], cjk_subfield);
+        assert_eq!(properties["language"], json!({ "type": "keyword" }));
+
+        let body = index_body(&options(false));
+        let properties = &body["mappings"]["properties"];
+        assert!(properties["content"]["fields"].get("cjk").is_none());
+        assert!(properties["title"]["fields"].get("cjk").is_none());
+    }
+}
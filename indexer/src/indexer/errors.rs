@@ -0,0 +1,170 @@
+use std::{collections::BTreeSet, fmt, io::ErrorKind, path::PathBuf, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use common_lib::{
+    elasticsearch::enclosing_archive,
+    indexer::{
+        FileError, FileErrorStage, FileErrorsPage, IndexingEvent, FILE_ERRORS_MAX_PAGE_SIZE,
+        FILE_ERRORS_PAGE_SIZE,
+    },
+};
+use serde::Deserialize;
+use tracing_unwrap::ResultExt;
+
+use crate::ServerState;
+
+use super::{indexing_process, on_event, IndexingTarget};
+
+const FILE_ERRORS_FILE_PATH: &str = "indexing_errors.json";
+/// Only errors of the latest indexing are kept
+const LATEST_RUN: &str = "latest";
+
+/// Context of error of processing file, it's recorded in report of errors with path and stage
+#[derive(Debug)]
+pub struct FileErrorContext {
+    pub path: PathBuf,
+    pub stage: FileErrorStage,
+}
+
+impl fmt::Display for FileErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.stage {
+            FileErrorStage::Hash => "reading",
+            FileErrorStage::Parse | FileErrorStage::Embedding => "parsing",
+            FileErrorStage::Bulk => "indexing",
+        };
+        write!(f, "Error {} file: {}", action, self.path.display())
+    }
+}
+
+/// Errors of files of the latest indexing from file, empty if it doesn't exist
+pub async fn read_file_errors_file() -> Vec<FileError> {
+    match tokio::fs::read_to_string(FILE_ERRORS_FILE_PATH).await {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+            tracing::warn!(
+                "Error reading errors of files of the latest indexing: {}",
+                e
+            );
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Clear errors of files when indexing starts, including the ones in file
+pub async fn clear_file_errors(state: &ServerState) {
+    state.file_errors.write().await.clear();
+    if let Err(e) = tokio::fs::remove_file(FILE_ERRORS_FILE_PATH).await {
+        if e.kind() != ErrorKind::NotFound {
+            tracing::error!("Error removing errors of files of previous indexing: {}", e);
+        }
+    }
+}
+
+/// Write errors of files to file when indexing finishes
+pub async fn save_file_errors(state: &ServerState) {
+    let s = serde_json::to_string(&*state.file_errors.read().await).unwrap_or_log();
+    if let Err(e) = tokio::fs::write(FILE_ERRORS_FILE_PATH, s).await {
+        tracing::error!("Error writing errors of files of indexing: {}", e);
+    }
+}
+
+/// Add error of file to report of current indexing
+pub async fn record_file_error(state: &ServerState, error: FileError) {
+    state.file_errors.write().await.push(error);
+}
+
+/// Add error of file to report of current indexing and send event with given message
+pub async fn on_file_error(state: Arc<ServerState>, error: FileError, message: String) {
+    record_file_error(&state, error).await;
+    on_event(state, IndexingEvent::Error(message)).await;
+}
+
+/// Send event with error, recording it in report if it has context of file
+pub async fn on_error(state: Arc<ServerState>, e: anyhow::Error) {
+    let message = format!("{e:?}");
+    match e.downcast_ref::<FileErrorContext>() {
+        Some(context) => {
+            // Path and stage are stored separately from causes
+            let causes: Vec<_> = e.chain().skip(1).map(ToString::to_string).collect();
+            let error = FileError {
+                path: context.path.clone(),
+                stage: context.stage,
+                error: causes.join(": "),
+            };
+            on_file_error(state, error, message).await;
+        }
+        None => on_event(state, IndexingEvent::Error(message)).await,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileErrorsQuery {
+    /// Indexing run, only `latest` is supported
+    pub run: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Get page of errors of files in the latest indexing
+pub async fn get_file_errors(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<FileErrorsQuery>,
+) -> Result<Json<FileErrorsPage>, (StatusCode, String)> {
+    if query.run.as_deref().is_some_and(|x| x != LATEST_RUN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Only errors of the latest indexing are kept".to_owned(),
+        ));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(FILE_ERRORS_PAGE_SIZE)
+        .min(FILE_ERRORS_MAX_PAGE_SIZE);
+    let errors = state.file_errors.read().await;
+    Ok(Json(FileErrorsPage {
+        total: errors.len(),
+        errors: errors
+            .iter()
+            .skip(query.offset)
+            .take(limit)
+            .cloned()
+            .collect(),
+    }))
+}
+
+/// Start indexing only files that failed in the latest indexing.
+/// Entries of archives are indexed again with their archives
+pub async fn retry_failed_files(
+    State(state): State<Arc<ServerState>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !state.indexing_status.read().await.can_start() {
+        return Err((StatusCode::CONFLICT, "Already indexing".to_owned()));
+    }
+
+    let paths: BTreeSet<_> = state
+        .file_errors
+        .read()
+        .await
+        .iter()
+        .map(|x| enclosing_archive(&x.path).unwrap_or_else(|| x.path.clone()))
+        .collect();
+    if paths.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No failed files".to_owned()));
+    }
+    tokio::spawn(async move {
+        indexing_process(
+            state,
+            Some(paths.into_iter().collect()),
+            IndexingTarget::Current,
+            None,
+        )
+        .await
+    });
+    Ok(StatusCode::ACCEPTED)
+}
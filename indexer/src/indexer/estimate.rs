@@ -0,0 +1,231 @@
+use std::{
+    cmp::min,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, http::StatusCode, Json};
+use common_lib::indexer::{IndexingEstimate, IndexingEstimateRequest, ESTIMATE_MAX_SAMPLE_CNT};
+use rand::seq::SliceRandom;
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing_unwrap::ResultExt;
+
+use crate::{
+    parser::parse_file,
+    scanner::{get_file_system_files_list, FileInfo},
+    ServerState,
+};
+
+/// Durations of processing stages and size of document for one file
+struct SampleResult {
+    hash_duration: Duration,
+    metadata_duration: Duration,
+    processing_duration: Duration,
+    doc_size: u64,
+}
+
+/// Process file as in indexing, without sending it to Elasticsearch
+async fn process_sample_file(
+    state: Arc<ServerState>,
+    file: FileInfo,
+) -> anyhow::Result<SampleResult> {
+    let start_time = Instant::now();
+    let (hash_algorithm, hash_chunk_size) = {
+        let settings = state.settings.read().await;
+        (settings.hash_algorithm, settings.hash_chunk_size)
+    };
+    let mut file_es = file.into_file_es(hash_algorithm, hash_chunk_size).await?;
+    let hash_duration = start_time.elapsed();
+
+    let parse_durations = parse_file(state, &mut file_es)
+        .await
+        .map_err(|e| e.context(format!("Error parsing file: {}", file_es.path.display())))?;
+    let doc_size = serde_json::to_vec(&file_es)?.len() as u64;
+
+    Ok(SampleResult {
+        hash_duration,
+        metadata_duration: parse_durations.metadata,
+        processing_duration: parse_durations.processing,
+        doc_size,
+    })
+}
+
+/// Process random sample of indexable files and extrapolate results to all files
+async fn estimation_process(
+    state: Arc<ServerState>,
+    sample_rate: f64,
+) -> anyhow::Result<IndexingEstimate> {
+    let tmp = Arc::clone(&state);
+    let files: Vec<_> = tokio::task::spawn_blocking(move || {
+        get_file_system_files_list(&tmp.settings.blocking_read())
+    })
+    .await??
+    .into_iter()
+    .filter(|x| x.process_contents)
+    .collect();
+    let files_cnt = files.len();
+    let files_size = files.iter().map(|x| x.size).sum();
+
+    let sample_cnt = ((files_cnt as f64 * sample_rate).ceil() as usize)
+        .clamp(min(1, files_cnt), ESTIMATE_MAX_SAMPLE_CNT);
+    let sample: Vec<_> = files
+        .choose_multiple(&mut rand::thread_rng(), sample_cnt)
+        .cloned()
+        .collect();
+    let sample_size: u64 = sample.iter().map(|x| x.size).sum();
+    tracing::info!(
+        "Estimating indexing on {} of {} files",
+        sample_cnt,
+        files_cnt
+    );
+
+    // Tasks are aborted if estimation is cancelled and the set is dropped
+    let max_concurrent_files = state.settings.read().await.max_concurrent_files;
+    let semaphore = Arc::new(Semaphore::new(max_concurrent_files));
+    let mut tasks = JoinSet::new();
+    let start_time = Instant::now();
+    for file in sample {
+        let permit = Arc::clone(&semaphore).acquire_owned().await.unwrap_or_log();
+        let state = Arc::clone(&state);
+        tasks.spawn(async move {
+            let res = process_sample_file(state, file).await;
+            drop(permit);
+            res
+        });
+    }
+
+    let mut estimate = IndexingEstimate {
+        files_cnt,
+        files_size,
+        sample_cnt,
+        sample_size,
+        ..Default::default()
+    };
+    let mut docs_size = 0;
+    let mut processed_cnt = 0;
+    while let Some(res) = tasks.join_next().await {
+        match res.unwrap_or_log() {
+            Ok(x) => {
+                estimate.hash_duration += x.hash_duration;
+                estimate.metadata_duration += x.metadata_duration;
+                estimate.processing_duration += x.processing_duration;
+                docs_size += x.doc_size;
+                processed_cnt += 1;
+            }
+            Err(e) => {
+                tracing::warn!("Error while estimating: {:?}", e);
+                estimate.errors_cnt += 1;
+            }
+        }
+    }
+    let sample_duration = start_time.elapsed();
+
+    // Time scales with total size of files, stages are processed in parallel
+    let scale = if sample_size != 0 {
+        files_size as f64 / sample_size as f64
+    } else if sample_cnt != 0 {
+        files_cnt as f64 / sample_cnt as f64
+    } else {
+        0.0
+    };
+    let stage_scale = scale / max_concurrent_files as f64;
+    estimate.hash_duration = estimate.hash_duration.mul_f64(stage_scale);
+    estimate.metadata_duration = estimate.metadata_duration.mul_f64(stage_scale);
+    estimate.processing_duration = estimate.processing_duration.mul_f64(stage_scale);
+    estimate.total_duration = sample_duration.mul_f64(scale);
+    // Size of document doesn't depend on file size much because of embeddings
+    if let Some(x) = (docs_size * files_cnt as u64).checked_div(processed_cnt) {
+        estimate.index_size = x;
+    }
+    Ok(estimate)
+}
+
+/// Estimate time and index size of full indexing by processing sample of files,
+/// `None` if estimation was cancelled
+pub async fn estimate(
+    State(state): State<Arc<ServerState>>,
+    Json(estimate_request): Json<IndexingEstimateRequest>,
+) -> Result<Json<Option<IndexingEstimate>>, (StatusCode, String)> {
+    if !(estimate_request.sample_rate > 0.0 && estimate_request.sample_rate <= 1.0) {
+        return Err((StatusCode::BAD_REQUEST, "Incorrect sample rate".to_owned()));
+    }
+    // Estimate would be distorted by load of indexing
+    if !state.indexing_status.read().await.can_start() {
+        return Err((StatusCode::CONFLICT, "Already indexing".to_owned()));
+    }
+    {
+        let mut estimation_running = state.estimation_running.write().await;
+        if *estimation_running {
+            return Err((StatusCode::CONFLICT, "Already estimating".to_owned()));
+        }
+        *estimation_running = true;
+    }
+
+    let tmp = Arc::clone(&state);
+    let sample_rate = estimate_request.sample_rate;
+    let res = tokio::spawn(async move {
+        let res = tokio::select! {
+            res = estimation_process(Arc::clone(&tmp), sample_rate) => res.map(Some),
+            _ = tmp.estimation_cancel.notified() => Ok(None),
+        };
+        *tmp.estimation_running.write().await = false;
+        res
+    })
+    .await
+    .unwrap_or_log();
+
+    res.map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}")))
+}
+
+/// Cancel running estimation
+pub async fn cancel_estimate(State(state): State<Arc<ServerState>>) {
+    if *state.estimation_running.read().await {
+        state.estimation_cancel.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_lib::indexer::IndexingStatus;
+
+    use crate::test_util::{test_settings, test_state};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn estimate_is_rejected_while_indexing() {
+        let state = test_state(test_settings()).await;
+        *state.indexing_status.write().await = IndexingStatus::Indexing(Default::default());
+
+        let res = estimate(
+            State(Arc::clone(&state)),
+            Json(IndexingEstimateRequest { sample_rate: 0.5 }),
+        )
+        .await;
+
+        assert_eq!(
+            res.err().map(|(status, _)| status),
+            Some(StatusCode::CONFLICT)
+        );
+        assert!(!*state.estimation_running.read().await);
+    }
+
+    #[tokio::test]
+    async fn second_estimation_is_rejected() {
+        let state = test_state(test_settings()).await;
+        *state.estimation_running.write().await = true;
+
+        let res = estimate(
+            State(Arc::clone(&state)),
+            Json(IndexingEstimateRequest { sample_rate: 0.5 }),
+        )
+        .await;
+
+        assert_eq!(
+            res.err().map(|(status, _)| status),
+            Some(StatusCode::CONFLICT)
+        );
+        assert!(*state.estimation_running.read().await);
+    }
+}
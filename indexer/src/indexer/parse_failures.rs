@@ -0,0 +1,248 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use common_lib::{elasticsearch::FileES, indexer::RetryParsesRequest};
+use serde::{Deserialize, Serialize};
+use tracing_unwrap::ResultExt;
+
+use crate::{scanner::FileInfo, ServerState};
+
+use super::{indexing_process, IndexingTarget};
+
+const PARSE_FAILURES_FILE_PATH: &str = "parse_failures.json";
+
+/// Failures of parsing of file, counted while its contents don't change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseFailure {
+    /// Hash of contents when parsing failed
+    pub hash: Option<String>,
+    /// Last modification time when parsing failed
+    #[serde(with = "ts_seconds")]
+    pub modified: DateTime<Utc>,
+    /// Number of failed attempts
+    pub count: u32,
+    pub last_error: String,
+}
+
+impl ParseFailure {
+    /// Checks if failure was recorded for the same contents of file
+    fn is_same_file(&self, hash: &Option<String>, modified: DateTime<Utc>) -> bool {
+        self.hash == *hash && self.modified.timestamp() == modified.timestamp()
+    }
+}
+
+/// Failures of parsing of files from file, empty if it doesn't exist
+pub async fn read_parse_failures_file() -> HashMap<PathBuf, ParseFailure> {
+    match tokio::fs::read_to_string(PARSE_FAILURES_FILE_PATH).await {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+            tracing::warn!("Error reading failures of parsing of files: {}", e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Write failures of parsing of files to file when indexing finishes
+pub async fn save_parse_failures(state: &ServerState) {
+    let s = serde_json::to_string(&*state.parse_failures.read().await).unwrap_or_log();
+    if let Err(e) = tokio::fs::write(PARSE_FAILURES_FILE_PATH, s).await {
+        tracing::error!("Error writing failures of parsing of files: {}", e);
+    }
+}
+
+/// Checks if parsing of file with given contents failed too many times, 0 disables skipping
+pub fn is_skipped(
+    failures: &HashMap<PathBuf, ParseFailure>,
+    max_failures: u32,
+    path: &Path,
+    hash: &Option<String>,
+    modified: DateTime<Utc>,
+) -> bool {
+    max_failures > 0
+        && failures
+            .get(path)
+            .is_some_and(|x| x.count >= max_failures && x.is_same_file(hash, modified))
+}
+
+/// Checks if file should be indexed without parsing its contents
+pub async fn is_skipped_file(state: &ServerState, file: &FileES) -> bool {
+    let max_failures = state.settings.read().await.parse_failures_max;
+    is_skipped(
+        &*state.parse_failures.read().await,
+        max_failures,
+        &file.path,
+        &file.hash,
+        file.modified,
+    )
+}
+
+/// Count failure of parsing of file, counting starts again if file changed
+pub async fn record_parse_failure(state: &ServerState, file: &FileES, error: &anyhow::Error) {
+    let mut failures = state.parse_failures.write().await;
+    let count = match failures.get(&file.path) {
+        Some(x) if x.is_same_file(&file.hash, file.modified) => x.count + 1,
+        _ => 1,
+    };
+    failures.insert(
+        file.path.clone(),
+        ParseFailure {
+            hash: file.hash.clone(),
+            modified: file.modified,
+            count,
+            last_error: format!("{error:#}"),
+        },
+    );
+}
+
+/// Forget failures of parsing of file, after it was parsed or removed
+pub async fn remove_parse_failure(state: &ServerState, path: &Path) {
+    state.parse_failures.write().await.remove(path);
+}
+
+/// Forget failures of files that don't exist anymore.
+/// For partial indexing only files inside of given paths are checked
+pub async fn prune_parse_failures(
+    state: &ServerState,
+    file_system_files: &[FileInfo],
+    paths: Option<&[PathBuf]>,
+) {
+    let existing: HashSet<_> = file_system_files.iter().map(|x| x.path.as_path()).collect();
+    state.parse_failures.write().await.retain(|path, _| {
+        existing.contains(path.as_path())
+            || paths.is_some_and(|paths| !paths.iter().any(|x| path.starts_with(x)))
+    });
+}
+
+/// Forget failures of given files and start indexing them, so their contents are parsed again
+pub async fn retry_parses(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<RetryParsesRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !state.indexing_status.read().await.can_start() {
+        return Err((StatusCode::CONFLICT, "Already indexing".to_owned()));
+    }
+    if request.paths.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No files to retry".to_owned()));
+    }
+
+    {
+        let mut failures = state.parse_failures.write().await;
+        for path in &request.paths {
+            failures.remove(path);
+        }
+    }
+    tokio::spawn(async move {
+        indexing_process(state, Some(request.paths), IndexingTarget::Current, None).await
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use common_lib::indexer::IndexingStatus;
+    use serde_json::json;
+
+    use crate::test_util::{test_settings, test_state};
+
+    use super::*;
+
+    /// Document of file with given hash of contents and modification time
+    fn document(path: &str, hash: &str, modified: i64) -> FileES {
+        serde_json::from_value(json!({
+            "path": path,
+            "modified": modified,
+            "size": 1,
+            "hash": hash,
+            "content_type": "application/pdf",
+            "content_type_mime_type": "application",
+            "content_type_mime_essence": "application/pdf"
+        }))
+        .unwrap()
+    }
+
+    async fn state_with_max_failures(parse_failures_max: u32) -> Arc<ServerState> {
+        let mut settings = test_settings();
+        settings.parse_failures_max = parse_failures_max;
+        test_state(settings).await
+    }
+
+    async fn failed_paths(state: &ServerState) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = state.parse_failures.read().await.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn file_is_skipped_after_max_failures_of_same_contents() {
+        let state = state_with_max_failures(2).await;
+        let error = anyhow::anyhow!("broken");
+        let file = document("/docs/a.pdf", "old", 1000);
+
+        record_parse_failure(&state, &file, &error).await;
+        assert!(!is_skipped_file(&state, &file).await);
+        record_parse_failure(&state, &file, &error).await;
+        assert!(is_skipped_file(&state, &file).await);
+
+        // Changed file is parsed again and its failures are counted from the start
+        let changed = document("/docs/a.pdf", "new", 2000);
+        assert!(!is_skipped_file(&state, &changed).await);
+        record_parse_failure(&state, &changed, &error).await;
+        let failures = state.parse_failures.read().await;
+        let failure = &failures[Path::new("/docs/a.pdf")];
+        assert_eq!(failure.count, 1);
+        assert_eq!(failure.hash.as_deref(), Some("new"));
+        assert_eq!(failure.last_error, "broken");
+    }
+
+    #[tokio::test]
+    async fn files_are_not_skipped_with_zero_max_failures() {
+        let state = state_with_max_failures(0).await;
+        let file = document("/docs/a.pdf", "old", 1000);
+        for _ in 0..5 {
+            record_parse_failure(&state, &file, &anyhow::anyhow!("broken")).await;
+        }
+        assert!(!is_skipped_file(&state, &file).await);
+    }
+
+    #[tokio::test]
+    async fn failures_of_parsed_and_removed_files_are_forgotten() {
+        let state = state_with_max_failures(1).await;
+        for path in ["/docs/a.pdf", "/docs/b.pdf", "/other/c.pdf"] {
+            let file = document(path, "hash", 1000);
+            record_parse_failure(&state, &file, &anyhow::anyhow!("broken")).await;
+        }
+        remove_parse_failure(&state, Path::new("/docs/a.pdf")).await;
+        assert_eq!(
+            failed_paths(&state).await,
+            [PathBuf::from("/docs/b.pdf"), PathBuf::from("/other/c.pdf")]
+        );
+
+        // Partial indexing checks only files inside of its paths
+        prune_parse_failures(&state, &[], Some(&[PathBuf::from("/docs")])).await;
+        assert_eq!(failed_paths(&state).await, [PathBuf::from("/other/c.pdf")]);
+
+        prune_parse_failures(&state, &[], None).await;
+        assert!(failed_paths(&state).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn retry_is_rejected_while_indexing() {
+        let state = state_with_max_failures(1).await;
+        *state.indexing_status.write().await = IndexingStatus::Indexing(Default::default());
+
+        let res = retry_parses(
+            State(state),
+            Json(RetryParsesRequest {
+                paths: vec![PathBuf::from("/docs/a.pdf")],
+            }),
+        )
+        .await;
+
+        assert_eq!(res.err().map(|x| x.0), Some(StatusCode::CONFLICT));
+    }
+}
@@ -0,0 +1,53 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::ServerState;
+
+use super::{indexing_process, IndexingTarget};
+
+/// Start timer of periodic indexing with interval from settings, stopping previous one.
+/// Timer isn't restarted if interval didn't change
+pub async fn start_periodic_indexing(state: Arc<ServerState>) {
+    let interval_hours = {
+        let settings = state.settings.read().await;
+        settings
+            .periodic_indexing_enabled
+            .then_some(settings.periodic_indexing_interval_hours.max(1))
+    };
+
+    let mut periodic_indexing = state.periodic_indexing.write().await;
+    if periodic_indexing.as_ref().map(|(x, _)| *x) == interval_hours {
+        return;
+    }
+    if let Some((_, task)) = periodic_indexing.take() {
+        tracing::info!("Stopping periodic indexing");
+        task.abort();
+    }
+    let Some(interval_hours) = interval_hours else {
+        return;
+    };
+    tracing::info!("Starting periodic indexing every {} h", interval_hours);
+
+    let period = Duration::from_secs(u64::from(interval_hours) * 60 * 60);
+    let tmp = Arc::clone(&state);
+    let task = tokio::spawn(async move {
+        // First indexing is after full interval, not on start
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if !tmp.indexing_status.read().await.can_start() {
+                tracing::info!("Skipping scheduled indexing, indexing is already running");
+                continue;
+            }
+            tracing::info!("Starting scheduled indexing");
+            // Indexing is separate from timer, so it isn't aborted when timer is stopped
+            let state = Arc::clone(&tmp);
+            tokio::spawn(async move {
+                indexing_process(state, None, IndexingTarget::Current, None).await
+            });
+        }
+    });
+    *periodic_indexing = Some((interval_hours, task));
+}
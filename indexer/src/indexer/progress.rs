@@ -0,0 +1,54 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common_lib::indexer::{IndexingEvent, IndexingStatus};
+
+use crate::ServerState;
+
+use super::on_event;
+
+/// Interval between progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// Window of processing rate calculation
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+/// Time after start of processing when rate is too noisy for estimating time remaining
+const ETA_MIN_ELAPSED: Duration = Duration::from_secs(5);
+
+/// Periodically send processing rate and estimated time remaining while files are processed
+pub async fn report_progress(state: Arc<ServerState>) {
+    let start_time = Instant::now();
+    // Number of processed files at each tick within rate window
+    let mut samples = VecDeque::new();
+    let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+    loop {
+        interval.tick().await;
+        let (processed, remaining) = match &*state.indexing_status.read().await {
+            IndexingStatus::Indexing(data) => {
+                (data.processed, data.total().saturating_sub(data.processed))
+            }
+            _ => return,
+        };
+
+        let now = Instant::now();
+        samples.push_back((now, processed));
+        while samples
+            .front()
+            .is_some_and(|(time, _)| now - *time > RATE_WINDOW)
+        {
+            samples.pop_front();
+        }
+        let (first_time, first_processed) = samples[0];
+        let elapsed = (now - first_time).as_secs_f64();
+        if elapsed == 0.0 {
+            continue;
+        }
+
+        let rate = (processed - first_processed) as f64 / elapsed;
+        let eta = (now - start_time >= ETA_MIN_ELAPSED && rate > 0.0)
+            .then(|| Duration::from_secs_f64(remaining as f64 / rate));
+        on_event(Arc::clone(&state), IndexingEvent::Progress { rate, eta }).await;
+    }
+}
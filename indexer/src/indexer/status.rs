@@ -5,15 +5,17 @@ use axum::{
         ws::{self, WebSocket},
         State, WebSocketUpgrade,
     },
+    http::StatusCode,
     response::Response,
+    Json,
 };
 use common_lib::{
     elasticsearch::ELASTICSEARCH_INDEX,
-    indexer::{IndexStats, IndexingEvent, IndexingWSMessage},
+    indexer::{DirectoryStats, IndexStats, IndexingEvent, IndexingWSConfig, IndexingWSMessage},
 };
-use elasticsearch::{indices::IndicesStatsParts, Elasticsearch};
+use elasticsearch::{indices::IndicesStatsParts, Elasticsearch, SearchParts};
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{json, Map, Value};
 use tokio::sync::broadcast;
 use tracing_unwrap::{OptionExt, ResultExt};
 
@@ -22,15 +24,18 @@ use crate::ServerState;
 async fn get_es_response(es_client: &Elasticsearch) -> Result<Value, elasticsearch::Error> {
     es_client
         .indices()
-        .stats(IndicesStatsParts::Metric(&["docs", "store"]))
+        .stats(IndicesStatsParts::IndexMetric(
+            &[ELASTICSEARCH_INDEX],
+            &["docs", "store"],
+        ))
         .send()
         .await?
         .json::<Value>()
         .await
 }
 
-async fn index_stats(es_client: &Elasticsearch) -> Result<IndexStats, elasticsearch::Error> {
-    let es_response_body = &get_es_response(es_client).await?["indices"][ELASTICSEARCH_INDEX];
+pub async fn index_stats(es_client: &Elasticsearch) -> Result<IndexStats, elasticsearch::Error> {
+    let es_response_body = &get_es_response(es_client).await?["_all"];
 
     Ok(IndexStats {
         doc_cnt: es_response_body["total"]["docs"]["count"]
@@ -42,6 +47,72 @@ async fn index_stats(es_client: &Elasticsearch) -> Result<IndexStats, elasticsea
     })
 }
 
+/// Number and total size of documents in each indexing directory, counted with filter on path
+/// hierarchy in one request. Excluded directories are listed with zero
+pub async fn directory_stats(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<DirectoryStats>>, (StatusCode, String)> {
+    let indexing_directories = state.settings.read().await.indexing_directories.clone();
+    let filters: Map<String, Value> = indexing_directories
+        .iter()
+        .enumerate()
+        .filter(|(_, dir)| !dir.exclude)
+        .map(|(i, dir)| {
+            let path = dir.path.to_string_lossy().replace('\\', "/");
+            (i.to_string(), json!({ "term": { "path.hierarchy": path } }))
+        })
+        .collect();
+
+    let buckets = if filters.is_empty() {
+        Value::Null
+    } else {
+        let mut es_response_body = state
+            .es_client()
+            .await
+            .search(SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+            .size(0)
+            .body(json!({
+                "aggs": {
+                    "directories": {
+                        "filters": {
+                            "filters": filters
+                        },
+                        "aggs": {
+                            "total_size": {
+                                "sum": {
+                                    "field": "size"
+                                }
+                            }
+                        }
+                    }
+                }
+            }))
+            .send()
+            .await
+            .and_then(|res| res.error_for_status_code())
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .json::<Value>()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        es_response_body["aggregations"]["directories"]["buckets"].take()
+    };
+
+    let stats = indexing_directories
+        .into_iter()
+        .enumerate()
+        .map(|(i, dir)| {
+            let bucket = &buckets[i.to_string()];
+            DirectoryStats {
+                path: dir.path,
+                doc_count: bucket["doc_count"].as_u64().unwrap_or_default(),
+                total_size: bucket["total_size"]["value"].as_f64().unwrap_or_default() as u64,
+                exclude: dir.exclude,
+            }
+        })
+        .collect();
+    Ok(Json(stats))
+}
+
 pub async fn indexing_status(
     ws: WebSocketUpgrade,
     State(state): State<Arc<ServerState>>,
@@ -62,7 +133,7 @@ async fn indexing_status_ws(mut socket: WebSocket, state: Arc<ServerState>) {
         send(socket, state.indexing_status.read().await.clone()).await
     }
     async fn send_index_stats(socket: &mut WebSocket, state: &ServerState) -> bool {
-        let stats_message: IndexingWSMessage = match index_stats(&state.es_client).await {
+        let stats_message: IndexingWSMessage = match index_stats(&state.es_client().await).await {
             Ok(res) => res.into(),
             Err(e) => e.to_string().into(),
         };
@@ -76,25 +147,51 @@ async fn indexing_status_ws(mut socket: WebSocket, state: Arc<ServerState>) {
         return;
     }
 
-    let mut rx = state.indexing_events.subscribe();
+    let mut rx = state.indexing_events.read().await.subscribe();
+    let mut watcher_rx = state.watcher_events.subscribe();
+    // Client can mute activity of watcher with configuration message
+    let mut mute_watcher = false;
     loop {
-        match rx.recv().await {
-            Ok(event) => {
-                if let IndexingEvent::Finished(_) = event {
-                    if !send_index_stats(&mut socket, &state).await {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Ok(event) => {
+                    // Statistics change after indexing and when files are moved to trash
+                    if let IndexingEvent::Finished { .. } | IndexingEvent::FileDeleted(_) = event {
+                        if !send_index_stats(&mut socket, &state).await {
+                            return;
+                        }
+                    }
+                    if !send(&mut socket, event).await {
                         return;
                     }
                 }
-                if !send(&mut socket, event).await {
-                    return;
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    if !send_indexing_status(&mut socket, &state).await {
+                        return;
+                    }
                 }
-            }
-            Err(broadcast::error::RecvError::Lagged(_)) => {
-                if !send_indexing_status(&mut socket, &state).await {
-                    return;
+                _ => return,
+            },
+            // Only the latest events of watcher are shown, skipped ones aren't sent again
+            event = watcher_rx.recv() => match event {
+                Ok(event) if !mute_watcher => {
+                    if !send(&mut socket, event).await {
+                        return;
+                    }
                 }
-            }
-            _ => return,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                _ => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(ws::Message::Text(s))) => {
+                    match serde_json::from_str::<IndexingWSConfig>(&s) {
+                        Ok(config) => mute_watcher = config.mute_watcher,
+                        Err(e) => tracing::warn!("Invalid configuration of indexing status: {}", e),
+                    }
+                }
+                Some(Ok(_)) => {}
+                _ => return,
+            },
         }
     }
 }
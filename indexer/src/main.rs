@@ -1,24 +1,41 @@
 #![recursion_limit = "256"]
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
+use anyhow::Context;
 use axum::{
     error_handling::HandleErrorLayer,
+    extract::DefaultBodyLimit,
     http::StatusCode,
+    middleware,
     routing::{get, post},
     BoxError, Router,
 };
 use common_lib::{
-    indexer::{IndexingEvent, IndexingStatus},
+    indexer::{
+        FileError, IndexSchemaStatus, IndexingEvent, IndexingStatus, WatcherEvent,
+        MAX_WATCHER_EVENT_CNT,
+    },
     settings::Settings,
 };
-use elasticsearch::{http::transport::Transport, Elasticsearch};
+use elasticsearch::{
+    auth::Credentials,
+    cert::{Certificate, CertificateValidation},
+    http::transport::{MultiNodeConnectionPool, TransportBuilder},
+    Elasticsearch,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
 use notify::RecommendedWatcher;
-use notify_debouncer_mini::Debouncer;
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
 use tokio::{
     signal,
-    sync::{broadcast, RwLock},
+    sync::{broadcast, Mutex, Notify, RwLock},
+    task::JoinHandle,
 };
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
@@ -28,27 +45,120 @@ use tracing_subscriber::{
 use tracing_unwrap::ResultExt;
 
 use crate::{
-    indexer::create_index::create_index, settings::read_settings_file, watcher::start_watcher,
+    embeddings::ImageBatch,
+    indexer::{
+        create_index::{create_index, IndexOptions},
+        parse_failures::ParseFailure,
+        periodic::start_periodic_indexing,
+    },
+    provenance::Toolchain,
+    settings::{read_settings_file, settings_file_modified, start_settings_reload},
+    watcher::start_watcher,
 };
 
 mod actions;
+mod auth;
+mod client_errors;
+mod compare;
+mod duplicates;
 mod embeddings;
+mod fault_injection;
 mod file_server;
+mod health;
 mod indexer;
 mod parser;
+mod provenance;
+mod rerank;
 mod scanner;
 mod search;
 mod settings;
+mod tags;
+mod telemetry;
+#[cfg(test)]
+mod test_util;
 mod thumbnails;
 mod watcher;
 
 pub struct ServerState {
     settings: RwLock<Settings>,
-    es_client: Elasticsearch,
+    /// Re-created when settings of connection to Elasticsearch are changed
+    es_client: RwLock<Elasticsearch>,
     reqwest_client: reqwest_middleware::ClientWithMiddleware,
     indexing_status: RwLock<IndexingStatus>,
-    indexing_events: broadcast::Sender<IndexingEvent>,
-    watcher_debouncer: RwLock<Option<Debouncer<RecommendedWatcher>>>,
+    /// Replaced when capacity of channel changes
+    indexing_events: RwLock<broadcast::Sender<IndexingEvent>>,
+    /// Activity of file system watcher, receivers that lag behind skip old events
+    watcher_events: broadcast::Sender<WatcherEvent>,
+    watcher: RwLock<Option<RecommendedWatcher>>,
+    estimation_running: RwLock<bool>,
+    estimation_cancel: Notify,
+    /// Set while all files are indexed into new generation of index. Changes of documents in
+    /// current index would be lost when alias is switched, so they are rejected meanwhile
+    rebuilding: RwLock<bool>,
+    toolchain: RwLock<Toolchain>,
+    /// Last issued token for deleting index and time of its issue
+    delete_token: RwLock<Option<(String, Instant)>>,
+    /// Serializes access to client errors log file
+    client_errors_lock: Mutex<()>,
+    /// Held while least recently used thumbnails are evicted from cache
+    thumbnail_cache_lock: Mutex<()>,
+    /// Files selected in file dialog with time of selection,
+    /// served for some time even outside of indexed directories
+    picked_files: RwLock<HashMap<PathBuf, Instant>>,
+    /// Interval in hours and timer task of periodic indexing, if it's enabled
+    periodic_indexing: RwLock<Option<(u32, JoinHandle<()>)>>,
+    /// Renders recorded metrics for Prometheus
+    metrics_handle: PrometheusHandle,
+    /// Modification time of settings file when it was last read or written.
+    /// Held while settings from API or file are applied and written
+    settings_file_modified: Mutex<Option<SystemTime>>,
+    /// Errors of files in the latest indexing, saved to file after it
+    file_errors: RwLock<Vec<FileError>>,
+    /// Failures of parsing of files by path, saved to file after indexing
+    parse_failures: RwLock<HashMap<PathBuf, ParseFailure>>,
+    /// Images of concurrently indexed files waiting for calculation of their embeddings
+    image_batch: Mutex<ImageBatch>,
+    /// Compatibility of index checked on start, index is compatible after it's replaced
+    index_schema: RwLock<IndexSchemaStatus>,
+}
+
+impl ServerState {
+    /// Current Elasticsearch client, it's cheap to clone
+    pub async fn es_client(&self) -> Elasticsearch {
+        self.es_client.read().await.clone()
+    }
+}
+
+/// Channel of indexing events, with capacity for events of all concurrently processed files
+pub fn indexing_events_channel(max_concurrent_files: usize) -> broadcast::Sender<IndexingEvent> {
+    broadcast::channel(2 * max_concurrent_files).0
+}
+
+/// Create client of Elasticsearch nodes from settings, with credentials and CA certificate if set
+pub async fn create_es_client(settings: &Settings) -> anyhow::Result<Elasticsearch> {
+    if settings.elasticsearch_urls.is_empty() {
+        return Err(anyhow::anyhow!("No Elasticsearch URLs in settings"));
+    }
+    let connection_pool =
+        MultiNodeConnectionPool::round_robin(settings.elasticsearch_urls.clone(), None);
+    let mut transport_builder = TransportBuilder::new(connection_pool);
+    if let Some(user) = settings
+        .elasticsearch_user
+        .as_ref()
+        .filter(|x| !x.is_empty())
+    {
+        let password = settings.elasticsearch_password.clone().unwrap_or_default();
+        transport_builder = transport_builder.auth(Credentials::Basic(user.clone(), password));
+    }
+    if let Some(path) = &settings.elasticsearch_ca_cert_path {
+        let pem = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Can't read CA certificate: {}", path.display()))?;
+        let certificate = Certificate::from_pem(&pem).context("Invalid CA certificate")?;
+        transport_builder =
+            transport_builder.cert_validation(CertificateValidation::Full(certificate));
+    }
+    Ok(Elasticsearch::new(transport_builder.build()?))
 }
 
 #[tokio::main]
@@ -62,18 +172,34 @@ async fn main() {
         )
         .init();
 
+    let settings_modified = settings_file_modified().await;
     let settings = read_settings_file().await;
+    let metrics_handle = telemetry::install_recorder();
 
-    let es_transport = Transport::single_node(settings.elasticsearch_url.as_str())
-        .expect_or_log("Can't create connection to Elasticsearch");
-    let es_client = Elasticsearch::new(es_transport);
-    create_index(&es_client)
-        .await
-        .expect_or_log("Can't create Elasticsearch index");
+    let es_client = match create_es_client(&settings).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::error!("Can't create connection to Elasticsearch: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let index_schema = match create_index(&es_client, &IndexOptions::new(&settings)).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::error!("Can't create Elasticsearch index: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    if let IndexSchemaStatus::Outdated { reason, .. } = &index_schema {
+        tracing::error!(
+            "Index has to be cleared and files indexed again: {}",
+            reason
+        );
+    }
 
     let address = settings.indexer_address;
     let open_on_start = settings.open_on_start;
-    let indexing_events_channel_capacity = 2 * settings.max_concurrent_files;
+    let indexing_events = indexing_events_channel(settings.max_concurrent_files);
 
     let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
     let reqwest_client = reqwest_middleware::ClientBuilder::new(
@@ -87,38 +213,107 @@ async fn main() {
 
     let server_state = Arc::new(ServerState {
         settings: RwLock::new(settings),
-        es_client,
+        es_client: RwLock::new(es_client),
         reqwest_client,
         indexing_status: RwLock::new(IndexingStatus::NotStarted),
-        indexing_events: broadcast::channel(indexing_events_channel_capacity).0,
-        watcher_debouncer: RwLock::new(None),
+        indexing_events: RwLock::new(indexing_events),
+        watcher_events: broadcast::channel(MAX_WATCHER_EVENT_CNT).0,
+        watcher: RwLock::new(None),
+        estimation_running: RwLock::new(false),
+        estimation_cancel: Notify::new(),
+        rebuilding: RwLock::new(false),
+        toolchain: RwLock::new(Toolchain::default()),
+        delete_token: RwLock::new(None),
+        client_errors_lock: Mutex::new(()),
+        thumbnail_cache_lock: Mutex::new(()),
+        picked_files: RwLock::new(HashMap::new()),
+        periodic_indexing: RwLock::new(None),
+        metrics_handle,
+        settings_file_modified: Mutex::new(settings_modified),
+        file_errors: RwLock::new(indexer::errors::read_file_errors_file().await),
+        parse_failures: RwLock::new(indexer::parse_failures::read_parse_failures_file().await),
+        image_batch: Mutex::new(ImageBatch::default()),
+        index_schema: RwLock::new(index_schema),
     });
 
     start_watcher(Arc::clone(&server_state)).await;
+    start_periodic_indexing(Arc::clone(&server_state)).await;
+    start_settings_reload(Arc::clone(&server_state));
 
     let app = Router::new()
         .route(
             "/settings",
             get(settings::get_settings).put(settings::put_settings),
         )
+        .route("/settings/nn_server", get(settings::get_nn_server_devices))
         .route(
             "/index",
             get(indexer::status::indexing_status)
                 .patch(indexer::index)
                 .delete(indexer::delete_index),
         )
-        .route("/search", post(search::search))
+        .route("/index/delete_token", get(indexer::delete_token))
+        .route("/index/schema", get(indexer::index_schema))
+        .route("/index/directories", get(indexer::status::directory_stats))
+        .route("/index/errors", get(indexer::errors::get_file_errors))
+        .route(
+            "/index/errors/retry",
+            post(indexer::errors::retry_failed_files),
+        )
+        .route(
+            "/index/parse_failures/retry",
+            post(indexer::parse_failures::retry_parses),
+        )
+        .route("/index/rebuild", post(indexer::rebuild_index))
+        .route("/index/reprocess", post(indexer::reprocess))
+        .route(
+            "/index/estimate",
+            post(indexer::estimate::estimate).delete(indexer::estimate::cancel_estimate),
+        )
+        // Image of query can exceed default limit of request body
+        .route(
+            "/search",
+            post(search::search).layer(DefaultBodyLimit::max(search::SEARCH_REQUEST_MAX_SIZE)),
+        )
+        .route(
+            "/search/export",
+            post(search::export).layer(DefaultBodyLimit::max(search::SEARCH_REQUEST_MAX_SIZE)),
+        )
+        .route("/search/complete", get(search::complete))
+        .route("/search/similar", post(search::similar))
+        .route("/api/search", get(search::api_search))
+        .route("/rerank", post(rerank::rerank))
+        .route("/client_error", post(client_errors::report_client_error))
+        .route("/client_errors", get(client_errors::get_client_errors))
         .route("/open_path", post(actions::open_path))
+        .route("/reveal_path", post(actions::reveal_path))
+        .route("/delete_path", post(actions::delete_path))
         .route("/pick_file", post(actions::pick_file))
         .route("/pick_folder", post(actions::pick_folder))
         .route("/open_request", post(actions::open_request))
-        .route("/save_request", post(actions::save_request))
+        .route(
+            "/save_request",
+            post(actions::save_request)
+                .layer(DefaultBodyLimit::max(search::SEARCH_REQUEST_MAX_SIZE)),
+        )
         .route("/file", get(file_server::get_file))
         .route("/document_content", get(file_server::get_document_content))
+        .route("/document/:id/tags", post(tags::update_tags))
+        .route("/tags", get(tags::list_tags).post(tags::set_tags))
+        .route("/document/compare", get(compare::compare_documents))
+        .route("/duplicates", get(duplicates::get_duplicates))
+        .route("/metrics", get(telemetry::get_metrics))
+        // Applies only to routes above, client files and translations are served without token
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&server_state),
+            auth::require_token,
+        ))
         .route(
             "/client_translation",
             get(file_server::get_client_translation),
         )
+        // Probed by launcher and external monitoring
+        .route("/health", get(health::get_health))
         .fallback(file_server::get_client_file)
         .with_state(server_state)
         .layer(
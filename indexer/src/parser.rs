@@ -1,24 +1,52 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use chrono::{DateTime, Local, TimeZone, Utc};
-use common_lib::elasticsearch::FileES;
+use common_lib::{
+    elasticsearch::{ContentTypeDetection, FileES},
+    indexer::{FileError, FileErrorStage},
+};
 use mime::Mime;
 use serde::{de::Error, Deserialize, Deserializer};
+use tokio::io::AsyncReadExt;
 
-use crate::ServerState;
+use crate::{
+    fault_injection::injector, indexer::errors::on_file_error, provenance::start_provenance,
+    telemetry::RequestTimer, ServerState,
+};
 
-use self::{document::DocumentMetadata, image::ImageMetadata, multimedia::MultimediaMetadata};
+use self::{
+    document::DocumentMetadata,
+    email::EmailMetadata,
+    image::ImageMetadata,
+    multimedia::MultimediaMetadata,
+    source::{overridden_content_type, read_source_file},
+};
 
+pub mod archive;
 mod document;
+mod email;
 mod image;
 mod multimedia;
+mod ocr;
+mod source;
 mod text;
 
-const PARSERS: [&(dyn Parser + Send + Sync); 4] = [
+/// Size of beginning of file that is sent to Apache Tika to detect content type
+const DETECT_MAX_BYTES: u64 = 64 * 1024;
+
+/// Email parser copies subject and sent date from document metadata, so it goes before
+/// document parser
+const PARSERS: [&(dyn Parser + Send + Sync); 5] = [
     &text::TextParser,
     &image::ImageParser,
     &multimedia::MultimediaParser,
+    &email::EmailParser,
     &document::DocumentParser,
 ];
 
@@ -34,6 +62,26 @@ pub trait Parser {
     ) -> anyhow::Result<()>;
 }
 
+/// Durations of file parsing stages
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseDurations {
+    /// Reading file and extracting metadata with Apache Tika
+    pub metadata: Duration,
+    /// Processing metadata and contents with parsers, including embeddings calculation
+    pub processing: Duration,
+    /// Parts of processing that are timed separately
+    pub parsers: ParserDurations,
+}
+
+/// Durations of slow steps of parsers, included in processing duration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParserDurations {
+    /// Creating thumbnail of audio or video file
+    pub thumbnail: Duration,
+    pub text_embedding: Duration,
+    pub image_embedding: Duration,
+}
+
 #[derive(Deserialize)]
 pub struct Metadata {
     #[serde(rename = "Content-Type")]
@@ -49,6 +97,12 @@ pub struct Metadata {
     /// Fields for document files
     #[serde(flatten)]
     pub document_data: DocumentMetadata,
+    /// Fields for email files
+    #[serde(flatten)]
+    pub email_data: EmailMetadata,
+    /// Recorded by parsers, not part of metadata from Apache Tika
+    #[serde(skip)]
+    pub durations: ParserDurations,
 }
 
 impl Default for Metadata {
@@ -59,10 +113,30 @@ impl Default for Metadata {
             image_data: Default::default(),
             multimedia_data: Default::default(),
             document_data: Default::default(),
+            email_data: Default::default(),
+            durations: Default::default(),
         }
     }
 }
 
+/// Extract metadata and text content with Apache Tika
+async fn get_metadata(state: &ServerState, bytes: Vec<u8>) -> anyhow::Result<Metadata> {
+    let mut tika_meta_url = state.settings.read().await.tika_url.clone();
+    tika_meta_url.set_path("rmeta/text");
+    let _timer = RequestTimer::start("tika", "rmeta/text");
+    let [metadata]: [Metadata; 1] = state
+        .reqwest_client
+        .put(tika_meta_url)
+        .header("Accept", "application/json")
+        .header("maxEmbeddedResources", "0")
+        .body(bytes)
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(metadata)
+}
+
 async fn get_metadata_and_bytes(
     state: Arc<ServerState>,
     file: &mut FileES,
@@ -71,35 +145,177 @@ async fn get_metadata_and_bytes(
         return Ok((Metadata::default(), Vec::new()));
     }
 
-    let mut tika_meta_url = state.settings.read().await.tika_url.clone();
-    tika_meta_url.set_path("rmeta/text");
-    let req_builder = state.reqwest_client.put(tika_meta_url);
     let file = tokio::fs::read(&file.path).await?;
-    let [metadata]: [Metadata; 1] = req_builder
-        .header("Accept", "application/json")
-        .header("maxEmbeddedResources", "0")
-        .body(file.clone())
+    let metadata = get_metadata(&state, file.clone()).await?;
+    Ok((metadata, file))
+}
+
+/// Detect content type with Apache Tika from beginning of file and its name
+async fn detect_content_type(state: &ServerState, path: &Path) -> anyhow::Result<String> {
+    let mut tika_detect_url = state.settings.read().await.tika_url.clone();
+    tika_detect_url.set_path("detect/stream");
+    let mut file_start = Vec::new();
+    tokio::fs::File::open(path)
+        .await?
+        .take(DETECT_MAX_BYTES)
+        .read_to_end(&mut file_start)
+        .await?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let _timer = RequestTimer::start("tika", "detect/stream");
+    let content_type = state
+        .reqwest_client
+        .put(tika_detect_url)
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{file_name}\""),
+        )
+        .body(file_start)
         .send()
         .await?
-        .json()
+        .error_for_status()?
+        .text()
         .await?;
-    Ok((metadata, file))
+    Ok(content_type.trim().to_owned())
 }
 
-pub async fn parse_file(state: Arc<ServerState>, file: &mut FileES) -> anyhow::Result<()> {
-    let (mut metadata, file_bytes) = get_metadata_and_bytes(Arc::clone(&state), file).await?;
-    let mut content_type_mime: Mime = metadata.content_type.parse()?;
-    if content_type_mime.type_() == mime::TEXT {
-        let new_mime = mime_guess::from_path(&file.path).first_or_octet_stream();
-        if new_mime.type_() == mime::TEXT {
-            content_type_mime = new_mime;
-            metadata.content_type = content_type_mime.to_string();
+/// Checks if content type starts with any of skipped content types from settings
+pub fn is_skipped_content_type(skip_content_types: &[String], content_type: &str) -> bool {
+    skip_content_types
+        .iter()
+        .any(|x| content_type.starts_with(x.as_str()))
+}
+
+/// Parse content type from metadata, text types are refined by file extension.
+/// Generic binary type is refined by signature of file contents.
+/// Overrides from settings are kept as is
+fn parse_content_type(
+    metadata: &mut Metadata,
+    path: &Path,
+    content_type_overrides: &BTreeMap<String, String>,
+    file_bytes: &[u8],
+) -> anyhow::Result<(Mime, ContentTypeDetection)> {
+    let content_type_mime: Mime = metadata.content_type.parse()?;
+    if overridden_content_type(content_type_overrides, path).is_some() {
+        return Ok((content_type_mime, ContentTypeDetection::Override));
+    }
+
+    let refined = if content_type_mime.type_() == mime::TEXT {
+        let new_mime = mime_guess::from_path(path).first_or_octet_stream();
+        (new_mime.type_() == mime::TEXT).then_some((new_mime, ContentTypeDetection::Extension))
+    } else if content_type_mime.essence_str() == mime::APPLICATION_OCTET_STREAM.essence_str() {
+        infer::get(file_bytes)
+            .and_then(|x| x.mime_type().parse().ok())
+            .map(|x| (x, ContentTypeDetection::Magic))
+    } else {
+        None
+    };
+    match refined {
+        Some((new_mime, detection)) => {
+            metadata.content_type = new_mime.to_string();
+            Ok((new_mime, detection))
         }
+        None => Ok((content_type_mime, ContentTypeDetection::Tika)),
     }
+}
 
-    file.content_type = metadata.content_type.clone();
+fn set_content_type(
+    file: &mut FileES,
+    content_type: String,
+    content_type_mime: &Mime,
+    detection: ContentTypeDetection,
+) {
+    file.content_type = content_type;
     file.content_type_mime_type = content_type_mime.type_().to_string();
     file.content_type_mime_essence = content_type_mime.essence_str().to_owned();
+    file.content_type_detection = Some(detection);
+}
+
+pub async fn parse_file(
+    state: Arc<ServerState>,
+    file: &mut FileES,
+) -> anyhow::Result<ParseDurations> {
+    injector().change_file(&file.path)?;
+    injector().parse_file(&file.path)?;
+
+    // Files of skipped content types are indexed only with basic metadata
+    let start_time = Instant::now();
+    let (skip_content_types, content_type_overrides, max_file_size) = {
+        let tmp = state.settings.read().await;
+        (
+            tmp.skip_content_types.clone(),
+            tmp.content_type_overrides.clone(),
+            tmp.max_file_size,
+        )
+    };
+    let source_content_type = overridden_content_type(&content_type_overrides, &file.path);
+    if !skip_content_types.is_empty() && file.size > 0 {
+        let content_type = match &source_content_type {
+            Some(x) => x.clone(),
+            None => detect_content_type(&state, &file.path).await?,
+        };
+        if is_skipped_content_type(&skip_content_types, &content_type) {
+            tracing::debug!(
+                "Skipping parsing of file with content type {}: {}",
+                content_type,
+                file.path.display()
+            );
+            let content_type_mime: Mime = content_type.parse()?;
+            let detection = match source_content_type {
+                Some(_) => ContentTypeDetection::Override,
+                None => ContentTypeDetection::Tika,
+            };
+            set_content_type(file, content_type, &content_type_mime, detection);
+            start_provenance(&state, file).await;
+            return Ok(ParseDurations {
+                metadata: start_time.elapsed(),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Markdown and source code files are read directly
+    let (mut metadata, file_bytes) = match source_content_type {
+        Some(content_type) => read_source_file(&file.path, content_type, max_file_size).await?,
+        None => get_metadata_and_bytes(Arc::clone(&state), file).await?,
+    };
+    let metadata_duration = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let (content_type_mime, detection) = parse_content_type(
+        &mut metadata,
+        &file.path,
+        &content_type_overrides,
+        &file_bytes,
+    )?;
+    set_content_type(
+        file,
+        metadata.content_type.clone(),
+        &content_type_mime,
+        detection,
+    );
+    start_provenance(&state, file).await;
+
+    // Recognized text is processed as content, file is indexed without it on errors
+    if metadata.content_type.starts_with("image") {
+        match ocr::recognize_text(&state, &metadata, &file_bytes).await {
+            Ok(Some(text)) => metadata.content = Some(text),
+            Ok(None) => {}
+            Err(e) => {
+                let error = FileError {
+                    path: file.path.clone(),
+                    stage: FileErrorStage::Parse,
+                    error: format!("{e:#}"),
+                };
+                let e = e.context(format!("Error recognizing text: {}", file.path.display()));
+                on_file_error(Arc::clone(&state), error, format!("{e:?}")).await;
+            }
+        }
+    }
+
+    // Image parser takes image dimensions, so frame size of videos is copied before it
+    if metadata.content_type.starts_with("video") {
+        metadata.multimedia_data.video_dimensions = metadata.image_data.dimensions();
+    }
 
     for parser in PARSERS {
         if parser.is_supported_file(&metadata) {
@@ -109,7 +325,11 @@ pub async fn parse_file(state: Arc<ServerState>, file: &mut FileES) -> anyhow::R
         }
     }
 
-    Ok(())
+    Ok(ParseDurations {
+        metadata: metadata_duration,
+        processing: start_time.elapsed(),
+        parsers: metadata.durations,
+    })
 }
 
 /// Deserialize Option<DateTime> from string with given time zone, or local if not given
@@ -130,3 +350,99 @@ where
         None => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Content type detected for file with metadata of given type from Apache Tika
+    fn detect(
+        tika_content_type: &str,
+        path: &str,
+        file_bytes: &[u8],
+        content_type_overrides: &BTreeMap<String, String>,
+    ) -> (String, ContentTypeDetection) {
+        let mut metadata: Metadata =
+            serde_json::from_value(serde_json::json!({ "Content-Type": tika_content_type }))
+                .unwrap();
+        let (content_type_mime, detection) = parse_content_type(
+            &mut metadata,
+            Path::new(path),
+            content_type_overrides,
+            file_bytes,
+        )
+        .unwrap();
+        assert_eq!(metadata.content_type, content_type_mime.to_string());
+        (content_type_mime.essence_str().to_owned(), detection)
+    }
+
+    #[test]
+    fn extensionless_binary_files_are_detected_by_signature() {
+        let corpus: [(&[u8], &str); 6] = [
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "image/png"),
+            (b"\xff\xd8\xff\xe0\0\x10JFIF\0", "image/jpeg"),
+            (b"GIF89a\x01\0\x01\0", "image/gif"),
+            (b"%PDF-1.7\n%\xe2\xe3\xcf\xd3", "application/pdf"),
+            (b"PK\x03\x04\x14\0\0\0\x08\0", "application/zip"),
+            (b"ID3\x03\0\0\0\0\0\0", "audio/mpeg"),
+        ];
+        for (file_bytes, content_type) in corpus {
+            assert_eq!(
+                detect(
+                    "application/octet-stream",
+                    "/files/unnamed",
+                    file_bytes,
+                    &BTreeMap::new()
+                ),
+                (content_type.to_owned(), ContentTypeDetection::Magic)
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_signature_keeps_type_from_tika() {
+        assert_eq!(
+            detect(
+                "application/octet-stream",
+                "/files/unnamed",
+                b"\0\x01\x02\x03 unknown",
+                &BTreeMap::new()
+            ),
+            (
+                "application/octet-stream".to_owned(),
+                ContentTypeDetection::Tika
+            )
+        );
+        // Signature isn't checked when Apache Tika detected specific type
+        assert_eq!(
+            detect(
+                "application/x-sqlite3",
+                "/files/unnamed",
+                b"\x89PNG\r\n\x1a\n",
+                &BTreeMap::new()
+            ),
+            (
+                "application/x-sqlite3".to_owned(),
+                ContentTypeDetection::Tika
+            )
+        );
+    }
+
+    #[test]
+    fn text_is_refined_by_extension_and_overrides_are_kept() {
+        assert_eq!(
+            detect(
+                "text/plain",
+                "/files/style.css",
+                b"body {}",
+                &BTreeMap::new()
+            ),
+            ("text/css".to_owned(), ContentTypeDetection::Extension)
+        );
+        let overrides = BTreeMap::from([("css".to_owned(), "text/plain".to_owned())]);
+        assert_eq!(
+            detect("text/plain", "/files/style.css", b"body {}", &overrides),
+            ("text/plain".to_owned(), ContentTypeDetection::Override)
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use common_lib::{
+    elasticsearch::{FileES, ARCHIVE_ENTRY_SEPARATOR},
+    indexer::IndexingEvent,
+    provenance::Provenance,
+};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::{self, Sender};
+use tracing_unwrap::ResultExt;
+use zip::ZipArchive;
+
+use crate::{indexer::on_event, ServerState};
+
+use super::{
+    get_metadata, is_skipped_content_type, parse_content_type, set_content_type, text::TextParser,
+    Metadata, Parser, DETECT_MAX_BYTES,
+};
+
+/// Maximum number of entries indexed from one archive
+const ARCHIVE_MAX_ENTRIES: usize = 1000;
+
+/// Checks if entries of file are indexed with current settings
+pub async fn is_indexed_archive(state: &ServerState, file: &FileES) -> bool {
+    let settings = state.settings.read().await;
+    settings.index_archives
+        && file.provenance.is_some()
+        && file.content_type_mime_essence == "application/zip"
+        && !is_skipped_content_type(&settings.skip_content_types, &file.content_type)
+}
+
+/// Convert modification time of zip entry, stored in local time
+fn entry_modified(x: zip::DateTime) -> Option<DateTime<Utc>> {
+    Local
+        .with_ymd_and_hms(
+            x.year().into(),
+            x.month().into(),
+            x.day().into(),
+            x.hour().into(),
+            x.minute().into(),
+            x.second().into(),
+        )
+        .single()
+        .map(Into::into)
+}
+
+/// File extracted from archive
+struct ArchiveEntry {
+    /// Path inside of archive, with `/` separators
+    name: String,
+    modified: DateTime<Utc>,
+    bytes: Vec<u8>,
+}
+
+/// Read entry of archive with given index, `None` for directories, too large files
+/// and files with unsafe paths
+fn read_entry(
+    zip: &mut ZipArchive<File>,
+    i: usize,
+    max_file_size: u64,
+    archive_modified: DateTime<Utc>,
+) -> anyhow::Result<Option<ArchiveEntry>> {
+    let mut zip_file = zip.by_index(i)?;
+    if zip_file.is_dir() || zip_file.size() > max_file_size {
+        return Ok(None);
+    }
+    let Some(name) = zip_file
+        .enclosed_name()
+        .map(|x| x.to_string_lossy().replace('\\', "/"))
+    else {
+        return Ok(None);
+    };
+    let modified = entry_modified(zip_file.last_modified()).unwrap_or(archive_modified);
+    // Size in header can be wrong
+    let mut bytes = Vec::new();
+    (&mut zip_file)
+        .take(max_file_size)
+        .read_to_end(&mut bytes)?;
+    Ok(Some(ArchiveEntry {
+        name,
+        modified,
+        bytes,
+    }))
+}
+
+/// Read entries of zip archive one by one and send them to channel, with errors of single
+/// entries. Returns error if archive can't be read at all. Runs in blocking task
+fn read_entries(
+    path: &Path,
+    max_file_size: u64,
+    archive_modified: DateTime<Utc>,
+    tx: Sender<anyhow::Result<ArchiveEntry>>,
+) -> anyhow::Result<()> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let mut entries_cnt = 0;
+    for i in 0..zip.len() {
+        if entries_cnt == ARCHIVE_MAX_ENTRIES {
+            tracing::warn!(
+                "Archive has more than {} entries, others are skipped: {}",
+                ARCHIVE_MAX_ENTRIES,
+                path.display()
+            );
+            break;
+        }
+
+        let entry = match read_entry(&mut zip, i, max_file_size, archive_modified) {
+            Ok(Some(x)) => Ok(x),
+            Ok(None) => continue,
+            Err(e) => Err(e.context(format!("Error reading entry {i} of archive"))),
+        };
+        entries_cnt += 1;
+        // Receiver is dropped if processing of archive stopped
+        if tx.blocking_send(entry).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Process contents of archive entry with Apache Tika and text parser
+async fn parse_entry(
+    state: &Arc<ServerState>,
+    entry: &mut FileES,
+    bytes: Vec<u8>,
+) -> anyhow::Result<()> {
+    let hash_bytes: [u8; 32] = Sha256::digest(&bytes).into();
+    entry.hash = Some(base16ct::lower::encode_string(&hash_bytes));
+
+    // Beginning of entry is kept for detection of content type by signature
+    let file_start = bytes[..bytes.len().min(DETECT_MAX_BYTES as usize)].to_vec();
+    let mut metadata = if bytes.is_empty() {
+        Metadata::default()
+    } else {
+        get_metadata(state, bytes).await?
+    };
+    let content_type_overrides = state.settings.read().await.content_type_overrides.clone();
+    let (content_type_mime, detection) = parse_content_type(
+        &mut metadata,
+        &entry.path,
+        &content_type_overrides,
+        &file_start,
+    )?;
+    set_content_type(
+        entry,
+        metadata.content_type.clone(),
+        &content_type_mime,
+        detection,
+    );
+    if TextParser.is_supported_file(&metadata) {
+        TextParser
+            .parse(Arc::clone(state), entry, &mut metadata, &[])
+            .await?;
+    }
+    Ok(())
+}
+
+/// Extract text of files in zip archive, each entry is a separate document.
+/// Entries larger than maximum file size are skipped, errors of single entries are reported
+/// as indexing errors. Archive is read in blocking task, entry by entry
+pub async fn parse_archive_entries(
+    state: Arc<ServerState>,
+    archive: &FileES,
+) -> anyhow::Result<Vec<FileES>> {
+    let max_file_size = state.settings.read().await.max_file_size;
+    let (tx, mut rx) = mpsc::channel(1);
+    let (path, archive_modified) = (archive.path.clone(), archive.modified);
+    let read_f = tokio::task::spawn_blocking(move || {
+        read_entries(&path, max_file_size, archive_modified, tx)
+    });
+
+    let mut entries = Vec::new();
+    while let Some(entry) = rx.recv().await {
+        let ArchiveEntry {
+            name,
+            modified,
+            bytes,
+        } = match entry {
+            Ok(x) => x,
+            Err(e) => {
+                let e = e.context(format!("Error reading archive: {}", archive.path.display()));
+                on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
+                continue;
+            }
+        };
+
+        let mut entry = FileES {
+            _id: None,
+            path: PathBuf::from(format!(
+                "{}{}{}",
+                archive.path.display(),
+                ARCHIVE_ENTRY_SEPARATOR,
+                name
+            )),
+            modified,
+            size: bytes.len() as u64,
+            hash: None,
+            content_type: String::new(),
+            content_type_mime_type: String::new(),
+            content_type_mime_essence: String::new(),
+            content_type_detection: None,
+            content: None,
+            tags: Vec::new(),
+            has_thumbnail: Some(false),
+            // Models are recorded by parsers of entry
+            provenance: archive.provenance.clone().map(|x| Provenance {
+                models: Vec::new(),
+                ..x
+            }),
+            attributes: archive.attributes.clone(),
+            in_archive: Some(true),
+            parse_failed: None,
+            text_data: Default::default(),
+            image_data: Default::default(),
+            document_data: Default::default(),
+            multimedia_data: Default::default(),
+            email_data: Default::default(),
+        };
+        tracing::debug!("Parsing archive entry: {}", entry.path.display());
+        if let Err(e) = parse_entry(&state, &mut entry, bytes).await {
+            let e = e.context(format!(
+                "Error parsing archive entry: {}",
+                entry.path.display()
+            ));
+            on_event(Arc::clone(&state), IndexingEvent::Error(format!("{e:?}"))).await;
+            continue;
+        }
+        entries.push(entry);
+    }
+    read_f
+        .await
+        .unwrap_or_log()
+        .context("Error opening archive")?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use common_lib::{indexer::IndexingStatus, settings::HashAlgorithm};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    use crate::{
+        scanner::FileInfo,
+        test_util::{mock_tika, test_settings, test_state},
+    };
+
+    use super::*;
+
+    /// Zip archive with given text files, local header of file with given index is damaged
+    fn write_archive(path: &Path, files: &[(&str, &str)], damaged: usize) {
+        let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, text) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(text.as_bytes()).unwrap();
+        }
+        let mut bytes = zip.finish().unwrap().into_inner();
+        let header = bytes
+            .windows(4)
+            .enumerate()
+            .filter(|(_, x)| *x == b"PK\x03\x04")
+            .nth(damaged)
+            .unwrap()
+            .0;
+        bytes[header] = 0;
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[tokio::test]
+    async fn damaged_entry_is_reported_without_losing_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.zip");
+        let files = [("a.txt", "first"), ("b.txt", "second"), ("c.txt", "third")];
+        write_archive(&path, &files, 1);
+        let mut settings = test_settings();
+        settings.tika_url = mock_tika("test");
+        let state = test_state(settings).await;
+        *state.indexing_status.write().await = IndexingStatus::Indexing(Default::default());
+        let archive: FileInfo = serde_json::from_value(serde_json::json!({
+            "path": path,
+            "modified": 0,
+            "size": std::fs::metadata(&path).unwrap().len()
+        }))
+        .unwrap();
+        let archive = archive
+            .into_file_es(HashAlgorithm::Sha256, 1024)
+            .await
+            .unwrap();
+
+        let entries = parse_archive_entries(Arc::clone(&state), &archive)
+            .await
+            .unwrap();
+
+        let contents: Vec<_> = entries
+            .iter()
+            .map(|x| (x.path.clone(), x.content.clone().unwrap()))
+            .collect();
+        let entry_path = |name| PathBuf::from(format!("{}!/{}", path.display(), name));
+        assert_eq!(
+            contents,
+            [
+                (entry_path("a.txt"), "first".to_owned()),
+                (entry_path("c.txt"), "third".to_owned())
+            ]
+        );
+        let IndexingStatus::Indexing(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing isn't running");
+        };
+        assert_eq!(data.errors_cnt, 1);
+    }
+
+    #[tokio::test]
+    async fn unreadable_archive_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.zip");
+        std::fs::write(&path, "not an archive").unwrap();
+        let state = test_state(test_settings()).await;
+        let archive: FileInfo = serde_json::from_value(serde_json::json!({
+            "path": path,
+            "modified": 0,
+            "size": 14
+        }))
+        .unwrap();
+        let archive = archive
+            .into_file_es(HashAlgorithm::None, 1024)
+            .await
+            .unwrap();
+
+        assert!(parse_archive_entries(state, &archive).await.is_err());
+    }
+}
@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use common_lib::elasticsearch::{DocumentData, FileES, FileMetadata};
 use serde::Deserialize;
-use serde_with::{serde_as, DisplayFromStr};
+use serde_with::{formats::PreferOne, serde_as, DisplayFromStr, OneOrMany};
 
 use crate::ServerState;
 
@@ -14,15 +14,23 @@ use super::{deserialize_datetime_maybe_local, Metadata, Parser};
 #[derive(Default, Deserialize)]
 pub struct DocumentMetadata {
     #[serde(rename = "dc:title")]
-    title: Option<String>,
+    pub title: Option<String>,
+    /// E-books can have several authors, languages and publishers
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
     #[serde(rename = "dc:creator")]
-    creator: Option<String>,
+    creator: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
+    #[serde(rename = "dc:language")]
+    pub doc_language: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
+    #[serde(rename = "dc:publisher")]
+    publisher: Option<Vec<String>>,
     #[serde(
         rename = "dcterms:created",
         default,
         deserialize_with = "deserialize_datetime_maybe_local"
     )]
-    doc_created: Option<DateTime<Utc>>,
+    pub doc_created: Option<DateTime<Utc>>,
     #[serde(
         rename = "dcterms:modified",
         default,
@@ -44,6 +52,8 @@ impl FileMetadata for DocumentMetadata {
     fn any_metadata(&self) -> bool {
         self.title.is_some()
             || self.creator.is_some()
+            || self.doc_language.is_some()
+            || self.publisher.is_some()
             || self.doc_created.is_some()
             || self.doc_modified.is_some()
             || self.num_pages.is_some()
@@ -70,7 +80,9 @@ impl Parser for DocumentParser {
         let data = std::mem::take(&mut metadata.document_data);
         file.document_data = DocumentData {
             title: data.title,
-            creator: data.creator,
+            creator: data.creator.map(|x| x.join(", ")),
+            doc_language: data.doc_language.map(|x| x.join(", ")),
+            publisher: data.publisher.map(|x| x.join(", ")),
             doc_created: data.doc_created,
             doc_modified: data.doc_modified,
             num_pages: data.num_pages,
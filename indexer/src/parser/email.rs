@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use common_lib::elasticsearch::{EmailData, FileES};
+use serde::Deserialize;
+use serde_with::{formats::PreferOne, serde_as, DisplayFromStr, OneOrMany};
+
+use crate::ServerState;
+
+use super::{Metadata, Parser};
+
+/// Content types of RFC 822 and Outlook messages
+const EMAIL_CONTENT_TYPES: [&str; 2] = ["message/rfc822", "application/vnd.ms-outlook"];
+
+#[serde_as]
+#[derive(Default, Deserialize)]
+pub struct EmailMetadata {
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
+    #[serde(rename = "Message-From")]
+    sender: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
+    #[serde(rename = "Message-To")]
+    to: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
+    #[serde(rename = "Message-Cc")]
+    cc: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_, PreferOne>>")]
+    #[serde(rename = "Message-Bcc")]
+    bcc: Option<Vec<String>>,
+    /// Apache Tika is asked not to parse embedded resources, so it reports skipped attachments
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(rename = "X-TIKA:EXCEPTION:embedded_resource_limit_reached")]
+    embedded_resources_skipped: Option<bool>,
+}
+
+pub struct EmailParser;
+
+#[async_trait]
+impl Parser for EmailParser {
+    fn is_supported_file(&self, metadata: &Metadata) -> bool {
+        EMAIL_CONTENT_TYPES
+            .iter()
+            .any(|x| metadata.content_type.starts_with(x))
+    }
+
+    async fn parse(
+        &self,
+        _state: Arc<ServerState>,
+        file: &mut FileES,
+        metadata: &mut Metadata,
+        _file_bytes: &[u8],
+    ) -> anyhow::Result<()> {
+        let data = std::mem::take(&mut metadata.email_data);
+        let recipients: Vec<_> = [data.to, data.cc, data.bcc]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        // Subject and sent date are indexed as title and creation date of document too
+        file.email_data = EmailData {
+            sender: data.sender.map(|x| x.join(", ")),
+            recipients: (!recipients.is_empty()).then(|| recipients.join(", ")),
+            subject: metadata.document_data.title.clone(),
+            email_sent: metadata.document_data.doc_created,
+            has_attachments: Some(data.embedded_resources_skipped.unwrap_or(false)),
+        };
+        Ok(())
+    }
+}
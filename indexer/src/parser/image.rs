@@ -1,15 +1,14 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
+use anyhow::Context;
 use async_trait::async_trait;
-use common_lib::{
-    elasticsearch::{FileES, ImageData, ResolutionUnit},
-    BatchRequest,
-};
+use common_lib::elasticsearch::{FileES, ImageData, ResolutionUnit};
 use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
 
 use crate::{
-    embeddings::{get_image_search_image_embedding_generic, ImageEmbedding},
+    embeddings::{get_image_search_image_embedding_batched, EmbeddingError, ImageEmbedding},
+    provenance::record_model,
     thumbnails::get_thumbnail,
     ServerState,
 };
@@ -66,6 +65,13 @@ pub struct ImageMetadata {
     image_software: Option<String>,
 }
 
+impl ImageMetadata {
+    /// Width and height in pixels, if known
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.width.zip(self.height)
+    }
+}
+
 pub struct ImageParser;
 
 #[async_trait]
@@ -90,27 +96,27 @@ impl Parser for ImageParser {
 
         let image_search_enabled = state.settings.read().await.nn_server.image_search_enabled;
         let embedding = if image_search_enabled {
-            let nn_server_url = state.settings.read().await.nn_server_url.clone();
             if metadata.content_type.starts_with("image") {
-                get_image_search_image_embedding_generic(
-                    &state.reqwest_client,
-                    nn_server_url,
-                    BatchRequest { batched: true },
-                    file_bytes.to_vec(),
-                )
-                .await?
+                let start_time = Instant::now();
+                let embedding =
+                    get_image_search_image_embedding_batched(&state, file_bytes.to_vec())
+                        .await
+                        .context(EmbeddingError)?;
+                metadata.durations.image_embedding += start_time.elapsed();
+                embedding
             } else {
                 // Try to get thumbnail for audio/video files, ignore errors
-                match get_thumbnail(&file.path.to_string_lossy(), &None).await {
+                let start_time = Instant::now();
+                let thumbnail = get_thumbnail(&file.path, &None).await;
+                metadata.durations.thumbnail += start_time.elapsed();
+                file.has_thumbnail = Some(thumbnail.is_ok());
+                match thumbnail {
                     Ok(thumbnail) => {
-                        match get_image_search_image_embedding_generic(
-                            &state.reqwest_client,
-                            nn_server_url,
-                            BatchRequest { batched: true },
-                            thumbnail.0,
-                        )
-                        .await
-                        {
+                        let start_time = Instant::now();
+                        let embedding =
+                            get_image_search_image_embedding_batched(&state, thumbnail.0).await;
+                        metadata.durations.image_embedding += start_time.elapsed();
+                        match embedding {
                             Ok(res) => res,
                             Err(err) => {
                                 tracing::debug!(
@@ -131,6 +137,10 @@ impl Parser for ImageParser {
             ImageEmbedding { embedding: None }
         };
 
+        if embedding.embedding.is_some() {
+            record_model(&state, file, |x| &x.clip_image).await;
+        }
+
         let data = std::mem::take(&mut metadata.image_data);
         file.image_data = ImageData {
             image_embedding: embedding.embedding,
@@ -34,6 +34,9 @@ pub struct MultimediaMetadata {
     #[serde_as(as = "Option<DisplayFromStr>")]
     #[serde(rename = "xmpDM:audioChannelType")]
     audio_channel_type: Option<AudioChannelType>,
+    /// Frame width and height of video in pixels, Tika reports them as image dimensions
+    #[serde(skip)]
+    pub video_dimensions: Option<(u32, u32)>,
 }
 
 impl FileMetadata for MultimediaMetadata {
@@ -47,6 +50,7 @@ impl FileMetadata for MultimediaMetadata {
             || self.duration.is_some()
             || self.audio_sample_rate.is_some()
             || self.audio_channel_type.is_some()
+            || self.video_dimensions.is_some()
     }
 }
 
@@ -76,6 +80,8 @@ impl Parser for MultimediaParser {
             duration: data.duration,
             audio_sample_rate: data.audio_sample_rate,
             audio_channel_type: data.audio_channel_type,
+            video_width: data.video_dimensions.map(|x| x.0),
+            video_height: data.video_dimensions.map(|x| x.1),
         };
         Ok(())
     }
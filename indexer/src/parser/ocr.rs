@@ -0,0 +1,59 @@
+use std::process::Stdio;
+
+use anyhow::Context;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::ServerState;
+
+use super::Metadata;
+
+/// Recognize text in image with Tesseract, if it's enabled, image is large enough
+/// and has no text extracted by Apache Tika
+pub async fn recognize_text(
+    state: &ServerState,
+    metadata: &Metadata,
+    file_bytes: &[u8],
+) -> anyhow::Result<Option<String>> {
+    let (tesseract_path, languages) = {
+        let settings = state.settings.read().await;
+        let too_small = metadata
+            .image_data
+            .dimensions()
+            .is_some_and(|(w, h)| w.min(h) < settings.ocr_min_dimension);
+        let has_content = metadata
+            .content
+            .as_ref()
+            .is_some_and(|x| !x.trim().is_empty());
+        if !settings.ocr_enabled || too_small || has_content {
+            return Ok(None);
+        }
+        (
+            settings.ocr_tesseract_path.clone(),
+            settings.ocr_languages.clone(),
+        )
+    };
+
+    let mut child = Command::new(&tesseract_path)
+        .args(["stdin", "stdout", "-l", &languages])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Can't start Tesseract: {tesseract_path}"))?;
+    // Write image while reading output, otherwise Tesseract can block on full pipe
+    let mut stdin = child.stdin.take().unwrap();
+    let image = file_bytes.to_vec();
+    let write_task = tokio::spawn(async move { stdin.write_all(&image).await });
+    let output = child.wait_with_output().await?;
+    write_task.await??;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    Ok((!text.is_empty()).then_some(text))
+}
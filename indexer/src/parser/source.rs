@@ -0,0 +1,59 @@
+use std::{collections::BTreeMap, path::Path};
+
+use tokio::io::AsyncReadExt;
+
+use super::Metadata;
+
+/// Content type from overrides in settings by extension of file
+pub fn overridden_content_type(
+    content_type_overrides: &BTreeMap<String, String>,
+    path: &Path,
+) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    content_type_overrides.get(&extension).cloned()
+}
+
+/// Read Markdown or source code file as text without Apache Tika, which loses its structure.
+/// Title of Markdown file is taken from its first heading
+pub async fn read_source_file(
+    path: &Path,
+    content_type: String,
+    max_file_size: u64,
+) -> anyhow::Result<(Metadata, Vec<u8>)> {
+    let mut bytes = Vec::new();
+    tokio::fs::File::open(path)
+        .await?
+        .take(max_file_size)
+        .read_to_end(&mut bytes)
+        .await?;
+    let content = String::from_utf8_lossy(&bytes).into_owned();
+
+    let mut metadata = Metadata {
+        content_type,
+        ..Default::default()
+    };
+    if metadata.content_type == "text/markdown" {
+        metadata.document_data.title = markdown_title(&content);
+    }
+    metadata.content = Some(content);
+    Ok((metadata, bytes))
+}
+
+/// Text of the first level 1 heading, lines of code blocks are skipped
+fn markdown_title(text: &str) -> Option<String> {
+    let mut in_code_block = false;
+    for line in text.lines().map(str::trim_start) {
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            // Closing sequence of hashes is optional
+            let title = line
+                .strip_prefix("# ")
+                .map(|x| x.trim_end_matches('#').trim());
+            if let Some(title) = title.filter(|x| !x.is_empty()) {
+                return Some(title.to_owned());
+            }
+        }
+    }
+    None
+}
@@ -1,13 +1,18 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
+use anyhow::Context;
 use async_trait::async_trait;
 use common_lib::{
-    elasticsearch::{FileES, TextData},
+    elasticsearch::{ContentLanguage, FileES, TextData},
     BatchRequest,
 };
 use tracing_unwrap::OptionExt;
 
-use crate::{embeddings::get_text_search_embedding, ServerState};
+use crate::{
+    embeddings::{get_text_search_embedding, EmbeddingError},
+    provenance::record_model,
+    ServerState,
+};
 
 use super::{Metadata, Parser};
 
@@ -36,6 +41,7 @@ impl Parser for TextParser {
         let text_search_enabled = state.settings.read().await.nn_server.text_search_enabled;
         if text_search_enabled {
             let nn_server_url = state.settings.read().await.nn_server_url.clone();
+            let start_time = Instant::now();
             let embedding = get_text_search_embedding(
                 &state.reqwest_client,
                 nn_server_url,
@@ -43,13 +49,69 @@ impl Parser for TextParser {
                 file.content.as_ref().unwrap_or_log(),
                 true,
             )
-            .await?;
+            .await
+            .context(EmbeddingError)?;
+            metadata.durations.text_embedding += start_time.elapsed();
+            record_model(&state, file, |x| &x.minilm_text).await;
 
             file.text_data = TextData {
                 text_embedding: Some(embedding.embedding),
                 summary: embedding.summary,
+                language: None,
             };
         }
+        // Language from metadata of document is preferred, if it's known
+        file.text_data.language = metadata
+            .document_data
+            .doc_language
+            .iter()
+            .flatten()
+            .find_map(|x| ContentLanguage::from_tag(x))
+            .or_else(|| file.content.as_deref().and_then(ContentLanguage::detect));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_of_latin_script_are_detected_without_frequent_words() {
+        let detect = ContentLanguage::detect;
+        assert_eq!(
+            detect("Fröhliche Grüße aus München, wir wünschen schönes Wochenende"),
+            Some(ContentLanguage::De)
+        );
+        assert_eq!(
+            detect("Rapport annuel concernant les résultats financiers obtenus"),
+            Some(ContentLanguage::Fr)
+        );
+        assert_eq!(
+            detect("Informe anual sobre los resultados financieros obtenidos"),
+            Some(ContentLanguage::Es)
+        );
+        assert_eq!(
+            detect("Quarterly financial report summarizing revenue growth"),
+            Some(ContentLanguage::En)
+        );
+    }
+
+    #[test]
+    fn languages_of_other_scripts_are_detected() {
+        let detect = ContentLanguage::detect;
+        assert_eq!(
+            detect("Годовой отчёт о финансовых результатах компании"),
+            Some(ContentLanguage::Ru)
+        );
+        assert_eq!(
+            detect("今日はとても良い天気ですね"),
+            Some(ContentLanguage::Ja)
+        );
+        assert_eq!(
+            detect("오늘은 날씨가 매우 좋습니다"),
+            Some(ContentLanguage::Ko)
+        );
+        assert_eq!(detect("12345 !?"), None);
+    }
+}
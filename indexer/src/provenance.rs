@@ -0,0 +1,96 @@
+use common_lib::{
+    elasticsearch::FileES,
+    provenance::{ModelVersions, Provenance},
+};
+
+use crate::ServerState;
+
+/// Version of parsing code, increased when it changes the way documents are produced
+pub const PARSER_VERSION: u32 = 4;
+
+/// Versions of tools that currently process files
+#[derive(Debug, Clone, Default)]
+pub struct Toolchain {
+    pub tika_version: Option<String>,
+    pub models: ModelVersions,
+}
+
+impl Toolchain {
+    /// Provenance of file processed with all enabled models
+    pub fn provenance(&self) -> Provenance {
+        Provenance {
+            tika_version: self.tika_version.clone(),
+            models: self.models.iter().cloned().collect(),
+            parser_version: Some(PARSER_VERSION),
+        }
+    }
+}
+
+async fn get_tika_version(state: &ServerState) -> anyhow::Result<String> {
+    let mut tika_version_url = state.settings.read().await.tika_url.clone();
+    tika_version_url.set_path("version");
+    let version = state
+        .reqwest_client
+        .get(tika_version_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(version.trim().to_owned())
+}
+
+async fn get_model_versions(state: &ServerState) -> anyhow::Result<ModelVersions> {
+    let mut nn_server_models_url = state.settings.read().await.nn_server_url.clone();
+    nn_server_models_url.set_path("models");
+    let versions = state
+        .reqwest_client
+        .get(nn_server_models_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(versions)
+}
+
+/// Get current versions of tools and store them for recording provenance of processed files.
+/// Versions that can't be received are unknown
+pub async fn update_toolchain(state: &ServerState) -> Toolchain {
+    let tika_version = get_tika_version(state)
+        .await
+        .map_err(|e| tracing::error!("Error getting Apache Tika version: {}", e))
+        .ok();
+    let models = get_model_versions(state)
+        .await
+        .map_err(|e| tracing::error!("Error getting NN server model versions: {}", e))
+        .unwrap_or_default();
+
+    let toolchain = Toolchain {
+        tika_version,
+        models,
+    };
+    *state.toolchain.write().await = toolchain.clone();
+    toolchain
+}
+
+/// Start recording provenance of file whose contents are processed
+pub async fn start_provenance(state: &ServerState, file: &mut FileES) {
+    file.provenance = Some(Provenance {
+        tika_version: state.toolchain.read().await.tika_version.clone(),
+        models: Vec::new(),
+        parser_version: Some(PARSER_VERSION),
+    });
+}
+
+/// Record model that calculated embedding of file
+pub async fn record_model(
+    state: &ServerState,
+    file: &mut FileES,
+    model: fn(&ModelVersions) -> &Option<String>,
+) {
+    let toolchain = state.toolchain.read().await;
+    if let (Some(provenance), Some(version)) = (&mut file.provenance, model(&toolchain.models)) {
+        provenance.models.push(version.clone());
+    }
+}
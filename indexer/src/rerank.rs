@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Json};
+use common_lib::{
+    rerank::{RankedPassage, RerankRequest, RerankResponse},
+    BatchRequest,
+};
+
+use crate::{embeddings::get_rerank_scores, ServerState};
+
+/// Score passages by relevance to query with reranking model of neural network server,
+/// for external callers. Number and length of passages are limited by settings
+pub async fn rerank(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<RerankRequest>,
+) -> Result<Json<RerankResponse>, (StatusCode, String)> {
+    let (reranking_enabled, nn_server_url, max_passages, max_passage_len) = {
+        let settings = state.settings.read().await;
+        (
+            settings.nn_server.reranking_enabled,
+            settings.nn_server_url.clone(),
+            settings.rerank_max_passages,
+            settings.rerank_max_passage_len,
+        )
+    };
+    if !reranking_enabled {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Reranking is disabled in settings of neural network server".to_owned(),
+        ));
+    }
+    if request.passages.len() > max_passages {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Too many passages: {}, maximum is {}",
+                request.passages.len(),
+                max_passages
+            ),
+        ));
+    }
+    if let Some(i) = request
+        .passages
+        .iter()
+        .position(|x| x.chars().count() > max_passage_len)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Passage {i} is longer than {max_passage_len} characters"),
+        ));
+    }
+    if request.passages.is_empty() {
+        return Ok(Json(RerankResponse {
+            scores: Vec::new(),
+            passages: Vec::new(),
+        }));
+    }
+
+    let queries = vec![request.query; request.passages.len()];
+    let scores = get_rerank_scores(
+        &state.reqwest_client,
+        nn_server_url,
+        BatchRequest { batched: true },
+        queries,
+        request.passages.clone(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .scores;
+
+    let mut passages: Vec<_> = request
+        .passages
+        .into_iter()
+        .zip(&scores)
+        .enumerate()
+        .map(|(index, (passage, score))| RankedPassage {
+            index,
+            passage,
+            score: *score,
+        })
+        .collect();
+    // Stable sort keeps order of request for equal scores
+    passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(Json(RerankResponse { scores, passages }))
+}
@@ -1,19 +1,38 @@
-use std::{cmp::Eq, collections::HashSet, hash::Hash, path::PathBuf};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Eq,
+    collections::{HashMap, HashSet},
+    fs::{File, Metadata},
+    hash::Hash,
+    io::{ErrorKind, Read},
+    path::{is_separator, Path, PathBuf, MAIN_SEPARATOR},
+    time::{Duration, Instant},
+};
 
+use anyhow::anyhow;
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use common_lib::{
     elasticsearch::{
-        FileES, ELASTICSEARCH_INDEX, ELASTICSEARCH_MAX_SIZE, ELASTICSEARCH_PIT_KEEP_ALIVE,
+        enclosing_archive, FileAttributes, FileES, ARCHIVE_ENTRY_SEPARATOR, ELASTICSEARCH_MAX_SIZE,
+        ELASTICSEARCH_PIT_MAX_RENEWALS, FUTURE_MODIFIED_TOLERANCE_SECS,
     },
-    settings::{IndexingDirectory, Settings},
+    provenance::Provenance,
+    settings::{ChangeDetection, HashAlgorithm, IndexingDirectory, Settings},
 };
-use elasticsearch::{Elasticsearch, SearchParts};
+use elasticsearch::{params::Conflicts, DeleteByQueryParts, Elasticsearch, SearchParts};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 use tracing_unwrap::{OptionExt, ResultExt};
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::fault_injection::injector;
+
+/// Rate of scanning is logged for directories that took longer, to tune their concurrency
+const SCAN_RATE_LOG_MIN_DURATION: Duration = Duration::from_secs(1);
 
 /// Struct with file path and data to determine if file has been modified
 #[derive(Debug, Clone, Deserialize)]
@@ -30,55 +49,106 @@ pub struct FileInfo {
     /// Process contents or include only basic metadata
     #[serde(default = "FileInfo::default_process_contents")]
     pub process_contents: bool,
+    /// Tags added by user, kept when file is reindexed
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Versions of tools that processed contents, only for files from index
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// Hash of contents, from index or calculated for hash change detection
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// How modification is detected, from settings of containing directory
+    #[serde(skip)]
+    pub change_detection: ChangeDetection,
+    /// Compare hashes even if modification time changed
+    #[serde(skip)]
+    pub hash_always: bool,
+    /// Permissions and attributes from file system, only online-only flag is read from index
+    #[serde(flatten)]
+    pub attributes: FileAttributes,
+    /// Entry of archive, only for files from index
+    #[serde(default)]
+    pub in_archive: bool,
+    /// Indexed without contents after failures of parsing, only for files from index
+    #[serde(default)]
+    pub parse_failed: bool,
+    /// Compare path ignoring case, from settings
+    #[serde(skip)]
+    pub case_insensitive_path: bool,
 }
 
-impl TryFrom<FileInfo> for FileES {
-    type Error = std::io::Error;
-
-    fn try_from(x: FileInfo) -> Result<Self, Self::Error> {
-        let hash = x
-            .process_contents
-            .then(|| {
-                tracing::debug!("Calculating hash of file: {}", x.path.display());
-                let file = match std::fs::read(&x.path) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        tracing::error!("Error reading file: {}", e);
-                        return Err(e);
-                    }
-                };
-                let hash_bytes: [u8; 32] = Sha256::digest(file).into();
-                Ok(base16ct::lower::encode_string(&hash_bytes))
-            })
-            .transpose()?;
+impl FileInfo {
+    /// Convert to document, calculating hash of contents in blocking task.
+    /// Hash calculated for change detection is reused
+    pub async fn into_file_es(
+        self,
+        hash_algorithm: HashAlgorithm,
+        hash_chunk_size: usize,
+    ) -> std::io::Result<FileES> {
+        let hash = match self.hash {
+            Some(hash) if self.process_contents => Some(hash),
+            _ if self.process_contents => {
+                let path = self.path.clone();
+                tokio::task::spawn_blocking(move || {
+                    file_hash(&path, hash_algorithm, hash_chunk_size)
+                })
+                .await
+                .unwrap_or_log()?
+            }
+            _ => None,
+        };
 
-        Ok(Self {
-            _id: x._id,
-            path: x.path,
-            modified: x.modified,
-            size: x.size,
+        Ok(FileES {
+            _id: self._id,
+            path: self.path,
+            modified: self.modified,
+            size: self.size,
             hash,
             content_type: String::new(),
             content_type_mime_type: String::new(),
             content_type_mime_essence: String::new(),
+            content_type_detection: None,
             content: None,
+            tags: self.tags,
+            has_thumbnail: None,
+            provenance: None,
+            attributes: self.attributes,
+            in_archive: None,
+            parse_failed: None,
             text_data: Default::default(),
             image_data: Default::default(),
             document_data: Default::default(),
             multimedia_data: Default::default(),
+            email_data: Default::default(),
         })
     }
-}
 
-impl FileInfo {
     /// Create file info and check if file contents can be processed with current settings
-    fn new(path: PathBuf, modified: DateTime<Utc>, size: u64, settings: &Settings) -> Self {
+    fn new(
+        path: PathBuf,
+        modified: DateTime<Utc>,
+        size: u64,
+        attributes: FileAttributes,
+        settings: &Settings,
+    ) -> Self {
+        let directory = indexing_directory(settings, &path);
         Self {
             _id: None,
             path,
             modified,
             size,
-            process_contents: size <= settings.max_file_size,
+            process_contents: size <= settings.max_file_size
+                && attributes.is_online_only != Some(true),
+            tags: Vec::new(),
+            provenance: None,
+            hash: None,
+            change_detection: directory.map(|x| x.change_detection).unwrap_or_default(),
+            hash_always: directory.is_some_and(|x| x.hash_always),
+            attributes,
+            in_archive: false,
+            parse_failed: false,
+            case_insensitive_path: settings.case_insensitive_paths,
         }
     }
 
@@ -86,25 +156,70 @@ impl FileInfo {
         true
     }
 
+    /// Get current info of same file, keeping ID and tags.
+    /// Returns `None` if file doesn't exist anymore
+    pub fn refreshed(&self, settings: &Settings) -> Option<FileInfo> {
+        file_info_from_path(settings, self.path.clone()).map(|x| FileInfo {
+            _id: self._id.clone(),
+            tags: self.tags.clone(),
+            ..x
+        })
+    }
+
+    /// Path for comparison with other files, lowercased if paths are case-insensitive
+    pub fn path_key(&self) -> Cow<'_, Path> {
+        if self.case_insensitive_path {
+            Cow::Owned(lowercase_path(&self.path))
+        } else {
+            Cow::Borrowed(&self.path)
+        }
+    }
+
+    /// Path of archive that contains entry, or path of file itself for other files
+    pub fn archive_path(&self) -> Cow<'_, Path> {
+        match enclosing_archive(&self.path) {
+            Some(archive) if self.in_archive => Cow::Owned(archive),
+            _ => Cow::Borrowed(&self.path),
+        }
+    }
+
+    /// Checks if modification time is later than current time, beyond tolerance for clock differences
+    pub fn is_modified_in_future(&self) -> bool {
+        self.modified.timestamp() > Utc::now().timestamp() + FUTURE_MODIFIED_TOLERANCE_SECS
+    }
+
     /// Checks if file was modified.
-    /// Checks last modification time, then size
-    fn is_modified(&self, new: &FileInfo) -> bool {
-        self.modified.timestamp() != new.modified.timestamp() || self.size != new.size
+    /// Checks last modification time and size, or also hash if both hashes are known
+    /// with hash change detection. Modification time is ignored if hashes are always compared
+    pub fn is_modified(&self, new: &FileInfo) -> bool {
+        let mtime_changed = self.modified.timestamp() != new.modified.timestamp();
+        let size_changed = self.size != new.size;
+        match (new.change_detection, &self.hash, &new.hash) {
+            (ChangeDetection::Hash, Some(old_hash), Some(new_hash)) => {
+                size_changed || old_hash != new_hash || (mtime_changed && !new.hash_always)
+            }
+            _ => mtime_changed || size_changed,
+        }
     }
 }
 
 impl PartialEq for FileInfo {
     fn eq(&self, other: &Self) -> bool {
-        self.path == other.path
+        self.path_key() == other.path_key()
     }
 }
 impl Eq for FileInfo {}
 impl Hash for FileInfo {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.path.hash(state)
+        self.path_key().hash(state)
     }
 }
 
+/// Path with all characters lowercased, same as in `path.lowercase` field of index
+pub fn lowercase_path(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().to_lowercase())
+}
+
 /// Difference between two lists of files
 pub struct FilesDiff {
     /// Files present only in new list
@@ -116,8 +231,12 @@ pub struct FilesDiff {
 }
 
 impl FilesDiff {
-    /// Calculates difference
-    pub fn from_vec(old: Vec<FileInfo>, new: Vec<FileInfo>) -> Self {
+    /// Calculates difference, also treating unmodified files as modified if they are outdated.
+    /// With case-insensitive paths, renaming that only changes case is a modification
+    pub fn from_vec_with<F>(old: Vec<FileInfo>, new: Vec<FileInfo>, is_outdated: F) -> Self
+    where
+        F: Fn(&FileInfo, &FileInfo) -> bool,
+    {
         let old_hs: HashSet<_> = old.into_iter().collect();
         let new_hs: HashSet<_> = new.into_iter().collect();
         FilesDiff {
@@ -131,16 +250,301 @@ impl FilesDiff {
                         new_hs.get(x).unwrap_or_log().clone(),
                     )
                 })
-                .filter(|(x, y)| x.is_modified(y))
+                .filter(|(x, y)| x.is_modified(y) || x.path != y.path || is_outdated(x, y))
                 .collect(),
         }
     }
 }
 
+/// Remove entries of removed and modified archives, or all entries if archives aren't indexed.
+/// Entries of modified archives are added again when archive is processed
+pub fn remove_archive_entries(diff: &mut FilesDiff, entries: Vec<FileInfo>, index_archives: bool) {
+    let changed_archives: HashSet<_> = diff
+        .removed
+        .iter()
+        .chain(diff.modified.iter().map(|(old, _)| old))
+        .map(|x| x.path.as_path())
+        .collect();
+    let removed_entries: Vec<_> = entries
+        .into_iter()
+        .filter(|x| {
+            !index_archives
+                || enclosing_archive(&x.path)
+                    .is_none_or(|archive| changed_archives.contains(archive.as_path()))
+        })
+        .collect();
+    diff.removed.extend(removed_entries);
+}
+
+/// Read file for hashing in chunks of given size
+fn read_chunks(path: &Path, chunk_size: usize, mut f: impl FnMut(&[u8])) -> std::io::Result<()> {
+    tracing::debug!("Calculating hash of file: {}", path.display());
+    let mut file = File::open(path)?;
+    let mut chunk = vec![0; chunk_size.max(1)];
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&chunk[..n]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Calculate hex digest of file contents, `None` if hashing is disabled
+fn file_hash(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    chunk_size: usize,
+) -> std::io::Result<Option<String>> {
+    let hash = match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            read_chunks(path, chunk_size, |x| hasher.update(x))?;
+            let hash_bytes: [u8; 32] = hasher.finalize().into();
+            base16ct::lower::encode_string(&hash_bytes)
+        }
+        HashAlgorithm::Xxh3 => {
+            let mut hasher = Xxh3::new();
+            read_chunks(path, chunk_size, |x| hasher.update(x))?;
+            format!("{:016x}", hasher.digest())
+        }
+        HashAlgorithm::None => return Ok(None),
+    };
+    Ok(Some(hash))
+}
+
+/// Calculate hashes of files with hash change detection that have hash in index.
+/// Only files that look unchanged are hashed, or all files with unchanged size
+/// if modification time is ignored, other files are detected as modified anyway.
+/// Hashes of other algorithm can't be compared, such files are checked by modification time and size
+pub fn calculate_change_detection_hashes(
+    old: &[FileInfo],
+    new: &mut [FileInfo],
+    hash_algorithm: HashAlgorithm,
+    hash_chunk_size: usize,
+) {
+    let Some(digest_len) = hash_algorithm.digest_len() else {
+        return;
+    };
+    let old_files: HashMap<_, _> = old
+        .iter()
+        .filter(|x| x.hash.as_ref().is_some_and(|x| x.len() == digest_len))
+        .map(|x| (x.path_key(), x))
+        .collect();
+    for file in new
+        .iter_mut()
+        .filter(|x| x.change_detection == ChangeDetection::Hash && x.process_contents)
+    {
+        let Some(old_file) = old_files.get(&file.path_key()) else {
+            continue;
+        };
+        let mtime_changed = old_file.modified.timestamp() != file.modified.timestamp();
+        if old_file.size != file.size || (mtime_changed && !file.hash_always) {
+            continue;
+        }
+        match file_hash(&file.path, hash_algorithm, hash_chunk_size) {
+            Ok(x) => file.hash = x,
+            Err(e) => tracing::error!("Error reading file: {}", e),
+        }
+    }
+}
+
+/// Configured directory that contains path, the innermost one if directories are nested
+fn indexing_directory<'a>(settings: &'a Settings, path: &Path) -> Option<&'a IndexingDirectory> {
+    settings
+        .indexing_directories
+        .iter()
+        .filter(|x| path.starts_with(&x.path))
+        .max_by_key(|x| x.path.components().count())
+}
+
+/// Limit of concurrency for files of indexing directory, global limit is the upper bound.
+/// At least one file is processed even if limits are zero
+pub fn directory_concurrency(max_concurrency: usize, max_concurrent_files: usize) -> usize {
+    max_concurrency.min(max_concurrent_files).max(1)
+}
+
+/// Checks if path is in excluded configured directory, and not in included one nested in it
+fn in_excluded_directory(settings: &Settings, path: &Path) -> bool {
+    indexing_directory(settings, path).is_some_and(|dir| dir.exclude)
+}
+
+/// Checks if indexed file is excluded by current settings. Entries of archives are checked
+/// by their archive, as entries aren't scanned separately
+pub fn is_excluded_file(
+    settings: &Settings,
+    exclude_regexes: &ExcludeRegexes,
+    file: &FileInfo,
+) -> bool {
+    let path = file.archive_path();
+    in_excluded_directory(settings, &path) || exclude_regexes.is_excluded(settings, &path)
+}
+
+/// Compiled global regex and regexes of configured directories for excluding files
+pub struct ExcludeRegexes {
+    global: Regex,
+    directories: Vec<(PathBuf, Regex)>,
+}
+
+impl ExcludeRegexes {
+    pub fn new(settings: &Settings) -> Result<Self, regex::Error> {
+        Ok(Self {
+            global: Regex::new(&settings.exclude_file_regex)?,
+            directories: settings
+                .indexing_directories
+                .iter()
+                .filter_map(|dir| dir.exclude_regex.as_ref().map(|re| (&dir.path, re)))
+                .map(|(path, re)| Ok((path.clone(), Regex::new(re)?)))
+                .collect::<Result<_, regex::Error>>()?,
+        })
+    }
+
+    /// Checks if file matches global regex or regex of configured directory that contains it
+    pub fn is_excluded(&self, settings: &Settings, path: &Path) -> bool {
+        let path_str = path.to_string_lossy();
+        self.global.is_match(&path_str)
+            || indexing_directory(settings, path).is_some_and(|dir| {
+                self.directories
+                    .iter()
+                    .any(|(x, re)| *x == dir.path && re.is_match(&path_str))
+            })
+    }
+}
+
+/// Use only modification time and size for files in directories with hash change detection
+/// that are too large to be hashed
+fn limit_hash_change_detection(settings: &Settings, files: &mut [FileInfo]) {
+    let mut directory_sizes = HashMap::new();
+    for file in files
+        .iter()
+        .filter(|x| x.change_detection == ChangeDetection::Hash)
+    {
+        if let Some(dir) = indexing_directory(settings, &file.path) {
+            *directory_sizes.entry(dir.path.as_path()).or_insert(0) += file.size;
+        }
+    }
+
+    let too_large: HashSet<_> = directory_sizes
+        .into_iter()
+        .filter(|(_, size)| *size > settings.hash_change_detection_max_size)
+        .map(|(path, size)| {
+            tracing::warn!(
+                "Directory is too large for hash change detection ({} bytes), \
+                 using modification time: {}",
+                size,
+                path.display()
+            );
+            path
+        })
+        .collect();
+    if too_large.is_empty() {
+        return;
+    }
+
+    for file in files {
+        if indexing_directory(settings, &file.path)
+            .is_some_and(|x| too_large.contains(x.path.as_path()))
+        {
+            file.change_detection = ChangeDetection::MtimeSize;
+        }
+    }
+}
+
+/// Online-only files that are downloaded for processing during indexing
+#[derive(Debug, Default)]
+pub struct Hydration {
+    /// Total size of downloaded files
+    pub size: u64,
+    /// Number of files left for later indexings
+    pub deferred: usize,
+}
+
+/// Allow processing contents of online-only files until their total size reaches hydration budget.
+/// Other online-only files are indexed without contents, or left in index as is if not modified.
+/// Files left by previous indexings are downloaded first
+pub fn limit_hydration(settings: &Settings, diff: &mut FilesDiff) -> Hydration {
+    let budget = settings.online_only_hydration_budget;
+    let mut hydration = Hydration::default();
+    let mut hydrate = |file: &mut FileInfo| {
+        if file.attributes.is_online_only != Some(true) || file.size > settings.max_file_size {
+            return;
+        }
+        if hydration.size + file.size <= budget {
+            hydration.size += file.size;
+            file.process_contents = true;
+            file.attributes.is_online_only = Some(false);
+        } else if budget > 0 {
+            hydration.deferred += 1;
+        }
+    };
+
+    diff.modified.retain_mut(|(old, new)| {
+        hydrate(new);
+        new.attributes.is_online_only != Some(true) || old.is_modified(new)
+    });
+    for file in &mut diff.added {
+        hydrate(file);
+    }
+    hydration
+}
+
+/// Read permissions and attributes of file
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn file_attributes(path: &Path, metadata: &Metadata, settings: &Settings) -> FileAttributes {
+    let mut attributes = FileAttributes {
+        is_readonly: Some(metadata.permissions().readonly()),
+        ..Default::default()
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        attributes.is_executable = Some(metadata.permissions().mode() & 0o111 != 0);
+        attributes.is_hidden = Some(
+            path.file_name()
+                .is_some_and(|x| x.to_string_lossy().starts_with('.')),
+        );
+        if settings.index_xattrs {
+            match xattr::list(path) {
+                Ok(names) => {
+                    attributes.xattrs =
+                        Some(names.map(|x| x.to_string_lossy().into_owned()).collect())
+                }
+                Err(e) => tracing::error!("Error reading extended attributes: {}", e),
+            }
+        }
+        if !settings.online_only_xattr.is_empty() {
+            match xattr::get(path, &settings.online_only_xattr) {
+                Ok(value) => attributes.is_online_only = Some(value.is_some()),
+                Err(e) => tracing::error!("Error reading extended attributes: {}", e),
+            }
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+        attributes.is_hidden =
+            Some(metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0);
+        attributes.is_online_only =
+            Some(metadata.file_attributes() & FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS != 0);
+    }
+
+    attributes
+}
+
 fn file_info_from_path(settings: &Settings, path: PathBuf) -> Option<FileInfo> {
     tracing::debug!("Scanning path: {}", path.display());
 
-    let metadata = match std::fs::metadata(&path) {
+    let metadata = match injector()
+        .metadata(&path)
+        .and_then(|_| std::fs::metadata(&path))
+    {
         Ok(x) => x,
         Err(e) => {
             tracing::error!("Error getting file metadata: {}", e);
@@ -150,15 +554,56 @@ fn file_info_from_path(settings: &Settings, path: PathBuf) -> Option<FileInfo> {
     if !metadata.file_type().is_file() {
         return None;
     }
+    let is_symlink = path.is_symlink();
+    if is_symlink && !settings.follow_symlinks {
+        return None;
+    }
 
+    let mut attributes = file_attributes(&path, &metadata, settings);
+    attributes.is_symlink = Some(is_symlink);
     Some(FileInfo::new(
         path,
         metadata.modified().unwrap_or_log().into(),
         metadata.len(),
+        attributes,
         settings,
     ))
 }
 
+/// Identity of file or directory that doesn't depend on path it's reached by
+#[cfg(unix)]
+type FileId = (u64, u64);
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+/// Device and inode on Unix, canonical path on other platforms
+fn file_id(entry: &DirEntry) -> Option<FileId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        entry.metadata().ok().map(|x| (x.dev(), x.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::canonicalize(entry.path()).ok()
+    }
+}
+
+/// Checks if file or directory is reached for the first time, ones without identity are
+/// always processed
+fn is_first_visit(visited: &RefCell<HashSet<FileId>>, entry: &DirEntry) -> bool {
+    match file_id(entry) {
+        Some(id) => visited.borrow_mut().insert(id),
+        None => true,
+    }
+}
+
+/// Recursively iterates directories and processes their files and subdirectories.
+/// When following symbolic links, files and directories reached by several paths are
+/// processed only once, and loops of links are skipped.
+/// Directories are walked by one thread, as parallel requests of metadata can overload
+/// network file systems
 pub fn process_indexable_files<T, F>(
     settings: &Settings,
     indexing_directories: &[IndexingDirectory],
@@ -173,55 +618,83 @@ where
         .iter()
         .map(|x| x.path.as_path())
         .collect();
-    let exclude_file_regex = Regex::new(&settings.exclude_file_regex)?;
+    let exclude_regexes = ExcludeRegexes::new(settings)?;
+    // Shared by all directories, as links can lead from one of them to another
+    let visited = RefCell::new(HashSet::new());
 
-    Ok(indexing_directories
+    let mut files = Vec::new();
+    for dir in indexing_directories
         .iter()
         .filter(|dir| !dir.exclude && (!exclude_non_watching || dir.watch))
-        .flat_map(|dir| {
-            WalkDir::new(&dir.path)
-                .into_iter()
-                .filter_entry(|e| {
-                    (e.path() == dir.path || !indexing_directories_hs.contains(e.path()))
-                        && (!e.path().is_file()
-                            || !exclude_file_regex.is_match(&e.path().to_string_lossy()))
-                })
-                .filter_map(|entry_res| {
-                    let entry = match entry_res {
-                        Ok(x) => x,
-                        Err(e) => {
-                            if allow_errors {
-                                tracing::debug!("Error while scanning file system: {}", e);
-                            } else {
-                                tracing::error!("Error while scanning file system: {}", e);
-                            }
-                            return None;
+    {
+        let start_time = Instant::now();
+        let dir_files: Vec<_> = WalkDir::new(&dir.path)
+            .follow_links(settings.follow_symlinks)
+            .into_iter()
+            .filter_entry(|e| {
+                (e.path() == dir.path || !indexing_directories_hs.contains(e.path()))
+                    // Paths of partial indexing can be inside of excluded directories
+                    && !in_excluded_directory(settings, e.path())
+                    // Type of entry is known from walking, without request of metadata
+                    && (!e.file_type().is_file()
+                        || !exclude_regexes.is_excluded(settings, e.path()))
+                    && (!settings.follow_symlinks || is_first_visit(&visited, e))
+            })
+            .filter_map(|entry_res| {
+                let entry = match entry_res {
+                    Ok(x) => x,
+                    Err(e) if e.loop_ancestor().is_some() => {
+                        tracing::debug!("Skipping loop of symbolic links: {}", e);
+                        return None;
+                    }
+                    Err(e) => {
+                        if allow_errors {
+                            tracing::debug!("Error while scanning file system: {}", e);
+                        } else {
+                            tracing::error!("Error while scanning file system: {}", e);
                         }
-                    };
+                        return None;
+                    }
+                };
 
-                    process(settings, entry.into_path())
-                })
-        })
-        .collect())
+                process(settings, entry.into_path())
+            })
+            .collect();
+
+        let duration = start_time.elapsed();
+        if duration >= SCAN_RATE_LOG_MIN_DURATION {
+            tracing::info!(
+                "Scanned {} files of {} in {:.1} s, {:.1} files/s",
+                dir_files.len(),
+                dir.path.display(),
+                duration.as_secs_f64(),
+                dir_files.len() as f64 / duration.as_secs_f64()
+            );
+        }
+        files.extend(dir_files);
+    }
+    Ok(files)
 }
 
 /// Recursively iterates list of directories and returns indexable files.
 /// Inaccessible files are skipped
 pub fn get_file_system_files_list(settings: &Settings) -> anyhow::Result<Vec<FileInfo>> {
-    process_indexable_files(
+    let mut files = process_indexable_files(
         settings,
         &settings.indexing_directories,
         file_info_from_path,
         false,
         false,
-    )
+    )?;
+    limit_hash_change_detection(settings, &mut files);
+    Ok(files)
 }
 
 pub fn get_file_system_partial_files_list(
     settings: &Settings,
     paths: Vec<PathBuf>,
 ) -> anyhow::Result<Vec<FileInfo>> {
-    process_indexable_files(
+    let mut files = process_indexable_files(
         settings,
         &paths
             .iter()
@@ -229,25 +702,120 @@ pub fn get_file_system_partial_files_list(
                 path: path.to_path_buf(),
                 exclude: false,
                 watch: true,
+                ..Default::default()
             })
             .collect::<Vec<_>>(),
         file_info_from_path,
         true,
         true,
-    )
+    )?;
+    limit_hash_change_detection(settings, &mut files);
+    Ok(files)
 }
 
-/// Returns all files from Elasticsearch index
-pub async fn get_elasticsearch_files_list(
+/// Open point in time for index
+pub async fn open_pit(
     es_client: &Elasticsearch,
-    paths: Option<&[PathBuf]>,
-) -> Result<Vec<FileInfo>, elasticsearch::Error> {
+    index: &str,
+    keep_alive: &str,
+) -> Result<String, elasticsearch::Error> {
     #[allow(clippy::upper_case_acronyms)]
-    #[derive(Serialize, Deserialize)]
+    #[derive(Deserialize)]
     struct PIT {
         id: String,
     }
 
+    let pit: PIT = es_client
+        .open_point_in_time(elasticsearch::OpenPointInTimeParts::Index(&[index]))
+        .keep_alive(keep_alive)
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(pit.id)
+}
+
+/// Checks if file can be zip archive by its extension
+pub fn is_zip_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("zip"))
+}
+
+/// Checks if search failed because point in time has expired
+fn is_pit_expired(response: &Value) -> bool {
+    let is_missing = |x: &Value| x["type"] == "search_context_missing_exception";
+    is_missing(&response["error"])
+        || response["error"]["root_cause"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(is_missing)
+}
+
+/// Prefix of paths of files inside of directory
+fn descendants_prefix(dir: &Path) -> String {
+    let dir = dir.display().to_string();
+    if dir.ends_with(is_separator) {
+        dir
+    } else {
+        format!("{dir}{MAIN_SEPARATOR}")
+    }
+}
+
+/// Removes documents of files outside of all given directories, such as files of indexing
+/// directories removed from settings, which partial indexing doesn't reach.
+/// Case-insensitive prefixes of keyword field are used, as lowercased subfield may be
+/// still backfilled in upgraded index. Returns number of removed documents
+pub async fn remove_outside_directories(
+    es_client: &Elasticsearch,
+    index: &str,
+    directories: &[PathBuf],
+    case_insensitive: bool,
+) -> Result<u64, elasticsearch::Error> {
+    let inside: Vec<_> = directories
+        .iter()
+        .map(|x| {
+            json!({
+                "prefix": {
+                    "path.keyword": {
+                        "value": descendants_prefix(x),
+                        "case_insensitive": case_insensitive
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let response: Value = es_client
+        .delete_by_query(DeleteByQueryParts::Index(&[index]))
+        .conflicts(Conflicts::Proceed)
+        .refresh(true)
+        .body(json!({
+            "query": {
+                "bool": {
+                    "must_not": inside
+                }
+            }
+        }))
+        .send()
+        .await?
+        .error_for_status_code()?
+        .json()
+        .await?;
+    Ok(response["deleted"].as_u64().unwrap_or_default())
+}
+
+/// Returns all files from Elasticsearch index, or files with given paths, files inside of them
+/// if they are directories, and entries of archives among them.
+/// Descendants are needed to remove files of deleted directories. Point in time is reopened if it expires, continuing from last received file.
+/// Case-insensitive paths are matched with lowercased subfield of path
+pub async fn get_elasticsearch_files_list(
+    es_client: &Elasticsearch,
+    index: &str,
+    paths: Option<&[PathBuf]>,
+    keep_alive_min: u32,
+    case_insensitive: bool,
+) -> anyhow::Result<Vec<FileInfo>> {
     #[derive(Serialize)]
     struct RequestBody {
         _source: Value,
@@ -258,25 +826,66 @@ pub async fn get_elasticsearch_files_list(
         search_after: Option<Vec<Value>>,
     }
 
-    let mut pit: PIT = es_client
-        .open_point_in_time(elasticsearch::OpenPointInTimeParts::Index(&[
-            ELASTICSEARCH_INDEX,
-        ]))
-        .keep_alive(ELASTICSEARCH_PIT_KEEP_ALIVE)
-        .send()
-        .await?
-        .json()
-        .await?;
+    // Keep-alive is renewed with each page request
+    let keep_alive = format!("{keep_alive_min}m");
+    let mut pit_id = open_pit(es_client, index, &keep_alive).await?;
+    let mut pit_renewals = 0;
     let mut search_after = None;
     let mut files = Vec::new();
+    let mut ids = HashSet::new();
+    let (path_field, paths) = if case_insensitive {
+        let paths = paths.map(|x| x.iter().map(|x| lowercase_path(x)).collect::<Vec<_>>());
+        ("path.lowercase", paths.map(Cow::Owned))
+    } else {
+        ("path.keyword", paths.map(Cow::Borrowed))
+    };
 
     loop {
-        let query = match paths {
-            Some(paths) => json!({
-                "terms": {
-                    "path.keyword": paths
-                }
-            }),
+        let query = match paths.as_deref() {
+            Some(paths) => {
+                let descendants = paths.iter().map(|x| {
+                    json!({
+                        "prefix": {
+                            path_field: descendants_prefix(x)
+                        }
+                    })
+                });
+                let archive_entries: Vec<_> = paths
+                    .iter()
+                    .filter(|x| is_zip_path(x))
+                    .map(|x| {
+                        json!({
+                            "prefix": {
+                                path_field: format!("{}{}", x.display(), ARCHIVE_ENTRY_SEPARATOR)
+                            }
+                        })
+                    })
+                    .collect();
+                let should: Vec<_> = [json!({
+                    "terms": {
+                        path_field: paths
+                    }
+                })]
+                .into_iter()
+                .chain(descendants)
+                .chain([json!({
+                    "bool": {
+                        "filter": {
+                            "term": {
+                                "in_archive": true
+                            }
+                        },
+                        "should": archive_entries,
+                        "minimum_should_match": 1
+                    }
+                })])
+                .collect();
+                json!({
+                    "bool": {
+                        "should": should
+                    }
+                })
+            }
             None => json!({
                 "match_all": {}
             }),
@@ -288,41 +897,503 @@ pub async fn get_elasticsearch_files_list(
             .track_total_hits(false)
             .body(RequestBody {
                 _source: json!({
-                    "includes": ["path", "modified", "size"]
+                    "includes": [
+                        "path", "modified", "size", "tags", "provenance", "hash", "is_online_only",
+                        "in_archive", "parse_failed"
+                    ]
                 }),
                 query,
                 pit: json!({
-                    "id": pit.id,
-                    "keep_alive": ELASTICSEARCH_PIT_KEEP_ALIVE
+                    "id": pit_id,
+                    "keep_alive": keep_alive
                 }),
-                sort: vec![json!({"_shard_doc": "asc"})],
-                search_after,
+                // Sorting by path allows to continue from the same file with new point in time
+                sort: vec![json!({"path.keyword": "asc"}), json!({"_shard_doc": "asc"})],
+                search_after: search_after.clone(),
             })
             .send()
             .await?
             .json()
             .await?;
 
+        if is_pit_expired(&response) {
+            if pit_renewals >= ELASTICSEARCH_PIT_MAX_RENEWALS {
+                return Err(anyhow!(
+                    "Point in time expired {} times while reading files list, \
+                     try increasing its keep-alive duration",
+                    pit_renewals + 1
+                ));
+            }
+            pit_renewals += 1;
+            tracing::warn!(
+                "Point in time expired while reading files list, reopening ({}/{})",
+                pit_renewals,
+                ELASTICSEARCH_PIT_MAX_RENEWALS
+            );
+            pit_id = open_pit(es_client, index, &keep_alive).await?;
+            // Shard document number is not valid for new point in time,
+            // files with the same path that were already received are skipped
+            if let Some(x) = &mut search_after {
+                x[1] = json!(-1);
+            }
+            continue;
+        }
+        if let Some(e) = response.get("error") {
+            return Err(anyhow!("Error reading files list: {}", e));
+        }
+
         let hits = response["hits"]["hits"].as_array().unwrap_or_log();
         if hits.is_empty() {
             break;
         }
-        pit.id = response["pit_id"].as_str().unwrap_or_log().to_owned();
+        pit_id = response["pit_id"].as_str().unwrap_or_log().to_owned();
         search_after = hits.last().unwrap_or_log()["sort"].as_array().cloned();
         let mut new_files: Vec<FileInfo> = hits
             .iter()
+            .filter(|x| ids.insert(x["_id"].as_str().unwrap_or_log().to_owned()))
             .map(|x| {
                 let mut val = x["_source"].to_owned();
                 val["_id"] = x["_id"].to_owned();
-                serde_json::from_value(val).unwrap_or_log()
+                FileInfo {
+                    case_insensitive_path: case_insensitive,
+                    ..serde_json::from_value(val).unwrap_or_log()
+                }
             })
             .collect();
+        let last_page = hits.len() < ELASTICSEARCH_MAX_SIZE as usize;
         files.append(&mut new_files);
-        if paths.is_some() {
+        if paths.is_some() && last_page {
             break;
         }
     }
-    es_client.close_point_in_time().body(pit).send().await?;
+    es_client
+        .close_point_in_time()
+        .body(json!({ "id": pit_id }))
+        .send()
+        .await?;
 
     Ok(files)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use common_lib::elasticsearch::ELASTICSEARCH_INDEX;
+
+    use crate::{
+        create_es_client,
+        test_util::{test_settings, MockElasticsearch},
+    };
+
+    use super::*;
+
+    /// Change of indexed file before it's scanned again
+    #[derive(Clone, Copy)]
+    enum ContentChange {
+        None,
+        SameSize,
+        Size,
+    }
+
+    /// Checks if file indexed with contents "abcd" is detected as modified
+    /// after given changes of its modification time and contents
+    fn is_detected(
+        change_detection: ChangeDetection,
+        hash_always: bool,
+        mtime_drift: bool,
+        content_change: ContentChange,
+    ) -> bool {
+        const HASH_CHUNK_SIZE: usize = 1024;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, "abcd").unwrap();
+        let old_hash = file_hash(&path, HashAlgorithm::Sha256, HASH_CHUNK_SIZE).unwrap();
+        let old: FileInfo = serde_json::from_value(json!({
+            "_id": "file",
+            "path": path,
+            "modified": 1000,
+            "size": 4,
+            "hash": old_hash
+        }))
+        .unwrap();
+
+        let contents = match content_change {
+            ContentChange::None => "abcd",
+            ContentChange::SameSize => "wxyz",
+            ContentChange::Size => "abcdef",
+        };
+        std::fs::write(&path, contents).unwrap();
+        let mut new = FileInfo {
+            _id: None,
+            modified: old.modified + chrono::Duration::hours(if mtime_drift { 1 } else { 0 }),
+            size: contents.len() as u64,
+            hash: None,
+            change_detection,
+            hash_always,
+            ..old.clone()
+        };
+        calculate_change_detection_hashes(
+            std::slice::from_ref(&old),
+            std::slice::from_mut(&mut new),
+            HashAlgorithm::Sha256,
+            HASH_CHUNK_SIZE,
+        );
+        old.is_modified(&new)
+    }
+
+    #[test]
+    fn modification_is_detected_by_mtime_and_size() {
+        use ContentChange::*;
+        let detection = ChangeDetection::MtimeSize;
+        assert!(!is_detected(detection, false, false, None));
+        // Contents aren't read, change that keeps size and time is missed
+        assert!(!is_detected(detection, false, false, SameSize));
+        assert!(is_detected(detection, false, false, Size));
+        assert!(is_detected(detection, false, true, None));
+        assert!(is_detected(detection, false, true, SameSize));
+        assert!(is_detected(detection, false, true, Size));
+    }
+
+    #[test]
+    fn modification_is_detected_by_hash_when_mtime_and_size_are_unchanged() {
+        use ContentChange::*;
+        let detection = ChangeDetection::Hash;
+        assert!(!is_detected(detection, false, false, None));
+        assert!(is_detected(detection, false, false, SameSize));
+        assert!(is_detected(detection, false, false, Size));
+        // Changed modification time is trusted without reading file
+        assert!(is_detected(detection, false, true, None));
+        assert!(is_detected(detection, false, true, SameSize));
+        assert!(is_detected(detection, false, true, Size));
+    }
+
+    #[test]
+    fn modification_time_drift_is_ignored_when_hash_is_always_compared() {
+        use ContentChange::*;
+        let detection = ChangeDetection::Hash;
+        assert!(!is_detected(detection, true, false, None));
+        assert!(is_detected(detection, true, false, SameSize));
+        assert!(is_detected(detection, true, false, Size));
+        assert!(!is_detected(detection, true, true, None));
+        assert!(is_detected(detection, true, true, SameSize));
+        assert!(is_detected(detection, true, true, Size));
+    }
+
+    /// File from index or file system with given path and modification time
+    fn file(path: &str, modified: i64, case_insensitive_path: bool) -> FileInfo {
+        FileInfo {
+            case_insensitive_path,
+            ..serde_json::from_value(json!({ "path": path, "modified": modified, "size": 1 }))
+                .unwrap()
+        }
+    }
+
+    /// Sorted paths of files
+    fn paths<'a>(files: impl IntoIterator<Item = &'a FileInfo>) -> Vec<&'a Path> {
+        let mut paths: Vec<_> = files.into_iter().map(|x| x.path.as_path()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn renaming_that_changes_case_is_modification_with_case_insensitive_paths() {
+        let old = vec![
+            file("/Docs/Photo.JPG", 1, true),
+            file("/docs/notes.txt", 1, true),
+            file("/docs/Removed.txt", 1, true),
+        ];
+        let new = vec![
+            file("/docs/photo.jpg", 1, true),
+            file("/docs/notes.txt", 1, true),
+            file("/docs/removed.txt.bak", 1, true),
+        ];
+        let diff = FilesDiff::from_vec_with(old, new, |_, _| false);
+
+        assert_eq!(paths(&diff.added), [Path::new("/docs/removed.txt.bak")]);
+        assert_eq!(paths(&diff.removed), [Path::new("/docs/Removed.txt")]);
+        assert_eq!(diff.modified.len(), 1);
+        let (old_file, new_file) = &diff.modified[0];
+        assert_eq!(old_file.path, Path::new("/Docs/Photo.JPG"));
+        assert_eq!(new_file.path, Path::new("/docs/photo.jpg"));
+    }
+
+    #[test]
+    fn paths_differing_in_case_are_different_files_with_case_sensitive_paths() {
+        let old = vec![
+            file("/Docs/Photo.JPG", 1, false),
+            file("/docs/a.txt", 1, false),
+        ];
+        let new = vec![
+            file("/docs/photo.jpg", 1, false),
+            file("/Docs/Photo.JPG", 1, false),
+            file("/docs/a.txt", 1, false),
+        ];
+        let diff = FilesDiff::from_vec_with(old, new, |_, _| false);
+
+        assert_eq!(paths(&diff.added), [Path::new("/docs/photo.jpg")]);
+        assert!(diff.removed.is_empty());
+        assert!(diff.modified.is_empty());
+    }
+
+    #[test]
+    fn non_ascii_paths_are_lowercased() {
+        assert_eq!(
+            lowercase_path(Path::new("/Фото/ÄRGER.Txt")),
+            Path::new("/фото/ärger.txt")
+        );
+        assert_eq!(
+            file("/Фото/ÄRGER.Txt", 1, true),
+            file("/фото/Ärger.txt", 2, true)
+        );
+        assert_ne!(
+            file("/Фото/ÄRGER.Txt", 1, false),
+            file("/фото/Ärger.txt", 1, false)
+        );
+    }
+
+    #[test]
+    fn entries_are_excluded_by_their_archive() {
+        let mut settings = test_settings();
+        settings.exclude_file_regex = r"\.zip$".to_owned();
+        let exclude_regexes = ExcludeRegexes::new(&settings).unwrap();
+        let mut entry = file("/files/a.zip!/b.txt", 1, false);
+        entry.in_archive = true;
+        assert!(is_excluded_file(&settings, &exclude_regexes, &entry));
+        // Name of directory can end with separator of entries
+        let file_in_directory = file("/files/a.zip!/b.txt", 1, false);
+        assert!(!is_excluded_file(
+            &settings,
+            &exclude_regexes,
+            &file_in_directory
+        ));
+    }
+
+    #[test]
+    fn file_is_hashed_in_chunks_of_bounded_size() {
+        const CHUNK_SIZE: usize = 4096;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.bin");
+        let contents: Vec<u8> = (0..1024 * 1024 + 123).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &contents).unwrap();
+
+        let mut chunks_cnt = 0;
+        let mut max_chunk_len = 0;
+        let mut total_len = 0;
+        read_chunks(&path, CHUNK_SIZE, |x| {
+            chunks_cnt += 1;
+            max_chunk_len = max_chunk_len.max(x.len());
+            total_len += x.len();
+        })
+        .unwrap();
+        assert!(max_chunk_len <= CHUNK_SIZE);
+        assert!(chunks_cnt > contents.len() / CHUNK_SIZE);
+        assert_eq!(total_len, contents.len());
+
+        // Hash doesn't depend on size of chunks
+        let hash_bytes: [u8; 32] = Sha256::digest(&contents).into();
+        let expected = Some(base16ct::lower::encode_string(&hash_bytes));
+        for chunk_size in [1000, CHUNK_SIZE, 2 * contents.len()] {
+            assert_eq!(
+                file_hash(&path, HashAlgorithm::Sha256, chunk_size).unwrap(),
+                expected
+            );
+        }
+    }
+
+    /// Maximum number of files of directory processed at once while walking it with given limit.
+    /// Checks that order of walking is kept
+    fn max_walking_concurrency(max_concurrency: Option<usize>) -> usize {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..16 {
+            std::fs::write(dir.path().join(format!("{i}.txt")), "").unwrap();
+        }
+        let mut settings = test_settings();
+        settings.max_concurrent_files = 8;
+        settings.indexing_directories = vec![IndexingDirectory {
+            path: dir.path().to_path_buf(),
+            max_concurrency,
+            ..Default::default()
+        }];
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        let paths = process_indexable_files(
+            &settings,
+            &settings.indexing_directories,
+            |_, path| {
+                let cnt = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(cnt, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(10));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Some(path)
+            },
+            false,
+            false,
+        )
+        .unwrap();
+
+        let walked: Vec<_> = WalkDir::new(dir.path())
+            .into_iter()
+            .map(|x| x.unwrap().into_path())
+            .collect();
+        assert_eq!(paths, walked);
+        max_running.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn walking_is_serial_with_or_without_concurrency_of_directory() {
+        // Limit of directory applies only to processing of its files
+        assert_eq!(max_walking_concurrency(Some(2)), 1);
+        assert_eq!(max_walking_concurrency(None), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn loops_of_symbolic_links_are_skipped() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+        std::fs::write(root.join("sub").join("b.txt"), "b").unwrap();
+        // Link to ancestor directory, self-referencing link and second path of the same file
+        symlink(&root, root.join("sub").join("loop")).unwrap();
+        symlink(root.join("self"), root.join("self")).unwrap();
+        symlink(root.join("a.txt"), root.join("alias.txt")).unwrap();
+
+        for follow_symlinks in [false, true] {
+            let mut settings = test_settings();
+            settings.follow_symlinks = follow_symlinks;
+            settings.indexing_directories = vec![IndexingDirectory {
+                path: root.clone(),
+                ..Default::default()
+            }];
+
+            let files = get_file_system_files_list(&settings).unwrap();
+
+            let mut names: Vec<_> = files
+                .iter()
+                .map(|x| x.path.strip_prefix(&root).unwrap().to_owned())
+                .collect();
+            names.sort();
+            // File reached by two paths is indexed once, by the path that is visited first
+            assert_eq!(names.len(), 2, "{names:?}");
+            assert!(names.contains(&Path::new("sub").join("b.txt")));
+            assert!(
+                names.contains(&PathBuf::from("a.txt"))
+                    || names.contains(&PathBuf::from("alias.txt"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn documents_outside_directories_are_removed_ignoring_case_of_paths() {
+        // Sibling directory with the same beginning of name isn't inside
+        let es = MockElasticsearch::with_documents(
+            [
+                "/Data/Docs/a.txt",
+                "/data/docs/b.txt",
+                "/Data/Docs2/c.txt",
+                "/other/d.txt",
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, path)| (i.to_string(), json!({ "path": path }))),
+        );
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        let es_client = create_es_client(&settings).await.unwrap();
+
+        let removed = remove_outside_directories(
+            &es_client,
+            ELASTICSEARCH_INDEX,
+            &[PathBuf::from("/data/docs")],
+            true,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(removed, 2);
+        let mut kept: Vec<_> = es
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| x["path"].as_str().unwrap().to_owned())
+            .collect();
+        kept.sort();
+        assert_eq!(kept, ["/Data/Docs/a.txt", "/data/docs/b.txt"]);
+    }
+
+    #[tokio::test]
+    async fn deleted_directory_removes_all_nested_files() {
+        let data = tempfile::tempdir().unwrap();
+        let deleted = data.path().join("dir");
+        let removed_paths = [
+            deleted.join("a.txt"),
+            deleted.join("sub").join("b.txt"),
+            deleted.join("sub").join("deeper").join("c.txt"),
+        ];
+        // Sibling directory with the same beginning of name and files outside are kept
+        let kept_paths = [
+            data.path().join("dir2").join("d.txt"),
+            data.path().join("dir.txt"),
+        ];
+        let es = MockElasticsearch::with_documents(
+            removed_paths
+                .iter()
+                .chain(&kept_paths)
+                .enumerate()
+                .map(|(i, path)| {
+                    let document = json!({ "path": path, "modified": 0, "size": 1 });
+                    (i.to_string(), document)
+                }),
+        );
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        let es_client = create_es_client(&settings).await.unwrap();
+
+        let changed = vec![deleted];
+        let file_system_files =
+            get_file_system_partial_files_list(&settings, changed.clone()).unwrap();
+        let es_files =
+            get_elasticsearch_files_list(&es_client, ELASTICSEARCH_INDEX, Some(&changed), 1, false)
+                .await
+                .unwrap();
+        let diff = FilesDiff::from_vec_with(es_files, file_system_files, |_, _| false);
+
+        let mut expected: Vec<_> = removed_paths.iter().map(PathBuf::as_path).collect();
+        expected.sort();
+        assert_eq!(paths(&diff.removed), expected);
+        assert!(diff.added.is_empty());
+        assert!(diff.modified.is_empty());
+    }
+
+    #[tokio::test]
+    async fn files_list_is_complete_when_point_in_time_expires() {
+        let cnt = 2 * ELASTICSEARCH_MAX_SIZE as usize + 100;
+        let mut es = MockElasticsearch::with_documents((0..cnt).map(|i| {
+            let path = format!("/files/{i:05}.txt");
+            (
+                format!("{i}"),
+                json!({ "path": path, "modified": 0, "size": 1 }),
+            )
+        }));
+        es.pit_expires_after = Some(2);
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        let es_client = create_es_client(&settings).await.unwrap();
+
+        let files = get_elasticsearch_files_list(&es_client, ELASTICSEARCH_INDEX, None, 1, false)
+            .await
+            .unwrap();
+
+        let ids: HashSet<_> = files.iter().map(|x| x._id.clone().unwrap()).collect();
+        assert_eq!(files.len(), cnt);
+        assert_eq!(ids.len(), cnt);
+        // Search after expiry and the final empty page
+        assert_eq!(es.searches.load(std::sync::atomic::Ordering::SeqCst), 5);
+    }
+}
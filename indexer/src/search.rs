@@ -1,16 +1,31 @@
-use std::{cmp::min, sync::Arc};
+use std::{cmp::min, sync::Arc, time::Instant};
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    body::{boxed, Body, Bytes},
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::Response,
+    Json,
+};
+use chrono::Utc;
 use common_lib::{
-    elasticsearch::{FileES, ELASTICSEARCH_INDEX, ELASTICSEARCH_MAX_SIZE},
+    elasticsearch::{
+        ContentLanguage, FileES, ELASTICSEARCH_INDEX, ELASTICSEARCH_MAX_SIZE,
+        FUTURE_MODIFIED_TOLERANCE_SECS,
+    },
     search::{
-        ContentTypeRequestItem, DocumentHighlightedFields, HighlightedFields,
-        ImageHighlightedFields, ImageQuery, MultimediaHighlightedFields, PageType, QueryType,
-        SearchRequest, SearchResponse, SearchResult, TextQuery,
+        is_leading_wildcard_glob, validate_path_glob, AlbumResult, ApiSearchParams,
+        ApiSearchResult, ContentTypeRequestItem, DocumentHighlightedFields, EmailHighlightedFields,
+        ExportRequest, HighlightedFields, ImageHighlightedFields, ImageQuery, KnnDebugInfo,
+        MultimediaHighlightedFields, PageType, QueryType, SearchRequest, SearchResponse,
+        SearchResult, SimilarRequest, SimilarityMode, SortOrder, SuggestionMode, TextQuery,
+        CONTENT_FRAGMENTS_MAX, QUERY_IMAGE_MAX_SIZE,
     },
+    settings::{FieldWeights, Settings},
     BatchRequest,
 };
-use elasticsearch::{Elasticsearch, SearchParts};
+use elasticsearch::{CountParts, Elasticsearch, GetParts, SearchParts};
+use metrics::histogram;
 use serde_json::{json, Value};
 use tracing_unwrap::{OptionExt, ResultExt};
 use url::Url;
@@ -18,24 +33,68 @@ use uuid::Uuid;
 
 use crate::{
     embeddings::{
-        get_image_search_image_embedding, get_image_search_text_embedding, get_rerank_scores,
-        get_text_search_embedding, Scores,
+        get_image_search_image_embedding, get_image_search_image_embedding_generic,
+        get_image_search_text_embedding, get_rerank_scores, get_text_search_embedding, Scores,
     },
+    indexer::create_index::CONTENT_LANGUAGE_FIELDS,
+    scanner::open_pit,
+    telemetry::SEARCH_DURATION,
     ServerState,
 };
 
-use self::query::{range, simple_query_string, suggest, term, terms};
+use self::{
+    complete::{get_completions, CompletionQuery},
+    query::{range, recency_boost, simple_query_string, sort, suggest, term, terms, wildcard},
+};
 
+mod api;
+mod complete;
+mod export;
 mod query;
 
 const ADJACENT_PAGES: u32 = 3;
+/// Number of pages of similar files
+const SIMILAR_PAGES: u32 = 10;
+/// Fields that have subfield analyzed with bigrams of CJK characters, if it's enabled
+const CJK_FIELDS: [&str; 2] = ["content", "title"];
+/// Characters for splitting highlighted fragments of unspaced text
+const CJK_BOUNDARY_CHARS: &str = ".,!?;: \t\n。．，、！？；：「」『』（）";
+/// Maximum size of body of search request, image of query is base64-encoded in it
+pub const SEARCH_REQUEST_MAX_SIZE: usize = QUERY_IMAGE_MAX_SIZE / 3 * 4 + 1024 * 1024;
+/// Patterns of path starting with wildcard are rejected for indexes with more documents,
+/// unless expensive queries are allowed in settings
+const LEADING_WILDCARD_MAX_DOCS: u64 = 100_000;
+/// Tag before highlighted match
+const HIGHLIGHT_PRE_TAG: &str = "<b>";
+/// Name of text query, it's in `matched_queries` of files that matched it
+const TEXT_QUERY_NAME: &str = "text";
+/// Matched field of files found only by kNN search
+const SEMANTIC_MATCH: &str = "semantic";
+/// Signatures of JPEG, PNG, GIF and BMP images
+const QUERY_IMAGE_SIGNATURES: [&[u8]; 4] = [b"\xFF\xD8\xFF", b"\x89PNG\r\n\x1A\n", b"GIF8", b"BM"];
 
-fn get_es_request_filter(search_request: &SearchRequest) -> Vec<Value> {
+/// Wildcard pattern of Elasticsearch for glob pattern of path. Its wildcards are the same,
+/// and backslashes of Windows paths are escaped to match themselves
+fn path_glob_wildcard(glob: &str) -> String {
+    glob.replace('\\', "\\\\")
+}
+
+fn get_es_request_filter(
+    search_request: &SearchRequest,
+    case_insensitive_paths: bool,
+) -> Vec<Value> {
     [
         search_request
             .path_prefix
             .as_ref()
             .map(|x| term("path.hierarchy", x.to_string_lossy().replace('\\', "/"))),
+        search_request.path_glob.as_ref().map(|x| {
+            wildcard(
+                "path.keyword",
+                path_glob_wildcard(x),
+                case_insensitive_paths,
+            )
+        }),
         search_request.content_type.as_ref().map(|v| {
             let mut include_type = Vec::new();
             let mut include_subtypes = Vec::new();
@@ -78,8 +137,41 @@ fn get_es_request_filter(search_request: &SearchRequest) -> Vec<Value> {
                 )
             },
         ),
+        search_request.modified_in_future.map(|x| {
+            let future_from = Utc::now().timestamp() + FUTURE_MODIFIED_TOLERANCE_SECS + 1;
+            if x {
+                range("modified", future_from, None::<i64>)
+            } else {
+                range("modified", None::<i64>, future_from - 1)
+            }
+        }),
         (search_request.size_from.is_some() || search_request.size_to.is_some())
             .then(|| range("size", search_request.size_from, search_request.size_to)),
+        (!search_request.tags.is_empty()).then(|| terms("tags", &search_request.tags)),
+        search_request
+            .is_executable
+            .map(|x| term("is_executable", x)),
+        search_request.is_readonly.map(|x| term("is_readonly", x)),
+        search_request.is_hidden.map(|x| term("is_hidden", x)),
+        search_request
+            .is_online_only
+            .map(|x| term("is_online_only", x)),
+        search_request.parse_failed.map(|x| term("parse_failed", x)),
+        search_request.language.map(|x| term("language", x)),
+        (!search_request.provenance.tika_versions.is_empty()).then(|| {
+            terms(
+                "provenance.tika_version",
+                &search_request.provenance.tika_versions,
+            )
+        }),
+        (!search_request.provenance.models.is_empty())
+            .then(|| terms("provenance.models", &search_request.provenance.models)),
+        (!search_request.provenance.parser_versions.is_empty()).then(|| {
+            terms(
+                "provenance.parser_version",
+                &search_request.provenance.parser_versions,
+            )
+        }),
         // Fields for image files
         (search_request.image_data.width_from.is_some()
             || search_request.image_data.width_to.is_some())
@@ -188,6 +280,43 @@ fn get_es_request_filter(search_request: &SearchRequest) -> Vec<Value> {
             .multimedia_data
             .audio_channel_type
             .map(|x| term("audio_channel_type", x)),
+        (search_request.multimedia_data.video_width_from.is_some()
+            || search_request.multimedia_data.video_width_to.is_some())
+        .then(|| {
+            range(
+                "video_width",
+                search_request.multimedia_data.video_width_from,
+                search_request.multimedia_data.video_width_to,
+            )
+        }),
+        (search_request.multimedia_data.video_height_from.is_some()
+            || search_request.multimedia_data.video_height_to.is_some())
+        .then(|| {
+            range(
+                "video_height",
+                search_request.multimedia_data.video_height_from,
+                search_request.multimedia_data.video_height_to,
+            )
+        }),
+        search_request
+            .multimedia_data
+            .album_exact
+            .as_ref()
+            .map(|x| {
+                if x.is_empty() {
+                    json!({
+                        "bool": {
+                            "must_not": {
+                                "exists": {
+                                    "field": "album.keyword"
+                                }
+                            }
+                        }
+                    })
+                } else {
+                    term("album.keyword", x)
+                }
+            }),
         // Fields for document files
         (search_request.document_data.doc_created_from.is_some()
             || search_request.document_data.doc_created_to.is_some())
@@ -246,13 +375,38 @@ fn get_es_request_filter(search_request: &SearchRequest) -> Vec<Value> {
                 search_request.document_data.num_characters_to,
             )
         }),
+        // Fields for email files
+        (search_request.email_data.email_sent_from.is_some()
+            || search_request.email_data.email_sent_to.is_some())
+        .then(|| {
+            range(
+                "email_sent",
+                search_request
+                    .email_data
+                    .email_sent_from
+                    .map(|d| d.timestamp()),
+                search_request
+                    .email_data
+                    .email_sent_to
+                    .map(|d| d.timestamp()),
+            )
+        }),
+        search_request
+            .email_data
+            .has_attachments
+            .map(|x| term("has_attachments", x)),
     ]
     .into_iter()
     .flatten()
     .collect()
 }
 
-fn get_es_request_must(search_request: &SearchRequest) -> Vec<Value> {
+fn get_es_request_must(
+    search_request: &SearchRequest,
+    field_weights: &FieldWeights,
+    cjk_analysis: bool,
+    content_languages: &[ContentLanguage],
+) -> Vec<Value> {
     let query_string = match search_request.query {
         QueryType::Text(TextQuery {
             ref query,
@@ -310,9 +464,41 @@ fn get_es_request_must(search_request: &SearchRequest) -> Vec<Value> {
                     .document_data
                     .creator_enabled
                     .then_some("creator"),
+                search_request
+                    .document_data
+                    .publisher_enabled
+                    .then_some("publisher"),
+                // Fields for email files
+                search_request.email_data.sender_enabled.then_some("sender"),
+                search_request
+                    .email_data
+                    .recipients_enabled
+                    .then_some("recipients"),
+                search_request
+                    .email_data
+                    .subject_enabled
+                    .then_some("subject"),
             ]
             .into_iter()
             .flatten()
+            .flat_map(|x| {
+                // Boost of request is multiplied by weight from settings
+                let boost = match x {
+                    "path" => search_request.path_boost.filter(|x| *x > 0.0),
+                    _ => None,
+                };
+                let weight = field_weights.get(x) * boost.unwrap_or(1.0);
+                let cjk_field =
+                    (cjk_analysis && CJK_FIELDS.contains(&x)).then(|| format!("{x}.cjk^{weight}"));
+                let language_fields = content_languages
+                    .iter()
+                    .filter(move |_| CONTENT_LANGUAGE_FIELDS.contains(&x))
+                    .map(move |language| format!("{x}.{language}^{weight}"));
+                [Some(format!("{x}^{weight}")), cjk_field]
+                    .into_iter()
+                    .flatten()
+                    .chain(language_fields)
+            })
             .collect::<Vec<_>>();
 
             if query_fields.is_empty() {
@@ -326,18 +512,210 @@ fn get_es_request_must(search_request: &SearchRequest) -> Vec<Value> {
     [query_string].into_iter().flatten().collect()
 }
 
+/// Embeddings of text query, kept while query has the same words.
+/// Embedding models are uncased, so case of query doesn't change them
+#[derive(Default)]
+struct QueryEmbeddings {
+    /// Lowercase words of query
+    words: Vec<String>,
+    text_search: Option<Vec<f32>>,
+    image_search: Option<Vec<f32>>,
+}
+
+impl QueryEmbeddings {
+    /// Forget embeddings if words of query changed
+    fn set_query(&mut self, query: &str) {
+        let words: Vec<_> = query.split_whitespace().map(str::to_lowercase).collect();
+        if words != self.words {
+            *self = Self {
+                words,
+                ..Default::default()
+            };
+        }
+    }
+}
+
+/// Phrase suggestion for text query from shingles of searched fields, content is preferred
+/// over path. `None` if neither of them is searched
+fn text_suggest(search_request: &SearchRequest, query: String) -> Option<Value> {
+    let QueryType::Text(text_query) = &search_request.query else {
+        return None;
+    };
+    let fields: Vec<_> = [
+        (text_query.content_enabled, "content", "content.shingles"),
+        (search_request.path_enabled, "path", "path.shingles"),
+    ]
+    .into_iter()
+    .filter(|(enabled, _, _)| *enabled)
+    .collect();
+    let (_, _, main_field) = fields.first()?;
+    let shingle_fields: Vec<_> = fields.iter().map(|(_, _, x)| *x).collect();
+    let collate_fields: Vec<_> = fields.iter().map(|(_, x, _)| *x).collect();
+    Some(suggest(query, main_field, &shingle_fields, &collate_fields))
+}
+
+/// Check pattern of path, patterns starting with wildcard are allowed only for small indexes
+/// or if expensive queries are allowed in settings
+async fn check_path_glob(
+    state: &ServerState,
+    search_request: &SearchRequest,
+) -> Result<(), (StatusCode, String)> {
+    let Some(glob) = &search_request.path_glob else {
+        return Ok(());
+    };
+    validate_path_glob(glob).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if !is_leading_wildcard_glob(glob) || state.settings.read().await.allow_expensive_queries {
+        return Ok(());
+    }
+
+    let doc_count = state
+        .es_client()
+        .await
+        .count(CountParts::Index(&[ELASTICSEARCH_INDEX]))
+        .send()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?["count"]
+        .as_u64()
+        .unwrap_or_default();
+    if doc_count > LEADING_WILDCARD_MAX_DOCS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Pattern of path starting with wildcard is too slow for index with {doc_count} \
+                 documents, enable allow_expensive_queries in settings to use it"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Check size and format of uploaded image of query before sending it to neural network server
+fn check_query_image(query: &QueryType) -> Result<(), (StatusCode, String)> {
+    let QueryType::Image(ImageQuery {
+        image_data: Some(data),
+        ..
+    }) = query
+    else {
+        return Ok(());
+    };
+    if data.len() > QUERY_IMAGE_MAX_SIZE {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Image of query is larger than {} MiB",
+                QUERY_IMAGE_MAX_SIZE / 1024 / 1024
+            ),
+        ));
+    }
+    // WebP is stored in RIFF container
+    let is_webp = data.starts_with(b"RIFF") && data.get(8..12) == Some(&b"WEBP"[..]);
+    if !is_webp && !QUERY_IMAGE_SIGNATURES.iter().any(|x| data.starts_with(x)) {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Unsupported format of image of query, supported are JPEG, PNG, GIF, BMP and WebP"
+                .to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Values of `k` and `num_candidates` of kNN search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KnnSize {
+    k: u32,
+    num_candidates: u32,
+}
+
+/// Size of kNN search for number of results. If filter matches few enough documents, all of
+/// them are candidates, so search inside small folder of large index isn't left with fewer
+/// than `k` candidates from the whole index
+fn knn_size(results: u32, knn_candidates_multiplier: u32, filtered_count: Option<u64>) -> KnnSize {
+    let max_size = ELASTICSEARCH_MAX_SIZE as u32;
+    let k = min(results, max_size);
+    let num_candidates = min(results * knn_candidates_multiplier, max_size);
+    match filtered_count {
+        // `k` can't be zero or greater than `num_candidates`
+        Some(count) if count <= u64::from(max_size) => {
+            let count = count.max(1) as u32;
+            KnnSize {
+                k: min(k, count),
+                num_candidates: count,
+            }
+        }
+        _ => KnnSize { k, num_candidates },
+    }
+}
+
+/// Checks if request uses kNN search with filter by path prefix
+fn is_knn_with_path_prefix(search_request: &SearchRequest) -> bool {
+    let knn_enabled = match &search_request.query {
+        QueryType::Text(x) => {
+            search_request.sort == SortOrder::Relevance
+                && !x.query.is_empty()
+                && (x.text_search_enabled || x.image_search_enabled)
+        }
+        QueryType::Image(_) => true,
+    };
+    knn_enabled && search_request.path_prefix.is_some()
+}
+
+/// Number of documents matching filters of request, counted only for kNN search limited by path
+/// prefix, other filters usually match large part of index
+async fn get_knn_filtered_count(
+    es_client: &Elasticsearch,
+    search_request: &SearchRequest,
+    case_insensitive_paths: bool,
+) -> Result<Option<u64>, elasticsearch::Error> {
+    if !is_knn_with_path_prefix(search_request) {
+        return Ok(None);
+    }
+
+    let count = es_client
+        .count(CountParts::Index(&[ELASTICSEARCH_INDEX]))
+        .body(json!({
+            "query": {
+                "bool": {
+                    "filter": get_es_request_filter(search_request, case_insensitive_paths)
+                }
+            }
+        }))
+        .send()
+        .await?
+        .error_for_status_code()?
+        .json::<Value>()
+        .await?["count"]
+        .as_u64();
+    Ok(count)
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn get_request_body(
     results_per_page: u32,
     reqwest_client: &reqwest_middleware::ClientWithMiddleware,
     nn_server_url: Url,
     knn_candidates_multiplier: u32,
+    recency_boost_scale_days: u32,
+    field_weights: &FieldWeights,
+    cjk_analysis: bool,
+    content_languages: &[ContentLanguage],
+    case_insensitive_paths: bool,
+    knn_filtered_count: Option<u64>,
     search_request: &SearchRequest,
+    embeddings: &mut QueryEmbeddings,
 ) -> anyhow::Result<Value> {
     let mut request_body = Value::Object(serde_json::Map::new());
     let mut request_body_knn = Vec::new();
 
-    let es_request_must = get_es_request_must(search_request);
-    let es_request_filter = get_es_request_filter(search_request);
+    let es_request_must = get_es_request_must(
+        search_request,
+        field_weights,
+        cjk_analysis,
+        content_languages,
+    );
+    let es_request_filter = get_es_request_filter(search_request, case_insensitive_paths);
 
     match search_request.query {
         QueryType::Text(TextQuery {
@@ -349,78 +727,100 @@ async fn get_request_body(
             query_coeff,
             text_search_coeff,
             image_search_coeff,
+            content_fragments,
             ..
         }) => {
-            if text_search_enabled && !query.is_empty() {
-                let text_search_embedding = get_text_search_embedding(
-                    reqwest_client,
-                    nn_server_url.clone(),
-                    BatchRequest { batched: false },
-                    query,
-                    false,
-                )
-                .await?;
+            embeddings.set_query(query);
+            // Semantic matches don't match query text, so they aren't mixed into sorted results
+            let knn_enabled = search_request.sort == SortOrder::Relevance && !query.is_empty();
 
-                let k = min(
+            if text_search_enabled && knn_enabled {
+                let text_search_embedding = match &embeddings.text_search {
+                    Some(x) => x.clone(),
+                    None => {
+                        get_text_search_embedding(
+                            reqwest_client,
+                            nn_server_url.clone(),
+                            BatchRequest { batched: false },
+                            query,
+                            false,
+                        )
+                        .await?
+                        .embedding
+                    }
+                };
+
+                let KnnSize { k, num_candidates } = knn_size(
                     results_per_page * text_search_pages,
-                    ELASTICSEARCH_MAX_SIZE as u32,
-                );
-                let num_candidates = min(
-                    results_per_page * text_search_pages * knn_candidates_multiplier,
-                    ELASTICSEARCH_MAX_SIZE as u32,
+                    knn_candidates_multiplier,
+                    knn_filtered_count,
                 );
                 request_body_knn.push(json!({
                     "field": "text_embedding",
-                    "query_vector": text_search_embedding.embedding,
+                    "query_vector": text_search_embedding,
                     "k": k,
                     "num_candidates": num_candidates,
                     "filter": es_request_filter,
                     "boost": text_search_coeff
                 }));
+                embeddings.text_search = Some(text_search_embedding);
             }
 
-            if image_search_enabled && !query.is_empty() {
-                let image_search_text_embedding = get_image_search_text_embedding(
-                    reqwest_client,
-                    nn_server_url,
-                    BatchRequest { batched: false },
-                    query,
-                )
-                .await?;
+            if image_search_enabled && knn_enabled {
+                let image_search_text_embedding = match &embeddings.image_search {
+                    Some(x) => x.clone(),
+                    None => {
+                        get_image_search_text_embedding(
+                            reqwest_client,
+                            nn_server_url,
+                            BatchRequest { batched: false },
+                            query,
+                        )
+                        .await?
+                        .embedding
+                    }
+                };
 
-                let k = min(
+                let KnnSize { k, num_candidates } = knn_size(
                     results_per_page * image_search_pages,
-                    ELASTICSEARCH_MAX_SIZE as u32,
-                );
-                let num_candidates = min(
-                    results_per_page * image_search_pages * knn_candidates_multiplier,
-                    ELASTICSEARCH_MAX_SIZE as u32,
+                    knn_candidates_multiplier,
+                    knn_filtered_count,
                 );
                 request_body_knn.push(json!({
                     "field": "image_embedding",
-                    "query_vector": image_search_text_embedding.embedding,
+                    "query_vector": image_search_text_embedding,
                     "k": k,
                     "num_candidates": num_candidates,
                     "filter": es_request_filter,
                     "boost": image_search_coeff
                 }));
+                embeddings.image_search = Some(image_search_text_embedding);
             }
 
-            request_body.as_object_mut().unwrap_or_log().insert(
-                "query".to_owned(),
-                json!({
-                    "bool": {
-                        "must": es_request_must,
-                        "filter": es_request_filter,
-                        "boost": query_coeff
-                    }
-                }),
-            );
+            let mut es_query = json!({
+                "bool": {
+                    "must": es_request_must,
+                    "filter": es_request_filter,
+                    "boost": query_coeff,
+                    "_name": TEXT_QUERY_NAME
+                }
+            });
+            // Function score can't wrap kNN clauses, so only text query is boosted
+            if let Some(boost) = search_request.recency_boost.filter(|x| *x > 0.0) {
+                let scale = i64::from(recency_boost_scale_days.max(1)) * 24 * 60 * 60;
+                es_query = recency_boost(es_query, boost, Utc::now().timestamp(), scale);
+            }
+            request_body
+                .as_object_mut()
+                .unwrap_or_log()
+                .insert("query".to_owned(), es_query);
 
+            // Zero fragments would highlight whole content
+            let content_fragments = content_fragments.clamp(1, CONTENT_FRAGMENTS_MAX);
             request_body.as_object_mut().unwrap_or_log().insert(
                 "highlight".to_owned(),
                 json!({
-                    "pre_tags": ["<b>"],
+                    "pre_tags": [HIGHLIGHT_PRE_TAG],
                     "post_tags": ["</b>"],
                     "encoder": "html",
                     "number_of_fragments": 0,
@@ -431,7 +831,7 @@ async fn get_request_body(
                         "content": {
                             "fragment_size": 300,
                             "no_match_size": 300,
-                            "number_of_fragments": 1
+                            "number_of_fragments": content_fragments
                         },
                         // Fields for image files
                         "image_make": {},
@@ -446,42 +846,81 @@ async fn get_request_body(
                         "release_date": {},
                         // Fields for document files
                         "title": {},
-                        "creator": {}
+                        "creator": {},
+                        "publisher": {},
+                        // Fields for email files
+                        "sender": {},
+                        "recipients": {},
+                        "subject": {}
                     }
                 }),
             );
 
-            request_body.as_object_mut().unwrap_or_log().insert(
-                "suggest".to_owned(),
-                suggest(
-                    query.clone(),
-                    "content.shingles",
-                    &["content.shingles", "path.shingles"],
-                ),
-            );
+            if cjk_analysis {
+                // Fast vector highlighter can split unspaced text at punctuation instead of words
+                let fields = &mut request_body["highlight"]["fields"];
+                fields["content.cjk"] = json!({
+                    "type": "fvh",
+                    "boundary_scanner": "chars",
+                    "boundary_chars": CJK_BOUNDARY_CHARS,
+                    "fragment_size": 300,
+                    "number_of_fragments": content_fragments
+                });
+                fields["title.cjk"] = json!({ "type": "fvh" });
+            }
+            // Subfields are highlighted only if they match, highlights of subfield of language
+            // of text are preferred
+            for language in content_languages {
+                let fields = &mut request_body["highlight"]["fields"];
+                fields[format!("content.{language}")] = json!({
+                    "fragment_size": 300,
+                    "number_of_fragments": content_fragments
+                });
+                fields[format!("title.{language}")] = json!({});
+            }
+
+            if search_request.suggestions == SuggestionMode::Always {
+                if let Some(suggest) = text_suggest(search_request, query.clone()) {
+                    request_body
+                        .as_object_mut()
+                        .unwrap_or_log()
+                        .insert("suggest".to_owned(), suggest);
+                }
+            }
         }
         QueryType::Image(ImageQuery {
             ref image_path,
+            ref image_data,
             image_search_pages,
         }) => {
-            let image_search_image_embedding = get_image_search_image_embedding(
-                reqwest_client,
-                nn_server_url,
-                BatchRequest { batched: false },
-                image_path,
-            )
-            .await?;
+            let image_search_image_embedding = match image_data {
+                Some(data) => {
+                    get_image_search_image_embedding_generic(
+                        reqwest_client,
+                        nn_server_url,
+                        BatchRequest { batched: false },
+                        data.clone(),
+                    )
+                    .await?
+                }
+                None => {
+                    get_image_search_image_embedding(
+                        reqwest_client,
+                        nn_server_url,
+                        BatchRequest { batched: false },
+                        image_path,
+                    )
+                    .await?
+                }
+            };
             let embedding = image_search_image_embedding
                 .embedding
                 .ok_or_else(|| anyhow::anyhow!("Incorrect image"))?;
 
-            let k = min(
+            let KnnSize { k, num_candidates } = knn_size(
                 results_per_page * image_search_pages,
-                ELASTICSEARCH_MAX_SIZE as u32,
-            );
-            let num_candidates = min(
-                results_per_page * image_search_pages * knn_candidates_multiplier,
-                ELASTICSEARCH_MAX_SIZE as u32,
+                knn_candidates_multiplier,
+                knn_filtered_count,
             );
             request_body_knn.push(json!({
                 "field": "image_embedding",
@@ -499,6 +938,77 @@ async fn get_request_body(
             .unwrap_or_log()
             .insert("knn".to_owned(), Value::Array(request_body_knn));
     }
+
+    // Nearest neighbors of image query are found first and then sorted.
+    // Scores aren't computed by default when sorting, but they are shown in results
+    if let Some(sort) = sort(search_request.sort) {
+        let request_body = request_body.as_object_mut().unwrap_or_log();
+        request_body.insert("sort".to_owned(), sort);
+        request_body.insert("track_scores".to_owned(), json!(true));
+    }
+
+    if search_request.group_albums() {
+        // Buckets up to the end of requested page are collected and then the page is cut out
+        request_body.as_object_mut().unwrap_or_log().insert(
+            "aggs".to_owned(),
+            json!({
+                "albums": {
+                    "terms": {
+                        "field": "album.keyword",
+                        "missing": "",
+                        "size": (search_request.page + 1) * results_per_page,
+                        "order": {
+                            "max_score": "desc"
+                        }
+                    },
+                    "aggs": {
+                        "max_score": {
+                            "max": {
+                                "script": "_score"
+                            }
+                        },
+                        "duration": {
+                            "sum": {
+                                "field": "duration"
+                            }
+                        },
+                        "top_file": {
+                            "top_hits": {
+                                "size": 1,
+                                "_source": {
+                                    "excludes": ["content", "text_embedding", "image_embedding"]
+                                }
+                            }
+                        },
+                        "page": {
+                            "bucket_sort": {
+                                "from": search_request.page * results_per_page,
+                                "size": results_per_page
+                            }
+                        }
+                    }
+                },
+                "album_cnt": {
+                    "cardinality": {
+                        "field": "album.keyword",
+                        "missing": ""
+                    }
+                }
+            }),
+        );
+    } else {
+        request_body.as_object_mut().unwrap_or_log().insert(
+            "aggs".to_owned(),
+            json!({
+                "languages": {
+                    "terms": {
+                        "field": "language",
+                        "size": ContentLanguage::ALL.len()
+                    }
+                }
+            }),
+        );
+    }
     Ok(request_body)
 }
 
@@ -519,6 +1029,50 @@ async fn get_es_response(
         .await
 }
 
+/// Get only phrase suggestion for text query, without search results
+async fn get_suggest_response(
+    es_client: &Elasticsearch,
+    suggest: Value,
+) -> Result<Value, elasticsearch::Error> {
+    es_client
+        .search(SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+        .size(0)
+        .body(json!({ "suggest": suggest }))
+        .send()
+        .await?
+        .json::<Value>()
+        .await
+}
+
+/// Fields with highlighted matches, subfields are merged into their fields.
+/// Fragments without matches are returned for content too, so only fragments with tags count.
+/// Files that didn't match text query were found only by kNN search
+fn get_matched_fields(result_value: &Value) -> Vec<String> {
+    let mut fields: Vec<_> = result_value["highlight"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, fragments)| {
+            fragments
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|x| x.as_str().is_some_and(|x| x.contains(HIGHLIGHT_PRE_TAG)))
+        })
+        .map(|(field, _)| field.split('.').next().unwrap_or_log().to_owned())
+        .collect();
+    fields.sort_unstable();
+    fields.dedup();
+
+    let text_matched = result_value["matched_queries"]
+        .as_array()
+        .is_some_and(|x| x.iter().any(|x| x == TEXT_QUERY_NAME));
+    if !text_matched {
+        fields.push(SEMANTIC_MATCH.to_owned());
+    }
+    fields
+}
+
 fn get_highlighted_field(result_value: &Value, field: &str, field_value: &str) -> String {
     result_value["highlight"][field].as_array().map_or_else(
         || html_escape::encode_text(field_value).to_string(),
@@ -534,6 +1088,53 @@ fn get_highlighted_optional_field(
     field_value.map(|field_val| get_highlighted_field(result_value, field, field_val))
 }
 
+/// Highlighted subfield or field itself, preferring CJK subfield and then subfield of language
+/// of text, if they have highlights
+fn get_highlighted_subfield(
+    result_value: &Value,
+    field: &str,
+    language: Option<ContentLanguage>,
+) -> String {
+    ["cjk".to_owned()]
+        .into_iter()
+        .chain(language.map(|x| x.to_string()))
+        .map(|x| format!("{field}.{x}"))
+        .find(|x| result_value["highlight"][x].is_array())
+        .unwrap_or_else(|| field.to_owned())
+}
+
+/// Get highlighted field, preferring highlights of its subfields if they exist
+fn get_highlighted_analyzed_field(
+    result_value: &Value,
+    field: &str,
+    language: Option<ContentLanguage>,
+    field_value: Option<&str>,
+) -> Option<String> {
+    let field = get_highlighted_subfield(result_value, field, language);
+    get_highlighted_optional_field(result_value, &field, field_value)
+}
+
+/// Get all highlighted fragments of field, preferring its subfields if they exist
+fn get_highlighted_analyzed_fragments(
+    result_value: &Value,
+    field: &str,
+    language: Option<ContentLanguage>,
+    field_value: Option<&str>,
+) -> Option<Vec<String>> {
+    let field_value = field_value?;
+    let field = get_highlighted_subfield(result_value, field, language);
+    let fragments = result_value["highlight"][&field].as_array();
+    Some(fragments.map_or_else(
+        || vec![html_escape::encode_text(field_value).to_string()],
+        |x| {
+            x.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect()
+        },
+    ))
+}
+
 async fn rerank_results(
     state: Arc<ServerState>,
     nn_server_url: Url,
@@ -605,10 +1206,16 @@ fn get_results(es_response_body: &Value) -> Vec<SearchResult> {
             let mut file_es: FileES =
                 serde_json::from_value(val["_source"].clone()).unwrap_or_log();
             file_es._id = Some(val["_id"].as_str().unwrap_or_log().to_owned());
+            let language = file_es.text_data.language;
             let highlights = HighlightedFields {
                 path: get_highlighted_field(val, "path", file_es.path.to_str().unwrap_or_log()),
                 hash: get_highlighted_optional_field(val, "hash", file_es.hash.as_deref()),
-                content: get_highlighted_optional_field(val, "content", file_es.content.as_deref()),
+                content: get_highlighted_analyzed_fragments(
+                    val,
+                    "content",
+                    language,
+                    file_es.content.as_deref(),
+                ),
                 summary: None,
                 image_data: ImageHighlightedFields {
                     image_make: get_highlighted_optional_field(
@@ -660,9 +1267,10 @@ fn get_results(es_response_body: &Value) -> Vec<SearchResult> {
                     ),
                 },
                 document_data: DocumentHighlightedFields {
-                    title: get_highlighted_optional_field(
+                    title: get_highlighted_analyzed_field(
                         val,
                         "title",
+                        language,
                         file_es.document_data.title.as_deref(),
                     ),
                     creator: get_highlighted_optional_field(
@@ -670,6 +1278,28 @@ fn get_results(es_response_body: &Value) -> Vec<SearchResult> {
                         "creator",
                         file_es.document_data.creator.as_deref(),
                     ),
+                    publisher: get_highlighted_optional_field(
+                        val,
+                        "publisher",
+                        file_es.document_data.publisher.as_deref(),
+                    ),
+                },
+                email_data: EmailHighlightedFields {
+                    sender: get_highlighted_optional_field(
+                        val,
+                        "sender",
+                        file_es.email_data.sender.as_deref(),
+                    ),
+                    recipients: get_highlighted_optional_field(
+                        val,
+                        "recipients",
+                        file_es.email_data.recipients.as_deref(),
+                    ),
+                    subject: get_highlighted_optional_field(
+                        val,
+                        "subject",
+                        file_es.email_data.subject.as_deref(),
+                    ),
                 },
             };
 
@@ -681,6 +1311,7 @@ fn get_results(es_response_body: &Value) -> Vec<SearchResult> {
             SearchResult {
                 file: file_es,
                 highlights,
+                matched_fields: get_matched_fields(val),
                 score,
                 id: Uuid::new_v4(),
             }
@@ -688,13 +1319,59 @@ fn get_results(es_response_body: &Value) -> Vec<SearchResult> {
         .collect()
 }
 
-fn get_pages(results_per_page: u32, es_response_body: &Value, page: u32) -> Vec<PageType> {
-    let total_pages = (es_response_body["hits"]["total"]["value"]
+/// Numbers of results with each detected language of text
+fn get_languages(es_response_body: &Value) -> Vec<(ContentLanguage, u64)> {
+    es_response_body["aggregations"]["languages"]["buckets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|x| Some((x["key"].as_str()?.parse().ok()?, x["doc_count"].as_u64()?)))
+        .collect()
+}
+
+fn get_albums(es_response_body: &Value) -> Vec<AlbumResult> {
+    es_response_body["aggregations"]["albums"]["buckets"]
+        .as_array()
+        .map(|buckets| {
+            buckets
+                .iter()
+                .map(|bucket| {
+                    let hit = &bucket["top_file"]["hits"]["hits"][0];
+                    let mut file_es: FileES =
+                        serde_json::from_value(hit["_source"].clone()).unwrap_or_log();
+                    file_es._id = Some(hit["_id"].as_str().unwrap_or_log().to_owned());
+
+                    AlbumResult {
+                        album: bucket["key"]
+                            .as_str()
+                            .filter(|x| !x.is_empty())
+                            .map(str::to_owned),
+                        file: file_es,
+                        track_cnt: bucket["doc_count"].as_u64().unwrap_or_log(),
+                        duration: bucket["duration"]["value"].as_f64().unwrap_or_default() as f32,
+                        id: Uuid::new_v4(),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn get_total_hits(es_response_body: &Value) -> u64 {
+    es_response_body["hits"]["total"]["value"]
+        .as_u64()
+        .unwrap_or_log()
+}
+
+/// Number of albums of all pages, approximate for large numbers
+fn get_album_cnt(es_response_body: &Value) -> u64 {
+    es_response_body["aggregations"]["album_cnt"]["value"]
         .as_u64()
-        .unwrap_or_log() as u32
-        + results_per_page
-        - 1)
-        / results_per_page;
+        .unwrap_or_default()
+}
+
+fn get_pages(results_per_page: u32, total: u64, page: u32) -> Vec<PageType> {
+    let total_pages = (total as u32).div_ceil(results_per_page);
 
     let mut pages = Vec::new();
     if page > 1 {
@@ -723,6 +1400,54 @@ fn get_pages(results_per_page: u32, es_response_body: &Value, page: u32) -> Vec<
     pages
 }
 
+/// Original and corrected text query and request with corrected query,
+/// if auto-correction is enabled, query has too few results and suggestion has matches
+fn get_corrected_request(
+    search_request: &SearchRequest,
+    es_response_body: &Value,
+    auto_correct_max_hits: u64,
+) -> Option<(String, String, SearchRequest)> {
+    let QueryType::Text(text_query) = &search_request.query else {
+        return None;
+    };
+    if !text_query.auto_correct || get_total_hits(es_response_body) >= auto_correct_max_hits {
+        return None;
+    }
+    let suggest_json = &es_response_body["suggest"]["simple_phrase"][0]["options"][0];
+    if suggest_json["collate_match"].as_bool() != Some(true) {
+        return None;
+    }
+    let corrected_query = suggest_json["text"].as_str()?.to_owned();
+    let corrected_request = SearchRequest {
+        query: QueryType::Text(TextQuery {
+            query: corrected_query.clone(),
+            ..text_query.clone()
+        }),
+        ..search_request.clone()
+    };
+    Some((text_query.query.clone(), corrected_query, corrected_request))
+}
+
+/// Checks if suggestion must be requested separately, for showing it or for auto-correction
+fn is_suggestion_needed(
+    search_request: &SearchRequest,
+    es_response_body: &Value,
+    auto_correct_max_hits: u64,
+) -> bool {
+    let QueryType::Text(text_query) = &search_request.query else {
+        return false;
+    };
+    if search_request.group_albums() {
+        return false;
+    }
+    let hits = get_total_hits(es_response_body);
+    match search_request.suggestions {
+        SuggestionMode::Always | SuggestionMode::Never => false,
+        mode => mode.is_shown(hits) || (text_query.auto_correct && hits < auto_correct_max_hits),
+    }
+}
+
+/// Suggestion from response, if it has suggest section
 fn get_suggestion(es_response_body: &Value) -> Option<(String, String)> {
     let suggest_json = &es_response_body["suggest"]["simple_phrase"][0]["options"][0];
     suggest_json["highlighted"].as_str().and_then(|highlight| {
@@ -732,44 +1457,722 @@ fn get_suggestion(es_response_body: &Value) -> Option<(String, String)> {
     })
 }
 
-pub async fn search(
-    State(state): State<Arc<ServerState>>,
-    Json(search_request): Json<SearchRequest>,
-) -> Result<Json<SearchResponse>, (StatusCode, String)> {
-    let (nn_server_url, results_per_page, knn_candidates_multiplier) = {
+/// Number of results per page from request or settings
+fn get_results_per_page(settings: &Settings, search_request: &SearchRequest) -> u32 {
+    search_request
+        .results_per_page
+        .map_or(settings.results_per_page, |x| {
+            x.clamp(1, settings.results_per_page_max.max(1))
+        })
+}
+
+/// Number of values of each embedding vector kept in request body returned for debugging
+const DEBUG_EMBEDDING_VALUES: usize = 4;
+
+/// Copy of request body for debugging, with embedding vectors truncated to keep response small
+fn get_debug_request_body(request_body: &Value) -> Value {
+    fn truncate_embeddings(value: &mut Value) {
+        match value {
+            Value::Object(x) => {
+                for (key, value) in x {
+                    match value {
+                        Value::Array(v) if key == "query_vector" => {
+                            v.truncate(DEBUG_EMBEDDING_VALUES)
+                        }
+                        _ => truncate_embeddings(value),
+                    }
+                }
+            }
+            Value::Array(x) => x.iter_mut().for_each(truncate_embeddings),
+            _ => {}
+        }
+    }
+
+    let mut request_body = request_body.clone();
+    truncate_embeddings(&mut request_body);
+    request_body
+}
+
+/// Sizes of kNN clauses of request body for debugging
+fn get_knn_debug_info(request_body: &Value, filtered_count: Option<u64>) -> KnnDebugInfo {
+    let clauses = request_body["knn"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|x| {
+            Some((
+                x["field"].as_str()?.to_owned(),
+                x["k"].as_u64()? as u32,
+                x["num_candidates"].as_u64()? as u32,
+            ))
+        })
+        .collect();
+    KnnDebugInfo {
+        filtered_count,
+        clauses,
+    }
+}
+
+/// Build request and get its response from Elasticsearch, whether kNN search was used,
+/// and request body and sizes of kNN search for debugging if it was requested
+async fn get_search_response(
+    state: &ServerState,
+    search_request: &SearchRequest,
+    embeddings: &mut QueryEmbeddings,
+) -> Result<(Value, bool, Option<(Value, KnnDebugInfo)>), (StatusCode, String)> {
+    let (
+        nn_server_url,
+        results_per_page,
+        knn_candidates_multiplier,
+        recency_boost_scale_days,
+        field_weights,
+        cjk_analysis,
+        content_languages,
+        case_insensitive_paths,
+        auto_correct_max_hits,
+    ) = {
         let tmp = state.settings.read().await;
         (
             tmp.nn_server_url.clone(),
-            tmp.results_per_page,
+            get_results_per_page(&tmp, search_request),
             tmp.knn_candidates_multiplier,
+            tmp.recency_boost_scale_days,
+            tmp.field_weights.clone(),
+            tmp.cjk_analysis,
+            tmp.content_analyzer_languages(),
+            tmp.case_insensitive_paths,
+            tmp.auto_correct_max_hits,
         )
     };
+    let knn_filtered_count = get_knn_filtered_count(
+        &state.es_client().await,
+        search_request,
+        case_insensitive_paths,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     let es_request_body = get_request_body(
         results_per_page,
         &state.reqwest_client,
-        nn_server_url.clone(),
+        nn_server_url,
         knn_candidates_multiplier,
-        &search_request,
+        recency_boost_scale_days,
+        &field_weights,
+        cjk_analysis,
+        &content_languages,
+        case_insensitive_paths,
+        knn_filtered_count,
+        search_request,
+        embeddings,
     )
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let es_response_body = get_es_response(
-        results_per_page,
-        &state.es_client,
+    let knn_used = es_request_body.get("knn").is_some();
+    let debug_info = search_request.debug.then(|| {
+        (
+            get_debug_request_body(&es_request_body),
+            get_knn_debug_info(&es_request_body, knn_filtered_count),
+        )
+    });
+    let mut es_response_body = get_es_response(
+        // Only aggregation results are needed when grouping by album
+        if search_request.group_albums() {
+            0
+        } else {
+            results_per_page
+        },
+        &state.es_client().await,
         search_request.page,
         es_request_body,
     )
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if is_suggestion_needed(search_request, &es_response_body, auto_correct_max_hits) {
+        let suggest = match &search_request.query {
+            QueryType::Text(text_query) => text_suggest(search_request, text_query.query.clone()),
+            QueryType::Image(_) => None,
+        };
+        if let Some(suggest) = suggest {
+            let mut suggest_response_body = get_suggest_response(&state.es_client().await, suggest)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            es_response_body["suggest"] = suggest_response_body["suggest"].take();
+        }
+    }
+    Ok((es_response_body, knn_used, debug_info))
+}
+
+/// Record duration of search request since its start
+fn record_search_duration(start: Instant, knn_used: bool, reranked: bool) {
+    histogram!(
+        SEARCH_DURATION,
+        start.elapsed().as_secs_f64(),
+        "knn" => knn_used.to_string(),
+        "rerank" => reranked.to_string()
+    );
+}
+
+/// Search with request, correcting query and reranking results if needed
+async fn get_search_results(
+    state: Arc<ServerState>,
+    search_request: SearchRequest,
+) -> Result<SearchResponse, (StatusCode, String)> {
+    check_query_image(&search_request.query)?;
+    check_path_glob(&state, &search_request).await?;
+    let (nn_server_url, results_per_page, auto_correct_max_hits) = {
+        let tmp = state.settings.read().await;
+        (
+            tmp.nn_server_url.clone(),
+            get_results_per_page(&tmp, &search_request),
+            tmp.auto_correct_max_hits,
+        )
+    };
+    let start = Instant::now();
+    let mut embeddings = QueryEmbeddings::default();
+    let (mut es_response_body, mut knn_used, mut debug_info) =
+        get_search_response(&state, &search_request, &mut embeddings).await?;
+
+    // Search again with corrected query, embeddings are reused if only case changed
+    let mut original_query = None;
+    let mut corrected_query = None;
+    let mut original_hits = None;
+    let search_request =
+        match get_corrected_request(&search_request, &es_response_body, auto_correct_max_hits) {
+            Some((original, corrected, corrected_request)) => {
+                original_hits = Some(get_total_hits(&es_response_body));
+                (es_response_body, knn_used, debug_info) =
+                    get_search_response(&state, &corrected_request, &mut embeddings).await?;
+                original_query = Some(original);
+                corrected_query = Some(corrected);
+                corrected_request
+            }
+            None => search_request,
+        };
+
+    // Duration of search in Elasticsearch, and total duration once results are ready
+    let es_took_ms = es_response_body["took"]
+        .as_u64()
+        .filter(|_| search_request.debug);
+    let took_ms = || {
+        search_request
+            .debug
+            .then(|| start.elapsed().as_millis() as u64)
+    };
+    let (es_request, knn) = debug_info.unzip();
+
+    if search_request.group_albums() {
+        record_search_duration(start, knn_used, false);
+        return Ok(SearchResponse {
+            albums: get_albums(&es_response_body),
+            pages: get_pages(
+                results_per_page,
+                get_album_cnt(&es_response_body),
+                search_request.page,
+            ),
+            original_query,
+            corrected_query,
+            original_hits,
+            es_request,
+            took_ms: took_ms(),
+            es_took_ms,
+            knn,
+            ..Default::default()
+        });
+    }
     let mut results = get_results(&es_response_body);
-    results = rerank_results(state, nn_server_url, &search_request.query, results)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let pages = get_pages(results_per_page, &es_response_body, search_request.page);
-    let suggestion = get_suggestion(&es_response_body);
-    Ok(Json(SearchResponse {
+    // Reranking would break sort order
+    let reranked = search_request.sort == SortOrder::Relevance
+        && matches!(
+            &search_request.query,
+            QueryType::Text(x) if x.reranking_enabled && !x.query.is_empty()
+        );
+    if search_request.sort == SortOrder::Relevance {
+        results = rerank_results(state, nn_server_url, &search_request.query, results)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    let pages = get_pages(
+        results_per_page,
+        get_total_hits(&es_response_body),
+        search_request.page,
+    );
+    let suggestion = search_request
+        .suggestions
+        .is_shown(get_total_hits(&es_response_body))
+        .then(|| get_suggestion(&es_response_body))
+        .flatten();
+    record_search_duration(start, knn_used, reranked);
+    Ok(SearchResponse {
         results,
+        albums: Vec::new(),
         pages,
         suggestion,
+        original_query,
+        corrected_query,
+        original_hits,
+        languages: get_languages(&es_response_body),
+        es_request,
+        took_ms: took_ms(),
+        es_took_ms,
+        knn,
+    })
+}
+
+pub async fn search(
+    State(state): State<Arc<ServerState>>,
+    Json(search_request): Json<SearchRequest>,
+) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    get_search_results(state, search_request).await.map(Json)
+}
+
+/// Search with simple query parameters, for scripts
+pub async fn api_search(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<ApiSearchParams>,
+) -> Result<Json<Vec<ApiSearchResult>>, (StatusCode, String)> {
+    let search_request = api::search_request(&params, &state.settings.read().await.nn_server);
+    let response = get_search_results(state, search_request).await?;
+    Ok(Json(
+        response
+            .results
+            .into_iter()
+            .map(api::search_result)
+            .collect(),
+    ))
+}
+
+/// Find files with nearest stored embedding of chosen type, excluding source document
+pub async fn similar(
+    State(state): State<Arc<ServerState>>,
+    Json(similar_request): Json<SimilarRequest>,
+) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    let (results_per_page, knn_candidates_multiplier) = {
+        let tmp = state.settings.read().await;
+        (tmp.results_per_page, tmp.knn_candidates_multiplier)
+    };
+    let field = match similar_request.mode {
+        SimilarityMode::Image => "image_embedding",
+        SimilarityMode::Text => "text_embedding",
+    };
+
+    let mut es_document = state
+        .es_client()
+        .await
+        .get(GetParts::IndexId(ELASTICSEARCH_INDEX, &similar_request.id))
+        ._source(&[field])
+        .send()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if es_document["found"].as_bool() != Some(true) {
+        return Err((StatusCode::NOT_FOUND, "Document not found".to_owned()));
+    }
+    let embedding = es_document["_source"][field].take();
+    if !embedding.is_array() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Document has no {field}, it wasn't processed by this neural network"),
+        ));
+    }
+
+    let k = min(
+        results_per_page * SIMILAR_PAGES,
+        ELASTICSEARCH_MAX_SIZE as u32,
+    );
+    let num_candidates = min(k * knn_candidates_multiplier, ELASTICSEARCH_MAX_SIZE as u32);
+    let es_request_body = json!({
+        "knn": {
+            "field": field,
+            "query_vector": embedding,
+            "k": k,
+            "num_candidates": num_candidates,
+            "filter": {
+                "bool": {
+                    "must_not": {
+                        "ids": {
+                            "values": [similar_request.id]
+                        }
+                    }
+                }
+            }
+        }
+    });
+    let es_response_body = get_es_response(
+        results_per_page,
+        &state.es_client().await,
+        similar_request.page,
+        es_request_body,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(SearchResponse {
+        results: get_results(&es_response_body),
+        pages: get_pages(
+            results_per_page,
+            get_total_hits(&es_response_body),
+            similar_request.page,
+        ),
+        ..Default::default()
     }))
 }
+
+/// Complete query while it's typed, without semantic search and highlighting
+pub async fn complete(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<CompletionQuery>,
+) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    get_completions(&state.es_client().await, &query)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Export results of search from all pages as downloadable file, without reranking.
+/// Errors after the first page abort the download
+pub async fn export(
+    State(state): State<Arc<ServerState>>,
+    Json(export_request): Json<ExportRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    check_query_image(&export_request.search_request.query)?;
+    check_path_glob(&state, &export_request.search_request).await?;
+    let (
+        nn_server_url,
+        results_per_page,
+        knn_candidates_multiplier,
+        recency_boost_scale_days,
+        field_weights,
+        cjk_analysis,
+        content_languages,
+        case_insensitive_paths,
+        pit_keep_alive,
+    ) = {
+        let tmp = state.settings.read().await;
+        (
+            tmp.nn_server_url.clone(),
+            get_results_per_page(&tmp, &export_request.search_request),
+            tmp.knn_candidates_multiplier,
+            tmp.recency_boost_scale_days,
+            tmp.field_weights.clone(),
+            tmp.cjk_analysis,
+            tmp.content_analyzer_languages(),
+            tmp.case_insensitive_paths,
+            tmp.elasticsearch_pit_keep_alive,
+        )
+    };
+    let knn_filtered_count = get_knn_filtered_count(
+        &state.es_client().await,
+        &export_request.search_request,
+        case_insensitive_paths,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let request_body = get_request_body(
+        results_per_page,
+        &state.reqwest_client,
+        nn_server_url,
+        knn_candidates_multiplier,
+        recency_boost_scale_days,
+        &field_weights,
+        cjk_analysis,
+        &content_languages,
+        case_insensitive_paths,
+        knn_filtered_count,
+        &export_request.search_request,
+        &mut QueryEmbeddings::default(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let keep_alive = format!("{pit_keep_alive}m");
+    let pit_id = open_pit(&state.es_client().await, ELASTICSEARCH_INDEX, &keep_alive)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let max_results = export_request
+        .max_results
+        .map_or(export::EXPORT_MAX_RESULTS, |x| {
+            x.min(export::EXPORT_MAX_RESULTS)
+        });
+    let mut pages = export::ExportPages::new(
+        export::export_request_body(request_body),
+        pit_id,
+        keep_alive,
+        max_results,
+    );
+
+    // Errors of request are returned before starting download
+    let format = export_request.format;
+    let first_page = match pages.next(&state.es_client().await).await {
+        Ok(x) => x,
+        Err(e) => {
+            pages.close(&state.es_client().await).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let res: anyhow::Result<()> = async {
+            sender
+                .send_data(export::format_start(format)?.into())
+                .await?;
+            let mut page = first_page;
+            let mut written = 0;
+            while !page.is_empty() {
+                sender
+                    .send_data(export::format_page(format, &page, written)?.into())
+                    .await?;
+                written += page.len();
+                page = pages.next(&state.es_client().await).await?;
+            }
+            sender
+                .send_data(Bytes::from_static(export::format_end(format)))
+                .await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = res {
+            tracing::error!("Error exporting search results: {}", e);
+            sender.abort();
+        }
+        pages.close(&state.es_client().await).await;
+    });
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, export::content_type(format))
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"search_results.{format}\""),
+        )
+        .body(boxed(body))
+        .unwrap_or_log())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use common_lib::{
+        indexer::IndexingStatus, search::ApiSearchParams, settings::NNServerSettings,
+    };
+
+    use crate::{
+        create_es_client,
+        indexer::{indexing_process, IndexingTarget},
+        test_util::{indexing_test_state, matches, test_settings, MockElasticsearch},
+    };
+
+    use super::*;
+
+    #[test]
+    fn path_boost_of_request_is_multiplied_by_field_weight() {
+        let params = ApiSearchParams {
+            q: "report".to_owned(),
+            path_boost: Some(2.0),
+            ..Default::default()
+        };
+        let search_request = api::search_request(&params, &NNServerSettings::default());
+        let field_weights = FieldWeights(BTreeMap::from([
+            ("path".to_owned(), 1.5),
+            ("content".to_owned(), 0.5),
+        ]));
+
+        let must = get_es_request_must(&search_request, &field_weights, false, &[]);
+
+        assert_eq!(
+            must,
+            [json!({
+                "simple_query_string": {
+                    "query": "report",
+                    "fields": ["path^3", "hash^1", "content^0.5"]
+                }
+            })]
+        );
+    }
+
+    #[test]
+    fn album_pages_are_counted_from_number_of_albums() {
+        let es_response_body = json!({
+            "hits": { "total": { "value": 500 } },
+            "aggregations": { "album_cnt": { "value": 25 } }
+        });
+
+        let pages = get_pages(10, get_album_cnt(&es_response_body), 1);
+
+        assert_eq!(
+            pages,
+            [
+                PageType::Previous(0),
+                PageType::Other(0),
+                PageType::Current(1),
+                PageType::Other(2),
+                PageType::Next(2)
+            ]
+        );
+    }
+
+    /// Request for text query "report" with given fields searched
+    fn suggest_request(content_enabled: bool, path_enabled: bool) -> SearchRequest {
+        let params = ApiSearchParams {
+            q: "report".to_owned(),
+            ..Default::default()
+        };
+        let mut search_request = api::search_request(&params, &NNServerSettings::default());
+        if let QueryType::Text(text_query) = &mut search_request.query {
+            text_query.content_enabled = content_enabled;
+        }
+        search_request.path_enabled = path_enabled;
+        search_request
+    }
+
+    #[test]
+    fn suggestion_prefers_content_and_collates_with_all_searched_fields() {
+        let suggest = text_suggest(&suggest_request(true, true), "report".to_owned()).unwrap();
+
+        let phrase = &suggest["simple_phrase"]["phrase"];
+        assert_eq!(phrase["field"], "content.shingles");
+        assert_eq!(
+            phrase["direct_generator"],
+            json!([
+                { "field": "content.shingles", "suggest_mode": "missing" },
+                { "field": "path.shingles", "suggest_mode": "missing" }
+            ])
+        );
+        assert_eq!(
+            phrase["collate"]["query"]["source"]["multi_match"]["fields"],
+            json!(["content", "path"])
+        );
+    }
+
+    #[test]
+    fn suggestion_uses_only_path_when_content_is_not_searched() {
+        let suggest = text_suggest(&suggest_request(false, true), "report".to_owned()).unwrap();
+
+        let phrase = &suggest["simple_phrase"]["phrase"];
+        assert_eq!(phrase["field"], "path.shingles");
+        assert_eq!(
+            phrase["direct_generator"],
+            json!([{ "field": "path.shingles", "suggest_mode": "missing" }])
+        );
+        assert_eq!(
+            phrase["collate"]["query"]["source"]["multi_match"]["fields"],
+            json!(["path"])
+        );
+    }
+
+    #[test]
+    fn no_suggestion_when_neither_content_nor_path_is_searched() {
+        assert_eq!(
+            text_suggest(&suggest_request(false, false), "report".to_owned()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn file_modified_in_future_is_indexed_and_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.txt");
+        std::fs::write(&path, "text").unwrap();
+        let year_ahead = SystemTime::now() + Duration::from_secs(365 * 24 * 60 * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(year_ahead)
+            .unwrap();
+        let es = MockElasticsearch::default();
+        let state = indexing_test_state(dir.path(), &es).await;
+
+        indexing_process(Arc::clone(&state), None, IndexingTarget::Current, None).await;
+
+        let IndexingStatus::Finished(data) = &*state.indexing_status.read().await else {
+            panic!("Indexing didn't finish");
+        };
+        assert_eq!(data.modified_in_future, 1);
+        assert_eq!(data.errors_cnt, 0);
+        let documents = es.documents.lock().unwrap();
+        let document = documents.values().next().unwrap();
+        assert_eq!(document["path"], json!(path));
+
+        let is_found = |modified_in_future, modified_from| {
+            let search_request = SearchRequest {
+                modified_in_future,
+                modified_from,
+                ..api::search_request(&Default::default(), &Default::default())
+            };
+            let filter = get_es_request_filter(&search_request, false);
+            matches(&json!({ "bool": { "filter": filter } }), document)
+        };
+        assert!(is_found(None, None));
+        assert!(is_found(Some(true), None));
+        assert!(!is_found(Some(false), None));
+        // Relative filters have no upper bound, so files from future aren't excluded
+        assert!(is_found(
+            None,
+            Some(Utc::now() - chrono::Duration::days(30))
+        ));
+    }
+
+    #[test]
+    fn knn_size_is_limited_by_count_of_filtered_documents() {
+        let size = |k, num_candidates| KnnSize { k, num_candidates };
+        assert_eq!(knn_size(20, 10, None), size(20, 200));
+        // All documents of small folder are candidates, even more than by default
+        assert_eq!(knn_size(20, 10, Some(5)), size(5, 5));
+        assert_eq!(knn_size(20, 10, Some(1000)), size(20, 1000));
+        assert_eq!(knn_size(20, 10, Some(0)), size(1, 1));
+        let too_many = ELASTICSEARCH_MAX_SIZE as u64 + 1;
+        assert_eq!(knn_size(20, 10, Some(too_many)), size(20, 200));
+    }
+
+    #[tokio::test]
+    async fn knn_search_inside_folder_uses_count_of_its_documents() {
+        let es = MockElasticsearch::with_documents(
+            [
+                "/data/photos/a.jpg",
+                "/data/photos/trip/b.jpg",
+                "/data/photos2/c.jpg",
+                "/data/d.jpg",
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, path)| (i.to_string(), json!({ "path": path }))),
+        );
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        let es_client = create_es_client(&settings).await.unwrap();
+        let params = ApiSearchParams {
+            q: "mountains".to_owned(),
+            ..Default::default()
+        };
+        let mut search_request = api::search_request(&params, &NNServerSettings::default());
+        if let QueryType::Text(text_query) = &mut search_request.query {
+            text_query.text_search_enabled = true;
+        }
+
+        let count = get_knn_filtered_count(&es_client, &search_request, false)
+            .await
+            .unwrap();
+        assert_eq!(count, None);
+
+        search_request.path_prefix = Some(PathBuf::from("/data/photos"));
+        let count = get_knn_filtered_count(&es_client, &search_request, false)
+            .await
+            .unwrap();
+        assert_eq!(count, Some(2));
+        assert_eq!(
+            knn_size(20, 10, count),
+            KnnSize {
+                k: 2,
+                num_candidates: 2
+            }
+        );
+    }
+}
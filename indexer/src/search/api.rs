@@ -0,0 +1,186 @@
+use common_lib::{
+    search::{
+        ApiSearchParams, ApiSearchResult, ContentTypeRequestItem, QueryType, SearchRequest,
+        SearchResult, SortOrder, SuggestionMode, TextQuery,
+    },
+    settings::NNServerSettings,
+};
+
+/// Content type filter from comma-separated types and subtypes
+fn content_type_filter(content_type: &str) -> Option<Vec<ContentTypeRequestItem>> {
+    let (subtypes, types): (Vec<_>, Vec<_>) = content_type
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .partition(|x| x.contains('/'));
+
+    let mut items: Vec<_> = types
+        .into_iter()
+        .map(|x| ContentTypeRequestItem::IncludeType {
+            type_: x.to_owned(),
+        })
+        .collect();
+    if !subtypes.is_empty() {
+        items.push(ContentTypeRequestItem::IncludeSubtypes {
+            subtypes: subtypes.into_iter().map(str::to_owned).collect(),
+        });
+    }
+    (!items.is_empty()).then_some(items)
+}
+
+/// Build search request from simple query parameters, with defaults of web interface.
+/// Semantic search uses only neural networks enabled in settings
+pub fn search_request(params: &ApiSearchParams, nn_server: &NNServerSettings) -> SearchRequest {
+    SearchRequest {
+        page: params.page,
+        results_per_page: params.per_page,
+        query: QueryType::Text(TextQuery {
+            query: params.q.clone(),
+            content_enabled: true,
+            text_search_enabled: params.semantic && nn_server.text_search_enabled,
+            image_search_enabled: params.semantic && nn_server.image_search_enabled,
+            reranking_enabled: params.semantic && nn_server.reranking_enabled,
+            text_search_pages: 1,
+            image_search_pages: 1,
+            query_coeff: 1.0,
+            text_search_coeff: 7.5,
+            image_search_coeff: 7.5,
+            reranking_coeff: 1.1,
+            auto_correct: false,
+            content_fragments: 1,
+        }),
+        path_prefix: params.path_prefix.clone(),
+        path_glob: params.path_glob.clone(),
+        content_type: params.content_type.as_deref().and_then(content_type_filter),
+        path_enabled: true,
+        hash_enabled: true,
+        modified_from: params.modified_from,
+        modified_to: params.modified_to,
+        modified_in_future: None,
+        size_from: params.size_from,
+        size_to: params.size_to,
+        tags: Vec::new(),
+        is_executable: None,
+        is_readonly: None,
+        is_hidden: None,
+        is_online_only: None,
+        parse_failed: None,
+        language: None,
+        provenance: Default::default(),
+        sort: SortOrder::Relevance,
+        recency_boost: None,
+        path_boost: params.path_boost,
+        // Suggestion isn't returned
+        suggestions: SuggestionMode::Never,
+        debug: false,
+        image_data: Default::default(),
+        multimedia_data: Default::default(),
+        document_data: Default::default(),
+        email_data: Default::default(),
+    }
+}
+
+/// Trim search result to basic fields
+pub fn search_result(result: SearchResult) -> ApiSearchResult {
+    ApiSearchResult {
+        path: result.file.path,
+        score: result.score,
+        content_type: result.file.content_type,
+        modified: result.file.modified,
+        size: result.file.size,
+        highlight: result.highlights.content.map(|x| x.join(" … ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Query, http::Uri};
+
+    use super::*;
+
+    /// Search request built from query string of request to search API
+    fn request_from_query(uri: &str, nn_server: &NNServerSettings) -> SearchRequest {
+        let Query(params) =
+            Query::<ApiSearchParams>::try_from_uri(&uri.parse::<Uri>().unwrap()).unwrap();
+        search_request(&params, nn_server)
+    }
+
+    #[test]
+    fn request_has_defaults_of_web_interface() {
+        let request = request_from_query("/api/search?q=report", &NNServerSettings::default());
+
+        let QueryType::Text(text_query) = &request.query else {
+            panic!("Query isn't text");
+        };
+        assert_eq!(text_query.query, "report");
+        assert!(text_query.content_enabled);
+        // Semantic search is requested explicitly
+        assert!(!text_query.text_search_enabled);
+        assert!(!text_query.image_search_enabled);
+        assert!(!text_query.reranking_enabled);
+        assert_eq!((request.page, request.results_per_page), (0, None));
+        assert!(request.path_enabled && request.hash_enabled);
+        assert_eq!(request.sort, SortOrder::Relevance);
+        assert_eq!(request.suggestions, SuggestionMode::Never);
+        assert_eq!(request.content_type, None);
+        assert_eq!(request.path_prefix, None);
+        assert_eq!(request.modified_from, None);
+        assert_eq!(request.size_from, None);
+        assert!(request.tags.is_empty());
+    }
+
+    #[test]
+    fn request_has_filters_from_query_parameters() {
+        let nn_server = NNServerSettings {
+            image_search_enabled: false,
+            ..Default::default()
+        };
+        let request = request_from_query(
+            "/api/search?q=cat&page=2&per_page=5&path_prefix=/docs\
+             &content_type=image,%20application/pdf,&modified_from=2023-01-02T03:04:05Z\
+             &size_from=10&size_to=20&semantic=true",
+            &nn_server,
+        );
+
+        let QueryType::Text(text_query) = &request.query else {
+            panic!("Query isn't text");
+        };
+        // Only neural networks enabled in settings are used
+        assert!(text_query.text_search_enabled);
+        assert!(!text_query.image_search_enabled);
+        assert!(text_query.reranking_enabled);
+        assert_eq!((request.page, request.results_per_page), (2, Some(5)));
+        assert_eq!(request.path_prefix, Some("/docs".into()));
+        assert_eq!(
+            request.content_type,
+            Some(vec![
+                ContentTypeRequestItem::IncludeType {
+                    type_: "image".to_owned()
+                },
+                ContentTypeRequestItem::IncludeSubtypes {
+                    subtypes: vec!["application/pdf".to_owned()]
+                },
+            ])
+        );
+        assert_eq!(
+            request.modified_from.map(|x| x.timestamp()),
+            Some(1672628645)
+        );
+        assert_eq!((request.size_from, request.size_to), (Some(10), Some(20)));
+    }
+
+    #[test]
+    fn result_without_highlight_omits_it() {
+        let result = ApiSearchResult {
+            path: "/docs/report.pdf".into(),
+            score: 1.0,
+            content_type: "application/pdf".to_owned(),
+            modified: Default::default(),
+            size: 10,
+            highlight: None,
+        };
+
+        let value = serde_json::to_value(result).unwrap();
+        assert!(value.get("highlight").is_none());
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use common_lib::elasticsearch::ELASTICSEARCH_INDEX;
+use elasticsearch::{Elasticsearch, SearchParts};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Maximum number of completion candidates
+const COMPLETION_MAX_CANDIDATES: usize = 10;
+/// Number of requested documents, more than candidates because file names repeat
+const COMPLETION_DOCUMENTS: u32 = 30;
+
+#[derive(Deserialize)]
+pub struct CompletionQuery {
+    /// Typed part of query
+    q: String,
+}
+
+/// Names of files with path matching typed words, the last one as prefix.
+/// Indices created before completion field was added return no candidates
+pub async fn get_completions(
+    es_client: &Elasticsearch,
+    query: &CompletionQuery,
+) -> Result<Vec<String>, elasticsearch::Error> {
+    if query.q.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let response: Value = es_client
+        .search(SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+        .size(COMPLETION_DOCUMENTS.into())
+        .track_total_hits(false)
+        .body(json!({
+            "_source": ["path"],
+            "query": {
+                "multi_match": {
+                    "query": query.q,
+                    "type": "bool_prefix",
+                    "fields": [
+                        "path.complete",
+                        "path.complete._2gram",
+                        "path.complete._3gram"
+                    ]
+                }
+            }
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(e) = response.get("error") {
+        tracing::warn!("Error completing query: {}", e);
+        return Ok(Vec::new());
+    }
+
+    let mut candidates = Vec::new();
+    let hits = response["hits"]["hits"].as_array().into_iter().flatten();
+    for path in hits.filter_map(|x| x["_source"]["path"].as_str()) {
+        // Windows paths are stored with backslashes
+        let Some(name) = Path::new(&path.replace('\\', "/"))
+            .file_name()
+            .map(|x| x.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        if !candidates.contains(&name) {
+            candidates.push(name);
+        }
+        if candidates.len() == COMPLETION_MAX_CANDIDATES {
+            break;
+        }
+    }
+    Ok(candidates)
+}
@@ -0,0 +1,244 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use common_lib::search::ExportFormat;
+use elasticsearch::{Elasticsearch, SearchParts};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing_unwrap::{OptionExt, ResultExt};
+
+/// Maximum number of exported results
+pub const EXPORT_MAX_RESULTS: u32 = 100_000;
+/// Number of results requested from Elasticsearch at once
+const EXPORT_PAGE_SIZE: u32 = 1000;
+/// Header of CSV file, in order of fields of exported result
+const CSV_HEADER: [&str; 5] = ["path", "size", "modified", "content_type", "score"];
+
+/// Exported fields of search result
+#[derive(Serialize, Deserialize)]
+pub struct ExportedResult {
+    path: PathBuf,
+    size: u64,
+    #[serde(deserialize_with = "ts_seconds::deserialize")]
+    modified: DateTime<Utc>,
+    content_type: String,
+    #[serde(skip_deserializing)]
+    score: f32,
+}
+
+/// Search request body without highlighting, suggestion and albums, sorted for pagination
+pub fn export_request_body(mut request_body: Value) -> Value {
+    let body = request_body.as_object_mut().unwrap_or_log();
+    for key in ["highlight", "suggest", "aggs"] {
+        body.remove(key);
+    }
+    body.insert(
+        "_source".to_owned(),
+        json!(["path", "size", "modified", "content_type"]),
+    );
+
+    // Shard document number breaks ties between pages
+    let mut sort = match body.remove("sort") {
+        Some(Value::Array(x)) => x,
+        _ => vec![json!("_score")],
+    };
+    sort.push(json!({"_shard_doc": "asc"}));
+    body.insert("sort".to_owned(), Value::Array(sort));
+    request_body
+}
+
+/// Pages of search results read with point in time
+pub struct ExportPages {
+    request_body: Value,
+    pit_id: String,
+    keep_alive: String,
+    search_after: Option<Value>,
+    remaining: u32,
+}
+
+impl ExportPages {
+    pub fn new(request_body: Value, pit_id: String, keep_alive: String, max_results: u32) -> Self {
+        Self {
+            request_body,
+            pit_id,
+            keep_alive,
+            search_after: None,
+            remaining: max_results,
+        }
+    }
+
+    /// Get next page of results, empty after the last one
+    pub async fn next(&mut self, es_client: &Elasticsearch) -> anyhow::Result<Vec<ExportedResult>> {
+        if self.remaining == 0 {
+            return Ok(Vec::new());
+        }
+
+        let size = self.remaining.min(EXPORT_PAGE_SIZE);
+        let mut request_body = self.request_body.clone();
+        request_body["pit"] = json!({
+            "id": self.pit_id,
+            "keep_alive": self.keep_alive
+        });
+        if let Some(x) = &self.search_after {
+            request_body["search_after"] = x.clone();
+        }
+
+        let response: Value = es_client
+            .search(SearchParts::None)
+            .size(size.into())
+            .track_total_hits(false)
+            .body(request_body)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(e) = response.get("error") {
+            return Err(anyhow!("Error reading search results: {}", e));
+        }
+
+        let hits = response["hits"]["hits"].as_array().unwrap_or_log();
+        self.pit_id = response["pit_id"].as_str().unwrap_or_log().to_owned();
+        self.search_after = hits.last().map(|x| x["sort"].clone());
+        self.remaining = if hits.len() < size as usize {
+            0
+        } else {
+            self.remaining - size
+        };
+        Ok(hits
+            .iter()
+            .map(|hit| {
+                let mut result: ExportedResult =
+                    serde_json::from_value(hit["_source"].clone()).unwrap_or_log();
+                result.score = hit["_score"].as_f64().unwrap_or_default() as f32;
+                result
+            })
+            .collect())
+    }
+
+    /// Close point in time after reading results
+    pub async fn close(self, es_client: &Elasticsearch) {
+        if let Err(e) = es_client
+            .close_point_in_time()
+            .body(json!({ "id": self.pit_id }))
+            .send()
+            .await
+        {
+            tracing::error!("Error closing point in time: {}", e);
+        }
+    }
+}
+
+/// Start of file before results
+pub fn format_start(format: ExportFormat) -> anyhow::Result<Vec<u8>> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(CSV_HEADER)?;
+            Ok(writer.into_inner().map_err(|e| e.into_error())?)
+        }
+        ExportFormat::Json => Ok(b"[".to_vec()),
+    }
+}
+
+/// Page of results, given number of already written results
+pub fn format_page(
+    format: ExportFormat,
+    results: &[ExportedResult],
+    written: usize,
+) -> anyhow::Result<Vec<u8>> {
+    match format {
+        ExportFormat::Csv => {
+            // Fields with separators, quotes or line breaks are quoted
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            for result in results {
+                writer.serialize(result)?;
+            }
+            Ok(writer.into_inner().map_err(|e| e.into_error())?)
+        }
+        ExportFormat::Json => {
+            let mut bytes = Vec::new();
+            for (i, result) in results.iter().enumerate() {
+                if written + i > 0 {
+                    bytes.push(b',');
+                }
+                serde_json::to_writer(&mut bytes, result)?;
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+/// End of file after results
+pub fn format_end(format: ExportFormat) -> &'static [u8] {
+    match format {
+        ExportFormat::Csv => b"",
+        ExportFormat::Json => b"]",
+    }
+}
+
+/// MIME type of file
+pub fn content_type(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Csv => "text/csv; charset=utf-8",
+        ExportFormat::Json => "application/json",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str) -> ExportedResult {
+        ExportedResult {
+            path: PathBuf::from(path),
+            size: 10,
+            modified: DateTime::default(),
+            content_type: "text/plain".to_owned(),
+            score: 1.5,
+        }
+    }
+
+    #[test]
+    fn csv_paths_with_separators_quotes_and_newlines_are_escaped() {
+        let paths = [
+            "/docs/plain.txt",
+            "/docs/a,b.txt",
+            "/docs/\"quoted\".txt",
+            "/docs/line\nbreak.txt",
+        ];
+        let results: Vec<_> = paths.iter().map(|x| result(x)).collect();
+        let mut bytes = format_start(ExportFormat::Csv).unwrap();
+        bytes.extend(format_page(ExportFormat::Csv, &results[..2], 0).unwrap());
+        bytes.extend(format_page(ExportFormat::Csv, &results[2..], 2).unwrap());
+        bytes.extend(format_end(ExportFormat::Csv));
+
+        let csv = String::from_utf8(bytes).unwrap();
+        assert!(csv.starts_with("path,size,modified,content_type,score\n/docs/plain.txt,10,"));
+        assert!(csv.contains("\n\"/docs/a,b.txt\",10,"));
+        assert!(csv.contains("\n\"/docs/\"\"quoted\"\".txt\",10,"));
+        assert!(csv.contains("\n\"/docs/line\nbreak.txt\",10,"));
+
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let records: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), paths.len());
+        for (record, path) in records.iter().zip(paths) {
+            assert_eq!(&record[0], path);
+            assert_eq!(&record[4], "1.5");
+        }
+    }
+
+    #[test]
+    fn json_pages_form_one_array() {
+        let mut bytes = format_start(ExportFormat::Json).unwrap();
+        bytes.extend(format_page(ExportFormat::Json, &[result("/a,\"b\".txt")], 0).unwrap());
+        bytes.extend(format_page(ExportFormat::Json, &[result("/c\n.txt")], 1).unwrap());
+        bytes.extend(format_end(ExportFormat::Json));
+
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value[0]["path"], "/a,\"b\".txt");
+        assert_eq!(value[1]["path"], "/c\n.txt");
+    }
+}
@@ -1,7 +1,8 @@
+use common_lib::search::SortOrder;
 use serde::Serialize;
 use serde_json::{json, Value};
 
-pub fn simple_query_string(mut query: String, fields: &[&str]) -> Value {
+pub fn simple_query_string(mut query: String, fields: &[String]) -> Value {
     if query.is_empty() {
         query = "*".to_owned();
     }
@@ -31,6 +32,17 @@ pub fn term(field: &str, value: impl Serialize) -> Value {
     })
 }
 
+pub fn wildcard(field: &str, value: impl Serialize, case_insensitive: bool) -> Value {
+    json!({
+        "wildcard": {
+            field: {
+                "value": value,
+                "case_insensitive": case_insensitive,
+            }
+        }
+    })
+}
+
 // pub fn match_(field: &str, query: impl Serialize) -> Value {
 //     json!({
 //         "match": {
@@ -52,7 +64,68 @@ pub fn range(field: &str, gte: impl Serialize, lte: impl Serialize) -> Value {
     })
 }
 
-pub fn suggest(mut query: String, main_field: &str, all_fields: &[&str]) -> Value {
+/// Multiply scores of query by `1 + boost * decay`, where decay of modification time is 1 now
+/// and halves at given distance from now in seconds
+pub fn recency_boost(query: Value, boost: f32, now: i64, scale: i64) -> Value {
+    json!({
+        "function_score": {
+            "query": query,
+            "functions": [
+                {
+                    "gauss": {
+                        "modified": {
+                            "origin": now,
+                            "scale": scale,
+                            "decay": 0.5
+                        }
+                    },
+                    "weight": boost
+                },
+                { "weight": 1 }
+            ],
+            "score_mode": "sum",
+            "boost_mode": "multiply"
+        }
+    })
+}
+
+/// Sort clause for order other than by relevance
+pub fn sort(order: SortOrder) -> Option<Value> {
+    // File name is the part of path after last separator of any OS
+    const FILE_NAME_SCRIPT: &str = "def p = doc['path.keyword'].value; \
+        return p.substring(Math.max(p.lastIndexOf('/'), p.lastIndexOf('\\\\')) + 1).toLowerCase();";
+
+    let field_sort = |field: &str, order: &str| json!([{ field: { "order": order } }]);
+    let name_sort = |order: &str| {
+        json!([{
+            "_script": {
+                "type": "string",
+                "order": order,
+                "script": {
+                    "source": FILE_NAME_SCRIPT
+                }
+            }
+        }])
+    };
+
+    match order {
+        SortOrder::Relevance => None,
+        SortOrder::ModifiedAsc => Some(field_sort("modified", "asc")),
+        SortOrder::ModifiedDesc => Some(field_sort("modified", "desc")),
+        SortOrder::SizeAsc => Some(field_sort("size", "asc")),
+        SortOrder::SizeDesc => Some(field_sort("size", "desc")),
+        SortOrder::NameAsc => Some(name_sort("asc")),
+        SortOrder::NameDesc => Some(name_sort("desc")),
+    }
+}
+
+/// Phrase suggestion, checked for matches in collate fields
+pub fn suggest(
+    mut query: String,
+    main_field: &str,
+    all_fields: &[&str],
+    collate_fields: &[&str],
+) -> Value {
     if query.is_empty() {
         query = "*".to_owned();
     }
@@ -78,6 +151,18 @@ pub fn suggest(mut query: String, main_field: &str, all_fields: &[&str]) -> Valu
                 "highlight": {
                     "pre_tag": "<i>",
                     "post_tag": "</i>"
+                },
+                "collate": {
+                    "query": {
+                        "source": {
+                            "multi_match": {
+                                "query": "{{suggestion}}",
+                                "fields": collate_fields,
+                                "operator": "and"
+                            }
+                        }
+                    },
+                    "prune": true
                 }
             }
         }
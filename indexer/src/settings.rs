@@ -1,27 +1,132 @@
-use std::sync::Arc;
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use axum::{extract::State, http::StatusCode, Json};
-use common_lib::settings::Settings;
+use common_lib::{elasticsearch::ContentLanguage, settings::Settings, NNServerHealth};
 use tracing_unwrap::ResultExt;
 
-use crate::{watcher::start_watcher, ServerState};
+use crate::{
+    create_es_client,
+    embeddings::get_nn_server_health,
+    indexer::{
+        create_index::{create_index, IndexOptions},
+        periodic::start_periodic_indexing,
+        remove_excluded_files,
+    },
+    indexing_events_channel,
+    scanner::{lowercase_path, ExcludeRegexes},
+    watcher::start_watcher,
+    ServerState,
+};
 
 const SETTINGS_FILE_PATH: &str = "Settings.toml";
+/// Settings are written to this file first and then renamed, so they are never read partially written
+const SETTINGS_TEMP_FILE_PATH: &str = "Settings.toml.tmp";
+/// How often modification time of settings file is checked
+const SETTINGS_RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Warn about weights of fields that aren't searched, they are ignored
+fn warn_unknown_field_weights(settings: &Settings) {
+    for field in settings.field_weights.unknown_fields() {
+        tracing::warn!("Ignoring weight of unknown field: {}", field);
+    }
+}
+
+/// Normalize paths of indexing directories, rejecting relative and duplicate ones. Warn about
+/// indexed directories nested in other indexed ones, files in them are indexed only once,
+/// with settings of the inner directory
+fn normalize_indexing_directories(settings: &mut Settings) -> Result<(), String> {
+    for dir in &mut settings.indexing_directories {
+        if !dir.path.is_absolute() {
+            return Err(format!(
+                "Path of indexing directory isn't absolute: {}",
+                dir.path.display()
+            ));
+        }
+        // Removes trailing separators and `.`, symbolic links are kept as paths of files
+        // in index are built from configured paths
+        dir.path = dir.path.components().collect();
+    }
+
+    let mut paths = HashSet::new();
+    for dir in &settings.indexing_directories {
+        let key = if settings.case_insensitive_paths {
+            lowercase_path(&dir.path)
+        } else {
+            dir.path.clone()
+        };
+        if !paths.insert(key) {
+            return Err(format!(
+                "Indexing directory is listed more than once: {}",
+                dir.path.display()
+            ));
+        }
+    }
+
+    let included: Vec<_> = settings
+        .indexing_directories
+        .iter()
+        .filter(|x| !x.exclude)
+        .collect();
+    for inner in &included {
+        for outer in included.iter().filter(|x| x.path != inner.path) {
+            if inner.path.starts_with(&outer.path) {
+                tracing::warn!(
+                    "Indexing directory {} is inside of indexing directory {}",
+                    inner.path.display(),
+                    outer.path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
 
 pub async fn read_settings_file() -> Settings {
-    match tokio::fs::read_to_string(SETTINGS_FILE_PATH).await {
+    let mut settings: Settings = match tokio::fs::read_to_string(SETTINGS_FILE_PATH).await {
         Ok(s) => toml::from_str(&s).expect_or_log("Error reading settings"),
         Err(e) => {
             tracing::warn!("Error reading settings file: {}, using defaults", e);
             Default::default()
         }
+    };
+    settings.nn_server.fill_model_defaults();
+    warn_unknown_field_weights(&settings);
+    if let Err(e) = check_content_languages(&settings) {
+        tracing::warn!("{}, it's ignored", e);
     }
+    settings
+}
+
+/// Modification time of settings file, `None` if it doesn't exist
+pub async fn settings_file_modified() -> Option<SystemTime> {
+    tokio::fs::metadata(SETTINGS_FILE_PATH)
+        .await
+        .and_then(|x| x.modified())
+        .ok()
 }
 
-async fn write_settings_file(state: Arc<ServerState>) -> std::io::Result<()> {
+/// Write contents to temporary file and rename it over file, so readers see either old
+/// or new contents completely
+async fn write_atomically(path: &Path, temp_path: &Path, contents: String) -> std::io::Result<()> {
+    tokio::fs::write(temp_path, contents).await?;
+    tokio::fs::rename(temp_path, path).await
+}
+
+/// Atomically replace settings file with current settings, returns its new modification time
+async fn write_settings_file(state: &ServerState) -> std::io::Result<Option<SystemTime>> {
     let s = toml::to_string(&*state.settings.read().await).unwrap_or_log();
-    tokio::fs::write(SETTINGS_FILE_PATH, s).await?;
-    Ok(())
+    write_atomically(
+        Path::new(SETTINGS_FILE_PATH),
+        Path::new(SETTINGS_TEMP_FILE_PATH),
+        s,
+    )
+    .await?;
+    Ok(settings_file_modified().await)
 }
 
 /// Get current settings
@@ -29,15 +134,328 @@ pub async fn get_settings(State(state): State<Arc<ServerState>>) -> Json<Setting
     Json(state.settings.read().await.clone())
 }
 
+/// Are settings of connection to Elasticsearch different?
+fn es_connection_changed(old: &Settings, new: &Settings) -> bool {
+    old.elasticsearch_urls != new.elasticsearch_urls
+        || old.elasticsearch_user != new.elasticsearch_user
+        || old.elasticsearch_password != new.elasticsearch_password
+        || old.elasticsearch_ca_cert_path != new.elasticsearch_ca_cert_path
+}
+
+/// Get devices that models of neural network server are running on
+pub async fn get_nn_server_devices(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<NNServerHealth>, (StatusCode, String)> {
+    let nn_server_url = state.settings.read().await.nn_server_url.clone();
+    let health = get_nn_server_health(&state.reqwest_client, nn_server_url)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{e:#}")))?;
+    Ok(Json(health))
+}
+
+/// Are settings used by watcher different? It has to be restarted to apply them
+fn watcher_settings_changed(old: &Settings, new: &Settings) -> bool {
+    old.watcher_enabled != new.watcher_enabled
+        || old.debouncer_timeout != new.debouncer_timeout
+        || old.exclude_file_regex != new.exclude_file_regex
+        || old.indexing_directories != new.indexing_directories
+}
+
+/// Checks that analyzers exist for all content languages
+fn check_content_languages(settings: &Settings) -> Result<(), String> {
+    match settings
+        .content_languages
+        .iter()
+        .find(|x| !x.parse::<ContentLanguage>().is_ok_and(|x| x.has_analyzer()))
+    {
+        Some(language) => Err(format!(
+            "Content language {language} isn't supported, supported languages are: {}",
+            ContentLanguage::ALL
+                .into_iter()
+                .filter(|x| x.has_analyzer())
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Are settings that exclude files from indexing different? Documents of newly excluded files
+/// have to be removed
+fn exclusion_settings_changed(old: &Settings, new: &Settings) -> bool {
+    fn exclusions(settings: &Settings) -> Vec<(&Path, bool, Option<&str>)> {
+        settings
+            .indexing_directories
+            .iter()
+            .filter(|x| x.exclude || x.exclude_regex.is_some())
+            .map(|x| (x.path.as_path(), x.exclude, x.exclude_regex.as_deref()))
+            .collect()
+    }
+
+    old.exclude_file_regex != new.exclude_file_regex || exclusions(old) != exclusions(new)
+}
+
+/// Validate new settings and apply them, restarting components that depend on changed ones
+async fn apply_settings(
+    state: &Arc<ServerState>,
+    mut new_settings: Settings,
+) -> Result<(), (StatusCode, String)> {
+    new_settings.nn_server.fill_model_defaults();
+    warn_unknown_field_weights(&new_settings);
+    normalize_indexing_directories(&mut new_settings).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    check_content_languages(&new_settings).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ExcludeRegexes::new(&new_settings).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    // New client is checked before settings are applied, so working connection isn't lost
+    if es_connection_changed(&*state.settings.read().await, &new_settings) {
+        let es_client = create_es_client(&new_settings)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("{e:#}")))?;
+        create_index(&es_client, &IndexOptions::new(&new_settings))
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{e:#}")))?;
+        *state.es_client.write().await = es_client;
+    }
+    let (watcher_changed, max_concurrent_files_changed, exclusion_changed) = {
+        let mut settings = state.settings.write().await;
+        if settings.cjk_analysis != new_settings.cjk_analysis {
+            tracing::warn!("CJK analysis setting was changed, index has to be rebuilt to apply it");
+        }
+        let (old_options, new_options) = (
+            IndexOptions::new(&settings),
+            IndexOptions::new(&new_settings),
+        );
+        if old_options.content_languages != new_options.content_languages {
+            tracing::warn!("Content languages were changed, index has to be rebuilt to apply them");
+        }
+        if old_options.text_embedding_dim != new_options.text_embedding_dim
+            || old_options.image_embedding_dim != new_options.image_embedding_dim
+        {
+            tracing::warn!(
+                "Embedding dimensions were changed, index has to be rebuilt to apply them"
+            );
+        }
+        let changed = (
+            watcher_settings_changed(&settings, &new_settings),
+            settings.max_concurrent_files != new_settings.max_concurrent_files,
+            exclusion_settings_changed(&settings, &new_settings),
+        );
+        *settings = new_settings;
+        changed
+    };
+    if max_concurrent_files_changed {
+        // Subscribers of old channel see it closed, watcher subscribes again when restarted
+        let max_concurrent_files = state.settings.read().await.max_concurrent_files;
+        *state.indexing_events.write().await = indexing_events_channel(max_concurrent_files);
+    }
+    if watcher_changed || max_concurrent_files_changed {
+        start_watcher(Arc::clone(state)).await;
+    }
+    start_periodic_indexing(Arc::clone(state)).await;
+    if exclusion_changed {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            if let Err(e) = remove_excluded_files(state).await {
+                tracing::warn!("Can't remove documents of excluded files: {:#}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
 /// Set settings from JSON
 pub async fn put_settings(
     State(state): State<Arc<ServerState>>,
     Json(new_settings): Json<Settings>,
 ) -> Result<(), (StatusCode, String)> {
-    *state.settings.write().await = new_settings;
-    start_watcher(Arc::clone(&state)).await;
-    write_settings_file(state)
+    // Held until file is written, so reload from file doesn't run in between
+    let mut file_modified = state.settings_file_modified.lock().await;
+    apply_settings(&state, new_settings).await?;
+    // The most recent write wins, edits of file that weren't reloaded yet are lost
+    if settings_file_modified().await != *file_modified {
+        tracing::warn!("Settings file was changed on disk, overwriting it with settings from API");
+    }
+    *file_modified = write_settings_file(&state)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     Ok(())
 }
+
+/// Read changed settings file and apply it. Invalid settings are logged and current ones are kept
+async fn reload_settings_file(state: &Arc<ServerState>) {
+    let s = match tokio::fs::read_to_string(SETTINGS_FILE_PATH).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!("Error reading changed settings file: {}", e);
+            return;
+        }
+    };
+    let new_settings = match toml::from_str(&s) {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::error!("Error parsing changed settings file: {}", e);
+            return;
+        }
+    };
+    match apply_settings(state, new_settings).await {
+        Ok(()) => tracing::info!("Settings were reloaded from file"),
+        Err((_, e)) => tracing::error!("Can't apply changed settings file: {}", e),
+    }
+}
+
+/// Debounce of changes of settings file. File is reloaded once its modification time
+/// stays the same for a whole check interval, so file that is still being written isn't read
+#[derive(Default)]
+struct ReloadDebounce {
+    /// Changed modification time seen on previous check
+    pending: Option<Option<SystemTime>>,
+}
+
+impl ReloadDebounce {
+    /// Checks modification time of file against time when it was last read or written,
+    /// returns `true` if file has to be reloaded now
+    fn check(&mut self, modified: Option<SystemTime>, file_modified: Option<SystemTime>) -> bool {
+        if modified == file_modified {
+            self.pending = None;
+            false
+        } else if self.pending != Some(modified) {
+            self.pending = Some(modified);
+            false
+        } else {
+            self.pending = None;
+            true
+        }
+    }
+}
+
+/// Start task that reloads settings when modification time of settings file changes
+pub fn start_settings_reload(state: Arc<ServerState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SETTINGS_RELOAD_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut debounce = ReloadDebounce::default();
+        loop {
+            interval.tick().await;
+            let modified = settings_file_modified().await;
+            let mut file_modified = state.settings_file_modified.lock().await;
+            if debounce.check(modified, *file_modified) {
+                *file_modified = modified;
+                reload_settings_file(&state).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::atomic::Ordering};
+
+    use axum::extract::Query;
+    use common_lib::settings::IndexingDirectory;
+
+    use crate::{
+        search::api_search,
+        test_util::{test_settings, test_state, text_document, MockElasticsearch},
+    };
+
+    use super::*;
+
+    #[test]
+    fn directories_differing_in_case_are_duplicates_with_case_insensitive_paths() {
+        let mut settings = test_settings();
+        settings.indexing_directories = ["/Docs/Work", "/docs/work/"]
+            .into_iter()
+            .map(|x| IndexingDirectory {
+                path: PathBuf::from(x),
+                ..Default::default()
+            })
+            .collect();
+        settings.case_insensitive_paths = false;
+        assert!(normalize_indexing_directories(&mut settings.clone()).is_ok());
+        settings.case_insensitive_paths = true;
+        assert!(normalize_indexing_directories(&mut settings).is_err());
+    }
+
+    #[tokio::test]
+    async fn file_is_replaced_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Settings.toml");
+        let temp_path = dir.path().join("Settings.toml.tmp");
+        std::fs::write(&path, "old = true").unwrap();
+        // Leftover of interrupted write is overwritten
+        std::fs::write(&temp_path, "partial").unwrap();
+        #[cfg(unix)]
+        let mut reader = std::fs::File::open(&path).unwrap();
+
+        write_atomically(&path, &temp_path, "new = true".to_owned())
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new = true");
+        assert!(!temp_path.exists());
+        // File opened before the write still has old contents completely
+        #[cfg(unix)]
+        {
+            use std::io::Read;
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents).unwrap();
+            assert_eq!(contents, "old = true");
+        }
+    }
+
+    #[test]
+    fn reload_waits_until_modification_time_is_stable() {
+        let time = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let mut debounce = ReloadDebounce::default();
+        let file_modified = time(1);
+        assert!(!debounce.check(time(1), file_modified));
+
+        // Changed file is reloaded on the next check if it didn't change again
+        assert!(!debounce.check(time(2), file_modified));
+        assert!(debounce.check(time(2), file_modified));
+
+        // File that is still being written isn't reloaded
+        assert!(!debounce.check(time(3), file_modified));
+        assert!(!debounce.check(time(4), file_modified));
+        assert!(!debounce.check(time(5), file_modified));
+        assert!(debounce.check(time(5), file_modified));
+
+        // File written by server itself isn't reloaded
+        assert!(!debounce.check(time(6), file_modified));
+        assert!(!debounce.check(time(6), time(6)));
+        assert!(!debounce.check(time(6), time(6)));
+
+        // Removed file is reloaded too, then error is logged and settings are kept
+        assert!(!debounce.check(None, time(6)));
+        assert!(debounce.check(None, time(6)));
+    }
+
+    #[tokio::test]
+    async fn search_uses_elasticsearch_from_saved_settings() {
+        let old_es =
+            MockElasticsearch::with_documents([("old".to_owned(), text_document("/old.txt"))]);
+        let new_es =
+            MockElasticsearch::with_documents([("new".to_owned(), text_document("/new.txt"))]);
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![old_es.serve()];
+        let state = test_state(settings.clone()).await;
+
+        settings.elasticsearch_urls = vec![new_es.serve()];
+        put_settings(State(Arc::clone(&state)), Json(settings.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            state.settings.read().await.elasticsearch_urls,
+            settings.elasticsearch_urls
+        );
+
+        let Json(results) = api_search(State(state), Query(Default::default()))
+            .await
+            .unwrap();
+        assert_eq!(old_es.searches.load(Ordering::SeqCst), 0);
+        assert!(new_es.searches.load(Ordering::SeqCst) > 0);
+        let paths: Vec<_> = results.iter().map(|x| &x.path).collect();
+        assert_eq!(paths, [Path::new("/new.txt")]);
+    }
+}
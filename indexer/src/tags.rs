@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use common_lib::{
+    elasticsearch::ELASTICSEARCH_INDEX,
+    tags::{SetTagsRequest, TagsOperation, TagsRequest},
+};
+use elasticsearch::{params::Refresh, GetParts, SearchParts, UpdateParts};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{indexer::check_not_rebuilding, ServerState};
+
+/// Maximum number of listed distinct tags
+const TAGS_LIST_MAX: u32 = 1000;
+
+#[derive(Deserialize)]
+struct DocumentTags {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Change tags of document, returns new tags
+pub async fn update_tags(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+    Json(tags_request): Json<TagsRequest>,
+) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    check_not_rebuilding(&state).await?;
+
+    let es_response = state
+        .es_client()
+        .await
+        .get(GetParts::IndexId(ELASTICSEARCH_INDEX, &id))
+        ._source(&["tags"])
+        .send()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if es_response.status_code() == StatusCode::NOT_FOUND {
+        return Err((StatusCode::NOT_FOUND, "Document not found".to_owned()));
+    }
+    let es_response_body = es_response
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let current_tags = serde_json::from_value::<DocumentTags>(es_response_body["_source"].clone())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .tags;
+
+    let tags = tags_request.apply(current_tags);
+    state
+        .es_client()
+        .await
+        .update(UpdateParts::IndexId(ELASTICSEARCH_INDEX, &id))
+        .refresh(Refresh::True)
+        .body(json!({
+            "doc": {
+                "tags": tags
+            }
+        }))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(tags))
+}
+
+/// Replace tags of document by its `_id` from request body, returns new tags
+pub async fn set_tags(
+    state: State<Arc<ServerState>>,
+    Json(request): Json<SetTagsRequest>,
+) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    update_tags(
+        state,
+        Path(request.id),
+        Json(TagsRequest {
+            operation: TagsOperation::Set,
+            tags: request.tags,
+        }),
+    )
+    .await
+}
+
+/// All distinct tags of documents, the most used first
+pub async fn list_tags(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    let es_response_body = state
+        .es_client()
+        .await
+        .search(SearchParts::Index(&[ELASTICSEARCH_INDEX]))
+        .size(0)
+        .body(json!({
+            "aggs": {
+                "tags": {
+                    "terms": {
+                        "field": "tags",
+                        "size": TAGS_LIST_MAX
+                    }
+                }
+            }
+        }))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status_code())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .json::<Value>()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let tags = es_response_body["aggregations"]["tags"]["buckets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x["key"].as_str().map(str::to_owned))
+        .collect();
+    Ok(Json(tags))
+}
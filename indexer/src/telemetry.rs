@@ -0,0 +1,137 @@
+use std::{sync::Arc, time::Instant};
+
+use axum::{extract::State, http::StatusCode};
+use metrics::{describe_counter, describe_histogram, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use tracing_unwrap::ResultExt;
+
+use crate::ServerState;
+
+pub const SEARCH_DURATION: &str = "search_duration_seconds";
+pub const FILES_PROCESSED: &str = "files_processed_total";
+pub const BULK_BATCH_SIZE: &str = "bulk_batch_size";
+pub const EXTERNAL_REQUEST_DURATION: &str = "external_request_duration_seconds";
+pub const WATCHER_EVENTS: &str = "watcher_events_total";
+
+/// Buckets of durations in seconds, from fast searches to slow parsing of large files
+const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+/// Buckets of number of operations in bulk request
+const BATCH_SIZE_BUCKETS: [f64; 8] = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 1000.0];
+
+/// Install global recorder of metrics, they are recorded even if endpoint is disabled
+pub fn install_recorder() -> PrometheusHandle {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_owned()), &DURATION_BUCKETS)
+        .and_then(|x| {
+            x.set_buckets_for_metric(
+                Matcher::Full(BULK_BATCH_SIZE.to_owned()),
+                &BATCH_SIZE_BUCKETS,
+            )
+        })
+        .and_then(|x| x.install_recorder())
+        .expect_or_log("Can't install metrics recorder");
+
+    describe_histogram!(
+        SEARCH_DURATION,
+        Unit::Seconds,
+        "Duration of search requests, by usage of kNN search and reranking"
+    );
+    describe_counter!(FILES_PROCESSED, "Number of files processed by indexing");
+    describe_histogram!(
+        BULK_BATCH_SIZE,
+        Unit::Count,
+        "Number of operations in bulk requests to Elasticsearch"
+    );
+    describe_histogram!(
+        EXTERNAL_REQUEST_DURATION,
+        Unit::Seconds,
+        "Duration of requests to Apache Tika and neural network server, by endpoint"
+    );
+    describe_counter!(WATCHER_EVENTS, "Number of file system events, by kind");
+    handle
+}
+
+/// Records duration of request to external service when dropped, so failed requests are included
+pub struct RequestTimer {
+    service: &'static str,
+    endpoint: &'static str,
+    start: Instant,
+}
+
+impl RequestTimer {
+    pub fn start(service: &'static str, endpoint: &'static str) -> Self {
+        Self {
+            service,
+            endpoint,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for RequestTimer {
+    fn drop(&mut self) {
+        histogram!(
+            EXTERNAL_REQUEST_DURATION,
+            self.start.elapsed().as_secs_f64(),
+            "service" => self.service,
+            "endpoint" => self.endpoint
+        );
+    }
+}
+
+/// Get metrics in Prometheus text format, if they are enabled in settings
+pub async fn get_metrics(
+    State(state): State<Arc<ServerState>>,
+) -> Result<String, (StatusCode, String)> {
+    if !state.settings.read().await.metrics_enabled {
+        return Err((StatusCode::NOT_FOUND, "Metrics are disabled".to_owned()));
+    }
+    Ok(state.metrics_handle.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use axum::{extract::Query, Json};
+
+    use crate::{
+        search::api_search,
+        test_util::{test_settings, test_state, text_document, MockElasticsearch},
+    };
+
+    use super::*;
+
+    /// Handle of global recorder, it can be installed only once
+    fn recorder_handle() -> PrometheusHandle {
+        static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+        HANDLE.get_or_init(install_recorder).clone()
+    }
+
+    #[tokio::test]
+    async fn search_duration_is_exported() {
+        let es = MockElasticsearch::with_documents([("a".to_owned(), text_document("/a.txt"))]);
+        let mut settings = test_settings();
+        settings.elasticsearch_urls = vec![es.serve()];
+        settings.metrics_enabled = true;
+        let mut state = test_state(settings).await;
+        Arc::get_mut(&mut state).unwrap().metrics_handle = recorder_handle();
+
+        let Json(results) = api_search(State(Arc::clone(&state)), Query(Default::default()))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let metrics = get_metrics(State(Arc::clone(&state))).await.unwrap();
+
+        assert!(metrics.contains("# TYPE search_duration_seconds histogram"));
+        assert!(metrics.contains(r#"search_duration_seconds_count{knn="false",rerank="false"}"#));
+        assert!(metrics
+            .contains(r#"search_duration_seconds_bucket{knn="false",rerank="false",le="0.005"}"#));
+
+        state.settings.write().await.metrics_enabled = false;
+        let (status, _) = get_metrics(State(state)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
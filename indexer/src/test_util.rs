@@ -0,0 +1,483 @@
+//! Helpers for tests: state of server with test settings and mocked HTTP services
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    net::TcpListener,
+    path::Path as FsPath,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex as StdMutex, Once,
+    },
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use common_lib::{
+    indexer::{IndexSchemaStatus, IndexingStatus, MAX_WATCHER_EVENT_CNT},
+    settings::{IndexingDirectory, Settings},
+};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, Mutex, Notify, RwLock};
+use url::Url;
+
+use crate::{
+    create_es_client, embeddings::ImageBatch, indexing_events_channel, provenance::Toolchain,
+    ServerState,
+};
+
+/// Nothing listens on this port, requests to services that test doesn't mock fail immediately
+const UNUSED_URL: &str = "http://127.0.0.1:9";
+
+/// Settings with unavailable services and without neural networks, thumbnails and watcher
+pub fn test_settings() -> Settings {
+    let mut settings = Settings {
+        elasticsearch_urls: vec![Url::parse(UNUSED_URL).unwrap()],
+        tika_url: Url::parse(UNUSED_URL).unwrap(),
+        nn_server_url: Url::parse(UNUSED_URL).unwrap(),
+        open_on_start: false,
+        watcher_enabled: false,
+        thumbnail_cache_enabled: false,
+        case_insensitive_paths: false,
+        ..Default::default()
+    };
+    settings.nn_server.text_search_enabled = false;
+    settings.nn_server.image_search_enabled = false;
+    settings.nn_server.reranking_enabled = false;
+    settings
+}
+
+/// Makes temporary directory current, so files that server writes next to settings
+/// don't end up in the source tree. Tests use only absolute paths of their own files
+fn set_temp_current_dir() {
+    static CURRENT_DIR: Once = Once::new();
+    CURRENT_DIR.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("indexer-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::env::set_current_dir(dir).unwrap();
+    });
+}
+
+/// State of server with given settings, requests to services aren't retried
+pub async fn test_state(settings: Settings) -> Arc<ServerState> {
+    set_temp_current_dir();
+    let es_client = create_es_client(&settings).await.unwrap();
+    let indexing_events = indexing_events_channel(settings.max_concurrent_files);
+    Arc::new(ServerState {
+        settings: RwLock::new(settings),
+        es_client: RwLock::new(es_client),
+        reqwest_client: reqwest_middleware::ClientBuilder::new(reqwest::Client::new()).build(),
+        indexing_status: RwLock::new(IndexingStatus::NotStarted),
+        indexing_events: RwLock::new(indexing_events),
+        watcher_events: broadcast::channel(MAX_WATCHER_EVENT_CNT).0,
+        watcher: RwLock::new(None),
+        estimation_running: RwLock::new(false),
+        estimation_cancel: Notify::new(),
+        rebuilding: RwLock::new(false),
+        toolchain: RwLock::new(Toolchain::default()),
+        delete_token: RwLock::new(None),
+        client_errors_lock: Mutex::new(()),
+        thumbnail_cache_lock: Mutex::new(()),
+        picked_files: RwLock::new(Default::default()),
+        periodic_indexing: RwLock::new(None),
+        metrics_handle: PrometheusBuilder::new().build_recorder().handle(),
+        settings_file_modified: Mutex::new(None),
+        file_errors: RwLock::new(Vec::new()),
+        parse_failures: RwLock::new(HashMap::new()),
+        image_batch: Mutex::new(ImageBatch::default()),
+        index_schema: RwLock::new(IndexSchemaStatus::Compatible),
+    })
+}
+
+/// State for indexing of given directory into mock Elasticsearch with mock Apache Tika
+pub async fn indexing_test_state(directory: &FsPath, es: &MockElasticsearch) -> Arc<ServerState> {
+    let mut settings = test_settings();
+    settings.elasticsearch_urls = vec![es.serve()];
+    settings.tika_url = mock_tika("test");
+    settings.indexing_directories = vec![IndexingDirectory {
+        path: directory.to_path_buf(),
+        ..Default::default()
+    }];
+    test_state(settings).await
+}
+
+/// Serves given routes on free local port, returns URL of server
+pub fn serve(router: Router) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router.into_make_service()),
+    );
+    Url::parse(&format!("http://{address}")).unwrap()
+}
+
+/// Apache Tika that returns text files as they are
+pub fn mock_tika(version: &'static str) -> Url {
+    serve(
+        Router::new()
+            .route("/version", get(move || async move { version }))
+            .route(
+                "/rmeta/text",
+                axum::routing::put(|body: String| async move {
+                    Json(json!([{ "Content-Type": "text/plain", "X-TIKA:content": body }]))
+                }),
+            )
+            .route(
+                "/detect/stream",
+                axum::routing::put(|| async { "text/plain" }),
+            ),
+    )
+}
+
+/// Source of indexed text file with fields required in search results
+pub fn text_document(path: &str) -> Value {
+    json!({
+        "path": path,
+        "modified": 0,
+        "size": 1,
+        "content_type": "text/plain",
+        "content_type_mime_type": "text",
+        "content_type_mime_essence": "text/plain",
+        "summary": []
+    })
+}
+
+/// Elasticsearch keeping documents of all indices together in memory.
+/// Supports requests made by indexing and simple queries of them
+#[derive(Clone, Default)]
+pub struct MockElasticsearch {
+    /// Sources of documents by ID
+    pub documents: Arc<StdMutex<BTreeMap<String, Value>>>,
+    /// Number of bulk requests received
+    pub bulk_requests: Arc<AtomicUsize>,
+    /// Number of search requests received
+    pub searches: Arc<AtomicUsize>,
+    /// Point in time expires once after this number of searches
+    pub pit_expires_after: Option<usize>,
+    /// Responses to this number of first bulk requests fail after operations are applied,
+    /// like when request times out
+    pub lost_bulk_responses: usize,
+    /// Documents with paths containing this string are rejected by bulk requests
+    pub rejected_path: Option<&'static str>,
+    next_id: Arc<AtomicUsize>,
+}
+
+#[derive(Deserialize)]
+struct SizeQuery {
+    size: Option<usize>,
+}
+
+impl MockElasticsearch {
+    /// Mock with given documents
+    pub fn with_documents(documents: impl IntoIterator<Item = (String, Value)>) -> Self {
+        let mock = Self::default();
+        mock.documents.lock().unwrap().extend(documents);
+        mock
+    }
+
+    /// Routes of mock, other routes can be merged into them
+    pub fn router(&self) -> Router {
+        Router::new()
+            // Existence check of index isn't routed, so index is created
+            .route(
+                "/:index",
+                axum::routing::put(|| async { Json(json!({ "acknowledged": true })) }),
+            )
+            .route(
+                "/:index/_pit",
+                post(|| async { Json(json!({ "id": "pit" })) }),
+            )
+            .route(
+                "/_pit",
+                delete(|| async { Json(json!({ "succeeded": true })) }),
+            )
+            .route("/_search", post(Self::search))
+            .route("/:index/_search", post(Self::search))
+            .route("/:index/_count", post(Self::count))
+            .route("/:index/_bulk", post(Self::bulk))
+            .route("/:index/_refresh", post(|| async { Json(json!({})) }))
+            .route("/:index/_delete_by_query", post(Self::delete_by_query))
+            .route("/:index/_update/:id", post(Self::update))
+            .route("/:index/_doc/:id", get(Self::get))
+            .with_state(self.clone())
+    }
+
+    /// Starts mock, returns its URL
+    pub fn serve(&self) -> Url {
+        serve(self.router())
+    }
+
+    /// Documents matching query with their sort values of path and document number
+    fn matching(&self, query: &Value) -> Vec<((String, i64), String, Value)> {
+        let mut documents: Vec<_> = self
+            .documents
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, (id, source))| {
+                // ID is matched by ids clause like a field
+                let mut document = (*source).clone();
+                document["_id"] = json!(id);
+                matches(query, &document)
+            })
+            .map(|(i, (id, source))| {
+                let path = source["path"].as_str().unwrap_or_default().to_owned();
+                ((path, i as i64), id.clone(), source.clone())
+            })
+            .collect();
+        documents.sort_by(|(x, _, _), (y, _, _)| x.cmp(y));
+        documents
+    }
+
+    async fn search(
+        State(mock): State<Self>,
+        Query(params): Query<SizeQuery>,
+        body: Option<Json<Value>>,
+    ) -> (StatusCode, Json<Value>) {
+        if mock.pit_expires_after == Some(mock.searches.fetch_add(1, Ordering::SeqCst)) {
+            let error = json!({ "type": "search_context_missing_exception" });
+            return (
+                StatusCode::NOT_FOUND,
+                Json(
+                    json!({ "error": { "root_cause": [error], "type": "search_phase_execution_exception" } }),
+                ),
+            );
+        }
+        let body = body.map(|x| x.0).unwrap_or_default();
+        let size = params
+            .size
+            .or(body["size"].as_u64().map(|x| x as usize))
+            .unwrap_or(10);
+        let search_after = match &body["search_after"] {
+            Value::Array(x) => Some((
+                x[0].as_str().unwrap().to_owned(),
+                x[1].as_i64().unwrap_or(i64::MAX),
+            )),
+            _ => None,
+        };
+        let hits: Vec<_> = mock
+            .matching(&body["query"])
+            .into_iter()
+            .filter(|(sort, _, _)| search_after.as_ref().is_none_or(|x| sort > x))
+            .take(size)
+            .map(|((path, i), id, source)| {
+                json!({ "_id": id, "_score": 1.0, "_source": source, "sort": [path, i] })
+            })
+            .collect();
+        (
+            StatusCode::OK,
+            Json(json!({
+                "pit_id": "pit",
+                "hits": { "total": { "value": hits.len() }, "hits": hits }
+            })),
+        )
+    }
+
+    async fn count(State(mock): State<Self>, body: Option<Json<Value>>) -> Json<Value> {
+        let body = body.map(|x| x.0).unwrap_or_default();
+        Json(json!({ "count": mock.matching(&body["query"]).len() }))
+    }
+
+    async fn bulk(State(mock): State<Self>, body: String) -> (StatusCode, Json<Value>) {
+        let request_number = mock.bulk_requests.fetch_add(1, Ordering::SeqCst);
+        let mut lines = body
+            .lines()
+            .map(|x| serde_json::from_str::<Value>(x).unwrap());
+        let mut items = Vec::new();
+        let mut errors = false;
+        let mut documents = mock.documents.lock().unwrap();
+        while let Some(action) = lines.next() {
+            let (kind, params) = action.as_object().unwrap().iter().next().unwrap();
+            let id = params["_id"]
+                .as_str()
+                .map(str::to_owned)
+                .unwrap_or_else(|| {
+                    format!("generated-{}", mock.next_id.fetch_add(1, Ordering::SeqCst))
+                });
+            match kind.as_str() {
+                "index" | "create" => {
+                    let document = lines.next().unwrap();
+                    let path = document["path"].as_str().unwrap_or_default();
+                    if mock.rejected_path.is_some_and(|x| path.contains(x)) {
+                        errors = true;
+                        let error = json!({ "type": "mapper_parsing_exception" });
+                        items.push(json!({ kind: { "_id": id, "status": 400, "error": error } }));
+                        continue;
+                    }
+                    documents.insert(id.clone(), document);
+                }
+                "update" => {
+                    let doc = lines.next().unwrap();
+                    if let Some(source) = documents.get_mut(&id) {
+                        merge(source, &doc["doc"]);
+                    }
+                }
+                "delete" => {
+                    documents.remove(&id);
+                }
+                _ => unreachable!(),
+            }
+            items.push(json!({ kind: { "_id": id, "status": 200 } }));
+        }
+        if request_number < mock.lost_bulk_responses {
+            return (StatusCode::GATEWAY_TIMEOUT, Json(json!({})));
+        }
+        (
+            StatusCode::OK,
+            Json(json!({ "errors": errors, "items": items })),
+        )
+    }
+
+    async fn delete_by_query(State(mock): State<Self>, Json(body): Json<Value>) -> Json<Value> {
+        let ids: HashSet<_> = mock
+            .matching(&body["query"])
+            .into_iter()
+            .map(|(_, id, _)| id)
+            .collect();
+        mock.documents
+            .lock()
+            .unwrap()
+            .retain(|id, _| !ids.contains(id));
+        Json(json!({ "deleted": ids.len() }))
+    }
+
+    async fn update(
+        State(mock): State<Self>,
+        Path((_, id)): Path<(String, String)>,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        if let Some(source) = mock.documents.lock().unwrap().get_mut(&id) {
+            merge(source, &body["doc"]);
+        }
+        Json(json!({ "_id": id, "result": "updated" }))
+    }
+
+    async fn get(State(mock): State<Self>, Path((_, id)): Path<(String, String)>) -> Json<Value> {
+        match mock.documents.lock().unwrap().get(&id) {
+            Some(source) => Json(json!({ "_id": id, "found": true, "_source": source })),
+            None => Json(json!({ "_id": id, "found": false })),
+        }
+    }
+}
+
+/// Sets fields of partial document
+fn merge(source: &mut Value, doc: &Value) {
+    for (key, value) in doc.as_object().into_iter().flatten() {
+        source[key] = value.clone();
+    }
+}
+
+/// Values of field of document, path has keyword, lowercased and hierarchy subfields
+fn field_values(source: &Value, field: &str) -> Vec<Value> {
+    let value = match field {
+        "path.keyword" => source["path"].clone(),
+        "path.lowercase" => json!(source["path"].as_str().map(str::to_lowercase)),
+        // Path and paths of all its ancestors, like tokens of path_hierarchy tokenizer
+        "path.hierarchy" => json!(source["path"].as_str().map(|x| {
+            let path = x.replace('\\', "/");
+            let mut ancestors: Vec<_> = path
+                .match_indices('/')
+                .filter(|(i, _)| *i > 0)
+                .map(|(i, _)| path[..i].to_owned())
+                .collect();
+            ancestors.push(path);
+            ancestors
+        })),
+        _ => field
+            .split('.')
+            .fold(source.clone(), |value, key| value[key].clone()),
+    };
+    match value {
+        Value::Array(x) => x,
+        Value::Null => Vec::new(),
+        x => vec![x],
+    }
+}
+
+/// Checks if document matches query of bool, ids, term, terms, prefix, range, exists
+/// and match_all clauses. Other clauses match all documents
+pub fn matches(query: &Value, source: &Value) -> bool {
+    let Some((kind, params)) = query.as_object().and_then(|x| x.iter().next()) else {
+        return true;
+    };
+    let clauses = |key: &str| match &params[key] {
+        Value::Array(x) => x.clone(),
+        Value::Null => Vec::new(),
+        x => vec![x.clone()],
+    };
+    let field_param = || {
+        let (field, value) = params.as_object().unwrap().iter().next().unwrap();
+        let value = value.get("value").unwrap_or(value).clone();
+        (field_values(source, field), value)
+    };
+    match kind.as_str() {
+        "bool" => {
+            let should = clauses("should");
+            let min_should = params["minimum_should_match"].as_u64().unwrap_or(
+                if should.is_empty() || !clauses("must").is_empty() || !clauses("filter").is_empty()
+                {
+                    0
+                } else {
+                    1
+                },
+            );
+            clauses("must")
+                .iter()
+                .chain(clauses("filter").iter())
+                .all(|x| matches(x, source))
+                && !clauses("must_not").iter().any(|x| matches(x, source))
+                && should.iter().filter(|x| matches(x, source)).count() as u64 >= min_should
+        }
+        "term" => {
+            let (values, value) = field_param();
+            values.contains(&value)
+        }
+        "terms" => {
+            let (values, value) = field_param();
+            value.as_array().unwrap().iter().any(|x| values.contains(x))
+        }
+        "prefix" => {
+            let (values, value) = field_param();
+            let case_insensitive = params
+                .as_object()
+                .and_then(|x| x.values().next())
+                .and_then(|x| x["case_insensitive"].as_bool())
+                .unwrap_or_default();
+            let normalize = |x: &Value| {
+                let x = x.as_str().unwrap();
+                if case_insensitive {
+                    x.to_lowercase()
+                } else {
+                    x.to_owned()
+                }
+            };
+            let value = normalize(&value);
+            values.iter().any(|x| normalize(x).starts_with(&value))
+        }
+        "range" => {
+            let (field, bounds) = params.as_object().unwrap().iter().next().unwrap();
+            let bound = |key: &str| bounds[key].as_f64();
+            field_values(source, field).iter().any(|x| {
+                let x = x.as_f64().unwrap();
+                bound("gte").is_none_or(|y| x >= y)
+                    && bound("gt").is_none_or(|y| x > y)
+                    && bound("lte").is_none_or(|y| x <= y)
+                    && bound("lt").is_none_or(|y| x < y)
+            })
+        }
+        "exists" => !field_values(source, params["field"].as_str().unwrap()).is_empty(),
+        "ids" => params["values"]
+            .as_array()
+            .unwrap()
+            .contains(&source["_id"]),
+        _ => true,
+    }
+}
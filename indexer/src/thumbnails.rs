@@ -1,34 +1,75 @@
-use std::process::Stdio;
+use std::{io, path::Path, process::Stdio};
 
 use tokio::process::Command;
 
-pub async fn get_thumbnail(
-    path: &str,
-    content_type: &Option<String>,
-) -> std::io::Result<(Vec<u8>, &'static str)> {
-    let (output_format, out_content_type) = match content_type.as_deref() {
+pub mod cache;
+
+/// Quality of JPEG encoding of downscaled images for preview, from 2 (best) to 31
+const SCALED_IMAGE_QUALITY: &str = "3";
+
+/// Output format of ffmpeg and content type of thumbnail for file with content type
+pub fn thumbnail_format(content_type: &Option<String>) -> (&'static str, &'static str) {
+    match content_type.as_deref() {
         Some("image/png") => ("png", "image/png"),
         _ => ("mjpeg", "image/jpeg"),
-    };
-
-    Command::new("ffmpeg")
-        .args([
-            "-i",
-            path,
-            "-threads",
-            "1",
-            "-vf",
-            r#"select='eq(pict_type\,I)',scale='512:512:force_original_aspect_ratio=decrease'"#,
-            "-vframes",
-            "1",
-            "-c:v",
-            output_format,
-            "-f",
-            "image2pipe",
-            "-",
-        ])
+    }
+}
+
+/// Create thumbnail of image, video or audio file with ffmpeg.
+/// Fails if ffmpeg exits with error or doesn't output anything
+pub async fn get_thumbnail(
+    path: &Path,
+    content_type: &Option<String>,
+) -> io::Result<(Vec<u8>, &'static str)> {
+    run_ffmpeg(
+        path,
+        content_type,
+        r#"select='eq(pict_type\,I)',scale='512:512:force_original_aspect_ratio=decrease'"#,
+        &[],
+    )
+    .await
+}
+
+/// Downscale image with ffmpeg to fit in square with given side, smaller images keep their size
+pub async fn get_scaled_image(
+    path: &Path,
+    content_type: &Option<String>,
+    max_dimension: u32,
+) -> io::Result<(Vec<u8>, &'static str)> {
+    run_ffmpeg(
+        path,
+        content_type,
+        &format!(
+            r#"scale='min(iw\,{max_dimension})':'min(ih\,{max_dimension})':force_original_aspect_ratio=decrease"#
+        ),
+        &["-q:v", SCALED_IMAGE_QUALITY],
+    )
+    .await
+}
+
+/// Output the first frame of file after video filter as image
+async fn run_ffmpeg(
+    path: &Path,
+    content_type: &Option<String>,
+    filter: &str,
+    extra_args: &[&str],
+) -> io::Result<(Vec<u8>, &'static str)> {
+    let (output_format, out_content_type) = thumbnail_format(content_type);
+
+    let output = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(path)
+        .args(["-threads", "1", "-vf", filter, "-vframes", "1"])
+        .args(extra_args)
+        .args(["-c:v", output_format, "-f", "image2pipe", "-"])
         .stdin(Stdio::null())
         .output()
-        .await
-        .map(|data| (data.stdout, out_content_type))
+        .await?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(io::Error::other(format!(
+            "ffmpeg exited with {}",
+            output.status
+        )));
+    }
+    Ok((output.stdout, out_content_type))
 }
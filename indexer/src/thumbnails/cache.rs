@@ -0,0 +1,161 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use tracing_unwrap::ResultExt;
+use uuid::Uuid;
+
+use crate::{scanner::FileInfo, ServerState};
+
+/// Extensions of cached thumbnails, for each content type of thumbnail
+const EXTENSIONS: [(&str, &str); 2] = [("image/png", "png"), ("image/jpeg", "jpg")];
+
+/// Key of thumbnail of file with hash of contents, doesn't change when file is moved
+pub fn hash_key(hash: &str) -> String {
+    key(&format!("hash:{hash}"))
+}
+
+/// Key of thumbnail of file without hash, changes when file is modified
+pub fn path_key(path: &Path, modified: DateTime<Utc>) -> String {
+    key(&format!("path:{}:{}", path.display(), modified.timestamp()))
+}
+
+/// Key of image downscaled for preview, derived from key of thumbnail of the same file
+pub fn scaled_key(thumbnail_key: &str, max_dimension: u32) -> String {
+    key(&format!("scaled:{thumbnail_key}:{max_dimension}"))
+}
+
+fn key(source: &str) -> String {
+    let hash_bytes: [u8; 32] = Sha256::digest(source.as_bytes()).into();
+    base16ct::lower::encode_string(&hash_bytes)
+}
+
+fn cache_path(dir: &Path, key: &str, content_type: &str) -> PathBuf {
+    let extension = EXTENSIONS
+        .iter()
+        .find(|(x, _)| *x == content_type)
+        .map_or("bin", |(_, x)| x);
+    dir.join(format!("{key}.{extension}"))
+}
+
+/// Read cached thumbnail and mark it as recently used
+pub async fn read(dir: &Path, key: &str, content_type: &str) -> Option<Vec<u8>> {
+    let path = cache_path(dir, key, content_type);
+    tokio::task::spawn_blocking(move || {
+        let bytes = std::fs::read(&path).ok()?;
+        // Modification time orders thumbnails for eviction
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|x| x.set_modified(SystemTime::now()))
+        {
+            tracing::warn!("Can't update time of cached thumbnail: {}", e);
+        }
+        Some(bytes)
+    })
+    .await
+    .unwrap_or_log()
+}
+
+/// Store thumbnail in cache, then evict least recently used thumbnails if cache is too large
+pub async fn write(state: Arc<ServerState>, key: &str, content_type: &str, bytes: &[u8]) {
+    let (dir, max_size) = {
+        let settings = state.settings.read().await;
+        (
+            settings.thumbnail_cache_dir.clone(),
+            settings.thumbnail_cache_max_size,
+        )
+    };
+
+    // Thumbnail is written to temporary file first, so partially written files aren't read
+    let tmp_path = dir.join(format!("{}.tmp", Uuid::new_v4()));
+    let res = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&tmp_path, bytes).await?;
+        tokio::fs::rename(&tmp_path, cache_path(&dir, key, content_type)).await
+    }
+    .await;
+    if let Err(e) = res {
+        tracing::error!("Error writing thumbnail to cache: {}", e);
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return;
+    }
+
+    // Skip eviction if it's already running
+    let Ok(_lock) = state.thumbnail_cache_lock.try_lock() else {
+        return;
+    };
+    let res = tokio::task::spawn_blocking(move || evict(&dir, max_size))
+        .await
+        .unwrap_or_log();
+    if let Err(e) = res {
+        tracing::error!("Error evicting thumbnails from cache: {}", e);
+    }
+}
+
+/// Remove least recently used thumbnails until total size is within maximum
+fn evict(dir: &Path, max_size: u64) -> io::Result<()> {
+    let mut entries = Vec::new();
+    let mut total_size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let (path, metadata) = match entry.and_then(|x| Ok((x.path(), x.metadata()?))) {
+            Ok((path, metadata)) if metadata.is_file() => (path, metadata),
+            Ok(_) => continue,
+            // Removed by other request
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        total_size += metadata.len();
+        entries.push((metadata.modified()?, metadata.len(), path));
+    }
+    if total_size <= max_size {
+        return Ok(());
+    }
+
+    entries.sort_unstable_by_key(|(modified, ..)| *modified);
+    for (_, size, path) in entries {
+        if total_size <= max_size {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        total_size -= size;
+    }
+    Ok(())
+}
+
+/// Remove cached thumbnails of file that was modified or removed
+pub async fn invalidate(state: &ServerState, file: &FileInfo) {
+    let dir = {
+        let settings = state.settings.read().await;
+        if !settings.thumbnail_cache_enabled {
+            return;
+        }
+        settings.thumbnail_cache_dir.clone()
+    };
+
+    let keys = file
+        .hash
+        .as_deref()
+        .map(hash_key)
+        .into_iter()
+        .chain([path_key(&file.path, file.modified)]);
+    for key in keys {
+        for (content_type, _) in EXTENSIONS {
+            match tokio::fs::remove_file(cache_path(&dir, &key, content_type)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => tracing::error!("Error removing thumbnail from cache: {}", e),
+            }
+        }
+    }
+}
@@ -1,20 +1,202 @@
-use std::{ops::DerefMut, path::PathBuf, sync::Arc, time::Duration};
+use std::{collections::HashSet, ops::DerefMut, path::PathBuf, sync::Arc, time::Duration};
 
 use common_lib::{
-    elasticsearch::ELASTICSEARCH_MAX_SIZE, indexer::IndexingStatus, settings::IndexingDirectory,
+    elasticsearch::ELASTICSEARCH_MAX_SIZE,
+    indexer::{WatcherEvent, WatcherEventKind, MAX_WATCHER_EVENT_CNT},
+    settings::{IndexingDirectory, Settings},
+};
+use metrics::increment_counter;
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecursiveMode, Watcher,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    time::Instant,
 };
-use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
-use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tracing_unwrap::{OptionExt, ResultExt};
 
-use crate::{indexer::indexing_process, scanner::process_indexable_files, ServerState};
+use crate::{
+    indexer::{indexing_process, mark_indexing_started, move_files, IndexingTarget},
+    scanner::{lowercase_path, process_indexable_files, ExcludeRegexes},
+    telemetry::WATCHER_EVENTS,
+    ServerState,
+};
+
+/// File system events received during debouncer timeout
+#[derive(Default)]
+struct EventBatch {
+    /// Changed paths
+    paths: Vec<PathBuf>,
+    /// Old and new paths of renamed files
+    moves: Vec<(PathBuf, PathBuf)>,
+    /// Old path of rename waiting for new path, with its tracker
+    rename_from: Option<(Option<usize>, PathBuf)>,
+    /// Trackers of renames that already have both paths
+    paired_trackers: HashSet<usize>,
+}
+
+impl EventBatch {
+    /// Checks if rename with tracker already has both paths
+    fn is_paired(&self, tracker: Option<usize>) -> bool {
+        tracker.is_some_and(|x| self.paired_trackers.contains(&x))
+    }
+
+    fn add_move(&mut self, tracker: Option<usize>, from: PathBuf, to: PathBuf) {
+        if let Some(x) = tracker {
+            self.paired_trackers.insert(x);
+            if self
+                .rename_from
+                .as_ref()
+                .is_some_and(|(from_tracker, _)| *from_tracker == tracker)
+            {
+                self.rename_from = None;
+            }
+        }
+        self.moves.push((from, to));
+    }
+
+    fn add(&mut self, event: Event) {
+        let tracker = event.attrs.tracker();
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                self.paths
+                    .extend(self.rename_from.take().map(|(_, path)| path));
+                self.rename_from = event.paths.into_iter().next().map(|path| (tracker, path));
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                let Some(to) = event.paths.into_iter().next() else {
+                    return;
+                };
+                if self.is_paired(tracker) {
+                    return;
+                }
+                match self.rename_from.take() {
+                    // Backends without trackers send both events of rename one after another
+                    Some((from_tracker, from)) if from_tracker == tracker => {
+                        self.add_move(tracker, from, to);
+                    }
+                    rename_from => {
+                        self.paths.extend(rename_from.map(|(_, path)| path));
+                        self.paths.push(to);
+                    }
+                }
+            }
+            // Sent by some backends in addition to separate events
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                if self.is_paired(tracker) {
+                    return;
+                }
+                let mut paths = event.paths.into_iter();
+                let from = paths.next().unwrap_or_log();
+                let to = paths.next().unwrap_or_log();
+                self.add_move(tracker, from, to);
+            }
+            _ => self.paths.extend(event.paths),
+        }
+    }
+
+    /// Take changed paths and renamed files without excluded paths.
+    /// Renames of directories, excluded files and files with other changes are returned as
+    /// changed paths
+    fn take(
+        &mut self,
+        settings: &Settings,
+        exclude_regexes: &ExcludeRegexes,
+    ) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+        let mut batch = std::mem::take(self);
+        batch
+            .paths
+            .extend(batch.rename_from.take().map(|(_, path)| path));
+
+        let changed: HashSet<_> = batch.paths.iter().cloned().collect();
+        let (moves, other): (Vec<_>, Vec<_>) = batch.moves.into_iter().partition(|(from, to)| {
+            !changed.contains(from)
+                && !changed.contains(to)
+                && !to.is_dir()
+                && !exclude_regexes.is_excluded(settings, from)
+                && !exclude_regexes.is_excluded(settings, to)
+        });
+        batch
+            .paths
+            .extend(other.into_iter().flat_map(|(from, to)| [from, to]));
+
+        let paths = batch
+            .paths
+            .into_iter()
+            .filter(|path| !exclude_regexes.is_excluded(settings, path))
+            .collect();
+        (paths, moves)
+    }
+}
+
+/// Kind of file system event used as label of metric
+fn event_kind(event: &notify::Result<Event>) -> &'static str {
+    match event {
+        Ok(x) => match x.kind {
+            EventKind::Create(_) => "create",
+            EventKind::Modify(ModifyKind::Name(_)) => "rename",
+            EventKind::Modify(_) => "modify",
+            EventKind::Remove(_) => "remove",
+            EventKind::Access(_) => "access",
+            EventKind::Any | EventKind::Other => "other",
+        },
+        Err(_) => "error",
+    }
+}
+
+/// The latest changed paths and new paths of renamed files of batch, for status page
+fn batch_activity(
+    paths: &[PathBuf],
+    moves: &[(PathBuf, PathBuf)],
+) -> Vec<(PathBuf, WatcherEventKind)> {
+    let changed = paths.iter().map(|x| (x.clone(), WatcherEventKind::Changed));
+    let moved = moves
+        .iter()
+        .map(|(_, to)| (to.clone(), WatcherEventKind::Moved));
+    let mut activity: Vec<_> = changed
+        .chain(moved)
+        .rev()
+        .take(MAX_WATCHER_EVENT_CNT)
+        .collect();
+    activity.reverse();
+    activity
+}
+
+/// Send activity of watcher to status page, full indexing is reported instead of paths
+fn send_activity(
+    state: &ServerState,
+    activity: Vec<(PathBuf, WatcherEventKind)>,
+    full_indexing: bool,
+    queued: bool,
+) {
+    let events: Vec<_> = if full_indexing {
+        vec![WatcherEvent {
+            path: None,
+            kind: WatcherEventKind::FullIndexing,
+            queued,
+        }]
+    } else {
+        activity
+            .into_iter()
+            .map(|(path, kind)| WatcherEvent {
+                path: Some(path),
+                kind,
+                queued,
+            })
+            .collect()
+    };
+    for event in events {
+        let _ = state.watcher_events.send(event);
+    }
+}
 
 pub async fn start_watcher(state: Arc<ServerState>) {
-    let debouncer = std::mem::take(state.watcher_debouncer.write().await.deref_mut());
-    if let Some(debouncer) = debouncer {
+    let watcher = std::mem::take(state.watcher.write().await.deref_mut());
+    if watcher.is_some() {
         tracing::info!("Stopping watcher");
-        debouncer.stop_nonblocking();
+        // Event handler stops when channel is closed with watcher
+        drop(watcher);
     }
     if !state.settings.read().await.watcher_enabled {
         return;
@@ -25,15 +207,16 @@ pub async fn start_watcher(state: Arc<ServerState>) {
     let tmp = Arc::clone(&state);
     tokio::spawn(async { event_handler(tmp, rx).await });
 
-    let mut debouncer = new_debouncer(
-        Duration::from_secs_f32(state.settings.read().await.debouncer_timeout),
-        None,
-        move |e| {
-            tx.send(e).unwrap_or_log();
-        },
-    )
+    let mut watcher = notify::recommended_watcher(move |e: notify::Result<Event>| {
+        increment_counter!(WATCHER_EVENTS, "kind" => event_kind(&e));
+        tx.send(e).unwrap_or_log();
+    })
     .expect_or_log("Can't start file system watcher");
 
+    let case_insensitive = state.settings.read().await.case_insensitive_paths;
+    // Duplicate or nested indexing directories can list the same path several times,
+    // watching it again would produce duplicate events
+    let mut watched = HashSet::new();
     for path in process_indexable_files(
         &*state.settings.read().await,
         &state.settings.read().await.indexing_directories,
@@ -43,104 +226,182 @@ pub async fn start_watcher(state: Arc<ServerState>) {
     )
     .expect_or_log("Can't add paths to watcher")
     {
-        if let Err(e) = debouncer
-            .watcher()
-            .watch(&path, RecursiveMode::NonRecursive)
-        {
+        let key = if case_insensitive {
+            lowercase_path(&path)
+        } else {
+            path.clone()
+        };
+        if !watched.insert(key) {
+            continue;
+        }
+        if let Err(e) = watcher.watch(&path, RecursiveMode::NonRecursive) {
             tracing::warn!("Can't add path to watcher: {}", e);
         }
     }
 
-    *state.watcher_debouncer.write().await = Some(debouncer);
+    *state.watcher.write().await = Some(watcher);
 }
 
-async fn event_handler(
-    state: Arc<ServerState>,
-    mut watcher_rx: UnboundedReceiver<DebounceEventResult>,
-) {
-    let mut indexing_status = state.indexing_status.read().await.clone();
-    let mut indexing_rx = state.indexing_events.subscribe();
-    let mut paths = Some(Vec::new());
+/// Start indexing of changed paths and renames if there are any and indexing isn't running,
+/// all files are indexed if paths are `None`. Returns `true` if indexing was started
+async fn process_paths(
+    state: &Arc<ServerState>,
+    paths: &mut Option<Vec<PathBuf>>,
+    moves: &mut Vec<(PathBuf, PathBuf)>,
+) -> bool {
+    if paths.as_ref().is_some_and(|x| x.is_empty()) && moves.is_empty() {
+        return false;
+    }
+    // Indexing is marked as started before renames are applied to index,
+    // so other indexing can't start while files are moved
+    let previous_status = match mark_indexing_started(state).await {
+        Ok(x) => x,
+        Err(_) => return false,
+    };
 
-    let process_paths = |indexing_status: &IndexingStatus, paths: &mut Option<Vec<PathBuf>>| {
-        if indexing_status.can_start() && (paths.is_none() || !paths.as_ref().unwrap().is_empty()) {
-            let state_tmp = Arc::clone(&state);
-            let paths_tmp = std::mem::replace(paths, Some(Vec::new()));
-            tokio::spawn(async move {
-                match paths_tmp {
-                    Some(mut x) => {
-                        x.sort_unstable();
-                        x.dedup();
-
-                        {
-                            let mut tmp = state_tmp.watcher_debouncer.write().await;
-                            let debouncer = tmp.as_mut().unwrap_or_log();
-                            for path in process_indexable_files(
-                                &*state_tmp.settings.read().await,
-                                &x.iter()
-                                    .map(|path| IndexingDirectory {
-                                        path: path.to_path_buf(),
-                                        exclude: false,
-                                        watch: true,
-                                    })
-                                    .collect::<Vec<_>>(),
-                                |_, path| Some(path),
-                                true,
-                                true,
-                            )
-                            .expect_or_log("Can't add paths to watcher")
-                            {
-                                if let Err(e) = debouncer
-                                    .watcher()
-                                    .watch(&path, RecursiveMode::NonRecursive)
-                                {
-                                    tracing::warn!("Can't add path to watcher: {}", e);
-                                }
-                            }
-                        }
+    let state = Arc::clone(state);
+    let paths = std::mem::replace(paths, Some(Vec::new()));
+    let moves = std::mem::take(moves);
+    tokio::spawn(async move {
+        match paths {
+            Some(mut x) => {
+                // Renamed files without changes aren't processed again
+                x.append(&mut move_files(Arc::clone(&state), moves).await);
+                if x.is_empty() {
+                    *state.indexing_status.write().await = previous_status;
+                    return;
+                }
+                x.sort_unstable();
+                x.dedup();
 
-                        indexing_process(Arc::clone(&state_tmp), Some(x)).await;
+                {
+                    let mut tmp = state.watcher.write().await;
+                    let watcher = tmp.as_mut().unwrap_or_log();
+                    for path in process_indexable_files(
+                        &*state.settings.read().await,
+                        &x.iter()
+                            .map(|path| IndexingDirectory {
+                                path: path.to_path_buf(),
+                                exclude: false,
+                                watch: true,
+                                ..Default::default()
+                            })
+                            .collect::<Vec<_>>(),
+                        |_, path| Some(path),
+                        true,
+                        true,
+                    )
+                    .expect_or_log("Can't add paths to watcher")
+                    {
+                        if let Err(e) = watcher.watch(&path, RecursiveMode::NonRecursive) {
+                            tracing::warn!("Can't add path to watcher: {}", e);
+                        }
                     }
-                    None => indexing_process(state_tmp, None).await,
                 }
-            });
+
+                indexing_process(Arc::clone(&state), Some(x), IndexingTarget::Current, None).await;
+            }
+            // Renames are indexed with all files
+            None => indexing_process(state, None, IndexingTarget::Current, None).await,
         }
-    };
+    });
+    true
+}
+
+async fn event_handler(
+    state: Arc<ServerState>,
+    mut watcher_rx: UnboundedReceiver<notify::Result<Event>>,
+) {
+    let mut indexing_rx = state.indexing_events.read().await.subscribe();
+    let debouncer_timeout = Duration::from_secs_f32(state.settings.read().await.debouncer_timeout);
+    let mut batch = EventBatch::default();
+    let mut batch_deadline = None;
+    let mut paths = Some(Vec::new());
+    let mut moves = Vec::new();
 
     loop {
         tokio::select! {
             indexing_event = indexing_rx.recv() => {
-                match indexing_event {
-                    Ok(e) => indexing_status.process_event(e),
-                    Err(_) => break,
+                if indexing_event.is_err() {
+                    break;
                 }
-                process_paths(&indexing_status, &mut paths);
+                process_paths(&state, &mut paths, &mut moves).await;
             },
             watch_event = watcher_rx.recv() => {
                 match watch_event {
-                    Some(e) => {
-                        let mut curr_paths = match e {
-                            Ok(x) => (x.len() <= ELASTICSEARCH_MAX_SIZE as usize).then(|| {
-                                x.into_iter()
-                                    .map(|event| event.path)
-                                    .collect()
-                            }),
-                            Err(e) => {
-                                tracing::warn!("File system watcher errors: {:#?}", e);
-                                continue;
-                            }
-                        };
-
-                        match (&mut paths, &mut curr_paths) {
-                            (Some(x), Some(y)) => x.append(y),
-                            (None, _) => {},
-                            (_, None) => paths = None,
-                        }
-                        process_paths(&indexing_status, &mut paths);
+                    Some(Ok(e)) => {
+                        batch.add(e);
+                        // Events are collected during timeout after the first of them
+                        batch_deadline.get_or_insert_with(|| Instant::now() + debouncer_timeout);
                     }
+                    Some(Err(e)) => tracing::warn!("File system watcher error: {:#?}", e),
                     None => break,
                 }
             },
+            _ = tokio::time::sleep_until(batch_deadline.unwrap_or_else(Instant::now)),
+                if batch_deadline.is_some() => {
+                batch_deadline = None;
+                // Events of excluded files don't start indexing
+                let (mut curr_paths, mut curr_moves) = {
+                    let settings = state.settings.read().await;
+                    let exclude_regexes = ExcludeRegexes::new(&settings)
+                        .expect_or_log("Can't filter watcher events");
+                    batch.take(&settings, &exclude_regexes)
+                };
+                if curr_paths.is_empty() && curr_moves.is_empty() {
+                    continue;
+                }
+                // Paths aren't copied if nobody watches status
+                let activity = if state.watcher_events.receiver_count() > 0 {
+                    batch_activity(&curr_paths, &curr_moves)
+                } else {
+                    Vec::new()
+                };
+
+                match &mut paths {
+                    Some(_) if curr_paths.len() > ELASTICSEARCH_MAX_SIZE as usize => {
+                        paths = None;
+                        moves.clear();
+                    }
+                    Some(x) => {
+                        x.append(&mut curr_paths);
+                        moves.append(&mut curr_moves);
+                    }
+                    None => {}
+                }
+                let full_indexing = paths.is_none();
+                let queued = process_paths(&state, &mut paths, &mut moves).await;
+                send_activity(&state, activity, full_indexing, queued);
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common_lib::indexer::IndexingStatus;
+
+    use crate::test_util::{test_settings, test_state};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn indexing_is_marked_as_started_before_files_are_moved() {
+        let state = test_state(test_settings()).await;
+        let mut paths = Some(Vec::new());
+        let mut moves = vec![(PathBuf::from("/docs/a.pdf"), PathBuf::from("/docs/b.pdf"))];
+
+        // Spawned indexing doesn't run until test yields
+        assert!(process_paths(&state, &mut paths, &mut moves).await);
+        assert!(moves.is_empty());
+        assert!(matches!(
+            *state.indexing_status.read().await,
+            IndexingStatus::CalculatingDiff
+        ));
+
+        // Paths changed meanwhile wait for the next indexing
+        let mut paths = Some(vec![PathBuf::from("/docs/c.pdf")]);
+        assert!(!process_paths(&state, &mut paths, &mut moves).await);
+        assert_eq!(paths, Some(vec![PathBuf::from("/docs/c.pdf")]));
+    }
+}
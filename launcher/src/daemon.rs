@@ -0,0 +1,178 @@
+use std::{process::ExitStatus, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use common_lib::{
+    auth::tokens_match,
+    launcher::{Component, WakeStatus},
+    settings::Settings,
+};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tower_http::cors::CorsLayer;
+use tracing_unwrap::ResultExt;
+
+use crate::{await_component, component_command, read_settings_file, run_command, Args};
+
+type ComponentTask = Option<JoinHandle<tokio::io::Result<ExitStatus>>>;
+
+/// Running components
+#[derive(Default)]
+struct ComponentTasks {
+    elasticsearch: ComponentTask,
+    tika: ComponentTask,
+    nn_server: ComponentTask,
+    indexer: ComponentTask,
+}
+
+struct DaemonState {
+    args: Args,
+    reqwest_client: reqwest::Client,
+    status: Mutex<WakeStatus>,
+    tasks: Mutex<ComponentTasks>,
+}
+
+impl ComponentTasks {
+    fn get_mut(&mut self, component: Component) -> &mut ComponentTask {
+        match component {
+            Component::Elasticsearch => &mut self.elasticsearch,
+            Component::Tika => &mut self.tika,
+            Component::NNServer => &mut self.nn_server,
+            Component::Indexer => &mut self.indexer,
+        }
+    }
+}
+
+fn is_running(task: &ComponentTask) -> bool {
+    matches!(task, Some(x) if !x.is_finished())
+}
+
+/// Start component if it isn't running and wait until it's available
+async fn start_component(
+    state: &DaemonState,
+    component: Component,
+    settings: &Settings,
+) -> Result<(), String> {
+    *state.status.lock().await = WakeStatus::Starting(component);
+    {
+        let mut tasks = state.tasks.lock().await;
+        let task = tasks.get_mut(component);
+        if !is_running(task) {
+            let command = component_command(component, &settings.launcher)?;
+            tracing::info!("Starting {}", component);
+            *task = Some(
+                run_command(command).map_err(|e| format!("{component} can't be started: {e}"))?,
+            );
+        }
+    }
+    await_component(component, &state.reqwest_client, settings)
+        .await
+        .map_err(|e| format!("{component} didn't start: {e}"))?;
+    tracing::info!("{} started", component);
+    Ok(())
+}
+
+/// Start all components that aren't running
+async fn start_components(state: Arc<DaemonState>) {
+    let settings = read_settings_file().await;
+
+    let res = async {
+        for component in Component::ALL {
+            if state.args.is_enabled(component) {
+                start_component(&state, component, &settings).await?;
+            }
+        }
+        Ok::<_, String>(())
+    }
+    .await;
+
+    *state.status.lock().await = match res {
+        Ok(()) => WakeStatus::Running,
+        Err(e) => {
+            tracing::error!("{}", e);
+            WakeStatus::Failed(e)
+        }
+    };
+}
+
+/// Report indexer that has exited as idle
+async fn update_status(state: &DaemonState, status: &mut WakeStatus) {
+    if *status == WakeStatus::Running && !is_running(&state.tasks.lock().await.indexer) {
+        *status = WakeStatus::Idle;
+    }
+}
+
+/// Reject requests without API token of indexer from settings, if it's set
+async fn check_token(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let api_token = read_settings_file().await.api_token;
+    if let Some(api_token) = api_token.filter(|x| !x.is_empty()) {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.strip_prefix("Bearer "));
+        if !token.is_some_and(|x| tokens_match(x, &api_token)) {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid API token".to_owned(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn wake_status(
+    State(state): State<Arc<DaemonState>>,
+    headers: HeaderMap,
+) -> Result<Json<WakeStatus>, (StatusCode, String)> {
+    check_token(&headers).await?;
+    let mut status = state.status.lock().await;
+    update_status(&state, &mut status).await;
+    Ok(Json(status.clone()))
+}
+
+/// Start components if they aren't running or starting already
+async fn wake(
+    State(state): State<Arc<DaemonState>>,
+    headers: HeaderMap,
+) -> Result<Json<WakeStatus>, (StatusCode, String)> {
+    check_token(&headers).await?;
+    // Status is locked until starting is scheduled to prevent double starts
+    let mut status = state.status.lock().await;
+    update_status(&state, &mut status).await;
+    if matches!(*status, WakeStatus::Idle | WakeStatus::Failed(_)) {
+        *status = WakeStatus::Starting(Component::Elasticsearch);
+        tokio::spawn(start_components(Arc::clone(&state)));
+    }
+    Ok(Json(status.clone()))
+}
+
+/// Serve wake endpoint, starting components on demand
+pub async fn run_daemon(args: Args, reqwest_client: reqwest::Client) {
+    let address = args.daemon_address;
+    let state = Arc::new(DaemonState {
+        args,
+        reqwest_client,
+        status: Mutex::new(WakeStatus::Idle),
+        tasks: Default::default(),
+    });
+
+    // Client is served by indexer on another port, other sites can't wake it
+    let indexer_url = format!("http://{}", read_settings_file().await.indexer_address);
+    let cors = CorsLayer::new()
+        .allow_origin(HeaderValue::from_str(&indexer_url).unwrap_or_log())
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([header::AUTHORIZATION]);
+    let app = Router::new()
+        .route("/wake", get(wake_status).post(wake))
+        .with_state(state)
+        .layer(cors);
+
+    tracing::info!("Waiting for wake requests on {}", address);
+    axum::Server::bind(&address)
+        .serve(app.into_make_service())
+        .await
+        .expect_or_log("Can't start wake endpoint");
+}
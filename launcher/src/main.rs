@@ -1,21 +1,35 @@
-use std::{process::ExitStatus, time::Duration};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use clap::{ArgAction, Parser};
-use common_lib::settings::Settings;
+use common_lib::{
+    launcher::Component,
+    settings::{LauncherSettings, Settings},
+};
 use reqwest::Url;
-use tokio::process::Command;
+use tokio::{process::Command, sync::mpsc, task::JoinHandle};
 use tracing_subscriber::{
     filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
 };
-use tracing_unwrap::ResultExt;
+use tracing_unwrap::{OptionExt, ResultExt};
+
+use crate::{
+    daemon::run_daemon,
+    supervisor::{shutdown_signal, spawn_supervised, SupervisedComponent, Supervision},
+};
+
+mod daemon;
+mod supervisor;
 
 const SETTINGS_FILE_PATH: &str = "Settings.toml";
-const ELASTICSEARCH_FOLDER: &str = "elasticsearch-8.7.0";
-const TIKA_JAR: &str = "tika-server-standard-2.7.0.jar";
-const TIKA_CONFIG: &str = "tika-config.xml";
-const NN_SERVER_PATH: &str = "nn_server/nn_server";
-const ONNX_RUNTIME_LIB_FOLDER: &str = "onnxruntime-linux-x64-gpu-1.14.1/lib";
-const INDEXER_PATH: &str = "./indexer";
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
 const REQUEST_RETRIES: u32 = 120;
@@ -24,12 +38,44 @@ const REQUEST_RETRY_DURATION: Duration = Duration::from_secs(1);
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Don't run Apache Tika
+    /// Don't run Apache Tika, same as `--skip tika`
     #[arg(long = "disable-tika", action = ArgAction::SetFalse)]
     tika_enabled: bool,
-    /// Don't run nn_server
+    /// Don't run nn_server, same as `--skip nn_server`
     #[arg(long = "disable-nn-server", action = ArgAction::SetFalse)]
     nn_server_enabled: bool,
+    /// Run only these components: elasticsearch, tika, nn_server or indexer. Can be repeated
+    #[arg(long, value_name = "COMPONENT")]
+    only: Vec<Component>,
+    /// Don't run these components. Can be repeated
+    #[arg(long, value_name = "COMPONENT")]
+    skip: Vec<Component>,
+    /// Only serve wake endpoint, starting other components when it's requested
+    #[arg(long)]
+    daemon: bool,
+    /// Address of wake endpoint in daemon mode
+    #[arg(long, default_value = "127.0.0.1:11001")]
+    daemon_address: SocketAddr,
+    /// Don't restart components that exit, only wait for them
+    #[arg(long)]
+    no_restart: bool,
+    /// Restarts of component before giving up, Elasticsearch or indexer giving up stops everything
+    #[arg(long, default_value_t = 5)]
+    max_restarts: u32,
+}
+
+impl Args {
+    /// Whether component is run by launcher
+    fn is_enabled(&self, component: Component) -> bool {
+        let enabled = match component {
+            Component::Tika => self.tika_enabled,
+            Component::NNServer => self.nn_server_enabled,
+            Component::Elasticsearch | Component::Indexer => true,
+        };
+        enabled
+            && (self.only.is_empty() || self.only.contains(&component))
+            && !self.skip.contains(&component)
+    }
 }
 
 pub async fn read_settings_file() -> Settings {
@@ -42,59 +88,105 @@ pub async fn read_settings_file() -> Settings {
     }
 }
 
-async fn run_elasticsearch() -> tokio::io::Result<ExitStatus> {
-    let mut es_path = ELASTICSEARCH_FOLDER.to_owned() + "/bin/elasticsearch";
+/// Check that path of component exists, error names setting that changes it
+fn check_path(component: Component, path: &Path, setting: &str) -> Result<(), String> {
+    if path.exists() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Can't start {component}: {} doesn't exist, \
+            set `{setting}` in [launcher] section of {SETTINGS_FILE_PATH}",
+            path.display()
+        ))
+    }
+}
+
+/// Add `.exe` on Windows to path of executable without extension
+fn executable_path(path: &Path) -> PathBuf {
+    if cfg!(windows) && path.extension().is_none() {
+        path.with_extension("exe")
+    } else {
+        path.to_owned()
+    }
+}
+
+fn elasticsearch_command(settings: &LauncherSettings) -> Result<Command, String> {
+    let mut es_path = settings.elasticsearch_path.join("bin/elasticsearch");
     if cfg!(windows) {
-        es_path += ".bat";
+        es_path.set_extension("bat");
     }
-    Command::new(es_path).spawn().unwrap_or_log().wait().await
+    check_path(Component::Elasticsearch, &es_path, "elasticsearch_path")?;
+    Ok(Command::new(es_path))
 }
 
-async fn run_tika() -> tokio::io::Result<ExitStatus> {
+fn tika_command(settings: &LauncherSettings) -> Result<Command, String> {
     if cfg!(windows) {
         let tika_path = "tika.bat".to_owned();
-        Command::new(tika_path).spawn().unwrap_or_log().wait().await
+        Ok(Command::new(tika_path))
     } else {
-        let java_path = ELASTICSEARCH_FOLDER.to_owned() + "/jdk/bin/java";
-        Command::new(java_path)
-            .args(["-jar", TIKA_JAR, "-c", TIKA_CONFIG])
-            .spawn()
-            .unwrap_or_log()
-            .wait()
-            .await
+        let java_path = settings.elasticsearch_path.join("jdk/bin/java");
+        check_path(Component::Tika, &java_path, "elasticsearch_path")?;
+        check_path(Component::Tika, &settings.tika_jar_path, "tika_jar_path")?;
+        check_path(
+            Component::Tika,
+            &settings.tika_config_path,
+            "tika_config_path",
+        )?;
+        let mut command = Command::new(java_path);
+        command
+            .arg("-jar")
+            .arg(&settings.tika_jar_path)
+            .arg("-c")
+            .arg(&settings.tika_config_path);
+        Ok(command)
     }
 }
 
-async fn run_nn_server() -> tokio::io::Result<ExitStatus> {
-    if cfg!(windows) {
-        let nn_server_path = NN_SERVER_PATH.to_owned() + ".exe";
-        Command::new(nn_server_path)
-            .spawn()
-            .unwrap_or_log()
-            .wait()
-            .await
-    } else {
+fn nn_server_command(settings: &LauncherSettings) -> Result<Command, String> {
+    let nn_server_path = executable_path(&settings.nn_server_path);
+    check_path(Component::NNServer, &nn_server_path, "nn_server_path")?;
+    let mut command = Command::new(nn_server_path);
+    if !cfg!(windows) {
         let env_name = "LD_LIBRARY_PATH";
-        let env_value = std::fs::canonicalize(ONNX_RUNTIME_LIB_FOLDER).unwrap_or_log();
-        Command::new(NN_SERVER_PATH)
-            .env(env_name, env_value)
-            .spawn()
-            .unwrap_or_log()
-            .wait()
-            .await
+        check_path(
+            Component::NNServer,
+            &settings.onnx_runtime_lib_path,
+            "onnx_runtime_lib_path",
+        )?;
+        let env_value = std::fs::canonicalize(&settings.onnx_runtime_lib_path).unwrap_or_log();
+        command.env(env_name, env_value);
     }
+    Ok(command)
 }
 
-async fn run_indexer() -> tokio::io::Result<ExitStatus> {
-    let mut indexer_path = INDEXER_PATH.to_owned();
-    if cfg!(windows) {
-        indexer_path += ".exe";
-    }
-    Command::new(indexer_path)
-        .spawn()
-        .unwrap_or_log()
-        .wait()
-        .await
+fn indexer_command(settings: &LauncherSettings) -> Result<Command, String> {
+    let indexer_path = executable_path(&settings.indexer_path);
+    check_path(Component::Indexer, &indexer_path, "indexer_path")?;
+    Ok(Command::new(indexer_path))
+}
+
+/// Command starting component with paths from settings, the component is killed if its handle
+/// is dropped. Fails if path of component doesn't exist
+pub fn component_command(
+    component: Component,
+    settings: &LauncherSettings,
+) -> Result<Command, String> {
+    let mut command = match component {
+        Component::Elasticsearch => elasticsearch_command(settings),
+        Component::Tika => tika_command(settings),
+        Component::NNServer => nn_server_command(settings),
+        Component::Indexer => indexer_command(settings),
+    }?;
+    command.kill_on_drop(true);
+    Ok(command)
+}
+
+/// Start component, returned task waits for it to exit
+pub fn run_command(
+    mut command: Command,
+) -> tokio::io::Result<JoinHandle<tokio::io::Result<ExitStatus>>> {
+    let mut child = command.spawn()?;
+    Ok(tokio::spawn(async move { child.wait().await }))
 }
 
 async fn retry_request(reqwest_client: &reqwest::Client, url: Url) -> reqwest::Result<()> {
@@ -114,10 +206,25 @@ async fn retry_request(reqwest_client: &reqwest::Client, url: Url) -> reqwest::R
     res
 }
 
+/// Wait for health of cluster through the first Elasticsearch node, with credentials from settings
 async fn await_elasticsearch(
     reqwest_client: &reqwest::Client,
-    mut elasticsearch_url: Url,
+    settings: &Settings,
 ) -> reqwest::Result<()> {
+    let mut elasticsearch_url = settings
+        .elasticsearch_urls
+        .first()
+        .cloned()
+        .expect_or_log("No Elasticsearch URLs in settings");
+    if let Some(user) = settings
+        .elasticsearch_user
+        .as_ref()
+        .filter(|x| !x.is_empty())
+    {
+        // URL credentials are sent by reqwest with basic authentication
+        let _ = elasticsearch_url.set_username(user);
+        let _ = elasticsearch_url.set_password(settings.elasticsearch_password.as_deref());
+    }
     elasticsearch_url.set_path("/_cluster/health");
     elasticsearch_url.set_query(Some("wait_for_status=yellow&timeout=2m"));
     retry_request(reqwest_client, elasticsearch_url).await
@@ -136,6 +243,91 @@ async fn await_nn_server(
     retry_request(reqwest_client, nn_server_url).await
 }
 
+/// Wait for health endpoint of indexer, it fails while Elasticsearch is unreachable
+pub async fn await_indexer(
+    reqwest_client: &reqwest::Client,
+    settings: &Settings,
+) -> reqwest::Result<()> {
+    let mut indexer_url =
+        Url::parse(&format!("http://{}", settings.indexer_address)).unwrap_or_log();
+    indexer_url.set_path("/health");
+    retry_request(reqwest_client, indexer_url).await
+}
+
+/// Wait until component is available
+pub async fn await_component(
+    component: Component,
+    reqwest_client: &reqwest::Client,
+    settings: &Settings,
+) -> reqwest::Result<()> {
+    match component {
+        Component::Elasticsearch => await_elasticsearch(reqwest_client, settings).await,
+        Component::Tika => await_tika(reqwest_client, settings.tika_url.clone()).await,
+        Component::NNServer => {
+            await_nn_server(reqwest_client, settings.nn_server_url.clone()).await
+        }
+        Component::Indexer => await_indexer(reqwest_client, settings).await,
+    }
+}
+
+/// Start components in order of dependencies and wait until Elasticsearch or indexer
+/// exhausts restarts. Without restarts, wait until all components exit
+async fn run_components(
+    args: &Args,
+    supervision: &Arc<Supervision>,
+    components: &mut Vec<SupervisedComponent>,
+    exited: &mut mpsc::UnboundedReceiver<Component>,
+) -> Option<Component> {
+    let reqwest_client = &supervision.reqwest_client;
+    let settings = &supervision.settings;
+
+    // Indexer is started after its dependencies are available
+    for component in [
+        Component::Elasticsearch,
+        Component::Tika,
+        Component::NNServer,
+    ] {
+        if args.is_enabled(component) {
+            components.push(spawn_supervised(supervision, component));
+        }
+    }
+
+    if args.is_enabled(Component::Elasticsearch) {
+        await_elasticsearch(reqwest_client, settings)
+            .await
+            .expect_or_log("Elasticsearch didn't start");
+        tracing::info!("Elasticsearch started");
+    }
+    if args.is_enabled(Component::Tika) {
+        await_tika(reqwest_client, settings.tika_url.clone())
+            .await
+            .expect_or_log("Apache Tika didn't start");
+        tracing::info!("Apache Tika started");
+    }
+    if args.is_enabled(Component::NNServer) {
+        await_nn_server(reqwest_client, settings.nn_server_url.clone())
+            .await
+            .expect_or_log("nn_server didn't start");
+        tracing::info!("nn_server started");
+    }
+
+    if args.is_enabled(Component::Indexer) {
+        components.push(spawn_supervised(supervision, Component::Indexer));
+        match await_indexer(reqwest_client, settings).await {
+            Ok(_) => tracing::info!("Indexer started"),
+            Err(e) => tracing::error!("Indexer didn't become healthy: {}", e),
+        }
+    }
+
+    for _ in 0..components.len() {
+        let component = exited.recv().await.unwrap_or_log();
+        if !args.no_restart && matches!(component, Component::Elasticsearch | Component::Indexer) {
+            return Some(component);
+        }
+    }
+    None
+}
+
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
@@ -149,56 +341,63 @@ async fn main() {
         )
         .init();
 
-    let settings = read_settings_file().await;
-
-    let elasticsearch_task = tokio::spawn(async { run_elasticsearch().await });
-    let tika_task = args
-        .tika_enabled
-        .then(|| tokio::spawn(async { run_tika().await }));
-    let nn_server_task = args
-        .nn_server_enabled
-        .then(|| tokio::spawn(async { run_nn_server().await }));
-
     let reqwest_client = reqwest::Client::builder()
         .timeout(REQUEST_TIMEOUT)
         .build()
         .unwrap_or_log();
 
-    await_elasticsearch(&reqwest_client, settings.elasticsearch_url)
-        .await
-        .expect_or_log("Elasticsearch didn't start");
-    tracing::info!("Elasticsearch started");
-    if args.tika_enabled {
-        await_tika(&reqwest_client, settings.tika_url)
-            .await
-            .expect_or_log("Apache Tika didn't start");
-        tracing::info!("Apache Tika started");
+    if args.daemon {
+        run_daemon(args, reqwest_client).await;
+        return;
     }
-    if args.nn_server_enabled {
-        await_nn_server(&reqwest_client, settings.nn_server_url)
-            .await
-            .expect_or_log("nn_server didn't start");
-        tracing::info!("nn_server started");
+
+    let settings = read_settings_file().await;
+    // Missing paths are reported before anything is started
+    for component in Component::ALL {
+        if !args.is_enabled(component) {
+            continue;
+        }
+        if let Err(e) = component_command(component, &settings.launcher) {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
     }
 
-    let indexer_task = tokio::spawn(async { run_indexer().await });
+    let max_restarts = if args.no_restart {
+        0
+    } else {
+        args.max_restarts
+    };
+    let (exited_sender, mut exited) = mpsc::unbounded_channel();
+    let supervision = Arc::new(Supervision {
+        reqwest_client,
+        settings,
+        max_restarts,
+        exited: exited_sender,
+        stopping: AtomicBool::new(false),
+    });
+
+    let mut components = Vec::new();
+    let failed_component = tokio::select! {
+        res = run_components(&args, &supervision, &mut components, &mut exited) => res,
+        _ = shutdown_signal() => {
+            tracing::info!("Received shutdown signal, stopping components");
+            None
+        }
+    };
+    if let Some(component) = failed_component {
+        tracing::error!(
+            component = %component,
+            "Critical component failed, stopping other components"
+        );
+    }
 
-    elasticsearch_task
-        .await
-        .unwrap_or_log()
-        .expect_or_log("Failed to start Elasticsearch");
-    if let Some(task) = tika_task {
-        task.await
-            .unwrap_or_log()
-            .expect_or_log("Failed to start Apache Tika");
+    // Components are stopped in reverse order of starting, so they don't lose their dependencies
+    supervision.stopping.store(true, Ordering::Relaxed);
+    for component in components.into_iter().rev() {
+        component.stop().await;
     }
-    if let Some(task) = nn_server_task {
-        task.await
-            .unwrap_or_log()
-            .expect_or_log("Failed to start nn_server");
+    if failed_component.is_some() {
+        std::process::exit(1);
     }
-    indexer_task
-        .await
-        .unwrap_or_log()
-        .expect_or_log("Failed to start indexer");
 }
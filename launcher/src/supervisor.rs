@@ -0,0 +1,277 @@
+use std::{
+    process::ExitStatus,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use common_lib::{launcher::Component, settings::Settings};
+use tokio::{
+    process::Child,
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tracing_unwrap::ResultExt;
+
+use crate::{await_component, component_command};
+
+const RESTART_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const RESTART_MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Restarts of component that ran at least this long are counted from zero again
+const RESTART_RESET_DURATION: Duration = Duration::from_secs(10 * 60);
+/// Time for component to exit after it's asked to, before it's killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// State shared by supervisors of components
+pub struct Supervision {
+    pub reqwest_client: reqwest::Client,
+    pub settings: Settings,
+    pub max_restarts: u32,
+    /// Components that exhausted restarts
+    pub exited: mpsc::UnboundedSender<Component>,
+    /// Components are being stopped, so they aren't restarted when they exit.
+    /// Ctrl+C is sent to all processes of terminal, so they can exit before they are stopped
+    pub stopping: AtomicBool,
+}
+
+/// Component running under supervisor
+pub struct SupervisedComponent {
+    task: JoinHandle<()>,
+    shutdown: oneshot::Sender<()>,
+}
+
+impl SupervisedComponent {
+    /// Stop component and wait until it exits
+    pub async fn stop(self) {
+        // Supervisor that exhausted restarts has already finished
+        let _ = self.shutdown.send(());
+        self.task.await.unwrap_or_log();
+    }
+}
+
+/// Start component with supervisor restarting it when it exits
+pub fn spawn_supervised(
+    supervision: &Arc<Supervision>,
+    component: Component,
+) -> SupervisedComponent {
+    let (shutdown, shutdown_receiver) = oneshot::channel();
+    let task = tokio::spawn(supervise(
+        Arc::clone(supervision),
+        component,
+        shutdown_receiver,
+    ));
+    SupervisedComponent { task, shutdown }
+}
+
+/// Wait for Ctrl+C or SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect_or_log("Can't listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect_or_log("Can't listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Wait for component to exit. Availability of restarted component is checked meanwhile,
+/// the first start is checked by caller
+async fn wait_child(
+    supervision: &Supervision,
+    component: Component,
+    child: &mut Child,
+    restarted: bool,
+) -> tokio::io::Result<ExitStatus> {
+    if restarted {
+        let await_started = await_component(
+            component,
+            &supervision.reqwest_client,
+            &supervision.settings,
+        );
+        tokio::select! {
+            res = child.wait() => return res,
+            res = await_started => match res {
+                Ok(()) => tracing::info!(component = %component, "Component restarted"),
+                Err(e) => tracing::error!(
+                    component = %component,
+                    error = %e,
+                    "Restarted component didn't become available"
+                ),
+            },
+        }
+    }
+    child.wait().await
+}
+
+/// Run component, restarting it with exponential backoff when it exits, until restarts are
+/// exhausted or shutdown is requested
+async fn supervise(
+    supervision: Arc<Supervision>,
+    component: Component,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    let mut restarted = false;
+    let mut restarts = 0;
+    let mut backoff = RESTART_INITIAL_BACKOFF;
+    loop {
+        let start_time = Instant::now();
+        let child = component_command(component, &supervision.settings.launcher)
+            .and_then(|mut command| command.spawn().map_err(|e| e.to_string()));
+        let res = match child {
+            Ok(mut child) => {
+                let res = tokio::select! {
+                    res = wait_child(&supervision, component, &mut child, restarted) => Some(res),
+                    _ = &mut shutdown => None,
+                };
+                match res {
+                    Some(res) => res.map_err(|e| e.to_string()),
+                    None => {
+                        stop_child(component, child).await;
+                        return;
+                    }
+                }
+            }
+            Err(e) => Err(e),
+        };
+        match res {
+            Ok(status) => tracing::warn!(component = %component, %status, "Component exited"),
+            Err(e) => tracing::error!(
+                component = %component,
+                error = %e,
+                "Error running component"
+            ),
+        }
+
+        if supervision.stopping.load(Ordering::Relaxed) {
+            return;
+        }
+        if start_time.elapsed() >= RESTART_RESET_DURATION {
+            restarts = 0;
+            backoff = RESTART_INITIAL_BACKOFF;
+        }
+        if restarts >= supervision.max_restarts {
+            tracing::error!(component = %component, restarts, "Component won't be restarted");
+            let _ = supervision.exited.send(component);
+            return;
+        }
+        restarts += 1;
+        tracing::warn!(
+            component = %component,
+            restart = restarts,
+            max_restarts = supervision.max_restarts,
+            backoff_secs = backoff.as_secs(),
+            "Restarting component"
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = &mut shutdown => return,
+        }
+        if supervision.stopping.load(Ordering::Relaxed) {
+            return;
+        }
+        backoff = (backoff * 2).min(RESTART_MAX_BACKOFF);
+        restarted = true;
+    }
+}
+
+/// Ask component to exit, killing it if it doesn't exit in time
+async fn stop_child(component: Component, mut child: Child) {
+    tracing::info!(component = %component, "Stopping component");
+    if let Err(e) = request_exit(&child).await {
+        tracing::warn!(
+            component = %component,
+            error = %e,
+            "Error asking component to exit"
+        );
+    }
+    match tokio::time::timeout(STOP_TIMEOUT, child.wait()).await {
+        Ok(Ok(status)) => tracing::info!(component = %component, %status, "Component stopped"),
+        Ok(Err(e)) => tracing::error!(
+            component = %component,
+            error = %e,
+            "Error waiting for component to stop"
+        ),
+        Err(_) => {
+            tracing::warn!(
+                component = %component,
+                timeout_secs = STOP_TIMEOUT.as_secs(),
+                "Component didn't stop in time, killing it"
+            );
+            match kill_child(&mut child).await {
+                Ok(()) => tracing::info!(component = %component, "Component killed"),
+                Err(e) => tracing::error!(
+                    component = %component,
+                    error = %e,
+                    "Error killing component"
+                ),
+            }
+        }
+    }
+}
+
+/// Send SIGTERM to child
+#[cfg(unix)]
+async fn request_exit(child: &Child) -> tokio::io::Result<()> {
+    use nix::{
+        sys::signal::{kill, Signal},
+        unistd::Pid,
+    };
+
+    // Child that has already exited has no ID
+    if let Some(id) = child.id() {
+        kill(Pid::from_raw(id as i32), Signal::SIGTERM)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn kill_child(child: &mut Child) -> tokio::io::Result<()> {
+    child.kill().await
+}
+
+/// Run `taskkill` for tree of processes of child, as components started by batch files
+/// run Java in child process that would be left running otherwise
+#[cfg(windows)]
+async fn taskkill(child: &Child, force: bool) -> tokio::io::Result<()> {
+    let Some(id) = child.id() else {
+        return Ok(());
+    };
+    let mut command = tokio::process::Command::new("taskkill");
+    command.args(["/T", "/PID", &id.to_string()]);
+    if force {
+        command.arg("/F");
+    }
+    let status = command.status().await?;
+    if !status.success() {
+        return Err(tokio::io::Error::new(
+            tokio::io::ErrorKind::Other,
+            format!("taskkill exited with {status}"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn request_exit(child: &Child) -> tokio::io::Result<()> {
+    taskkill(child, false).await
+}
+
+#[cfg(windows)]
+async fn kill_child(child: &mut Child) -> tokio::io::Result<()> {
+    taskkill(child, true).await?;
+    child.wait().await.map(|_| ())
+}
@@ -1,5 +1,9 @@
 use std::{
     fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
@@ -19,11 +23,32 @@ pub enum Command<In, Out> {
     Flush,
 }
 
-/// Start batch process with given settings and processing function, returns command sender
-pub fn start_batch_process<In, Out, F>(
-    settings: &NNSettings,
-    process: F,
-) -> mpsc::Sender<Command<In, Out>>
+/// Sender of commands to batch process
+pub struct BatchSender<In, Out> {
+    tx: mpsc::Sender<Command<In, Out>>,
+    /// Number of items that are added and not processed yet
+    queued: Arc<AtomicUsize>,
+}
+
+impl<In, Out> BatchSender<In, Out> {
+    /// Process current batch without waiting for more items
+    pub async fn flush(&self) {
+        self.tx
+            .send(Command::Flush)
+            .await
+            .expect_or_log("Error sending to batch processing channel");
+    }
+
+    /// Number of items waiting for processing or being processed
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+}
+
+/// Start batch process with given settings and processing function, returns command sender.
+/// Batch is processed as soon as it's full, or after maximum delay since its first item.
+/// When `max_queue` commands are waiting, senders wait until batch process receives them
+pub fn start_batch_process<In, Out, F>(settings: &NNSettings, process: F) -> BatchSender<In, Out>
 where
     In: Send + 'static,
     Out: Send + 'static,
@@ -31,9 +56,11 @@ where
 {
     let batch_size = settings.batch_size;
     let max_delay = Duration::from_millis(settings.max_delay_ms);
-    let max_capacity = 2 * settings.batch_size;
+    let max_capacity = settings.max_queue.max(1);
 
     let (tx, mut rx) = mpsc::channel(max_capacity);
+    let queued = Arc::new(AtomicUsize::new(0));
+    let queued_tmp = Arc::clone(&queued);
     // Start task for processing commands
     tokio::spawn(async move {
         // Current batch
@@ -71,6 +98,7 @@ where
                 let outputs = tokio::task::spawn_blocking(move || process(inputs))
                     .await
                     .unwrap_or_log();
+                queued_tmp.fetch_sub(senders.len(), Ordering::Relaxed);
                 // Send all outputs
                 for (sender, output) in senders.into_iter().zip(outputs) {
                     if sender.send(output).is_err() {
@@ -80,28 +108,27 @@ where
             }
         }
     });
-    tx
+    BatchSender { tx, queued }
 }
 
 /// Send item to batch process, optionally send flush command, receive output
 pub async fn batch_process<In: Debug, Out: Debug>(
-    sender: &mpsc::Sender<Command<In, Out>>,
+    sender: &BatchSender<In, Out>,
     value: In,
     flush: bool,
 ) -> Out {
     // Create channel for receiving output
     let (tx, rx) = oneshot::channel();
     // Send input
+    sender.queued.fetch_add(1, Ordering::Relaxed);
     sender
+        .tx
         .send(Command::Add((value, tx)))
         .await
         .expect_or_log("Error sending to batch processing channel");
     // Send flush command if needed
     if flush {
-        sender
-            .send(Command::Flush)
-            .await
-            .expect_or_log("Error sending to batch processing channel");
+        sender.flush().await;
     }
     // Receive output
     rx.await
@@ -128,3 +155,91 @@ where
     );
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use common_lib::settings::NNDevice;
+
+    use super::*;
+
+    const MAX_DELAY: Duration = Duration::from_millis(300);
+
+    fn settings(batch_size: usize) -> NNSettings {
+        NNSettings {
+            device: NNDevice::CPU,
+            batch_size,
+            max_delay_ms: MAX_DELAY.as_millis() as u64,
+            max_queue: 16,
+            model_path: Default::default(),
+            embedding_dim: 0,
+            idle_timeout_mins: 0,
+        }
+    }
+
+    /// Fake processing function, returns size of batch and time of its dispatch for each item
+    fn record_dispatch(batch: Vec<u32>) -> Vec<(usize, Instant)> {
+        let time = Instant::now();
+        vec![(batch.len(), time); batch.len()]
+    }
+
+    /// Send items concurrently, returns outputs in order of items
+    async fn send_items(
+        sender: &Arc<BatchSender<u32, (usize, Instant)>>,
+        cnt: u32,
+    ) -> Vec<(usize, Instant)> {
+        let handles: Vec<_> = (0..cnt)
+            .map(|x| {
+                let sender = Arc::clone(sender);
+                tokio::spawn(async move { batch_process(&sender, x, false).await })
+            })
+            .collect();
+        let mut outputs = Vec::new();
+        for handle in handles {
+            outputs.push(handle.await.unwrap());
+        }
+        outputs
+    }
+
+    #[tokio::test]
+    async fn full_batch_is_dispatched_without_waiting_for_delay() {
+        let sender = Arc::new(start_batch_process(&settings(4), record_dispatch));
+        let start_time = Instant::now();
+
+        let outputs = send_items(&sender, 4).await;
+
+        assert!(outputs.iter().all(|(batch_len, _)| *batch_len == 4));
+        assert!(outputs[0].1 - start_time < MAX_DELAY);
+        assert_eq!(sender.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn partial_batch_is_dispatched_after_max_delay() {
+        let sender = Arc::new(start_batch_process(&settings(4), record_dispatch));
+        let start_time = Instant::now();
+
+        let outputs = send_items(&sender, 6).await;
+
+        let mut batch_lens: Vec<_> = outputs.iter().map(|(batch_len, _)| *batch_len).collect();
+        batch_lens.sort();
+        assert_eq!(batch_lens, [2, 2, 4, 4, 4, 4]);
+        for (batch_len, dispatch_time) in outputs {
+            let delay = dispatch_time - start_time;
+            match batch_len {
+                4 => assert!(delay < MAX_DELAY),
+                _ => assert!(delay >= MAX_DELAY),
+            }
+        }
+        assert_eq!(sender.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn flush_dispatches_partial_batch_immediately() {
+        let sender = start_batch_process(&settings(4), record_dispatch);
+        let start_time = Instant::now();
+
+        let (batch_len, dispatch_time) = batch_process(&sender, 1, true).await;
+
+        assert_eq!(batch_len, 1);
+        assert!(dispatch_time - start_time < MAX_DELAY);
+    }
+}
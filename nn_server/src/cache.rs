@@ -0,0 +1,106 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use serde::Serialize;
+use tracing_unwrap::{OptionExt, ResultExt};
+
+/// Maximum length of cached text in bytes
+const CACHED_TEXT_MAX_LEN: usize = 1024;
+
+/// Hits and misses of cache since start of server
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+struct CacheEntries<K, V> {
+    /// Values with time of last use
+    values: HashMap<K, (V, u64)>,
+    /// Keys by time of last use
+    by_use: BTreeMap<u64, K>,
+    time: u64,
+}
+
+/// Least recently used cache of results of model, cache with zero capacity is disabled
+pub struct Cache<K, V> {
+    capacity: usize,
+    entries: Mutex<CacheEntries<K, V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(CacheEntries {
+                values: HashMap::new(),
+                by_use: BTreeMap::new(),
+                time: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Get value and mark it as recently used
+    pub fn get(&self, key: &K) -> Option<V> {
+        if self.capacity == 0 {
+            return None;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_log();
+        let entries = &mut *entries;
+        entries.time += 1;
+        let Some((value, last_use)) = entries.values.get_mut(key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        let key = entries.by_use.remove(last_use).unwrap_or_log();
+        *last_use = entries.time;
+        entries.by_use.insert(entries.time, key);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(value.clone())
+    }
+
+    /// Insert value, removing the least recently used one if cache is full
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_log();
+        entries.time += 1;
+        let time = entries.time;
+        if let Some((_, last_use)) = entries.values.insert(key.clone(), (value, time)) {
+            entries.by_use.remove(&last_use);
+        } else if entries.values.len() > self.capacity {
+            let (_, oldest) = entries.by_use.pop_first().unwrap_or_log();
+            entries.values.remove(&oldest);
+        }
+        entries.by_use.insert(time, key);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap_or_log().values.len(),
+        }
+    }
+}
+
+/// Key of text in cache with whitespace collapsed, so that texts differing only in it share entry.
+/// Long texts are mostly documents being indexed, they rarely repeat and aren't cached
+pub fn text_key(text: &str) -> Option<String> {
+    (text.len() <= CACHED_TEXT_MAX_LEN)
+        .then(|| text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
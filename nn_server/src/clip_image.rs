@@ -1,31 +1,42 @@
 use axum::{body::Bytes, extract::Query, http::StatusCode, Json};
-use common_lib::{settings::NNServerSettings, BatchRequest};
+use common_lib::{settings::NNServerSettings, BatchRequest, ImageBatchRequest};
 use image::{imageops::FilterType, DynamicImage};
 use ndarray::{arr3, Array3, Axis};
 use nshare::ToNdarray3;
 use once_cell::sync::OnceCell;
-use onnxruntime::{environment::Environment, session::Session, GraphOptimizationLevel};
-use tokio::sync::mpsc;
+use onnxruntime::environment::Environment;
+use serde::Serialize;
 use tracing_unwrap::{OptionExt, ResultExt};
 
 use crate::{
-    batch_processing::{batch_process, log_processing_function, start_batch_process, Command},
-    set_device, Embedding, PATH_PREFIX,
+    batch_processing::{batch_process, log_processing_function, start_batch_process, BatchSender},
+    session::ModelSession,
+    Embedding,
 };
 
-static MODEL: OnceCell<Session> = OnceCell::new();
-static BATCH_SENDER: OnceCell<mpsc::Sender<Command<Array3<f32>, Embedding>>> = OnceCell::new();
+/// Name of model file in directory of model
+pub const MODEL_FILE: &str = "model.onnx";
+
+pub static MODEL: OnceCell<ModelSession> = OnceCell::new();
+pub static BATCH_SENDER: OnceCell<BatchSender<Array3<f32>, Embedding>> = OnceCell::new();
+
+/// Embedding of image from batch request, missing if image can't be read
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionalEmbedding {
+    pub embedding: Option<Vec<f32>>,
+}
 
 pub fn initialize_model(
     settings: &NNServerSettings,
     environment: &Environment,
 ) -> onnxruntime::Result<()> {
     MODEL
-        .set(
-            set_device(environment.new_session_builder()?, &settings.clip_image)?
-                .with_graph_optimization_level(GraphOptimizationLevel::All)?
-                .with_model_from_file(PATH_PREFIX.to_owned() + "models/clip-ViT-B-32/model.onnx")?,
-        )
+        .set(ModelSession::new(
+            "clip_image",
+            environment,
+            &settings.clip_image,
+            settings.clip_image.model_path.join(MODEL_FILE),
+        )?)
         .unwrap_or_log();
     BATCH_SENDER
         .set(start_batch_process(&settings.clip_image, |batch| {
@@ -78,14 +89,16 @@ fn compute_embeddings(arrays: Vec<Array3<f32>>) -> anyhow::Result<Vec<Embedding>
     )
     .unwrap_or_log();
 
-    let output = session.run(vec![pixel_values.into()])?;
-
-    let res: Vec<_> = output[0]
-        .float_array()
-        .unwrap_or_log()
-        .outer_iter()
-        .map(|x| Embedding::from_unnormalized_array(x.into_owned()))
-        .collect();
+    let res = session.run(|session| {
+        let output = session.run(vec![pixel_values.clone().into()])?;
+        let res: Vec<_> = output[0]
+            .float_array()
+            .unwrap_or_log()
+            .outer_iter()
+            .map(|x| Embedding::from_unnormalized_array(x.into_owned()))
+            .collect();
+        Ok(res)
+    })?;
     Ok(res)
 }
 
@@ -110,3 +123,48 @@ pub async fn process_request(
         .await,
     ))
 }
+
+/// Calculate embeddings of several images, in order of request.
+/// Images that can't be read don't fail the whole request
+pub async fn process_batch_request(
+    Query(batch_query): Query<BatchRequest>,
+    Json(request): Json<ImageBatchRequest>,
+) -> Json<Vec<OptionalEmbedding>> {
+    // Decode and preprocess images in parallel
+    let arrays: Vec<_> = request
+        .images
+        .into_iter()
+        .map(|image| {
+            tokio::task::spawn_blocking(move || match image::load_from_memory(&image) {
+                Ok(image) => Some(preprocess_image(image)),
+                Err(err) => {
+                    tracing::debug!("Can't read image in batch: {}", err);
+                    None
+                }
+            })
+        })
+        .collect();
+    // Spawn tasks for each image that was read
+    let mut tasks = Vec::new();
+    for x in arrays {
+        tasks.push(x.await.unwrap_or_log().map(|array| {
+            tokio::spawn(async move {
+                batch_process(BATCH_SENDER.get().unwrap_or_log(), array, false).await
+            })
+        }));
+    }
+    // Send flush command if needed
+    if !batch_query.batched {
+        BATCH_SENDER.get().unwrap_or_log().flush().await;
+    }
+    // Wait for all tasks to finish
+    let mut embeddings = Vec::new();
+    for x in tasks {
+        let embedding = match x {
+            Some(task) => Some(task.await.unwrap_or_log().embedding),
+            None => None,
+        };
+        embeddings.push(OptionalEmbedding { embedding });
+    }
+    Json(embeddings)
+}
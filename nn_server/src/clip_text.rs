@@ -4,20 +4,21 @@ use once_cell::sync::OnceCell;
 use onnxruntime::{environment::Environment, session::Session, GraphOptimizationLevel};
 use serde::Deserialize;
 use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
-use tokio::sync::mpsc;
 use tracing_unwrap::{OptionExt, ResultExt};
 
 use crate::{
-    batch_processing::{batch_process, log_processing_function, start_batch_process, Command},
-    set_device,
+    batch_processing::{batch_process, log_processing_function, start_batch_process, BatchSender},
+    cache::{text_key, Cache},
+    session::ModelSession,
     text_processing::{mean_pooling, preprocess_texts, PreprocessedText},
-    Embedding, PATH_PREFIX,
+    Embedding,
 };
 
-static MAIN_MODEL: OnceCell<Session> = OnceCell::new();
+pub static MAIN_MODEL: OnceCell<ModelSession> = OnceCell::new();
 static DENSE_MODEL: OnceCell<Session> = OnceCell::new();
 static TOKENIZER: OnceCell<Tokenizer> = OnceCell::new();
-static BATCH_SENDER: OnceCell<mpsc::Sender<Command<String, Embedding>>> = OnceCell::new();
+pub static BATCH_SENDER: OnceCell<BatchSender<String, Embedding>> = OnceCell::new();
+pub static CACHE: OnceCell<Cache<String, Embedding>> = OnceCell::new();
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct CLIPTextRequest {
@@ -29,13 +30,12 @@ pub fn initialize_model(
     environment: &Environment,
 ) -> anyhow::Result<()> {
     MAIN_MODEL
-        .set(
-            set_device(environment.new_session_builder()?, &settings.clip_text)?
-                .with_graph_optimization_level(GraphOptimizationLevel::All)?
-                .with_model_from_file(
-                    PATH_PREFIX.to_owned() + "models/clip-ViT-B-32-multilingual-v1/model.onnx",
-                )?,
-        )
+        .set(ModelSession::new(
+            "clip_text",
+            environment,
+            &settings.clip_text,
+            settings.clip_text.model_path.join("model.onnx"),
+        )?)
         .unwrap_or_log();
     // Always on CPU
     DENSE_MODEL
@@ -43,22 +43,18 @@ pub fn initialize_model(
             environment
                 .new_session_builder()?
                 .with_graph_optimization_level(GraphOptimizationLevel::All)?
-                .with_model_from_file(
-                    PATH_PREFIX.to_owned() + "models/clip-ViT-B-32-multilingual-v1/dense.onnx",
-                )?,
+                .with_model_from_file(settings.clip_text.model_path.join("dense.onnx"))?,
         )
         .unwrap_or_log();
     TOKENIZER
         .set(
-            Tokenizer::from_file(
-                PATH_PREFIX.to_owned() + "models/clip-ViT-B-32-multilingual-v1/tokenizer.json",
-            )
-            .map(|mut x| {
-                x.with_padding(Some(PaddingParams::default()));
-                x.with_truncation(Some(TruncationParams::default()));
-                x
-            })
-            .map_err(|err| anyhow::anyhow!(err))?,
+            Tokenizer::from_file(settings.clip_text.model_path.join("tokenizer.json"))
+                .map(|mut x| {
+                    x.with_padding(Some(PaddingParams::default()));
+                    x.with_truncation(Some(TruncationParams::default()));
+                    x
+                })
+                .map_err(|err| anyhow::anyhow!(err))?,
         )
         .unwrap_or_log();
     BATCH_SENDER
@@ -66,6 +62,9 @@ pub fn initialize_model(
             log_processing_function("CLIP/Text", compute_embeddings, batch)
         }))
         .unwrap_or_log();
+    CACHE
+        .set(Cache::new(settings.text_cache_capacity))
+        .unwrap_or_log();
     Ok(())
 }
 
@@ -80,8 +79,17 @@ fn compute_embeddings(texts: Vec<String>) -> anyhow::Result<Vec<Embedding>> {
         ..
     } = preprocess_texts(tokenizer, texts, false).unwrap_or_log();
 
-    let output_main = session_main.run(vec![input_ids.into(), attention_mask.clone().into()])?;
-    let res_main = mean_pooling(output_main[0].float_array().unwrap_or_log(), attention_mask);
+    let res_main = session_main.run(|session| {
+        let output_main = session.run(vec![
+            input_ids.clone().into(),
+            attention_mask.clone().into(),
+        ])?;
+        let res = mean_pooling(
+            output_main[0].float_array().unwrap_or_log(),
+            attention_mask.clone(),
+        );
+        Ok(res)
+    })?;
     let output_dense = session_dense.run(vec![res_main.into()])?;
 
     let res: Vec<_> = output_dense[0]
@@ -97,12 +105,20 @@ pub async fn process_request(
     Query(batch_query): Query<BatchRequest>,
     Json(request): Json<CLIPTextRequest>,
 ) -> Result<Json<Embedding>, (StatusCode, String)> {
-    Ok(Json(
-        batch_process(
-            BATCH_SENDER.get().unwrap_or_log(),
-            request.text,
-            !batch_query.batched,
-        )
-        .await,
-    ))
+    let cache = CACHE.get().unwrap_or_log();
+    let key = text_key(&request.text);
+    if let Some(embedding) = key.as_ref().and_then(|x| cache.get(x)) {
+        return Ok(Json(embedding));
+    }
+
+    let embedding = batch_process(
+        BATCH_SENDER.get().unwrap_or_log(),
+        request.text,
+        !batch_query.batched,
+    )
+    .await;
+    if let Some(key) = key {
+        cache.insert(key, embedding.clone());
+    }
+    Ok(Json(embedding))
 }
@@ -1,15 +1,19 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use axum::{
-    error_handling::HandleErrorLayer,
-    extract::DefaultBodyLimit,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    BoxError, Router,
+    Json, Router,
+};
+use common_lib::{
+    settings::{NNServerSettings, Settings},
+    NNServerHealth, NNServerInfo,
 };
-use common_lib::settings::{NNDevice, NNServerSettings, NNSettings, Settings};
 use ndarray::{Array, ArrayD, Dimension};
-use onnxruntime::{environment::Environment, session::SessionBuilder, LoggingLevel};
+use onnxruntime::{environment::Environment, LoggingLevel};
 use serde::Serialize;
 use tokio::signal;
 use tower::ServiceBuilder;
@@ -19,16 +23,35 @@ use tracing_subscriber::{
 };
 use tracing_unwrap::ResultExt;
 
+use crate::{
+    batch_processing::BatchSender,
+    cache::{Cache, CacheStats},
+};
+
 mod batch_processing;
+mod cache;
 mod clip_image;
 mod clip_text;
 mod lexrank;
 mod minilm_rerank;
 mod minilm_text;
+mod models;
+mod session;
 mod text_processing;
 
-const PATH_PREFIX: &str = "nn_server/";
 const SETTINGS_FILE_PATH: &str = "Settings.toml";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Timeout of requests while some model is unloaded, as request can load it again
+const MODEL_LOADING_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Statistics of server, caches and queues of disabled models are missing
+#[derive(Debug, Clone, Serialize)]
+pub struct Metrics {
+    pub clip_text_cache: Option<CacheStats>,
+    pub minilm_text_cache: Option<CacheStats>,
+    /// Number of requests waiting for processing or being processed by each model
+    pub queued: BTreeMap<&'static str, usize>,
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Embedding {
@@ -50,15 +73,12 @@ impl Embedding {
     }
 }
 
-/// Configure ONNX Runtime to use CPU or CUDA depending on the setting
-fn set_device<'a>(
-    session_builder: SessionBuilder<'a>,
-    settings: &'a NNSettings,
-) -> onnxruntime::Result<SessionBuilder<'a>> {
-    match settings.device {
-        NNDevice::CUDA => session_builder.use_cuda(0),
-        NNDevice::CPU => Ok(session_builder),
-    }
+/// Create ONNX Runtime environment, it's shared by all sessions
+fn create_environment() -> onnxruntime::Result<Environment> {
+    Environment::builder()
+        .with_name("nn_server_env")
+        .with_log_level(LoggingLevel::Warning)
+        .build()
 }
 
 #[tokio::main]
@@ -72,7 +92,7 @@ async fn main() {
         )
         .init();
 
-    let settings = match tokio::fs::read_to_string(SETTINGS_FILE_PATH).await {
+    let mut settings = match tokio::fs::read_to_string(SETTINGS_FILE_PATH).await {
         Ok(s) => toml::from_str::<Settings>(&s).expect_or_log("Error reading settings"),
         Err(e) => {
             tracing::warn!("Error reading settings file: {}, using defaults", e);
@@ -80,14 +100,31 @@ async fn main() {
         }
     }
     .nn_server;
+    settings.fill_model_defaults();
     let address = settings.nn_server_address;
 
     initialize_models(&settings).expect_or_log("Can't initialize models");
+    session::start_idle_unloading(
+        [
+            clip_image::MODEL.get(),
+            clip_text::MAIN_MODEL.get(),
+            minilm_text::MODEL.get(),
+            minilm_rerank::MODEL.get(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    );
 
-    let mut app = Router::new().route("/health", get(get_health));
+    let mut app = Router::new()
+        .route("/health", get(get_health))
+        .route("/info", get(get_info))
+        .route("/metrics", get(get_metrics))
+        .route("/models", get(models::get_model_versions));
     if settings.image_search_enabled {
         app = app
             .route("/clip/image", post(clip_image::process_request))
+            .route("/clip/image_batch", post(clip_image::process_batch_request))
             .route("/clip/text", post(clip_text::process_request));
     }
     if settings.text_search_enabled {
@@ -101,17 +138,7 @@ async fn main() {
         .layer(DefaultBodyLimit::max(100 * 1024 * 1024))
         .layer(
             ServiceBuilder::new()
-                .layer(HandleErrorLayer::new(|error: BoxError| async move {
-                    if error.is::<tower::timeout::error::Elapsed>() {
-                        Ok(StatusCode::REQUEST_TIMEOUT)
-                    } else {
-                        Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Unhandled internal error: {error}"),
-                        ))
-                    }
-                }))
-                .timeout(Duration::from_secs(30))
+                .layer(middleware::from_fn(request_timeout))
                 .layer(TraceLayer::new_for_http()),
         );
     let url = format!("http://{address}");
@@ -125,10 +152,18 @@ async fn main() {
 }
 
 fn initialize_models(settings: &NNServerSettings) -> anyhow::Result<()> {
-    let environment = Environment::builder()
-        .with_name("nn_server_env")
-        .with_log_level(LoggingLevel::Warning)
-        .build()?;
+    // Embeddings of images and texts are compared with each other
+    if settings.image_search_enabled
+        && settings.clip_image.embedding_dim != settings.clip_text.embedding_dim
+    {
+        return Err(anyhow::anyhow!(
+            "Embedding dimensions of CLIP image ({}) and text ({}) models are different",
+            settings.clip_image.embedding_dim,
+            settings.clip_text.embedding_dim
+        ));
+    }
+
+    let environment = create_environment()?;
     if settings.image_search_enabled {
         clip_image::initialize_model(settings, &environment)?;
         clip_text::initialize_model(settings, &environment)?;
@@ -139,11 +174,73 @@ fn initialize_models(settings: &NNServerSettings) -> anyhow::Result<()> {
     if settings.reranking_enabled {
         minilm_rerank::initialize_model(settings, &environment)?;
     }
+    models::initialize_versions(settings)?;
     Ok(())
 }
 
-async fn get_health() -> &'static str {
-    "OK"
+/// Respond with timeout error if request takes too long. Unloaded model takes time to load,
+/// so timeout is longer while some model is unloaded
+async fn request_timeout<B>(request: Request<B>, next: Next<B>) -> Response {
+    let timeout = if session::all_loaded() {
+        REQUEST_TIMEOUT
+    } else {
+        MODEL_LOADING_REQUEST_TIMEOUT
+    };
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => StatusCode::REQUEST_TIMEOUT.into_response(),
+    }
+}
+
+/// Get devices that models are running on, they can fall back to CPU if CUDA fails,
+/// and whether models are loaded
+async fn get_health() -> Json<NNServerHealth> {
+    Json(NNServerHealth {
+        devices: session::devices(),
+        loaded: session::loaded(),
+    })
+}
+
+/// Get statistics of caches of enabled text models and queues of enabled models
+async fn get_metrics() -> Json<Metrics> {
+    let queued = [
+        (
+            "clip_image",
+            clip_image::BATCH_SENDER.get().map(BatchSender::queued),
+        ),
+        (
+            "clip_text",
+            clip_text::BATCH_SENDER.get().map(BatchSender::queued),
+        ),
+        (
+            "minilm_text",
+            minilm_text::BATCH_SENDER.get().map(BatchSender::queued),
+        ),
+        (
+            "minilm_rerank",
+            minilm_rerank::BATCH_SENDER.get().map(BatchSender::queued),
+        ),
+    ];
+    Json(Metrics {
+        clip_text_cache: clip_text::CACHE.get().map(Cache::stats),
+        minilm_text_cache: minilm_text::CACHE.get().map(Cache::stats),
+        queued: queued
+            .into_iter()
+            .filter_map(|(model, x)| Some((model, x?)))
+            .collect(),
+    })
+}
+
+/// Get dimensions of embeddings of enabled models
+async fn get_info(State(settings): State<Arc<NNServerSettings>>) -> Json<NNServerInfo> {
+    Json(NNServerInfo {
+        text_embedding_dim: settings
+            .text_search_enabled
+            .then_some(settings.minilm_text.embedding_dim),
+        image_embedding_dim: settings
+            .image_search_enabled
+            .then_some(settings.clip_image.embedding_dim),
+    })
 }
 
 async fn shutdown_signal() {
@@ -1,25 +1,23 @@
 use axum::{extract::Query, http::StatusCode, Json};
 use common_lib::{settings::NNServerSettings, BatchRequest};
 use once_cell::sync::OnceCell;
-use onnxruntime::{environment::Environment, session::Session, GraphOptimizationLevel};
+use onnxruntime::environment::Environment;
 use serde::{Deserialize, Serialize};
 use tokenizers::{
     PaddingDirection, PaddingParams, PaddingStrategy, Tokenizer, TruncationDirection,
     TruncationParams, TruncationStrategy,
 };
-use tokio::sync::mpsc;
 use tracing_unwrap::{OptionExt, ResultExt};
 
 use crate::{
-    batch_processing::{batch_process, log_processing_function, start_batch_process, Command},
-    set_device,
+    batch_processing::{batch_process, log_processing_function, start_batch_process, BatchSender},
+    session::ModelSession,
     text_processing::{preprocess_texts, PreprocessedText},
-    PATH_PREFIX,
 };
 
-static MODEL: OnceCell<Session> = OnceCell::new();
+pub static MODEL: OnceCell<ModelSession> = OnceCell::new();
 static TOKENIZER: OnceCell<Tokenizer> = OnceCell::new();
-static BATCH_SENDER: OnceCell<mpsc::Sender<Command<(String, String), f32>>> = OnceCell::new();
+pub static BATCH_SENDER: OnceCell<BatchSender<(String, String), f32>> = OnceCell::new();
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct MiniLMRerankRequest {
@@ -37,37 +35,34 @@ pub fn initialize_model(
     environment: &Environment,
 ) -> anyhow::Result<()> {
     MODEL
-        .set(
-            set_device(environment.new_session_builder()?, &settings.minilm_rerank)?
-                .with_graph_optimization_level(GraphOptimizationLevel::All)?
-                .with_model_from_file(
-                    PATH_PREFIX.to_owned() + "models/mMiniLM-L6-v2-mmarco-v2/model.onnx",
-                )?,
-        )
+        .set(ModelSession::new(
+            "minilm_rerank",
+            environment,
+            &settings.minilm_rerank,
+            settings.minilm_rerank.model_path.join("model.onnx"),
+        )?)
         .unwrap_or_log();
     TOKENIZER
         .set(
-            Tokenizer::from_file(
-                PATH_PREFIX.to_owned() + "models/mMiniLM-L6-v2-mmarco-v2/tokenizer.json",
-            )
-            .map(|mut x| {
-                x.with_truncation(Some(TruncationParams {
-                    max_length: 256,
-                    strategy: TruncationStrategy::default(),
-                    stride: 0,
-                    direction: TruncationDirection::default(),
-                }));
-                x.with_padding(Some(PaddingParams {
-                    strategy: PaddingStrategy::BatchLongest,
-                    direction: PaddingDirection::Right,
-                    pad_to_multiple_of: None,
-                    pad_id: 1,
-                    pad_type_id: 0,
-                    pad_token: "<pad>".to_owned(),
-                }));
-                x
-            })
-            .map_err(|err| anyhow::anyhow!(err))?,
+            Tokenizer::from_file(settings.minilm_rerank.model_path.join("tokenizer.json"))
+                .map(|mut x| {
+                    x.with_truncation(Some(TruncationParams {
+                        max_length: 256,
+                        strategy: TruncationStrategy::default(),
+                        stride: 0,
+                        direction: TruncationDirection::default(),
+                    }));
+                    x.with_padding(Some(PaddingParams {
+                        strategy: PaddingStrategy::BatchLongest,
+                        direction: PaddingDirection::Right,
+                        pad_to_multiple_of: None,
+                        pad_id: 1,
+                        pad_type_id: 0,
+                        pad_token: "<pad>".to_owned(),
+                    }));
+                    x
+                })
+                .map_err(|err| anyhow::anyhow!(err))?,
         )
         .unwrap_or_log();
     BATCH_SENDER
@@ -88,11 +83,17 @@ fn compute_embeddings(queries_paragraphs: Vec<(String, String)>) -> anyhow::Resu
         ..
     } = preprocess_texts(tokenizer, queries_paragraphs, false).unwrap_or_log();
 
-    let output = session.run(vec![input_ids.into(), attention_mask.into()])?;
-    let res: Vec<_> = (*output[0].float_array().unwrap_or_log())
-        .to_owned()
-        .into_iter()
-        .collect();
+    let res = session.run(|session| {
+        let output = session.run(vec![
+            input_ids.clone().into(),
+            attention_mask.clone().into(),
+        ])?;
+        let res: Vec<_> = (*output[0].float_array().unwrap_or_log())
+            .to_owned()
+            .into_iter()
+            .collect();
+        Ok(res)
+    })?;
     Ok(res)
 }
 
@@ -113,12 +114,7 @@ pub async fn process_request(
         .collect();
     // Send flush command if needed
     if !batch_query.batched {
-        BATCH_SENDER
-            .get()
-            .unwrap_or_log()
-            .send(Command::Flush)
-            .await
-            .expect_or_log("Error sending to batch processing channel");
+        BATCH_SENDER.get().unwrap_or_log().flush().await;
     }
     // Wait for all tasks to finish
     let mut scores = Vec::new();
@@ -6,27 +6,33 @@ use axum::{
     Json,
 };
 use common_lib::{settings::NNServerSettings, BatchRequest};
-use ndarray::{ArrayD, Axis};
+use ndarray::{Array2, ArrayD, Axis};
 use once_cell::sync::OnceCell;
-use onnxruntime::{environment::Environment, session::Session, GraphOptimizationLevel};
+use onnxruntime::environment::Environment;
 use serde::{Deserialize, Serialize};
 use tokenizers::Tokenizer;
-use tokio::sync::mpsc;
 use tracing_unwrap::{OptionExt, ResultExt};
 
 use crate::{
-    batch_processing::{batch_process, log_processing_function, start_batch_process, Command},
+    batch_processing::{batch_process, log_processing_function, start_batch_process, BatchSender},
+    cache::{text_key, Cache},
     lexrank::degree_centrality_scores,
-    set_device,
-    text_processing::{mean_pooling, preprocess_texts, PreprocessedText},
-    Embedding, PATH_PREFIX,
+    session::ModelSession,
+    text_processing::{
+        adaptive_summary_len, language_groups, mean_pooling, preprocess_texts, proportional_counts,
+        sample_windows, PreprocessedText,
+    },
+    Embedding,
 };
 
-const EMBEDDING_SIZE: usize = 384;
+/// Name of model file in directory of model
+pub const MODEL_FILE: &str = "model.onnx";
 
-static MODEL: OnceCell<Session> = OnceCell::new();
+pub static MODEL: OnceCell<ModelSession> = OnceCell::new();
 static TOKENIZER: OnceCell<Tokenizer> = OnceCell::new();
-static BATCH_SENDER: OnceCell<mpsc::Sender<Command<String, ArrayD<f32>>>> = OnceCell::new();
+pub static BATCH_SENDER: OnceCell<BatchSender<String, ArrayD<f32>>> = OnceCell::new();
+/// Results by summary being enabled and text
+pub static CACHE: OnceCell<Cache<(bool, String), SummaryEmbedding>> = OnceCell::new();
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct MiniLMTextRequest {
@@ -46,22 +52,17 @@ pub fn initialize_model(
     environment: &Environment,
 ) -> anyhow::Result<()> {
     MODEL
-        .set(
-            set_device(environment.new_session_builder()?, &settings.minilm_text)?
-                .with_graph_optimization_level(GraphOptimizationLevel::All)?
-                .with_model_from_file(
-                    PATH_PREFIX.to_owned()
-                        + "models/paraphrase-multilingual-MiniLM-L12-v2/model.onnx",
-                )?,
-        )
+        .set(ModelSession::new(
+            "minilm_text",
+            environment,
+            &settings.minilm_text,
+            settings.minilm_text.model_path.join(MODEL_FILE),
+        )?)
         .unwrap_or_log();
     TOKENIZER
         .set(
-            Tokenizer::from_file(
-                PATH_PREFIX.to_owned()
-                    + "models/paraphrase-multilingual-MiniLM-L12-v2/tokenizer.json",
-            )
-            .map_err(|err| anyhow::anyhow!(err))?,
+            Tokenizer::from_file(settings.minilm_text.model_path.join("tokenizer.json"))
+                .map_err(|err| anyhow::anyhow!(err))?,
         )
         .unwrap_or_log();
     BATCH_SENDER
@@ -69,6 +70,9 @@ pub fn initialize_model(
             log_processing_function("MiniLM/Text", compute_embeddings, batch)
         }))
         .unwrap_or_log();
+    CACHE
+        .set(Cache::new(settings.text_cache_capacity))
+        .unwrap_or_log();
     Ok(())
 }
 
@@ -82,33 +86,65 @@ fn compute_embeddings(paragraphs: Vec<String>) -> anyhow::Result<Vec<ArrayD<f32>
         type_ids,
     } = preprocess_texts(tokenizer, paragraphs, true).unwrap_or_log();
 
-    let output = session.run(vec![
-        input_ids.into(),
-        attention_mask.clone().into(),
-        type_ids.unwrap_or_log().into(),
-    ])?;
-    let res: Vec<_> = mean_pooling(output[0].float_array().unwrap_or_log(), attention_mask)
+    let type_ids = type_ids.unwrap_or_log();
+    let res = session.run(|session| {
+        let output = session.run(vec![
+            input_ids.clone().into(),
+            attention_mask.clone().into(),
+            type_ids.clone().into(),
+        ])?;
+        let res: Vec<_> = mean_pooling(
+            output[0].float_array().unwrap_or_log(),
+            attention_mask.clone(),
+        )
         .outer_iter()
         .map(|x| x.into_owned())
         .collect();
+        Ok(res)
+    })?;
     Ok(res)
 }
 
+/// Indices of `cnt` most central paragraphs by LexRank, the most central first
+fn central_paragraphs(norm_paragraphs: &Array2<f32>, cnt: usize) -> Vec<usize> {
+    let paragraphs_cos_sim = norm_paragraphs.dot(&norm_paragraphs.t()).mapv(|x| x as f64);
+    let centrality_scores = degree_centrality_scores(paragraphs_cos_sim).to_vec();
+    let mut indices: Vec<usize> = (0..centrality_scores.len()).collect();
+    indices.sort_unstable_by(|i, j| {
+        centrality_scores[*j]
+            .partial_cmp(&centrality_scores[*i])
+            .unwrap()
+    });
+    indices.truncate(cnt);
+    indices
+}
+
 pub async fn process_request(
     State(settings): State<Arc<NNServerSettings>>,
     Query(batch_query): Query<BatchRequest>,
     Json(request): Json<MiniLMTextRequest>,
 ) -> Result<Json<SummaryEmbedding>, (StatusCode, String)> {
+    let cache = CACHE.get().unwrap_or_log();
+    let key = text_key(&request.text).map(|x| (request.summary_enabled, x));
+    if let Some(result) = key.as_ref().and_then(|x| cache.get(x)) {
+        return Ok(Json(result));
+    }
+
     let (max_sentences, window_size, window_step) = (
         settings.max_sentences as usize,
         settings.window_size as usize,
         settings.window_step as usize,
     );
     let words: Vec<_> = request.text.split_whitespace().collect();
-    let paragraphs: Vec<_> = (0..words.len())
-        .step_by(window_step)
-        .take(max_sentences)
-        .map(|i| words[i..(i + window_size).min(words.len())].join(" "))
+    // Long texts are sampled across all windows instead of only the first ones.
+    // Seed depends only on the text, so the same text gets the same sample
+    let windows_cnt = words.len().div_ceil(window_step);
+    let paragraphs: Vec<_> = sample_windows(windows_cnt, max_sentences, words.len() as u64)
+        .into_iter()
+        .map(|i| {
+            let start = i * window_step;
+            words[start..(start + window_size).min(words.len())].join(" ")
+        })
         .collect();
 
     // Spawn tasks for each paragraph
@@ -123,12 +159,7 @@ pub async fn process_request(
         .collect();
     // Send flush command if needed
     if !batch_query.batched {
-        BATCH_SENDER
-            .get()
-            .unwrap_or_log()
-            .send(Command::Flush)
-            .await
-            .expect_or_log("Error sending to batch processing channel");
+        BATCH_SENDER.get().unwrap_or_log().flush().await;
     }
     // Wait for all tasks to finish
     let mut paragraphs_embeddings = Vec::new();
@@ -162,29 +193,43 @@ pub async fn process_request(
                 .collect::<Vec<_>>(),
         )
         .unwrap_or_log()
-        .into_shape((norm_paragraphs_embeddings.len(), EMBEDDING_SIZE))
+        .into_shape((
+            norm_paragraphs_embeddings.len(),
+            settings.minilm_text.embedding_dim,
+        ))
         .unwrap_or_log();
 
-        let paragraphs_cos_sim = norm_paragraphs.dot(&norm_paragraphs.t()).mapv(|x| x as f64);
-        let centrality_scores = degree_centrality_scores(paragraphs_cos_sim).to_vec();
-        let mut indices: Vec<usize> = (0..centrality_scores.len()).collect();
-        indices.sort_unstable_by(|i, j| {
-            centrality_scores[*j]
-                .partial_cmp(&centrality_scores[*i])
-                .unwrap()
-        });
-
-        indices
-            .into_iter()
-            .take(settings.summary_len as usize)
-            .map(|i| paragraphs[i].clone())
+        let summary_len = adaptive_summary_len(
+            settings.summary_len as usize,
+            settings.summary_len_max as usize,
+            windows_cnt,
+            max_sentences,
+        );
+        // Paragraphs of each language are ranked separately and get share of summary
+        // proportional to their number, so that mixed text isn't summarized in one language
+        let groups = language_groups(&paragraphs);
+        let sizes: Vec<_> = groups.iter().map(Vec::len).collect();
+        groups
+            .iter()
+            .zip(proportional_counts(&sizes, summary_len))
+            .flat_map(|(indices, cnt)| {
+                let group_paragraphs = norm_paragraphs.select(Axis(0), indices);
+                central_paragraphs(&group_paragraphs, cnt)
+                    .into_iter()
+                    .map(|i| paragraphs[indices[i]].clone())
+                    .collect::<Vec<_>>()
+            })
             .collect()
     } else {
         Vec::new()
     };
 
-    Ok(Json(SummaryEmbedding {
+    let result = SummaryEmbedding {
         embedding: mean_embedding,
         summary,
-    }))
+    };
+    if let Some(key) = key {
+        cache.insert(key, result.clone());
+    }
+    Ok(Json(result))
 }
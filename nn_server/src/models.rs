@@ -0,0 +1,57 @@
+use std::{fs::File, io, path::Path};
+
+use axum::Json;
+use common_lib::{provenance::ModelVersions, settings::NNServerSettings};
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use tracing_unwrap::OptionExt;
+
+use crate::{clip_image, minilm_text};
+
+/// Number of hexadecimal digits of model hash in its identifier
+const MODEL_HASH_LEN: usize = 16;
+
+static MODEL_VERSIONS: OnceCell<ModelVersions> = OnceCell::new();
+
+/// Get identifier of model from name of its folder and hash of its file
+fn model_version(model_file: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(model_file)?, &mut hasher)?;
+    let hash_bytes: [u8; 32] = hasher.finalize().into();
+    let hash = base16ct::lower::encode_string(&hash_bytes);
+
+    let name = model_file
+        .parent()
+        .and_then(Path::file_name)
+        .unwrap_or_log()
+        .to_string_lossy();
+    Ok(format!("{}@{}", name, &hash[..MODEL_HASH_LEN]))
+}
+
+/// Calculate identifiers of enabled models that calculate embeddings of files
+pub fn initialize_versions(settings: &NNServerSettings) -> io::Result<()> {
+    let versions = ModelVersions {
+        clip_image: settings
+            .image_search_enabled
+            .then(|| model_version(&settings.clip_image.model_path.join(clip_image::MODEL_FILE)))
+            .transpose()?,
+        minilm_text: settings
+            .text_search_enabled
+            .then(|| {
+                model_version(
+                    &settings
+                        .minilm_text
+                        .model_path
+                        .join(minilm_text::MODEL_FILE),
+                )
+            })
+            .transpose()?,
+    };
+    MODEL_VERSIONS.set(versions).unwrap_or_log();
+    Ok(())
+}
+
+/// Get identifiers of enabled models
+pub async fn get_model_versions() -> Json<ModelVersions> {
+    Json(MODEL_VERSIONS.get().unwrap_or_log().clone())
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::PathBuf,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use common_lib::settings::{NNDevice, NNSettings};
+use onnxruntime::{environment::Environment, session::Session, GraphOptimizationLevel};
+use tracing_unwrap::ResultExt;
+
+use crate::create_environment;
+
+/// Devices that models are running on, by name of model
+static DEVICES: Mutex<BTreeMap<&'static str, NNDevice>> = Mutex::new(BTreeMap::new());
+/// Whether models are loaded, by name of model
+static LOADED: Mutex<BTreeMap<&'static str, bool>> = Mutex::new(BTreeMap::new());
+
+/// Interval of checking for models that weren't used for their idle timeout
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Load model on given device
+fn build_session(
+    environment: &Environment,
+    device: NNDevice,
+    model_file: PathBuf,
+) -> onnxruntime::Result<Session> {
+    let session_builder = environment.new_session_builder()?;
+    let session_builder = match device {
+        NNDevice::CUDA => session_builder.use_cuda(0)?,
+        NNDevice::CPU => session_builder,
+    };
+    session_builder
+        .with_graph_optimization_level(GraphOptimizationLevel::All)?
+        .with_model_from_file(model_file)
+}
+
+/// Errors of CUDA initialization and GPU memory allocation
+fn is_cuda_error(e: &impl Display) -> bool {
+    let message = e.to_string().to_lowercase();
+    ["cuda", "cudnn", "out of memory", "allocate"]
+        .iter()
+        .any(|x| message.contains(x))
+}
+
+/// Load model on given device, or on CPU if CUDA can't be initialized
+fn build_session_with_fallback(
+    name: &'static str,
+    environment: &Environment,
+    device: NNDevice,
+    model_file: PathBuf,
+) -> onnxruntime::Result<(Session, NNDevice)> {
+    match build_session(environment, device, model_file.clone()) {
+        Err(e) if device == NNDevice::CUDA => {
+            tracing::warn!("{}: can't initialize CUDA, using CPU: {}", name, e);
+            let session = build_session(environment, NNDevice::CPU, model_file)?;
+            Ok((session, NNDevice::CPU))
+        }
+        x => Ok((x?, device)),
+    }
+}
+
+/// Session of model that falls back to CPU if CUDA fails. Session is unloaded
+/// after idle timeout and loaded again when model is used
+pub struct ModelSession {
+    name: &'static str,
+    model_file: PathBuf,
+    session: RwLock<Option<Session>>,
+    /// `None` if model is never unloaded
+    idle_timeout: Option<Duration>,
+    last_used: Mutex<Instant>,
+}
+
+impl ModelSession {
+    /// Load model on device from settings, or on CPU if CUDA can't be initialized
+    pub fn new(
+        name: &'static str,
+        environment: &Environment,
+        settings: &NNSettings,
+        model_file: PathBuf,
+    ) -> onnxruntime::Result<Self> {
+        let (session, device) =
+            build_session_with_fallback(name, environment, settings.device, model_file.clone())?;
+        DEVICES.lock().unwrap_or_log().insert(name, device);
+        LOADED.lock().unwrap_or_log().insert(name, true);
+        Ok(Self {
+            name,
+            model_file,
+            session: RwLock::new(Some(session)),
+            idle_timeout: (settings.idle_timeout_mins != 0)
+                .then_some(Duration::from_secs(settings.idle_timeout_mins * 60)),
+            last_used: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Device that model is running on
+    pub fn device(&self) -> NNDevice {
+        DEVICES.lock().unwrap_or_log()[self.name]
+    }
+
+    /// Run function with session, loading it first if it was unloaded. If it fails with
+    /// CUDA error, session is loaded on CPU and function is run again, subsequent runs also use CPU
+    pub fn run<T>(&self, f: impl Fn(&Session) -> onnxruntime::Result<T>) -> onnxruntime::Result<T> {
+        *self.last_used.lock().unwrap_or_log() = Instant::now();
+        let res = match self.run_loaded(&f) {
+            Err(e) if self.device() == NNDevice::CUDA && is_cuda_error(&e) => {
+                tracing::warn!("{}: error on CUDA, switching to CPU: {}", self.name, e);
+                self.fall_back_to_cpu()?;
+                self.run_loaded(&f)
+            }
+            x => x,
+        };
+        *self.last_used.lock().unwrap_or_log() = Instant::now();
+        res
+    }
+
+    fn run_loaded<T>(
+        &self,
+        f: &impl Fn(&Session) -> onnxruntime::Result<T>,
+    ) -> onnxruntime::Result<T> {
+        loop {
+            if let Some(session) = self.session.read().unwrap_or_log().as_ref() {
+                return f(session);
+            }
+            self.load()?;
+        }
+    }
+
+    /// Load unloaded session on device that model was running on
+    fn load(&self) -> onnxruntime::Result<()> {
+        let mut session = self.session.write().unwrap_or_log();
+        // Other batch could have already loaded it while waiting for lock
+        if session.is_some() {
+            return Ok(());
+        }
+        let start_time = Instant::now();
+        let (new_session, device) = build_session_with_fallback(
+            self.name,
+            &create_environment()?,
+            self.device(),
+            self.model_file.clone(),
+        )?;
+        *session = Some(new_session);
+        DEVICES.lock().unwrap_or_log().insert(self.name, device);
+        LOADED.lock().unwrap_or_log().insert(self.name, true);
+        tracing::info!("{}: loaded in {:#?}", self.name, start_time.elapsed());
+        Ok(())
+    }
+
+    /// Unload session if model wasn't used for its idle timeout and isn't in use now
+    fn unload_if_idle(&self) {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return;
+        };
+        if self.last_used.lock().unwrap_or_log().elapsed() < idle_timeout {
+            return;
+        }
+        // Session is in use if lock can't be acquired
+        let Ok(mut session) = self.session.try_write() else {
+            return;
+        };
+        if session.take().is_some() {
+            LOADED.lock().unwrap_or_log().insert(self.name, false);
+            tracing::info!(
+                "{}: unloaded after {} minutes without requests",
+                self.name,
+                idle_timeout.as_secs() / 60
+            );
+        }
+    }
+
+    fn fall_back_to_cpu(&self) -> onnxruntime::Result<()> {
+        let mut session = self.session.write().unwrap_or_log();
+        // Other batch could have already switched while waiting for lock
+        if self.device() == NNDevice::CPU {
+            return Ok(());
+        }
+        *session = Some(build_session(
+            &create_environment()?,
+            NNDevice::CPU,
+            self.model_file.clone(),
+        )?);
+        LOADED.lock().unwrap_or_log().insert(self.name, true);
+        DEVICES
+            .lock()
+            .unwrap_or_log()
+            .insert(self.name, NNDevice::CPU);
+        Ok(())
+    }
+}
+
+/// Start task that periodically unloads models that weren't used for their idle timeout
+pub fn start_idle_unloading(models: Vec<&'static ModelSession>) {
+    let models: Vec<_> = models
+        .into_iter()
+        .filter(|x| x.idle_timeout.is_some())
+        .collect();
+    if models.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let models = models.clone();
+            // Freeing memory of session can take a while
+            tokio::task::spawn_blocking(move || models.iter().for_each(|x| x.unload_if_idle()))
+                .await
+                .unwrap_or_log();
+        }
+    });
+}
+
+/// Are all models loaded? Requests can take longer while some model is loaded again
+pub fn all_loaded() -> bool {
+    LOADED.lock().unwrap_or_log().values().all(|x| *x)
+}
+
+/// Whether models are loaded
+pub fn loaded() -> BTreeMap<String, bool> {
+    LOADED
+        .lock()
+        .unwrap_or_log()
+        .iter()
+        .map(|(name, loaded)| (name.to_string(), *loaded))
+        .collect()
+}
+
+/// Devices that models are running on
+pub fn devices() -> BTreeMap<String, NNDevice> {
+    DEVICES
+        .lock()
+        .unwrap_or_log()
+        .iter()
+        .map(|(name, device)| (name.to_string(), *device))
+        .collect()
+}
@@ -1,3 +1,6 @@
+use std::cmp::Reverse;
+
+use common_lib::elasticsearch::ContentLanguage;
 use ndarray::{Array2, ArrayD, ArrayViewD, Axis};
 
 use tokenizers::{EncodeInput, Tokenizer};
@@ -63,3 +66,149 @@ pub fn mean_pooling(
     (last_hidden_state * &input_mask_expanded.view()).sum_axis(Axis(1))
         / (input_mask_expanded.sum_axis(Axis(1)).mapv(|x| x.max(1e-9)))
 }
+
+/// Indices of `cnt` of `total` windows, one from each of equal consecutive strata,
+/// so that sampled windows cover the whole text. Offsets in strata are pseudorandom from seed
+pub fn sample_windows(total: usize, cnt: usize, seed: u64) -> Vec<usize> {
+    if total <= cnt {
+        return (0..total).collect();
+    }
+    let mut state = seed;
+    (0..cnt)
+        .map(|i| {
+            let start = i * total / cnt;
+            let end = (i + 1) * total / cnt;
+            start + (splitmix64(&mut state) % (end - start) as u64) as usize
+        })
+        .collect()
+}
+
+/// Summary length multiplied by how many times the text exceeds `max_sentences` windows,
+/// but not more than `summary_len_max`
+pub fn adaptive_summary_len(
+    summary_len: usize,
+    summary_len_max: usize,
+    total: usize,
+    max_sentences: usize,
+) -> usize {
+    let scale = total.div_ceil(max_sentences.max(1)).max(1);
+    (summary_len * scale).min(summary_len_max.max(summary_len))
+}
+
+/// Paragraphs grouped by detected language, the largest group first
+pub fn language_groups(paragraphs: &[String]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(Option<ContentLanguage>, Vec<usize>)> = Vec::new();
+    for (i, paragraph) in paragraphs.iter().enumerate() {
+        let language = ContentLanguage::detect(paragraph);
+        match groups.iter_mut().find(|(x, _)| *x == language) {
+            Some((_, indices)) => indices.push(i),
+            None => groups.push((language, vec![i])),
+        }
+    }
+    groups.sort_by_key(|(_, indices)| Reverse(indices.len()));
+    groups.into_iter().map(|(_, indices)| indices).collect()
+}
+
+/// Split `total` among groups proportionally to their sizes by largest remainder method,
+/// no group gets more than its size
+pub fn proportional_counts(sizes: &[usize], total: usize) -> Vec<usize> {
+    let sum: usize = sizes.iter().sum();
+    let total = total.min(sum);
+    if sum == 0 {
+        return vec![0; sizes.len()];
+    }
+    let mut counts: Vec<_> = sizes.iter().map(|x| x * total / sum).collect();
+    let left = total - counts.iter().sum::<usize>();
+    let mut by_remainder: Vec<_> = (0..sizes.len()).collect();
+    by_remainder.sort_by_key(|&i| Reverse(sizes[i] * total % sum));
+    for i in by_remainder.into_iter().take(left) {
+        counts[i] += 1;
+    }
+    counts
+}
+
+/// Step of SplitMix64 pseudorandom number generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_uses_all_windows() {
+        assert_eq!(sample_windows(3, 5, 42), vec![0, 1, 2]);
+        assert_eq!(sample_windows(5, 5, 42), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn sampled_windows_cover_all_positions() {
+        for (total, cnt) in [(1000, 10), (101, 7), (11, 10)] {
+            let sample = sample_windows(total, cnt, 7);
+            assert_eq!(sample.len(), cnt);
+            // One window from each stratum, in order of position
+            for (i, &x) in sample.iter().enumerate() {
+                assert!(x >= i * total / cnt && x < (i + 1) * total / cnt);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_is_determined_by_seed() {
+        assert_eq!(sample_windows(1000, 10, 7), sample_windows(1000, 10, 7));
+        assert_ne!(sample_windows(1000, 10, 7), sample_windows(1000, 10, 8));
+    }
+
+    #[test]
+    fn summary_length_grows_with_text_up_to_cap() {
+        assert_eq!(adaptive_summary_len(3, 10, 50, 100), 3);
+        assert_eq!(adaptive_summary_len(3, 10, 250, 100), 9);
+        assert_eq!(adaptive_summary_len(3, 10, 1000, 100), 10);
+        // Cap below summary length doesn't shorten it
+        assert_eq!(adaptive_summary_len(3, 1, 1000, 100), 3);
+    }
+
+    #[test]
+    fn summary_is_split_by_largest_remainder() {
+        // Shares 7 * 5/10 = 3.5, 7 * 3/10 = 2.1 and 7 * 2/10 = 1.4
+        assert_eq!(proportional_counts(&[5, 3, 2], 7), vec![4, 2, 1]);
+        assert_eq!(proportional_counts(&[1, 1, 1], 2), vec![1, 1, 0]);
+        assert_eq!(proportional_counts(&[6, 2], 4), vec![3, 1]);
+    }
+
+    #[test]
+    fn group_gets_no_more_than_its_size() {
+        assert_eq!(proportional_counts(&[2, 1], 10), vec![2, 1]);
+        assert_eq!(proportional_counts(&[4], 3), vec![3]);
+    }
+
+    #[test]
+    fn empty_groups_get_nothing() {
+        assert_eq!(proportional_counts(&[0, 4, 0], 2), vec![0, 2, 0]);
+        assert_eq!(proportional_counts(&[0, 0], 3), vec![0, 0]);
+        assert_eq!(proportional_counts(&[], 3), Vec::<usize>::new());
+        assert_eq!(proportional_counts(&[3, 2], 0), vec![0, 0]);
+    }
+
+    #[test]
+    fn paragraphs_of_mixed_text_are_grouped_by_language() {
+        let paragraphs = [
+            "Годовой отчёт о финансовых результатах компании",
+            "The company reported strong growth of revenue this year",
+            "Выручка компании выросла за последний квартал",
+            "Новые продукты были представлены на выставке",
+            "New products were presented at the exhibition",
+        ]
+        .map(str::to_owned);
+
+        assert_eq!(
+            language_groups(&paragraphs),
+            vec![vec![0, 2, 3], vec![1, 4]]
+        );
+    }
+}